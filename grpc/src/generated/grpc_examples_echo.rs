@@ -92,6 +92,42 @@ pub mod echo_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
+        /// Limits the size of the metadata of requests.
+        ///
+        /// Default: no limit
+        #[must_use]
+        pub fn max_metadata_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_metadata_size(limit);
+            self
+        }
+        /// Limits the number of entries of the metadata of requests.
+        ///
+        /// Default: no limit
+        #[must_use]
+        pub fn max_metadata_entries(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_metadata_entries(limit);
+            self
+        }
+        /// Sets what to do with reserved headers set in the metadata of requests.
+        ///
+        /// Default: `ReservedHeaderPolicy::Allow`
+        #[must_use]
+        pub fn reserved_header_policy(
+            mut self,
+            policy: tonic::metadata::ReservedHeaderPolicy,
+        ) -> Self {
+            self.inner = self.inner.reserved_header_policy(policy);
+            self
+        }
+        /// Validate requests before sending them and responses before returning them.
+        #[must_use]
+        pub fn message_validators(
+            mut self,
+            validators: tonic::codec::MessageValidators,
+        ) -> Self {
+            self.inner = self.inner.message_validators(validators);
+            self
+        }
         /// UnaryEcho is unary echo.
         pub async fn unary_echo(
             &mut self,
@@ -0,0 +1,125 @@
+use super::*;
+use hyper_util::rt::TokioExecutor;
+use tonic::codec::{CodecExecutor, CompressionEncoding};
+
+/// Spawns onto Tokio and counts the spawned tasks.
+#[allow(dead_code)]
+#[derive(Clone)]
+struct CountingExecutor(Arc<AtomicUsize>);
+
+impl<F> hyper::rt::Executor<F> for CountingExecutor
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        self.0.fetch_add(1, SeqCst);
+        TokioExecutor::new().execute(fut);
+    }
+}
+
+/// Small enough for the compressed messages to be decompressed on the executor too.
+#[allow(dead_code)]
+const MIN_MESSAGE_SIZE: usize = 16;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn offloads_compression_and_decompression() {
+    let encoding = CompressionEncoding::Gzip;
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let server_tasks = Arc::new(AtomicUsize::new(0));
+    let client_tasks = Arc::new(AtomicUsize::new(0));
+
+    let svc = test_server::TestServer::new(Svc::default())
+        .accept_compressed(encoding)
+        .send_compressed(encoding);
+
+    tokio::spawn({
+        let executor = CodecExecutor::new(CountingExecutor(server_tasks.clone()))
+            .min_message_size(MIN_MESSAGE_SIZE);
+        async move {
+            Server::builder()
+                .codec_executor(executor)
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let executor = CodecExecutor::new(CountingExecutor(client_tasks.clone()))
+        .min_message_size(MIN_MESSAGE_SIZE);
+
+    let mut client = Some(client);
+    let channel = Endpoint::from_static("http://[::]:50051")
+        .codec_executor(executor.clone())
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let client = hyper_util::rt::TokioIo::new(client.take().unwrap());
+            async move { Ok::<_, std::io::Error>(client) }
+        }))
+        .await
+        .unwrap();
+
+    // The server compresses the response and the client decompresses it.
+    let mut client = test_client::TestClient::new(channel.clone()).accept_compressed(encoding);
+    let res = client.compress_output_unary(()).await.unwrap();
+    assert_eq!(res.into_inner().data.len(), UNCOMPRESSED_MIN_BODY_SIZE);
+    assert_eq!(server_tasks.load(SeqCst), 1);
+    assert_eq!(client_tasks.load(SeqCst), 1);
+
+    // Requests carrying the executor are compressed on it, and the server decompresses them.
+    let mut req = Request::new(SomeData {
+        data: vec![0; UNCOMPRESSED_MIN_BODY_SIZE],
+    });
+    req.extensions_mut().insert(executor);
+    let mut client = test_client::TestClient::new(channel).send_compressed(encoding);
+    client.compress_input_unary(req).await.unwrap();
+    assert_eq!(server_tasks.load(SeqCst), 2);
+    assert_eq!(client_tasks.load(SeqCst), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streams_messages_compressed_on_the_executor() {
+    let encoding = CompressionEncoding::Gzip;
+    let (client, server) = tokio::io::duplex(UNCOMPRESSED_MIN_BODY_SIZE * 10);
+
+    let server_tasks = Arc::new(AtomicUsize::new(0));
+    let svc = test_server::TestServer::new(Svc::default()).send_compressed(encoding);
+
+    tokio::spawn({
+        let executor = CodecExecutor::new(CountingExecutor(server_tasks.clone()))
+            .min_message_size(MIN_MESSAGE_SIZE);
+        async move {
+            Server::builder()
+                .codec_executor(executor)
+                .add_service(svc)
+                .serve_with_incoming(tokio_stream::iter(vec![Ok::<_, std::io::Error>(server)]))
+                .await
+                .unwrap();
+        }
+    });
+
+    let mut client = Some(client);
+    let channel = Endpoint::from_static("http://[::]:50051")
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let client = hyper_util::rt::TokioIo::new(client.take().unwrap());
+            async move { Ok::<_, std::io::Error>(client) }
+        }))
+        .await
+        .unwrap();
+
+    // The header and the payload of each message are sent separately, in order.
+    let mut client = test_client::TestClient::new(channel).accept_compressed(encoding);
+    let mut stream = client
+        .compress_output_server_stream(())
+        .await
+        .unwrap()
+        .into_inner();
+    let mut messages = 0;
+    while let Some(message) = stream.message().await.unwrap() {
+        assert_eq!(message.data.len(), UNCOMPRESSED_MIN_BODY_SIZE);
+        messages += 1;
+    }
+    assert_eq!(messages, 2);
+    assert_eq!(server_tasks.load(SeqCst), 2);
+}
@@ -20,6 +20,7 @@ use tower_http::{map_request_body::MapRequestBodyLayer, map_response_body::MapRe
 
 mod bidirectional_stream;
 mod client_stream;
+mod codec_executor;
 mod compressing_request;
 mod compressing_response;
 mod server_stream;
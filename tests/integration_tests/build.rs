@@ -1,4 +1,15 @@
+use std::{env, path::PathBuf};
+
 fn main() {
-    tonic_prost_build::compile_protos("proto/test.proto").unwrap();
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    tonic_prost_build::configure()
+        .file_descriptor_set_path(out_dir.join("test_descriptor.bin"))
+        .compile_protos(&["proto/test.proto"], &["proto"])
+        .unwrap();
     tonic_prost_build::compile_protos("proto/stream.proto").unwrap();
+    tonic_prost_build::configure()
+        .file_descriptor_set_path(out_dir.join("transcoding_descriptor.bin"))
+        .compile_protos(&["proto/transcoding.proto"], &["proto"])
+        .unwrap();
 }
@@ -1,6 +1,15 @@
 pub mod pb {
     tonic::include_proto!("test");
     tonic::include_proto!("stream");
+
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("test_descriptor");
+
+    pub mod transcoding {
+        tonic::include_proto!("transcoding");
+
+        pub const FILE_DESCRIPTOR_SET: &[u8] =
+            tonic::include_file_descriptor_set!("transcoding_descriptor");
+    }
 }
 
 pub mod mock {
@@ -72,3 +81,9 @@ pub fn trace_init() {
 }
 
 pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
+impl tonic::resume::ResumeToken for pb::Output1 {
+    fn resume_token(&self) -> Option<bytes::Bytes> {
+        Some(self.buf.clone().into())
+    }
+}
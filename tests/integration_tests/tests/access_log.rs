@@ -0,0 +1,57 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use tokio::{net::TcpListener, sync::mpsc};
+use tonic::{service::AccessLogLayer, transport::Server, Code, Request, Response, Status};
+
+#[tokio::test]
+async fn logs_unary_calls() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            match req.metadata().get("fail") {
+                Some(_) => Err(Status::internal("failed")),
+                None => Ok(Response::new(Output {})),
+            }
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let layer = AccessLogLayer::new().sink(move |record| {
+        let _ = tx.send(record.clone());
+    });
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(layer)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    client.unary_call(Input {}).await.unwrap();
+    let record = rx.recv().await.unwrap();
+    assert_eq!(record.method(), "/test.Test/UnaryCall");
+    assert_eq!(record.code(), Code::Ok);
+    assert_eq!(record.peer().unwrap().ip(), addr.ip());
+    assert!(record.user_agent().unwrap().starts_with("tonic/"));
+    assert_eq!(record.request_messages(), 1);
+    assert_eq!(record.request_bytes(), 5);
+    assert_eq!(record.response_messages(), 1);
+    assert_eq!(record.response_bytes(), 5);
+
+    let mut req = Request::new(Input {});
+    req.metadata_mut().insert("fail", "true".parse().unwrap());
+    let err = client.unary_call(req).await.unwrap_err();
+    assert_eq!(err.code(), Code::Internal);
+    let record = rx.recv().await.unwrap();
+    assert_eq!(record.code(), Code::Internal);
+    assert_eq!(record.response_messages(), 0);
+}
@@ -0,0 +1,106 @@
+use integration_tests::pb::{test_server, Input, Output};
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    reflection::ReflectionService,
+    transport::{server::AdminServices, Channel, Server},
+    Request, Response, Status,
+};
+use tonic_health::pb::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+use tonic_reflection::pb::v1::{
+    server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse, ServerReflectionRequest,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(admin: &AdminServices) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let router = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .add_admin_services(admin);
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+async fn check(channel: Channel, service: &str) -> Result<ServingStatus, Status> {
+    let request = HealthCheckRequest {
+        service: service.to_owned(),
+    };
+    let response = HealthClient::new(channel).check(request).await?;
+    Ok(response.into_inner().status())
+}
+
+#[tokio::test]
+async fn mounts_health_and_reflection() {
+    let reflection = ReflectionService::builder()
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()
+        .unwrap();
+    let admin = AdminServices::new().reflection(reflection);
+    admin
+        .health_reporter()
+        .set_serving::<test_server::TestServer<Svc>>();
+    let channel = serve(&admin).await;
+
+    assert_eq!(
+        check(channel.clone(), "").await.unwrap(),
+        ServingStatus::Serving
+    );
+    assert_eq!(
+        check(channel.clone(), "test.Test").await.unwrap(),
+        ServingStatus::Serving
+    );
+
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = ServerReflectionClient::new(channel)
+        .server_reflection_info(tokio_stream::iter([request]))
+        .await
+        .unwrap()
+        .into_inner();
+    let response = responses.next().await.unwrap().unwrap();
+    let Some(MessageResponse::ListServicesResponse(services)) = response.message_response else {
+        panic!("unexpected response");
+    };
+    assert_eq!(services.service[0].name, "grpc.health.v1.Health");
+}
+
+#[tokio::test]
+async fn skips_reflection_until_set() {
+    let channel = serve(&AdminServices::new()).await;
+
+    assert_eq!(
+        check(channel.clone(), "").await.unwrap(),
+        ServingStatus::Serving
+    );
+
+    let err = ServerReflectionClient::new(channel)
+        .server_reflection_info(tokio_stream::iter(Vec::<ServerReflectionRequest>::new()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::Unimplemented);
+}
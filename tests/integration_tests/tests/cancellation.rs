@@ -0,0 +1,101 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic::{
+    transport::{server::CancellationToken, Server},
+    Request, Response, Status,
+};
+
+type TokenSlot = Arc<Mutex<Option<CancellationToken>>>;
+
+struct Svc(TokenSlot);
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        *self.0.lock().unwrap() = req.cancellation_token();
+        Ok(Response::new(Output1::default()))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        req: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let token = req.cancellation_token().unwrap();
+        *self.0.lock().unwrap() = Some(token.clone());
+
+        // Produces messages in the background until the client goes away.
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tx.send(Ok(Output1::default())) => {}
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+async fn serve() -> (
+    test1_client::Test1Client<tonic::transport::Channel>,
+    TokenSlot,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let token = Arc::new(Mutex::new(None));
+    let svc = test1_server::Test1Server::new(Svc(token.clone()));
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    let client = test1_client::Test1Client::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+    (client, token)
+}
+
+#[tokio::test]
+async fn cancels_when_client_drops_stream() {
+    let (mut client, slot) = serve().await;
+
+    let mut stream = client
+        .stream_call(Input1::default())
+        .await
+        .unwrap()
+        .into_inner();
+    stream.next().await.unwrap().unwrap();
+
+    let token = slot.lock().unwrap().clone().unwrap();
+    assert!(!token.is_cancelled());
+
+    drop(stream);
+    tokio::time::timeout(Duration::from_secs(5), token.cancelled())
+        .await
+        .expect("token was not cancelled");
+}
+
+#[tokio::test]
+async fn does_not_cancel_completed_calls() {
+    let (mut client, slot) = serve().await;
+
+    client.unary_call(Input1::default()).await.unwrap();
+    let token = slot.lock().unwrap().clone().unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!token.is_cancelled());
+}
@@ -0,0 +1,91 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::pin::Pin;
+use tokio::net::TcpListener;
+use tokio_stream::Stream;
+use tonic::{
+    service::{Capabilities, CapabilitiesLayer},
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        // Answers with the version of `fields` both sides agree on, if any.
+        let buf = match req.peer_capabilities() {
+            Some(peer) => server_capabilities()
+                .negotiate(peer)
+                .version("fields")
+                .map_or(vec![], |v| vec![v as u8]),
+            None => vec![],
+        };
+
+        Ok(Response::new(Output1 { buf }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        unimplemented!()
+    }
+}
+
+fn server_capabilities() -> Capabilities {
+    Capabilities::new().feature("zstd").versioned("fields", 2)
+}
+
+async fn channel() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(CapabilitiesLayer::server(server_capabilities()))
+            .add_service(test1_server::Test1Server::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn exchanges_capabilities() {
+    let svc = ServiceBuilder::new()
+        .layer(CapabilitiesLayer::client(
+            Capabilities::new().versioned("fields", 3),
+        ))
+        .service(channel().await);
+    let mut client = test1_client::Test1Client::new(svc);
+
+    let res = client.unary_call(Input1 { buf: vec![] }).await.unwrap();
+
+    let peer = res.peer_capabilities().unwrap();
+    assert!(peer.supports("zstd"));
+    assert_eq!(peer.version("fields"), Some(2));
+    assert_eq!(res.into_inner().buf, [2]);
+}
+
+#[tokio::test]
+async fn peers_without_capabilities() {
+    let mut client = test1_client::Test1Client::new(channel().await);
+
+    let res = client.unary_call(Input1 { buf: vec![] }).await.unwrap();
+
+    // The server still advertises its capabilities, but had nothing to negotiate with.
+    assert!(res.peer_capabilities().is_none());
+    assert!(res.metadata().get("tonic-capabilities").is_some());
+    assert!(res.into_inner().buf.is_empty());
+}
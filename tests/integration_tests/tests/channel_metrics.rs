@@ -0,0 +1,124 @@
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::net::TcpListener;
+use tonic::{
+    body::Body,
+    service::{ServiceConfig, ServiceConfigLayer},
+    transport::{channel::ConnectivityState, server::TcpIncoming, Channel, Endpoint, Server},
+    Request, Response, Status,
+};
+use tower::{ServiceBuilder, ServiceExt};
+
+/// Fails the first two calls it counts.
+struct Svc(AtomicUsize);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        if self.0.fetch_add(1, Ordering::SeqCst) < 2 {
+            Err(Status::unavailable("not yet"))
+        } else {
+            Ok(Response::new(Output {}))
+        }
+    }
+}
+
+async fn connect(calls: usize) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc(AtomicUsize::new(calls))))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn counts_connections_retries_and_bytes() {
+    let channel = connect(0).await;
+    let metrics = channel.metrics();
+    assert_eq!(metrics.state(), ConnectivityState::Ready);
+    assert_eq!(metrics.connections(), 1);
+    assert_eq!(metrics.in_flight(), 0);
+
+    let config = ServiceConfig::from_json(
+        r#"{
+            "methodConfig": [{
+                "name": [{ "service": "test.Test" }],
+                "retryPolicy": {
+                    "maxAttempts": 3,
+                    "initialBackoff": "0.01s",
+                    "maxBackoff": "0.1s",
+                    "backoffMultiplier": 2,
+                    "retryableStatusCodes": ["UNAVAILABLE"]
+                }
+            }]
+        }"#,
+    )
+    .unwrap();
+    let mut client = TestClient::new(
+        ServiceBuilder::new()
+            .layer(ServiceConfigLayer::new(config))
+            .service(channel.clone()),
+    );
+    client.unary_call(Input {}).await.unwrap();
+
+    // Clones of the channel share its metrics.
+    let metrics = channel.metrics();
+    assert_eq!(metrics.retries(), 2);
+    assert_eq!(metrics.in_flight(), 0);
+    assert_eq!(metrics.queued_requests(), 0);
+    assert!(metrics.bytes_sent() > 0);
+    assert!(metrics.bytes_received() > 0);
+}
+
+#[tokio::test]
+async fn calls_are_in_flight_until_their_response_ends() {
+    let channel = connect(2).await;
+
+    // An empty `Input` message.
+    let message = Bytes::from_static(&[0; 5]);
+    let request = http::Request::builder()
+        .uri("/test.Test/UnaryCall")
+        .header("content-type", "application/grpc")
+        .body(Body::new(Full::new(message)))
+        .unwrap();
+    let response = channel.clone().oneshot(request).await.unwrap();
+    assert_eq!(channel.metrics().in_flight(), 1);
+
+    // Only the body of the response is left.
+    drop(response.into_body().collect().await.unwrap());
+    assert_eq!(channel.metrics().in_flight(), 0);
+}
+
+#[tokio::test]
+async fn reports_failed_connections() {
+    // A port nothing listens on.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    assert_eq!(channel.metrics().state(), ConnectivityState::Idle);
+
+    let mut client = TestClient::new(channel.clone());
+    client.unary_call(Input {}).await.unwrap_err();
+
+    let metrics = channel.metrics();
+    assert_eq!(metrics.state(), ConnectivityState::TransientFailure);
+    assert_eq!(metrics.connections(), 0);
+    assert_eq!(metrics.queued_requests(), 0);
+    assert_eq!(metrics.in_flight(), 0);
+}
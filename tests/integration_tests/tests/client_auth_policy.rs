@@ -0,0 +1,125 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::{ClientAuth, TcpIncoming},
+        Certificate, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig,
+    },
+    Code, Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        match req.peer_certs() {
+            Some(_) => Ok(Response::new(Output {})),
+            None => Err(Status::unauthenticated("no client certificate")),
+        }
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+async fn serve() -> SocketAddr {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let tls = ServerTlsConfig::new()
+        .identity(Identity::from_pem(read("server.pem"), read("server.key")))
+        .client_ca_root(Certificate::from_pem(read("client3.pem")))
+        .client_auth_policy(|conn| {
+            if !conn
+                .remote_addr()
+                .is_some_and(|addr| addr.ip().is_loopback())
+            {
+                return ClientAuth::Required;
+            }
+            match conn.server_name() {
+                Some("internal.example.com") => ClientAuth::Required,
+                Some("example.test") => ClientAuth::Optional,
+                _ => ClientAuth::Disabled,
+            }
+        });
+
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+async fn call(addr: SocketAddr, domain: &str, client: Option<&str>) -> Result<(), Status> {
+    let mut tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name(domain);
+    if let Some(client) = client {
+        tls = tls.identity(Identity::from_pem(
+            read(&format!("{client}.pem")),
+            read(&format!("{client}.key")),
+        ));
+    }
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect_lazy();
+
+    TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .map(drop)
+}
+
+#[tokio::test]
+async fn requires_client_cert_when_policy_says_so() {
+    let addr = serve().await;
+
+    call(addr, "internal.example.com", Some("client3"))
+        .await
+        .unwrap();
+
+    call(addr, "internal.example.com", None).await.unwrap_err();
+    call(addr, "internal.example.com", Some("client1"))
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn accepts_missing_client_cert_when_optional() {
+    let addr = serve().await;
+
+    call(addr, "example.test", Some("client3")).await.unwrap();
+
+    let status = call(addr, "example.test", None).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    call(addr, "example.test", Some("client1"))
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn ignores_client_cert_when_disabled() {
+    let addr = serve().await;
+
+    let status = call(addr, "example.com", Some("client1"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
@@ -3,7 +3,7 @@ use integration_tests::pb::{test_client::TestClient, test_server, Input, Output}
 use std::time::Duration;
 use tokio::{net::TcpListener, sync::oneshot};
 use tonic::{
-    transport::{server::TcpIncoming, Endpoint, Server},
+    transport::{server::TcpIncoming, Channel, Endpoint, Server},
     Request, Response, Status,
 };
 use tower::ServiceBuilder;
@@ -61,3 +61,48 @@ async fn connect_supports_standard_tower_layers() {
     tx.send(()).unwrap();
     jh.await.unwrap();
 }
+
+#[tokio::test]
+async fn endpoint_layers_apply_per_endpoint() {
+    struct Svc(String);
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            match req.metadata().get("x-endpoint") {
+                Some(value) if value == self.0.as_str() => Ok(Response::new(Output {})),
+                _ => Err(Status::internal("x-endpoint header does not match")),
+            }
+        }
+    }
+
+    let mut endpoints = Vec::new();
+    for name in ["a", "b"] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from(listener);
+
+        tokio::spawn(async move {
+            Server::builder()
+                .add_service(test_server::TestServer::new(Svc(name.into())))
+                .serve_with_incoming(incoming)
+                .await
+                .unwrap();
+        });
+
+        let endpoint = Endpoint::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .layer(SetRequestHeaderLayer::overriding(
+                HeaderName::from_static("x-endpoint"),
+                HeaderValue::from_static(name),
+            ))
+            .layer(TraceLayer::new_for_grpc());
+        endpoints.push(endpoint);
+    }
+
+    let mut client = TestClient::new(Channel::balance_list(endpoints.into_iter()));
+
+    for _ in 0..10 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+}
@@ -68,7 +68,10 @@ pub mod unix {
     };
     use tokio_stream::wrappers::UnixListenerStream;
     use tonic::{
-        transport::{server::UdsConnectInfo, Endpoint, Server, Uri},
+        transport::{
+            server::{UdsConnectInfo, UdsIncoming},
+            Endpoint, Server, Uri,
+        },
         Request, Response, Status,
     };
     use tower::service_fn;
@@ -86,8 +89,19 @@ pub mod unix {
             assert!(req.local_addr().is_none());
             assert!(req.remote_addr().is_none());
             assert!(conn_info.peer_addr.as_ref().unwrap().is_unnamed());
+            // The server socket is bound to a path.
+            assert!(conn_info
+                .local_addr
+                .as_ref()
+                .unwrap()
+                .as_pathname()
+                .is_some_and(|path| path.starts_with(std::env::temp_dir())));
             // This should contain process credentials for the client socket.
             assert!(conn_info.peer_cred.as_ref().is_some());
+            assert_eq!(
+                req.peer_cred().unwrap().pid(),
+                Some(std::process::id() as i32)
+            );
 
             Ok(Response::new(Output {}))
         }
@@ -133,4 +147,56 @@ pub mod unix {
 
         std::fs::remove_file(unix_socket_path).unwrap();
     }
+
+    #[tokio::test]
+    async fn sets_socket_permissions() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let mut unix_socket_path = std::env::temp_dir();
+        unix_socket_path.push("uds-permissions-integration-test");
+        let _ = std::fs::remove_file(&unix_socket_path);
+
+        let incoming = UdsIncoming::bind(&unix_socket_path).unwrap();
+        assert_eq!(incoming.path(), unix_socket_path);
+
+        // Only a privileged process can give the socket to another user.
+        let uid = std::fs::metadata(&unix_socket_path).unwrap().uid();
+        let incoming = incoming
+            .with_mode(0o600)
+            .unwrap()
+            .with_owner(Some(uid), None)
+            .unwrap();
+
+        let metadata = std::fs::metadata(&unix_socket_path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(metadata.uid(), uid);
+
+        let service = test_server::TestServer::new(Svc {});
+        let (tx, rx) = oneshot::channel::<()>();
+        let jh = tokio::spawn(async move {
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async { drop(rx.await) })
+                .await
+                .unwrap();
+        });
+
+        let path = unix_socket_path.clone();
+        let channel = Endpoint::try_from("http://[::]:50051")
+            .unwrap()
+            .connect_with_connector(service_fn(move |_: Uri| {
+                let path = path.clone();
+                async move { Ok::<_, io::Error>(TokioIo::new(UnixStream::connect(path).await?)) }
+            }))
+            .await
+            .unwrap();
+
+        let mut client = test_client::TestClient::new(channel);
+        client.unary_call(Input {}).await.unwrap();
+
+        tx.send(()).unwrap();
+        jh.await.unwrap();
+
+        std::fs::remove_file(unix_socket_path).unwrap();
+    }
 }
@@ -0,0 +1,88 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, sync::mpsc};
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn listen() -> (SocketAddr, TcpIncoming) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    (addr, TcpIncoming::from(listener))
+}
+
+#[tokio::test]
+async fn reports_connection_lifecycle() {
+    let (addr, incoming) = listen().await;
+    let (connected_tx, mut connected_rx) = mpsc::unbounded_channel();
+    let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .on_connect(move |conn| {
+                connected_tx.send(conn.remote_addr()).unwrap();
+                true
+            })
+            .on_disconnect(move |conn, stats| {
+                closed_tx
+                    .send((conn.remote_addr(), stats.streams()))
+                    .unwrap();
+            })
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+    for _ in 0..3 {
+        client.unary_call(Input {}).await.unwrap();
+    }
+
+    let remote_addr = connected_rx.recv().await.unwrap();
+    assert!(remote_addr.is_some());
+
+    drop(client);
+    let closed = tokio::time::timeout(Duration::from_secs(5), closed_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(closed, (remote_addr, 3));
+}
+
+#[tokio::test]
+async fn closes_connections_refused_by_hook() {
+    let (addr, incoming) = listen().await;
+
+    tokio::spawn(async move {
+        Server::builder()
+            .on_connect(|_| false)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+}
@@ -0,0 +1,99 @@
+use hyper_util::rt::TokioIo;
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::net::{TcpListener, TcpStream};
+use tonic::{
+    time::{Clock, Sleep},
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+use tower::service_fn;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// A clock that sleeps on threads, standing in for the timer of another runtime.
+struct ThreadClock;
+
+impl Clock for ThreadClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            let _ = tx.send(());
+        });
+
+        Box::pin(async move {
+            let _ = rx.await;
+        })
+    }
+}
+
+/// Runs the server on its own runtime, as it relies on the Tokio timer.
+fn run_server() -> SocketAddr {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            tx.send(listener.local_addr().unwrap()).unwrap();
+
+            Server::builder()
+                .add_service(test_server::TestServer::new(Svc))
+                .serve_with_incoming(TcpIncoming::from(listener))
+                .await
+                .unwrap();
+        });
+    });
+
+    rx.recv().unwrap()
+}
+
+#[test]
+fn client_runs_without_tokio_timer() {
+    let addr = run_server();
+
+    // The runtime has no time driver, so any use of the Tokio timer panics.
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    rt.block_on(async move {
+        let channel = Endpoint::from_static("http://[::]:50051")
+            .clock(ThreadClock)
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(5))
+            .http2_keep_alive_interval(Duration::from_secs(5))
+            .rate_limit(2, Duration::from_millis(50))
+            .connect_with_connector(service_fn(move |_| async move {
+                Ok::<_, std::io::Error>(TokioIo::new(TcpStream::connect(addr).await?))
+            }))
+            .await
+            .unwrap();
+
+        let mut client = test_client::TestClient::new(channel);
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            client.unary_call(Input {}).await.unwrap();
+        }
+
+        // The 5 calls span at least two rate limit periods.
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    });
+}
@@ -0,0 +1,113 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{net::SocketAddr, time::Duration};
+use tokio::net::TcpListener;
+use tonic::{
+    service::DeadlinePropagationLayer,
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+
+/// Answers with the `grpc-timeout` it received.
+struct Backend;
+
+#[tonic::async_trait]
+impl test_server::Test for Backend {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut response = Response::new(Output {});
+        if let Some(timeout) = req.metadata().get("grpc-timeout") {
+            response.metadata_mut().insert("x-timeout", timeout.clone());
+        }
+        Ok(response)
+    }
+}
+
+/// Calls the backend, after sleeping for the duration in `x-sleep-ms` if any.
+struct Frontend(TestClient<Channel>);
+
+#[tonic::async_trait]
+impl test_server::Test for Frontend {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        if let Some(sleep) = req.metadata().get("x-sleep-ms") {
+            let ms = sleep.to_str().unwrap().parse().unwrap();
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+        self.0.clone().unary_call(Input {}).await
+    }
+}
+
+async fn serve<S>(svc: test_server::TestServer<S>) -> SocketAddr
+where
+    S: test_server::Test,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(DeadlinePropagationLayer::new().margin(Duration::from_millis(100)))
+            .add_service(svc)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+fn client(addr: SocketAddr) -> TestClient<Channel> {
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    TestClient::new(channel)
+}
+
+async fn frontend() -> TestClient<Channel> {
+    let backend = serve(test_server::TestServer::new(Backend)).await;
+    let frontend = serve(test_server::TestServer::new(Frontend(client(backend)))).await;
+    client(frontend)
+}
+
+fn timeout(response: &Response<Output>) -> Option<Duration> {
+    let value = response.metadata().get("x-timeout")?.to_str().unwrap();
+    let (value, unit) = value.split_at(value.len() - 1);
+    let value: u64 = value.parse().unwrap();
+    Some(match unit {
+        "H" => Duration::from_secs(value * 3600),
+        "M" => Duration::from_secs(value * 60),
+        "S" => Duration::from_secs(value),
+        "m" => Duration::from_millis(value),
+        "u" => Duration::from_micros(value),
+        "n" => Duration::from_nanos(value),
+        _ => panic!("invalid grpc-timeout {value}{unit}"),
+    })
+}
+
+#[tokio::test]
+async fn deadline_is_propagated_to_outgoing_calls() {
+    let mut client = frontend().await;
+
+    let mut req = Request::new(Input {});
+    req.set_timeout(Duration::from_secs(5));
+    let res = client.unary_call(req).await.unwrap();
+    let timeout = timeout(&res).unwrap();
+    assert!(timeout <= Duration::from_millis(4900), "{timeout:?}");
+    assert!(timeout > Duration::from_secs(4), "{timeout:?}");
+
+    // Calls without a deadline propagate nothing.
+    let res = client.unary_call(Input {}).await.unwrap();
+    assert_eq!(self::timeout(&res), None);
+}
+
+#[tokio::test]
+async fn expired_deadline_fails_outgoing_calls() {
+    let mut client = frontend().await;
+
+    let mut req = Request::new(Input {});
+    req.set_timeout(Duration::from_millis(300));
+    req.metadata_mut()
+        .insert("x-sleep-ms", "250".parse().unwrap());
+    let status = client.unary_call(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+    assert_eq!(
+        status.message(),
+        "propagated deadline exceeded before the call was made"
+    );
+}
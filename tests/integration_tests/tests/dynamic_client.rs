@@ -0,0 +1,186 @@
+use integration_tests::pb::{test1_server, Input1, Output1, FILE_DESCRIPTOR_SET};
+use serde_json::json;
+use std::pin::Pin;
+use tokio::net::TcpListener;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    reflection::ReflectionService,
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tonic_prost::dynamic::{
+    prost_reflect::{DynamicMessage, Value},
+    DynamicClient,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        let mut buf = req.into_inner().buf;
+        buf.reverse();
+        Ok(Response::new(Output1 { buf }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send>>;
+
+    async fn stream_call(
+        &self,
+        req: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let buf = req.into_inner().buf;
+        let stream = tokio_stream::iter(1..=buf.len()).map(move |len| {
+            Ok(Output1 {
+                buf: buf[..len].to_vec(),
+            })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+async fn client() -> DynamicClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test1_server::Test1Server::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    DynamicClient::from_encoded_file_descriptor_set(channel, FILE_DESCRIPTOR_SET).unwrap()
+}
+
+fn input(client: &DynamicClient<Channel>, buf: &[u8]) -> DynamicMessage {
+    let method = client.method("test.Test1.UnaryCall").unwrap();
+    let mut message = DynamicMessage::new(method.input());
+    message.set_field_by_name("buf", Value::Bytes(buf.to_vec().into()));
+    message
+}
+
+#[tokio::test]
+async fn unary() {
+    let mut client = client().await;
+
+    let request = input(&client, b"abc");
+    let response = client
+        .unary("/test.Test1/UnaryCall", request)
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        response
+            .get_field_by_name("buf")
+            .unwrap()
+            .as_bytes()
+            .unwrap(),
+        &b"cba"[..]
+    );
+
+    let response = client
+        .unary_json("test.Test1.UnaryCall", json!({ "buf": "YWJj" }))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner(), json!({ "buf": "Y2Jh" }));
+}
+
+#[tokio::test]
+async fn server_streaming() {
+    let mut client = client().await;
+
+    let request = input(&client, b"abc");
+    let responses = client
+        .server_streaming("test.Test1.StreamCall", request)
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|message| {
+            let message = message.unwrap();
+            message
+                .get_field_by_name("buf")
+                .unwrap()
+                .as_bytes()
+                .unwrap()
+                .len()
+        })
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(responses, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn invalid_calls() {
+    let mut client = client().await;
+
+    let status = client
+        .unary("test.Test1.Missing", input(&client, b""))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+
+    let status = client
+        .unary("test.Test1.StreamCall", input(&client, b""))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "method test.Test1.StreamCall is server streaming, not unary"
+    );
+
+    let output = client.method("test.Test1.UnaryCall").unwrap().output();
+    let status = client
+        .unary("test.Test1.UnaryCall", DynamicMessage::new(output))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = client
+        .unary_json("test.Test1.UnaryCall", json!({ "buf": 1 }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "invalid JSON message: buf: expected a base64 string"
+    );
+}
+
+#[tokio::test]
+async fn discover() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let reflection = ReflectionService::builder()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test1_server::Test1Server::new(Svc))
+            // Only v1alpha, which the client falls back to.
+            .add_service(reflection.v1alpha())
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = DynamicClient::discover(channel).await.unwrap();
+
+    let response = client
+        .unary_json("test.Test1.UnaryCall", json!({ "buf": "YWJj" }))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner(), json!({ "buf": "Y2Jh" }));
+}
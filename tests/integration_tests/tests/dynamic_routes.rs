@@ -0,0 +1,127 @@
+use integration_tests::pb::{
+    test1_client::Test1Client, test1_server, test_client::TestClient, test_server, Input, Input1,
+    Output, Output1,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    codegen::BoxStream,
+    service::DynamicRoutes,
+    transport::{server::TcpIncoming, Channel, Endpoint, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc(&'static str);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("svc", self.0.parse().unwrap());
+        Ok(response)
+    }
+}
+
+struct Svc1;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc1 {
+    async fn unary_call(&self, request: Request<Input1>) -> Result<Response<Output1>, Status> {
+        Ok(Response::new(Output1 {
+            buf: request.into_inner().buf,
+        }))
+    }
+
+    type StreamCallStream = BoxStream<Output1>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        Err(Status::unimplemented(""))
+    }
+}
+
+async fn serve(router: tonic::transport::server::Router) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+async fn call(channel: &Channel) -> Result<String, Status> {
+    let response = TestClient::new(channel.clone())
+        .unary_call(Input {})
+        .await?;
+    Ok(response
+        .metadata()
+        .get("svc")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .into())
+}
+
+#[tokio::test]
+async fn adds_and_removes_services_while_serving() {
+    let dynamic = DynamicRoutes::new();
+    let channel = serve(
+        Server::builder()
+            .add_service(test1_server::Test1Server::new(Svc1))
+            .add_dynamic_routes(dynamic.clone()),
+    )
+    .await;
+
+    assert_eq!(
+        call(&channel).await.unwrap_err().code(),
+        Code::Unimplemented
+    );
+
+    assert!(!dynamic.add_service(test_server::TestServer::new(Svc("first"))));
+    assert_eq!(call(&channel).await.unwrap(), "first");
+
+    assert!(dynamic.add_service(test_server::TestServer::new(Svc("second"))));
+    assert_eq!(call(&channel).await.unwrap(), "second");
+    assert_eq!(dynamic.service_names(), ["test.Test"]);
+
+    assert!(dynamic.remove_service("test.Test"));
+    assert!(!dynamic.contains_service("test.Test"));
+    assert_eq!(
+        call(&channel).await.unwrap_err().code(),
+        Code::Unimplemented
+    );
+
+    // Statically added services are still served.
+    let response = Test1Client::new(channel)
+        .unary_call(Input1 { buf: vec![1, 2] })
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().buf, [1, 2]);
+}
+
+#[tokio::test]
+async fn static_services_take_precedence() {
+    let dynamic = DynamicRoutes::new();
+    dynamic.add_service(test_server::TestServer::new(Svc("dynamic")));
+
+    let channel = serve(
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc("static")))
+            .add_dynamic_routes(dynamic),
+    )
+    .await;
+
+    assert_eq!(call(&channel).await.unwrap(), "static");
+}
@@ -0,0 +1,110 @@
+use integration_tests::pb::{
+    test1_client::Test1Client, test_stream_client::TestStreamClient, Input1, InputStream,
+    FILE_DESCRIPTOR_SET,
+};
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    service::Routes,
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tonic_prost::dynamic::{
+    prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor, Value},
+    DynamicService, DynamicStream,
+};
+
+/// Answers each request message with its prefixes, or with a message of the wrong type if it is
+/// empty.
+async fn handler(
+    method: MethodDescriptor,
+    request: Request<DynamicStream>,
+) -> Result<Response<DynamicStream>, Status> {
+    let mut messages = request.into_inner();
+    let message = messages.next().await.unwrap()?;
+    let buf = message
+        .get_field_by_name("buf")
+        .unwrap()
+        .as_bytes()
+        .unwrap()
+        .clone();
+
+    let responses = (1..=buf.len().max(1)).map(move |len| {
+        let descriptor = if buf.is_empty() {
+            method.input()
+        } else {
+            method.output()
+        };
+        let mut response = DynamicMessage::new(descriptor);
+        response.set_field_by_name("buf", Value::Bytes(buf.slice(..len.min(buf.len()))));
+        Ok(response)
+    });
+    Ok(Response::new(Box::pin(tokio_stream::iter(responses))))
+}
+
+async fn channel() -> Channel {
+    let pool = DescriptorPool::decode(FILE_DESCRIPTOR_SET).unwrap();
+    let svc = DynamicService::new(pool, handler);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_routes(Routes::default().fallback_service(svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn serves_all_shapes() {
+    let mut client = Test1Client::new(channel().await);
+
+    let response = client
+        .unary_call(Input1 {
+            buf: b"abc".to_vec(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().buf, b"a");
+
+    let responses = client
+        .stream_call(Input1 {
+            buf: b"abc".to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|output| output.unwrap().buf)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(responses, [&b"a"[..], b"ab", b"abc"]);
+}
+
+#[tokio::test]
+async fn invalid_calls() {
+    let channel = channel().await;
+
+    let status = Test1Client::new(channel.clone())
+        .unary_call(Input1 { buf: Vec::new() })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(
+        status.message(),
+        "handler responded with a test.Input1 message instead of test.Output1"
+    );
+
+    let status = TestStreamClient::new(channel)
+        .stream_call(InputStream {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
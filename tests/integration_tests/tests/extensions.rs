@@ -10,7 +10,7 @@ use tokio::{net::TcpListener, sync::oneshot};
 use tonic::{
     body::Body,
     server::NamedService,
-    transport::{server::TcpIncoming, Endpoint, Server},
+    transport::{channel::EndpointAttributes, server::TcpIncoming, Channel, Endpoint, Server},
     Request, Response, Status,
 };
 use tower_service::Service;
@@ -117,6 +117,51 @@ async fn setting_extension_from_tower() {
     jh.await.unwrap();
 }
 
+#[tokio::test]
+async fn endpoint_attributes_in_response_extensions() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    let svc = test_server::TestServer::new(Svc);
+
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener).with_nodelay(Some(true));
+
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming_shutdown(incoming, async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let endpoint = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .attribute("zone", "a");
+    let channel = Channel::balance_list(std::iter::once(endpoint));
+
+    let mut client = test_client::TestClient::new(channel);
+
+    let res = client.unary_call(Input {}).await.unwrap();
+    let attributes = res.extensions().get::<EndpointAttributes>().unwrap();
+    assert_eq!(attributes.get("zone"), Some("a"));
+
+    tx.send(()).unwrap();
+
+    jh.await.unwrap();
+}
+
 #[derive(Debug, Clone)]
 struct InterceptedService<S> {
     inner: S,
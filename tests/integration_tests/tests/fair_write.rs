@@ -0,0 +1,77 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+use tokio_stream::Stream;
+use tonic::{
+    service::StreamBacklog,
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+
+const LEN: usize = 100 * 1024;
+
+/// Answers with a message of `LEN` bytes and keeps the backlog of its response.
+struct Svc(Arc<Mutex<Option<StreamBacklog>>>);
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        *self.0.lock().unwrap() = req.extensions().get::<StreamBacklog>().cloned();
+
+        Ok(Response::new(Output1 { buf: vec![1; LEN] }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn tracks_backlog_of_both_sides() {
+    let backlog = Arc::new(Mutex::new(None));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let svc = test1_server::Test1Server::new(Svc(backlog.clone()));
+    tokio::spawn(async move {
+        Server::builder()
+            .fair_write_quantum(1024)
+            .add_service(svc)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .fair_write_quantum(1024)
+        .connect()
+        .await
+        .unwrap();
+    let mut client = test1_client::Test1Client::new(channel);
+
+    let res = client
+        .unary_call(Input1 { buf: vec![2; LEN] })
+        .await
+        .unwrap();
+
+    // The response carries the backlog of the request, which was fully written.
+    let request_backlog = res.extensions().get::<StreamBacklog>().unwrap();
+    assert_eq!(request_backlog.pending_bytes(), 0);
+    assert!(request_backlog.written_bytes() > LEN as u64);
+    assert_eq!(res.into_inner().buf.len(), LEN);
+
+    let response_backlog = backlog.lock().unwrap().clone().unwrap();
+    assert_eq!(response_backlog.pending_bytes(), 0);
+    assert!(response_backlog.written_bytes() > LEN as u64);
+}
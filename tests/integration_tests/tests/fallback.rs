@@ -0,0 +1,76 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+use tonic::{
+    body::Body,
+    service::{DynamicRoutes, Routes},
+    transport::{
+        server::{Router, TcpIncoming},
+        Channel, Endpoint, Server,
+    },
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(router: Router) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn fallback() -> Router {
+    let routes = Routes::default().fallback_service(tower::service_fn(
+        |req: http::Request<Body>| async move {
+            let status = Status::not_found(format!("no route for {}", req.uri().path()));
+            Ok::<_, Infallible>(status.into_http::<Body>())
+        },
+    ));
+    Server::builder().add_routes(routes)
+}
+
+#[tokio::test]
+async fn answers_unknown_services_with_fallback() {
+    let channel = serve(fallback()).await;
+
+    let status = TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    assert_eq!(status.message(), "no route for /test.Test/UnaryCall");
+}
+
+#[tokio::test]
+async fn answers_unknown_dynamic_services_with_fallback() {
+    let dynamic = DynamicRoutes::new();
+    let channel = serve(fallback().add_dynamic_routes(dynamic.clone())).await;
+    let mut client = TestClient::new(channel);
+
+    assert_eq!(
+        client.unary_call(Input {}).await.unwrap_err().code(),
+        Code::NotFound
+    );
+
+    dynamic.add_service(test_server::TestServer::new(Svc));
+    client.unary_call(Input {}).await.unwrap();
+}
@@ -0,0 +1,128 @@
+use integration_tests::pb::{test1_client::Test1Client, test1_server, Input1, Output1};
+use std::time::{Duration, Instant};
+use tonic::{
+    service::{Fault, FaultInjectionLayer},
+    testing::TestServer,
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+const PAYLOAD: &[u8] = b"0123456789";
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, _: Request<Input1>) -> Result<Response<Output1>, Status> {
+        Ok(Response::new(Output1 {
+            buf: PAYLOAD.to_vec(),
+        }))
+    }
+
+    type StreamCallStream = tokio_stream::Empty<Result<Output1, Status>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        unimplemented!()
+    }
+}
+
+async fn start(layer: FaultInjectionLayer) -> TestServer {
+    let router = Server::builder()
+        .layer(layer)
+        .add_service(test1_server::Test1Server::new(Svc));
+    TestServer::start_in_memory(router).await
+}
+
+fn client(
+    channel: Channel,
+    layer: FaultInjectionLayer,
+) -> Test1Client<tonic::service::FaultInjection<Channel>> {
+    Test1Client::new(ServiceBuilder::new().layer(layer).service(channel))
+}
+
+fn tagged(fault: &str) -> Request<Input1> {
+    let mut req = Request::new(Input1::default());
+    req.metadata_mut().insert("x-fault", fault.parse().unwrap());
+    req
+}
+
+#[tokio::test]
+async fn fails_calls_with_matching_metadata() {
+    let layer = FaultInjectionLayer::new()
+        .fault(Fault::status(Status::unavailable("injected")).when_metadata("x-fault", "fail"));
+    let server = start(layer).await;
+    let mut client = Test1Client::new(server.channel());
+
+    let status = client.unary_call(tagged("fail")).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "injected");
+
+    client.unary_call(tagged("other")).await.unwrap();
+    client.unary_call(Input1::default()).await.unwrap();
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn delays_responses() {
+    let delay = Duration::from_millis(50);
+    let server = start(FaultInjectionLayer::new().fault(Fault::delay(delay))).await;
+    let mut client = Test1Client::new(server.channel());
+
+    let start = Instant::now();
+    client.unary_call(Input1::default()).await.unwrap();
+    assert!(start.elapsed() >= delay);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn skips_calls_outside_the_percentage() {
+    let layer = FaultInjectionLayer::new()
+        .fault(Fault::status(Status::unavailable("injected")).percentage(0.0));
+    let server = start(layer).await;
+    let mut client = Test1Client::new(server.channel());
+
+    for _ in 0..10 {
+        client.unary_call(Input1::default()).await.unwrap();
+    }
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn truncates_responses_received_by_clients() {
+    let server = TestServer::start_in_memory(
+        Server::builder().add_service(test1_server::Test1Server::new(Svc)),
+    )
+    .await;
+    let layer = FaultInjectionLayer::new().fault(Fault::truncate());
+    let mut client = client(server.channel(), layer);
+
+    let status = client.unary_call(Input1::default()).await.unwrap_err();
+    assert_eq!(status.code(), Code::Internal, "{status:?}");
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn corrupts_responses_received_by_clients() {
+    let server = TestServer::start_in_memory(
+        Server::builder().add_service(test1_server::Test1Server::new(Svc)),
+    )
+    .await;
+    let layer = FaultInjectionLayer::new().fault(Fault::corrupt());
+    let mut client = client(server.channel(), layer);
+
+    // Depending on the corrupted byte, the message cannot be decoded or has a different payload.
+    match client.unary_call(Input1::default()).await {
+        Ok(response) => assert_ne!(response.into_inner().buf, PAYLOAD),
+        Err(status) => assert_eq!(status.code(), Code::Internal, "{status:?}"),
+    }
+
+    server.shutdown().await;
+}
@@ -0,0 +1,157 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tonic::{
+    transport::{
+        server::{GrpcWebConfig, TcpIncoming},
+        Endpoint, Server,
+    },
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(config: GrpcWebConfig) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .grpc_web(config)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+/// Sends a raw HTTP/1.1 request, as a browser would, and returns the raw response.
+async fn http1(addr: SocketAddr, method: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut request = format!(
+        "{method} /test.Test/UnaryCall HTTP/1.1\r\nhost: {addr}\r\nconnection: close\r\ncontent-length: {}\r\n",
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream.write_all(body).await.unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    response
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[tokio::test]
+async fn serves_grpc_web_requests() {
+    let addr = serve(GrpcWebConfig::new()).await;
+
+    let response = http1(
+        addr,
+        "POST",
+        &[
+            ("content-type", "application/grpc-web+proto"),
+            ("origin", "https://example.com"),
+        ],
+        b"\0\0\0\0\0",
+    )
+    .await;
+
+    assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    assert!(contains(
+        &response,
+        b"content-type: application/grpc-web+proto\r\n"
+    ));
+    assert!(contains(
+        &response,
+        b"access-control-allow-origin: https://example.com\r\n"
+    ));
+    // The trailers are sent as the last frame of the body.
+    assert!(contains(&response, b"\x80\0\0\0\x0fgrpc-status:0\r\n"));
+}
+
+#[tokio::test]
+async fn serves_grpc_requests_alongside() {
+    let addr = serve(GrpcWebConfig::new()).await;
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+}
+
+#[tokio::test]
+async fn answers_preflight_requests() {
+    let addr =
+        serve(GrpcWebConfig::new().allow_origin("https://example.com".parse().unwrap())).await;
+
+    let response = http1(
+        addr,
+        "OPTIONS",
+        &[
+            ("origin", "https://example.com"),
+            ("access-control-request-method", "POST"),
+            ("access-control-request-headers", "content-type,x-grpc-web"),
+        ],
+        b"",
+    )
+    .await;
+    assert!(response.starts_with(b"HTTP/1.1 204 No Content\r\n"));
+    assert!(contains(
+        &response,
+        b"access-control-allow-headers: content-type,x-grpc-web\r\n"
+    ));
+
+    let response = http1(
+        addr,
+        "OPTIONS",
+        &[
+            ("origin", "https://other.test"),
+            ("access-control-request-method", "POST"),
+        ],
+        b"",
+    )
+    .await;
+    assert!(response.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+}
+
+#[tokio::test]
+async fn rejects_disallowed_origins() {
+    let addr =
+        serve(GrpcWebConfig::new().allow_origin("https://example.com".parse().unwrap())).await;
+
+    let response = http1(
+        addr,
+        "POST",
+        &[
+            ("content-type", "application/grpc-web+proto"),
+            ("origin", "https://other.test"),
+        ],
+        b"\0\0\0\0\0",
+    )
+    .await;
+    assert!(response.starts_with(b"HTTP/1.1 403 Forbidden\r\n"));
+}
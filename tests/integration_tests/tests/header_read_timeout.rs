@@ -0,0 +1,111 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(accept_http1: bool) -> (SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let disconnected = Arc::new(AtomicUsize::new(0));
+
+    let counter = disconnected.clone();
+    tokio::spawn(async move {
+        Server::builder()
+            .accept_http1(accept_http1)
+            .header_read_timeout(Duration::from_millis(100))
+            .on_disconnect(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    (addr, disconnected)
+}
+
+async fn assert_closed(mut stream: TcpStream) {
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received))
+        .await
+        .expect("connection was not closed")
+        .ok();
+}
+
+#[tokio::test]
+async fn closes_idle_connections() {
+    let (addr, _) = serve(false).await;
+    assert_closed(TcpStream::connect(addr).await.unwrap()).await;
+}
+
+#[tokio::test]
+async fn closes_connections_without_request_headers() {
+    let (addr, _) = serve(false).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+        .await
+        .unwrap();
+    // An empty SETTINGS frame, but no HEADERS frame.
+    stream
+        .write_all(&[0, 0, 0, 0x4, 0, 0, 0, 0, 0])
+        .await
+        .unwrap();
+
+    assert_closed(stream).await;
+}
+
+#[tokio::test]
+async fn closes_http1_connections_with_slow_request_headers() {
+    let (addr, _) = serve(true).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"POST /test.Test/UnaryCall HTTP/1.1\r\n")
+        .await
+        .unwrap();
+
+    assert_closed(stream).await;
+}
+
+#[tokio::test]
+async fn keeps_connections_open_after_first_request() {
+    let (addr, disconnected) = serve(false).await;
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    client.unary_call(Input {}).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client.unary_call(Input {}).await.unwrap();
+
+    assert_eq!(disconnected.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,101 @@
+use integration_tests::pb::{test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    health::{HealthReporter, ServingStatus},
+    transport::{Channel, Server},
+    Code, Request, Response, Status,
+};
+use tonic_health::pb::{
+    health_check_response::ServingStatus as WireStatus, health_client::HealthClient,
+    HealthCheckRequest, HealthCheckResponse,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(reporter: &HealthReporter) -> HealthClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let router = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .add_health_service(reporter);
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    HealthClient::new(channel)
+}
+
+fn request(service: &str) -> HealthCheckRequest {
+    HealthCheckRequest {
+        service: service.to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn check_reports_service_status() {
+    let reporter = HealthReporter::new();
+    let mut client = serve(&reporter).await;
+
+    let res = client.check(request("")).await.unwrap().into_inner();
+    assert_eq!(res.status(), WireStatus::Serving);
+
+    let err = client.check(request("test.Test")).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    reporter.set_not_serving::<test_server::TestServer<Svc>>();
+    let res = client
+        .check(request("test.Test"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(res.status(), WireStatus::NotServing);
+}
+
+#[tokio::test]
+async fn watch_streams_status_changes() {
+    let reporter = HealthReporter::new();
+    let mut client = serve(&reporter).await;
+
+    let mut stream = client
+        .watch(request("test.Test"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(next(&mut stream).await, WireStatus::ServiceUnknown);
+
+    reporter.set_serving::<test_server::TestServer<Svc>>();
+    assert_eq!(next(&mut stream).await, WireStatus::Serving);
+
+    reporter.set_service_status("test.Test", ServingStatus::NotServing);
+    assert_eq!(next(&mut stream).await, WireStatus::NotServing);
+
+    reporter.clear_service_status("test.Test");
+    assert_eq!(next(&mut stream).await, WireStatus::ServiceUnknown);
+}
+
+async fn next(stream: &mut tonic::Streaming<HealthCheckResponse>) -> WireStatus {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .status()
+}
@@ -0,0 +1,64 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{channel::EndpointAttributes, server::TcpIncoming, Channel, Endpoint, Server},
+    Request, Response, Status,
+};
+use tonic_health::ServingStatus;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn run_server(status: ServingStatus) -> std::net::SocketAddr {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    reporter.set_service_status("test.Test", status).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        // keep the reporter alive for as long as the server runs
+        let _reporter = reporter;
+
+        Server::builder()
+            .add_service(health)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn unhealthy_endpoints_are_avoided() {
+    let serving = run_server(ServingStatus::Serving).await;
+    let not_serving = run_server(ServingStatus::NotServing).await;
+
+    let endpoints = [("serving", serving), ("not_serving", not_serving)]
+        .into_iter()
+        .map(|(name, addr)| {
+            Endpoint::from_shared(format!("http://{addr}"))
+                .unwrap()
+                .attribute("name", name)
+                .health_check("test.Test", Duration::from_millis(50))
+        });
+    let channel = Channel::balance_list(endpoints);
+
+    let mut client = test_client::TestClient::new(channel);
+
+    for _ in 0..10 {
+        let res = client.unary_call(Input {}).await.unwrap();
+        let attributes = res.extensions().get::<EndpointAttributes>().unwrap();
+        assert_eq!(attributes.get("name"), Some("serving"));
+    }
+}
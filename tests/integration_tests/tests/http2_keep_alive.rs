@@ -1,6 +1,10 @@
 use std::time::Duration;
 
-use tokio::{net::TcpListener, sync::oneshot};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+};
 
 use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
 use tonic::transport::{server::TcpIncoming, Channel, Server};
@@ -80,3 +84,214 @@ async fn http2_keepalive_does_not_cause_panics_on_client_side() {
     tx.send(()).unwrap();
     jh.await.unwrap();
 }
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+    frame.extend_from_slice(&[kind, 0, 0, 0, 0, 0]);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn settings() -> Vec<u8> {
+    frame(0x4, &[])
+}
+
+fn ping() -> Vec<u8> {
+    frame(0x6, &[0; 8])
+}
+
+async fn serve_with_ping_policy(
+    min_ping_interval: Duration,
+    permit_without_streams: bool,
+) -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .http2_min_ping_interval(Some(min_ping_interval))
+            .http2_permit_pings_without_streams(permit_without_streams)
+            .add_service(test_server::TestServer::new(Svc {}))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(PREFACE).await.unwrap();
+    stream.write_all(&settings()).await.unwrap();
+    stream
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[tokio::test]
+async fn http2_closes_connections_that_ping_too_often() {
+    let mut stream = serve_with_ping_policy(Duration::from_secs(60), true).await;
+
+    for _ in 0..4 {
+        stream.write_all(&ping()).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received))
+        .await
+        .unwrap()
+        .unwrap();
+
+    // A GOAWAY frame with the ENHANCE_YOUR_CALM error code.
+    assert!(contains(&received, &[0, 0, 0, 0xb, b't']));
+    assert!(contains(&received, b"too_many_pings"));
+}
+
+#[tokio::test]
+async fn http2_closes_idle_connections_that_ping() {
+    let mut stream = serve_with_ping_policy(Duration::from_millis(10), false).await;
+
+    for _ in 0..4 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stream.write_all(&ping()).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut received))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(contains(&received, b"too_many_pings"));
+}
+
+#[tokio::test]
+async fn http2_permits_pings_at_min_interval() {
+    let mut stream = serve_with_ping_policy(Duration::from_millis(10), true).await;
+
+    for _ in 0..4 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stream.write_all(&ping()).await.unwrap();
+    }
+
+    // The connection stays open.
+    let mut received = Vec::new();
+    tokio::time::timeout(
+        Duration::from_millis(200),
+        stream.read_to_end(&mut received),
+    )
+    .await
+    .unwrap_err();
+    assert!(!contains(&received, b"too_many_pings"));
+}
+
+#[tokio::test]
+async fn http2_ping_policy_serves_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .http2_min_ping_interval(Some(Duration::from_secs(1)))
+            .add_service(test_server::TestServer::new(Svc {}))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .http2_keep_alive_interval(Duration::from_secs(5))
+        .connect()
+        .await
+        .unwrap();
+    let mut client = TestClient::new(channel);
+
+    for _ in 0..3 {
+        client.unary_call(Request::new(Input {})).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn keep_alive_stats_record_ping_rtt() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc {}))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let endpoint = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .http2_keep_alive_interval(Duration::from_millis(50))
+        .keep_alive_while_idle(true);
+    let stats = endpoint.keep_alive_stats();
+    let mut client = TestClient::new(endpoint.connect().await.unwrap());
+    client.unary_call(Request::new(Input {})).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert!(stats.pings_acked() >= 1);
+    assert!(stats.last_ping_rtt().unwrap() < Duration::from_secs(1));
+    assert_eq!(stats.missed_pings(), 0);
+    assert_eq!(stats.goaways_received(), 0);
+}
+
+#[tokio::test]
+async fn keep_alive_stats_record_goaways() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .max_connection_age(Duration::from_millis(50))
+            .add_service(test_server::TestServer::new(Svc {}))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let endpoint = Channel::from_shared(format!("http://{addr}")).unwrap();
+    let stats = endpoint.keep_alive_stats();
+    let mut client = TestClient::new(endpoint.connect().await.unwrap());
+    client.unary_call(Request::new(Input {})).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert!(stats.goaways_received() >= 1);
+}
+
+#[tokio::test]
+async fn keep_alive_stats_record_missed_pings() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // A server that never acknowledges pings.
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(&settings()).await.unwrap();
+        let mut buf = [0; 1024];
+        while stream.read(&mut buf).await.unwrap_or(0) > 0 {}
+    });
+
+    let endpoint = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .http2_keep_alive_interval(Duration::from_millis(50))
+        .keep_alive_timeout(Duration::from_millis(50))
+        .keep_alive_while_idle(true);
+    let stats = endpoint.keep_alive_stats();
+    let _channel = endpoint.connect().await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    assert!(stats.pings_sent() >= 1);
+    assert_eq!(stats.pings_acked(), 0);
+    assert!(stats.missed_pings() >= 1);
+    assert_eq!(stats.last_ping_rtt(), None);
+}
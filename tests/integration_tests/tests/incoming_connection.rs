@@ -0,0 +1,86 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    transport::{
+        server::{ConnectionConfig, IncomingConnection, TcpIncoming},
+        Certificate, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+fn tls_config(name: &str) -> ServerTlsConfig {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    ServerTlsConfig::new().identity(Identity::from_pem(
+        read(&format!("{name}.pem")),
+        read(&format!("{name}.key")),
+    ))
+}
+
+async fn serve(config: ConnectionConfig) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener)
+        .map(move |io| io.map(|io| IncomingConnection::new(io, config.clone())));
+
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls_config("server"))
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn serves_plaintext_connections() {
+    let addr = serve(ConnectionConfig::new().plaintext()).await;
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+}
+
+#[tokio::test]
+async fn accepts_connections_with_their_tls_config() {
+    let config = ConnectionConfig::new()
+        .tls_config(tls_config("other"))
+        .unwrap();
+    let addr = serve(config).await;
+
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("other.pem")))
+        .domain_name("other.test");
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+}
@@ -0,0 +1,81 @@
+#![cfg(unix)]
+
+use std::{io, os::fd::OwnedFd};
+
+use hyper_util::rt::TokioIo;
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use tokio::net::UnixStream;
+use tonic::{
+    transport::{server::ListenFds, Endpoint, Server, Uri},
+    Request, Response, Status,
+};
+use tower::service_fn;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[tokio::test]
+async fn serves_passed_unix_socket() {
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let path = std::env::temp_dir().join("listen-fds-integration-test");
+    let _ = std::fs::remove_file(&path);
+    let uds = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+    let mut fds = ListenFds::from_fds([OwnedFd::from(tcp), OwnedFd::from(uds)]);
+    assert_eq!(fds.len(), 2);
+
+    let err = fds.take_uds(0).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let uds = fds.take_uds(1).unwrap();
+    assert_eq!(uds.path(), path);
+    assert!(fds.take_uds(1).is_err());
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(uds)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::try_from("http://[::]:50051")
+        .unwrap()
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let path = path.clone();
+            async move { Ok::<_, io::Error>(TokioIo::new(UnixStream::connect(path).await?)) }
+        }))
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+}
+
+#[tokio::test]
+async fn serves_passed_tcp_socket() {
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut fds = ListenFds::from_fds([OwnedFd::from(tcp)]);
+    let incoming = fds.take_tcp(0).unwrap();
+    assert_eq!(incoming.local_addr().unwrap(), addr);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+}
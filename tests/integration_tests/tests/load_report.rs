@@ -0,0 +1,90 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    metadata::MetadataValue,
+    transport::{
+        channel::{LoadReport, LoadReporting},
+        server::TcpIncoming,
+        Channel, Endpoint, Server,
+    },
+    Request, Response, Status,
+};
+
+struct Svc {
+    cpu_utilization: f64,
+    calls: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+
+        // xds.data.orca.v3.OrcaLoadReport { cpu_utilization, rps_fractional: 10 }
+        let mut report = vec![0x09];
+        report.extend_from_slice(&self.cpu_utilization.to_le_bytes());
+        report.push(0x31);
+        report.extend_from_slice(&10f64.to_le_bytes());
+
+        let mut response = Response::new(Output {});
+        response.metadata_mut().insert_bin(
+            "endpoint-load-metrics-bin",
+            MetadataValue::from_bytes(&report),
+        );
+        Ok(response)
+    }
+}
+
+async fn run_server(cpu_utilization: f64) -> (std::net::SocketAddr, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let svc = Svc {
+        cpu_utilization,
+        calls: calls.clone(),
+    };
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    (addr, calls)
+}
+
+#[tokio::test]
+async fn prefers_less_utilized_endpoint() {
+    let (busy, busy_calls) = run_server(0.9).await;
+    let (idle, idle_calls) = run_server(0.1).await;
+
+    let endpoints = [busy, idle].into_iter().map(|addr| {
+        Endpoint::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .load_reporting(LoadReporting::new())
+    });
+    let channel = Channel::balance_list(endpoints);
+
+    let mut client = test_client::TestClient::new(channel);
+
+    for _ in 0..20 {
+        let response = client.unary_call(Input {}).await.unwrap();
+        let report = LoadReport::from_metadata(response.metadata()).unwrap();
+        assert_eq!(report.rps(), 10.0);
+    }
+
+    // Until both endpoints reported their load, the busy one may still be picked.
+    let busy_calls = busy_calls.load(Ordering::SeqCst);
+    assert!(
+        busy_calls <= 2,
+        "{busy_calls} requests went to the busy endpoint"
+    );
+    assert_eq!(idle_calls.load(Ordering::SeqCst), 20 - busy_calls);
+}
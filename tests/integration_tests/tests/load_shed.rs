@@ -1,7 +1,7 @@
 use integration_tests::pb::{test_client, test_server, Input, Output};
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use tonic::{transport::Server, Code, Request, Response, Status};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, sync::Notify};
+use tonic::{service::AdaptiveLoadShedLayer, transport::Server, Code, Request, Response, Status};
 
 #[tokio::test]
 async fn service_resource_exhausted() {
@@ -32,6 +32,63 @@ async fn service_resource_not_exhausted() {
     assert!(res.is_ok());
 }
 
+#[tokio::test]
+async fn adaptive_load_shed_across_connections() {
+    struct Svc(std::sync::Arc<Notify>);
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, _req: Request<Input>) -> Result<Response<Output>, Status> {
+            self.0.notified().await;
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    let release = std::sync::Arc::new(Notify::new());
+    let svc = test_server::TestServer::new(Svc(release.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let layer = AdaptiveLoadShedLayer::new()
+        .max_in_flight(1)
+        .pushback(Duration::from_millis(250));
+    let in_flight = layer.clone();
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(layer)
+            .add_service(svc)
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    let mut first = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+    let pending = tokio::spawn(async move { first.unary_call(Input {}).await });
+    while in_flight.in_flight() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // Requests on other connections are shed while the first one is in flight.
+    let mut second = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+    let err = second.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(err.code(), Code::ResourceExhausted);
+    assert_eq!(err.metadata().get("grpc-retry-pushback-ms").unwrap(), "250");
+
+    release.notify_one();
+    pending.await.unwrap().unwrap();
+    while in_flight.in_flight() != 0 {
+        tokio::task::yield_now().await;
+    }
+
+    release.notify_one();
+    second.unary_call(Input {}).await.unwrap();
+}
+
 async fn run_service_in_background(concurrency_limit: usize) -> SocketAddr {
     struct Svc;
 
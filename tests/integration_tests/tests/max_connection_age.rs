@@ -0,0 +1,51 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// Makes a call that outlives the max connection age of the server.
+async fn call(server: Server) -> Result<Response<Output>, Status> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let mut server = server.max_connection_age(Duration::from_millis(50));
+    tokio::spawn(async move {
+        server
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await
+}
+
+#[tokio::test]
+async fn aged_connection_finishes_requests() {
+    call(Server::builder()).await.unwrap();
+}
+
+#[tokio::test]
+async fn aged_connection_is_closed_after_grace_period() {
+    let server = Server::builder().max_connection_age_grace(Duration::from_millis(50));
+    call(server).await.unwrap_err();
+}
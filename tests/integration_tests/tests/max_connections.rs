@@ -0,0 +1,48 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[tokio::test]
+async fn rejects_connections_over_the_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .max_connections(1)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let endpoint = Endpoint::from_shared(format!("http://{addr}")).unwrap();
+
+    let mut first = TestClient::new(endpoint.connect().await.unwrap());
+    first.unary_call(Input {}).await.unwrap();
+
+    let mut second = TestClient::new(endpoint.connect_lazy());
+    let status = second.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+
+    // The connection is available again once the first one is closed.
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut third = TestClient::new(endpoint.connect_lazy());
+    third.unary_call(Input {}).await.unwrap();
+}
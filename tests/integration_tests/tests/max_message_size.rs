@@ -114,6 +114,47 @@ fn max_message_send_size() {
     });
 }
 
+#[test]
+fn server_default_message_size_limits() {
+    trace_init();
+
+    // The default limits of the server apply to the services without their own limits.
+    assert_test_case(TestCase {
+        client_blob_size: 5 * 1024 * 1024,
+        server_default_recv_max: Some(8 * 1024 * 1024),
+        ..Default::default()
+    });
+
+    assert_test_case(TestCase {
+        client_blob_size: 1024,
+        server_default_recv_max: Some(512),
+        expected_code: Some(Code::OutOfRange),
+        ..Default::default()
+    });
+
+    assert_test_case(TestCase {
+        server_blob_size: 1024,
+        server_default_send_max: Some(512),
+        expected_code: Some(Code::OutOfRange),
+        ..Default::default()
+    });
+
+    // The limits of a service take precedence over the defaults of the server.
+    assert_test_case(TestCase {
+        client_blob_size: 1024,
+        server_recv_max: Some(2048),
+        server_default_recv_max: Some(512),
+        ..Default::default()
+    });
+
+    assert_test_case(TestCase {
+        server_blob_size: 1024,
+        server_send_max: Some(2048),
+        server_default_send_max: Some(512),
+        ..Default::default()
+    });
+}
+
 #[tokio::test]
 async fn response_stream_limit() {
     let client_blob = vec![0; 1];
@@ -263,6 +304,8 @@ struct TestCase {
     server_recv_max: Option<usize>,
     client_send_max: Option<usize>,
     server_send_max: Option<usize>,
+    server_default_recv_max: Option<usize>,
+    server_default_send_max: Option<usize>,
 
     expected_code: Option<Code>,
 }
@@ -309,8 +352,16 @@ async fn max_message_run(case: &TestCase) -> Result<(), Status> {
         svc
     };
 
+    let mut builder = Server::builder();
+    if let Some(size) = case.server_default_recv_max {
+        builder = builder.max_decoding_message_size(size);
+    }
+    if let Some(size) = case.server_default_send_max {
+        builder = builder.max_encoding_message_size(size);
+    }
+
     tokio::spawn(async move {
-        Server::builder()
+        builder
             .add_service(svc)
             .serve_with_incoming(tokio_stream::once(Ok::<_, std::io::Error>(server)))
             .await
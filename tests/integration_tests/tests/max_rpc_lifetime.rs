@@ -0,0 +1,86 @@
+use integration_tests::pb::{test_client, test_server, test_stream_client, test_stream_server};
+use integration_tests::pb::{Input, InputStream, Output, OutputStream};
+use std::{pin::Pin, time::Duration};
+use tokio::net::TcpListener;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tonic_types::StatusExt;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        std::future::pending().await
+    }
+}
+
+#[tonic::async_trait]
+impl test_stream_server::TestStream for Svc {
+    type StreamCallStream =
+        Pin<Box<dyn Stream<Item = Result<OutputStream, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<InputStream>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let stream = tokio_stream::once(Ok(OutputStream {})).chain(tokio_stream::pending());
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+async fn run_server() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .max_rpc_lifetime(Duration::from_millis(100))
+            .add_service(test_server::TestServer::new(Svc))
+            .add_service(test_stream_server::TestStreamServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn assert_lifetime_exceeded(status: &Status) {
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "maximum RPC lifetime exceeded");
+
+    let retry_info = status.get_details_retry_info().unwrap();
+    assert_eq!(retry_info.retry_delay, Some(Duration::ZERO));
+}
+
+#[tokio::test]
+async fn ends_long_running_streams() {
+    let mut client = test_stream_client::TestStreamClient::new(run_server().await);
+
+    let mut stream = client
+        .stream_call(InputStream {})
+        .await
+        .unwrap()
+        .into_inner();
+
+    stream.message().await.unwrap().unwrap();
+    let status = stream.message().await.unwrap_err();
+    assert_lifetime_exceeded(&status);
+}
+
+#[tokio::test]
+async fn ends_calls_without_response() {
+    let mut client = test_client::TestClient::new(run_server().await);
+
+    let status = client.unary_call(Input {}).await.unwrap_err();
+    assert_lifetime_exceeded(&status);
+}
@@ -0,0 +1,197 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::pin::Pin;
+use tokio::net::TcpListener;
+use tokio_stream::Stream;
+use tonic::{
+    service::encryption::{Aead, EncryptionKey, KeyProvider, MessageEncryptionLayer},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        let encrypted = req.metadata().get("grpc-message-encryption-key").is_some();
+        let mut buf = req.into_inner().buf;
+        buf.push(encrypted as u8);
+
+        Ok(Response::new(Output1 { buf }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        unimplemented!()
+    }
+}
+
+/// Not a cipher, but it garbles messages that are not decrypted with the same key.
+struct Xor;
+
+impl Aead for Xor {
+    fn algorithm(&self) -> &str {
+        "xor"
+    }
+
+    fn seal(
+        &self,
+        key: &EncryptionKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, tonic::codegen::StdError> {
+        Ok(message.iter().map(|b| b ^ key.secret()[0]).collect())
+    }
+
+    fn open(
+        &self,
+        key: &EncryptionKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, tonic::codegen::StdError> {
+        self.seal(key, message)
+    }
+}
+
+/// Encrypts with `current` and knows the keys `k0`, `k1` and `k2`.
+struct Keys {
+    current: &'static str,
+}
+
+impl KeyProvider for Keys {
+    fn current_key(&self) -> Result<EncryptionKey, Status> {
+        self.key(self.current)
+    }
+
+    fn key(&self, id: &str) -> Result<EncryptionKey, Status> {
+        match id {
+            // Leaves messages untouched, so that servers without the layer can read them.
+            "k0" => Ok(EncryptionKey::new(id, vec![0])),
+            "k1" => Ok(EncryptionKey::new(id, vec![0x5a])),
+            "k2" => Ok(EncryptionKey::new(id, vec![0xa5])),
+            _ => Err(Status::unauthenticated(format!("unknown key {id}"))),
+        }
+    }
+}
+
+/// Encrypts with a key the server does not know.
+struct RogueKeys;
+
+impl KeyProvider for RogueKeys {
+    fn current_key(&self) -> Result<EncryptionKey, Status> {
+        Ok(EncryptionKey::new("k3", vec![0x33]))
+    }
+
+    fn key(&self, id: &str) -> Result<EncryptionKey, Status> {
+        Err(Status::unauthenticated(format!("unknown key {id}")))
+    }
+}
+
+async fn channel(layer: Option<MessageEncryptionLayer>) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        let svc = test1_server::Test1Server::new(Svc);
+        match layer {
+            Some(layer) => Server::builder()
+                .layer(layer)
+                .add_service(svc)
+                .serve_with_incoming(incoming)
+                .await
+                .unwrap(),
+            None => Server::builder()
+                .add_service(svc)
+                .serve_with_incoming(incoming)
+                .await
+                .unwrap(),
+        }
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn encrypts_messages_both_ways() {
+    let channel = channel(Some(MessageEncryptionLayer::server(
+        Xor,
+        Keys { current: "k2" },
+    )))
+    .await;
+
+    let svc = ServiceBuilder::new()
+        .layer(MessageEncryptionLayer::client(Xor, Keys { current: "k1" }))
+        .service(channel);
+    let mut client = test1_client::Test1Client::new(svc);
+
+    let res = client
+        .unary_call(Input1 {
+            buf: b"secret".to_vec(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        res.metadata().get("grpc-message-encryption-key").unwrap(),
+        "k2"
+    );
+    assert_eq!(res.into_inner().buf, b"secret\x01");
+}
+
+#[tokio::test]
+async fn server_requires_encryption() {
+    let channel = channel(Some(
+        MessageEncryptionLayer::server(Xor, Keys { current: "k1" }).required(true),
+    ))
+    .await;
+
+    let mut client = test1_client::Test1Client::new(channel);
+    let status = client
+        .unary_call(Input1 { buf: vec![1] })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+}
+
+#[tokio::test]
+async fn server_rejects_unknown_key() {
+    let channel = channel(Some(MessageEncryptionLayer::server(
+        Xor,
+        Keys { current: "k1" },
+    )))
+    .await;
+
+    let svc = ServiceBuilder::new()
+        .layer(MessageEncryptionLayer::client(Xor, RogueKeys))
+        .service(channel);
+    let mut client = test1_client::Test1Client::new(svc);
+
+    let status = client
+        .unary_call(Input1 { buf: vec![1] })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn client_requires_encryption() {
+    let channel = channel(None).await;
+
+    let svc = ServiceBuilder::new()
+        .layer(MessageEncryptionLayer::client(Xor, Keys { current: "k0" }).required(true))
+        .service(channel);
+    let mut client = test1_client::Test1Client::new(svc);
+
+    let status = client
+        .unary_call(Input1 { buf: vec![1] })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+}
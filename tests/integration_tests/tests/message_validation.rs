@@ -0,0 +1,138 @@
+use integration_tests::pb::{test1_client::Test1Client, test1_server, Input1, Output1};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::net::TcpListener;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    codec::{FieldViolation, MessageValidators},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(Response::new(Output1 {
+            buf: req.into_inner().buf,
+        }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send>>;
+
+    async fn stream_call(
+        &self,
+        req: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let buf = req.into_inner().buf;
+        let stream = tokio_stream::iter(1..=buf.len()).map(move |len| {
+            Ok(Output1 {
+                buf: buf[..len].to_vec(),
+            })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+fn validators() -> MessageValidators {
+    MessageValidators::new()
+        .message(|input: &Input1| {
+            if input.buf.is_empty() {
+                return Err(vec![FieldViolation::new("buf", "must not be empty")]);
+            }
+            Ok(())
+        })
+        .message(|output: &Output1| {
+            if output.buf.len() > 3 {
+                return Err(vec![FieldViolation::new("buf", "must be at most 3 bytes")]);
+            }
+            Ok(())
+        })
+}
+
+async fn run_server(validators: Option<MessageValidators>) -> (Channel, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = test1_server::Test1Server::new(Svc(calls.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut builder = Server::builder();
+        if let Some(validators) = validators {
+            builder = builder.message_validators(validators);
+        }
+        builder
+            .add_service(svc)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    (channel, calls)
+}
+
+fn input(buf: &[u8]) -> Input1 {
+    Input1 { buf: buf.to_vec() }
+}
+
+#[tokio::test]
+async fn server_validates_requests_and_responses() {
+    let (channel, calls) = run_server(Some(validators())).await;
+    let mut client = Test1Client::new(channel);
+
+    let res = client.unary_call(input(b"abc")).await.unwrap();
+    assert_eq!(res.into_inner().buf, b"abc");
+
+    let status = client.unary_call(input(b"")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "invalid request message: buf: must not be empty"
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let status = client.unary_call(input(b"abcd")).await.unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(
+        status.message(),
+        "invalid response message: buf: must be at most 3 bytes"
+    );
+
+    let mut stream = client
+        .stream_call(input(b"abcd"))
+        .await
+        .unwrap()
+        .into_inner();
+    for len in 1..=3 {
+        assert_eq!(stream.message().await.unwrap().unwrap().buf.len(), len);
+    }
+    let status = stream.message().await.unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+}
+
+#[tokio::test]
+async fn client_validates_requests_and_responses() {
+    let (channel, calls) = run_server(None).await;
+    let mut client = Test1Client::new(channel).message_validators(validators());
+
+    let status = client.unary_call(input(b"")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    let violations = status.extensions().get::<Vec<FieldViolation>>().unwrap();
+    assert_eq!(violations[0].field(), "buf");
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let status = client.unary_call(input(b"abcd")).await.unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,133 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tonic::{
+    metadata::ReservedHeaderPolicy,
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut res = Response::new(Output {});
+        for value in req.metadata().get_all("x-echo") {
+            res.metadata_mut().append("x-echo", value.clone());
+        }
+        if req.metadata().contains_key("x-reserved") {
+            res.metadata_mut()
+                .insert("grpc-foo", "bar".parse().unwrap());
+        }
+        Ok(res)
+    }
+}
+
+async fn serve(server: Server) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut server = server;
+    let router = server.add_service(test_server::TestServer::new(Svc));
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+fn channel(addr: SocketAddr) -> Channel {
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy()
+}
+
+fn request(echoes: usize) -> Request<Input> {
+    let mut req = Request::new(Input {});
+    for _ in 0..echoes {
+        req.metadata_mut()
+            .append("x-echo", "0123456789".parse().unwrap());
+    }
+    req
+}
+
+#[tokio::test]
+async fn client_fails_requests_with_too_much_metadata() {
+    let addr = serve(Server::builder()).await;
+
+    let mut client = TestClient::new(channel(addr))
+        .max_metadata_entries(3)
+        .max_metadata_size(128);
+
+    client.unary_call(request(2)).await.unwrap();
+
+    let status = client.unary_call(request(4)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(
+        status.message(),
+        "request metadata has 4 entries, exceeding the limit of 3"
+    );
+
+    let mut req = request(1);
+    req.metadata_mut()
+        .insert("x-large", "x".repeat(100).parse().unwrap());
+    let status = client.unary_call(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(
+        status.message(),
+        "request metadata of 187 bytes exceeds the limit of 128 bytes"
+    );
+}
+
+#[tokio::test]
+async fn server_replaces_responses_with_too_much_metadata() {
+    let addr = serve(Server::builder().max_metadata_entries(3)).await;
+    let mut client = TestClient::new(channel(addr));
+
+    let res = client.unary_call(request(3)).await.unwrap();
+    assert_eq!(res.metadata().get_all("x-echo").iter().count(), 3);
+
+    let status = client.unary_call(request(4)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(
+        status.message(),
+        "response metadata has 4 entries, exceeding the limit of 3"
+    );
+}
+
+#[tokio::test]
+async fn reserved_headers_are_stripped_or_rejected() {
+    let addr = serve(Server::builder().reserved_header_policy(ReservedHeaderPolicy::Strip)).await;
+
+    let mut req = request(1);
+    req.metadata_mut()
+        .insert("grpc-foo", "bar".parse().unwrap());
+    let status = TestClient::new(channel(addr))
+        .reserved_header_policy(ReservedHeaderPolicy::Reject)
+        .unary_call(req)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(
+        status.message(),
+        "request metadata sets reserved header `grpc-foo`"
+    );
+
+    let mut req = request(0);
+    req.metadata_mut()
+        .insert("x-echo", "0123456789".parse().unwrap());
+    req.set_timeout(std::time::Duration::from_secs(10));
+    req.metadata_mut()
+        .insert("x-reserved", "1".parse().unwrap());
+    let res = TestClient::new(channel(addr))
+        .reserved_header_policy(ReservedHeaderPolicy::Strip)
+        .unary_call(req)
+        .await
+        .unwrap();
+    assert!(res.metadata().get("x-echo").is_some());
+    assert!(res.metadata().get("grpc-foo").is_none());
+}
@@ -0,0 +1,116 @@
+use integration_tests::pb::{
+    test1_client::Test1Client, test1_server, test_client::TestClient, test_server, Input, Input1,
+    Output, Output1,
+};
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    codegen::BoxStream,
+    service::{interceptor::InterceptorLayer, LayerExt},
+    transport::{server::TcpIncoming, Channel, Endpoint, Server},
+    Code, Request, Response, Status,
+};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+struct Svc1;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc1 {
+    async fn unary_call(&self, request: Request<Input1>) -> Result<Response<Output1>, Status> {
+        Ok(Response::new(Output1 {
+            buf: request.into_inner().buf,
+        }))
+    }
+
+    type StreamCallStream = BoxStream<Output1>;
+
+    async fn stream_call(
+        &self,
+        request: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let output = Output1 {
+            buf: request.into_inner().buf,
+        };
+        Ok(Response::new(Box::pin(tokio_stream::once(Ok(output)))))
+    }
+}
+
+async fn connect() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let svc1 = test1_server::Test1Server::new(Svc1);
+    let traced = SetResponseHeaderLayer::overriding(
+        http::header::HeaderName::from_static("x-traced"),
+        http::HeaderValue::from_static("1"),
+    );
+    let auth = InterceptorLayer::new(|req: Request<()>| match req.metadata().get("token") {
+        Some(_) => Ok(req),
+        None => Err(Status::unauthenticated("missing token")),
+    });
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(auth.named_layer(test_server::TestServer::new(Svc)))
+            .add_service(svc1.clone())
+            .add_method_service("UnaryCall", traced.named_layer(svc1))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn applies_layers_to_single_methods() {
+    let mut client = Test1Client::new(connect().await);
+
+    let response = client.unary_call(Input1 { buf: vec![1] }).await.unwrap();
+    assert_eq!(response.metadata().get("x-traced").unwrap(), "1");
+    assert_eq!(response.into_inner().buf, [1]);
+
+    let response = client.stream_call(Input1 { buf: vec![2] }).await.unwrap();
+    assert!(response.metadata().get("x-traced").is_none());
+    let messages = response.into_inner().collect::<Vec<_>>().await;
+    assert_eq!(messages.len(), 1);
+}
+
+#[tokio::test]
+async fn applies_layers_to_single_services() {
+    let channel = connect().await;
+
+    let status = TestClient::new(channel.clone())
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut request = Request::new(Input {});
+    request
+        .metadata_mut()
+        .insert("token", "secret".parse().unwrap());
+    TestClient::new(channel.clone())
+        .unary_call(request)
+        .await
+        .unwrap();
+
+    // Other services are not wrapped in the layer of `Test`.
+    Test1Client::new(channel)
+        .unary_call(Input1 { buf: vec![] })
+        .await
+        .unwrap();
+}
@@ -0,0 +1,111 @@
+use integration_tests::pb::{
+    test1_client::Test1Client, test_client::TestClient, Input, Input1, Output, Output1,
+};
+use tokio_stream::StreamExt;
+use tonic::{testing::MockChannel, Code, Status};
+use tonic_prost::ProstCodec;
+
+fn message(buf: &[u8]) -> Output1 {
+    Output1 { buf: buf.to_vec() }
+}
+
+#[tokio::test]
+async fn answers_scripted_calls_in_order() {
+    let mock = MockChannel::new();
+    mock.expect("/test.Test1/UnaryCall", ProstCodec::default())
+        .request(Input1 {
+            buf: b"ping".to_vec(),
+        })
+        .respond(message(b"pong"));
+    mock.expect("/test.Test1/StreamCall", ProstCodec::default())
+        .request(Input1 {
+            buf: b"list".to_vec(),
+        })
+        .respond_stream([
+            Ok(message(b"a")),
+            Ok(message(b"b")),
+            Err(Status::aborted("stop")),
+        ]);
+    mock.expect(
+        "/test.Test/UnaryCall",
+        ProstCodec::<Output, Input>::default(),
+    )
+    .respond_status(Status::unavailable("down"));
+
+    let mut client = Test1Client::new(mock.clone());
+    let res = client
+        .unary_call(Input1 {
+            buf: b"ping".to_vec(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(res.into_inner(), message(b"pong"));
+
+    let mut stream = client
+        .stream_call(Input1 {
+            buf: b"list".to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(stream.next().await.unwrap().unwrap(), message(b"a"));
+    assert_eq!(stream.next().await.unwrap().unwrap(), message(b"b"));
+    assert_eq!(
+        stream.next().await.unwrap().unwrap_err().code(),
+        Code::Aborted
+    );
+
+    let status = TestClient::new(mock.clone())
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.message(), "down");
+
+    mock.verify();
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected request messages for /test.Test1/UnaryCall")]
+async fn panics_on_unexpected_request() {
+    let mock = MockChannel::new();
+    mock.expect("/test.Test1/UnaryCall", ProstCodec::default())
+        .request(Input1 {
+            buf: b"ping".to_vec(),
+        })
+        .respond(message(b"pong"));
+
+    let _ = Test1Client::new(mock)
+        .unary_call(Input1 {
+            buf: b"pang".to_vec(),
+        })
+        .await;
+}
+
+#[tokio::test]
+#[should_panic(
+    expected = "unexpected call to /test.Test/UnaryCall: expected a call to /test.Test1/UnaryCall"
+)]
+async fn panics_on_unexpected_method() {
+    let mock = MockChannel::new();
+    mock.expect(
+        "/test.Test1/UnaryCall",
+        ProstCodec::<Output1, Input1>::default(),
+    )
+    .respond(message(b"pong"));
+
+    let _ = TestClient::new(mock).unary_call(Input {}).await;
+}
+
+#[test]
+#[should_panic(expected = "expected calls were not made: [\"/test.Test/UnaryCall\"]")]
+fn verify_panics_on_missing_calls() {
+    let mock = MockChannel::new();
+    mock.expect(
+        "/test.Test/UnaryCall",
+        ProstCodec::<Output, Input>::default(),
+    )
+    .respond(Output {});
+
+    mock.verify();
+}
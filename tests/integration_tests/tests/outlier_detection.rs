@@ -0,0 +1,65 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{channel::OutlierDetection, server::TcpIncoming, Channel, Endpoint, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc(bool);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        if self.0 {
+            Ok(Response::new(Output {}))
+        } else {
+            Err(Status::unavailable("failing"))
+        }
+    }
+}
+
+async fn run_server(healthy: bool) -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc(healthy)))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn failing_endpoint_is_ejected() {
+    let healthy = run_server(true).await;
+    let failing = run_server(false).await;
+
+    let endpoints = [healthy, failing].into_iter().map(|addr| {
+        Endpoint::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .outlier_detection(
+                OutlierDetection::new()
+                    .consecutive_failures(1)
+                    .base_ejection_time(Duration::from_secs(60)),
+            )
+    });
+    let channel = Channel::balance_list(endpoints);
+
+    let mut client = test_client::TestClient::new(channel);
+
+    let mut failures = 0;
+    for _ in 0..20 {
+        if let Err(status) = client.unary_call(Input {}).await {
+            assert_eq!(status.code(), Code::Unavailable);
+            failures += 1;
+        }
+    }
+
+    assert!(failures <= 1, "{failures} requests failed");
+}
@@ -0,0 +1,97 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::{PeerIdentity, PeerIdentityPolicy, TcpIncoming, VerifyPeerIdentityLayer},
+        Certificate, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig,
+    },
+    Code, Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let identity = req.extensions().get::<PeerIdentity>().unwrap();
+        assert_eq!(
+            identity.spiffe_id(),
+            Some("spiffe://example.org/ns/default/sa/client")
+        );
+        assert_eq!(identity.dns_names(), ["client.example.org"]);
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+async fn serve(policy: PeerIdentityPolicy) -> SocketAddr {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let tls = ServerTlsConfig::new()
+        .identity(Identity::from_pem(read("server.pem"), read("server.key")))
+        .client_ca_root(Certificate::from_pem(read("client3.pem")))
+        .client_auth_optional(true);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .layer(VerifyPeerIdentityLayer::new(policy))
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+async fn call(addr: SocketAddr, client: Option<&str>) -> Result<(), Status> {
+    let mut tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name("example.com");
+    if let Some(client) = client {
+        tls = tls.identity(Identity::from_pem(
+            read(&format!("{client}.pem")),
+            read(&format!("{client}.key")),
+        ));
+    }
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect_lazy();
+
+    TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .map(drop)
+}
+
+#[tokio::test]
+async fn adds_allowed_identity_to_extensions() {
+    let addr = serve(PeerIdentityPolicy::new().allow_trust_domain("example.org")).await;
+
+    call(addr, Some("client3")).await.unwrap();
+
+    let status = call(addr, None).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn rejects_identity_not_allowed_by_policy() {
+    let addr = serve(PeerIdentityPolicy::new().allow_dns_name("other.example.org")).await;
+
+    let status = call(addr, Some("client3")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
@@ -0,0 +1,79 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{channel::PinEndpoint, server::TcpIncoming, Channel, Endpoint, Server, Uri},
+    Code, Request, Response, Status,
+};
+
+struct Svc(u16);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("server", self.0.to_string().parse().unwrap());
+        Ok(response)
+    }
+}
+
+async fn run_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc(addr.port())))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+fn server(response: &Response<Output>) -> u16 {
+    let server = response.metadata().get("server").unwrap().to_str().unwrap();
+    server.parse().unwrap()
+}
+
+#[tokio::test]
+async fn pinned_requests_reach_the_same_endpoint() {
+    let addrs = [run_server().await, run_server().await, run_server().await];
+    let channel = Channel::balance_list(
+        addrs
+            .iter()
+            .map(|addr| Endpoint::from_shared(format!("http://{addr}")).unwrap()),
+    );
+    let mut client = test_client::TestClient::new(channel);
+
+    // Give the balancer time to connect to all endpoints.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let first = client.unary_call(Input {}).await.unwrap();
+    let pin = first.extensions().get::<PinEndpoint>().unwrap().clone();
+    assert_eq!(
+        pin.uri().port_u16(),
+        Some(server(&first)),
+        "{:?}",
+        pin.uri()
+    );
+
+    let mut servers = HashSet::new();
+    for _ in 0..20 {
+        let mut request = Request::new(Input {});
+        request.extensions_mut().insert(pin.clone());
+        servers.insert(server(&client.unary_call(request).await.unwrap()));
+    }
+    assert_eq!(servers, HashSet::from([server(&first)]));
+
+    let mut request = Request::new(Input {});
+    request
+        .extensions_mut()
+        .insert(PinEndpoint::new(Uri::from_static("http://127.0.0.1:1")));
+    let status = client.unary_call(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(status.message().contains("127.0.0.1:1"), "{status:?}");
+}
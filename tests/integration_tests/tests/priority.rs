@@ -0,0 +1,118 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{channel::Priority, server::TcpIncoming, Channel, Endpoint, Server},
+    Request, Response, Status,
+};
+use tonic_health::ServingStatus;
+
+struct Svc(u16);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("server", self.0.to_string().parse().unwrap());
+        Ok(response)
+    }
+}
+
+async fn run_server(status: ServingStatus) -> SocketAddr {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    reporter.set_service_status("test.Test", status).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        // keep the reporter alive for as long as the server runs
+        let _reporter = reporter;
+
+        Server::builder()
+            .add_service(health)
+            .add_service(test_server::TestServer::new(Svc(addr.port())))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+fn endpoint(addr: SocketAddr, tier: u32) -> Endpoint {
+    Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .health_check("test.Test", Duration::from_millis(50))
+        .priority(tier)
+}
+
+/// Sends a few requests and returns the ports of the servers that served them.
+async fn servers(channel: Channel) -> HashSet<u16> {
+    let mut client = test_client::TestClient::new(channel);
+    let mut servers = HashSet::new();
+
+    for _ in 0..20 {
+        let response = client.unary_call(Input {}).await.unwrap();
+        let server = response.metadata().get("server").unwrap().to_str().unwrap();
+        servers.insert(server.parse().unwrap());
+    }
+
+    servers
+}
+
+#[tokio::test]
+async fn prefers_healthy_primary_tier() {
+    let primary = run_server(ServingStatus::Serving).await;
+    let fallback = run_server(ServingStatus::Serving).await;
+
+    let channel = Channel::priority_list(
+        [endpoint(fallback, 1), endpoint(primary, 0)].into_iter(),
+        Priority::new(),
+    );
+
+    // Give all endpoints time to pass their first health check.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(servers(channel).await, HashSet::from([primary.port()]));
+}
+
+#[tokio::test]
+async fn fails_over_to_next_tier() {
+    let primary = run_server(ServingStatus::NotServing).await;
+    let fallback = run_server(ServingStatus::Serving).await;
+
+    let channel = Channel::priority_list(
+        [endpoint(primary, 0), endpoint(fallback, 1)].into_iter(),
+        Priority::new(),
+    );
+
+    assert_eq!(servers(channel).await, HashSet::from([fallback.port()]));
+}
+
+#[tokio::test]
+async fn spills_over_below_threshold() {
+    let healthy = run_server(ServingStatus::Serving).await;
+    let unhealthy = run_server(ServingStatus::NotServing).await;
+    let fallback = run_server(ServingStatus::Serving).await;
+
+    let endpoints = [
+        endpoint(healthy, 0),
+        endpoint(unhealthy, 0),
+        endpoint(fallback, 1),
+    ];
+
+    let channel = Channel::priority_list(endpoints.clone().into_iter(), Priority::new());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(servers(channel).await, HashSet::from([healthy.port()]));
+
+    let channel = Channel::priority_list(
+        endpoints.into_iter(),
+        Priority::new().spillover_threshold(100),
+    );
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(servers(channel).await, HashSet::from([fallback.port()]));
+}
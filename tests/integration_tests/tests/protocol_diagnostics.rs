@@ -0,0 +1,148 @@
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue};
+use http_body::Frame;
+use integration_tests::{
+    pb::{test_stream_server, InputStream, OutputStream},
+    BoxFuture,
+};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    body::Body,
+    client::Grpc,
+    codec::ProtocolViolationKind,
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+use tonic_prost::ProstCodec;
+
+type Stream<T> = Pin<Box<dyn tokio_stream::Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+struct Frames(VecDeque<Frame<Bytes>>);
+
+impl http_body::Body for Frames {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Poll::Ready(self.0.pop_front().map(Ok))
+    }
+}
+
+#[tokio::test]
+async fn records_frames_after_trailers_only_response() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_stream_server::TestStream for Svc {
+        type StreamCallStream = Stream<OutputStream>;
+
+        async fn stream_call(
+            &self,
+            _: Request<InputStream>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            unreachable!()
+        }
+    }
+
+    #[derive(Clone)]
+    struct InconsistentLayer;
+
+    impl<S> tower::Layer<S> for InconsistentLayer {
+        type Service = InconsistentService;
+
+        fn layer(&self, _: S) -> Self::Service {
+            InconsistentService
+        }
+    }
+
+    #[derive(Clone)]
+    struct InconsistentService;
+
+    impl tower::Service<http::Request<Body>> for InconsistentService {
+        type Response = http::Response<Body>;
+        type Error = Infallible;
+        type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: http::Request<Body>) -> Self::Future {
+            Box::pin(async {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static("13"));
+
+                let frames = Frames(VecDeque::from([
+                    Frame::data(Bytes::from_static(&[0, 0, 0, 0, 0])),
+                    Frame::trailers(trailers),
+                ]));
+
+                Ok(http::Response::builder()
+                    .header("content-type", "application/grpc")
+                    .header("grpc-status", "0")
+                    .body(Body::new(frames))
+                    .unwrap())
+            })
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(InconsistentLayer)
+            .add_service(test_stream_server::TestStreamServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let mut client = Grpc::new(channel).protocol_diagnostics(true);
+    client.ready().await.unwrap();
+
+    let mut stream = client
+        .server_streaming(
+            Request::new(InputStream {}),
+            "/stream.TestStream/StreamCall".parse().unwrap(),
+            ProstCodec::<InputStream, OutputStream>::default(),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+
+    while stream.message().await.unwrap().is_some() {}
+
+    let kinds = stream
+        .protocol_violations()
+        .iter()
+        .map(|violation| violation.kind())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            ProtocolViolationKind::DataAfterTrailersOnly,
+            ProtocolViolationKind::StatusInHeadersAndTrailers,
+        ]
+    );
+}
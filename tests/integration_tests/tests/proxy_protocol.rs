@@ -0,0 +1,122 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use hyper_util::rt::TokioIo;
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tonic::{
+    transport::{
+        server::{ProxyProtocolIncoming, TcpIncoming},
+        Channel, Endpoint, Server, Uri,
+    },
+    Request, Response, Status,
+};
+use tower::service_fn;
+
+struct Svc(mpsc::UnboundedSender<Option<SocketAddr>>);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        self.0.send(req.remote_addr()).unwrap();
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve() -> (SocketAddr, mpsc::UnboundedReceiver<Option<SocketAddr>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = ProxyProtocolIncoming::new(TcpIncoming::from(listener))
+        .with_header_timeout(Duration::from_millis(200));
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc(tx)))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    (addr, rx)
+}
+
+async fn connect(
+    addr: SocketAddr,
+    header: &'static [u8],
+) -> Result<Channel, tonic::transport::Error> {
+    Endpoint::try_from("http://[::]:50051")
+        .unwrap()
+        .connect_with_connector(service_fn(move |_: Uri| async move {
+            let mut io = TcpStream::connect(addr).await?;
+            io.write_all(header).await?;
+            Ok::<_, io::Error>(TokioIo::new(io))
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn surfaces_the_client_address_of_v1_headers() {
+    let (addr, mut rx) = serve().await;
+
+    let channel = connect(addr, b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n")
+        .await
+        .unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap();
+
+    assert_eq!(
+        rx.recv().await.unwrap(),
+        Some("192.0.2.1:56324".parse().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn surfaces_the_client_address_of_v2_headers() {
+    const HEADER: &[u8] = &[
+        b'\r', b'\n', b'\r', b'\n', 0, b'\r', b'\n', b'Q', b'U', b'I', b'T', b'\n', 0x21, 0x21, 0,
+        36, // 2001:db8::1
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // 2001:db8::2
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, // 56324, 443
+        0xdc, 0x04, 0x01, 0xbb,
+    ];
+    let (addr, mut rx) = serve().await;
+
+    let channel = connect(addr, HEADER).await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap();
+
+    assert_eq!(
+        rx.recv().await.unwrap(),
+        Some("[2001:db8::1]:56324".parse().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn keeps_the_connection_address_of_local_headers() {
+    let (addr, mut rx) = serve().await;
+
+    let channel = connect(addr, b"PROXY UNKNOWN\r\n").await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap();
+
+    let remote_addr = rx.recv().await.unwrap().unwrap();
+    assert!(remote_addr.ip().is_loopback());
+}
+
+#[tokio::test]
+async fn closes_connections_without_header() {
+    let (addr, _rx) = serve().await;
+
+    let channel = connect(addr, b"").await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap_err();
+
+    // Other connections are still served.
+    let channel = connect(addr, b"PROXY UNKNOWN\r\n").await.unwrap();
+    let mut client = test_client::TestClient::new(channel);
+    client.unary_call(Input {}).await.unwrap();
+}
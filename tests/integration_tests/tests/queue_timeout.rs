@@ -0,0 +1,72 @@
+use hyper_util::rt::TokioIo;
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server, Uri},
+    Code, Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        Ok(Response::new(Output {}))
+    }
+}
+
+#[tokio::test]
+async fn fails_requests_waiting_for_connection() {
+    // A connector that never connects.
+    let connector = tower::service_fn(|_: Uri| {
+        std::future::pending::<Result<TokioIo<TcpStream>, std::io::Error>>()
+    });
+
+    let channel = Endpoint::from_static("http://[::1]:1")
+        .queue_timeout(Duration::from_millis(50))
+        .connect_with_connector_lazy(connector);
+
+    let status = TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(status.message().contains("waiting"), "{status:?}");
+}
+
+#[tokio::test]
+async fn fails_requests_waiting_for_busy_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .concurrency_limit(1)
+        .queue_timeout(Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+
+    let mut first = TestClient::new(channel.clone());
+    let mut second = TestClient::new(channel);
+
+    let first = tokio::spawn(async move { first.unary_call(Input {}).await });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let status = second.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+
+    // The request that was sent is not affected.
+    first.await.unwrap().unwrap();
+}
@@ -0,0 +1,194 @@
+use integration_tests::pb::{test1_client::Test1Client, test1_server, Input1, Output1};
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio_stream::StreamExt;
+use tonic::{
+    testing::{RecordLayer, Recording, ReplayChannel, TestServer},
+    transport::Server,
+    Code, Request, Response, Status,
+};
+
+const DELAY: Duration = Duration::from_millis(50);
+
+/// Echoes the request, and fails requests starting with `!`.
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        let buf = req.into_inner().buf;
+        if buf.starts_with(b"!") {
+            return Err(Status::invalid_argument("bang"));
+        }
+        Ok(Response::new(Output1 { buf }))
+    }
+
+    type StreamCallStream = tokio_stream::Iter<std::vec::IntoIter<Result<Output1, Status>>>;
+
+    async fn stream_call(
+        &self,
+        req: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let buf = req.into_inner().buf;
+        let messages = (0..3).map(|_| Ok(Output1 { buf: buf.clone() }));
+        Ok(Response::new(tokio_stream::iter(
+            messages.collect::<Vec<_>>(),
+        )))
+    }
+}
+
+/// A writer shared with the test.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn input(buf: &[u8]) -> Input1 {
+    Input1 { buf: buf.to_vec() }
+}
+
+/// Records the calls made by `calls` on a server.
+async fn record<F, Fut>(calls: F) -> Recording
+where
+    F: FnOnce(Test1Client<tonic::transport::Channel>) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let recording = Shared::default();
+    let router = Server::builder()
+        .layer(RecordLayer::to_writer(recording.clone()))
+        .add_service(test1_server::Test1Server::new(Svc));
+    let server = TestServer::start_in_memory(router).await;
+
+    calls(Test1Client::new(server.channel())).await;
+    server.shutdown().await;
+
+    let recording = recording.0.lock().unwrap().clone();
+    Recording::from_reader(&recording[..]).unwrap()
+}
+
+#[tokio::test]
+async fn replays_recorded_calls() {
+    let recording = record(|mut client| async move {
+        client.unary_call(input(b"first")).await.unwrap();
+        client.unary_call(input(b"!second")).await.unwrap_err();
+        let stream = client.stream_call(input(b"third")).await.unwrap();
+        assert_eq!(stream.into_inner().collect::<Vec<_>>().await.len(), 3);
+    })
+    .await;
+    assert_eq!(
+        recording.methods().collect::<Vec<_>>(),
+        [
+            "/test.Test1/UnaryCall",
+            "/test.Test1/UnaryCall",
+            "/test.Test1/StreamCall"
+        ]
+    );
+
+    let replay = ReplayChannel::new(recording);
+    let mut client = Test1Client::new(replay.clone());
+
+    // The calls are matched by their requests, whatever their order.
+    let status = client.unary_call(input(b"!second")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "bang");
+
+    let stream = client.stream_call(input(b"third")).await.unwrap();
+    let messages = stream.into_inner().collect::<Vec<_>>().await;
+    assert_eq!(messages.len(), 3);
+    for message in messages {
+        assert_eq!(message.unwrap().buf, b"third");
+    }
+
+    let response = client.unary_call(input(b"first")).await.unwrap();
+    assert_eq!(response.into_inner().buf, b"first");
+
+    replay.verify();
+}
+
+#[tokio::test]
+async fn replays_by_method_without_matching_requests() {
+    let recording = record(|mut client| async move {
+        client.unary_call(input(b"first")).await.unwrap();
+        client.unary_call(input(b"second")).await.unwrap();
+    })
+    .await;
+
+    let replay = ReplayChannel::new(recording).match_requests(false);
+    let mut client = Test1Client::new(replay.clone());
+    for expected in [&b"first"[..], b"second"] {
+        let response = client.unary_call(input(b"other")).await.unwrap();
+        assert_eq!(response.into_inner().buf, expected);
+    }
+
+    replay.verify();
+}
+
+#[tokio::test]
+#[should_panic(expected = "recorded calls were not replayed")]
+async fn verify_fails_on_calls_not_replayed() {
+    let recording = record(|mut client| async move {
+        client.unary_call(input(b"first")).await.unwrap();
+    })
+    .await;
+
+    ReplayChannel::new(recording).verify();
+}
+
+#[tokio::test]
+async fn replays_with_recorded_timing() {
+    let recording = Recording::from_reader(
+        format!(
+            r#"{{"method":"/test.Test1/UnaryCall","request":{{"at":0,"headers":[]}},"response":{{"at":{},"headers":[["content-type","application/grpc"]],"messages":[{{"at":{},"data":"AAAAAAA="}}],"trailers":[["grpc-status","0"]]}}}}"#,
+            DELAY.as_micros(),
+            DELAY.as_micros() * 2,
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(recording.len(), 1);
+
+    let replay = ReplayChannel::new(recording)
+        .match_requests(false)
+        .with_timing();
+    let mut client = Test1Client::new(replay);
+
+    let start = Instant::now();
+    client.unary_call(input(b"")).await.unwrap();
+    assert!(start.elapsed() >= DELAY * 2);
+}
+
+#[tokio::test]
+async fn records_calls_on_clients() {
+    let server = TestServer::start_in_memory(
+        Server::builder().add_service(test1_server::Test1Server::new(Svc)),
+    )
+    .await;
+    let recording = Shared::default();
+    let channel = tower::ServiceBuilder::new()
+        .layer(RecordLayer::to_writer(recording.clone()))
+        .service(server.channel());
+
+    let mut client = Test1Client::new(channel);
+    client.unary_call(input(b"first")).await.unwrap();
+    drop(client);
+    server.shutdown().await;
+
+    let recording = recording.0.lock().unwrap().clone();
+    let replay = ReplayChannel::new(Recording::from_reader(&recording[..]).unwrap());
+    let mut client = Test1Client::new(replay.clone());
+    let response = client.unary_call(input(b"first")).await.unwrap();
+    assert_eq!(response.into_inner().buf, b"first");
+
+    replay.verify();
+}
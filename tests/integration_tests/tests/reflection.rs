@@ -0,0 +1,199 @@
+use integration_tests::pb::{test_server, Input, Output};
+use prost::Message;
+use prost_types::FileDescriptorProto;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use tonic::{
+    reflection::ReflectionService,
+    transport::{Channel, Server},
+    Code, Request, Response, Status, Streaming,
+};
+use tonic_reflection::pb::{
+    v1::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest, ServerReflectionResponse,
+    },
+    v1alpha,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+async fn serve(reflection: ReflectionService) -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let router = Server::builder()
+        .add_service(test_server::TestServer::new(Svc))
+        .add_reflection_service(reflection);
+    tokio::spawn(async move {
+        router
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn reflection() -> ReflectionService {
+    ReflectionService::builder()
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET)
+        .build()
+        .unwrap()
+}
+
+struct Call {
+    tx: mpsc::Sender<ServerReflectionRequest>,
+    responses: Streaming<ServerReflectionResponse>,
+}
+
+impl Call {
+    async fn start(channel: Channel) -> Self {
+        let (tx, rx) = mpsc::channel(1);
+        let responses = ServerReflectionClient::new(channel)
+            .server_reflection_info(ReceiverStream::new(rx))
+            .await
+            .unwrap()
+            .into_inner();
+        Call { tx, responses }
+    }
+
+    async fn send(&mut self, message: MessageRequest) -> MessageResponse {
+        let request = ServerReflectionRequest {
+            host: "localhost".to_owned(),
+            message_request: Some(message),
+        };
+        self.tx.send(request.clone()).await.unwrap();
+
+        let response = self.responses.next().await.unwrap().unwrap();
+        assert_eq!(response.valid_host, "localhost");
+        assert_eq!(response.original_request, Some(request));
+        response.message_response.unwrap()
+    }
+}
+
+fn file_names(response: MessageResponse) -> Vec<String> {
+    let MessageResponse::FileDescriptorResponse(response) = response else {
+        panic!("unexpected response: {response:?}");
+    };
+    response
+        .file_descriptor_proto
+        .iter()
+        .map(|file| {
+            FileDescriptorProto::decode(file.as_slice())
+                .unwrap()
+                .name
+                .unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn lists_services_and_describes_their_files() {
+    let mut call = Call::start(serve(reflection()).await).await;
+
+    let MessageResponse::ListServicesResponse(services) =
+        call.send(MessageRequest::ListServices(String::new())).await
+    else {
+        panic!("unexpected response");
+    };
+    let names: Vec<_> = services.service.into_iter().map(|s| s.name).collect();
+    assert_eq!(
+        names,
+        [
+            "grpc.health.v1.Health",
+            "grpc.reflection.v1.ServerReflection"
+        ]
+    );
+
+    let response = call
+        .send(MessageRequest::FileContainingSymbol(
+            "grpc.health.v1.Health.Check".to_owned(),
+        ))
+        .await;
+    assert_eq!(file_names(response), ["health.proto"]);
+
+    let response = call
+        .send(MessageRequest::FileByFilename(
+            "reflection_v1.proto".to_owned(),
+        ))
+        .await;
+    assert_eq!(file_names(response), ["reflection_v1.proto"]);
+
+    // Unknown symbols are answered without failing the call.
+    let MessageResponse::ErrorResponse(error) = call
+        .send(MessageRequest::FileContainingSymbol(
+            "test.Missing".to_owned(),
+        ))
+        .await
+    else {
+        panic!("unexpected response");
+    };
+    assert_eq!(error.error_code, Code::NotFound as i32);
+
+    let response = call
+        .send(MessageRequest::FileContainingSymbol(
+            "grpc.health.v1.HealthCheckResponse.ServingStatus.SERVING".to_owned(),
+        ))
+        .await;
+    assert_eq!(file_names(response), ["health.proto"]);
+}
+
+#[tokio::test]
+async fn lists_only_the_filtered_services() {
+    let reflection = ReflectionService::builder()
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .with_service_name("grpc.health.v1.Health")
+        .build()
+        .unwrap();
+    let mut call = Call::start(serve(reflection).await).await;
+
+    let MessageResponse::ListServicesResponse(services) =
+        call.send(MessageRequest::ListServices(String::new())).await
+    else {
+        panic!("unexpected response");
+    };
+    assert_eq!(services.service.len(), 1);
+    assert_eq!(services.service[0].name, "grpc.health.v1.Health");
+}
+
+#[tokio::test]
+async fn serves_v1alpha() {
+    let channel = serve(reflection()).await;
+    let mut client = v1alpha::server_reflection_client::ServerReflectionClient::new(channel);
+
+    let request = v1alpha::ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(
+            v1alpha::server_reflection_request::MessageRequest::ListServices(String::new()),
+        ),
+    };
+    let mut responses = client
+        .server_reflection_info(tokio_stream::iter([request]))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let response = responses.next().await.unwrap().unwrap();
+    let Some(v1alpha::server_reflection_response::MessageResponse::ListServicesResponse(services)) =
+        response.message_response
+    else {
+        panic!("unexpected response");
+    };
+    assert_eq!(services.service.len(), 2);
+    assert!(responses.next().await.is_none());
+}
@@ -0,0 +1,126 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    service::{
+        response_cache::{CacheControl, MemoryStore},
+        ResponseCache, ResponseCacheLayer,
+    },
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let calls = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+        if req.metadata().contains_key("x-fail") {
+            return Err(Status::unavailable("try again"));
+        }
+
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("x-calls", calls.to_string().parse().unwrap());
+        if req.metadata().contains_key("x-no-store") {
+            CacheControl::NoStore.insert_into(response.metadata_mut());
+        }
+        Ok(response)
+    }
+}
+
+async fn client() -> (TestClient<ResponseCache<Channel>>, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = test_server::TestServer::new(Svc(calls.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let channel = ServiceBuilder::new()
+        .layer(
+            ResponseCacheLayer::new(MemoryStore::default())
+                .method("/test.Test/UnaryCall")
+                .key_metadata(http::HeaderName::from_static("x-tenant")),
+        )
+        .service(channel);
+
+    (TestClient::new(channel), calls)
+}
+
+fn request(metadata: &[(&'static str, &'static str)]) -> Request<Input> {
+    let mut req = Request::new(Input {});
+    for (key, value) in metadata {
+        req.metadata_mut().insert(*key, value.parse().unwrap());
+    }
+    req
+}
+
+#[tokio::test]
+async fn unary_responses_are_cached() {
+    let (mut client, calls) = client().await;
+
+    for _ in 0..3 {
+        let res = client
+            .unary_call(request(&[("x-tenant", "a")]))
+            .await
+            .unwrap();
+        assert_eq!(res.metadata().get("x-calls").unwrap(), "1");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // The tenant is part of the key.
+    let res = client
+        .unary_call(request(&[("x-tenant", "b")]))
+        .await
+        .unwrap();
+    assert_eq!(res.metadata().get("x-calls").unwrap(), "2");
+
+    // A no-cache request reaches the server and refreshes the cache.
+    let req = request(&[("x-tenant", "a"), ("cache-control", "no-cache")]);
+    let res = client.unary_call(req).await.unwrap();
+    assert_eq!(res.metadata().get("x-calls").unwrap(), "3");
+    let res = client
+        .unary_call(request(&[("x-tenant", "a")]))
+        .await
+        .unwrap();
+    assert_eq!(res.metadata().get("x-calls").unwrap(), "3");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn failures_and_no_store_responses_are_not_cached() {
+    let (mut client, calls) = client().await;
+
+    for _ in 0..2 {
+        let status = client
+            .unary_call(request(&[("x-fail", "1")]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    for _ in 0..2 {
+        client
+            .unary_call(request(&[("x-no-store", "1")]))
+            .await
+            .unwrap();
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
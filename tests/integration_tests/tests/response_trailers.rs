@@ -0,0 +1,104 @@
+use integration_tests::pb::{test_stream_client, test_stream_server, InputStream, OutputStream};
+use std::pin::Pin;
+use tokio::net::TcpListener;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status,
+};
+
+type OutputStreamResult = Pin<Box<dyn Stream<Item = Result<OutputStream, Status>> + Send>>;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_stream_server::TestStream for Svc {
+    type StreamCallStream = OutputStreamResult;
+
+    async fn stream_call(
+        &self,
+        req: Request<InputStream>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let fail = req.metadata().contains_key("x-fail");
+
+        let mut response = Response::new(Box::pin(tokio_stream::empty()) as OutputStreamResult);
+        let trailers = response.trailers();
+
+        let mut count = 0u32;
+        let stream = tokio_stream::iter(0..3)
+            .map(move |_| {
+                count += 1;
+                if count == 3 {
+                    trailers.update(|t| {
+                        t.insert("x-count", count.into());
+                        t.insert("x-origin", "trailers".parse().unwrap());
+                    });
+                }
+                Ok(OutputStream {})
+            })
+            .chain(tokio_stream::iter(fail.then(|| {
+                let mut status = Status::aborted("failed");
+                status
+                    .metadata_mut()
+                    .insert("x-origin", "status".parse().unwrap());
+                Err(status)
+            })));
+
+        Ok(response.map(|_| Box::pin(stream) as OutputStreamResult))
+    }
+}
+
+async fn client() -> test_stream_client::TestStreamClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_stream_server::TestStreamServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    test_stream_client::TestStreamClient::new(channel)
+}
+
+#[tokio::test]
+async fn trailers_set_after_streaming_are_sent() {
+    let mut stream = client()
+        .await
+        .stream_call(InputStream {})
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut messages = 0;
+    while stream.message().await.unwrap().is_some() {
+        messages += 1;
+    }
+    assert_eq!(messages, 3);
+
+    let trailers = stream.trailers().await.unwrap().unwrap();
+    assert_eq!(trailers.get("x-count").unwrap(), "3");
+    assert_eq!(trailers.get("x-origin").unwrap(), "trailers");
+}
+
+#[tokio::test]
+async fn trailers_are_sent_with_errors() {
+    let mut req = Request::new(InputStream {});
+    req.metadata_mut().insert("x-fail", "1".parse().unwrap());
+    let mut stream = client().await.stream_call(req).await.unwrap().into_inner();
+
+    let status = loop {
+        match stream.message().await {
+            Ok(Some(_)) => {}
+            Ok(None) => panic!("expected an error"),
+            Err(status) => break status,
+        }
+    };
+    assert_eq!(status.message(), "failed");
+    assert_eq!(status.metadata().get("x-count").unwrap(), "3");
+    assert_eq!(status.metadata().get("x-origin").unwrap(), "status");
+}
@@ -0,0 +1,95 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::{pin::Pin, time::Duration};
+use tokio::net::TcpListener;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    resume::{resume_token, ResumableStream},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+
+const MESSAGES: u8 = 5;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, _: Request<Input1>) -> Result<Response<Output1>, Status> {
+        unimplemented!()
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    /// Streams `MESSAGES` numbered messages, failing after every second one.
+    async fn stream_call(
+        &self,
+        req: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        let start = resume_token(&req).map_or(0, |token| token[0] + 1);
+        let end = (start + 2).min(MESSAGES);
+
+        let messages = (start..end).map(|i| Ok(Output1 { buf: vec![i] }));
+        let stream = tokio_stream::iter(messages).chain(tokio_stream::iter(
+            (end < MESSAGES).then(|| Err(Status::unavailable("restarting"))),
+        ));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+async fn client() -> test1_client::Test1Client<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test1_server::Test1Server::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    test1_client::Test1Client::new(channel)
+}
+
+#[tokio::test]
+async fn resumes_after_transient_failures() {
+    let client = client().await;
+
+    let stream = ResumableStream::new(Request::new(Input1 { buf: vec![] }), move |req| {
+        let mut client = client.clone();
+        async move { client.stream_call(req).await }
+    })
+    .backoff(Duration::from_millis(1), Duration::from_millis(1));
+
+    let received: Vec<u8> = stream
+        .map(|message| message.unwrap().buf[0])
+        .collect()
+        .await;
+    assert_eq!(received, (0..MESSAGES).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn yields_errors_that_are_not_retried() {
+    let client = client().await;
+
+    let mut stream = ResumableStream::new(Request::new(Input1 { buf: vec![] }), move |req| {
+        let mut client = client.clone();
+        async move { client.stream_call(req).await }
+    })
+    .retry_on([]);
+
+    assert_eq!(stream.next().await.unwrap().unwrap().buf, [0]);
+    assert_eq!(stream.next().await.unwrap().unwrap().buf, [1]);
+    assert_eq!(stream.resume_token().unwrap().as_ref(), [1]);
+
+    let status = stream.next().await.unwrap().unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(stream.next().await.is_none());
+}
@@ -0,0 +1,85 @@
+use integration_tests::pb::{test_client, test_server, Input, Output};
+use std::collections::HashSet;
+use tokio::net::TcpListener;
+use tonic::{
+    metadata::AsciiMetadataKey,
+    transport::{channel::RingHash, server::TcpIncoming, Channel, Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc(u16);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("server", self.0.to_string().parse().unwrap());
+        Ok(response)
+    }
+}
+
+async fn run_server() -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc(addr.port())))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+async fn server_for(client: &mut test_client::TestClient<Channel>, session: &str) -> String {
+    let mut request = Request::new(Input {});
+    request
+        .metadata_mut()
+        .insert("session-id", session.parse().unwrap());
+
+    let response = client.unary_call(request).await.unwrap();
+    response
+        .metadata()
+        .get("server")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn requests_with_same_key_hit_same_endpoint() {
+    let mut addrs = Vec::new();
+    for _ in 0..3 {
+        addrs.push(run_server().await);
+    }
+
+    let endpoints = addrs
+        .iter()
+        .map(|addr| Endpoint::from_shared(format!("http://{addr}")).unwrap());
+    let channel = Channel::ring_hash_list(
+        endpoints,
+        RingHash::metadata(AsciiMetadataKey::from_static("session-id")),
+    );
+    let mut client = test_client::TestClient::new(channel);
+
+    // Wait for all endpoints to connect, so requests are not moved to the first ready one.
+    let mut servers = HashSet::new();
+    for session in 0..64 {
+        servers.insert(server_for(&mut client, &session.to_string()).await);
+    }
+    assert_eq!(servers.len(), 3);
+
+    for session in 0..64 {
+        let session = session.to_string();
+        let server = server_for(&mut client, &session).await;
+        for _ in 0..3 {
+            assert_eq!(server_for(&mut client, &session).await, server);
+        }
+    }
+}
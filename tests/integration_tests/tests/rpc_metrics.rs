@@ -0,0 +1,112 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tonic::{
+    service::rpc_metrics::{Instrument, RpcAttributes, RpcMetricsLayer},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        if req.metadata().contains_key("x-fail") {
+            return Err(Status::not_found("no such thing"));
+        }
+        Ok(Response::new(Output {}))
+    }
+}
+
+type Recorded = Arc<Mutex<Vec<(&'static str, f64, Option<Code>)>>>;
+
+fn layer(recorded: &Recorded, client: bool) -> RpcMetricsLayer {
+    let recorded = recorded.clone();
+    let recorder = move |instrument: Instrument, value: f64, attributes: &RpcAttributes| {
+        assert_eq!(attributes.system(), "grpc");
+        assert_eq!(attributes.service(), "test.Test");
+        assert_eq!(attributes.method(), "UnaryCall");
+        let record = (instrument.name(), value, attributes.status_code());
+        recorded.lock().unwrap().push(record);
+    };
+    if client {
+        RpcMetricsLayer::client(recorder)
+    } else {
+        RpcMetricsLayer::server(recorder)
+    }
+}
+
+#[tokio::test]
+async fn records_metrics_of_both_sides() {
+    let server_recorded = Recorded::default();
+    let client_recorded = Recorded::default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_layer = layer(&server_recorded, false);
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(server_layer)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let channel = ServiceBuilder::new()
+        .layer(layer(&client_recorded, true))
+        .service(channel);
+    let mut client = TestClient::new(channel);
+
+    client.unary_call(Input {}).await.unwrap();
+    let mut req = Request::new(Input {});
+    req.metadata_mut().insert("x-fail", "1".parse().unwrap());
+    let status = client.unary_call(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let without_durations = |recorded: &Recorded| {
+        let recorded = recorded.lock().unwrap();
+        for (name, value, code) in recorded.iter() {
+            if name.ends_with(".duration") {
+                assert!(*value >= 0.0);
+                assert!(code.is_some());
+            }
+        }
+        recorded
+            .iter()
+            .filter(|(name, ..)| !name.ends_with(".duration"))
+            .copied()
+            .collect::<Vec<_>>()
+    };
+
+    // Sizes exclude the 5 byte header framing each message.
+    assert_eq!(
+        without_durations(&client_recorded),
+        [
+            ("rpc.client.request.size", 0.0, None),
+            ("rpc.client.response.size", 0.0, None),
+            ("rpc.client.requests_per_rpc", 1.0, Some(Code::Ok)),
+            ("rpc.client.responses_per_rpc", 1.0, Some(Code::Ok)),
+            ("rpc.client.request.size", 0.0, None),
+            ("rpc.client.requests_per_rpc", 1.0, Some(Code::NotFound)),
+            ("rpc.client.responses_per_rpc", 0.0, Some(Code::NotFound)),
+        ]
+    );
+    assert_eq!(
+        without_durations(&server_recorded),
+        [
+            ("rpc.server.request.size", 0.0, None),
+            ("rpc.server.response.size", 0.0, None),
+            ("rpc.server.requests_per_rpc", 1.0, Some(Code::Ok)),
+            ("rpc.server.responses_per_rpc", 1.0, Some(Code::Ok)),
+            ("rpc.server.request.size", 0.0, None),
+            ("rpc.server.requests_per_rpc", 1.0, Some(Code::NotFound)),
+            ("rpc.server.responses_per_rpc", 0.0, Some(Code::NotFound)),
+        ]
+    );
+}
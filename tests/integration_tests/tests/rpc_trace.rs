@@ -0,0 +1,241 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, OnceLock},
+};
+use tokio::net::TcpListener;
+use tonic::{
+    service::{RpcTrace, RpcTraceLayer, TraceContext},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+use tracing::{field::Field, span, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+/// Answers with the `traceparent` and `baggage` it received.
+struct Backend;
+
+#[tonic::async_trait]
+impl test_server::Test for Backend {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let traceparent = req.metadata().get("traceparent").unwrap().clone();
+        if req.metadata().contains_key("x-fail") {
+            return Err(Status::not_found("no such thing"));
+        }
+        let mut response = Response::new(Output {});
+        response.metadata_mut().insert("x-traceparent", traceparent);
+        if let Some(baggage) = req.metadata().get("baggage") {
+            response.metadata_mut().insert("x-baggage", baggage.clone());
+        }
+        Ok(response)
+    }
+}
+
+/// Calls the backend.
+struct Frontend(TestClient<RpcTrace<Channel>>);
+
+#[tonic::async_trait]
+impl test_server::Test for Frontend {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut outgoing = Request::new(Input {});
+        if let Some(fail) = req.metadata().get("x-fail") {
+            outgoing.metadata_mut().insert("x-fail", fail.clone());
+        }
+        self.0.clone().unary_call(outgoing).await
+    }
+}
+
+async fn serve<S>(svc: test_server::TestServer<S>) -> SocketAddr
+where
+    S: test_server::Test,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(RpcTraceLayer::server())
+            .add_service(svc)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+fn client(addr: SocketAddr) -> TestClient<RpcTrace<Channel>> {
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let channel = ServiceBuilder::new()
+        .layer(RpcTraceLayer::client())
+        .service(channel);
+    TestClient::new(channel)
+}
+
+async fn frontend() -> TestClient<RpcTrace<Channel>> {
+    let backend = serve(test_server::TestServer::new(Backend)).await;
+    let frontend = serve(test_server::TestServer::new(Frontend(client(backend)))).await;
+    client(frontend)
+}
+
+/// A span closed by the RPC tracing layers.
+#[derive(Debug, Clone, Default)]
+struct RecordedSpan {
+    name: &'static str,
+    trace_id: String,
+    method: String,
+    status_code: Option<i64>,
+    otel_status_code: Option<String>,
+}
+
+#[derive(Default)]
+struct Recorder(Arc<Mutex<Vec<RecordedSpan>>>);
+
+impl tracing::field::Visit for RecordedSpan {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if field.name() == "rpc.grpc.status_code" {
+            self.status_code = Some(value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "rpc.method" => self.method = value.to_owned(),
+            "otel.status_code" => self.otel_status_code = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "trace_id" {
+            self.trace_id = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut span = RecordedSpan {
+            name: attrs.metadata().name(),
+            ..Default::default()
+        };
+        attrs.record(&mut span);
+        ctx.span(id).unwrap().extensions_mut().insert(span);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        if let Some(span) = extensions.get_mut::<RecordedSpan>() {
+            values.record(span);
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let recorded = ctx
+            .span(&id)
+            .unwrap()
+            .extensions_mut()
+            .remove::<RecordedSpan>();
+        self.0.lock().unwrap().extend(recorded);
+    }
+}
+
+/// The spans closed so far, by every test of this file.
+fn spans() -> Arc<Mutex<Vec<RecordedSpan>>> {
+    static SPANS: OnceLock<Arc<Mutex<Vec<RecordedSpan>>>> = OnceLock::new();
+    SPANS
+        .get_or_init(|| {
+            let spans = Arc::default();
+            tracing_subscriber::registry()
+                .with(Recorder(Arc::clone(&spans)))
+                .init();
+            spans
+        })
+        .clone()
+}
+
+fn spans_of_trace(trace_id: u128) -> Vec<RecordedSpan> {
+    let trace_id = format!("{trace_id:032x}");
+    spans()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|span| span.trace_id == trace_id)
+        .cloned()
+        .collect()
+}
+
+#[tokio::test]
+async fn trace_is_propagated_across_calls() {
+    spans();
+    let mut client = frontend().await;
+
+    let parent =
+        TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap();
+    let mut req = Request::new(Input {});
+    req.extensions_mut().insert(parent.clone());
+    let res = client.unary_call(req).await.unwrap();
+
+    let traceparent = res.metadata().get("x-traceparent").unwrap();
+    let context = TraceContext::from_traceparent(traceparent.to_str().unwrap()).unwrap();
+    assert_eq!(context.trace_id(), parent.trace_id());
+    assert_ne!(context.span_id(), parent.span_id());
+
+    // The client, frontend server, frontend client and backend server spans.
+    let mut names = spans_of_trace(parent.trace_id())
+        .iter()
+        .map(|span| {
+            assert_eq!(span.method, "UnaryCall");
+            assert_eq!(span.status_code, Some(0));
+            span.name
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        ["grpc.client", "grpc.client", "grpc.server", "grpc.server"]
+    );
+}
+
+#[tokio::test]
+async fn failed_calls_record_their_status() {
+    spans();
+    let mut client = frontend().await;
+
+    let parent =
+        TraceContext::from_traceparent("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01")
+            .unwrap();
+    let mut req = Request::new(Input {});
+    req.extensions_mut().insert(parent.clone());
+    req.metadata_mut().insert("x-fail", "1".parse().unwrap());
+    let status = client.unary_call(req).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    let spans = spans_of_trace(parent.trace_id());
+    assert_eq!(spans.len(), 4);
+    for span in spans {
+        assert_eq!(span.status_code, Some(Code::NotFound as i64));
+        assert_eq!(span.otel_status_code.as_deref(), Some("ERROR"));
+    }
+}
+
+#[tokio::test]
+async fn baggage_is_propagated_across_calls() {
+    let mut client = frontend().await;
+
+    let parent =
+        TraceContext::from_traceparent("00-5bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap()
+            .with_baggage("tenant", "acme corp");
+    let mut req = Request::new(Input {});
+    req.extensions_mut().insert(parent);
+    let res = client.unary_call(req).await.unwrap();
+
+    assert_eq!(
+        res.metadata().get("x-baggage").unwrap(),
+        "tenant=acme%20corp"
+    );
+}
@@ -0,0 +1,87 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::oneshot};
+use tonic::{
+    service::{ServiceConfig, ServiceConfigLayer},
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+#[tokio::test]
+async fn service_config_retries_and_sets_timeout() {
+    struct Svc(Arc<AtomicUsize>);
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            assert!(req.metadata().get("grpc-timeout").is_some());
+
+            if self.0.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(Status::unavailable("not yet"))
+            } else {
+                Ok(Response::new(Output {}))
+            }
+        }
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = test_server::TestServer::new(Svc(calls.clone()));
+
+    let (tx, rx) = oneshot::channel::<()>();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener).with_nodelay(Some(true));
+
+    let jh = tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming_shutdown(incoming, async { drop(rx.await) })
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let config = ServiceConfig::from_json(
+        r#"{
+            "methodConfig": [{
+                "name": [{ "service": "test.Test" }],
+                "timeout": "5s",
+                "retryPolicy": {
+                    "maxAttempts": 3,
+                    "initialBackoff": "0.01s",
+                    "maxBackoff": "0.1s",
+                    "backoffMultiplier": 2,
+                    "retryableStatusCodes": ["UNAVAILABLE"]
+                }
+            }]
+        }"#,
+    )
+    .unwrap();
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let mut client = TestClient::new(
+        ServiceBuilder::new()
+            .layer(ServiceConfigLayer::new(config))
+            .service(channel),
+    );
+
+    client.unary_call(Input {}).await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    tx.send(()).unwrap();
+    jh.await.unwrap();
+}
@@ -0,0 +1,99 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::time::Duration;
+use tonic::{
+    sim::{SimClock, Simulation},
+    time::Clock,
+    transport::{Endpoint, Server},
+    Code, Request, Response, Status,
+};
+
+struct Svc {
+    clock: SimClock,
+    delay: Duration,
+}
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        self.clock.sleep(self.delay).await;
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// Runs a batch of calls over a lossy network and returns the outcome of each call together
+/// with the simulated time it took.
+fn lossy_run(seed: u64) -> (Vec<Result<(), Code>>, Duration) {
+    let sim = Simulation::new(seed);
+    let network = sim.network();
+    network.set_loss_rate(0.02);
+    network.set_connect_latency(Duration::from_millis(5));
+
+    sim.block_on(async {
+        let listener = network.bind("server:50051");
+        let svc = test_server::TestServer::new(Svc {
+            clock: sim.clock(),
+            delay: Duration::from_millis(10),
+        });
+        tokio::spawn(
+            Server::builder()
+                .clock(sim.clock())
+                .add_service(svc)
+                .serve_with_incoming(listener),
+        );
+
+        let channel = Endpoint::from_static("http://server:50051")
+            .clock(sim.clock())
+            .timeout(Duration::from_secs(1))
+            .connect_with_connector_lazy(network.connector());
+        let mut client = TestClient::new(channel);
+
+        let mut outcomes = Vec::new();
+        for _ in 0..20 {
+            let outcome = client.unary_call(Input {}).await;
+            outcomes.push(outcome.map(|_| ()).map_err(|status| status.code()));
+        }
+
+        (outcomes, sim.clock().elapsed())
+    })
+}
+
+#[test]
+fn same_seed_same_outcome() {
+    let first = lossy_run(7);
+    assert_eq!(first, lossy_run(7));
+    assert!(first.0.iter().any(Result::is_ok));
+}
+
+#[test]
+fn deadlines_use_simulated_time() {
+    let sim = Simulation::new(1);
+    let network = sim.network();
+
+    let status = sim.block_on(async {
+        let listener = network.bind("server:50051");
+        let svc = test_server::TestServer::new(Svc {
+            clock: sim.clock(),
+            delay: Duration::from_secs(3600),
+        });
+        tokio::spawn(
+            Server::builder()
+                .clock(sim.clock())
+                .add_service(svc)
+                .serve_with_incoming(listener),
+        );
+
+        let channel = Endpoint::from_static("http://server:50051")
+            .clock(sim.clock())
+            .timeout(Duration::from_secs(2))
+            .connect_with_connector_lazy(network.connector());
+
+        TestClient::new(channel)
+            .unary_call(Input {})
+            .await
+            .unwrap_err()
+    });
+
+    assert_eq!(status.code(), Code::Cancelled);
+    assert!(sim.clock().elapsed() >= Duration::from_secs(2));
+    assert!(sim.clock().elapsed() < Duration::from_secs(3600));
+}
@@ -0,0 +1,136 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    service::{SingleFlight, SingleFlightLayer},
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::ServiceBuilder;
+
+struct Svc(Arc<AtomicUsize>);
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let calls = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        if req.metadata().contains_key("x-fail") {
+            return Err(Status::unavailable("try again"));
+        }
+
+        let mut response = Response::new(Output {});
+        response
+            .metadata_mut()
+            .insert("x-calls", calls.to_string().parse().unwrap());
+        Ok(response)
+    }
+}
+
+async fn client() -> (TestClient<SingleFlight<Channel>>, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let svc = test_server::TestServer::new(Svc(calls.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let channel = ServiceBuilder::new()
+        .layer(
+            SingleFlightLayer::new()
+                .method("/test.Test/UnaryCall")
+                .key_metadata(http::HeaderName::from_static("x-tenant")),
+        )
+        .service(channel);
+
+    (TestClient::new(channel), calls)
+}
+
+fn request(metadata: &[(&'static str, &'static str)]) -> Request<Input> {
+    let mut req = Request::new(Input {});
+    for (key, value) in metadata {
+        req.metadata_mut().insert(*key, value.parse().unwrap());
+    }
+    req
+}
+
+#[tokio::test]
+async fn concurrent_identical_calls_are_coalesced() {
+    let (client, calls) = client().await;
+
+    let tasks = (0..10)
+        .map(|i| {
+            let mut client = client.clone();
+            let tenant = if i % 2 == 0 { "a" } else { "b" };
+            tokio::spawn(async move { client.unary_call(request(&[("x-tenant", tenant)])).await })
+        })
+        .collect::<Vec<_>>();
+
+    for task in tasks {
+        let res = task.await.unwrap().unwrap();
+        assert!(res.metadata().contains_key("x-calls"));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // Nothing is kept once the calls completed.
+    client
+        .clone()
+        .unary_call(request(&[("x-tenant", "a")]))
+        .await
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn errors_are_shared() {
+    let (client, calls) = client().await;
+
+    let tasks = (0..5)
+        .map(|_| {
+            let mut client = client.clone();
+            tokio::spawn(async move { client.unary_call(request(&[("x-fail", "1")])).await })
+        })
+        .collect::<Vec<_>>();
+
+    for task in tasks {
+        let status = task.await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.message(), "try again");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn abandoned_calls_are_sent_by_the_waiting_ones() {
+    let (client, calls) = client().await;
+
+    let mut leader = client.clone();
+    let leader = tokio::spawn(async move {
+        tokio::time::timeout(Duration::from_millis(50), leader.unary_call(request(&[])))
+            .await
+            .unwrap_err();
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let mut follower = client.clone();
+    let res = follower.unary_call(request(&[])).await.unwrap();
+    leader.await.unwrap();
+
+    assert_eq!(res.metadata().get("x-calls").unwrap(), "2");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
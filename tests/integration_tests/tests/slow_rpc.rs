@@ -0,0 +1,137 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    service::SlowRpcLayer,
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+use tower::ServiceBuilder;
+use tracing::{field::Field, Event, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+const DELAY: Duration = Duration::from_millis(50);
+
+/// Takes [`DELAY`] to answer.
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        tokio::time::sleep(DELAY).await;
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// The fields of an event logged under the `tonic::slow_rpc` target, by name.
+type Fields = HashMap<&'static str, String>;
+
+#[derive(Default)]
+struct Recorder(Arc<Mutex<Vec<Fields>>>);
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl tracing::field::Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if event.metadata().target() == "tonic::slow_rpc" {
+            let mut fields = Fields::new();
+            event.record(&mut Visitor(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+}
+
+/// Makes `calls` calls through a lazy channel, with both sides logging the RPCs slower than
+/// `threshold`, one in every `sample`.
+async fn call(calls: usize, threshold: Duration, sample: u64) -> Vec<Fields> {
+    let events = Arc::<Mutex<Vec<Fields>>>::default();
+    let _guard = tracing_subscriber::registry()
+        .with(Recorder(events.clone()))
+        .set_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(SlowRpcLayer::server(threshold).sample(sample))
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let svc = ServiceBuilder::new()
+        .layer(SlowRpcLayer::client(threshold).sample(sample))
+        .service(channel);
+    let mut client = TestClient::new(svc);
+    for _ in 0..calls {
+        client.unary_call(Input {}).await.unwrap();
+    }
+    // The server logs its RPCs once their responses are dropped, after the client received them.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+#[tokio::test]
+async fn logs_slow_rpcs_on_both_sides() {
+    let events = call(1, DELAY / 2, 1).await;
+    assert_eq!(events.len(), 2, "{events:?}");
+
+    let server = events
+        .iter()
+        .find(|event| event["message"] == "slow RPC served")
+        .unwrap();
+    let client = events
+        .iter()
+        .find(|event| event["message"] == "slow RPC call")
+        .unwrap();
+
+    for event in [server, client] {
+        assert_eq!(event["method"], "/test.Test/UnaryCall");
+        assert_eq!(event["code"], "Ok");
+        assert_eq!(event["request_messages"], "1");
+        assert_eq!(event["response_messages"], "1");
+        assert!(event.contains_key("latency"));
+        assert!(event.contains_key("streaming"));
+    }
+
+    assert!(server["peer"].starts_with("127.0.0.1:"));
+    assert!(server.contains_key("handler"));
+
+    assert!(client["peer"].starts_with("http://127.0.0.1:"));
+    for phase in ["queued", "connect", "server"] {
+        assert!(client.contains_key(phase), "{phase}");
+    }
+}
+
+#[tokio::test]
+async fn skips_fast_rpcs() {
+    assert_eq!(call(1, Duration::from_secs(10), 1).await, []);
+}
+
+#[tokio::test]
+async fn samples_slow_rpcs() {
+    let events = call(4, DELAY / 2, 2).await;
+    let count = |message| events.iter().filter(|e| e["message"] == message).count();
+    assert_eq!(count("slow RPC served"), 2);
+    assert_eq!(count("slow RPC call"), 2);
+}
@@ -15,7 +15,7 @@ use tonic::body::Body;
 use tonic::metadata::{MetadataMap, MetadataValue};
 use tonic::{
     transport::{server::TcpIncoming, Endpoint, Server},
-    Code, Request, Response, Status,
+    Code, Request, Response, Status, TransportCause,
 };
 
 #[tokio::test]
@@ -281,6 +281,53 @@ async fn status_from_server_stream_with_inferred_status() {
 
     tokio::time::sleep(Duration::from_millis(100)).await;
 
+    let mut client = test_stream_client::TestStreamClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    let status = client.stream_call(InputStream {}).await.unwrap_err();
+
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn message_and_then_status_from_server_stream() {
+    integration_tests::trace_init();
+
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_stream_server::TestStream for Svc {
+        type StreamCallStream = Stream<OutputStream>;
+
+        async fn stream_call(
+            &self,
+            _: Request<InputStream>,
+        ) -> Result<Response<Self::StreamCallStream>, Status> {
+            let s = tokio_stream::iter(vec![
+                Ok(OutputStream {}),
+                Err::<OutputStream, _>(Status::unavailable("foo")),
+            ]);
+            Ok(Response::new(Box::pin(s) as Self::StreamCallStream))
+        }
+    }
+
+    let svc = test_stream_server::TestStreamServer::new(Svc);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener).with_nodelay(Some(true));
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
     let mut client = test_stream_client::TestStreamClient::connect(format!("http://{addr}"))
         .await
         .unwrap();
@@ -291,16 +338,13 @@ async fn status_from_server_stream_with_inferred_status() {
         .unwrap()
         .into_inner();
 
-    assert_eq!(
-        stream.message().await.unwrap_err().code(),
-        Code::Unavailable
-    );
-
+    assert_eq!(stream.message().await.unwrap(), Some(OutputStream {}));
+    assert_eq!(stream.message().await.unwrap_err().message(), "foo");
     assert_eq!(stream.message().await.unwrap(), None);
 }
 
 #[tokio::test]
-async fn message_and_then_status_from_server_stream() {
+async fn partial_messages_from_failed_server_stream() {
     integration_tests::trace_init();
 
     struct Svc;
@@ -314,6 +358,7 @@ async fn message_and_then_status_from_server_stream() {
             _: Request<InputStream>,
         ) -> Result<Response<Self::StreamCallStream>, Status> {
             let s = tokio_stream::iter(vec![
+                Ok(OutputStream {}),
                 Ok(OutputStream {}),
                 Err::<OutputStream, _>(Status::unavailable("foo")),
             ]);
@@ -348,6 +393,125 @@ async fn message_and_then_status_from_server_stream() {
         .into_inner();
 
     assert_eq!(stream.message().await.unwrap(), Some(OutputStream {}));
-    assert_eq!(stream.message().await.unwrap_err().message(), "foo");
-    assert_eq!(stream.message().await.unwrap(), None);
+    assert_eq!(stream.messages_received(), 1);
+
+    let (messages, end) = stream.into_parts_on_error().await;
+    assert_eq!(messages, vec![OutputStream {}]);
+    assert_eq!(end.unwrap_err().message(), "foo");
+}
+
+#[tokio::test]
+async fn status_from_plain_http_error_response() {
+    integration_tests::trace_init();
+
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+            Ok(Response::new(Output {}))
+        }
+    }
+
+    #[derive(Clone)]
+    struct ProxyLayer;
+
+    impl<S> tower::Layer<S> for ProxyLayer {
+        type Service = ProxyService;
+
+        fn layer(&self, _: S) -> Self::Service {
+            ProxyService
+        }
+    }
+
+    #[derive(Clone)]
+    struct ProxyService;
+
+    impl tower::Service<http::Request<Body>> for ProxyService {
+        type Response = http::Response<Body>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: http::Request<Body>) -> Self::Future {
+            Box::pin(async {
+                Ok(http::Response::builder()
+                    .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                    .header("content-type", "text/html")
+                    .body(Body::new(
+                        "<html><body>upstream unavailable</body></html>".to_string(),
+                    ))
+                    .unwrap())
+            })
+        }
+    }
+
+    let svc = test_server::TestServer::new(Svc);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming: TcpIncoming = TcpIncoming::from(listener).with_nodelay(Some(true));
+
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(ProxyLayer)
+            .add_service(svc)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut client = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    let status = client.unary_call(Input {}).await.unwrap_err();
+
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(status.message().contains("503"));
+    assert!(status.message().contains("upstream unavailable"));
+}
+
+#[tokio::test]
+async fn status_from_transport_failure_has_cause() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let status = test_client::TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(status.transport_cause(), Some(TransportCause::Connect));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+        }
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let status = test_client::TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+    assert_eq!(
+        status.transport_cause(),
+        Some(TransportCause::ConnectionClosed),
+        "{status:?}"
+    );
 }
@@ -0,0 +1,59 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tonic::{
+    body::Body,
+    transport::{server::TcpIncoming, Channel, Server},
+    Code, Request, Response, Status,
+};
+use tower::util::MapResponseLayer;
+
+#[derive(Clone, Debug, PartialEq)]
+struct TraceId(u64);
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        let mut status = Status::internal("oops");
+        status.extensions_mut().insert(TraceId(42));
+        Err(status)
+    }
+}
+
+#[tokio::test]
+async fn status_extensions_stay_on_the_server() {
+    let seen = Arc::new(Mutex::new(None));
+    let seen_by_layer = seen.clone();
+    let layer = MapResponseLayer::new(move |res: http::Response<Body>| {
+        *seen_by_layer.lock().unwrap() = res
+            .extensions()
+            .get::<Status>()
+            .and_then(|status| status.extensions().get::<TraceId>().cloned());
+        res
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .layer(layer)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    let status = TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .unwrap_err();
+
+    assert_eq!(status.code(), Code::Internal);
+    assert!(status.extensions().is_empty());
+    assert_eq!(*seen.lock().unwrap(), Some(TraceId(42)));
+}
@@ -0,0 +1,189 @@
+use integration_tests::pb::{test1_client, test1_server, Input1, Output1};
+use std::{
+    convert::Infallible,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::net::TcpListener;
+use tokio_stream::Stream;
+use tonic::{
+    body::Body,
+    client::Grpc,
+    codec::{MessageSink, StreamedMessage},
+    server::NamedService,
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status,
+};
+use tonic_prost::ProstCodec;
+use tower_service::Service;
+
+const LEN: usize = 1024 * 1024;
+
+fn payload() -> Vec<u8> {
+    (0..LEN).map(|i| (i % 251) as u8).collect()
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tonic-{}-{name}", std::process::id()))
+}
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test1_server::Test1 for Svc {
+    async fn unary_call(&self, req: Request<Input1>) -> Result<Response<Output1>, Status> {
+        let buf = req.into_inner().buf;
+        assert!(buf == payload(), "received a different payload");
+
+        Ok(Response::new(Output1 {
+            buf: (buf.len() as u32).to_be_bytes().to_vec(),
+        }))
+    }
+
+    type StreamCallStream = Pin<Box<dyn Stream<Item = Result<Output1, Status>> + Send + 'static>>;
+
+    async fn stream_call(
+        &self,
+        _: Request<Input1>,
+    ) -> Result<Response<Self::StreamCallStream>, Status> {
+        unimplemented!()
+    }
+}
+
+/// Writes the payload of the `UnaryCall` request to a file and answers with an empty `Output1`.
+#[derive(Clone)]
+struct Sink {
+    path: PathBuf,
+}
+
+impl NamedService for Sink {
+    const NAME: &'static str = "test.Test1";
+}
+
+impl Service<http::Request<Body>> for Sink {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let path = self.path.clone();
+
+        Box::pin(async move {
+            let mut file = tokio::fs::File::create(&path).await.unwrap();
+            let mut sink = MessageSink::new(req.into_body()).bytes_field(1);
+            assert_eq!(sink.copy_to(&mut file).await.unwrap(), Some(LEN as u64));
+            assert_eq!(sink.copy_to(&mut file).await.unwrap(), None);
+
+            let mut trailers = http::HeaderMap::new();
+            trailers.insert("grpc-status", "0".parse().unwrap());
+
+            let body = StreamedMessage::new(&b""[..], 0).trailers(trailers);
+            Ok(http::Response::builder()
+                .header("content-type", "application/grpc")
+                .body(Body::new(body))
+                .unwrap())
+        })
+    }
+}
+
+async fn channel<S>(svc: S) -> Channel
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = Infallible>
+        + NamedService
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(svc)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sends_message_from_file() {
+    let path = temp_file("send");
+    tokio::fs::write(&path, payload()).await.unwrap();
+
+    let channel = channel(test1_server::Test1Server::new(Svc)).await;
+
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    let len = file.metadata().await.unwrap().len();
+    let request = Request::new(
+        StreamedMessage::new(file, len)
+            .bytes_field(1)
+            .chunk_size(10_000),
+    );
+
+    let mut client = Grpc::new(channel);
+    client.ready().await.unwrap();
+    let response = client
+        .streamed_unary(
+            request,
+            "/test.Test1/UnaryCall".parse().unwrap(),
+            ProstCodec::<(), Output1>::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().buf, (LEN as u32).to_be_bytes());
+
+    tokio::fs::remove_file(&path).await.unwrap();
+}
+
+#[tokio::test]
+async fn fails_when_file_is_shorter() {
+    let path = temp_file("short");
+    tokio::fs::write(&path, b"short").await.unwrap();
+
+    let channel = channel(test1_server::Test1Server::new(Svc)).await;
+
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    let request = Request::new(StreamedMessage::new(file, LEN as u64).bytes_field(1));
+
+    let mut client = Grpc::new(channel);
+    client.ready().await.unwrap();
+    let result = client
+        .streamed_unary(
+            request,
+            "/test.Test1/UnaryCall".parse().unwrap(),
+            ProstCodec::<(), Output1>::default(),
+        )
+        .await;
+    assert!(result.is_err());
+
+    tokio::fs::remove_file(&path).await.unwrap();
+}
+
+#[tokio::test]
+async fn writes_message_to_file() {
+    let path = temp_file("sink");
+    let channel = channel(Sink { path: path.clone() }).await;
+
+    let mut client = test1_client::Test1Client::new(channel);
+    client.unary_call(Input1 { buf: payload() }).await.unwrap();
+
+    assert!(tokio::fs::read(&path).await.unwrap() == payload());
+
+    tokio::fs::remove_file(&path).await.unwrap();
+}
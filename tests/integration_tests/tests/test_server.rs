@@ -0,0 +1,67 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use tonic::{testing::TestServer, transport::Server, Code, Request, Response, Status};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        if req.metadata().contains_key("panic") {
+            panic!("asked to panic");
+        }
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn router() -> tonic::transport::server::Router {
+    Server::builder().add_service(test_server::TestServer::new(Svc))
+}
+
+fn panicking_call() -> Request<Input> {
+    let mut req = Request::new(Input {});
+    req.metadata_mut().insert("panic", "1".parse().unwrap());
+    req
+}
+
+#[tokio::test]
+async fn serves_on_an_ephemeral_port() {
+    let server = TestServer::start(router()).await;
+    assert_eq!(server.uri().host(), Some("127.0.0.1"));
+
+    let mut client = TestClient::new(server.channel());
+    client.unary_call(Input {}).await.unwrap();
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+async fn serves_in_memory() {
+    let server = TestServer::start_in_memory(router()).await;
+
+    let mut client = TestClient::new(server.channel());
+    client.unary_call(Input {}).await.unwrap();
+    client.unary_call(Input {}).await.unwrap();
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "the test server panicked: [\"asked to panic\"]")]
+async fn fails_the_test_if_a_handler_panics() {
+    let server = TestServer::start_in_memory(router()).await;
+
+    let mut client = TestClient::new(server.channel());
+    let status = client.unary_call(panicking_call()).await.unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+
+    server.shutdown().await;
+}
+
+#[tokio::test]
+#[should_panic(expected = "the test server panicked")]
+async fn fails_the_test_once_dropped() {
+    let server = TestServer::start(router()).await;
+    let _ = TestClient::new(server.channel())
+        .unary_call(panicking_call())
+        .await;
+}
@@ -1,5 +1,8 @@
 use integration_tests::pb::{test_client, test_server, Input, Output};
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 use tokio::net::TcpListener;
 use tonic::{transport::Server, Code, Request, Response, Status};
 
@@ -38,8 +41,8 @@ async fn picks_server_timeout_if_thats_sorter() {
 
     let res = client.unary_call(req).await;
     let err = res.unwrap_err();
-    assert!(err.message().contains("Timeout expired"));
-    assert_eq!(err.code(), Code::Cancelled);
+    assert!(err.message().contains("Deadline exceeded after"));
+    assert_eq!(err.code(), Code::DeadlineExceeded);
 }
 
 #[tokio::test]
@@ -61,7 +64,100 @@ async fn picks_client_timeout_if_thats_sorter() {
     assert_eq!(err.code(), Code::Cancelled);
 }
 
+#[tokio::test]
+async fn picks_method_timeout_over_server_timeout() {
+    let addr = run_service_in_background_with(Duration::from_millis(300), |server| {
+        server
+            .timeout(Duration::from_millis(100))
+            .method_timeout("/test.Test/UnaryCall", Duration::from_secs(100))
+    })
+    .await;
+
+    let mut client = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    client.unary_call(Request::new(Input {})).await.unwrap();
+
+    let mut req = Request::new(Input {});
+    req.metadata_mut()
+        // 100 ms
+        .insert("grpc-timeout", "100m".parse().unwrap());
+
+    let err = client.unary_call(req).await.unwrap_err();
+    assert!(err.message().contains("Timeout expired"));
+    assert_eq!(err.code(), Code::Cancelled);
+}
+
+#[tokio::test]
+async fn clamps_client_timeout_to_max() {
+    let addr = run_service_in_background_with(Duration::from_secs(1), |server| {
+        server.max_client_timeout(Duration::from_millis(100))
+    })
+    .await;
+
+    let mut client = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    let mut req = Request::new(Input {});
+    req.metadata_mut()
+        // 10 hours
+        .insert("grpc-timeout", "10H".parse().unwrap());
+
+    let err = client.unary_call(req).await.unwrap_err();
+    assert_eq!(err.code(), Code::DeadlineExceeded);
+}
+
+#[tokio::test]
+async fn exposes_deadline_to_handlers() {
+    struct Svc;
+
+    #[tonic::async_trait]
+    impl test_server::Test for Svc {
+        async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+            let remaining = req
+                .deadline()
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            match remaining {
+                Some(remaining) if remaining > Duration::ZERO => Ok(Response::new(Output {})),
+                _ => Err(Status::internal(format!(
+                    "unexpected deadline {remaining:?}"
+                ))),
+            }
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    });
+
+    let mut client = test_client::TestClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+
+    let mut req = Request::new(Input {});
+    req.set_timeout(Duration::from_secs(10));
+    client.unary_call(req).await.unwrap();
+
+    let err = client.unary_call(Input {}).await.unwrap_err();
+    assert_eq!(err.message(), "unexpected deadline None");
+}
+
 async fn run_service_in_background(latency: Duration, server_timeout: Duration) -> SocketAddr {
+    run_service_in_background_with(latency, |server| server.timeout(server_timeout)).await
+}
+
+async fn run_service_in_background_with(
+    latency: Duration,
+    configure: impl FnOnce(Server) -> Server,
+) -> SocketAddr {
     struct Svc {
         latency: Duration,
     }
@@ -79,9 +175,9 @@ async fn run_service_in_background(latency: Duration, server_timeout: Duration)
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
+    let mut server = configure(Server::builder());
     tokio::spawn(async move {
-        Server::builder()
-            .timeout(server_timeout)
+        server
             .add_service(svc)
             .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
             .await
@@ -0,0 +1,71 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig, TlsInfo,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+// No process wide provider is installed, so the configured ones must be used.
+#[tokio::test]
+async fn uses_configured_crypto_provider() {
+    let mut provider = rustls::crypto::ring::default_provider();
+    provider
+        .cipher_suites
+        .retain(|suite| suite.suite() == rustls::CipherSuite::TLS13_CHACHA20_POLY1305_SHA256);
+    let provider = Arc::new(provider);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let server_tls = ServerTlsConfig::new()
+        .identity(Identity::from_pem(read("server.pem"), read("server.key")))
+        .crypto_provider(provider.clone());
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(server_tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let client_tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name("example.com")
+        .crypto_provider(provider);
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(client_tls)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let res = TestClient::new(channel).unary_call(Input {}).await.unwrap();
+    let info = res.extensions().get::<TlsInfo>().unwrap();
+    assert_eq!(
+        info.cipher_suite(),
+        Some(rustls::CipherSuite::TLS13_CHACHA20_POLY1305_SHA256)
+    );
+}
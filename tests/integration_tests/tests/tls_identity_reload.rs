@@ -0,0 +1,79 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::{net::TcpListener, sync::watch};
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+fn identity(name: &str) -> Identity {
+    Identity::from_pem(read(&format!("{name}.pem")), read(&format!("{name}.key")))
+}
+
+async fn connect(
+    addr: SocketAddr,
+    ca: &str,
+    domain: &str,
+) -> Result<Channel, tonic::transport::Error> {
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read(ca)))
+        .domain_name(domain);
+    Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect()
+        .await
+}
+
+#[tokio::test]
+async fn rotates_identity_without_restarting() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let (tx, rx) = watch::channel(identity("server"));
+    let tls = ServerTlsConfig::new().identity_watch(rx);
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let mut before = TestClient::new(connect(addr, "ca.pem", "example.com").await.unwrap());
+    before.unary_call(Input {}).await.unwrap();
+
+    tx.send(identity("other")).unwrap();
+
+    // New connections use the new identity.
+    let mut after = TestClient::new(connect(addr, "other.pem", "other.test").await.unwrap());
+    after.unary_call(Input {}).await.unwrap();
+    connect(addr, "ca.pem", "example.com").await.unwrap_err();
+
+    // Established connections keep theirs.
+    before.unary_call(Input {}).await.unwrap();
+}
@@ -0,0 +1,77 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use rustls::pki_types::{pem::PemObject, CertificateDer};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig, TlsInfo,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, req: Request<Input>) -> Result<Response<Output>, Status> {
+        let info = req.extensions().get::<TlsInfo>().unwrap();
+        assert_eq!(info.alpn_protocol(), Some(&b"h2"[..]));
+        assert!(info.protocol_version().is_some());
+        assert!(info.cipher_suite().is_some());
+        // The client did not present a certificate.
+        assert!(info.peer_certs().is_none());
+        assert_eq!(info.server_name(), Some("example.com"));
+        assert!(std::ptr::eq(info, req.tls_info().unwrap()));
+        assert!(req.local_addr().is_some());
+
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+#[tokio::test]
+async fn exposes_tls_info_on_both_sides() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let server_tls =
+        ServerTlsConfig::new().identity(Identity::from_pem(read("server.pem"), read("server.key")));
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(server_tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let client_tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name("example.com");
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(client_tls)
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+
+    let res = TestClient::new(channel).unary_call(Input {}).await.unwrap();
+    let info = res.extensions().get::<TlsInfo>().unwrap();
+    assert_eq!(info.alpn_protocol(), Some(&b"h2"[..]));
+    assert!(info.protocol_version().is_some());
+    assert!(info.cipher_suite().is_some());
+    assert_eq!(info.server_name(), None);
+
+    let server_cert = CertificateDer::from_pem_slice(&read("server.pem")).unwrap();
+    assert_eq!(info.peer_certs().unwrap()[0], server_cert);
+}
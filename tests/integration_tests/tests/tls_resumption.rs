@@ -0,0 +1,92 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+/// Makes a call on `calls` channels connected with the same endpoint to a TLS server, returning the
+/// client and server stats.
+async fn call(
+    client_tls: ClientTlsConfig,
+    server_tls: ServerTlsConfig,
+    calls: usize,
+) -> (tonic::transport::TlsStats, tonic::transport::TlsStats) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let server_stats = server_tls.stats();
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(server_tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let client_stats = client_tls.stats();
+    let endpoint = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(client_tls)
+        .unwrap();
+    for _ in 0..calls {
+        let channel = endpoint.connect().await.unwrap();
+        TestClient::new(channel).unary_call(Input {}).await.unwrap();
+    }
+
+    (client_stats, server_stats)
+}
+
+fn configs() -> (ClientTlsConfig, ServerTlsConfig) {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let client = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name("example.com");
+    let server =
+        ServerTlsConfig::new().identity(Identity::from_pem(read("server.pem"), read("server.key")));
+    (client, server)
+}
+
+#[tokio::test]
+async fn resumes_sessions_across_channels() {
+    let (client, server) = configs();
+    let (client, server) = call(client, server, 3).await;
+
+    assert_eq!(client.handshakes(), 3);
+    assert_eq!(client.resumed_handshakes(), 2);
+    assert_eq!(server.handshakes(), 3);
+    assert_eq!(server.resumed_handshakes(), 2);
+}
+
+#[tokio::test]
+async fn does_not_resume_disabled_sessions() {
+    let (client, server) = configs();
+    let (client, server) = call(client.session_resumption(false), server, 2).await;
+
+    assert_eq!(client.handshakes(), 2);
+    assert_eq!(client.resumed_handshakes(), 0);
+    assert_eq!(client.resumption_rate(), 0.0);
+    assert_eq!(server.resumed_handshakes(), 0);
+}
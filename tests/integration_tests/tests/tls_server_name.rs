@@ -0,0 +1,74 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+async fn serve() -> SocketAddr {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    let tls =
+        ServerTlsConfig::new().identity(Identity::from_pem(read("server.pem"), read("server.key")));
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+async fn call(addr: SocketAddr, domain: &str, server_name: &str) -> Result<(), Status> {
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read("ca.pem")))
+        .domain_name(domain);
+    let endpoint = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .origin("https://virtual.host".parse().unwrap())
+        .tls_config(tls)
+        .unwrap()
+        .tls_server_name(server_name)
+        .unwrap();
+
+    TestClient::new(endpoint.connect_lazy())
+        .unary_call(Input {})
+        .await
+        .map(drop)
+}
+
+#[tokio::test]
+async fn tls_server_name_overrides_domain_name() {
+    let addr = serve().await;
+
+    // The server certificate is valid for `example.test`, but not for `other.test`.
+    call(addr, "other.test", "example.test").await.unwrap();
+    call(addr, "example.test", "other.test").await.unwrap_err();
+}
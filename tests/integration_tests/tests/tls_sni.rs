@@ -0,0 +1,91 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{
+        server::TcpIncoming, Certificate, ClientTlsConfig, Endpoint, Identity, Server,
+        ServerTlsConfig,
+    },
+    Request, Response, Status,
+};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/data/tls");
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{DATA}/{name}")).unwrap()
+}
+
+async fn serve(tls: ServerTlsConfig) -> SocketAddr {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from(listener);
+
+    tokio::spawn(async move {
+        Server::builder()
+            .tls_config(tls)
+            .unwrap()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    addr
+}
+
+async fn call(addr: SocketAddr, ca: &str, domain: &str) -> Result<(), Status> {
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(read(ca)))
+        .domain_name(domain);
+    let channel = Endpoint::from_shared(format!("https://{addr}"))
+        .unwrap()
+        .tls_config(tls)
+        .unwrap()
+        .connect_lazy();
+
+    TestClient::new(channel)
+        .unary_call(Input {})
+        .await
+        .map(drop)
+}
+
+#[tokio::test]
+async fn selects_identity_by_server_name() {
+    let tls = ServerTlsConfig::new()
+        .identity(Identity::from_pem(read("server.pem"), read("server.key")))
+        .sni_identity(
+            "Other.Test",
+            Identity::from_pem(read("other.pem"), read("other.key")),
+        );
+    let addr = serve(tls).await;
+
+    call(addr, "other.pem", "other.test").await.unwrap();
+    call(addr, "ca.pem", "example.test").await.unwrap();
+    call(addr, "ca.pem", "localhost").await.unwrap();
+
+    // The default identity is not presented for a configured server name.
+    call(addr, "ca.pem", "other.test").await.unwrap_err();
+}
+
+#[tokio::test]
+async fn fails_unknown_server_names_without_default_identity() {
+    let tls = ServerTlsConfig::new().sni_identity(
+        "other.test",
+        Identity::from_pem(read("other.pem"), read("other.key")),
+    );
+    let addr = serve(tls).await;
+
+    call(addr, "other.pem", "other.test").await.unwrap();
+    call(addr, "ca.pem", "example.test").await.unwrap_err();
+}
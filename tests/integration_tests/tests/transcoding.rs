@@ -0,0 +1,193 @@
+use http::{header, Method, StatusCode};
+use http_body_util::{BodyExt, Full};
+use integration_tests::pb::transcoding::{
+    library_client::LibraryClient,
+    library_server::{Library, LibraryServer},
+    Book, CreateBookRequest, GetBookRequest, ListBooksRequest, FILE_DESCRIPTOR_SET,
+};
+use serde_json::{json, Value as Json};
+use std::pin::Pin;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_stream::Stream;
+use tonic::{
+    body::Body,
+    service::Routes,
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status,
+};
+use tonic_prost::dynamic::{prost_reflect::DescriptorPool, TranscodingLayer};
+use tower::{Layer, ServiceExt};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl Library for Svc {
+    async fn get_book(&self, req: Request<GetBookRequest>) -> Result<Response<Book>, Status> {
+        let name = req.into_inner().name;
+        if !name.ends_with("/books/1") {
+            return Err(Status::not_found(format!("{name} does not exist")));
+        }
+        Ok(Response::new(Book {
+            name,
+            title: "Dune".into(),
+            pages: 412,
+        }))
+    }
+
+    async fn create_book(&self, req: Request<CreateBookRequest>) -> Result<Response<Book>, Status> {
+        let auth = req.metadata().get("authorization").cloned();
+        let req = req.into_inner();
+        let book = req.book.unwrap_or_default();
+        Ok(Response::new(Book {
+            name: format!("shelves/{}/books/{:?}", req.shelf, auth.unwrap()),
+            ..book
+        }))
+    }
+
+    type ListBooksStream = Pin<Box<dyn Stream<Item = Result<Book, Status>> + Send>>;
+
+    async fn list_books(
+        &self,
+        req: Request<ListBooksRequest>,
+    ) -> Result<Response<Self::ListBooksStream>, Status> {
+        let req = req.into_inner();
+        let books = req
+            .titles
+            .iter()
+            .take(req.limit as usize)
+            .map(|title| {
+                Ok(Book {
+                    title: format!("{}: {title}", req.shelf),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(Response::new(Box::pin(tokio_stream::iter(books))))
+    }
+}
+
+async fn call(method: Method, uri: &str, body: Option<Json>) -> (StatusCode, Json) {
+    let pool = DescriptorPool::decode(FILE_DESCRIPTOR_SET).unwrap();
+    let svc = TranscodingLayer::new(&pool)
+        .unwrap()
+        .layer(Routes::new(LibraryServer::new(Svc)).prepare());
+
+    let body = body.map_or_else(Vec::new, |json| serde_json::to_vec(&json).unwrap());
+    let req = http::Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, "token")
+        .body(Body::new(Full::new(bytes::Bytes::from(body))))
+        .unwrap();
+
+    let res = svc.oneshot(req).await.unwrap();
+    let status = res.status();
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "application/json");
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn path_bindings() {
+    let (status, json) = call(Method::GET, "/v1/shelves/a/books/1", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json,
+        json!({ "name": "shelves/a/books/1", "title": "Dune", "pages": "412" })
+    );
+}
+
+#[tokio::test]
+async fn body_bindings() {
+    let book = json!({ "title": "Emma", "pages": 474 });
+    let (status, json) = call(Method::POST, "/v1/shelves/a/books", Some(book)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json,
+        json!({ "name": "shelves/a/books/\"token\"", "title": "Emma", "pages": "474" })
+    );
+
+    let request = json!({ "shelf": "ignored", "book": { "title": "Emma" } });
+    let (status, json) = call(Method::PUT, "/v1/shelves/b/books:create", Some(request)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["name"], "shelves/b/books/\"token\"");
+}
+
+#[tokio::test]
+async fn query_bindings() {
+    let uri = "/v1/shelves/a/books?limit=2&titles=Dune&titles=Emma+2&titles=Ulysses&shelf=b";
+    let (status, json) = call(Method::GET, uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json, json!(["a: Dune", "a: Emma 2"]));
+}
+
+#[tokio::test]
+async fn errors() {
+    let (status, json) = call(Method::GET, "/v1/shelves/a/books/2", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        json,
+        json!({ "code": 5, "message": "shelves/a/books/2 does not exist" })
+    );
+
+    let (status, json) = call(Method::GET, "/v1/shelves/a/books?limit=many", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json["message"],
+        "invalid parameter limit: \"many\" is not an integer"
+    );
+
+    let book = json!({ "pages": true });
+    let (status, json) = call(Method::POST, "/v1/shelves/a/books", Some(book)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json["message"],
+        "invalid JSON message: book.pages: expected an integer"
+    );
+}
+
+#[tokio::test]
+async fn same_listener() {
+    let pool = DescriptorPool::decode(FILE_DESCRIPTOR_SET).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .accept_http1(true)
+            .layer(TranscodingLayer::new(&pool).unwrap())
+            .add_service(LibraryServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            b"GET /v1/shelves/a/books/1 HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with(r#""title":"Dune"}"#), "{response}");
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let book = LibraryClient::new(channel)
+        .get_book(GetBookRequest {
+            name: "shelves/a/books/1".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(book.title, "Dune");
+}
@@ -0,0 +1,101 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// Runs a server and returns its endpoint and the number of connections it accepted.
+async fn run_server() -> (Endpoint, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let incoming = TcpIncoming::from(listener).map({
+        let accepted = accepted.clone();
+        move |conn| {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            conn
+        }
+    });
+
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let endpoint = Endpoint::from_shared(format!("http://{addr}")).unwrap();
+    (endpoint, accepted)
+}
+
+async fn wait_for_connections(accepted: &AtomicUsize, expected: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while accepted.load(Ordering::SeqCst) < expected {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn connect_establishes_all_connections() {
+    let (endpoint, accepted) = run_server().await;
+
+    let channel = endpoint.warmup(3).connect().await.unwrap();
+    wait_for_connections(&accepted, 3).await;
+
+    let mut client = TestClient::new(channel);
+    for _ in 0..10 {
+        client.unary_call(Input {}).await.unwrap();
+    }
+
+    assert_eq!(accepted.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn lazy_channel_connects_in_background() {
+    let (endpoint, accepted) = run_server().await;
+
+    let channel = endpoint.warmup(2).connect_lazy();
+    channel.warmup().await.unwrap();
+    wait_for_connections(&accepted, 2).await;
+
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn warmup_reports_connection_errors() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let endpoint = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .warmup(2);
+
+    assert!(endpoint.connect().await.is_err());
+
+    let channel = endpoint.connect_lazy();
+    assert!(channel.warmup().await.is_err());
+}
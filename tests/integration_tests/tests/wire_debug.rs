@@ -0,0 +1,130 @@
+use integration_tests::pb::{test_client::TestClient, test_server, Input, Output};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{server::TcpIncoming, Endpoint, Server},
+    Request, Response, Status,
+};
+use tracing::{field::Field, Event, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+struct Svc;
+
+#[tonic::async_trait]
+impl test_server::Test for Svc {
+    async fn unary_call(&self, _: Request<Input>) -> Result<Response<Output>, Status> {
+        Ok(Response::new(Output {}))
+    }
+}
+
+/// The fields of an event logged under the `tonic::wire` target, by name.
+type Fields = HashMap<&'static str, String>;
+
+#[derive(Default)]
+struct Recorder(Arc<Mutex<Vec<Fields>>>);
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl tracing::field::Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if event.metadata().target() == "tonic::wire" {
+            let mut fields = Fields::new();
+            event.record(&mut Visitor(&mut fields));
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+}
+
+/// Makes a call, logging the frames of the client and server if `wire_debug` is set.
+async fn call(wire_debug: bool) -> Vec<Fields> {
+    let events = Arc::<Mutex<Vec<Fields>>>::default();
+    let _guard = tracing_subscriber::registry()
+        .with(Recorder(events.clone()))
+        .set_default();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        Server::builder()
+            .wire_debug(wire_debug)
+            .add_service(test_server::TestServer::new(Svc))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+            .unwrap();
+    });
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .wire_debug(wire_debug)
+        .connect()
+        .await
+        .unwrap();
+    TestClient::new(channel).unary_call(Input {}).await.unwrap();
+
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+fn find<'a>(events: &'a [Fields], side: &str, direction: &str, message: &str) -> Vec<&'a Fields> {
+    events
+        .iter()
+        .filter(|event| {
+            event["side"] == side && event["direction"] == direction && event["message"] == message
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn logs_frames_and_message_boundaries() {
+    let events = call(true).await;
+
+    for (side, direction) in [("client", "send"), ("server", "recv")] {
+        let settings = find(&events, side, direction, "SETTINGS");
+        assert_eq!(settings[0]["ack"], "false", "{side} {direction}");
+
+        let headers = find(&events, side, direction, "HEADERS");
+        assert_eq!(headers[0]["stream_id"], "1");
+        assert_eq!(headers[0]["end_headers"], "true");
+
+        let message = find(&events, side, direction, "gRPC message");
+        assert_eq!(message.len(), 1, "{side} {direction}");
+        assert_eq!(message[0]["stream_id"], "1");
+        assert_eq!(message[0]["length"], "0");
+        assert_eq!(message[0]["compressed"], "false");
+
+        let data = find(&events, side, direction, "DATA");
+        assert_eq!(data[0]["length"], "5");
+        assert_eq!(data.last().unwrap()["end_stream"], "true");
+    }
+
+    for (side, direction) in [("server", "send"), ("client", "recv")] {
+        // The response message, followed by the trailers ending the stream.
+        let message = find(&events, side, direction, "gRPC message");
+        assert_eq!(message.len(), 1, "{side} {direction}");
+        let headers = find(&events, side, direction, "HEADERS");
+        assert_eq!(headers.last().unwrap()["end_stream"], "true");
+    }
+
+    // Header blocks and payloads are never logged.
+    for event in &events {
+        assert!(event.values().all(|value| !value.contains("UnaryCall")));
+    }
+}
+
+#[tokio::test]
+async fn logs_nothing_unless_enabled() {
+    assert_eq!(call(false).await, []);
+}
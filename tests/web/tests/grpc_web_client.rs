@@ -0,0 +1,84 @@
+use std::net::SocketAddr;
+
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::StreamExt;
+use tonic::transport::Server;
+use tonic::Code;
+use tower::ServiceBuilder;
+
+use test_web::pb::{test_client::TestClient, test_server::TestServer, Input};
+use test_web::Svc;
+use tonic_web::{GrpcWebClientLayer, GrpcWebLayer};
+
+#[tokio::test]
+async fn binary_client() {
+    calls(GrpcWebClientLayer::new()).await;
+}
+
+#[tokio::test]
+async fn text_client() {
+    calls(GrpcWebClientLayer::new().text(true)).await;
+}
+
+async fn calls(layer: GrpcWebClientLayer) {
+    let url = spawn().await;
+
+    // An HTTP/1 only client.
+    let client = Client::builder(TokioExecutor::new()).build_http();
+    let svc = ServiceBuilder::new().layer(layer).service(client);
+    let mut client = TestClient::with_origin(svc, url.parse().unwrap());
+
+    let res = client
+        .unary_call(Input {
+            id: 1,
+            desc: "one".into(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(res.into_inner().desc, "one");
+
+    let status = client
+        .unary_call(Input {
+            id: 2,
+            desc: "boom".into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let stream = client
+        .server_stream(Input {
+            id: 3,
+            desc: "three".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let descs = stream
+        .map(|output| output.unwrap().desc)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(descs, ["1-three", "2-three"]);
+}
+
+async fn spawn() -> String {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let listener = TcpListener::bind(addr).await.expect("listener");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let listener_stream = TcpListenerStream::new(listener);
+
+    drop(tokio::spawn(async move {
+        Server::builder()
+            .accept_http1(true)
+            .layer(GrpcWebLayer::new())
+            .add_service(TestServer::new(Svc))
+            .serve_with_incoming(listener_stream)
+            .await
+            .unwrap()
+    }));
+
+    url
+}
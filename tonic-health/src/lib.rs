@@ -1,5 +1,8 @@
 //! A `tonic` based gRPC healthcheck implementation.
 //!
+//! `tonic` also ships a built-in implementation that does not depend on `prost` in its
+//! `health` module, behind the `health` feature.
+//!
 //! # Example
 //!
 //! An example can be found [here].
@@ -140,7 +140,7 @@ impl<U: Message + Default> Decoder for ProstDecoder<U> {
     }
 }
 
-fn from_decode_error(error: prost::DecodeError) -> Status {
+pub(crate) fn from_decode_error(error: prost::DecodeError) -> Status {
     // Map Protobuf parse errors to an INTERNAL status code, as per
     // https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
     Status::internal(error.to_string())
@@ -149,9 +149,9 @@ fn from_decode_error(error: prost::DecodeError) -> Status {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bytes::{Buf, BufMut, BytesMut};
-    use http_body::Body;
-    use http_body_util::BodyExt as _;
+    use bytes::{Buf, BufMut, Bytes, BytesMut};
+    use http_body::{Body, Frame};
+    use http_body_util::{BodyExt as _, StreamBody};
     use std::pin::pin;
     use tonic::codec::SingleMessageCompressionOverride;
     use tonic::codec::{EncodeBody, Streaming, HEADER_SIZE};
@@ -306,6 +306,75 @@ mod tests {
         assert!(body.is_end_stream());
     }
 
+    /// Encodes `messages` and decodes them back from the body split in chunks of `chunk_len`
+    /// bytes.
+    async fn roundtrip(messages: &[Vec<u8>], chunk_len: usize) -> Vec<Vec<u8>> {
+        let source = tokio_stream::iter(messages.iter().cloned().map(Ok::<_, Status>));
+        let body = EncodeBody::new_client(MockEncoder::default(), source, None, None);
+        let encoded = body.collect().await.unwrap().to_bytes();
+
+        let chunks = encoded
+            .chunks(chunk_len)
+            .map(|chunk| Ok::<_, Status>(Frame::data(Bytes::copy_from_slice(chunk))))
+            .collect::<Vec<_>>();
+        let body = StreamBody::new(tokio_stream::iter(chunks));
+
+        let mut stream = Streaming::new_request(WholeDecoder, body, None, None);
+        let mut decoded = Vec::new();
+        while let Some(message) = stream.message().await.unwrap() {
+            decoded.push(message);
+        }
+        decoded
+    }
+
+    #[tokio::test]
+    async fn roundtrip_small_and_large_messages() {
+        let messages = vec![
+            vec![1; 3],
+            vec![2; 20_000],
+            vec![],
+            vec![3; 256],
+            vec![4; 257],
+            vec![5; 10],
+        ];
+
+        for chunk_len in [1, 7, 300, 100_000] {
+            assert_eq!(
+                roundtrip(&messages, chunk_len).await,
+                messages,
+                "{chunk_len}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn encode_small_message() {
+        let source = tokio_stream::iter([Ok::<_, Status>(vec![7; 10])]);
+        let mut body = pin!(EncodeBody::new_client(
+            MockEncoder::default(),
+            source,
+            None,
+            None
+        ));
+
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        assert_eq!(frame.len(), HEADER_SIZE + 10);
+        assert_eq!(frame[..HEADER_SIZE], [0, 0, 0, 0, 10]);
+        assert!(body.frame().await.is_none());
+    }
+
+    /// Decodes messages of any length as they are.
+    struct WholeDecoder;
+
+    impl Decoder for WholeDecoder {
+        type Item = Vec<u8>;
+        type Error = Status;
+
+        fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+            Ok(Some(buf.copy_to_bytes(buf.remaining()).to_vec()))
+        }
+    }
+
     #[derive(Debug, Clone, Default)]
     struct MockEncoder {}
 
@@ -0,0 +1,301 @@
+use super::{find_method, method_path, DynamicCodec};
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use prost_reflect::{
+    DescriptorError, DescriptorPool, DynamicMessage, MethodDescriptor, ReflectMessage,
+};
+use prost_types::FileDescriptorSet;
+use tonic::body::Body;
+use tonic::client::{Grpc, GrpcService};
+use tonic::codec::{CompressionEncoding, Streaming};
+use tonic::{IntoRequest, IntoStreamingRequest, Response, Status};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A gRPC client that calls any method of a [`DescriptorPool`] with [`DynamicMessage`]s.
+///
+/// Methods are looked up by their full name, either as `package.Service.Method` or as the path
+/// `/package.Service/Method`, and called with the function matching their shape: [`unary`],
+/// [`server_streaming`], [`client_streaming`] or [`streaming`]. Calling a method that is not in
+/// the pool fails with an `UNIMPLEMENTED` status, and calling it with the wrong shape or a single
+/// request message of the wrong type with an `INVALID_ARGUMENT` status, before anything is sent.
+///
+/// ```no_run
+/// # use tonic::{body::Body, client::GrpcService};
+/// # async fn run<T>(channel: T, file_descriptor_set: &[u8]) -> Result<(), Box<dyn std::error::Error>>
+/// # where
+/// #     T: GrpcService<Body, ResponseBody = Body>,
+/// #     T::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+/// # {
+/// use tonic_prost::dynamic::{
+///     prost_reflect::{DynamicMessage, Value},
+///     DynamicClient,
+/// };
+///
+/// let mut client = DynamicClient::from_encoded_file_descriptor_set(channel, file_descriptor_set)?;
+///
+/// let method = client.method("helloworld.Greeter.SayHello").unwrap();
+/// let mut request = DynamicMessage::new(method.input());
+/// request.set_field_by_name("name", Value::String("Tonic".to_owned()));
+///
+/// let response = client.unary("helloworld.Greeter.SayHello", request).await?;
+/// println!("{:?}", response.get_ref().get_field_by_name("message"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`unary`]: Self::unary
+/// [`server_streaming`]: Self::server_streaming
+/// [`client_streaming`]: Self::client_streaming
+/// [`streaming`]: Self::streaming
+#[derive(Debug, Clone)]
+pub struct DynamicClient<T> {
+    inner: Grpc<T>,
+    pool: DescriptorPool,
+}
+
+impl<T> DynamicClient<T> {
+    /// Create a client calling the methods of `pool` over `inner`.
+    pub fn new(inner: T, pool: DescriptorPool) -> Self {
+        Self {
+            inner: Grpc::new(inner),
+            pool,
+        }
+    }
+
+    /// Create a client calling the methods described by `file_descriptor_set` over `inner`.
+    pub fn from_file_descriptor_set(
+        inner: T,
+        file_descriptor_set: FileDescriptorSet,
+    ) -> Result<Self, DescriptorError> {
+        let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set)?;
+        Ok(Self::new(inner, pool))
+    }
+
+    /// Create a client calling the methods described by the encoded `file_descriptor_set` over
+    /// `inner`, e.g. one included with `tonic::include_file_descriptor_set!`.
+    pub fn from_encoded_file_descriptor_set(
+        inner: T,
+        file_descriptor_set: &[u8],
+    ) -> Result<Self, DescriptorError> {
+        let pool = DescriptorPool::decode(file_descriptor_set)?;
+        Ok(Self::new(inner, pool))
+    }
+
+    /// The descriptors of the methods this client calls.
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+
+    /// Finds the method with this full name, either as `package.Service.Method` or as the path
+    /// `/package.Service/Method`.
+    pub fn method(&self, name: &str) -> Option<MethodDescriptor> {
+        find_method(&self.pool, name)
+    }
+
+    /// Compress requests with the given encoding.
+    ///
+    /// This requires the server to support it otherwise it might respond with an
+    /// error.
+    #[must_use]
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.inner = self.inner.send_compressed(encoding);
+        self
+    }
+
+    /// Enable decompressing responses.
+    #[must_use]
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.inner = self.inner.accept_compressed(encoding);
+        self
+    }
+
+    /// Limits the maximum size of a decoded message.
+    ///
+    /// Default: `4MB`
+    #[must_use]
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_decoding_message_size(limit);
+        self
+    }
+
+    /// Limits the maximum size of an encoded message.
+    ///
+    /// Default: `usize::MAX`
+    #[must_use]
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_encoding_message_size(limit);
+        self
+    }
+
+    /// Looks up `name`, checking that the method has the expected shape.
+    fn prepare(
+        &self,
+        name: &str,
+        client_streaming: bool,
+        server_streaming: bool,
+    ) -> Result<(MethodDescriptor, PathAndQuery), Status> {
+        let method = self
+            .method(name)
+            .ok_or_else(|| Status::unimplemented(format!("unknown method {name}")))?;
+
+        if method.is_client_streaming() != client_streaming
+            || method.is_server_streaming() != server_streaming
+        {
+            return Err(Status::invalid_argument(format!(
+                "method {} is {}, not {}",
+                method.full_name(),
+                shape(method.is_client_streaming(), method.is_server_streaming()),
+                shape(client_streaming, server_streaming),
+            )));
+        }
+
+        let path = PathAndQuery::try_from(method_path(&method))
+            .map_err(|_| Status::invalid_argument(format!("invalid method name {name}")))?;
+        Ok((method, path))
+    }
+}
+
+impl<T> DynamicClient<T>
+where
+    T: GrpcService<Body>,
+    T::Error: Into<BoxError>,
+    T::ResponseBody: http_body::Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as http_body::Body>::Error: Into<BoxError> + Send,
+{
+    /// Create a client calling the services of the server behind `inner`, whose descriptors are
+    /// fetched with the gRPC server reflection protocol, like `grpcurl` does.
+    ///
+    /// The server must serve the reflection service, either `v1` or `v1alpha`, with the
+    /// descriptors of all its services.
+    pub async fn discover(inner: T) -> Result<Self, Status> {
+        let mut client = Self::new(inner, DescriptorPool::new());
+        client.pool = super::discover::discover(&mut client.inner).await?;
+        Ok(client)
+    }
+
+    /// Calls the unary method `name`.
+    pub async fn unary(
+        &mut self,
+        name: &str,
+        request: impl IntoRequest<DynamicMessage>,
+    ) -> Result<Response<DynamicMessage>, Status> {
+        let (method, path) = self.prepare(name, false, false)?;
+        let request = request.into_request();
+        check_input(&method, request.get_ref())?;
+        self.ready().await?;
+        let codec = DynamicCodec::new(method.output());
+        self.inner.unary(request, path, codec).await
+    }
+
+    /// Calls the server streaming method `name`.
+    pub async fn server_streaming(
+        &mut self,
+        name: &str,
+        request: impl IntoRequest<DynamicMessage>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status> {
+        let (method, path) = self.prepare(name, false, true)?;
+        let request = request.into_request();
+        check_input(&method, request.get_ref())?;
+        self.ready().await?;
+        let codec = DynamicCodec::new(method.output());
+        self.inner.server_streaming(request, path, codec).await
+    }
+
+    /// Calls the client streaming method `name`.
+    ///
+    /// The request messages must be of the input type of the method.
+    pub async fn client_streaming(
+        &mut self,
+        name: &str,
+        request: impl IntoStreamingRequest<Message = DynamicMessage>,
+    ) -> Result<Response<DynamicMessage>, Status> {
+        let (method, path) = self.prepare(name, true, false)?;
+        self.ready().await?;
+        let codec = DynamicCodec::new(method.output());
+        self.inner
+            .client_streaming(request.into_streaming_request(), path, codec)
+            .await
+    }
+
+    /// Calls the bidirectional streaming method `name`.
+    ///
+    /// The request messages must be of the input type of the method.
+    pub async fn streaming(
+        &mut self,
+        name: &str,
+        request: impl IntoStreamingRequest<Message = DynamicMessage>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status> {
+        let (method, path) = self.prepare(name, true, true)?;
+        self.ready().await?;
+        let codec = DynamicCodec::new(method.output());
+        self.inner
+            .streaming(request.into_streaming_request(), path, codec)
+            .await
+    }
+
+    async fn ready(&mut self) -> Result<(), Status> {
+        self.inner
+            .ready()
+            .await
+            .map_err(|e| Status::unknown(format!("Service was not ready: {}", e.into())))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> DynamicClient<T>
+where
+    T: GrpcService<Body>,
+    T::Error: Into<BoxError>,
+    T::ResponseBody: http_body::Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as http_body::Body>::Error: Into<BoxError> + Send,
+{
+    /// Calls the unary method `name` with a request message in the
+    /// [JSON mapping](super::json) of protobuf.
+    ///
+    /// A request message that does not match the input type of the method fails with an
+    /// `INVALID_ARGUMENT` status.
+    pub async fn unary_json(
+        &mut self,
+        name: &str,
+        request: impl IntoRequest<serde_json::Value>,
+    ) -> Result<Response<serde_json::Value>, Status> {
+        let method = self
+            .method(name)
+            .ok_or_else(|| Status::unimplemented(format!("unknown method {name}")))?;
+        let (metadata, extensions, message) = request.into_request().into_parts();
+        let message = super::json::from_json(method.input(), &message)?;
+        let request = tonic::Request::from_parts(metadata, extensions, message);
+
+        let response = self.unary(name, request).await?;
+        let (metadata, message, extensions) = response.into_parts();
+        Ok(Response::from_parts(
+            metadata,
+            super::json::to_json(&message),
+            extensions,
+        ))
+    }
+}
+
+/// Checks that a request message is of the input type of `method`.
+fn check_input(method: &MethodDescriptor, message: &DynamicMessage) -> Result<(), Status> {
+    let input = method.input();
+    if message.descriptor() != input {
+        return Err(Status::invalid_argument(format!(
+            "method {} expects a {} message, not {}",
+            method.full_name(),
+            input.full_name(),
+            message.descriptor().full_name(),
+        )));
+    }
+    Ok(())
+}
+
+fn shape(client_streaming: bool, server_streaming: bool) -> &'static str {
+    match (client_streaming, server_streaming) {
+        (false, false) => "unary",
+        (false, true) => "server streaming",
+        (true, false) => "client streaming",
+        (true, true) => "bidirectional streaming",
+    }
+}
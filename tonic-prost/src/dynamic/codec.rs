@@ -0,0 +1,70 @@
+use crate::codec::{from_decode_error, ProstEncoder};
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use tonic::codec::{BufferSettings, Codec, DecodeBuf, Decoder};
+use tonic::Status;
+
+/// A [`Codec`] that implements `application/grpc+proto` for [`DynamicMessage`]s.
+///
+/// The messages it decodes are described by the [`MessageDescriptor`] it is created with, e.g. the
+/// output of the method on a client. The messages it encodes describe themselves.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec {
+    decode: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    /// Create a codec decoding messages described by `decode`.
+    pub fn new(decode: MessageDescriptor) -> Self {
+        Self { decode }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+
+    type Encoder = ProstEncoder<DynamicMessage>;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        ProstEncoder::new(BufferSettings::default())
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder::new(self.decode.clone(), BufferSettings::default())
+    }
+}
+
+/// A [`Decoder`] that knows how to decode the [`DynamicMessage`]s of a [`MessageDescriptor`].
+#[derive(Debug, Clone)]
+pub struct DynamicDecoder {
+    descriptor: MessageDescriptor,
+    buffer_settings: BufferSettings,
+}
+
+impl DynamicDecoder {
+    /// Get a new decoder of the messages described by `descriptor`, with explicit buffer settings.
+    pub fn new(descriptor: MessageDescriptor, buffer_settings: BufferSettings) -> Self {
+        Self {
+            descriptor,
+            buffer_settings,
+        }
+    }
+}
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let item = DynamicMessage::decode(self.descriptor.clone(), buf)
+            .map(Option::Some)
+            .map_err(from_decode_error)?;
+
+        Ok(item)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        self.buffer_settings
+    }
+}
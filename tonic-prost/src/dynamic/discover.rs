@@ -0,0 +1,213 @@
+//! Fetching of descriptors with the gRPC server reflection protocol.
+
+use crate::ProstCodec;
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use prost::Message;
+use prost_reflect::DescriptorPool;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use std::collections::{HashMap, HashSet};
+use tonic::body::Body;
+use tonic::client::{Grpc, GrpcService};
+use tonic::{Code, Request, Status};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const PATHS: [&str; 2] = [
+    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+    "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+];
+
+/// The messages of `grpc/reflection/v1/reflection.proto` this client uses, which are the same in
+/// `v1alpha`.
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionRequest {
+    #[prost(string, tag = "1")]
+    host: String,
+    #[prost(oneof = "MessageRequest", tags = "3, 4, 7")]
+    message_request: Option<MessageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageRequest {
+    #[prost(string, tag = "3")]
+    FileByFilename(String),
+    #[prost(string, tag = "4")]
+    FileContainingSymbol(String),
+    #[prost(string, tag = "7")]
+    ListServices(String),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionResponse {
+    #[prost(oneof = "MessageResponse", tags = "4, 6, 7")]
+    message_response: Option<MessageResponse>,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageResponse {
+    #[prost(message, tag = "4")]
+    FileDescriptorResponse(FileDescriptorResponse),
+    #[prost(message, tag = "6")]
+    ListServicesResponse(ListServiceResponse),
+    #[prost(message, tag = "7")]
+    ErrorResponse(ErrorResponse),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorResponse {
+    #[prost(bytes = "bytes", repeated, tag = "1")]
+    file_descriptor_proto: Vec<Bytes>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ListServiceResponse {
+    #[prost(message, repeated, tag = "1")]
+    service: Vec<ServiceResponse>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceResponse {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorResponse {
+    #[prost(int32, tag = "1")]
+    error_code: i32,
+    #[prost(string, tag = "2")]
+    error_message: String,
+}
+
+/// Fetches the descriptors of all the services of a server, and of their dependencies.
+pub(crate) async fn discover<T>(grpc: &mut Grpc<T>) -> Result<DescriptorPool, Status>
+where
+    T: GrpcService<Body>,
+    T::Error: Into<BoxError>,
+    T::ResponseBody: http_body::Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as http_body::Body>::Error: Into<BoxError> + Send,
+{
+    let mut reflection = Reflection { grpc, path: 0 };
+
+    let services = match reflection
+        .call(MessageRequest::ListServices(String::new()))
+        .await?
+    {
+        MessageResponse::ListServicesResponse(list) => list.service,
+        _ => return Err(unexpected_response()),
+    };
+
+    let mut files = HashMap::new();
+    for service in services {
+        let request = MessageRequest::FileContainingSymbol(service.name);
+        reflection.fetch_files(request, &mut files).await?;
+    }
+
+    // Servers usually send the dependencies of a file along with it, but are not required to.
+    loop {
+        let missing = files
+            .values()
+            .flat_map(|file: &FileDescriptorProto| &file.dependency)
+            .filter(|dependency| !files.contains_key(*dependency))
+            .cloned()
+            .collect::<HashSet<_>>();
+        if missing.is_empty() {
+            break;
+        }
+        for name in missing {
+            let request = MessageRequest::FileByFilename(name);
+            reflection.fetch_files(request, &mut files).await?;
+        }
+    }
+
+    let set = FileDescriptorSet {
+        file: files.into_values().collect(),
+    };
+    DescriptorPool::from_file_descriptor_set(set)
+        .map_err(|error| Status::internal(format!("invalid descriptors from reflection: {error}")))
+}
+
+struct Reflection<'a, T> {
+    grpc: &'a mut Grpc<T>,
+    /// The index of the version of the reflection service in `PATHS`.
+    path: usize,
+}
+
+impl<T> Reflection<'_, T>
+where
+    T: GrpcService<Body>,
+    T::Error: Into<BoxError>,
+    T::ResponseBody: http_body::Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as http_body::Body>::Error: Into<BoxError> + Send,
+{
+    async fn fetch_files(
+        &mut self,
+        request: MessageRequest,
+        files: &mut HashMap<String, FileDescriptorProto>,
+    ) -> Result<(), Status> {
+        let MessageResponse::FileDescriptorResponse(response) = self.call(request).await? else {
+            return Err(unexpected_response());
+        };
+
+        for encoded in response.file_descriptor_proto {
+            let file = FileDescriptorProto::decode(encoded)
+                .map_err(|error| Status::internal(error.to_string()))?;
+            files.insert(file.name().to_owned(), file);
+        }
+        Ok(())
+    }
+
+    /// Sends a single request on its own stream, falling back to `v1alpha` if the server does not
+    /// implement `v1`.
+    async fn call(&mut self, request: MessageRequest) -> Result<MessageResponse, Status> {
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(request),
+        };
+
+        loop {
+            self.grpc
+                .ready()
+                .await
+                .map_err(|e| Status::unknown(format!("Service was not ready: {}", e.into())))?;
+
+            let path = PathAndQuery::from_static(PATHS[self.path]);
+            let codec = ProstCodec::<ServerReflectionRequest, ServerReflectionResponse>::default();
+            let messages = tokio_stream::once(request.clone());
+            let result = match self
+                .grpc
+                .streaming(Request::new(messages), path, codec)
+                .await
+            {
+                Ok(response) => response.into_inner().message().await,
+                Err(status) => Err(status),
+            };
+
+            match result {
+                Err(status)
+                    if status.code() == Code::Unimplemented && self.path + 1 < PATHS.len() =>
+                {
+                    self.path += 1;
+                }
+                Err(status) => return Err(status),
+                Ok(None) => return Err(Status::internal("Missing response message.")),
+                Ok(Some(response)) => {
+                    return match response.message_response {
+                        Some(MessageResponse::ErrorResponse(error)) => Err(Status::new(
+                            Code::from(error.error_code),
+                            error.error_message,
+                        )),
+                        Some(response) => Ok(response),
+                        None => Err(unexpected_response()),
+                    };
+                }
+            }
+        }
+    }
+}
+
+fn unexpected_response() -> Status {
+    Status::internal("unexpected server reflection response")
+}
@@ -0,0 +1,455 @@
+//! Conversion of [`DynamicMessage`]s to and from the JSON mapping of protobuf.
+//!
+//! This follows the [proto3 JSON mapping]: fields are named after their JSON name, 64 bit
+//! integers are strings, bytes are base64 encoded and enum values are named. When parsing, fields
+//! may also be named after their protobuf name, integers may be strings, enum values may be
+//! numbers and `null` leaves a field unset.
+//!
+//! The well-known types, such as `google.protobuf.Timestamp`, are mapped like any other message
+//! rather than with their special JSON representation.
+//!
+//! [proto3 JSON mapping]: https://protobuf.dev/programming-guides/json/
+
+use base64::engine::{general_purpose::STANDARD, general_purpose::STANDARD_NO_PAD};
+use base64::Engine as _;
+use prost_reflect::{
+    DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    ReflectMessage, Value,
+};
+use serde_json::{Number, Value as Json};
+use std::collections::HashMap;
+use std::fmt;
+use tonic::Status;
+
+/// Converts `message` to JSON.
+pub fn to_json(message: &DynamicMessage) -> Json {
+    let fields = message
+        .fields()
+        .map(|(field, value)| (field.json_name().to_owned(), field_to_json(&field, value)))
+        .collect();
+    Json::Object(fields)
+}
+
+/// Parses a message described by `descriptor` from JSON.
+pub fn from_json(descriptor: MessageDescriptor, json: &Json) -> Result<DynamicMessage, JsonError> {
+    let Json::Object(object) = json else {
+        return Err(JsonError::new("expected an object"));
+    };
+
+    let mut message = DynamicMessage::new(descriptor);
+    for (name, json) in object {
+        let field = find_field(&message.descriptor(), name)?;
+        if json.is_null() {
+            continue;
+        }
+
+        let value = field_from_json(&field, json).map_err(|error| error.within(name))?;
+        message.set_field(&field, value);
+    }
+    Ok(message)
+}
+
+/// An error parsing a message from JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    path: String,
+    message: String,
+}
+
+impl JsonError {
+    fn new(message: impl Into<String>) -> Self {
+        JsonError {
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+
+    fn within(mut self, field: &str) -> Self {
+        self.path = if self.path.is_empty() {
+            field.to_owned()
+        } else {
+            format!("{field}.{}", self.path)
+        };
+        self
+    }
+
+    /// The path of the field that could not be parsed, e.g. `address.zip_code`, or an empty
+    /// string for the message itself.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<JsonError> for Status {
+    fn from(error: JsonError) -> Self {
+        Status::invalid_argument(format!("invalid JSON message: {error}"))
+    }
+}
+
+fn field_to_json(field: &FieldDescriptor, value: &Value) -> Json {
+    match value {
+        Value::List(values) => Json::Array(
+            values
+                .iter()
+                .map(|value| value_to_json(&field.kind(), value))
+                .collect(),
+        ),
+        Value::Map(entries) => {
+            let kind = field.kind();
+            let value_kind = kind
+                .as_message()
+                .map(|entry| entry.map_entry_value_field().kind())
+                .unwrap_or(kind);
+            let entries = entries
+                .iter()
+                .map(|(key, value)| (map_key_to_string(key), value_to_json(&value_kind, value)))
+                .collect();
+            Json::Object(entries)
+        }
+        value => value_to_json(&field.kind(), value),
+    }
+}
+
+fn value_to_json(kind: &Kind, value: &Value) -> Json {
+    match value {
+        Value::Bool(value) => Json::Bool(*value),
+        Value::I32(value) => Json::from(*value),
+        Value::U32(value) => Json::from(*value),
+        Value::I64(value) => Json::String(value.to_string()),
+        Value::U64(value) => Json::String(value.to_string()),
+        Value::F32(value) => float_to_json(f64::from(*value)),
+        Value::F64(value) => float_to_json(*value),
+        Value::String(value) => Json::String(value.clone()),
+        Value::Bytes(value) => Json::String(STANDARD.encode(value)),
+        Value::EnumNumber(number) => match kind.as_enum().and_then(|e| e.get_value(*number)) {
+            Some(value) => Json::String(value.name().to_owned()),
+            None => Json::from(*number),
+        },
+        Value::Message(message) => to_json(message),
+        Value::List(_) | Value::Map(_) => unreachable!("nested repeated fields"),
+    }
+}
+
+fn float_to_json(value: f64) -> Json {
+    match Number::from_f64(value) {
+        Some(number) => Json::Number(number),
+        None if value.is_nan() => Json::String("NaN".to_owned()),
+        None if value > 0.0 => Json::String("Infinity".to_owned()),
+        None => Json::String("-Infinity".to_owned()),
+    }
+}
+
+fn map_key_to_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(key) => key.to_string(),
+        MapKey::I32(key) => key.to_string(),
+        MapKey::I64(key) => key.to_string(),
+        MapKey::U32(key) => key.to_string(),
+        MapKey::U64(key) => key.to_string(),
+        MapKey::String(key) => key.clone(),
+    }
+}
+
+fn field_from_json(field: &FieldDescriptor, json: &Json) -> Result<Value, JsonError> {
+    let kind = field.kind();
+    if field.is_map() {
+        let Json::Object(object) = json else {
+            return Err(JsonError::new("expected an object"));
+        };
+        let entry = kind.as_message().expect("map fields are messages").clone();
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+
+        let mut entries = HashMap::with_capacity(object.len());
+        for (key, json) in object {
+            let value = value_from_json(&value_kind, json).map_err(|error| error.within(key))?;
+            entries.insert(map_key_from_string(&key_kind, key)?, value);
+        }
+        Ok(Value::Map(entries))
+    } else if field.is_list() {
+        let Json::Array(array) = json else {
+            return Err(JsonError::new("expected an array"));
+        };
+        let values = array
+            .iter()
+            .map(|json| value_from_json(&kind, json))
+            .collect::<Result<_, _>>()?;
+        Ok(Value::List(values))
+    } else {
+        value_from_json(&kind, json)
+    }
+}
+
+fn value_from_json(kind: &Kind, json: &Json) -> Result<Value, JsonError> {
+    let value = match kind {
+        Kind::Double => Value::F64(float_from_json(json)?),
+        Kind::Float => Value::F32(float_from_json(json)? as f32),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(int_from_json(json)?),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(int_from_json(json)?),
+        Kind::Uint32 | Kind::Fixed32 => Value::U32(int_from_json(json)?),
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(int_from_json(json)?),
+        Kind::Bool => match json {
+            Json::Bool(value) => Value::Bool(*value),
+            _ => return Err(JsonError::new("expected a boolean")),
+        },
+        Kind::String => match json {
+            Json::String(value) => Value::String(value.clone()),
+            _ => return Err(JsonError::new("expected a string")),
+        },
+        Kind::Bytes => match json {
+            Json::String(value) => Value::Bytes(bytes_from_base64(value)?.into()),
+            _ => return Err(JsonError::new("expected a base64 string")),
+        },
+        Kind::Enum(descriptor) => Value::EnumNumber(enum_from_json(descriptor, json)?),
+        Kind::Message(descriptor) => Value::Message(from_json(descriptor.clone(), json)?),
+    };
+    Ok(value)
+}
+
+fn int_from_json<I>(json: &Json) -> Result<I, JsonError>
+where
+    I: TryFrom<i64> + TryFrom<u64> + std::str::FromStr,
+{
+    let out_of_range = || JsonError::new(format!("{json} is out of range"));
+    match json {
+        Json::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                I::try_from(value).map_err(|_| out_of_range())
+            } else if let Some(value) = number.as_i64() {
+                I::try_from(value).map_err(|_| out_of_range())
+            } else {
+                // Integers may be written with an exponent or a zero fraction, e.g. `1e3`.
+                let value = number.as_f64().unwrap_or(f64::NAN);
+                if value.fract() != 0.0 || value.abs() > i64::MAX as f64 {
+                    return Err(JsonError::new(format!("{json} is not an integer")));
+                }
+                I::try_from(value as i64).map_err(|_| out_of_range())
+            }
+        }
+        Json::String(value) => value
+            .parse()
+            .map_err(|_| JsonError::new(format!("{json} is not an integer"))),
+        _ => Err(JsonError::new("expected an integer")),
+    }
+}
+
+fn float_from_json(json: &Json) -> Result<f64, JsonError> {
+    match json {
+        Json::Number(number) => Ok(number.as_f64().unwrap_or(f64::NAN)),
+        Json::String(value) => match value.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            value => value
+                .parse()
+                .map_err(|_| JsonError::new(format!("{json} is not a number"))),
+        },
+        _ => Err(JsonError::new("expected a number")),
+    }
+}
+
+fn enum_from_json(descriptor: &EnumDescriptor, json: &Json) -> Result<i32, JsonError> {
+    match json {
+        Json::String(name) => descriptor
+            .get_value_by_name(name)
+            .map(|value| value.number())
+            .ok_or_else(|| {
+                JsonError::new(format!(
+                    "unknown value {name} of {}",
+                    descriptor.full_name()
+                ))
+            }),
+        Json::Number(_) => int_from_json(json),
+        _ => Err(JsonError::new("expected an enum value")),
+    }
+}
+
+/// Decodes standard or URL-safe base64, with or without padding.
+fn bytes_from_base64(value: &str) -> Result<Vec<u8>, JsonError> {
+    let value = value
+        .trim_end_matches('=')
+        .replace('-', "+")
+        .replace('_', "/");
+    STANDARD_NO_PAD
+        .decode(value)
+        .map_err(|error| JsonError::new(format!("invalid base64: {error}")))
+}
+
+fn map_key_from_string(kind: &Kind, key: &str) -> Result<MapKey, JsonError> {
+    let invalid = || JsonError::new(format!("invalid map key {key}"));
+    let key = match kind {
+        Kind::Bool => MapKey::Bool(key.parse().map_err(|_| invalid())?),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            MapKey::I32(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            MapKey::I64(key.parse().map_err(|_| invalid())?)
+        }
+        Kind::Uint32 | Kind::Fixed32 => MapKey::U32(key.parse().map_err(|_| invalid())?),
+        Kind::Uint64 | Kind::Fixed64 => MapKey::U64(key.parse().map_err(|_| invalid())?),
+        Kind::String => MapKey::String(key.to_owned()),
+        _ => return Err(invalid()),
+    };
+    Ok(key)
+}
+
+/// Converts the value of `field` of `message` to JSON, even if it is not set.
+pub(crate) fn field_value_to_json(message: &DynamicMessage, field: &FieldDescriptor) -> Json {
+    field_to_json(field, &message.get_field(field))
+}
+
+/// Sets the field `name` of `message` to a value parsed from JSON.
+pub(crate) fn set_field_from_json(
+    message: &mut DynamicMessage,
+    name: &str,
+    json: &Json,
+) -> Result<(), JsonError> {
+    let field = find_field(&message.descriptor(), name)?;
+    let value = field_from_json(&field, json).map_err(|error| error.within(name))?;
+    message.set_field(&field, value);
+    Ok(())
+}
+
+/// Sets the scalar field at `path` of `message`, e.g. `book.title`, to a value parsed from a URL
+/// parameter, appending it if the field is repeated.
+pub(crate) fn set_field_from_param(
+    message: &mut DynamicMessage,
+    path: &str,
+    param: &str,
+) -> Result<(), JsonError> {
+    let mut names = path.split('.');
+    let last = names.next_back().unwrap_or_default();
+
+    let mut message = message;
+    for name in names {
+        let field = find_field(&message.descriptor(), name).map_err(|e| e.within(path))?;
+        if field.is_list() || field.is_map() || field.kind().as_message().is_none() {
+            return Err(JsonError::new(format!("{name} is not a message")).within(path));
+        }
+        message = message
+            .get_field_mut(&field)
+            .as_message_mut()
+            .expect("message fields hold messages");
+    }
+
+    let field = find_field(&message.descriptor(), last).map_err(|e| e.within(path))?;
+    if field.is_map() {
+        return Err(JsonError::new("maps cannot be set from parameters").within(path));
+    }
+    let value = param_value(&field.kind(), param).map_err(|error| error.within(path))?;
+    match message.get_field_mut(&field) {
+        Value::List(values) => values.push(value),
+        field => *field = value,
+    }
+    Ok(())
+}
+
+fn find_field(descriptor: &MessageDescriptor, name: &str) -> Result<FieldDescriptor, JsonError> {
+    descriptor
+        .get_field_by_name(name)
+        .or_else(|| descriptor.get_field_by_json_name(name))
+        .ok_or_else(|| JsonError::new(format!("unknown field {name}")))
+}
+
+fn param_value(kind: &Kind, param: &str) -> Result<Value, JsonError> {
+    match kind {
+        Kind::Bool => match param {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(JsonError::new("expected a boolean")),
+        },
+        Kind::Enum(descriptor) => match param.parse() {
+            Ok(number) => Ok(Value::EnumNumber(number)),
+            Err(_) => {
+                enum_from_json(descriptor, &Json::String(param.to_owned())).map(Value::EnumNumber)
+            }
+        },
+        Kind::Message(_) => Err(JsonError::new("messages cannot be set from parameters")),
+        kind => value_from_json(kind, &Json::String(param.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::DescriptorPool;
+    use serde_json::json;
+
+    fn field_descriptor_proto() -> MessageDescriptor {
+        DescriptorPool::global()
+            .get_message_by_name("google.protobuf.FieldDescriptorProto")
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = json!({
+            "name": "id",
+            "number": 7,
+            "label": "LABEL_REPEATED",
+            "jsonName": "id",
+            "options": { "packed": true },
+        });
+
+        let message = from_json(field_descriptor_proto(), &json).unwrap();
+        assert_eq!(
+            message.get_field_by_name("label").unwrap().as_enum_number(),
+            Some(3)
+        );
+        assert_eq!(to_json(&message), json);
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let json = json!({
+            "json_name": "id",
+            "number": "7",
+            "type": 9,
+            "extendee": null,
+        });
+
+        let message = from_json(field_descriptor_proto(), &json).unwrap();
+        assert_eq!(
+            to_json(&message),
+            json!({ "jsonName": "id", "number": 7, "type": "TYPE_STRING" })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = from_json(field_descriptor_proto(), &json!({ "number": 1.5 })).unwrap_err();
+        assert_eq!(error.path(), "number");
+        assert_eq!(error.to_string(), "number: 1.5 is not an integer");
+
+        let error = from_json(
+            field_descriptor_proto(),
+            &json!({ "options": { "packed": "yes" } }),
+        )
+        .unwrap_err();
+        assert_eq!(error.path(), "options.packed");
+
+        let error = from_json(field_descriptor_proto(), &json!({ "size": 1 })).unwrap_err();
+        assert_eq!(error.to_string(), "unknown field size");
+        assert_eq!(Status::from(error).code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn bytes_and_floats() {
+        assert_eq!(bytes_from_base64("aGk-_w==").unwrap(), b"hi>\xff");
+        assert_eq!(bytes_from_base64("aGk+/w").unwrap(), b"hi>\xff");
+        assert_eq!(float_to_json(f64::NEG_INFINITY), json!("-Infinity"));
+        assert!(float_from_json(&json!("NaN")).unwrap().is_nan());
+    }
+}
@@ -0,0 +1,46 @@
+//! Calling and serving gRPC methods that are only known at runtime from their protobuf descriptors.
+//!
+//! Messages are [`DynamicMessage`](prost_reflect::DynamicMessage)s described by a [`DescriptorPool`], typically built from the
+//! `FileDescriptorSet` of the services, so that no code needs to be generated for them. This is
+//! the building block for tools such as command line clients and gateways.
+//!
+//! See [`DynamicClient`] and [`DynamicService`] for more details.
+
+mod client;
+mod codec;
+mod discover;
+#[cfg(feature = "json")]
+pub mod json;
+mod server;
+#[cfg(feature = "json")]
+pub mod transcoding;
+
+pub use client::DynamicClient;
+pub use codec::{DynamicCodec, DynamicDecoder};
+pub use server::{DynamicService, DynamicStream};
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use transcoding::{Transcoding, TranscodingLayer};
+
+// Re-export prost-reflect since its types are part of the API.
+pub use prost_reflect;
+
+use prost_reflect::{DescriptorPool, MethodDescriptor};
+
+/// Finds the method with this full name in `pool`, either as `package.Service.Method` or as the
+/// path `/package.Service/Method`.
+pub fn find_method(pool: &DescriptorPool, name: &str) -> Option<MethodDescriptor> {
+    let (service, method) = match name.strip_prefix('/') {
+        Some(path) => path.split_once('/')?,
+        None => name.rsplit_once('.')?,
+    };
+
+    pool.get_service_by_name(service)?
+        .methods()
+        .find(|candidate| candidate.name() == method)
+}
+
+/// The path of the requests to `method`, e.g. `/package.Service/Method`.
+pub fn method_path(method: &MethodDescriptor) -> String {
+    format!("/{}/{}", method.parent_service().full_name(), method.name())
+}
@@ -0,0 +1,294 @@
+use super::{find_method, DynamicCodec};
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor, ReflectMessage};
+use std::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio_stream::{Stream, StreamExt};
+use tonic::body::Body;
+use tonic::codec::{CompressionEncoding, EnabledCompressionEncodings, Streaming};
+use tonic::server::{
+    ClientStreamingService, Grpc, ServerStreamingService, StreamingService, UnaryService,
+};
+use tonic::{Request, Response, Status};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// A stream of [`DynamicMessage`]s, the requests and responses of a [`DynamicService`].
+pub type DynamicStream = Pin<Box<dyn Stream<Item = Result<DynamicMessage, Status>> + Send>>;
+
+/// A gRPC service that serves any method of a [`DescriptorPool`] with a single handler.
+///
+/// The handler is called with the descriptor of the method and a request whose messages are
+/// decoded as [`DynamicMessage`]s of its input type, and returns the response messages, which must
+/// be of its output type. Every method shape goes through the same handler: the request of a
+/// method that is not client streaming has a single message, and only the first response message
+/// of a method that is not server streaming is sent. This is the building block for proxies and
+/// mock servers whose services are only known at runtime.
+///
+/// Since its services are not known at compile time, a `DynamicService` is not a
+/// `NamedService`, and is served as the fallback service of routes, with
+/// `Routes::fallback_service`. It answers the requests for methods that are not in its pool with
+/// an `UNIMPLEMENTED` status.
+///
+/// ```
+/// # use tonic_prost::dynamic::{prost_reflect::DescriptorPool, DynamicStream};
+/// # let pool = DescriptorPool::global();
+/// use tonic::Response;
+/// use tonic_prost::dynamic::DynamicService;
+///
+/// // Echo every request message back.
+/// let svc = DynamicService::new(pool, |_method, request: tonic::Request<DynamicStream>| async move {
+///     Ok(Response::new(request.into_inner()))
+/// });
+/// // Serve it with `Routes::default().fallback_service(svc)`.
+/// ```
+pub struct DynamicService<F> {
+    pool: DescriptorPool,
+    handler: Arc<F>,
+    accept_compression_encodings: EnabledCompressionEncodings,
+    send_compression_encodings: EnabledCompressionEncodings,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+}
+
+impl<F, Fut> DynamicService<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    /// Create a service serving the methods of `pool` with `handler`.
+    pub fn new(pool: DescriptorPool, handler: F) -> Self {
+        Self {
+            pool,
+            handler: Arc::new(handler),
+            accept_compression_encodings: Default::default(),
+            send_compression_encodings: Default::default(),
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+        }
+    }
+}
+
+impl<F> DynamicService<F> {
+    /// The descriptors of the methods this service serves.
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+
+    /// Enable decompressing requests with the given encoding.
+    #[must_use]
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.accept_compression_encodings.enable(encoding);
+        self
+    }
+
+    /// Compress responses with the given encoding, if the client supports it.
+    #[must_use]
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.send_compression_encodings.enable(encoding);
+        self
+    }
+
+    /// Limits the maximum size of a decoded message.
+    ///
+    /// Default: `4MB`
+    #[must_use]
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the maximum size of an encoded message.
+    ///
+    /// Default: `usize::MAX`
+    #[must_use]
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+}
+
+impl<F> Clone for DynamicService<F> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            handler: self.handler.clone(),
+            accept_compression_encodings: self.accept_compression_encodings,
+            send_compression_encodings: self.send_compression_encodings,
+            max_decoding_message_size: self.max_decoding_message_size,
+            max_encoding_message_size: self.max_encoding_message_size,
+        }
+    }
+}
+
+impl<F> fmt::Debug for DynamicService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicService")
+            .field("pool", &self.pool)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut, B> tower_service::Service<http::Request<B>> for DynamicService<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<BoxError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let Some(method) = find_method(&self.pool, req.uri().path()) else {
+            return Box::pin(async { Ok(Status::unimplemented("").into_http()) });
+        };
+
+        let call = Call {
+            method: method.clone(),
+            handler: self.handler.clone(),
+        };
+        let mut grpc = Grpc::new(DynamicCodec::new(method.input()))
+            .apply_compression_config(
+                self.accept_compression_encodings,
+                self.send_compression_encodings,
+            )
+            .apply_max_message_size_config(
+                self.max_decoding_message_size,
+                self.max_encoding_message_size,
+            );
+
+        Box::pin(async move {
+            let res = match (method.is_client_streaming(), method.is_server_streaming()) {
+                (false, false) => grpc.unary(call, req).await,
+                (false, true) => grpc.server_streaming(call, req).await,
+                (true, false) => grpc.client_streaming(call, req).await,
+                (true, true) => grpc.streaming(call, req).await,
+            };
+            Ok(res)
+        })
+    }
+}
+
+/// A call to the handler of a [`DynamicService`], adapted to the shape of its method.
+struct Call<F> {
+    method: MethodDescriptor,
+    handler: Arc<F>,
+}
+
+impl<F, Fut> Call<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    fn stream<S>(&self, request: Request<S>) -> BoxFuture<Response<DynamicStream>, Status>
+    where
+        S: Stream<Item = Result<DynamicMessage, Status>> + Send + 'static,
+    {
+        let method = self.method.clone();
+        let response = (self.handler)(method.clone(), request.map(|s| Box::pin(s) as _));
+        Box::pin(async move {
+            let response = response.await?;
+            let output = method.output();
+            Ok(response.map(|messages| {
+                Box::pin(messages.map(move |message| check_output(&output, message?)))
+                    as DynamicStream
+            }))
+        })
+    }
+
+    fn single(
+        &self,
+        request: Request<DynamicStream>,
+    ) -> BoxFuture<Response<DynamicMessage>, Status> {
+        let response = self.stream(request);
+        Box::pin(async move {
+            let (metadata, mut messages, extensions) = response.await?.into_parts();
+            let message = messages
+                .next()
+                .await
+                .ok_or_else(|| Status::internal("missing response message"))??;
+            Ok(Response::from_parts(metadata, message, extensions))
+        })
+    }
+}
+
+impl<F, Fut> UnaryService<DynamicMessage> for Call<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<Self::Response>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        self.single(request.map(|message| Box::pin(tokio_stream::once(Ok(message))) as _))
+    }
+}
+
+impl<F, Fut> ServerStreamingService<DynamicMessage> for Call<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    type Response = DynamicMessage;
+    type ResponseStream = DynamicStream;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        self.stream(request.map(|message| tokio_stream::once(Ok(message))))
+    }
+}
+
+impl<F, Fut> ClientStreamingService<DynamicMessage> for Call<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<Self::Response>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        self.single(request.map(|messages| Box::pin(messages) as _))
+    }
+}
+
+impl<F, Fut> StreamingService<DynamicMessage> for Call<F>
+where
+    F: Fn(MethodDescriptor, Request<DynamicStream>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Response<DynamicStream>, Status>> + Send + 'static,
+{
+    type Response = DynamicMessage;
+    type ResponseStream = DynamicStream;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        self.stream(request)
+    }
+}
+
+/// Checks that a response message of the handler is of the output type of the method.
+fn check_output(
+    output: &prost_reflect::MessageDescriptor,
+    message: DynamicMessage,
+) -> Result<DynamicMessage, Status> {
+    if message.descriptor() != *output {
+        return Err(Status::internal(format!(
+            "handler responded with a {} message instead of {}",
+            message.descriptor().full_name(),
+            output.full_name(),
+        )));
+    }
+    Ok(message)
+}
@@ -0,0 +1,721 @@
+//! Middleware that transcodes REST/JSON requests to gRPC calls.
+//!
+//! See [`TranscodingLayer`] for more details.
+
+use super::{json, method_path};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+use http_body_util::{BodyExt, Full};
+use prost::Message as _;
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor, ReflectMessage};
+use serde_json::Value as Json;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tonic::{body::Body, Code, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// The headers of a REST request that are not forwarded as gRPC metadata.
+const DROPPED_HEADERS: &[header::HeaderName] = &[
+    header::ACCEPT,
+    header::ACCEPT_ENCODING,
+    header::CONNECTION,
+    header::CONTENT_ENCODING,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::HOST,
+    header::TE,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// A [`Layer`] that transcodes REST/JSON requests to the gRPC methods they are mapped to with
+/// `google.api.http` annotations, and their responses back.
+///
+/// The methods of the [`DescriptorPool`] the layer is created with are mapped following their
+/// [`HttpRule`], including its additional bindings:
+///
+/// - the variables of the path template, e.g. `/v1/{name=shelves/*}`, set the fields they name,
+/// - the `body` of the request, in the [JSON mapping](super::json) of protobuf, sets the whole
+///   request message if it is `*`, or else the field it names,
+/// - the query parameters, e.g. `?page_size=10&filter.kind=BOOK`, set the fields they name unless
+///   the whole request message is set from the body.
+///
+/// The response message, or its field named by `response_body`, is answered as JSON. The response
+/// of a server streaming method is a JSON array of its messages. A call that fails is answered
+/// with the HTTP status matching its gRPC status code, e.g. `404 Not Found` for `NOT_FOUND`, and a
+/// `{"code": 5, "message": "..."}` body. Client streaming methods cannot be mapped.
+///
+/// gRPC requests and requests that match no mapping are passed through, so the same listener
+/// serves both. Since REST clients mostly use HTTP/1.1, the server must accept it:
+///
+/// ```ignore
+/// let pool = DescriptorPool::decode(FILE_DESCRIPTOR_SET)?;
+/// Server::builder()
+///     .accept_http1(true)
+///     .layer(TranscodingLayer::new(&pool)?)
+///     .add_service(LibraryServer::new(MyLibrary::default()))
+///     .serve(addr)
+///     .await?;
+/// ```
+///
+/// The `google.api.http` annotations must be part of the pool, which is the case of a file
+/// descriptor set built with the imports of the protobuf files, as `tonic-prost-build` does.
+///
+/// [`HttpRule`]: https://cloud.google.com/endpoints/docs/grpc-service-config/reference/rpc/google.api#httprule
+#[derive(Debug, Clone)]
+pub struct TranscodingLayer {
+    routes: Arc<[HttpRoute]>,
+}
+
+impl TranscodingLayer {
+    /// Create a layer transcoding the methods of `pool` that have `google.api.http` annotations.
+    ///
+    /// Fails if one of the annotations cannot be mapped.
+    pub fn new(pool: &DescriptorPool) -> Result<Self, HttpRuleError> {
+        let mut routes = Vec::new();
+        let Some(extension) = pool.get_extension_by_name("google.api.http") else {
+            return Ok(TranscodingLayer {
+                routes: routes.into(),
+            });
+        };
+
+        for service in pool.services() {
+            for method in service.methods() {
+                let options = method.options();
+                if !options.has_extension(&extension) {
+                    continue;
+                }
+
+                let rule = options.get_extension(&extension);
+                let Some(rule) = rule.as_message() else {
+                    continue;
+                };
+                routes.push(HttpRoute::new(&method, rule)?);
+                let bindings = rule.get_field_by_name("additional_bindings");
+                let bindings = bindings
+                    .as_deref()
+                    .and_then(|b| b.as_list())
+                    .unwrap_or_default();
+                for binding in bindings.iter().filter_map(|b| b.as_message()) {
+                    routes.push(HttpRoute::new(&method, binding)?);
+                }
+            }
+        }
+
+        Ok(TranscodingLayer {
+            routes: routes.into(),
+        })
+    }
+}
+
+impl<S> Layer<S> for TranscodingLayer {
+    type Service = Transcoding<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Transcoding {
+            inner,
+            routes: self.routes.clone(),
+        }
+    }
+}
+
+/// An error mapping a `google.api.http` annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRuleError {
+    method: String,
+    message: String,
+}
+
+impl HttpRuleError {
+    fn new(method: &MethodDescriptor, message: impl Into<String>) -> Self {
+        HttpRuleError {
+            method: method.full_name().to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for HttpRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid HTTP rule of {}: {}", self.method, self.message)
+    }
+}
+
+impl std::error::Error for HttpRuleError {}
+
+/// Middleware that transcodes REST/JSON requests to gRPC calls.
+///
+/// See [`TranscodingLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct Transcoding<S> {
+    inner: S,
+    routes: Arc<[HttpRoute]>,
+}
+
+impl<S> Transcoding<S> {
+    /// Create a new [`Transcoding`] of the annotated methods of `pool`.
+    pub fn new(inner: S, pool: &DescriptorPool) -> Result<Self, HttpRuleError> {
+        Ok(TranscodingLayer::new(pool)?.layer(inner))
+    }
+}
+
+impl<S, B> Service<http::Request<Body>> for Transcoding<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = http::Response<Body>;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let route = if is_grpc(&req) {
+            None
+        } else {
+            self.routes.iter().find_map(|route| {
+                let params = route.matches(req.method(), req.uri().path())?;
+                Some((route.clone(), params))
+            })
+        };
+
+        let Some((route, params)) = route else {
+            let future = inner.call(req);
+            return Box::pin(async move { Ok(future.await?.map(Body::new)) });
+        };
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let message = match route.request_message(&parts, body, params).await {
+                Ok(message) => message,
+                Err(status) => return Ok(error_response(status)),
+            };
+
+            let res = inner.call(route.grpc_request(parts, message)).await?;
+            Ok(route.response(res).await.unwrap_or_else(error_response))
+        })
+    }
+}
+
+/// A method mapped to an HTTP method and path template.
+#[derive(Debug, Clone)]
+struct HttpRoute {
+    method: MethodDescriptor,
+    http_method: Method,
+    template: Arc<PathTemplate>,
+    body: Option<String>,
+    response_body: Option<String>,
+}
+
+impl HttpRoute {
+    fn new(method: &MethodDescriptor, rule: &DynamicMessage) -> Result<Self, HttpRuleError> {
+        let string = |name: &str| {
+            rule.get_field_by_name(name)
+                .and_then(|value| value.as_str().map(str::to_owned))
+                .filter(|value| !value.is_empty())
+        };
+
+        let pattern = [
+            (Method::GET, "get"),
+            (Method::PUT, "put"),
+            (Method::POST, "post"),
+            (Method::DELETE, "delete"),
+            (Method::PATCH, "patch"),
+        ]
+        .into_iter()
+        .find_map(|(http_method, name)| Some((http_method, string(name)?)));
+        let pattern = match pattern {
+            Some(pattern) => pattern,
+            None => {
+                let custom = rule
+                    .get_field_by_name("custom")
+                    .and_then(|custom| custom.as_message().cloned())
+                    .ok_or_else(|| HttpRuleError::new(method, "no pattern"))?;
+                let field = |name| {
+                    custom
+                        .get_field_by_name(name)
+                        .and_then(|value| value.as_str().map(str::to_owned))
+                        .unwrap_or_default()
+                };
+                let http_method = Method::from_bytes(field("kind").as_bytes())
+                    .map_err(|_| HttpRuleError::new(method, "invalid custom method"))?;
+                (http_method, field("path"))
+            }
+        };
+
+        if method.is_client_streaming() {
+            return Err(HttpRuleError::new(
+                method,
+                "client streaming methods cannot be transcoded",
+            ));
+        }
+
+        let template =
+            PathTemplate::parse(&pattern.1).map_err(|error| HttpRuleError::new(method, error))?;
+        let body = string("body");
+        let response_body = string("response_body");
+        let fields = [
+            (body.as_deref().filter(|body| *body != "*"), method.input()),
+            (response_body.as_deref(), method.output()),
+        ];
+        for (field, message) in fields {
+            if let Some(field) = field.filter(|f| message.get_field_by_name(f).is_none()) {
+                return Err(HttpRuleError::new(
+                    method,
+                    format!("unknown field {field} of {}", message.full_name()),
+                ));
+            }
+        }
+
+        Ok(HttpRoute {
+            method: method.clone(),
+            http_method: pattern.0,
+            template: Arc::new(template),
+            body,
+            response_body,
+        })
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> Option<Vec<(String, String)>> {
+        if *method != self.http_method {
+            return None;
+        }
+        self.template.matches(path)
+    }
+
+    /// Builds the request message from the path variables, the body and the query parameters.
+    async fn request_message(
+        &self,
+        parts: &http::request::Parts,
+        body: Body,
+        params: Vec<(String, String)>,
+    ) -> Result<DynamicMessage, Status> {
+        let mut message = DynamicMessage::new(self.method.input());
+
+        if let Some(field) = &self.body {
+            let body = body
+                .collect()
+                .await
+                .map_err(|error| Status::from_error(error.into()))?
+                .to_bytes();
+            let json = if body.is_empty() {
+                Json::Object(Default::default())
+            } else {
+                serde_json::from_slice(&body).map_err(|error| {
+                    Status::invalid_argument(format!("invalid JSON body: {error}"))
+                })?
+            };
+
+            if field == "*" {
+                message = json::from_json(self.method.input(), &json)?;
+            } else {
+                json::set_field_from_json(&mut message, field, &json)?;
+            }
+        }
+
+        let query = match (&self.body, parts.uri.query()) {
+            (Some(body), _) if body == "*" => None,
+            (_, query) => query,
+        };
+        let query = query
+            .into_iter()
+            .flat_map(|query| query.split('&'))
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let decode = |s: &str| percent_decode(&s.replace('+', " "));
+                match (decode(name), decode(value)) {
+                    (Some(name), Some(value)) => Ok((name, value)),
+                    _ => Err(Status::invalid_argument(format!(
+                        "invalid query parameter {pair}"
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The path variables take precedence over the query parameters.
+        let query = query
+            .into_iter()
+            .filter(|(name, _)| !params.iter().any(|(field, _)| field == name));
+        for (field, value) in params.iter().cloned().chain(query) {
+            json::set_field_from_param(&mut message, &field, &value)
+                .map_err(|error| Status::invalid_argument(format!("invalid parameter {error}")))?;
+        }
+
+        Ok(message)
+    }
+
+    fn grpc_request(
+        &self,
+        parts: http::request::Parts,
+        message: DynamicMessage,
+    ) -> http::Request<Body> {
+        let len = message.encoded_len();
+        let mut frame = BytesMut::with_capacity(5 + len);
+        frame.put_u8(0);
+        frame.put_u32(len as u32);
+        message
+            .encode(&mut frame)
+            .expect("Message only errors if not enough space");
+
+        let mut req = http::Request::new(Body::new(Full::new(frame.freeze())));
+        *req.method_mut() = Method::POST;
+        *req.uri_mut() = Uri::try_from(method_path(&self.method)).expect("method paths are valid");
+        *req.version_mut() = Version::HTTP_2;
+
+        let mut headers = parts.headers;
+        for name in DROPPED_HEADERS {
+            headers.remove(name);
+        }
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+        *req.headers_mut() = headers;
+        *req.extensions_mut() = parts.extensions;
+        req
+    }
+
+    /// Transcodes the gRPC response to JSON, or fails with its status.
+    async fn response<B>(&self, res: http::Response<B>) -> Result<http::Response<Body>, Status>
+    where
+        B: http_body::Body<Data = Bytes>,
+        B::Error: Into<BoxError>,
+    {
+        let (parts, body) = res.into_parts();
+        if parts.status != StatusCode::OK {
+            return Err(Status::unknown(format!(
+                "unexpected HTTP status {}",
+                parts.status
+            )));
+        }
+        check_status(&parts.headers)?;
+
+        let collected = body
+            .collect()
+            .await
+            .map_err(|error| Status::from_error(error.into()))?;
+        if let Some(trailers) = collected.trailers() {
+            check_status(trailers)?;
+        }
+
+        let mut data = collected.to_bytes();
+        let mut messages = Vec::new();
+        while data.has_remaining() {
+            if data.remaining() < 5 {
+                return Err(Status::internal("truncated gRPC message"));
+            }
+            let compressed = data.get_u8();
+            let len = data.get_u32() as usize;
+            if compressed != 0 {
+                return Err(Status::internal(
+                    "compressed gRPC messages cannot be transcoded",
+                ));
+            }
+            if data.remaining() < len {
+                return Err(Status::internal("truncated gRPC message"));
+            }
+
+            let message = DynamicMessage::decode(self.method.output(), data.split_to(len))
+                .map_err(|error| Status::internal(error.to_string()))?;
+            messages.push(match &self.response_body {
+                Some(name) => {
+                    let field = message
+                        .descriptor()
+                        .get_field_by_name(name)
+                        .expect("response body fields are checked");
+                    json::field_value_to_json(&message, &field)
+                }
+                None => json::to_json(&message),
+            });
+        }
+
+        let json = if self.method.is_server_streaming() {
+            Json::Array(messages)
+        } else {
+            messages
+                .into_iter()
+                .next()
+                .ok_or_else(|| Status::internal("Missing response message."))?
+        };
+        Ok(json_response(StatusCode::OK, &json))
+    }
+}
+
+/// Fails with the status in `headers`, if it is not `OK`.
+fn check_status(headers: &http::HeaderMap) -> Result<(), Status> {
+    match Status::from_header_map(headers) {
+        Some(status) if status.code() != Code::Ok => Err(status),
+        _ => Ok(()),
+    }
+}
+
+fn is_grpc<B>(req: &http::Request<B>) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/grpc"))
+}
+
+fn json_response(status: StatusCode, json: &Json) -> http::Response<Body> {
+    let body = serde_json::to_vec(json).expect("JSON values serialize");
+    let mut res = http::Response::new(Body::new(Full::new(Bytes::from(body))));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    res
+}
+
+fn error_response(status: Status) -> http::Response<Body> {
+    let json = serde_json::json!({
+        "code": status.code() as i32,
+        "message": status.message(),
+    });
+    json_response(http_status(status.code()), &json)
+}
+
+/// The HTTP status matching a gRPC status code, as in `google/rpc/code.proto`.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).expect("valid status code"),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A parsed path template, e.g. `/v1/{name=shelves/*}/books/{book}:publish`.
+#[derive(Debug, PartialEq)]
+struct PathTemplate {
+    segments: Vec<Segment>,
+    variables: Vec<Variable>,
+    verb: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    /// `*`, a single segment.
+    Single,
+    /// `**`, the remaining segments.
+    Multi,
+}
+
+/// A variable binding the segments `start..end` of a template to a field.
+#[derive(Debug, PartialEq)]
+struct Variable {
+    field: String,
+    start: usize,
+    end: usize,
+}
+
+impl PathTemplate {
+    fn parse(template: &str) -> Result<Self, String> {
+        let path = template
+            .strip_prefix('/')
+            .ok_or_else(|| format!("path template {template} does not start with /"))?;
+
+        // Split the path at the slashes and the verb separator outside of variables.
+        let mut tokens = Vec::new();
+        let mut verb = None;
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in path.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '/' if depth == 0 => {
+                    tokens.push(&path[start..i]);
+                    start = i + 1;
+                }
+                ':' if depth == 0 => {
+                    verb = Some(path[i + 1..].to_owned());
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let end = verb
+            .as_ref()
+            .map_or(path.len(), |verb| path.len() - verb.len() - 1);
+        tokens.push(&path[start..end]);
+
+        let mut template = PathTemplate {
+            segments: Vec::new(),
+            variables: Vec::new(),
+            verb,
+        };
+        for token in tokens {
+            match token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+                Some(variable) => {
+                    let (field, pattern) = variable.split_once('=').unwrap_or((variable, "*"));
+                    let start = template.segments.len();
+                    for segment in pattern.split('/') {
+                        template.segments.push(Segment::parse(segment)?);
+                    }
+                    template.variables.push(Variable {
+                        field: field.to_owned(),
+                        start,
+                        end: template.segments.len(),
+                    });
+                }
+                None => template.segments.push(Segment::parse(token)?),
+            }
+        }
+
+        let multi = template.segments.iter().position(|s| *s == Segment::Multi);
+        if multi.is_some_and(|multi| multi + 1 != template.segments.len()) {
+            return Err(format!("** is not the last segment of {template:?}"));
+        }
+        Ok(template)
+    }
+
+    /// Matches `path`, returning the values of the variables.
+    fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let mut path = path.strip_prefix('/')?;
+        if let Some(verb) = &self.verb {
+            path = path.strip_suffix(verb.as_str())?.strip_suffix(':')?;
+        }
+        let parts = path.split('/').collect::<Vec<_>>();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if parts.get(i) != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Single => match parts.get(i) {
+                    Some(part) if !part.is_empty() => {}
+                    _ => return None,
+                },
+                Segment::Multi => {}
+            }
+        }
+        if self.segments.last() != Some(&Segment::Multi) && parts.len() != self.segments.len() {
+            return None;
+        }
+
+        self.variables
+            .iter()
+            .map(|variable| {
+                let end = if self.segments[variable.end - 1] == Segment::Multi {
+                    parts.len()
+                } else {
+                    variable.end
+                };
+                let value = percent_decode(&parts.get(variable.start..end)?.join("/"))?;
+                Some((variable.field.clone(), value))
+            })
+            .collect()
+    }
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Result<Self, String> {
+        match segment {
+            "*" => Ok(Segment::Single),
+            "**" => Ok(Segment::Multi),
+            "" => Err("empty path segment".to_owned()),
+            literal if literal.contains(['{', '}', '*']) => {
+                Err(format!("invalid path segment {literal}"))
+            }
+            literal => Ok(Segment::Literal(literal.to_owned())),
+        }
+    }
+}
+
+/// Decodes the `%XX` escapes of a URL component.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn path_templates() {
+        let template = PathTemplate::parse("/v1/{name=shelves/*/books/*}").unwrap();
+        assert_eq!(
+            template.matches("/v1/shelves/1/books/2"),
+            Some(params(&[("name", "shelves/1/books/2")]))
+        );
+        assert_eq!(template.matches("/v1/shelves/1/books"), None);
+        assert_eq!(template.matches("/v1/shelves/1/books/2/x"), None);
+
+        let template = PathTemplate::parse("/v1/shelves/{shelf}/books/{book.id}:publish").unwrap();
+        assert_eq!(
+            template.matches("/v1/shelves/a%20b/books/3:publish"),
+            Some(params(&[("shelf", "a b"), ("book.id", "3")]))
+        );
+        assert_eq!(template.matches("/v1/shelves/a/books/3"), None);
+        assert_eq!(template.matches("/v1/shelves//books/3:publish"), None);
+
+        let template = PathTemplate::parse("/files/{path=**}").unwrap();
+        assert_eq!(
+            template.matches("/files/a/b/c.txt"),
+            Some(params(&[("path", "a/b/c.txt")]))
+        );
+
+        assert!(PathTemplate::parse("v1/books").is_err());
+        assert!(PathTemplate::parse("/v1/**/books").is_err());
+        assert!(PathTemplate::parse("/v1//books").is_err());
+    }
+
+    #[test]
+    fn status_codes() {
+        assert_eq!(http_status(Code::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(http_status(Code::Cancelled).as_u16(), 499);
+        assert_eq!(percent_decode("a%2Fb+c"), Some("a/b+c".to_owned()));
+        assert_eq!(percent_decode("%zz"), None);
+    }
+}
@@ -21,11 +21,17 @@
     html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
 )]
 #![doc(html_root_url = "https://docs.rs/tonic-prost/0.13.1")]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
 
 mod codec;
+mod metadata;
+
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
 
 pub use codec::{ProstCodec, ProstDecoder, ProstEncoder};
+pub use metadata::MetadataMapExt;
 
 // Re-export prost types that users might need
 pub use prost;
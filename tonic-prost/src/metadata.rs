@@ -0,0 +1,76 @@
+use prost::Message;
+use tonic::metadata::{Binary, MetadataKey, MetadataMap};
+use tonic::Status;
+
+/// Extension trait to read and write protobuf messages as binary metadata values.
+///
+/// # Example
+///
+/// ```
+/// use tonic::metadata::{MetadataKey, MetadataMap};
+/// use tonic_prost::MetadataMapExt;
+///
+/// let key = MetadataKey::from_static("x-version-bin");
+/// let mut map = MetadataMap::new();
+/// map.insert_bin_message(&key, &42u64).unwrap();
+///
+/// let version: Option<u64> = map.get_bin_message(&key).unwrap();
+/// assert_eq!(version, Some(42));
+/// ```
+pub trait MetadataMapExt: sealed::Sealed {
+    /// Decodes the first binary value associated with `key` as a message of type `M`.
+    ///
+    /// Fails with an `INTERNAL` status if the value cannot be decoded, or is longer than
+    /// [`MetadataMap::MAX_BIN_VALUE_LEN`].
+    fn get_bin_message<M>(&self, key: &MetadataKey<Binary>) -> Result<Option<M>, Status>
+    where
+        M: Message + Default;
+
+    /// Encodes `message` as the binary value of `key`, replacing any values associated with it.
+    ///
+    /// Fails with an `INTERNAL` status if the encoded message is longer than
+    /// [`MetadataMap::MAX_BIN_VALUE_LEN`].
+    fn insert_bin_message<M>(
+        &mut self,
+        key: &MetadataKey<Binary>,
+        message: &M,
+    ) -> Result<(), Status>
+    where
+        M: Message;
+}
+
+impl MetadataMapExt for MetadataMap {
+    fn get_bin_message<M>(&self, key: &MetadataKey<Binary>) -> Result<Option<M>, Status>
+    where
+        M: Message + Default,
+    {
+        let Some(bytes) = self
+            .get_bin_bytes(key)
+            .map_err(|e| Status::internal(format!("{key}: {e}")))?
+        else {
+            return Ok(None);
+        };
+
+        M::decode(bytes)
+            .map(Some)
+            .map_err(|e| Status::internal(format!("{key}: {e}")))
+    }
+
+    fn insert_bin_message<M>(
+        &mut self,
+        key: &MetadataKey<Binary>,
+        message: &M,
+    ) -> Result<(), Status>
+    where
+        M: Message,
+    {
+        self.insert_bin_bytes(key, message.encode_to_vec())
+            .map_err(|e| Status::internal(format!("{key}: {e}")))
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for tonic::metadata::MetadataMap {}
+}
@@ -50,9 +50,10 @@ enum Direction {
     Empty,
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
 pub(crate) enum Encoding {
     Base64,
+    #[default]
     None,
 }
 
@@ -93,12 +94,12 @@ impl<B> GrpcWebCall<B> {
         Self::new(inner, Direction::Encode, encoding)
     }
 
-    pub(crate) fn client_request(inner: B) -> Self {
-        Self::new_client(inner, Direction::Encode, Encoding::None)
+    pub(crate) fn client_request(inner: B, encoding: Encoding) -> Self {
+        Self::new_client(inner, Direction::Encode, encoding)
     }
 
-    pub(crate) fn client_response(inner: B) -> Self {
-        Self::new_client(inner, Direction::Decode, Encoding::None)
+    pub(crate) fn client_response(inner: B, encoding: Encoding) -> Self {
+        Self::new_client(inner, Direction::Decode, encoding)
     }
 
     fn new_client(inner: B, direction: Direction, encoding: Encoding) -> Self {
@@ -149,7 +150,13 @@ impl<B> GrpcWebCall<B> {
 
         // Split `buf` at the largest index that is multiple of 4. Decode the
         // returned `Bytes`, keeping the rest for the next attempt to decode.
-        let index = self.max_decodable();
+        //
+        // Peers may encode each frame on its own, so padding can also end a
+        // chunk in the middle of `buf`.
+        let index = match self.buf.iter().position(|b| *b == b'=') {
+            Some(pad) => std::cmp::min(self.max_decodable(), (pad / 4 + 1) * 4),
+            None => self.max_decodable(),
+        };
 
         crate::util::base64::STANDARD
             .decode(self.as_mut().project().buf.split_to(index))
@@ -659,4 +666,30 @@ mod tests {
 
         assert_eq!(trailers, expected);
     }
+
+    #[tokio::test]
+    async fn client_decodes_separately_encoded_base64_frames() {
+        use tokio_stream::StreamExt as _;
+
+        let message = b"\0\0\0\0\x02ab";
+        let trailers = b"\x80\0\0\0\x0fgrpc-status:0\r\n";
+
+        // Each frame is encoded with its own padding, as tonic-web servers do.
+        let body = [&message[..], &trailers[..]]
+            .iter()
+            .map(|frame| crate::util::base64::STANDARD.encode(frame))
+            .collect::<String>();
+        assert!(body[..body.len() - 4].contains('='));
+
+        let mut call = GrpcWebCall::client_response(body, Encoding::Base64);
+
+        let frame = call.next().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap(), &message[..]);
+
+        let frame = call.next().await.unwrap().unwrap();
+        let trailers = frame.into_trailers().unwrap();
+        assert_eq!(trailers.get(Status::GRPC_STATUS).unwrap(), "0");
+
+        assert!(call.next().await.is_none());
+    }
 }
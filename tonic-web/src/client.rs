@@ -1,4 +1,4 @@
-use http::header::CONTENT_TYPE;
+use http::header::{ACCEPT, CONTENT_TYPE};
 use http::{Request, Response, Version};
 use pin_project::pin_project;
 use std::fmt;
@@ -9,13 +9,17 @@ use tower_layer::Layer;
 use tower_service::Service;
 use tracing::debug;
 
-use crate::call::content_types::GRPC_WEB;
-use crate::call::GrpcWebCall;
+use crate::call::content_types::{GRPC_WEB, GRPC_WEB_TEXT};
+use crate::call::{Encoding, GrpcWebCall};
 
 /// Layer implementing the grpc-web protocol for clients.
+///
+/// Requests are sent over HTTP/1.1 with the trailers in the body, so that they can go through
+/// proxies and networks that only speak HTTP/1 to a grpc-web capable server, for example with
+/// the HTTP/1 client of [`hyper_util`](https://docs.rs/hyper-util).
 #[derive(Debug, Default, Clone)]
 pub struct GrpcWebClientLayer {
-    _priv: (),
+    encoding: Encoding,
 }
 
 impl GrpcWebClientLayer {
@@ -23,13 +27,30 @@ impl GrpcWebClientLayer {
     pub fn new() -> GrpcWebClientLayer {
         Self::default()
     }
+
+    /// Encode requests in base64, as `application/grpc-web-text`, and ask for responses
+    /// encoded the same way.
+    ///
+    /// This is for intermediaries that only pass text bodies through. Defaults to `false`.
+    pub fn text(self, enabled: bool) -> Self {
+        GrpcWebClientLayer {
+            encoding: if enabled {
+                Encoding::Base64
+            } else {
+                Encoding::None
+            },
+        }
+    }
 }
 
 impl<S> Layer<S> for GrpcWebClientLayer {
     type Service = GrpcWebClientService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        GrpcWebClientService::new(inner)
+        GrpcWebClientService {
+            inner,
+            encoding: self.encoding,
+        }
     }
 }
 
@@ -39,12 +60,13 @@ impl<S> Layer<S> for GrpcWebClientLayer {
 #[derive(Debug, Clone)]
 pub struct GrpcWebClientService<S> {
     inner: S,
+    encoding: Encoding,
 }
 
 impl<S> GrpcWebClientService<S> {
     /// Create a new grpc-web for clients service.
     pub fn new(inner: S) -> Self {
-        Self { inner }
+        GrpcWebClientLayer::new().layer(inner)
     }
 }
 
@@ -67,10 +89,19 @@ where
             *req.version_mut() = Version::HTTP_11;
         }
 
+        let content_type = match self.encoding {
+            Encoding::Base64 => GRPC_WEB_TEXT,
+            Encoding::None => GRPC_WEB,
+        };
         req.headers_mut()
-            .insert(CONTENT_TYPE, GRPC_WEB.try_into().unwrap());
+            .insert(CONTENT_TYPE, content_type.try_into().unwrap());
+        if self.encoding == Encoding::Base64 {
+            req.headers_mut()
+                .insert(ACCEPT, content_type.try_into().unwrap());
+        }
 
-        let req = req.map(GrpcWebCall::client_request);
+        let encoding = self.encoding;
+        let req = req.map(|body| GrpcWebCall::client_request(body, encoding));
 
         let fut = self.inner.call(req);
 
@@ -95,7 +126,11 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let res = ready!(self.project().inner.poll(cx));
 
-        Poll::Ready(res.map(|r| r.map(GrpcWebCall::client_response)))
+        Poll::Ready(res.map(|r| {
+            // Servers answer in the encoding of their choice.
+            let encoding = Encoding::from_content_type(r.headers());
+            r.map(|body| GrpcWebCall::client_response(body, encoding))
+        }))
     }
 }
 
@@ -0,0 +1,279 @@
+//! A gRPC-Web transport for WebAssembly clients running in a browser.
+
+use bytes::{Buf, Bytes, BytesMut};
+use http::{HeaderName, HeaderValue, Request, Response};
+use http_body::{Body, Frame};
+use js_sys::{Promise, Reflect, Uint8Array};
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+use tower_service::Service;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::BoxError;
+
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    // Available both in windows and in workers.
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_with_request(request: &web_sys::Request) -> Promise;
+}
+
+/// A [`Service`] that sends `grpc-web` requests with the [fetch API] of the browser.
+///
+/// It is meant to be wrapped in a [`GrpcWebClientLayer`](crate::GrpcWebClientLayer), which
+/// turns the requests of [`tonic::client::Grpc`] into `grpc-web` requests, so that generated
+/// clients work from WebAssembly frontends:
+///
+/// ```ignore
+/// let client = ServiceBuilder::new()
+///     .layer(GrpcWebClientLayer::new())
+///     .service(FetchClient::new("https://example.com"));
+///
+/// let mut greeter = GreeterClient::new(client);
+/// ```
+///
+/// The browser handles the connection, so only unary and server streaming calls are supported,
+/// and request bodies are sent once complete.
+///
+/// [fetch API]: https://developer.mozilla.org/en-US/docs/Web/API/Fetch_API
+#[derive(Debug, Clone)]
+pub struct FetchClient {
+    base_url: String,
+}
+
+impl FetchClient {
+    /// Create a new client sending requests to the server at `base_url`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl<B> Service<Request<B>> for FetchClient
+where
+    B: Body + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<FetchBody>;
+    type Error = FetchError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let url = match req.uri().path_and_query() {
+            Some(path) => format!("{}{}", self.base_url, path),
+            None => self.base_url.clone(),
+        };
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = collect(body).await?;
+
+            // The JavaScript values are not `Send`, so the fetch runs in a local task and hands
+            // the response back through channels.
+            let (tx, rx) = oneshot::channel();
+            wasm_bindgen_futures::spawn_local(fetch(url, parts, body, tx));
+
+            rx.await
+                .map_err(|_| FetchError::new("the fetch task was dropped"))?
+        })
+    }
+}
+
+async fn collect<B>(body: B) -> Result<Bytes, FetchError>
+where
+    B: Body,
+    B::Error: Into<BoxError>,
+{
+    let mut body = std::pin::pin!(body);
+    let mut buf = BytesMut::new();
+
+    while let Some(frame) = poll_fn(|cx| body.as_mut().poll_frame(cx)).await {
+        let frame = frame.map_err(|err| FetchError::new(err.into()))?;
+        if let Ok(mut data) = frame.into_data() {
+            while data.has_remaining() {
+                let chunk = data.chunk();
+                buf.extend_from_slice(chunk);
+                let len = chunk.len();
+                data.advance(len);
+            }
+        }
+    }
+
+    Ok(buf.freeze())
+}
+
+async fn fetch(
+    url: String,
+    parts: http::request::Parts,
+    body: Bytes,
+    tx: oneshot::Sender<Result<Response<FetchBody>, FetchError>>,
+) {
+    let (response, reader) = match send(&url, &parts, &body).await {
+        Ok(response) => response,
+        Err(err) => {
+            let _ = tx.send(Err(err));
+            return;
+        }
+    };
+
+    let (data_tx, data_rx) = mpsc::channel(1);
+    let response = response.map(|()| FetchBody { rx: data_rx });
+
+    if tx.send(Ok(response)).is_err() {
+        if let Some(reader) = reader {
+            let _ = reader.cancel();
+        }
+        return;
+    }
+
+    if let Some(reader) = reader {
+        read(reader, data_tx).await;
+    }
+}
+
+async fn send(
+    url: &str,
+    parts: &http::request::Parts,
+    body: &[u8],
+) -> Result<(Response<()>, Option<web_sys::ReadableStreamDefaultReader>), FetchError> {
+    let headers = web_sys::Headers::new()?;
+    for (name, value) in &parts.headers {
+        // Browsers pick the protocol, so they do not let the page set `te`.
+        if name == http::header::TE {
+            continue;
+        }
+
+        let value = value
+            .to_str()
+            .map_err(|_| FetchError::new(format!("header {name} is not valid text")))?;
+        headers.append(name.as_str(), value)?;
+    }
+
+    let init = web_sys::RequestInit::new();
+    init.set_method(parts.method.as_str());
+    init.set_headers(&headers);
+    init.set_body(&Uint8Array::from(body));
+
+    let request = web_sys::Request::new_with_str_and_init(url, &init)?;
+    let response: web_sys::Response = JsFuture::from(fetch_with_request(&request))
+        .await?
+        .dyn_into()?;
+
+    let mut builder = Response::builder().status(response.status());
+    if let Some(entries) = js_sys::try_iter(&response.headers())? {
+        for entry in entries {
+            let entry: js_sys::Array = entry?.dyn_into()?;
+            let name = entry.get(0).as_string().unwrap_or_default();
+            let value = entry.get(1).as_string().unwrap_or_default();
+            builder = builder.header(
+                HeaderName::try_from(name).map_err(FetchError::new)?,
+                HeaderValue::try_from(value).map_err(FetchError::new)?,
+            );
+        }
+    }
+
+    let reader = response
+        .body()
+        .map(|stream| stream.get_reader().unchecked_into());
+
+    Ok((builder.body(()).map_err(FetchError::new)?, reader))
+}
+
+async fn read(
+    reader: web_sys::ReadableStreamDefaultReader,
+    tx: mpsc::Sender<Result<Bytes, FetchError>>,
+) {
+    loop {
+        let chunk = match next_chunk(&reader).await {
+            Ok(Some(chunk)) => Ok(chunk),
+            Ok(None) => return,
+            Err(err) => Err(err),
+        };
+        let failed = chunk.is_err();
+
+        if tx.send(chunk).await.is_err() {
+            // The body was dropped, stop downloading it.
+            let _ = reader.cancel();
+            return;
+        }
+
+        if failed {
+            return;
+        }
+    }
+}
+
+async fn next_chunk(
+    reader: &web_sys::ReadableStreamDefaultReader,
+) -> Result<Option<Bytes>, FetchError> {
+    let result = JsFuture::from(reader.read()).await?;
+
+    if Reflect::get(&result, &"done".into())?.is_truthy() {
+        return Ok(None);
+    }
+
+    let value: Uint8Array = Reflect::get(&result, &"value".into())?.dyn_into()?;
+    Ok(Some(value.to_vec().into()))
+}
+
+/// The body of a response received by a [`FetchClient`].
+#[derive(Debug)]
+pub struct FetchBody {
+    rx: mpsc::Receiver<Result<Bytes, FetchError>>,
+}
+
+impl Body for FetchBody {
+    type Data = Bytes;
+    type Error = FetchError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.rx
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+}
+
+/// Errors raised by a [`FetchClient`].
+#[derive(Debug)]
+pub struct FetchError {
+    message: String,
+}
+
+impl FetchError {
+    fn new(err: impl fmt::Display) -> Self {
+        Self {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<JsValue> for FetchError {
+    fn from(value: JsValue) -> Self {
+        let message = match value.dyn_ref::<js_sys::Error>() {
+            Some(err) => String::from(err.message()),
+            None => format!("{value:?}"),
+        };
+
+        Self { message }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fetch failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
@@ -52,6 +52,21 @@
 //! }
 //! ```
 //!
+//! ## Browser clients
+//!
+//! With the `fetch` feature, tonic clients compiled to `wasm32` can call servers from the browser
+//! through `FetchClient`, which sends the requests translated by [`GrpcWebClientLayer`] with
+//! the fetch API.
+//!
+//! ```ignore
+//! let client = ServiceBuilder::new()
+//!     .layer(GrpcWebClientLayer::new())
+//!     .service(FetchClient::new("https://example.com"));
+//!
+//! let mut greeter = GreeterClient::new(client);
+//! let reply = greeter.say_hello(HelloRequest { name: "Tonic".into() }).await?;
+//! ```
+//!
 //! ## Limitations
 //!
 //! * `tonic_web` is designed to work with grpc-web-compliant clients only. It is not expected to
@@ -75,8 +90,13 @@ pub use client::{GrpcWebClientLayer, GrpcWebClientService};
 pub use layer::GrpcWebLayer;
 pub use service::{GrpcWebService, ResponseFuture};
 
+#[cfg(all(target_arch = "wasm32", feature = "fetch"))]
+pub use fetch::{FetchBody, FetchClient, FetchError};
+
 mod call;
 mod client;
+#[cfg(all(target_arch = "wasm32", feature = "fetch"))]
+mod fetch;
 mod layer;
 mod service;
 
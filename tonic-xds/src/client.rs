@@ -0,0 +1,396 @@
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+    client::Grpc,
+    codegen::http::uri::PathAndQuery,
+    transport::{channel::Change, Channel, Endpoint},
+    Code, Status,
+};
+use tracing::{debug, warn};
+
+use crate::proto::{
+    DiscoveryRequest, DiscoveryResponse, Node, RpcStatus, ADS_PATH, CLUSTER_LOAD_ASSIGNMENT_TYPE,
+    CLUSTER_TYPE, LISTENER_TYPE, ROUTE_CONFIGURATION_TYPE,
+};
+use crate::state::{ResolvedEndpoint, State, XdsConfig};
+
+const RESOURCE_TYPES: [&str; 4] = [
+    LISTENER_TYPE,
+    ROUTE_CONFIGURATION_TYPE,
+    CLUSTER_TYPE,
+    CLUSTER_LOAD_ASSIGNMENT_TYPE,
+];
+
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+const CHANGE_BUFFER_SIZE: usize = 64;
+
+type ConfigureEndpoint = Arc<dyn Fn(Endpoint) -> Endpoint + Send + Sync>;
+
+/// Builds an [`XdsChannel`] for an `xds:///<listener>` target.
+#[derive(Clone)]
+pub struct XdsChannelBuilder {
+    server: Endpoint,
+    node: Node,
+    scheme: &'static str,
+    configure: Option<ConfigureEndpoint>,
+}
+
+impl XdsChannelBuilder {
+    /// Create a builder that talks ADS to the control plane at `server`.
+    pub fn new(server: Endpoint) -> Self {
+        XdsChannelBuilder {
+            server,
+            node: Node {
+                id: String::new(),
+                cluster: String::new(),
+                user_agent_name: "tonic".into(),
+            },
+            scheme: "http",
+            configure: None,
+        }
+    }
+
+    /// Set the node id reported to the control plane.
+    pub fn node_id(mut self, id: impl Into<String>) -> Self {
+        self.node.id = id.into();
+        self
+    }
+
+    /// Set the node cluster reported to the control plane.
+    pub fn node_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.node.cluster = cluster.into();
+        self
+    }
+
+    /// Connect to the discovered endpoints over `https` instead of `http`.
+    ///
+    /// TLS itself still has to be configured through
+    /// [`configure_endpoints`](Self::configure_endpoints).
+    pub fn https(mut self, enabled: bool) -> Self {
+        self.scheme = if enabled { "https" } else { "http" };
+        self
+    }
+
+    /// Apply additional configuration to every discovered [`Endpoint`].
+    ///
+    /// The closure runs after the route timeout and endpoint attributes have
+    /// been set.
+    pub fn configure_endpoints<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Endpoint) -> Endpoint + Send + Sync + 'static,
+    {
+        self.configure = Some(Arc::new(f));
+        self
+    }
+
+    /// Build the channel and start watching the control plane.
+    ///
+    /// The target must be of the form `xds:///<listener>`. This must be
+    /// called from within a tokio runtime.
+    pub fn build(self, target: &str) -> Result<XdsChannel, InvalidTarget> {
+        let listener = parse_target(target)?;
+
+        let (channel, changes) = Channel::balance_channel(CHANGE_BUFFER_SIZE);
+        let (config_tx, config) = watch::channel(XdsConfig::default());
+
+        let worker = Worker {
+            server: self.server.connect_lazy(),
+            node: self.node,
+            scheme: self.scheme,
+            configure: self.configure,
+            state: State::new(listener),
+            changes,
+            config: config_tx,
+            inserted: HashMap::new(),
+            timeout: None,
+        };
+        tokio::spawn(worker.run());
+
+        Ok(XdsChannel { channel, config })
+    }
+}
+
+impl fmt::Debug for XdsChannelBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XdsChannelBuilder")
+            .field("server", &self.server)
+            .field("node_id", &self.node.id)
+            .field("node_cluster", &self.node.cluster)
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A balanced [`Channel`] whose endpoints come from an xDS control plane.
+#[derive(Clone, Debug)]
+pub struct XdsChannel {
+    channel: Channel,
+    config: watch::Receiver<XdsConfig>,
+}
+
+impl XdsChannel {
+    /// The channel to issue requests on.
+    pub fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+
+    /// The configuration most recently resolved from the control plane.
+    pub fn config(&self) -> XdsConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Wait until the resolved configuration changes.
+    ///
+    /// Returns `None` once the control plane watcher has stopped.
+    pub async fn changed(&mut self) -> Option<XdsConfig> {
+        self.config.changed().await.ok()?;
+        Some(self.config.borrow_and_update().clone())
+    }
+}
+
+/// Error returned when a target is not an `xds:///<listener>` URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidTarget {
+    target: String,
+}
+
+impl fmt::Display for InvalidTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid xDS target {:?}", self.target)
+    }
+}
+
+impl std::error::Error for InvalidTarget {}
+
+fn parse_target(target: &str) -> Result<String, InvalidTarget> {
+    let invalid = || InvalidTarget {
+        target: target.to_string(),
+    };
+
+    let rest = target.strip_prefix("xds://").ok_or_else(invalid)?;
+    // Authorities select alternative control planes, which are not supported.
+    let listener = rest.strip_prefix('/').ok_or_else(invalid)?;
+    if listener.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(listener.to_string())
+}
+
+struct Worker {
+    server: Channel,
+    node: Node,
+    scheme: &'static str,
+    configure: Option<ConfigureEndpoint>,
+    state: State,
+    changes: mpsc::Sender<Change<String, Endpoint>>,
+    config: watch::Sender<XdsConfig>,
+    inserted: HashMap<String, ResolvedEndpoint>,
+    timeout: Option<Duration>,
+}
+
+impl Worker {
+    async fn run(mut self) {
+        loop {
+            if let Err(status) = self.stream().await {
+                warn!("xDS stream failed: {}", status);
+            }
+
+            if self.changes.is_closed() {
+                return;
+            }
+
+            tokio::time::sleep(RETRY_BACKOFF).await;
+        }
+    }
+
+    async fn stream(&mut self) -> Result<(), Status> {
+        let (requests, rx) = mpsc::channel(RESOURCE_TYPES.len() * 2);
+        let mut subscribed = HashMap::new();
+        let mut acked: HashMap<String, (String, String)> = HashMap::new();
+
+        self.subscribe(&requests, &mut subscribed, &acked).await;
+
+        let mut grpc = Grpc::new(self.server.clone());
+        grpc.ready()
+            .await
+            .map_err(|e| Status::unknown(format!("Service was not ready: {e}")))?;
+        let codec = tonic_prost::ProstCodec::<DiscoveryRequest, DiscoveryResponse>::default();
+        let mut responses = grpc
+            .streaming(
+                tonic::Request::new(ReceiverStream::new(rx)),
+                PathAndQuery::from_static(ADS_PATH),
+                codec,
+            )
+            .await?
+            .into_inner();
+
+        while let Some(response) = responses.message().await? {
+            if self.changes.is_closed() {
+                return Ok(());
+            }
+
+            let type_url = response.type_url.clone();
+            let error_detail = match self.state.apply(&type_url, &response.resources) {
+                Ok(()) => {
+                    acked.insert(
+                        type_url.clone(),
+                        (response.version_info.clone(), response.nonce.clone()),
+                    );
+                    None
+                }
+                Err(message) => {
+                    warn!("rejecting xDS response for {}: {}", type_url, message);
+                    if let Some((_, nonce)) = acked.get_mut(&type_url) {
+                        nonce.clone_from(&response.nonce);
+                    }
+                    Some(RpcStatus {
+                        code: Code::InvalidArgument as i32,
+                        message,
+                    })
+                }
+            };
+
+            let version_info = acked
+                .get(&type_url)
+                .map(|(version, _)| version.clone())
+                .unwrap_or_default();
+            let ack = DiscoveryRequest {
+                version_info,
+                node: Some(self.node.clone()),
+                resource_names: self.state.subscriptions(&type_url),
+                type_url: type_url.clone(),
+                response_nonce: response.nonce,
+                error_detail,
+            };
+            if requests.send(ack).await.is_err() {
+                return Ok(());
+            }
+            subscribed.insert(type_url, self.state.subscriptions(&response.type_url));
+
+            self.subscribe(&requests, &mut subscribed, &acked).await;
+            self.sync().await;
+        }
+
+        Err(Status::unavailable(
+            "xDS stream closed by the control plane",
+        ))
+    }
+
+    /// Sends a request for every resource type whose subscription changed.
+    async fn subscribe(
+        &self,
+        requests: &mpsc::Sender<DiscoveryRequest>,
+        subscribed: &mut HashMap<String, Vec<String>>,
+        acked: &HashMap<String, (String, String)>,
+    ) {
+        for type_url in RESOURCE_TYPES {
+            let names = self.state.subscriptions(type_url);
+            let current = subscribed.get(type_url).map_or(&[][..], Vec::as_slice);
+            if names.is_empty() && current.is_empty() || names == current {
+                continue;
+            }
+
+            debug!("subscribing to {} {:?}", type_url, names);
+            let (version_info, response_nonce) = acked.get(type_url).cloned().unwrap_or_default();
+            let request = DiscoveryRequest {
+                version_info,
+                node: Some(self.node.clone()),
+                resource_names: names.clone(),
+                type_url: type_url.into(),
+                response_nonce,
+                error_detail: None,
+            };
+            subscribed.insert(type_url.into(), names);
+            let _ = requests.send(request).await;
+        }
+    }
+
+    /// Pushes endpoint and configuration changes to the channel.
+    async fn sync(&mut self) {
+        let config = self.state.config();
+        let timeout_changed = self.timeout != config.timeout();
+        self.timeout = config.timeout();
+
+        let desired: HashMap<_, _> = self
+            .state
+            .endpoints()
+            .iter()
+            .map(|endpoint| (endpoint.address.clone(), endpoint.clone()))
+            .collect();
+
+        let removed: Vec<_> = self
+            .inserted
+            .keys()
+            .filter(|address| !desired.contains_key(*address))
+            .cloned()
+            .collect();
+        for address in removed {
+            self.inserted.remove(&address);
+            let _ = self.changes.send(Change::Remove(address)).await;
+        }
+
+        for (address, resolved) in desired {
+            if !timeout_changed && self.inserted.get(&address) == Some(&resolved) {
+                continue;
+            }
+
+            let Some(endpoint) = self.endpoint(&resolved) else {
+                continue;
+            };
+            self.inserted.insert(address.clone(), resolved);
+            let _ = self.changes.send(Change::Insert(address, endpoint)).await;
+        }
+
+        self.config.send_if_modified(|current| {
+            if *current == config {
+                false
+            } else {
+                *current = config;
+                true
+            }
+        });
+    }
+
+    fn endpoint(&self, resolved: &ResolvedEndpoint) -> Option<Endpoint> {
+        let uri = format!("{}://{}", self.scheme, resolved.address);
+        let mut endpoint = match Endpoint::from_shared(uri) {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                warn!("invalid xDS endpoint {}: {}", resolved.address, e);
+                return None;
+            }
+        };
+
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        for (key, value) in &resolved.attributes {
+            endpoint = endpoint.attribute(*key, value.clone());
+        }
+        if let Some(configure) = &self.configure {
+            endpoint = configure(endpoint);
+        }
+
+        Some(endpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!(parse_target("xds:///svc").unwrap(), "svc");
+        assert_eq!(
+            parse_target("xds:///svc.example.com:8080").unwrap(),
+            "svc.example.com:8080"
+        );
+        assert!(parse_target("xds://authority/svc").is_err());
+        assert!(parse_target("xds:///").is_err());
+        assert!(parse_target("http://svc").is_err());
+    }
+}
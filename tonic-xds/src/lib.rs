@@ -0,0 +1,63 @@
+//! A minimal xDS client for `tonic` channels.
+//!
+//! [`XdsChannelBuilder`] resolves `xds:///<listener>` targets against an xDS
+//! control plane over the Aggregated Discovery Service. It follows the
+//! listener to its route configuration, picks the default route of the
+//! virtual host matching the listener name, and watches the route's cluster
+//! and its endpoints. The endpoints are fed into a balanced
+//! [`Channel`](tonic::transport::Channel), with the route timeout applied to
+//! each of them.
+//!
+//! Every discovered endpoint carries its cluster and locality as
+//! [endpoint attributes](tonic::transport::Endpoint::attribute); the keys are
+//! listed in [`attributes`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use tonic::transport::Endpoint;
+//! use tonic_xds::XdsChannelBuilder;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let control_plane = Endpoint::from_static("http://control-plane:18000");
+//!
+//! let xds = XdsChannelBuilder::new(control_plane)
+//!     .node_id("my-client")
+//!     .build("xds:///my-service")?;
+//!
+//! let channel = xds.channel();
+//! # Ok(())
+//! # }
+//! ```
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/tokio-rs/website/master/public/img/icons/tonic.svg"
+)]
+#![doc(issue_tracker_base_url = "https://github.com/hyperium/tonic/issues/")]
+#![doc(test(no_crate_inject, attr(deny(rust_2018_idioms))))]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
+
+mod client;
+mod proto;
+mod state;
+
+pub use client::{InvalidTarget, XdsChannel, XdsChannelBuilder};
+pub use state::{LbPolicy, XdsConfig};
+
+/// Endpoint attribute keys set on every discovered endpoint.
+pub mod attributes {
+    /// The cluster the endpoint belongs to.
+    pub const CLUSTER: &str = "xds.cluster";
+    /// The priority of the endpoint's locality, `0` being the highest.
+    pub const PRIORITY: &str = "xds.priority";
+    /// The region of the endpoint's locality.
+    pub const REGION: &str = "xds.locality.region";
+    /// The zone of the endpoint's locality.
+    pub const ZONE: &str = "xds.locality.zone";
+    /// The sub-zone of the endpoint's locality.
+    pub const SUB_ZONE: &str = "xds.locality.sub_zone";
+    /// The load-balancing weight of the endpoint's locality.
+    pub const LOCALITY_WEIGHT: &str = "xds.locality.weight";
+    /// The load-balancing weight of the endpoint.
+    pub const WEIGHT: &str = "xds.weight";
+}
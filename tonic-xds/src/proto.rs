@@ -0,0 +1,222 @@
+//! The subset of the Envoy xDS v3 API used by the client.
+//!
+//! Only the fields the client reads are declared. Unknown fields are skipped
+//! by prost when decoding, so these messages are wire compatible with the
+//! full definitions in `envoy/config` and `envoy/service/discovery`.
+
+use prost_types::{Any, Duration};
+
+pub(crate) const ADS_PATH: &str =
+    "/envoy.service.discovery.v3.AggregatedDiscoveryService/StreamAggregatedResources";
+
+pub(crate) const LISTENER_TYPE: &str = "type.googleapis.com/envoy.config.listener.v3.Listener";
+pub(crate) const ROUTE_CONFIGURATION_TYPE: &str =
+    "type.googleapis.com/envoy.config.route.v3.RouteConfiguration";
+pub(crate) const CLUSTER_TYPE: &str = "type.googleapis.com/envoy.config.cluster.v3.Cluster";
+pub(crate) const CLUSTER_LOAD_ASSIGNMENT_TYPE: &str =
+    "type.googleapis.com/envoy.config.endpoint.v3.ClusterLoadAssignment";
+pub(crate) const HTTP_CONNECTION_MANAGER_TYPE: &str = "type.googleapis.com/envoy.extensions.filters.network.http_connection_manager.v3.HttpConnectionManager";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DiscoveryRequest {
+    #[prost(string, tag = "1")]
+    pub(crate) version_info: String,
+    #[prost(message, optional, tag = "2")]
+    pub(crate) node: Option<Node>,
+    #[prost(string, repeated, tag = "3")]
+    pub(crate) resource_names: Vec<String>,
+    #[prost(string, tag = "4")]
+    pub(crate) type_url: String,
+    #[prost(string, tag = "5")]
+    pub(crate) response_nonce: String,
+    #[prost(message, optional, tag = "6")]
+    pub(crate) error_detail: Option<RpcStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DiscoveryResponse {
+    #[prost(string, tag = "1")]
+    pub(crate) version_info: String,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) resources: Vec<Any>,
+    #[prost(string, tag = "4")]
+    pub(crate) type_url: String,
+    #[prost(string, tag = "5")]
+    pub(crate) nonce: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Node {
+    #[prost(string, tag = "1")]
+    pub(crate) id: String,
+    #[prost(string, tag = "2")]
+    pub(crate) cluster: String,
+    #[prost(string, tag = "6")]
+    pub(crate) user_agent_name: String,
+}
+
+/// `google.rpc.Status`, used to NACK a response.
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub(crate) code: i32,
+    #[prost(string, tag = "2")]
+    pub(crate) message: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Listener {
+    #[prost(string, tag = "1")]
+    pub(crate) name: String,
+    #[prost(message, optional, tag = "19")]
+    pub(crate) api_listener: Option<ApiListener>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ApiListener {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) api_listener: Option<Any>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct HttpConnectionManager {
+    #[prost(message, optional, tag = "3")]
+    pub(crate) rds: Option<Rds>,
+    #[prost(message, optional, tag = "4")]
+    pub(crate) route_config: Option<RouteConfiguration>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Rds {
+    #[prost(string, tag = "2")]
+    pub(crate) route_config_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RouteConfiguration {
+    #[prost(string, tag = "1")]
+    pub(crate) name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) virtual_hosts: Vec<VirtualHost>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct VirtualHost {
+    #[prost(string, tag = "1")]
+    pub(crate) name: String,
+    #[prost(string, repeated, tag = "2")]
+    pub(crate) domains: Vec<String>,
+    #[prost(message, repeated, tag = "3")]
+    pub(crate) routes: Vec<Route>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Route {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) r#match: Option<RouteMatch>,
+    #[prost(message, optional, tag = "2")]
+    pub(crate) route: Option<RouteAction>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RouteMatch {
+    #[prost(string, optional, tag = "1")]
+    pub(crate) prefix: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub(crate) path: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RouteAction {
+    #[prost(string, tag = "1")]
+    pub(crate) cluster: String,
+    #[prost(message, optional, tag = "8")]
+    pub(crate) timeout: Option<Duration>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Cluster {
+    #[prost(string, tag = "1")]
+    pub(crate) name: String,
+    #[prost(int32, tag = "2")]
+    pub(crate) r#type: i32,
+    #[prost(message, optional, tag = "3")]
+    pub(crate) eds_cluster_config: Option<EdsClusterConfig>,
+    #[prost(int32, tag = "6")]
+    pub(crate) lb_policy: i32,
+}
+
+/// `Cluster.DiscoveryType.EDS`.
+pub(crate) const DISCOVERY_TYPE_EDS: i32 = 3;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct EdsClusterConfig {
+    #[prost(string, tag = "2")]
+    pub(crate) service_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ClusterLoadAssignment {
+    #[prost(string, tag = "1")]
+    pub(crate) cluster_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) endpoints: Vec<LocalityLbEndpoints>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct LocalityLbEndpoints {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) locality: Option<Locality>,
+    #[prost(message, repeated, tag = "2")]
+    pub(crate) lb_endpoints: Vec<LbEndpoint>,
+    #[prost(message, optional, tag = "3")]
+    pub(crate) load_balancing_weight: Option<u32>,
+    #[prost(uint32, tag = "5")]
+    pub(crate) priority: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Locality {
+    #[prost(string, tag = "1")]
+    pub(crate) region: String,
+    #[prost(string, tag = "2")]
+    pub(crate) zone: String,
+    #[prost(string, tag = "3")]
+    pub(crate) sub_zone: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct LbEndpoint {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) endpoint: Option<EndpointProto>,
+    #[prost(int32, tag = "2")]
+    pub(crate) health_status: i32,
+    #[prost(message, optional, tag = "4")]
+    pub(crate) load_balancing_weight: Option<u32>,
+}
+
+/// `HealthStatus.UNKNOWN`.
+pub(crate) const HEALTH_STATUS_UNKNOWN: i32 = 0;
+/// `HealthStatus.HEALTHY`.
+pub(crate) const HEALTH_STATUS_HEALTHY: i32 = 1;
+
+/// `envoy.config.endpoint.v3.Endpoint`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct EndpointProto {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) address: Option<Address>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Address {
+    #[prost(message, optional, tag = "1")]
+    pub(crate) socket_address: Option<SocketAddress>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct SocketAddress {
+    #[prost(string, tag = "2")]
+    pub(crate) address: String,
+    #[prost(uint32, tag = "3")]
+    pub(crate) port_value: u32,
+}
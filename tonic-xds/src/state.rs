@@ -0,0 +1,546 @@
+use std::time::Duration;
+
+use prost::Message;
+use prost_types::Any;
+
+use crate::attributes;
+use crate::proto::{
+    Cluster, ClusterLoadAssignment, HttpConnectionManager, Listener, RouteConfiguration,
+    VirtualHost, CLUSTER_LOAD_ASSIGNMENT_TYPE, CLUSTER_TYPE, DISCOVERY_TYPE_EDS,
+    HEALTH_STATUS_HEALTHY, HEALTH_STATUS_UNKNOWN, HTTP_CONNECTION_MANAGER_TYPE, LISTENER_TYPE,
+    ROUTE_CONFIGURATION_TYPE,
+};
+
+/// Load-balancing policy selected by the cluster resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LbPolicy {
+    /// `ROUND_ROBIN`, the xDS default.
+    #[default]
+    RoundRobin,
+    /// `LEAST_REQUEST`.
+    LeastRequest,
+    /// `RING_HASH`.
+    RingHash,
+    /// `RANDOM`.
+    Random,
+    /// `MAGLEV`.
+    Maglev,
+    /// Any other policy, carrying the raw `Cluster.LbPolicy` value.
+    Other(i32),
+}
+
+impl From<i32> for LbPolicy {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => LbPolicy::RoundRobin,
+            1 => LbPolicy::LeastRequest,
+            2 => LbPolicy::RingHash,
+            3 => LbPolicy::Random,
+            5 => LbPolicy::Maglev,
+            other => LbPolicy::Other(other),
+        }
+    }
+}
+
+/// The configuration currently resolved from the control plane.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XdsConfig {
+    cluster: Option<String>,
+    lb_policy: LbPolicy,
+    timeout: Option<Duration>,
+    endpoints: Vec<String>,
+}
+
+impl XdsConfig {
+    /// The cluster selected by the default route, if one has been resolved.
+    pub fn cluster(&self) -> Option<&str> {
+        self.cluster.as_deref()
+    }
+
+    /// The load-balancing policy of the cluster.
+    pub fn lb_policy(&self) -> LbPolicy {
+        self.lb_policy
+    }
+
+    /// The timeout of the default route, applied to every endpoint.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The `host:port` addresses of the endpoints currently in the channel.
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ResolvedEndpoint {
+    pub(crate) address: String,
+    pub(crate) attributes: Vec<(&'static str, String)>,
+}
+
+/// Follows the LDS -> RDS -> CDS -> EDS chain for a single listener.
+#[derive(Debug)]
+pub(crate) struct State {
+    listener: String,
+    route_config: Option<String>,
+    cluster: Option<String>,
+    eds_service: Option<String>,
+    timeout: Option<Duration>,
+    lb_policy: LbPolicy,
+    endpoints: Vec<ResolvedEndpoint>,
+}
+
+impl State {
+    pub(crate) fn new(listener: String) -> Self {
+        State {
+            listener,
+            route_config: None,
+            cluster: None,
+            eds_service: None,
+            timeout: None,
+            lb_policy: LbPolicy::default(),
+            endpoints: Vec::new(),
+        }
+    }
+
+    /// The resource names to subscribe to for `type_url`.
+    pub(crate) fn subscriptions(&self, type_url: &str) -> Vec<String> {
+        let name = match type_url {
+            LISTENER_TYPE => Some(&self.listener),
+            ROUTE_CONFIGURATION_TYPE => self.route_config.as_ref(),
+            CLUSTER_TYPE => self.cluster.as_ref(),
+            CLUSTER_LOAD_ASSIGNMENT_TYPE => self.eds_service.as_ref(),
+            _ => None,
+        };
+        name.cloned().into_iter().collect()
+    }
+
+    pub(crate) fn config(&self) -> XdsConfig {
+        XdsConfig {
+            cluster: self.cluster.clone(),
+            lb_policy: self.lb_policy,
+            timeout: self.timeout,
+            endpoints: self.endpoints.iter().map(|e| e.address.clone()).collect(),
+        }
+    }
+
+    pub(crate) fn endpoints(&self) -> &[ResolvedEndpoint] {
+        &self.endpoints
+    }
+
+    /// Applies the resources of a discovery response.
+    ///
+    /// An error means the response was rejected and should be NACKed.
+    pub(crate) fn apply(&mut self, type_url: &str, resources: &[Any]) -> Result<(), String> {
+        match type_url {
+            LISTENER_TYPE => {
+                let listener = find::<Listener>(resources, type_url, |l| l.name == self.listener)?;
+                if let Some(listener) = listener {
+                    self.apply_listener(listener)?;
+                }
+            }
+            ROUTE_CONFIGURATION_TYPE => {
+                let Some(name) = self.route_config.clone() else {
+                    return Ok(());
+                };
+                let config = find::<RouteConfiguration>(resources, type_url, |r| r.name == name)?;
+                if let Some(config) = config {
+                    self.apply_route_config(config)?;
+                }
+            }
+            CLUSTER_TYPE => {
+                let Some(name) = self.cluster.clone() else {
+                    return Ok(());
+                };
+                if let Some(cluster) = find::<Cluster>(resources, type_url, |c| c.name == name)? {
+                    self.apply_cluster(cluster)?;
+                }
+            }
+            CLUSTER_LOAD_ASSIGNMENT_TYPE => {
+                let Some(name) = self.eds_service.clone() else {
+                    return Ok(());
+                };
+                let assignment =
+                    find::<ClusterLoadAssignment>(resources, type_url, |a| a.cluster_name == name)?;
+                if let Some(assignment) = assignment {
+                    self.apply_assignment(assignment);
+                }
+            }
+            other => return Err(format!("unsupported resource type {other}")),
+        }
+
+        Ok(())
+    }
+
+    fn apply_listener(&mut self, listener: Listener) -> Result<(), String> {
+        let manager = listener
+            .api_listener
+            .and_then(|api| api.api_listener)
+            .ok_or_else(|| format!("listener {} is not an API listener", listener.name))?;
+        if manager.type_url != HTTP_CONNECTION_MANAGER_TYPE {
+            return Err(format!("unsupported API listener {}", manager.type_url));
+        }
+        let manager = HttpConnectionManager::decode(manager.value.as_slice())
+            .map_err(|e| format!("invalid HttpConnectionManager: {e}"))?;
+
+        match (manager.route_config, manager.rds) {
+            (Some(config), _) => {
+                self.route_config = None;
+                self.apply_route_config(config)
+            }
+            (None, Some(rds)) => {
+                self.route_config = Some(rds.route_config_name);
+                Ok(())
+            }
+            (None, None) => Err("HttpConnectionManager has no route configuration".into()),
+        }
+    }
+
+    fn apply_route_config(&mut self, config: RouteConfiguration) -> Result<(), String> {
+        let host = select_virtual_host(&config.virtual_hosts, &self.listener)
+            .ok_or_else(|| format!("no virtual host matches {}", self.listener))?;
+
+        let action = host
+            .routes
+            .iter()
+            .find(|route| {
+                route.r#match.as_ref().is_some_and(|m| {
+                    m.path.is_none() && matches!(m.prefix.as_deref(), Some("") | Some("/"))
+                })
+            })
+            .and_then(|route| route.route.as_ref())
+            .ok_or_else(|| format!("virtual host {} has no default route", host.name))?;
+
+        if action.cluster.is_empty() {
+            return Err("default route has no cluster".into());
+        }
+
+        self.timeout = action
+            .timeout
+            .and_then(|timeout| Duration::try_from(timeout).ok())
+            .filter(|timeout| !timeout.is_zero());
+
+        if self.cluster.as_deref() != Some(action.cluster.as_str()) {
+            self.cluster = Some(action.cluster.clone());
+            self.eds_service = None;
+        }
+
+        Ok(())
+    }
+
+    fn apply_cluster(&mut self, cluster: Cluster) -> Result<(), String> {
+        if cluster.r#type != DISCOVERY_TYPE_EDS {
+            return Err(format!("cluster {} is not an EDS cluster", cluster.name));
+        }
+
+        let service = cluster
+            .eds_cluster_config
+            .map(|config| config.service_name)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(cluster.name);
+
+        self.lb_policy = LbPolicy::from(cluster.lb_policy);
+        self.eds_service = Some(service);
+
+        Ok(())
+    }
+
+    fn apply_assignment(&mut self, assignment: ClusterLoadAssignment) {
+        let cluster = self.cluster.clone().unwrap_or_default();
+        let mut endpoints = Vec::new();
+
+        for locality_endpoints in assignment.endpoints {
+            let locality = locality_endpoints.locality.unwrap_or_default();
+
+            for lb_endpoint in locality_endpoints.lb_endpoints {
+                if !matches!(
+                    lb_endpoint.health_status,
+                    HEALTH_STATUS_UNKNOWN | HEALTH_STATUS_HEALTHY
+                ) {
+                    continue;
+                }
+
+                let Some(socket) = lb_endpoint
+                    .endpoint
+                    .and_then(|e| e.address)
+                    .and_then(|a| a.socket_address)
+                else {
+                    continue;
+                };
+
+                let address = if socket.address.contains(':') {
+                    format!("[{}]:{}", socket.address, socket.port_value)
+                } else {
+                    format!("{}:{}", socket.address, socket.port_value)
+                };
+
+                let mut attrs = vec![
+                    (attributes::CLUSTER, cluster.clone()),
+                    (
+                        attributes::PRIORITY,
+                        locality_endpoints.priority.to_string(),
+                    ),
+                ];
+                for (key, value) in [
+                    (attributes::REGION, &locality.region),
+                    (attributes::ZONE, &locality.zone),
+                    (attributes::SUB_ZONE, &locality.sub_zone),
+                ] {
+                    if !value.is_empty() {
+                        attrs.push((key, value.clone()));
+                    }
+                }
+                if let Some(weight) = locality_endpoints.load_balancing_weight {
+                    attrs.push((attributes::LOCALITY_WEIGHT, weight.to_string()));
+                }
+                if let Some(weight) = lb_endpoint.load_balancing_weight {
+                    attrs.push((attributes::WEIGHT, weight.to_string()));
+                }
+
+                endpoints.push(ResolvedEndpoint {
+                    address,
+                    attributes: attrs,
+                });
+            }
+        }
+
+        self.endpoints = endpoints;
+    }
+}
+
+fn find<T: Message + Default>(
+    resources: &[Any],
+    type_url: &str,
+    predicate: impl Fn(&T) -> bool,
+) -> Result<Option<T>, String> {
+    for resource in resources {
+        if resource.type_url != type_url {
+            return Err(format!("unexpected resource type {}", resource.type_url));
+        }
+        let resource =
+            T::decode(resource.value.as_slice()).map_err(|e| format!("invalid {type_url}: {e}"))?;
+        if predicate(&resource) {
+            return Ok(Some(resource));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Picks the virtual host whose domains best match `authority`: exact
+/// matches win over suffix wildcards, which win over prefix wildcards, which
+/// win over `*`.
+fn select_virtual_host<'a>(hosts: &'a [VirtualHost], authority: &str) -> Option<&'a VirtualHost> {
+    let rank = |domain: &str| -> Option<(u8, usize)> {
+        if domain == authority {
+            Some((3, domain.len()))
+        } else if domain == "*" {
+            Some((0, 0))
+        } else if let Some(suffix) = domain.strip_prefix('*') {
+            authority.ends_with(suffix).then_some((2, domain.len()))
+        } else if let Some(prefix) = domain.strip_suffix('*') {
+            authority.starts_with(prefix).then_some((1, domain.len()))
+        } else {
+            None
+        }
+    };
+
+    hosts
+        .iter()
+        .filter_map(|host| {
+            host.domains
+                .iter()
+                .filter_map(|domain| rank(domain))
+                .max()
+                .map(|rank| (rank, host))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, host)| host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{
+        Address, ApiListener, EdsClusterConfig, EndpointProto, LbEndpoint, Locality,
+        LocalityLbEndpoints, Rds, Route, RouteAction, RouteMatch, SocketAddress,
+    };
+
+    fn any<T: Message>(type_url: &str, message: &T) -> Any {
+        Any {
+            type_url: type_url.into(),
+            value: message.encode_to_vec(),
+        }
+    }
+
+    fn listener(name: &str, route_config_name: &str) -> Any {
+        let manager = HttpConnectionManager {
+            rds: Some(Rds {
+                route_config_name: route_config_name.into(),
+            }),
+            route_config: None,
+        };
+        let listener = Listener {
+            name: name.into(),
+            api_listener: Some(ApiListener {
+                api_listener: Some(any(HTTP_CONNECTION_MANAGER_TYPE, &manager)),
+            }),
+        };
+        any(LISTENER_TYPE, &listener)
+    }
+
+    fn route_config(name: &str, domain: &str, cluster: &str) -> Any {
+        let config = RouteConfiguration {
+            name: name.into(),
+            virtual_hosts: vec![VirtualHost {
+                name: "host".into(),
+                domains: vec![domain.into()],
+                routes: vec![Route {
+                    r#match: Some(RouteMatch {
+                        prefix: Some("".into()),
+                        path: None,
+                    }),
+                    route: Some(RouteAction {
+                        cluster: cluster.into(),
+                        timeout: Some(prost_types::Duration {
+                            seconds: 2,
+                            nanos: 0,
+                        }),
+                    }),
+                }],
+            }],
+        };
+        any(ROUTE_CONFIGURATION_TYPE, &config)
+    }
+
+    #[test]
+    fn resolves_full_chain() {
+        let mut state = State::new("svc".into());
+        assert_eq!(state.subscriptions(LISTENER_TYPE), vec!["svc"]);
+        assert!(state.subscriptions(ROUTE_CONFIGURATION_TYPE).is_empty());
+
+        state
+            .apply(LISTENER_TYPE, &[listener("svc", "routes")])
+            .unwrap();
+        assert_eq!(
+            state.subscriptions(ROUTE_CONFIGURATION_TYPE),
+            vec!["routes"]
+        );
+
+        state
+            .apply(
+                ROUTE_CONFIGURATION_TYPE,
+                &[route_config("routes", "*", "backend")],
+            )
+            .unwrap();
+        assert_eq!(state.subscriptions(CLUSTER_TYPE), vec!["backend"]);
+
+        let cluster = Cluster {
+            name: "backend".into(),
+            r#type: DISCOVERY_TYPE_EDS,
+            eds_cluster_config: Some(EdsClusterConfig {
+                service_name: "backend-eds".into(),
+            }),
+            lb_policy: 1,
+        };
+        state
+            .apply(CLUSTER_TYPE, &[any(CLUSTER_TYPE, &cluster)])
+            .unwrap();
+        assert_eq!(
+            state.subscriptions(CLUSTER_LOAD_ASSIGNMENT_TYPE),
+            vec!["backend-eds"]
+        );
+
+        let endpoint = |address: &str, health_status| LbEndpoint {
+            endpoint: Some(EndpointProto {
+                address: Some(Address {
+                    socket_address: Some(SocketAddress {
+                        address: address.into(),
+                        port_value: 50051,
+                    }),
+                }),
+            }),
+            health_status,
+            load_balancing_weight: None,
+        };
+        let assignment = ClusterLoadAssignment {
+            cluster_name: "backend-eds".into(),
+            endpoints: vec![LocalityLbEndpoints {
+                locality: Some(Locality {
+                    region: "us".into(),
+                    zone: "us-a".into(),
+                    sub_zone: String::new(),
+                }),
+                lb_endpoints: vec![
+                    endpoint("10.0.0.1", HEALTH_STATUS_HEALTHY),
+                    endpoint("::1", HEALTH_STATUS_UNKNOWN),
+                    endpoint("10.0.0.2", 2),
+                ],
+                load_balancing_weight: Some(10),
+                priority: 0,
+            }],
+        };
+        state
+            .apply(
+                CLUSTER_LOAD_ASSIGNMENT_TYPE,
+                &[any(CLUSTER_LOAD_ASSIGNMENT_TYPE, &assignment)],
+            )
+            .unwrap();
+
+        let config = state.config();
+        assert_eq!(config.cluster(), Some("backend"));
+        assert_eq!(config.lb_policy(), LbPolicy::LeastRequest);
+        assert_eq!(config.timeout(), Some(Duration::from_secs(2)));
+        assert_eq!(config.endpoints(), ["10.0.0.1:50051", "[::1]:50051"]);
+        assert!(state.endpoints()[0]
+            .attributes
+            .contains(&(attributes::ZONE, "us-a".to_string())));
+    }
+
+    #[test]
+    fn rejects_non_eds_cluster() {
+        let mut state = State::new("svc".into());
+        state
+            .apply(
+                ROUTE_CONFIGURATION_TYPE,
+                &[route_config("unsubscribed", "*", "backend")],
+            )
+            .unwrap();
+        assert!(state.subscriptions(CLUSTER_TYPE).is_empty());
+
+        state.cluster = Some("backend".into());
+        let cluster = Cluster {
+            name: "backend".into(),
+            r#type: 0,
+            eds_cluster_config: None,
+            lb_policy: 0,
+        };
+        assert!(state
+            .apply(CLUSTER_TYPE, &[any(CLUSTER_TYPE, &cluster)])
+            .is_err());
+    }
+
+    #[test]
+    fn virtual_host_selection() {
+        let host = |name: &str, domain: &str| VirtualHost {
+            name: name.into(),
+            domains: vec![domain.into()],
+            routes: Vec::new(),
+        };
+        let hosts = [
+            host("any", "*"),
+            host("suffix", "*.example.com"),
+            host("prefix", "api.*"),
+            host("exact", "api.example.com"),
+        ];
+
+        let name = |authority| select_virtual_host(&hosts, authority).map(|h| h.name.as_str());
+        assert_eq!(name("api.example.com"), Some("exact"));
+        assert_eq!(name("web.example.com"), Some("suffix"));
+        assert_eq!(name("api.test"), Some("prefix"));
+        assert_eq!(name("other"), Some("any"));
+        assert_eq!(select_virtual_host(&hosts[1..], "other"), None);
+    }
+}
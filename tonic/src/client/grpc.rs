@@ -1,18 +1,23 @@
 use crate::codec::EncodeBody;
 use crate::codec::{CompressionEncoding, EnabledCompressionEncodings};
-use crate::metadata::GRPC_CONTENT_TYPE;
+use crate::metadata::{MetadataLimits, ReservedHeaderPolicy, GRPC_CONTENT_TYPE};
 use crate::{
     body::Body,
     client::GrpcService,
-    codec::{Codec, Decoder, Streaming},
+    codec::{
+        validate_message, Codec, CodecExecutor, Decoder, MessageKind, MessageValidators, Streaming,
+        ValidatingDecoder,
+    },
     request::SanitizeHeaders,
     Code, Request, Response, Status,
 };
+use bytes::Buf;
 use http::{
     header::{HeaderValue, CONTENT_TYPE, TE},
     uri::{PathAndQuery, Uri},
 };
 use http_body::Body as HttpBody;
+use http_body_util::BodyExt;
 use std::{fmt, future, pin::pin};
 use tokio_stream::{Stream, StreamExt};
 
@@ -44,6 +49,12 @@ struct GrpcConfig {
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
     max_encoding_message_size: Option<usize>,
+    /// Limits the metadata of requests.
+    metadata_limits: MetadataLimits,
+    /// Record protocol violations of the server.
+    protocol_diagnostics: bool,
+    /// Validates requests and responses.
+    validators: Option<MessageValidators>,
 }
 
 impl<T> Grpc<T> {
@@ -65,6 +76,9 @@ impl<T> Grpc<T> {
                 accept_compression_encodings: EnabledCompressionEncodings::default(),
                 max_decoding_message_size: None,
                 max_encoding_message_size: None,
+                metadata_limits: MetadataLimits::default(),
+                protocol_diagnostics: false,
+                validators: None,
             },
         }
     }
@@ -191,6 +205,58 @@ impl<T> Grpc<T> {
         self
     }
 
+    /// Limits the size of the metadata of requests, counted like HTTP/2 counts the size of a
+    /// header list: the length of the name and the value of each entry plus 32 bytes.
+    ///
+    /// Requests exceeding the limit fail locally with a `RESOURCE_EXHAUSTED` status, instead of
+    /// being rejected by the server or by the HTTP/2 layer. Defaults to no limit.
+    pub fn max_metadata_size(mut self, limit: usize) -> Self {
+        self.config.metadata_limits.max_size = Some(limit);
+        self
+    }
+
+    /// Limits the number of entries of the metadata of requests.
+    ///
+    /// Requests exceeding the limit fail locally with a `RESOURCE_EXHAUSTED` status. Defaults to
+    /// no limit.
+    pub fn max_metadata_entries(mut self, limit: usize) -> Self {
+        self.config.metadata_limits.max_entries = Some(limit);
+        self
+    }
+
+    /// Sets what to do with reserved headers, e.g. `grpc-encoding` or `connection`, set in the
+    /// metadata of requests.
+    ///
+    /// See [`ReservedHeaderPolicy`] for the headers that are reserved. Defaults to
+    /// [`ReservedHeaderPolicy::Allow`].
+    pub fn reserved_header_policy(mut self, policy: ReservedHeaderPolicy) -> Self {
+        self.config.metadata_limits.reserved_headers = Some(policy);
+        self
+    }
+
+    /// Enable protocol diagnostics.
+    ///
+    /// Responses of inconsistent servers, e.g. sending a `grpc-status` in both the headers and the
+    /// trailers, or sending data after a trailers-only response, are always logged. With protocol
+    /// diagnostics enabled the offending frames are additionally recorded and made available
+    /// through [`Streaming::protocol_violations`], which is useful for interop bug reports.
+    ///
+    /// Disabled by default.
+    pub fn protocol_diagnostics(mut self, enabled: bool) -> Self {
+        self.config.protocol_diagnostics = enabled;
+        self
+    }
+
+    /// Validate requests before sending them and responses before returning them.
+    ///
+    /// Requests that are not valid fail with an `INVALID_ARGUMENT` status, before reaching the
+    /// server for unary and server streaming calls, and responses with an `INTERNAL` status.
+    /// See [`MessageValidators`] for more details.
+    pub fn message_validators(mut self, validators: MessageValidators) -> Self {
+        self.config.validators = Some(validators);
+        self
+    }
+
     /// Check if the inner [`GrpcService`] is able to accept a  new request.
     ///
     /// This will call [`GrpcService::poll_ready`] until it returns ready or
@@ -218,6 +284,9 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        self.config.validate_single_message(&request)?;
+        #[cfg(feature = "service-config")]
+        let request = mark_single_message(request);
         let request = request.map(|m| tokio_stream::once(m));
         self.client_streaming(request, path, codec).await
     }
@@ -259,6 +328,57 @@ impl<T> Grpc<T> {
         Ok(Response::from_parts(parts, message, extensions))
     }
 
+    /// Send a unary gRPC request whose message is streamed from a reader.
+    ///
+    /// The message is sent uncompressed and not subject to the maximum encoding message size.
+    #[cfg(any(feature = "server", feature = "channel"))]
+    pub async fn streamed_unary<R, M2, C>(
+        &mut self,
+        request: Request<crate::codec::StreamedMessage<R>>,
+        path: PathAndQuery,
+        mut codec: C,
+    ) -> Result<Response<M2>, Status>
+    where
+        T: GrpcService<Body>,
+        T::ResponseBody: HttpBody + Send + 'static,
+        <T::ResponseBody as HttpBody>::Error: Into<crate::BoxError>,
+        R: tokio::io::AsyncRead + Send + 'static,
+        C: Codec<Decode = M2>,
+        M2: Send + Sync + 'static,
+    {
+        // Not marked as a single message request, as the message is too large to be buffered
+        // for retries.
+        let request = self.config.prepare_request(request.map(Body::new), path);
+
+        let response = self
+            .inner
+            .call(request)
+            .await
+            .map_err(Status::from_error_generic)?;
+
+        let (mut parts, body, extensions) = self
+            .create_response(self.config.response_decoder(&mut codec), response)
+            .await?
+            .into_parts();
+
+        let mut body = pin!(body);
+
+        let message = body
+            .try_next()
+            .await
+            .map_err(|mut status| {
+                status.metadata_mut().merge(parts.clone());
+                status
+            })?
+            .ok_or_else(|| Status::internal("Missing response message."))?;
+
+        if let Some(trailers) = body.trailers().await? {
+            parts.merge(trailers);
+        }
+
+        Ok(Response::from_parts(parts, message, extensions))
+    }
+
     /// Send a server side streaming gRPC request.
     pub async fn server_streaming<M1, M2, C>(
         &mut self,
@@ -274,6 +394,9 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        self.config.validate_single_message(&request)?;
+        #[cfg(feature = "service-config")]
+        let request = mark_single_message(request);
         let request = request.map(|m| tokio_stream::once(m));
         self.streaming(request, path, codec).await
     }
@@ -281,7 +404,7 @@ impl<T> Grpc<T> {
     /// Send a bi-directional streaming gRPC request.
     pub async fn streaming<S, M1, M2, C>(
         &mut self,
-        request: Request<S>,
+        mut request: Request<S>,
         path: PathAndQuery,
         mut codec: C,
     ) -> Result<Response<Streaming<M2>>, Status>
@@ -294,14 +417,23 @@ impl<T> Grpc<T> {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        #[cfg(any(feature = "server", feature = "channel"))]
+        crate::service::deadline_propagation::apply(&mut request)?;
+        self.config
+            .metadata_limits
+            .enforce("request", request.metadata_mut())?;
+
+        let executor = request.extensions().get::<CodecExecutor>().cloned();
+        let validators = self.config.validators.clone();
         let request = request
             .map(|s| {
                 EncodeBody::new_client(
                     codec.encoder(),
-                    s.map(Ok),
+                    s.map(move |m| validate_message(validators.as_ref(), MessageKind::Request, m)),
                     self.config.send_compression_encodings,
                     self.config.max_encoding_message_size,
                 )
+                .with_executor(executor)
             })
             .map(Body::new);
 
@@ -313,14 +445,14 @@ impl<T> Grpc<T> {
             .await
             .map_err(Status::from_error_generic)?;
 
-        let decoder = codec.decoder();
+        let decoder = self.config.response_decoder(&mut codec);
 
-        self.create_response(decoder, response)
+        self.create_response(decoder, response).await
     }
 
     // Keeping this code in a separate function from Self::streaming lets functions that return the
     // same output share the generated binary code
-    fn create_response<M2>(
+    async fn create_response<M2>(
         &self,
         decoder: impl Decoder<Item = M2, Error = Status> + Send + 'static,
         response: http::Response<T::ResponseBody>,
@@ -338,6 +470,16 @@ impl<T> Grpc<T> {
         let status_code = response.status();
         let trailers_only_status = Status::from_header_map(response.headers());
 
+        // A response without `grpc-status` and a non 200 status code was most likely not produced
+        // by a gRPC server but by something in between, e.g. a proxy answering with an error page.
+        if trailers_only_status.is_none() && status_code != http::StatusCode::OK {
+            let snippet = read_body_snippet(response.into_body()).await;
+            return Err(crate::status::status_from_http_status(
+                status_code,
+                Some(&snippet),
+            ));
+        }
+
         // We do not need to check for trailers if the `grpc-status` header is present
         // with a valid code.
         let expect_additional_trailers = if let Some(status) = trailers_only_status {
@@ -350,6 +492,7 @@ impl<T> Grpc<T> {
             true
         };
 
+        let executor = response.extensions().get::<CodecExecutor>().cloned();
         let response = response.map(|body| {
             if expect_additional_trailers {
                 Streaming::new_response(
@@ -359,6 +502,9 @@ impl<T> Grpc<T> {
                     encoding,
                     self.config.max_decoding_message_size,
                 )
+                .with_executor(executor)
+            } else if self.config.protocol_diagnostics {
+                Streaming::new_empty(decoder, body).with_protocol_diagnostics()
             } else {
                 Streaming::new_empty(decoder, body)
             }
@@ -368,7 +514,63 @@ impl<T> Grpc<T> {
     }
 }
 
+/// The maximum number of bytes of a non-gRPC error response body included in the `Status`.
+const BODY_SNIPPET_LEN: usize = 256;
+
+/// Reads up to [`BODY_SNIPPET_LEN`] bytes of `body`, lossily converted to UTF-8.
+async fn read_body_snippet<B>(body: B) -> String
+where
+    B: HttpBody,
+{
+    let mut body = pin!(body);
+    let mut snippet = Vec::new();
+
+    while snippet.len() < BODY_SNIPPET_LEN {
+        match body.frame().await {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    let mut data = data.chunk();
+                    data = &data[..data.len().min(BODY_SNIPPET_LEN - snippet.len())];
+                    snippet.extend_from_slice(data);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    String::from_utf8_lossy(&snippet).trim().to_string()
+}
+
+/// Marks requests that carry exactly one message, which middleware such as the
+/// service config layer may buffer in order to retry them.
+#[cfg(feature = "service-config")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SingleMessageRequest;
+
+#[cfg(feature = "service-config")]
+fn mark_single_message<M>(mut request: Request<M>) -> Request<M> {
+    request.extensions_mut().insert(SingleMessageRequest);
+    request
+}
+
 impl GrpcConfig {
+    /// Validates the message of a single message request before the call is made, so that an
+    /// invalid request fails before reaching the server.
+    fn validate_single_message<M: 'static>(&self, request: &Request<M>) -> Result<(), Status> {
+        match &self.validators {
+            Some(validators) => validators.validate(MessageKind::Request, request.get_ref()),
+            None => Ok(()),
+        }
+    }
+
+    fn response_decoder<C: Codec>(&self, codec: &mut C) -> ValidatingDecoder<C::Decoder> {
+        ValidatingDecoder::new(
+            codec.decoder(),
+            self.validators.clone(),
+            MessageKind::Response,
+        )
+    }
+
     fn prepare_request(&self, request: Request<Body>, path: PathAndQuery) -> http::Request<Body> {
         let mut parts = self.origin.clone().into_parts();
 
@@ -436,6 +638,9 @@ impl<T: Clone> Clone for Grpc<T> {
                 accept_compression_encodings: self.config.accept_compression_encodings,
                 max_encoding_message_size: self.config.max_encoding_message_size,
                 max_decoding_message_size: self.config.max_decoding_message_size,
+                metadata_limits: self.config.metadata_limits,
+                protocol_diagnostics: self.config.protocol_diagnostics,
+                validators: self.config.validators.clone(),
             },
         }
     }
@@ -462,6 +667,9 @@ impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
                 "max_encoding_message_size",
                 &self.config.max_encoding_message_size,
             )
+            .field("metadata_limits", &self.config.metadata_limits)
+            .field("protocol_diagnostics", &self.config.protocol_diagnostics)
+            .field("validators", &self.config.validators)
             .finish()
     }
 }
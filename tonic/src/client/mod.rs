@@ -20,4 +20,6 @@ mod grpc;
 mod service;
 
 pub use self::grpc::Grpc;
+#[cfg(feature = "service-config")]
+pub(crate) use self::grpc::SingleMessageRequest;
 pub use self::service::GrpcService;
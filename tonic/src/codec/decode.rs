@@ -1,19 +1,23 @@
 use super::compression::{decompress, CompressionEncoding, CompressionSettings};
+use super::diagnostics::{ProtocolViolation, ProtocolViolationKind};
+use super::executor::{CodecExecutor, Offloaded};
 use super::{BufferSettings, DecodeBuf, Decoder, DEFAULT_MAX_RECV_MESSAGE_SIZE, HEADER_SIZE};
 use crate::{body::Body, metadata::MetadataMap, Code, Status};
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use http::{HeaderMap, StatusCode};
 use http_body::Body as HttpBody;
 use http_body_util::BodyExt;
 use std::{
-    fmt, future,
+    fmt,
+    future::{self, Future},
+    io,
     pin::Pin,
     task::ready,
     task::{Context, Poll},
 };
 use sync_wrapper::SyncWrapper;
 use tokio_stream::Stream;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 /// Streaming requests and responses.
 ///
@@ -29,10 +33,15 @@ struct StreamingInner {
     state: State,
     direction: Direction,
     buf: BytesMut,
+    buffer_settings: BufferSettings,
     trailers: Option<HeaderMap>,
     decompress_buf: BytesMut,
     encoding: Option<CompressionEncoding>,
     max_message_size: Option<usize>,
+    protocol_violations: Option<Vec<ProtocolViolation>>,
+    messages_received: u64,
+    executor: Option<CodecExecutor>,
+    decompressing: Option<Offloaded<io::Result<BytesMut>>>,
 }
 
 impl<T> Unpin for Streaming<T> {}
@@ -44,6 +53,10 @@ enum State {
         compression: Option<CompressionEncoding>,
         len: usize,
     },
+    /// The message is being decompressed on the [`CodecExecutor`].
+    Decompressing,
+    /// The decompressed message is in `decompress_buf`.
+    Decompressed,
     Error(Option<Status>),
 }
 
@@ -122,7 +135,7 @@ impl<T> Streaming<T> {
         B::Error: Into<crate::BoxError>,
         D: Decoder<Item = T, Error = Status> + Send + 'static,
     {
-        let buffer_size = decoder.buffer_settings().buffer_size;
+        let buffer_settings = decoder.buffer_settings();
         Self {
             decoder: SyncWrapper::new(Box::new(decoder)),
             inner: StreamingInner {
@@ -134,14 +147,53 @@ impl<T> Streaming<T> {
                 )),
                 state: State::ReadHeader,
                 direction,
-                buf: BytesMut::with_capacity(buffer_size),
+                // Allocated once the data received does not fit in a small message, see
+                // `StreamingInner::buffer`.
+                buf: BytesMut::new(),
+                buffer_settings,
                 trailers: None,
                 decompress_buf: BytesMut::new(),
                 encoding,
                 max_message_size,
+                protocol_violations: None,
+                messages_received: 0,
+                executor: None,
+                decompressing: None,
             },
         }
     }
+
+    /// Decompress large messages on `executor`.
+    pub(crate) fn with_executor(mut self, executor: Option<CodecExecutor>) -> Self {
+        self.inner.executor = executor;
+        self
+    }
+
+    /// Record the protocol violations of the peer, see [`Streaming::protocol_violations`].
+    pub(crate) fn with_protocol_diagnostics(mut self) -> Self {
+        self.inner.protocol_violations = Some(Vec::new());
+        self
+    }
+
+    /// The protocol violations of the peer detected so far.
+    ///
+    /// Violations are only recorded when protocol diagnostics are enabled on the client, see
+    /// [`Grpc::protocol_diagnostics`](crate::client::Grpc::protocol_diagnostics). Otherwise they
+    /// are only logged and this returns an empty slice.
+    pub fn protocol_violations(&self) -> &[ProtocolViolation] {
+        self.inner
+            .protocol_violations
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// The number of messages decoded from this stream so far.
+    ///
+    /// After a mid-stream failure this tells how far the stream got, which is what a caller
+    /// needs to resume it.
+    pub fn messages_received(&self) -> u64 {
+        self.inner.messages_received
+    }
 }
 
 impl StreamingInner {
@@ -210,25 +262,27 @@ impl StreamingInner {
             }
 
             let decode_buf = if let Some(encoding) = compression {
+                let settings = CompressionSettings {
+                    encoding,
+                    buffer_growth_interval: buffer_settings.buffer_size,
+                };
+
+                if let Some(executor) = self.executor.as_ref().filter(|e| e.offloads(len)) {
+                    let mut compressed = self.buf.split_to(len);
+                    self.decompressing = Some(executor.spawn(move || {
+                        let mut decompressed = BytesMut::new();
+                        decompress(settings, &mut compressed, &mut decompressed, len)
+                            .map(|()| decompressed)
+                    }));
+                    self.state = State::Decompressing;
+                    return Ok(None);
+                }
+
                 self.decompress_buf.clear();
 
-                if let Err(err) = decompress(
-                    CompressionSettings {
-                        encoding,
-                        buffer_growth_interval: buffer_settings.buffer_size,
-                    },
-                    &mut self.buf,
-                    &mut self.decompress_buf,
-                    len,
-                ) {
-                    let message = if let Direction::Response(status) = self.direction {
-                        format!(
-                            "Error decompressing: {err}, while receiving response with status: {status}"
-                        )
-                    } else {
-                        format!("Error decompressing: {err}, while sending request")
-                    };
-                    return Err(Status::internal(message));
+                if let Err(err) = decompress(settings, &mut self.buf, &mut self.decompress_buf, len)
+                {
+                    return Err(self.decompress_error(err));
                 }
                 let decompressed_len = self.decompress_buf.len();
                 DecodeBuf::new(&mut self.decompress_buf, decompressed_len)
@@ -239,9 +293,52 @@ impl StreamingInner {
             return Ok(Some(decode_buf));
         }
 
+        if let State::Decompressed = self.state {
+            let decompressed_len = self.decompress_buf.len();
+            return Ok(Some(DecodeBuf::new(
+                &mut self.decompress_buf,
+                decompressed_len,
+            )));
+        }
+
         Ok(None)
     }
 
+    /// Waits for the message being decompressed on the executor, if any.
+    fn poll_decompressed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        let Some(decompressing) = &mut self.decompressing else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let result = ready!(Pin::new(decompressing).poll(cx)).unwrap_or_else(|| {
+            Err(io::Error::other(
+                "the codec executor dropped the decompression task",
+            ))
+        });
+        self.decompressing = None;
+
+        match result {
+            Ok(decompressed) => {
+                self.decompress_buf = decompressed;
+                self.state = State::Decompressed;
+                Poll::Ready(Ok(()))
+            }
+            Err(err) => {
+                self.state = State::Error(None);
+                Poll::Ready(Err(self.decompress_error(err)))
+            }
+        }
+    }
+
+    fn decompress_error(&self, err: io::Error) -> Status {
+        let message = if let Direction::Response(status) = self.direction {
+            format!("Error decompressing: {err}, while receiving response with status: {status}")
+        } else {
+            format!("Error decompressing: {err}, while sending request")
+        };
+        Status::internal(message)
+    }
+
     // Returns Some(()) if data was found or None if the loop in `poll_next` should break
     fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<()>, Status>> {
         let frame = match ready!(Pin::new(self.body.get_mut()).poll_frame(cx)) {
@@ -266,8 +363,12 @@ impl StreamingInner {
             }
         };
 
+        if self.direction == Direction::EmptyResponse {
+            self.check_trailers_only_frame(&frame);
+        }
+
         Poll::Ready(if frame.is_data() {
-            self.buf.put(frame.into_data().unwrap());
+            self.buffer(frame.into_data().unwrap());
             Ok(Some(()))
         } else if frame.is_trailers() {
             if let Some(trailers) = &mut self.trailers {
@@ -282,6 +383,47 @@ impl StreamingInner {
         })
     }
 
+    /// Appends `data` to the buffer.
+    ///
+    /// When the buffer has no room for it, small data is taken as the buffer instead, without
+    /// copying it if it is not shared, rather than allocating `buffer_size` bytes for every RPC.
+    fn buffer(&mut self, data: Bytes) {
+        let spare = self.buf.capacity() - self.buf.len();
+        if spare >= data.len() {
+            self.buf.put(data);
+        } else if self.buf.is_empty()
+            && data.len() <= HEADER_SIZE + self.buffer_settings.small_message_size
+        {
+            self.buf = data
+                .try_into_mut()
+                .unwrap_or_else(|data| BytesMut::from(&data[..]));
+        } else {
+            self.buf
+                .reserve(data.len().max(self.buffer_settings.buffer_size));
+            self.buf.put(data);
+        }
+    }
+
+    /// Checks a frame received after a trailers-only response, which should not have any.
+    fn check_trailers_only_frame(&mut self, frame: &http_body::Frame<bytes::Bytes>) {
+        let violation = if let Some(data) = frame.data_ref() {
+            ProtocolViolation::data(ProtocolViolationKind::DataAfterTrailersOnly, data)
+        } else if let Some(trailers) = frame
+            .trailers_ref()
+            .filter(|trailers| trailers.contains_key(Status::GRPC_STATUS))
+        {
+            ProtocolViolation::trailers(ProtocolViolationKind::StatusInHeadersAndTrailers, trailers)
+        } else {
+            return;
+        };
+
+        warn!("{}", violation);
+
+        if let Some(violations) = &mut self.protocol_violations {
+            violations.push(violation);
+        }
+    }
+
     fn response(&mut self) -> Result<(), Status> {
         if let Direction::Response(status) = self.direction {
             if let Err(Some(e)) = crate::status::infer_grpc_status(self.trailers.as_ref(), status) {
@@ -367,6 +509,36 @@ impl<T> Streaming<T> {
         Ok(None)
     }
 
+    /// Drain the stream, returning the messages received together with how the stream ended.
+    ///
+    /// Unlike draining the stream with `?`, a failure in the middle of the stream does not
+    /// discard the messages that arrived before it: they are returned alongside the terminal
+    /// [`Status`]. When the stream completes successfully, its trailing metadata is returned
+    /// instead.
+    ///
+    /// ```rust
+    /// # use tonic::{Streaming, Status};
+    /// # async fn partial_ex<T>(request: Streaming<T>) {
+    /// let (messages, end) = request.into_parts_on_error().await;
+    /// if let Err(status) = end {
+    ///     println!("failed after {} messages: {}", messages.len(), status);
+    /// }
+    /// # }
+    /// ```
+    pub async fn into_parts_on_error(mut self) -> (Vec<T>, Result<Option<MetadataMap>, Status>) {
+        let mut messages = Vec::new();
+        loop {
+            match self.message().await {
+                Ok(Some(message)) => messages.push(message),
+                Ok(None) => break,
+                Err(status) => return (messages, Err(status)),
+            }
+        }
+
+        let trailers = self.trailers().await;
+        (messages, trailers)
+    }
+
     fn decode_chunk(&mut self) -> Result<Option<T>, Status> {
         match self
             .inner
@@ -375,6 +547,7 @@ impl<T> Streaming<T> {
             Some(mut decode_buf) => match self.decoder.get_mut().decode(&mut decode_buf)? {
                 Some(msg) => {
                     self.inner.state = State::ReadHeader;
+                    self.inner.messages_received += 1;
                     Ok(Some(msg))
                 }
                 None => Ok(None),
@@ -400,6 +573,11 @@ impl<T> Stream for Streaming<T> {
                 return Poll::Ready(Some(Ok(item)));
             }
 
+            if let State::Decompressing = self.inner.state {
+                ready!(self.inner.poll_decompressed(cx))?;
+                continue;
+            }
+
             if ready!(self.inner.poll_frame(cx))?.is_none() {
                 match self.inner.response() {
                     Ok(()) => return Poll::Ready(None),
@@ -0,0 +1,81 @@
+use bytes::Bytes;
+use http::HeaderMap;
+use std::fmt;
+
+/// The maximum number of bytes of a data frame kept in a [`ProtocolViolation`].
+const MAX_RECORDED_DATA: usize = 64;
+
+/// A violation of the gRPC protocol by the peer, detected while receiving a response.
+///
+/// Violations are only recorded when protocol diagnostics are enabled on the client, see
+/// [`Grpc::protocol_diagnostics`](crate::client::Grpc::protocol_diagnostics), and can be
+/// retrieved using [`Streaming::protocol_violations`](super::Streaming::protocol_violations).
+#[derive(Debug, Clone)]
+pub struct ProtocolViolation {
+    kind: ProtocolViolationKind,
+    frame: String,
+}
+
+/// The kind of a [`ProtocolViolation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProtocolViolationKind {
+    /// The response headers carried a `grpc-status`, making it a trailers-only response, but
+    /// data frames followed.
+    DataAfterTrailersOnly,
+    /// The response headers carried a `grpc-status`, making it a trailers-only response, but
+    /// trailers carrying another `grpc-status` followed.
+    StatusInHeadersAndTrailers,
+}
+
+impl ProtocolViolation {
+    pub(crate) fn data(kind: ProtocolViolationKind, data: &Bytes) -> Self {
+        let recorded = &data[..data.len().min(MAX_RECORDED_DATA)];
+        Self {
+            kind,
+            frame: format!(
+                "DATA ({} bytes): {:?}",
+                data.len(),
+                Bytes::copy_from_slice(recorded)
+            ),
+        }
+    }
+
+    pub(crate) fn trailers(kind: ProtocolViolationKind, trailers: &HeaderMap) -> Self {
+        Self {
+            kind,
+            frame: format!("TRAILERS: {trailers:?}"),
+        }
+    }
+
+    /// The kind of this violation.
+    pub fn kind(&self) -> ProtocolViolationKind {
+        self.kind
+    }
+
+    /// A description of the offending frame, suitable for bug reports.
+    ///
+    /// Only the first bytes of data frames are recorded.
+    pub fn frame(&self) -> &str {
+        &self.frame
+    }
+}
+
+impl fmt::Display for ProtocolViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            ProtocolViolationKind::DataAfterTrailersOnly => {
+                "received data after a trailers-only response"
+            }
+            ProtocolViolationKind::StatusInHeadersAndTrailers => {
+                "received grpc-status in both headers and trailers"
+            }
+        };
+
+        write!(
+            f,
+            "protocol violation: {description}, offending frame: {}",
+            self.frame
+        )
+    }
+}
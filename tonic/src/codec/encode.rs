@@ -1,23 +1,38 @@
 use super::compression::{
     compress, CompressionEncoding, CompressionSettings, SingleMessageCompressionOverride,
 };
+use super::executor::{CodecExecutor, Offloaded};
 use super::{BufferSettings, EncodeBuf, Encoder, DEFAULT_MAX_SEND_MESSAGE_SIZE, HEADER_SIZE};
-use crate::Status;
+use crate::metadata::{MergePolicies, MergePolicy};
+use crate::{ResponseTrailers, Status};
 use bytes::{BufMut, Bytes, BytesMut};
 use http::HeaderMap;
 use http_body::{Body, Frame};
 use pin_project::pin_project;
 use std::{
+    cell::RefCell,
+    future::Future,
+    io,
     pin::Pin,
     task::{ready, Context, Poll},
 };
 use tokio_stream::{adapters::Fuse, Stream, StreamExt};
 
+thread_local! {
+    /// The buffer messages are encoded into when the buffer of their RPC has no room left, see
+    /// [`encode_unbuffered`].
+    static SCRATCH: RefCell<BytesMut> = RefCell::new(BytesMut::new());
+}
+
 /// Combinator for efficient encoding of messages into reasonably sized buffers.
 /// EncodedBytes encodes ready messages from its delegate stream into a BytesMut,
 /// splitting off and yielding a buffer when either:
 ///  * The delegate stream polls as not ready, or
 ///  * The encoded buffer surpasses YIELD_THRESHOLD.
+///
+/// Messages compressed on the executor are not copied into the buffer: their header ends the
+/// buffer yielded before them, and their payload is yielded as is, so that the transport can
+/// write both with a single vectored write.
 #[pin_project(project = EncodedBytesProj)]
 #[derive(Debug)]
 struct EncodedBytes<T, U> {
@@ -29,6 +44,9 @@ struct EncodedBytes<T, U> {
     buf: BytesMut,
     uncompression_buf: BytesMut,
     error: Option<Status>,
+    executor: Option<CodecExecutor>,
+    compressing: Option<Offloaded<io::Result<BytesMut>>>,
+    payload: Option<Bytes>,
 }
 
 impl<T: Encoder, U: Stream> EncodedBytes<T, U> {
@@ -40,7 +58,8 @@ impl<T: Encoder, U: Stream> EncodedBytes<T, U> {
         max_message_size: Option<usize>,
     ) -> Self {
         let buffer_settings = encoder.buffer_settings();
-        let buf = BytesMut::with_capacity(buffer_settings.buffer_size);
+        // Allocated once a message does not fit in a small message, see `encode_unbuffered`.
+        let buf = BytesMut::new();
 
         let compression_encoding =
             if compression_override == SingleMessageCompressionOverride::Disable {
@@ -63,6 +82,9 @@ impl<T: Encoder, U: Stream> EncodedBytes<T, U> {
             buf,
             uncompression_buf,
             error: None,
+            executor: None,
+            compressing: None,
+            payload: None,
         }
     }
 }
@@ -83,14 +105,58 @@ where
             buf,
             uncompression_buf,
             error,
+            executor,
+            compressing,
+            payload,
         } = self.project();
         let buffer_settings = encoder.buffer_settings();
 
+        if let Some(payload) = payload.take() {
+            return Poll::Ready(Some(Ok(payload)));
+        }
+
         if let Some(status) = error.take() {
             return Poll::Ready(Some(Err(status)));
         }
 
         loop {
+            // Messages are sent in order, so wait for the one being compressed on the executor
+            // before encoding the next, sending what was encoded before it in the meantime.
+            if let Some(pending) = compressing {
+                let result = match Pin::new(pending).poll(cx) {
+                    Poll::Pending if buf.is_empty() => return Poll::Pending,
+                    Poll::Pending => {
+                        return Poll::Ready(Some(Ok(buf.split_to(buf.len()).freeze())));
+                    }
+                    Poll::Ready(result) => result,
+                };
+                *compressing = None;
+
+                let result = result
+                    .unwrap_or_else(|| {
+                        Err(io::Error::other(
+                            "the codec executor dropped the compression task",
+                        ))
+                    })
+                    .map_err(|err| Status::internal(format!("Error compressing: {err}")))
+                    .and_then(|compressed| {
+                        put_header(
+                            buf,
+                            compressed.len(),
+                            *compression_encoding,
+                            *max_message_size,
+                        )?;
+                        Ok(compressed)
+                    });
+                match result {
+                    Ok(compressed) => {
+                        *payload = Some(compressed.freeze());
+                        return Poll::Ready(Some(Ok(buf.split_to(buf.len()).freeze())));
+                    }
+                    Err(status) => return Poll::Ready(Some(Err(status))),
+                }
+            }
+
             match source.as_mut().poll_next(cx) {
                 Poll::Pending if buf.is_empty() => {
                     return Poll::Pending;
@@ -102,16 +168,22 @@ where
                     return Poll::Ready(Some(Ok(buf.split_to(buf.len()).freeze())));
                 }
                 Poll::Ready(Some(Ok(item))) => {
-                    if let Err(status) = encode_item(
+                    match encode_item(
                         encoder,
                         buf,
                         uncompression_buf,
                         *compression_encoding,
                         *max_message_size,
                         buffer_settings,
+                        executor.as_ref(),
                         item,
                     ) {
-                        return Poll::Ready(Some(Err(status)));
+                        Ok(Some(pending)) => {
+                            *compressing = Some(pending);
+                            continue;
+                        }
+                        Ok(None) => {}
+                        Err(status) => return Poll::Ready(Some(Err(status))),
                     }
 
                     if buf.len() >= buffer_settings.yield_threshold {
@@ -130,6 +202,9 @@ where
     }
 }
 
+/// Encodes `item` into `buf`, unless it is compressed on the `executor`, in which case the
+/// pending compression is returned instead.
+#[allow(clippy::too_many_arguments)]
 fn encode_item<T>(
     encoder: &mut T,
     buf: &mut BytesMut,
@@ -137,18 +212,14 @@ fn encode_item<T>(
     compression_encoding: Option<CompressionEncoding>,
     max_message_size: Option<usize>,
     buffer_settings: BufferSettings,
+    executor: Option<&CodecExecutor>,
     item: T::Item,
-) -> Result<(), Status>
+) -> Result<Option<Offloaded<io::Result<BytesMut>>>, Status>
 where
     T: Encoder<Error = Status>,
 {
     let offset = buf.len();
 
-    buf.reserve(HEADER_SIZE);
-    unsafe {
-        buf.advance_mut(HEADER_SIZE);
-    }
-
     if let Some(encoding) = compression_encoding {
         uncompression_buf.clear();
 
@@ -157,33 +228,132 @@ where
             .map_err(|err| Status::internal(format!("Error encoding: {err}")))?;
 
         let uncompressed_len = uncompression_buf.len();
+        let settings = CompressionSettings {
+            encoding,
+            buffer_growth_interval: buffer_settings.buffer_size,
+        };
 
-        compress(
-            CompressionSettings {
-                encoding,
-                buffer_growth_interval: buffer_settings.buffer_size,
-            },
-            uncompression_buf,
-            buf,
-            uncompressed_len,
-        )
-        .map_err(|err| Status::internal(format!("Error compressing: {err}")))?;
+        if let Some(executor) = executor.filter(|e| e.offloads(uncompressed_len)) {
+            let mut uncompressed = uncompression_buf.split();
+            return Ok(Some(executor.spawn(move || {
+                let mut compressed = BytesMut::new();
+                compress(
+                    settings,
+                    &mut uncompressed,
+                    &mut compressed,
+                    uncompressed_len,
+                )
+                .map(|()| compressed)
+            })));
+        }
+
+        if buf.capacity() == 0 {
+            buf.reserve(buffer_settings.buffer_size);
+        }
+        buf.reserve(HEADER_SIZE);
+        unsafe {
+            buf.advance_mut(HEADER_SIZE);
+        }
+
+        compress(settings, uncompression_buf, buf, uncompressed_len)
+            .map_err(|err| Status::internal(format!("Error compressing: {err}")))?;
+    } else if buf.capacity() == 0 {
+        encode_unbuffered(encoder, buf, buffer_settings, item)?;
     } else {
+        buf.reserve(HEADER_SIZE);
+        unsafe {
+            buf.advance_mut(HEADER_SIZE);
+        }
+
         encoder
             .encode(item, &mut EncodeBuf::new(buf))
             .map_err(|err| Status::internal(format!("Error encoding: {err}")))?;
     }
 
     // now that we know length, we can write the header
-    finish_encoding(compression_encoding, max_message_size, &mut buf[offset..])
+    let len = buf.len() - offset - HEADER_SIZE;
+    finish_encoding(
+        compression_encoding,
+        max_message_size,
+        len,
+        &mut buf[offset..offset + HEADER_SIZE],
+    )?;
+    Ok(None)
+}
+
+/// Encodes `item` into `buf`, which has no room left, with a zeroed header.
+///
+/// Rather than allocating `buffer_size` bytes for every RPC, the message is encoded into a
+/// thread-local buffer reused across RPCs. A small message is then copied into an allocation of
+/// its exact size, while a larger one takes the thread-local buffer over.
+fn encode_unbuffered<T>(
+    encoder: &mut T,
+    buf: &mut BytesMut,
+    buffer_settings: BufferSettings,
+    item: T::Item,
+) -> Result<(), Status>
+where
+    T: Encoder<Error = Status>,
+{
+    SCRATCH.with(|scratch| {
+        // An encoder encoding other messages while encoding this one gets a buffer of its own.
+        let Ok(mut scratch) = scratch.try_borrow_mut() else {
+            return encode_framed(encoder, buf, buffer_settings, item);
+        };
+
+        scratch.clear();
+        encode_framed(encoder, &mut scratch, buffer_settings, item)?;
+        if scratch.len() <= HEADER_SIZE + buffer_settings.small_message_size {
+            buf.extend_from_slice(&scratch);
+        } else {
+            *buf = std::mem::take(&mut *scratch);
+        }
+        Ok(())
+    })
+}
+
+/// Appends a zeroed header and `item` to `dst`.
+fn encode_framed<T>(
+    encoder: &mut T,
+    dst: &mut BytesMut,
+    buffer_settings: BufferSettings,
+    item: T::Item,
+) -> Result<(), Status>
+where
+    T: Encoder<Error = Status>,
+{
+    dst.reserve(buffer_settings.buffer_size.max(HEADER_SIZE));
+    dst.put_bytes(0, HEADER_SIZE);
+    encoder
+        .encode(item, &mut EncodeBuf::new(dst))
+        .map_err(|err| Status::internal(format!("Error encoding: {err}")))
+}
+
+/// Appends the header of a message of `len` bytes compressed on the executor to `buf`, the
+/// payload being sent separately.
+fn put_header(
+    buf: &mut BytesMut,
+    len: usize,
+    compression_encoding: Option<CompressionEncoding>,
+    max_message_size: Option<usize>,
+) -> Result<(), Status> {
+    let offset = buf.len();
+    buf.put_bytes(0, HEADER_SIZE);
+
+    finish_encoding(
+        compression_encoding,
+        max_message_size,
+        len,
+        &mut buf[offset..],
+    )
 }
 
 fn finish_encoding(
     compression_encoding: Option<CompressionEncoding>,
     max_message_size: Option<usize>,
-    buf: &mut [u8],
+    len: usize,
+    header: &mut [u8],
 ) -> Result<(), Status> {
-    let len = buf.len() - HEADER_SIZE;
     let limit = max_message_size.unwrap_or(DEFAULT_MAX_SEND_MESSAGE_SIZE);
     if len > limit {
         return Err(Status::out_of_range(format!(
@@ -197,9 +367,9 @@ fn finish_encoding(
         )));
     }
     {
-        let mut buf = &mut buf[..HEADER_SIZE];
-        buf.put_u8(compression_encoding.is_some() as u8);
-        buf.put_u32(len as u32);
+        let mut header = &mut header[..HEADER_SIZE];
+        header.put_u8(compression_encoding.is_some() as u8);
+        header.put_u32(len as u32);
     }
 
     Ok(())
@@ -225,6 +395,7 @@ struct EncodeState {
     error: Option<Status>,
     role: Role,
     is_end_stream: bool,
+    trailers: Option<ResponseTrailers>,
 }
 
 impl<T: Encoder, U: Stream> EncodeBody<T, U> {
@@ -248,6 +419,7 @@ impl<T: Encoder, U: Stream> EncodeBody<T, U> {
                 error: None,
                 role: Role::Client,
                 is_end_stream: false,
+                trailers: None,
             },
         }
     }
@@ -273,11 +445,26 @@ impl<T: Encoder, U: Stream> EncodeBody<T, U> {
                 error: None,
                 role: Role::Server,
                 is_end_stream: false,
+                trailers: None,
             },
         }
     }
 }
 
+impl<T, U> EncodeBody<T, U> {
+    /// Compress large messages on `executor`.
+    pub(crate) fn with_executor(mut self, executor: Option<CodecExecutor>) -> Self {
+        self.inner.executor = executor;
+        self
+    }
+
+    /// Sends the metadata set through `trailers` with the trailers of a server response.
+    pub(crate) fn with_trailers(mut self, trailers: Option<ResponseTrailers>) -> Self {
+        self.state.trailers = trailers;
+        self
+    }
+}
+
 impl EncodeState {
     fn trailers(&mut self) -> Option<Result<HeaderMap, Status>> {
         match self.role {
@@ -293,10 +480,20 @@ impl EncodeState {
                 } else {
                     Status::ok("")
                 };
-                Some(status.to_header_map())
+                Some(self.status_trailers(status))
             }
         }
     }
+
+    fn status_trailers(&mut self, mut status: Status) -> Result<HeaderMap, Status> {
+        if let Some(trailers) = self.trailers.take() {
+            let policy = MergePolicies::new()
+                .ascii(MergePolicy::KeepFirst)
+                .binary(MergePolicy::KeepFirst);
+            status.metadata_mut().merge_with(trailers.take(), policy);
+        }
+        status.to_header_map()
+    }
 }
 
 impl<T, U> Body for EncodeBody<T, U>
@@ -322,7 +519,10 @@ where
                 Role::Client => Some(Err(status)).into(),
                 Role::Server => {
                     self_proj.state.is_end_stream = true;
-                    Some(Ok(Frame::trailers(status.to_header_map()?))).into()
+                    Some(Ok(Frame::trailers(
+                        self_proj.state.status_trailers(status)?,
+                    )))
+                    .into()
                 }
             },
             None => self_proj
@@ -0,0 +1,137 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+#[cfg(any(feature = "server", feature = "channel"))]
+const DEFAULT_MIN_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Runs the compression and decompression of large messages on an executor.
+///
+/// Compressing and decompressing messages of several megabytes takes long enough to stall the
+/// runtime thread that also drives many other streams. With a `CodecExecutor`, messages of at
+/// least [`min_message_size`](Self::min_message_size) bytes are compressed and decompressed in a
+/// task spawned on the executor, e.g. one backed by a blocking or compute pool, while the stream
+/// waits for the result.
+///
+/// Servers use it for requests and responses when set with
+/// [`Server::codec_executor`](crate::transport::Server::codec_executor). Clients use it for
+/// responses when set with
+/// [`Endpoint::codec_executor`](crate::transport::Endpoint::codec_executor), and for requests
+/// that carry it in their extensions.
+#[derive(Clone)]
+pub struct CodecExecutor {
+    spawn: Arc<dyn Fn(BoxFuture) + Send + Sync>,
+    min_message_size: usize,
+}
+
+impl CodecExecutor {
+    /// Creates a `CodecExecutor` that spawns the work onto `executor`.
+    #[cfg(any(feature = "server", feature = "channel"))]
+    pub fn new<E>(executor: E) -> Self
+    where
+        E: hyper::rt::Executor<BoxFuture> + Send + Sync + 'static,
+    {
+        Self {
+            spawn: Arc::new(move |fut| executor.execute(fut)),
+            min_message_size: DEFAULT_MIN_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets the size from which messages are compressed and decompressed on the executor.
+    ///
+    /// Smaller messages are processed inline, as handing them off costs more than it saves.
+    /// This is the size of the message as received for decompression, and as encoded for
+    /// compression. Defaults to 1 MiB.
+    pub fn min_message_size(self, size: usize) -> Self {
+        CodecExecutor {
+            min_message_size: size,
+            ..self
+        }
+    }
+
+    pub(crate) fn offloads(&self, len: usize) -> bool {
+        len >= self.min_message_size
+    }
+
+    /// Runs `f` on the executor and returns a future resolving to its result, or to `None` if
+    /// the executor dropped the task.
+    pub(crate) fn spawn<T, F>(&self, f: F) -> Offloaded<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            value: None,
+            waker: None,
+            done: false,
+        }));
+
+        let sender = Sender(shared.clone());
+        (self.spawn)(Box::pin(async move { sender.send(f()) }));
+
+        Offloaded(shared)
+    }
+}
+
+impl fmt::Debug for CodecExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodecExecutor")
+            .field("min_message_size", &self.min_message_size)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Shared<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    done: bool,
+}
+
+/// Hands the result of an offloaded task back, and wakes the waiting stream even if the task is
+/// dropped without running.
+struct Sender<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Sender<T> {
+    fn send(self, value: T) {
+        self.0.lock().unwrap().value = Some(value);
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.0.lock().unwrap();
+        shared.done = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The result of work spawned with [`CodecExecutor::spawn`].
+pub(crate) struct Offloaded<T>(Arc<Mutex<Shared<T>>>);
+
+impl<T> Future for Offloaded<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.0.lock().unwrap();
+        if shared.done {
+            return Poll::Ready(shared.value.take());
+        }
+
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> fmt::Debug for Offloaded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Offloaded").finish_non_exhaustive()
+    }
+}
@@ -5,14 +5,25 @@
 mod buffer;
 pub(crate) mod compression;
 mod decode;
+mod diagnostics;
 mod encode;
+mod executor;
+#[cfg(any(feature = "server", feature = "channel"))]
+mod streamed;
+mod validate;
 use crate::Status;
 use std::io;
 
 pub use self::buffer::{DecodeBuf, EncodeBuf};
 pub use self::compression::{CompressionEncoding, EnabledCompressionEncodings};
 pub use self::decode::Streaming;
+pub use self::diagnostics::{ProtocolViolation, ProtocolViolationKind};
 pub use self::encode::EncodeBody;
+pub use self::executor::CodecExecutor;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::streamed::{MessageSink, StreamedMessage};
+pub(crate) use self::validate::{validate_message, MessageKind, ValidatingDecoder};
+pub use self::validate::{FieldViolation, MessageValidators};
 
 // Doc hidden since this is used in a test in another crate, we can expose this publically later
 // if we need it.
@@ -25,6 +36,7 @@ pub use self::compression::SingleMessageCompressionOverride;
 /// you may find it too expensive.
 const DEFAULT_CODEC_BUFFER_SIZE: usize = 8 * 1024;
 const DEFAULT_YIELD_THRESHOLD: usize = 32 * 1024;
+const DEFAULT_SMALL_MESSAGE_SIZE: usize = 256;
 
 /// Settings for how tonic allocates and grows buffers.
 ///
@@ -63,10 +75,17 @@ const DEFAULT_YIELD_THRESHOLD: usize = 32 * 1024;
 /// not affect the responsiveness of your streaming rpc (for reasonable
 /// sizes of yield threshold).
 /// Yield threshold defaults to 32 KiB.
+///
+/// The buffers are only allocated once a message does not fit in a small
+/// message size. Smaller messages, like the requests and acks of chatty
+/// unary RPCs, are encoded into a reused thread-local buffer and copied into
+/// an allocation of their exact size, and are decoded straight from the
+/// received data when possible. Small message size defaults to 256 bytes.
 #[derive(Clone, Copy, Debug)]
 pub struct BufferSettings {
     buffer_size: usize,
     yield_threshold: usize,
+    small_message_size: usize,
 }
 
 impl BufferSettings {
@@ -75,16 +94,23 @@ impl BufferSettings {
         Self {
             buffer_size,
             yield_threshold,
+            small_message_size: DEFAULT_SMALL_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets the size in bytes up to which messages do not allocate a buffer of
+    /// `buffer_size` bytes. `0` disables the small message fast path.
+    pub fn small_message_size(self, small_message_size: usize) -> Self {
+        Self {
+            small_message_size,
+            ..self
         }
     }
 }
 
 impl Default for BufferSettings {
     fn default() -> Self {
-        Self {
-            buffer_size: DEFAULT_CODEC_BUFFER_SIZE,
-            yield_threshold: DEFAULT_YIELD_THRESHOLD,
-        }
+        Self::new(DEFAULT_CODEC_BUFFER_SIZE, DEFAULT_YIELD_THRESHOLD)
     }
 }
 
@@ -0,0 +1,445 @@
+//! Messages that are streamed from and to `AsyncRead`/`AsyncWrite` instead of memory.
+//!
+//! Encoding and decoding a message through a [`Codec`](super::Codec) requires the whole message
+//! to be in memory, which is impractical for messages of gigabytes. [`StreamedMessage`] sends
+//! a message whose payload is read in chunks from e.g. a file, and [`MessageSink`] writes the
+//! payload of received messages in chunks to e.g. a file.
+//!
+//! Both only handle the gRPC framing of the message. The payload is passed through untouched,
+//! or wrapped in a single protobuf `bytes` field with [`StreamedMessage::bytes_field`] and
+//! [`MessageSink::bytes_field`], so the other side can still decode it into a message like:
+//!
+//! ```proto
+//! message Artifact {
+//!   bytes data = 1;
+//! }
+//! ```
+
+use super::HEADER_SIZE;
+use crate::Status;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::HeaderMap;
+use http_body::{Body, Frame, SizeHint};
+use http_body_util::BodyExt;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::poll_fn,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A body that sends a single uncompressed message whose payload is read from `R`.
+///
+/// The payload is read in chunks of [`chunk_size`](Self::chunk_size) bytes as the body is
+/// polled, so only one chunk is held in memory at a time. As gRPC messages are length-prefixed,
+/// the length of the payload has to be known upfront, and reading fewer bytes from `R` fails the
+/// body.
+///
+/// ```rust,ignore
+/// let file = tokio::fs::File::open("artifact.tar").await?;
+/// let len = file.metadata().await?.len();
+///
+/// let request = Request::new(StreamedMessage::new(file, len).bytes_field(1));
+/// let mut client = Grpc::new(channel);
+/// client.ready().await?;
+/// let response: Response<UploadResponse> = client
+///     .streamed_unary(request, "/artifacts.Artifacts/Upload".parse()?, ProstCodec::default())
+///     .await?;
+/// ```
+#[pin_project]
+pub struct StreamedMessage<R> {
+    #[pin]
+    reader: R,
+    len: u64,
+    field: Option<u32>,
+    chunk_size: usize,
+    trailers: Option<HeaderMap>,
+    state: State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Payload,
+    Trailers,
+    Done,
+}
+
+impl<R> StreamedMessage<R> {
+    /// Creates a body that sends the `len` bytes read from `reader` as one message.
+    pub fn new(reader: R, len: u64) -> Self {
+        Self {
+            reader,
+            len,
+            field: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            trailers: None,
+            state: State::Header,
+        }
+    }
+
+    /// Sends the payload as the protobuf `bytes` field with the number `field`, i.e. as an
+    /// encoded message whose only field is the payload.
+    pub fn bytes_field(self, field: u32) -> Self {
+        StreamedMessage {
+            field: Some(field),
+            ..self
+        }
+    }
+
+    /// Sets the number of bytes read from the reader at once. Defaults to 64 KiB.
+    pub fn chunk_size(self, size: usize) -> Self {
+        StreamedMessage {
+            chunk_size: size.max(1),
+            ..self
+        }
+    }
+
+    /// Sends `trailers` after the message, e.g. the `grpc-status` of a server response.
+    pub fn trailers(self, trailers: HeaderMap) -> Self {
+        StreamedMessage {
+            trailers: Some(trailers),
+            ..self
+        }
+    }
+}
+
+impl<R: AsyncRead> Body for StreamedMessage<R> {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+
+        loop {
+            match *this.state {
+                State::Header => {
+                    let header = header(*this.len, *this.field);
+                    *this.state = match header {
+                        Ok(_) => State::Payload,
+                        Err(_) => State::Done,
+                    };
+                    return Poll::Ready(Some(header.map(Frame::data)));
+                }
+                State::Payload if *this.len == 0 => *this.state = State::Trailers,
+                State::Payload => {
+                    let mut chunk = vec![0; (*this.chunk_size as u64).min(*this.len) as usize];
+                    let mut buf = ReadBuf::new(&mut chunk);
+                    if let Err(err) = ready!(this.reader.poll_read(cx, &mut buf)) {
+                        *this.state = State::Done;
+                        return Poll::Ready(Some(Err(Status::from_error(Box::new(err)))));
+                    }
+
+                    let read = buf.filled().len();
+                    if read == 0 {
+                        *this.state = State::Done;
+                        return Poll::Ready(Some(Err(Status::internal(format!(
+                            "reader ended {} bytes before the end of the message",
+                            this.len
+                        )))));
+                    }
+
+                    chunk.truncate(read);
+                    *this.len -= read as u64;
+                    return Poll::Ready(Some(Ok(Frame::data(Bytes::from(chunk)))));
+                }
+                State::Trailers => {
+                    *this.state = State::Done;
+                    return Poll::Ready(
+                        this.trailers
+                            .take()
+                            .map(|trailers| Ok(Frame::trailers(trailers))),
+                    );
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.state == State::Done
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.state {
+            State::Header => SizeHint::default(),
+            State::Payload => SizeHint::with_exact(self.len),
+            State::Trailers | State::Done => SizeHint::with_exact(0),
+        }
+    }
+}
+
+impl<R> fmt::Debug for StreamedMessage<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamedMessage")
+            .field("len", &self.len)
+            .field("field", &self.field)
+            .field("chunk_size", &self.chunk_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Writes the payload of the messages received on a body to an `AsyncWrite`.
+///
+/// This is the receiving counterpart of [`StreamedMessage`]: the payload is written out chunk
+/// by chunk as it arrives instead of being collected and decoded. As generated services always
+/// decode their messages, the body is typically taken from the `http::Request` in a hand-written
+/// service routed to the method.
+pub struct MessageSink<B> {
+    body: B,
+    buf: BytesMut,
+    field: Option<u32>,
+    max_message_size: Option<u64>,
+}
+
+impl<B> MessageSink<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<crate::BoxError>,
+{
+    /// Creates a sink reading the messages of `body`.
+    pub fn new(body: B) -> Self {
+        Self {
+            body,
+            buf: BytesMut::new(),
+            field: None,
+            max_message_size: None,
+        }
+    }
+
+    /// Expects every message to consist of the protobuf `bytes` field with the number `field`,
+    /// and only writes the contents of the field.
+    pub fn bytes_field(self, field: u32) -> Self {
+        MessageSink {
+            field: Some(field),
+            ..self
+        }
+    }
+
+    /// Limits the size of the received messages. Unlimited by default.
+    pub fn max_message_size(self, limit: u64) -> Self {
+        MessageSink {
+            max_message_size: Some(limit),
+            ..self
+        }
+    }
+
+    /// Writes the payload of the next message to `writer`, and returns its length.
+    ///
+    /// Returns `None` once the body ended.
+    pub async fn copy_to<W>(&mut self, writer: &mut W) -> Result<Option<u64>, Status>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        if !self.fill(HEADER_SIZE).await? {
+            if self.buf.is_empty() {
+                return Ok(None);
+            }
+            return Err(Status::internal("body ended in the middle of a message"));
+        }
+
+        if self.buf.get_u8() != 0 {
+            return Err(Status::unimplemented(
+                "compressed messages can not be written to a sink",
+            ));
+        }
+        let mut remaining = u64::from(self.buf.get_u32());
+
+        if let Some(limit) = self.max_message_size {
+            if remaining > limit {
+                return Err(Status::out_of_range(format!(
+                    "Error, decoded message length too large: found {remaining} bytes, the limit is: {limit} bytes"
+                )));
+            }
+        }
+
+        if let Some(field) = self.field {
+            remaining = self.read_field_prefix(field, remaining).await?;
+        }
+
+        let len = remaining;
+        while remaining > 0 {
+            if self.buf.is_empty() && !self.fill(1).await? {
+                return Err(Status::internal("body ended in the middle of a message"));
+            }
+
+            let chunk = self
+                .buf
+                .split_to((self.buf.len() as u64).min(remaining) as usize);
+            remaining -= chunk.len() as u64;
+            write_all(writer, &chunk).await?;
+        }
+
+        poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx))
+            .await
+            .map_err(|err| Status::from_error(Box::new(err)))?;
+
+        Ok(Some(len))
+    }
+
+    /// Consumes the tag and length of the `bytes` field, and returns the length of its contents.
+    async fn read_field_prefix(&mut self, field: u32, len: u64) -> Result<u64, Status> {
+        // An empty field is not encoded at all.
+        if len == 0 {
+            return Ok(0);
+        }
+
+        // Both varints take at most 10 bytes each.
+        self.fill(len.min(20) as usize).await?;
+        let mut prefix = &self.buf[..];
+
+        let tag = get_varint(&mut prefix);
+        let field_len = get_varint(&mut prefix);
+        let consumed = (self.buf.len() - prefix.len()) as u64;
+
+        match (tag, field_len) {
+            (Some(tag), Some(field_len))
+                if tag == u64::from(field) << 3 | 2 && consumed + field_len == len =>
+            {
+                self.buf.advance(consumed as usize);
+                Ok(field_len)
+            }
+            _ => Err(Status::internal(format!(
+                "message does not consist of bytes field {field}"
+            ))),
+        }
+    }
+
+    /// Reads from the body until at least `n` bytes are buffered, or returns `false` if the body
+    /// ended before.
+    async fn fill(&mut self, n: usize) -> Result<bool, Status> {
+        while self.buf.len() < n {
+            match self.body.frame().await {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        self.buf.put(data);
+                    }
+                }
+                Some(Err(err)) => return Err(Status::from_error(err.into())),
+                None => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl<B> fmt::Debug for MessageSink<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageSink")
+            .field("field", &self.field)
+            .field("max_message_size", &self.max_message_size)
+            .finish_non_exhaustive()
+    }
+}
+
+async fn write_all<W>(writer: &mut W, mut bytes: &[u8]) -> Result<(), Status>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    while !bytes.is_empty() {
+        let written = poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, bytes))
+            .await
+            .map_err(|err| Status::from_error(Box::new(err)))?;
+        if written == 0 {
+            return Err(Status::internal("writer does not accept any more bytes"));
+        }
+        bytes = &bytes[written..];
+    }
+
+    Ok(())
+}
+
+/// The gRPC message header and the field prefix of a payload of `len` bytes.
+fn header(len: u64, field: Option<u32>) -> Result<Bytes, Status> {
+    let mut prefix = BytesMut::new();
+    if let Some(field) = field {
+        put_varint(&mut prefix, u64::from(field) << 3 | 2);
+        put_varint(&mut prefix, len);
+    }
+
+    let message_len = prefix.len() as u64 + len;
+    let message_len = u32::try_from(message_len).map_err(|_| {
+        Status::out_of_range(format!(
+            "Error, encoded message length too large: found {message_len} bytes, the limit is: {} bytes",
+            u32::MAX
+        ))
+    })?;
+
+    let mut header = BytesMut::with_capacity(HEADER_SIZE + prefix.len());
+    header.put_u8(0);
+    header.put_u32(message_len);
+    header.put_slice(&prefix);
+    Ok(header.freeze())
+}
+
+fn put_varint(dst: &mut BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        dst.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    dst.put_u8(value as u8);
+}
+
+fn get_varint(src: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = src.split_first()?;
+        *src = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+
+    #[tokio::test]
+    async fn frames_payload() {
+        let body = StreamedMessage::new(&b"hello"[..], 5).chunk_size(2);
+        let bytes = body.collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], b"\x00\x00\x00\x00\x05hello");
+
+        let body = StreamedMessage::new(&b"hello"[..], 5).bytes_field(1);
+        let bytes = body.collect().await.unwrap().to_bytes();
+        assert_eq!(&bytes[..], b"\x00\x00\x00\x00\x07\x0a\x05hello");
+    }
+
+    #[tokio::test]
+    async fn fails_on_short_reader() {
+        let mut body = StreamedMessage::new(&b"hi"[..], 5);
+        assert!(body.frame().await.unwrap().is_ok());
+        assert!(body.frame().await.unwrap().is_ok());
+        let status = body.frame().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), crate::Code::Internal);
+        assert!(body.frame().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn sinks_split_messages() {
+        let encoded: &[u8] = b"\x00\x00\x00\x00\x07\x0a\x05hello\x00\x00\x00\x00\x00";
+        let frames = encoded
+            .chunks(3)
+            .map(|chunk| Ok::<_, Status>(Frame::data(Bytes::copy_from_slice(chunk))));
+        let body = StreamBody::new(tokio_stream::iter(frames));
+
+        let mut sink = MessageSink::new(body).bytes_field(1);
+        let mut out = Vec::new();
+        assert_eq!(sink.copy_to(&mut out).await.unwrap(), Some(5));
+        assert_eq!(out, b"hello");
+        assert_eq!(sink.copy_to(&mut out).await.unwrap(), Some(0));
+        assert_eq!(sink.copy_to(&mut out).await.unwrap(), None);
+    }
+}
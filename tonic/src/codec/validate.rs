@@ -0,0 +1,251 @@
+use super::{BufferSettings, DecodeBuf, Decoder};
+use crate::Status;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+/// A constraint a message does not satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldViolation {
+    field: String,
+    description: String,
+}
+
+impl FieldViolation {
+    /// Create a violation of the field at `field`, a path such as `address.zip_code` or
+    /// `items[2].quantity`.
+    pub fn new(field: impl Into<String>, description: impl Into<String>) -> Self {
+        FieldViolation {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+
+    /// The path of the field.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// What is wrong with the field.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl fmt::Display for FieldViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.description)
+    }
+}
+
+type Validator = dyn Fn(&dyn Any) -> Result<(), Vec<FieldViolation>> + Send + Sync;
+
+/// Checks messages against constraints as they are encoded and decoded.
+///
+/// A validator is registered per message type, and runs on every message of that type that a
+/// client or a server encodes or decodes, before it reaches the handler or the caller. Messages
+/// of other types are not checked.
+///
+/// A request that is not valid fails with an `INVALID_ARGUMENT` status, and a response with an
+/// `INTERNAL` status, whose message lists the violations, e.g.
+/// `invalid request message: name: must not be empty`. The violations themselves are available
+/// through the [extensions](Status::extensions) of the status as a `Vec<FieldViolation>`, on
+/// the side that validated the message.
+///
+/// ```
+/// use tonic::codec::{FieldViolation, MessageValidators};
+///
+/// struct HelloRequest {
+///     name: String,
+/// }
+///
+/// let validators = MessageValidators::new().message(|req: &HelloRequest| {
+///     if req.name.is_empty() {
+///         return Err(vec![FieldViolation::new("name", "must not be empty")]);
+///     }
+///     Ok(())
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct MessageValidators {
+    validators: Arc<HashMap<TypeId, Arc<Validator>>>,
+}
+
+impl MessageValidators {
+    /// Create an empty set of validators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates the messages of type `M` with `validator`, replacing any previous validator of
+    /// that type.
+    pub fn message<M, F>(mut self, validator: F) -> Self
+    where
+        M: 'static,
+        F: Fn(&M) -> Result<(), Vec<FieldViolation>> + Send + Sync + 'static,
+    {
+        let validator = move |message: &dyn Any| match message.downcast_ref::<M>() {
+            Some(message) => validator(message),
+            None => Ok(()),
+        };
+        Arc::make_mut(&mut self.validators).insert(TypeId::of::<M>(), Arc::new(validator));
+        self
+    }
+
+    pub(crate) fn validate<M: 'static>(
+        &self,
+        kind: MessageKind,
+        message: &M,
+    ) -> Result<(), Status> {
+        let Some(validator) = self.validators.get(&TypeId::of::<M>()) else {
+            return Ok(());
+        };
+
+        let violations = match validator(message) {
+            Ok(()) => return Ok(()),
+            Err(violations) => violations,
+        };
+
+        let list = violations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!("invalid {} message: {list}", kind.name());
+        let mut status = match kind {
+            MessageKind::Request => Status::invalid_argument(message),
+            MessageKind::Response => Status::internal(message),
+        };
+        status.extensions_mut().insert(violations);
+        Err(status)
+    }
+}
+
+impl fmt::Debug for MessageValidators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageValidators")
+            .field("messages", &self.validators.len())
+            .finish()
+    }
+}
+
+/// Whether messages are requests or responses.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MessageKind {
+    Request,
+    Response,
+}
+
+impl MessageKind {
+    fn name(self) -> &'static str {
+        match self {
+            MessageKind::Request => "request",
+            MessageKind::Response => "response",
+        }
+    }
+}
+
+/// Validates a message about to be encoded, if there are validators.
+pub(crate) fn validate_message<M: 'static>(
+    validators: Option<&MessageValidators>,
+    kind: MessageKind,
+    message: M,
+) -> Result<M, Status> {
+    match validators {
+        Some(validators) => validators.validate(kind, &message).map(|()| message),
+        None => Ok(message),
+    }
+}
+
+/// A [`Decoder`] that validates the messages it decodes.
+#[derive(Debug)]
+pub(crate) struct ValidatingDecoder<D> {
+    inner: D,
+    validators: Option<MessageValidators>,
+    kind: MessageKind,
+}
+
+impl<D> ValidatingDecoder<D> {
+    pub(crate) fn new(inner: D, validators: Option<MessageValidators>, kind: MessageKind) -> Self {
+        ValidatingDecoder {
+            inner,
+            validators,
+            kind,
+        }
+    }
+}
+
+impl<D> Decoder for ValidatingDecoder<D>
+where
+    D: Decoder<Error = Status>,
+    D::Item: 'static,
+{
+    type Item = D::Item;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let item = self.inner.decode(src)?;
+        if let (Some(validators), Some(item)) = (&self.validators, &item) {
+            validators.validate(self.kind, item)?;
+        }
+        Ok(item)
+    }
+
+    fn buffer_settings(&self) -> BufferSettings {
+        self.inner.buffer_settings()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn validate_messages() {
+        let validators = MessageValidators::new().message(|point: &Point| {
+            let mut violations = Vec::new();
+            if point.x < 0 {
+                violations.push(FieldViolation::new("x", "must not be negative"));
+            }
+            if point.y < 0 {
+                violations.push(FieldViolation::new("y", "must not be negative"));
+            }
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                Err(violations)
+            }
+        });
+
+        assert!(validators
+            .validate(MessageKind::Request, &Point { x: 1, y: 2 })
+            .is_ok());
+        // Messages without a validator are valid.
+        assert!(validators.validate(MessageKind::Request, &"text").is_ok());
+
+        let status = validators
+            .validate(MessageKind::Request, &Point { x: -1, y: -2 })
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "invalid request message: x: must not be negative, y: must not be negative"
+        );
+        let violations = status.extensions().get::<Vec<FieldViolation>>().unwrap();
+        assert_eq!(violations[1].field(), "y");
+
+        let status = validators
+            .validate(MessageKind::Response, &Point { x: 0, y: -1 })
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
@@ -0,0 +1,426 @@
+//! An implementation of the `grpc.health.v1.Health` service of the
+//! [gRPC health checking protocol](https://github.com/grpc/grpc/blob/master/doc/health-checking.md).
+//!
+//! The service is served from a [`HealthService`] and the health of each service is set with
+//! the linked [`HealthReporter`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let (reporter, health_service) = tonic::health::health_reporter();
+//! reporter.set_serving::<GreeterServer<MyGreeter>>();
+//!
+//! Server::builder()
+//!     .add_service(health_service)
+//!     .add_service(GreeterServer::new(MyGreeter::default()))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+
+use crate::{
+    body::Body,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    server::{Grpc, NamedService},
+    Request, Response, Status,
+};
+use bytes::{Buf, BufMut, Bytes};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+use tokio::sync::watch;
+use tokio_stream::Stream;
+use tower::service_fn;
+use tower_service::Service;
+
+const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+const WATCH_PATH: &str = "/grpc.health.v1.Health/Watch";
+
+type Statuses = Arc<RwLock<HashMap<String, watch::Sender<ServingStatus>>>>;
+
+/// The health of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServingStatus {
+    /// The health of the service is unknown.
+    Unknown,
+    /// The service is serving requests.
+    Serving,
+    /// The service is not serving requests.
+    NotServing,
+    /// The service is not known to the server. Only sent to the clients watching it.
+    ServiceUnknown,
+}
+
+impl ServingStatus {
+    /// The value of `grpc.health.v1.HealthCheckResponse.ServingStatus`.
+    fn to_wire(self) -> u64 {
+        match self {
+            ServingStatus::Unknown => 0,
+            ServingStatus::Serving => 1,
+            ServingStatus::NotServing => 2,
+            ServingStatus::ServiceUnknown => 3,
+        }
+    }
+}
+
+impl fmt::Display for ServingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServingStatus::Unknown => f.write_str("Unknown"),
+            ServingStatus::Serving => f.write_str("Serving"),
+            ServingStatus::NotServing => f.write_str("NotServing"),
+            ServingStatus::ServiceUnknown => f.write_str("ServiceUnknown"),
+        }
+    }
+}
+
+/// Creates a [`HealthReporter`] and a linked [`HealthService`].
+pub fn health_reporter() -> (HealthReporter, HealthService) {
+    let reporter = HealthReporter::new();
+    let service = reporter.service();
+    (reporter, service)
+}
+
+/// A handle to set the health of services, as served by its linked [`HealthService`]s.
+///
+/// The overall health of the server is set under the empty service name, and is `Serving`
+/// initially.
+#[derive(Clone)]
+pub struct HealthReporter {
+    statuses: Statuses,
+}
+
+impl HealthReporter {
+    /// Create a new `HealthReporter` with the server `Serving`.
+    pub fn new() -> Self {
+        let reporter = HealthReporter {
+            statuses: Statuses::default(),
+        };
+        reporter.set_service_status("", ServingStatus::Serving);
+        reporter
+    }
+
+    /// Create a [`HealthService`] that serves the statuses set with this reporter.
+    pub fn service(&self) -> HealthService {
+        HealthService {
+            statuses: self.statuses.clone(),
+        }
+    }
+
+    /// Sets the status of the service implemented by `S` to `Serving`.
+    pub fn set_serving<S: NamedService>(&self) {
+        self.set_service_status(S::NAME, ServingStatus::Serving);
+    }
+
+    /// Sets the status of the service implemented by `S` to `NotServing`.
+    pub fn set_not_serving<S: NamedService>(&self) {
+        self.set_service_status(S::NAME, ServingStatus::NotServing);
+    }
+
+    /// Sets the status of the service with the fully qualified `name`, e.g.
+    /// `helloworld.Greeter`, and notifies the clients watching it of changes.
+    pub fn set_service_status(&self, name: impl AsRef<str>, status: ServingStatus) {
+        let name = name.as_ref();
+        let mut statuses = self.statuses.write().unwrap();
+        match statuses.get(name) {
+            Some(tx) => {
+                tx.send_if_modified(|current| std::mem::replace(current, status) != status);
+            }
+            None => {
+                statuses.insert(name.to_owned(), watch::channel(status).0);
+            }
+        }
+    }
+
+    /// Gets the status of the service with the fully qualified `name`, if it is set.
+    pub fn service_status(&self, name: &str) -> Option<ServingStatus> {
+        let statuses = self.statuses.read().unwrap();
+        statuses
+            .get(name)
+            .map(|tx| *tx.borrow())
+            .filter(|status| *status != ServingStatus::ServiceUnknown)
+    }
+
+    /// Clears the status of the service with the fully qualified `name`.
+    ///
+    /// The clients watching it are sent `ServiceUnknown`.
+    pub fn clear_service_status(&self, name: &str) {
+        let mut statuses = self.statuses.write().unwrap();
+        if let Some(tx) = statuses.get(name) {
+            tx.send_replace(ServingStatus::ServiceUnknown);
+            if tx.receiver_count() == 0 {
+                statuses.remove(name);
+            }
+        }
+    }
+}
+
+impl Default for HealthReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for HealthReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthReporter").finish_non_exhaustive()
+    }
+}
+
+/// The `grpc.health.v1.Health` service, serving the statuses set with its [`HealthReporter`].
+#[derive(Clone)]
+pub struct HealthService {
+    statuses: Statuses,
+}
+
+impl HealthService {
+    fn check(&self, name: &str) -> Result<ServingStatus, Status> {
+        match self
+            .statuses
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|tx| *tx.borrow())
+        {
+            Some(ServingStatus::ServiceUnknown) | None => {
+                Err(Status::not_found("service not registered"))
+            }
+            Some(status) => Ok(status),
+        }
+    }
+
+    fn watch(&self, name: String) -> WatchStream {
+        let rx = self
+            .statuses
+            .write()
+            .unwrap()
+            .entry(name.clone())
+            .or_insert_with(|| watch::channel(ServingStatus::ServiceUnknown).0)
+            .subscribe();
+
+        WatchStream {
+            inner: tokio_stream::wrappers::WatchStream::new(rx),
+            name,
+            statuses: self.statuses.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for HealthService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthService").finish_non_exhaustive()
+    }
+}
+
+impl NamedService for HealthService {
+    const NAME: &'static str = "grpc.health.v1.Health";
+}
+
+impl<B> Service<http::Request<B>> for HealthService
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let health = self.clone();
+
+        match req.uri().path() {
+            CHECK_PATH => Box::pin(async move {
+                let check = service_fn(move |req: Request<String>| {
+                    let status = health.check(req.get_ref());
+                    async move { status.map(Response::new) }
+                });
+                Ok(Grpc::new(HealthCodec).unary(check, req).await)
+            }),
+            WATCH_PATH => Box::pin(async move {
+                let watch = service_fn(move |req: Request<String>| {
+                    let stream = health.watch(req.into_inner());
+                    async move { Ok(Response::new(stream)) }
+                });
+                Ok(Grpc::new(HealthCodec).server_streaming(watch, req).await)
+            }),
+            _ => Box::pin(async move {
+                let (parts, ()) = Status::unimplemented("").into_http::<()>().into_parts();
+                Ok(http::Response::from_parts(parts, Body::empty()))
+            }),
+        }
+    }
+}
+
+/// The statuses of a watched service.
+struct WatchStream {
+    inner: tokio_stream::wrappers::WatchStream<ServingStatus>,
+    name: String,
+    statuses: Statuses,
+}
+
+impl Stream for WatchStream {
+    type Item = Result<ServingStatus, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx).map(|s| s.map(Ok))
+    }
+}
+
+impl Drop for WatchStream {
+    fn drop(&mut self) {
+        // Forget the unknown services that are not watched anymore, so clients cannot make the
+        // server remember any number of them.
+        let mut statuses = self.statuses.write().unwrap();
+        if let Some(tx) = statuses.get(&self.name) {
+            // The receiver of this stream is only dropped after this.
+            if *tx.borrow() == ServingStatus::ServiceUnknown && tx.receiver_count() <= 1 {
+                statuses.remove(&self.name);
+            }
+        }
+    }
+}
+
+/// A minimal codec for `grpc.health.v1.HealthCheckRequest` and `HealthCheckResponse`.
+#[derive(Debug, Clone, Copy)]
+struct HealthCodec;
+
+impl Codec for HealthCodec {
+    type Encode = ServingStatus;
+    type Decode = String;
+
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        *self
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        *self
+    }
+}
+
+impl Encoder for HealthCodec {
+    type Item = ServingStatus;
+    type Error = Status;
+
+    fn encode(&mut self, status: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        let status = status.to_wire();
+        if status != 0 {
+            // field 1, wire type 0 (varint)
+            dst.put_u8(0x08);
+            put_varint(dst, status);
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for HealthCodec {
+    type Item = String;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let invalid = || Status::internal("invalid health check request");
+        let mut service = String::new();
+
+        while src.has_remaining() {
+            let key = get_varint(src).ok_or_else(invalid)?;
+
+            match (key >> 3, key & 0x7) {
+                (_, 0) => {
+                    get_varint(src).ok_or_else(invalid)?;
+                }
+                (_, 1) if src.remaining() >= 8 => src.advance(8),
+                (field, 2) => {
+                    let len = get_varint(src).ok_or_else(invalid)? as usize;
+                    if src.remaining() < len {
+                        return Err(invalid());
+                    }
+                    let bytes = src.copy_to_bytes(len);
+                    if field == 1 {
+                        service = String::from_utf8(bytes.into()).map_err(|_| invalid())?;
+                    }
+                }
+                (_, 5) if src.remaining() >= 4 => src.advance(4),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Some(service))
+    }
+}
+
+fn put_varint(dst: &mut impl BufMut, mut value: u64) {
+    while value >= 0x80 {
+        dst.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    dst.put_u8(value as u8);
+}
+
+fn get_varint(src: &mut impl Buf) -> Option<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        if !src.has_remaining() {
+            return None;
+        }
+
+        let byte = src.get_u8();
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clears_unwatched_services() {
+        let (reporter, service) = health_reporter();
+        assert_eq!(service.check("").unwrap(), ServingStatus::Serving);
+
+        reporter.set_service_status("test.Test", ServingStatus::NotServing);
+        assert_eq!(
+            service.check("test.Test").unwrap(),
+            ServingStatus::NotServing
+        );
+
+        reporter.clear_service_status("test.Test");
+        assert!(service.check("test.Test").is_err());
+        assert!(reporter.statuses.read().unwrap().get("test.Test").is_none());
+    }
+
+    #[test]
+    fn forgets_unknown_services_once_unwatched() {
+        let (reporter, service) = health_reporter();
+
+        let stream = service.watch("test.Unknown".to_owned());
+        assert!(service.check("test.Unknown").is_err());
+        assert_eq!(reporter.service_status("test.Unknown"), None);
+
+        drop(stream);
+        assert!(reporter
+            .statuses
+            .read()
+            .unwrap()
+            .get("test.Unknown")
+            .is_none());
+    }
+}
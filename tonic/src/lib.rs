@@ -38,6 +38,23 @@
 //!   Not enabled by default.
 //! - `zstd`: Enables compressing requests, responses, and streams. Depends on [`zstd`].
 //!   Not enabled by default.
+//! - `service-config`: Enables applying a gRPC service config to client calls through
+//!   [`ServiceConfigLayer`]. Depends on [`serde_json`]. Not enabled by default.
+//! - `rpc-trace`: Enables creating a `tracing` span for every RPC and propagating the W3C trace
+//!   context with [`RpcTraceLayer`]. Not enabled by default.
+//! - `rpc-metrics`: Enables recording the OpenTelemetry gRPC metrics of clients and servers with
+//!   [`RpcMetricsLayer`]. Requires `server` or `channel`. Not enabled by default.
+//! - `grpc-web`: Enables serving gRPC-Web requests from browsers with
+//!   `Server::grpc_web`. Not enabled by default.
+//! - `health`: Enables the built-in `grpc.health.v1.Health` service in the `health` module.
+//!   Not enabled by default.
+//! - `reflection`: Enables the built-in `grpc.reflection.v1.ServerReflection` service in the
+//!   `reflection` module. Not enabled by default.
+//! - `sim`: Enables the [`sim`] module to run clients and servers over a simulated clock and
+//!   network. Not enabled by default.
+//! - `testing`: Enables the [`MockChannel`] to unit test generated clients without a server,
+//!   and recording calls to replay them later. Depends on [`serde_json`]. Not enabled by
+//!   default.
 //!
 //! # Structure
 //!
@@ -85,6 +102,11 @@
 //! [`webpki-roots`]: https://docs.rs/webpki-roots
 //! [`flate2`]: https://docs.rs/flate2
 //! [`zstd`]: https://docs.rs/zstd
+//! [`serde_json`]: https://docs.rs/serde_json
+//! [`ServiceConfigLayer`]: service/service_config/struct.ServiceConfigLayer.html
+//! [`RpcTraceLayer`]: service/rpc_trace/struct.RpcTraceLayer.html
+//! [`RpcMetricsLayer`]: service/rpc_metrics/struct.RpcMetricsLayer.html
+//! [`MockChannel`]: testing/struct.MockChannel.html
 
 #![recursion_limit = "256"]
 #![doc(
@@ -97,10 +119,22 @@
 pub mod body;
 pub mod client;
 pub mod codec;
+#[cfg(feature = "health")]
+pub mod health;
 pub mod metadata;
+#[cfg(feature = "reflection")]
+pub mod reflection;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod resume;
 pub mod server;
 pub mod service;
 
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod time;
 #[cfg(any(feature = "server", feature = "channel"))]
 pub mod transport;
 
@@ -120,8 +154,8 @@ pub use codec::Streaming;
 pub use extensions::GrpcMethod;
 pub use http::Extensions;
 pub use request::{IntoRequest, IntoStreamingRequest, Request};
-pub use response::Response;
-pub use status::{Code, ConnectError, Status, TimeoutExpired};
+pub use response::{Response, ResponseTrailers};
+pub use status::{Code, ConnectError, Status, StatusBuilder, TimeoutExpired, TransportCause};
 
 pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
@@ -222,3 +222,53 @@ impl fmt::Display for InvalidMetadataValueBytes {
 }
 
 impl Error for InvalidMetadataValueBytes {}
+
+/// A possible error when reading or writing a binary metadata value as bytes, with
+/// [`MetadataMap::get_bin_bytes`](super::MetadataMap::get_bin_bytes) and
+/// [`MetadataMap::insert_bin_bytes`](super::MetadataMap::insert_bin_bytes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBinaryMetadataValue {
+    kind: BinaryErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BinaryErrorKind {
+    Base64,
+    TooLarge { len: usize, max: usize },
+}
+
+// ===== impl InvalidBinaryMetadataValue =====
+
+impl InvalidBinaryMetadataValue {
+    pub(crate) fn base64() -> Self {
+        Self {
+            kind: BinaryErrorKind::Base64,
+        }
+    }
+
+    pub(crate) fn too_large(len: usize, max: usize) -> Self {
+        Self {
+            kind: BinaryErrorKind::TooLarge { len, max },
+        }
+    }
+
+    /// Returns `true` if the value exceeds
+    /// [`MetadataMap::MAX_BIN_VALUE_LEN`](super::MetadataMap::MAX_BIN_VALUE_LEN).
+    pub fn is_too_large(&self) -> bool {
+        matches!(self.kind, BinaryErrorKind::TooLarge { .. })
+    }
+}
+
+impl fmt::Display for InvalidBinaryMetadataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            BinaryErrorKind::Base64 => f.write_str("binary metadata value is not valid base64"),
+            BinaryErrorKind::TooLarge { len, max } => write!(
+                f,
+                "binary metadata value of {len} bytes exceeds the limit of {max} bytes"
+            ),
+        }
+    }
+}
+
+impl Error for InvalidBinaryMetadataValue {}
@@ -0,0 +1,211 @@
+use super::{MetadataMap, GRPC_TIMEOUT_HEADER};
+use crate::Status;
+
+// The overhead of each entry in the size of a header list, see RFC 9113 section 6.5.2.
+const ENTRY_OVERHEAD: usize = 32;
+
+// Headers that are set by the gRPC or HTTP/2 layers and must not be set through metadata, in
+// addition to the ones starting with `grpc-`.
+const RESERVED_HEADERS: [&str; 8] = [
+    "te",
+    "content-type",
+    "host",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// What to do with reserved headers found in the metadata of outgoing requests or responses.
+///
+/// Reserved headers are those starting with `grpc-`, which are set by gRPC itself, and those
+/// that are set by HTTP/2 or that it forbids, e.g. `te`, `content-type` or `connection`. Pseudo
+/// headers, such as `:path`, cannot be set through metadata at all. The `grpc-timeout` of a
+/// request is not reserved, see [`Request::set_timeout`](crate::Request::set_timeout).
+///
+/// Whatever the policy, `te`, `content-type`, `grpc-status`, `grpc-message` and
+/// `grpc-message-type` are never sent from metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReservedHeaderPolicy {
+    /// Send reserved headers as they are.
+    #[default]
+    Allow,
+    /// Remove reserved headers, logging a warning with the name of each removed header.
+    Strip,
+    /// Fail with an `INTERNAL` status naming the first reserved header found.
+    Reject,
+}
+
+impl ReservedHeaderPolicy {
+    fn enforce(self, kind: &str, metadata: &mut MetadataMap) -> Result<(), Status> {
+        if self == Self::Allow {
+            return Ok(());
+        }
+
+        let reserved: Vec<_> = metadata
+            .as_ref()
+            .keys()
+            .filter(|name| is_reserved(kind, name.as_str()))
+            .cloned()
+            .collect();
+
+        match (self, reserved.first()) {
+            (_, None) => Ok(()),
+            (Self::Reject, Some(name)) => Err(Status::internal(format!(
+                "{kind} metadata sets reserved header `{name}`"
+            ))),
+            _ => {
+                for name in reserved {
+                    tracing::warn!(header = %name, "removed reserved header from {kind} metadata");
+                    metadata.as_mut().remove(name);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn is_reserved(kind: &str, name: &str) -> bool {
+    if kind == "request" && name == GRPC_TIMEOUT_HEADER {
+        return false;
+    }
+    name.starts_with("grpc-") || RESERVED_HEADERS.contains(&name)
+}
+
+/// Limits on the metadata of the outgoing requests of a client, or of the outgoing responses of
+/// a server.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct MetadataLimits {
+    /// Limits the size of the metadata, counted like HTTP/2 counts the size of a header list.
+    pub(crate) max_size: Option<usize>,
+    /// Limits the number of entries of the metadata.
+    pub(crate) max_entries: Option<usize>,
+    /// Protects the reserved headers.
+    pub(crate) reserved_headers: Option<ReservedHeaderPolicy>,
+}
+
+impl MetadataLimits {
+    /// Gets these limits, or else the ones of `defaults` for the limits that are not set.
+    pub(crate) fn or(self, defaults: MetadataLimits) -> Self {
+        Self {
+            max_size: self.max_size.or(defaults.max_size),
+            max_entries: self.max_entries.or(defaults.max_entries),
+            reserved_headers: self.reserved_headers.or(defaults.reserved_headers),
+        }
+    }
+
+    /// Applies the reserved header policy to the metadata of the outgoing `kind` (e.g.
+    /// "request"), then checks its size.
+    pub(crate) fn enforce(&self, kind: &str, metadata: &mut MetadataMap) -> Result<(), Status> {
+        self.reserved_headers
+            .unwrap_or_default()
+            .enforce(kind, metadata)?;
+        self.check(kind, metadata)
+    }
+
+    /// Fails with a `RESOURCE_EXHAUSTED` status naming the exceeded limit, if any, of the
+    /// metadata of the outgoing `kind` (e.g. "request").
+    pub(crate) fn check(&self, kind: &str, metadata: &MetadataMap) -> Result<(), Status> {
+        let headers = metadata.as_ref();
+
+        if let Some(max) = self.max_entries {
+            let entries = headers.len();
+            if entries > max {
+                return Err(Status::resource_exhausted(format!(
+                    "{kind} metadata has {entries} entries, exceeding the limit of {max}"
+                )));
+            }
+        }
+
+        if let Some(max) = self.max_size {
+            let size: usize = headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len() + ENTRY_OVERHEAD)
+                .sum();
+            if size > max {
+                return Err(Status::resource_exhausted(format!(
+                    "{kind} metadata of {size} bytes exceeds the limit of {max} bytes"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    #[test]
+    fn checks_size_and_entries() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-a", "1".parse().unwrap());
+        metadata.append("x-a", "2".parse().unwrap());
+
+        let limits = MetadataLimits {
+            max_size: Some(2 * (3 + 1 + ENTRY_OVERHEAD)),
+            max_entries: Some(2),
+            reserved_headers: None,
+        };
+        limits.check("request", &metadata).unwrap();
+
+        metadata.insert("x-b", "3".parse().unwrap());
+        let status = limits.check("request", &metadata).unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(
+            status.message(),
+            "request metadata has 3 entries, exceeding the limit of 2"
+        );
+
+        let limits = MetadataLimits {
+            max_entries: None,
+            ..limits
+        };
+        let status = limits.check("response", &metadata).unwrap_err();
+        assert_eq!(
+            status.message(),
+            "response metadata of 108 bytes exceeds the limit of 72 bytes"
+        );
+    }
+
+    #[test]
+    fn protects_reserved_headers() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-a", "1".parse().unwrap());
+        metadata.insert("grpc-encoding", "gzip".parse().unwrap());
+        metadata.insert("connection", "close".parse().unwrap());
+        metadata.insert("grpc-timeout", "1S".parse().unwrap());
+
+        let mut allowed = metadata.clone();
+        ReservedHeaderPolicy::Allow
+            .enforce("request", &mut allowed)
+            .unwrap();
+        assert_eq!(allowed.len(), 4);
+
+        let status = ReservedHeaderPolicy::Reject
+            .enforce("request", &mut metadata.clone())
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(
+            status.message(),
+            "request metadata sets reserved header `grpc-encoding`"
+        );
+
+        let mut request = metadata.clone();
+        ReservedHeaderPolicy::Strip
+            .enforce("request", &mut request)
+            .unwrap();
+        let keys: Vec<_> = request.as_ref().keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["x-a", "grpc-timeout"]);
+
+        let mut response = metadata;
+        ReservedHeaderPolicy::Strip
+            .enforce("response", &mut response)
+            .unwrap();
+        let keys: Vec<_> = response.as_ref().keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["x-a"]);
+    }
+}
@@ -4,10 +4,11 @@ pub(crate) use self::as_encoding_agnostic_metadata_key::AsEncodingAgnosticMetada
 pub(crate) use self::as_metadata_key::AsMetadataKey;
 pub(crate) use self::into_metadata_key::IntoMetadataKey;
 
-use super::encoding::{Ascii, Binary, ValueEncoding};
+use super::encoding::{Ascii, Binary, InvalidBinaryMetadataValue, ValueEncoding};
 use super::key::{InvalidMetadataKey, MetadataKey};
 use super::value::MetadataValue;
 
+use bytes::Bytes;
 use std::marker::PhantomData;
 
 /// A set of gRPC custom metadata entries.
@@ -208,11 +209,81 @@ pub struct OccupiedEntry<'a, VE: ValueEncoding> {
     phantom: PhantomData<VE>,
 }
 
+/// How [`MetadataMap::merge_with`] combines the values of a key found in both maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the existing values and add the merged ones after them.
+    Append,
+    /// Replace the existing values with the merged ones.
+    #[default]
+    Replace,
+    /// Keep the existing values and drop the merged ones.
+    KeepFirst,
+}
+
+/// The [`MergePolicy`] of each class of keys, used by [`MetadataMap::merge_with`].
+///
+/// Both ascii and binary keys are replaced by default, like [`MetadataMap::merge`] does.
+///
+/// # Examples
+///
+/// ```
+/// # use tonic::metadata::*;
+/// let policies = MergePolicies::new()
+///     .ascii(MergePolicy::Append)
+///     .binary(MergePolicy::KeepFirst);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MergePolicies {
+    ascii: MergePolicy,
+    binary: MergePolicy,
+}
+
+impl MergePolicies {
+    /// Creates policies that replace the values of all keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the policy for ascii keys.
+    #[must_use]
+    pub fn ascii(self, policy: MergePolicy) -> Self {
+        Self {
+            ascii: policy,
+            ..self
+        }
+    }
+
+    /// Sets the policy for binary keys, those whose names end with `-bin`.
+    #[must_use]
+    pub fn binary(self, policy: MergePolicy) -> Self {
+        Self {
+            binary: policy,
+            ..self
+        }
+    }
+
+    fn for_key(&self, key: &HeaderName) -> MergePolicy {
+        if Binary::is_valid_key(key.as_str()) {
+            self.binary
+        } else {
+            self.ascii
+        }
+    }
+}
+
 pub(crate) const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
 
 // ===== impl MetadataMap =====
 
 impl MetadataMap {
+    /// The maximum length in bytes of a binary value read or written with
+    /// [`get_bin_bytes`](Self::get_bin_bytes) and [`insert_bin_bytes`](Self::insert_bin_bytes),
+    /// before base64 encoding.
+    ///
+    /// Peers commonly limit all the metadata of a request or response to 8 KiB or 16 KiB.
+    pub const MAX_BIN_VALUE_LEN: usize = 8 * 1024;
+
     // Headers reserved by the gRPC protocol.
     pub(crate) const GRPC_RESERVED_HEADERS: [HeaderName; 5] = [
         HeaderName::from_static("te"),
@@ -1224,9 +1295,248 @@ impl MetadataMap {
         key.remove(self)
     }
 
-    pub(crate) fn merge(&mut self, other: MetadataMap) {
+    /// Returns the bytes of the binary value associated with the key, decoded from base64 with
+    /// or without padding.
+    ///
+    /// If there are multiple values associated with the key, then the first one is returned.
+    /// Fails if the value is not valid base64 or is longer than
+    /// [`MAX_BIN_VALUE_LEN`](Self::MAX_BIN_VALUE_LEN) once decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.insert_bin_bytes("trace-proto-bin", &b"hello\xfa"[..]).unwrap();
+    /// assert_eq!(map.get_bin_bytes("trace-proto-bin").unwrap().unwrap(), &b"hello\xfa"[..]);
+    ///
+    /// // Padded values sent by other implementations are accepted.
+    /// map.insert_bin("padded-bin", MetadataValue::from_static("aGk="));
+    /// assert_eq!(map.get_bin_bytes("padded-bin").unwrap().unwrap(), "hi");
+    ///
+    /// assert!(map.get_bin_bytes("missing-bin").unwrap().is_none());
+    /// ```
+    pub fn get_bin_bytes<K>(&self, key: K) -> Result<Option<Bytes>, InvalidBinaryMetadataValue>
+    where
+        K: AsMetadataKey<Binary>,
+    {
+        let Some(value) = self.get_bin(key) else {
+            return Ok(None);
+        };
+
+        // Reject values that cannot fit before decoding them.
+        let encoded_len = value.as_encoded_bytes().len();
+        if encoded_len > Self::MAX_BIN_VALUE_LEN.div_ceil(3) * 4 {
+            return Err(InvalidBinaryMetadataValue::too_large(
+                encoded_len / 4 * 3,
+                Self::MAX_BIN_VALUE_LEN,
+            ));
+        }
+
+        let bytes = value
+            .to_bytes()
+            .map_err(|_| InvalidBinaryMetadataValue::base64())?;
+        check_bin_len(bytes.len())?;
+        Ok(Some(bytes))
+    }
+
+    /// Inserts a binary value into the map, encoded with base64, replacing any values
+    /// associated with the key.
+    ///
+    /// Fails if the value is longer than [`MAX_BIN_VALUE_LEN`](Self::MAX_BIN_VALUE_LEN).
+    ///
+    /// This method panics when the given key is a string and it cannot be converted to a
+    /// `MetadataKey<Binary>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.insert_bin_bytes("trace-proto-bin", vec![1, 2, 3]).unwrap();
+    ///
+    /// let err = map
+    ///     .insert_bin_bytes("trace-proto-bin", vec![0; MetadataMap::MAX_BIN_VALUE_LEN + 1])
+    ///     .unwrap_err();
+    /// assert!(err.is_too_large());
+    /// ```
+    pub fn insert_bin_bytes<K>(
+        &mut self,
+        key: K,
+        value: impl Into<Bytes>,
+    ) -> Result<(), InvalidBinaryMetadataValue>
+    where
+        K: IntoMetadataKey<Binary>,
+    {
+        let value = value.into();
+        check_bin_len(value.len())?;
+        self.insert_bin(key, MetadataValue::from_bytes(&value));
+        Ok(())
+    }
+
+    /// Like [`insert_bin_bytes`](Self::insert_bin_bytes), but adds the value to those already
+    /// associated with the key.
+    pub fn append_bin_bytes<K>(
+        &mut self,
+        key: K,
+        value: impl Into<Bytes>,
+    ) -> Result<(), InvalidBinaryMetadataValue>
+    where
+        K: IntoMetadataKey<Binary>,
+    {
+        let value = value.into();
+        check_bin_len(value.len())?;
+        self.append_bin(key, MetadataValue::from_bytes(&value));
+        Ok(())
+    }
+
+    /// Returns the values associated with the key joined with commas, as they would be
+    /// combined into a single HTTP header.
+    ///
+    /// This method is for ascii metadata entries. Returns `None` if there are no values
+    /// associated with the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.append("x-tags", "a".parse().unwrap());
+    /// map.append("x-tags", "b, c".parse().unwrap());
+    ///
+    /// assert_eq!(map.get_all_joined("x-tags").unwrap(), "a, b, c");
+    /// assert!(map.get_all_joined("x-missing").is_none());
+    /// ```
+    pub fn get_all_joined<K>(&self, key: K) -> Option<MetadataValue<Ascii>>
+    where
+        K: AsMetadataKey<Ascii>,
+    {
+        let mut values = self.get_all(key).into_iter();
+        let first = values.next()?;
+
+        let mut joined = first.as_bytes().to_vec();
+        for value in values {
+            joined.extend_from_slice(b", ");
+            joined.extend_from_slice(value.as_bytes());
+        }
+
+        let value = http::HeaderValue::from_bytes(&joined).expect("joined values are valid");
+        Some(MetadataValue::unchecked_from_header_value(value))
+    }
+
+    /// Returns the items of the comma-separated values associated with the key, trimmed of
+    /// whitespace, across all of its values.
+    ///
+    /// This method is for ascii metadata entries. Empty items and values that are not valid
+    /// UTF-8 are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut map = MetadataMap::new();
+    /// map.append("grpc-accept-encoding", "gzip, zstd".parse().unwrap());
+    /// map.append("grpc-accept-encoding", "identity".parse().unwrap());
+    ///
+    /// let encodings: Vec<_> = map.get_all_split("grpc-accept-encoding").collect();
+    /// assert_eq!(encodings, ["gzip", "zstd", "identity"]);
+    /// ```
+    pub fn get_all_split<K>(&self, key: K) -> impl Iterator<Item = &str>
+    where
+        K: AsMetadataKey<Ascii>,
+    {
+        self.get_all(key)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+    }
+
+    /// Merges the entries of `other` into this map, replacing the values of the keys found in
+    /// both maps.
+    ///
+    /// See [`merge_with`](Self::merge_with) to combine them in other ways.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut headers = MetadataMap::new();
+    /// headers.insert("x-host", "example.com".parse().unwrap());
+    /// headers.insert("x-retries", "0".parse().unwrap());
+    ///
+    /// let mut trailers = MetadataMap::new();
+    /// trailers.insert("x-retries", "2".parse().unwrap());
+    ///
+    /// headers.merge(trailers);
+    /// assert_eq!(headers.get("x-host").unwrap(), "example.com");
+    /// assert_eq!(headers.get("x-retries").unwrap(), "2");
+    /// ```
+    pub fn merge(&mut self, other: MetadataMap) {
         self.headers.extend(other.headers);
     }
+
+    /// Merges the entries of `other` into this map, combining the values of the keys found in
+    /// both maps as set by `policies`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tonic::metadata::*;
+    /// let mut headers = MetadataMap::new();
+    /// headers.insert("x-hops", "a".parse().unwrap());
+    /// headers.insert_bin("x-trace-bin", MetadataValue::from_bytes(b"first"));
+    ///
+    /// let mut trailers = MetadataMap::new();
+    /// trailers.insert("x-hops", "b".parse().unwrap());
+    /// trailers.insert_bin("x-trace-bin", MetadataValue::from_bytes(b"second"));
+    ///
+    /// headers.merge_with(
+    ///     trailers,
+    ///     MergePolicies::new()
+    ///         .ascii(MergePolicy::Append)
+    ///         .binary(MergePolicy::KeepFirst),
+    /// );
+    /// assert_eq!(headers.get_all_joined("x-hops").unwrap(), "a, b");
+    /// assert_eq!(headers.get_bin("x-trace-bin").unwrap(), "first".as_bytes());
+    /// ```
+    pub fn merge_with(&mut self, other: MetadataMap, policies: MergePolicies) {
+        let mut key: Option<(HeaderName, MergePolicy, bool)> = None;
+
+        for (name, value) in other.headers {
+            // Only the first value of each key carries its name.
+            let first = name.is_some();
+            if let Some(name) = name {
+                let policy = policies.for_key(&name);
+                let existed = self.headers.contains_key(&name);
+                key = Some((name, policy, existed));
+            }
+            let Some((name, policy, existed)) = &key else {
+                continue;
+            };
+
+            match policy {
+                MergePolicy::Replace if first => {
+                    self.headers.insert(name.clone(), value);
+                }
+                MergePolicy::KeepFirst if *existed => {}
+                _ => {
+                    self.headers.append(name.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+fn check_bin_len(len: usize) -> Result<(), InvalidBinaryMetadataValue> {
+    if len > MetadataMap::MAX_BIN_VALUE_LEN {
+        return Err(InvalidBinaryMetadataValue::too_large(
+            len,
+            MetadataMap::MAX_BIN_VALUE_LEN,
+        ));
+    }
+    Ok(())
 }
 
 // ===== impl Iter =====
@@ -2729,6 +3039,50 @@ mod tests {
         assert!(found_x_word_bin);
     }
 
+    #[test]
+    fn test_merge_with_policies() {
+        let mut map = MetadataMap::new();
+        map.insert("x-replace", "old".parse().unwrap());
+        map.insert("x-keep", "old".parse().unwrap());
+
+        let mut other = MetadataMap::new();
+        other.append("x-replace", "a".parse().unwrap());
+        other.append("x-replace", "b".parse().unwrap());
+        other.insert("x-keep", "new".parse().unwrap());
+        other.append("x-new", "a".parse().unwrap());
+        other.append("x-new", "b".parse().unwrap());
+
+        let mut replaced = map.clone();
+        replaced.merge_with(other.clone(), MergePolicies::new());
+        assert_eq!(replaced.get_all_joined("x-replace").unwrap(), "a, b");
+        assert_eq!(replaced.get_all_joined("x-keep").unwrap(), "new");
+        assert_eq!(replaced.get_all_joined("x-new").unwrap(), "a, b");
+
+        map.merge_with(other, MergePolicies::new().ascii(MergePolicy::KeepFirst));
+        assert_eq!(map.get_all_joined("x-replace").unwrap(), "old");
+        assert_eq!(map.get_all_joined("x-keep").unwrap(), "old");
+        assert_eq!(map.get_all_joined("x-new").unwrap(), "a, b");
+    }
+
+    #[test]
+    fn test_get_bin_bytes_validates_values() {
+        let mut map = MetadataMap::new();
+
+        let max = MetadataMap::MAX_BIN_VALUE_LEN;
+        map.insert_bin_bytes("x-max-bin", vec![7; max]).unwrap();
+        assert_eq!(map.get_bin_bytes("x-max-bin").unwrap().unwrap().len(), max);
+
+        map.insert_bin("x-large-bin", MetadataValue::from_bytes(&vec![7; max + 1]));
+        assert!(map.get_bin_bytes("x-large-bin").unwrap_err().is_too_large());
+
+        map.insert_bin(
+            "x-invalid-bin",
+            MetadataValue::unchecked_from_header_value(http::HeaderValue::from_static("#")),
+        );
+        let err = map.get_bin_bytes("x-invalid-bin").unwrap_err();
+        assert!(!err.is_too_large());
+    }
+
     #[allow(dead_code)]
     fn value_drain_is_send_sync() {
         fn is_send_sync<T: Send + Sync>() {}
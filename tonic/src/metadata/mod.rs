@@ -2,6 +2,7 @@
 
 mod encoding;
 mod key;
+mod limits;
 mod map;
 mod value;
 
@@ -10,6 +11,7 @@ pub use self::encoding::Binary;
 pub use self::key::AsciiMetadataKey;
 pub use self::key::BinaryMetadataKey;
 pub use self::key::MetadataKey;
+pub use self::limits::ReservedHeaderPolicy;
 pub use self::map::Entry;
 pub use self::map::GetAll;
 pub use self::map::Iter;
@@ -18,6 +20,8 @@ pub use self::map::KeyAndMutValueRef;
 pub use self::map::KeyAndValueRef;
 pub use self::map::KeyRef;
 pub use self::map::Keys;
+pub use self::map::MergePolicies;
+pub use self::map::MergePolicy;
 pub use self::map::MetadataMap;
 pub use self::map::OccupiedEntry;
 pub use self::map::VacantEntry;
@@ -32,6 +36,9 @@ pub use self::value::BinaryMetadataValue;
 pub use self::value::MetadataValue;
 use http::HeaderValue;
 
+#[cfg(feature = "channel")]
+pub(crate) use self::encoding::ValueEncoding;
+pub(crate) use self::limits::MetadataLimits;
 pub(crate) use self::map::GRPC_TIMEOUT_HEADER;
 
 /// HTTP Header `content-type` value for gRPC calls.
@@ -40,6 +47,7 @@ pub const GRPC_CONTENT_TYPE: HeaderValue = HeaderValue::from_static("application
 /// The metadata::errors module contains types for errors that can occur
 /// while handling gRPC custom metadata.
 pub mod errors {
+    pub use super::encoding::InvalidBinaryMetadataValue;
     pub use super::encoding::InvalidMetadataValue;
     pub use super::encoding::InvalidMetadataValueBytes;
     pub use super::key::InvalidMetadataKey;
@@ -0,0 +1,780 @@
+//! An implementation of the `grpc.reflection.v1.ServerReflection` and
+//! `grpc.reflection.v1alpha.ServerReflection` services of the
+//! [gRPC server reflection protocol](https://github.com/grpc/grpc/blob/master/doc/server-reflection.md),
+//! which lets tools like `grpcurl` and `evans` discover the services of a server.
+//!
+//! The services describe the files of the encoded `google.protobuf.FileDescriptorSet`s
+//! registered with a [`Builder`], e.g. the one written by `tonic-prost-build` with
+//! `file_descriptor_set_path`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let reflection = ReflectionService::builder()
+//!     .register_encoded_file_descriptor_set(helloworld::FILE_DESCRIPTOR_SET)
+//!     .build()?;
+//!
+//! Server::builder()
+//!     .add_service(GreeterServer::new(MyGreeter::default()))
+//!     .add_reflection_service(reflection)
+//!     .serve(addr)
+//!     .await?;
+//! ```
+
+use crate::{
+    body::Body,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder, Streaming},
+    server::{Grpc, NamedService},
+    Request, Response, Status,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio_stream::Stream;
+use tower::service_fn;
+use tower_service::Service;
+
+const V1_PATH: &str = "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo";
+const V1ALPHA_PATH: &str = "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo";
+
+/// A builder of [`ReflectionService`]s.
+#[derive(Debug, Default)]
+pub struct Builder {
+    file_descriptor_sets: Vec<Bytes>,
+    service_names: Option<Vec<String>>,
+}
+
+impl Builder {
+    /// Registers an encoded `google.protobuf.FileDescriptorSet`.
+    ///
+    /// A file registered more than once, e.g. a common dependency of several sets, is only
+    /// described by its first registration.
+    pub fn register_encoded_file_descriptor_set(mut self, encoded: impl AsRef<[u8]>) -> Self {
+        self.file_descriptor_sets
+            .push(Bytes::copy_from_slice(encoded.as_ref()));
+        self
+    }
+
+    /// Lists the service with the fully qualified `name`, e.g. `helloworld.Greeter`.
+    ///
+    /// If not called, all the services of the registered files are listed. The symbols of the
+    /// services that are not listed can still be looked up.
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_names
+            .get_or_insert_with(Vec::new)
+            .push(name.into());
+        self
+    }
+
+    /// Build the `ReflectionService` describing the registered files.
+    pub fn build(self) -> Result<ReflectionService, Error> {
+        let mut state = State::default();
+
+        for set in self.file_descriptor_sets {
+            for field in Fields::new(set) {
+                if let (1, Value::Bytes(file)) = field? {
+                    state.add_file(file)?;
+                }
+            }
+        }
+
+        if let Some(service_names) = self.service_names {
+            state.service_names = service_names;
+        }
+
+        Ok(ReflectionService {
+            state: Arc::new(state),
+        })
+    }
+}
+
+/// The `grpc.reflection.v1.ServerReflection` service.
+///
+/// The `grpc.reflection.v1alpha.ServerReflection` service, which is still used by some
+/// clients, is served by its [`v1alpha`](Self::v1alpha) twin.
+#[derive(Clone)]
+pub struct ReflectionService {
+    state: Arc<State>,
+}
+
+impl ReflectionService {
+    /// Create a [`Builder`] of `ReflectionService`s.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Create a `grpc.reflection.v1alpha.ServerReflection` service describing the same files.
+    pub fn v1alpha(&self) -> ReflectionServiceV1Alpha {
+        ReflectionServiceV1Alpha {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Get the fully qualified names of the listed services.
+    pub fn service_names(&self) -> &[String] {
+        &self.state.service_names
+    }
+}
+
+impl fmt::Debug for ReflectionService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReflectionService")
+            .field("service_names", &self.state.service_names)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NamedService for ReflectionService {
+    const NAME: &'static str = "grpc.reflection.v1.ServerReflection";
+}
+
+impl<B> Service<http::Request<B>> for ReflectionService
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        Box::pin(serve(self.state.clone(), V1_PATH, req))
+    }
+}
+
+/// The `grpc.reflection.v1alpha.ServerReflection` service.
+///
+/// See [`ReflectionService::v1alpha`].
+#[derive(Clone)]
+pub struct ReflectionServiceV1Alpha {
+    state: Arc<State>,
+}
+
+impl fmt::Debug for ReflectionServiceV1Alpha {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReflectionServiceV1Alpha")
+            .field("service_names", &self.state.service_names)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NamedService for ReflectionServiceV1Alpha {
+    const NAME: &'static str = "grpc.reflection.v1alpha.ServerReflection";
+}
+
+impl<B> Service<http::Request<B>> for ReflectionServiceV1Alpha
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        Box::pin(serve(self.state.clone(), V1ALPHA_PATH, req))
+    }
+}
+
+async fn serve<B>(
+    state: Arc<State>,
+    path: &'static str,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>, Infallible>
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError> + Send + 'static,
+{
+    if req.uri().path() != path {
+        let (parts, ()) = Status::unimplemented("").into_http::<()>().into_parts();
+        return Ok(http::Response::from_parts(parts, Body::empty()));
+    }
+
+    let info = service_fn(move |req: Request<Streaming<ReflectionRequest>>| {
+        let stream = ReflectionStream {
+            requests: req.into_inner(),
+            state: state.clone(),
+            done: false,
+        };
+        async move { Ok(Response::new(stream)) }
+    });
+    Ok(Grpc::new(ReflectionCodec).streaming(info, req).await)
+}
+
+/// An error building a [`ReflectionService`] from an invalid `FileDescriptorSet`.
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    fn new(message: impl Into<String>) -> Self {
+        Error {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid FileDescriptorSet - {}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The files and symbols described by a reflection service.
+#[derive(Default)]
+struct State {
+    service_names: Vec<String>,
+    /// The encoded `FileDescriptorProto`s and their dependencies, by file name.
+    files: HashMap<String, (Bytes, Vec<String>)>,
+    /// The names of the files defining each fully qualified symbol.
+    symbols: HashMap<String, String>,
+}
+
+impl State {
+    fn add_file(&mut self, encoded: Bytes) -> Result<(), Error> {
+        let mut name = None;
+        let mut package = String::new();
+        let mut dependencies = Vec::new();
+        let mut messages = Vec::new();
+        let mut enums = Vec::new();
+        let mut services = Vec::new();
+
+        for field in Fields::new(encoded.clone()) {
+            match field? {
+                (1, Value::Bytes(value)) => name = Some(utf8(value)?),
+                (2, Value::Bytes(value)) => package = utf8(value)?,
+                (3, Value::Bytes(value)) => dependencies.push(utf8(value)?),
+                (4, Value::Bytes(value)) => messages.push(value),
+                (5, Value::Bytes(value)) => enums.push(value),
+                (6, Value::Bytes(value)) => services.push(value),
+                _ => {}
+            }
+        }
+
+        let name = name.ok_or_else(|| Error::new("missing file name"))?;
+        if self.files.contains_key(&name) {
+            return Ok(());
+        }
+
+        for message in messages {
+            self.add_message(&name, &package, message)?;
+        }
+        for en in enums {
+            self.add_enum(&name, &package, en)?;
+        }
+        for service in services {
+            let service_name = self.add_symbol(&name, &package, "service", service.clone())?;
+            self.service_names.push(service_name.clone());
+
+            for field in Fields::new(service) {
+                if let (2, Value::Bytes(method)) = field? {
+                    self.add_symbol(&name, &service_name, "method", method)?;
+                }
+            }
+        }
+
+        self.files.insert(name, (encoded, dependencies));
+        Ok(())
+    }
+
+    fn add_message(&mut self, file: &str, prefix: &str, message: Bytes) -> Result<(), Error> {
+        let message_name = self.add_symbol(file, prefix, "message", message.clone())?;
+
+        for field in Fields::new(message) {
+            match field? {
+                (2, Value::Bytes(value)) => {
+                    self.add_symbol(file, &message_name, "field", value)?;
+                }
+                (3, Value::Bytes(value)) => self.add_message(file, &message_name, value)?,
+                (4, Value::Bytes(value)) => self.add_enum(file, &message_name, value)?,
+                (8, Value::Bytes(value)) => {
+                    self.add_symbol(file, &message_name, "oneof", value)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_enum(&mut self, file: &str, prefix: &str, en: Bytes) -> Result<(), Error> {
+        let enum_name = self.add_symbol(file, prefix, "enum", en.clone())?;
+
+        for field in Fields::new(en) {
+            if let (2, Value::Bytes(value)) = field? {
+                self.add_symbol(file, &enum_name, "enum value", value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the symbol named by the `name` field of the `descriptor`, and returns its fully
+    /// qualified name.
+    fn add_symbol(
+        &mut self,
+        file: &str,
+        prefix: &str,
+        kind: &str,
+        descriptor: Bytes,
+    ) -> Result<String, Error> {
+        let mut name = None;
+        for field in Fields::new(descriptor) {
+            if let (1, Value::Bytes(value)) = field? {
+                name = Some(utf8(value)?);
+            }
+        }
+
+        let name = name.ok_or_else(|| Error::new(format!("missing {kind} name")))?;
+        let name = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}.{name}")
+        };
+
+        self.symbols.insert(name.clone(), file.to_owned());
+        Ok(name)
+    }
+
+    /// Gets the file named `filename`, followed by its registered transitive dependencies.
+    fn file_by_filename(&self, filename: &str) -> Result<Vec<Bytes>, Status> {
+        if !self.files.contains_key(filename) {
+            return Err(Status::not_found(format!("file '{filename}' not found")));
+        }
+
+        let mut files = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![filename];
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+            if let Some((encoded, dependencies)) = self.files.get(name) {
+                files.push(encoded.clone());
+                pending.extend(dependencies.iter().rev().map(String::as_str));
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn file_containing_symbol(&self, symbol: &str) -> Result<Vec<Bytes>, Status> {
+        match self.symbols.get(symbol) {
+            Some(file) => self.file_by_filename(file),
+            None => Err(Status::not_found(format!("symbol '{symbol}' not found"))),
+        }
+    }
+
+    fn respond(&self, request: &ReflectionRequest) -> Result<MessageResponse, Status> {
+        let response = match &request.message {
+            Some(MessageRequest::FileByFilename(filename)) => self.file_by_filename(filename),
+            Some(MessageRequest::FileContainingSymbol(symbol)) => {
+                self.file_containing_symbol(symbol)
+            }
+            Some(MessageRequest::FileContainingExtension) => {
+                Err(Status::not_found("extensions are not supported"))
+            }
+            Some(MessageRequest::AllExtensionNumbersOfType(base_type_name)) => {
+                // Some clients, e.g. grpcurl, expect this request not to fail.
+                return Ok(MessageResponse::AllExtensionNumbers(base_type_name.clone()));
+            }
+            Some(MessageRequest::ListServices) => {
+                return Ok(MessageResponse::ListServices(self.service_names.clone()));
+            }
+            None => return Err(Status::invalid_argument("invalid MessageRequest")),
+        };
+
+        Ok(match response {
+            Ok(files) => MessageResponse::FileDescriptors(files),
+            Err(status) => MessageResponse::Error(status),
+        })
+    }
+}
+
+/// The responses to the requests of a `ServerReflectionInfo` call.
+struct ReflectionStream {
+    requests: Streaming<ReflectionRequest>,
+    state: Arc<State>,
+    done: bool,
+}
+
+impl Stream for ReflectionStream {
+    type Item = Result<ReflectionResponse, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let request = match std::task::ready!(Pin::new(&mut self.requests).poll_next(cx)) {
+            Some(Ok(request)) => request,
+            Some(Err(status)) => {
+                self.done = true;
+                return Poll::Ready(Some(Err(status)));
+            }
+            None => return Poll::Ready(None),
+        };
+
+        match self.state.respond(&request) {
+            Ok(message) => Poll::Ready(Some(Ok(ReflectionResponse { request, message }))),
+            Err(status) => {
+                self.done = true;
+                Poll::Ready(Some(Err(status)))
+            }
+        }
+    }
+}
+
+/// A decoded `ServerReflectionRequest`.
+struct ReflectionRequest {
+    encoded: Bytes,
+    host: String,
+    message: Option<MessageRequest>,
+}
+
+enum MessageRequest {
+    FileByFilename(String),
+    FileContainingSymbol(String),
+    FileContainingExtension,
+    AllExtensionNumbersOfType(String),
+    ListServices,
+}
+
+/// A `ServerReflectionResponse` to be encoded.
+struct ReflectionResponse {
+    request: ReflectionRequest,
+    message: MessageResponse,
+}
+
+enum MessageResponse {
+    FileDescriptors(Vec<Bytes>),
+    AllExtensionNumbers(String),
+    ListServices(Vec<String>),
+    Error(Status),
+}
+
+/// A minimal codec for `ServerReflectionRequest` and `ServerReflectionResponse`, which are
+/// encoded the same by `v1` and `v1alpha`.
+#[derive(Debug, Clone, Copy)]
+struct ReflectionCodec;
+
+impl Codec for ReflectionCodec {
+    type Encode = ReflectionResponse;
+    type Decode = ReflectionRequest;
+
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        *self
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        *self
+    }
+}
+
+impl Encoder for ReflectionCodec {
+    type Item = ReflectionResponse;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        put_bytes(dst, 1, item.request.host.as_bytes());
+        put_bytes(dst, 2, &item.request.encoded);
+
+        let mut message = BytesMut::new();
+        let field = match item.message {
+            MessageResponse::FileDescriptors(files) => {
+                for file in files {
+                    put_bytes(&mut message, 1, &file);
+                }
+                4
+            }
+            MessageResponse::AllExtensionNumbers(base_type_name) => {
+                put_bytes(&mut message, 1, base_type_name.as_bytes());
+                5
+            }
+            MessageResponse::ListServices(service_names) => {
+                for name in service_names {
+                    let mut service = BytesMut::new();
+                    put_bytes(&mut service, 1, name.as_bytes());
+                    put_bytes(&mut message, 1, &service);
+                }
+                6
+            }
+            MessageResponse::Error(status) => {
+                let code = status.code() as u64;
+                if code != 0 {
+                    // field 1, wire type 0 (varint)
+                    message.put_u8(0x08);
+                    put_varint(&mut message, code);
+                }
+                put_bytes(&mut message, 2, status.message().as_bytes());
+                7
+            }
+        };
+        put_key(dst, field, 2);
+        put_varint(dst, message.len() as u64);
+        dst.put(message);
+
+        Ok(())
+    }
+}
+
+impl Decoder for ReflectionCodec {
+    type Item = ReflectionRequest;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let invalid = |_| Status::internal("invalid server reflection request");
+        let encoded = src.copy_to_bytes(src.remaining());
+        let mut host = String::new();
+        let mut message = None;
+
+        for field in Fields::new(encoded.clone()) {
+            let (field, value) = match field.map_err(invalid)? {
+                (field, Value::Bytes(value)) => (field, value),
+                _ => continue,
+            };
+            match (field, value) {
+                (1, value) => host = utf8(value).map_err(invalid)?,
+                (3, value) => {
+                    message = Some(MessageRequest::FileByFilename(
+                        utf8(value).map_err(invalid)?,
+                    ))
+                }
+                (4, value) => {
+                    message = Some(MessageRequest::FileContainingSymbol(
+                        utf8(value).map_err(invalid)?,
+                    ))
+                }
+                (5, _) => message = Some(MessageRequest::FileContainingExtension),
+                (6, value) => {
+                    message = Some(MessageRequest::AllExtensionNumbersOfType(
+                        utf8(value).map_err(invalid)?,
+                    ))
+                }
+                (7, _) => message = Some(MessageRequest::ListServices),
+                _ => {}
+            }
+        }
+
+        Ok(Some(ReflectionRequest {
+            encoded,
+            host,
+            message,
+        }))
+    }
+}
+
+/// The value of a protobuf field, with the values of the fields that are not length-delimited
+/// skipped.
+enum Value {
+    Bytes(Bytes),
+    Other,
+}
+
+/// The fields of an encoded protobuf message.
+struct Fields {
+    buf: Bytes,
+}
+
+impl Fields {
+    fn new(buf: Bytes) -> Self {
+        Fields { buf }
+    }
+
+    fn next_field(&mut self) -> Option<(u64, Value)> {
+        let key = get_varint(&mut self.buf)?;
+        let value = match key & 0x7 {
+            0 => get_varint(&mut self.buf).map(|_| Value::Other)?,
+            1 if self.buf.remaining() >= 8 => {
+                self.buf.advance(8);
+                Value::Other
+            }
+            2 => {
+                let len = usize::try_from(get_varint(&mut self.buf)?).ok()?;
+                if self.buf.remaining() < len {
+                    return None;
+                }
+                Value::Bytes(self.buf.split_to(len))
+            }
+            5 if self.buf.remaining() >= 4 => {
+                self.buf.advance(4);
+                Value::Other
+            }
+            _ => return None,
+        };
+        Some((key >> 3, value))
+    }
+}
+
+impl Iterator for Fields {
+    type Item = Result<(u64, Value), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.buf.has_remaining() {
+            return None;
+        }
+
+        match self.next_field() {
+            Some(field) => Some(Ok(field)),
+            None => {
+                // Stop at the first malformed field.
+                self.buf.clear();
+                Some(Err(Error::new("malformed protobuf message")))
+            }
+        }
+    }
+}
+
+fn utf8(value: Bytes) -> Result<String, Error> {
+    String::from_utf8(value.into()).map_err(|_| Error::new("invalid UTF-8 string"))
+}
+
+fn put_key(dst: &mut impl BufMut, field: u64, wire_type: u64) {
+    put_varint(dst, field << 3 | wire_type);
+}
+
+fn put_bytes(dst: &mut impl BufMut, field: u64, value: &[u8]) {
+    put_key(dst, field, 2);
+    put_varint(dst, value.len() as u64);
+    dst.put_slice(value);
+}
+
+fn put_varint(dst: &mut impl BufMut, mut value: u64) {
+    while value >= 0x80 {
+        dst.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    dst.put_u8(value as u8);
+}
+
+fn get_varint(src: &mut impl Buf) -> Option<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        if !src.has_remaining() {
+            return None;
+        }
+
+        let byte = src.get_u8();
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+
+    fn message(fields: &[(u64, &[u8])]) -> Bytes {
+        let mut buf = BytesMut::new();
+        for (field, value) in fields {
+            put_bytes(&mut buf, *field, value);
+        }
+        buf.freeze()
+    }
+
+    fn file(name: &str, dependencies: &[&str]) -> Bytes {
+        let method = message(&[(1, b"SayHello")]);
+        let service = message(&[(1, b"Greeter"), (2, &method)]);
+        let field = message(&[(1, b"name")]);
+        let request = message(&[(1, b"HelloRequest"), (2, &field)]);
+
+        let mut fields = vec![(1, name.as_bytes()), (2, b"helloworld".as_slice())];
+        fields.extend(dependencies.iter().map(|dep| (3, dep.as_bytes())));
+        fields.push((4, &request));
+        fields.push((6, &service));
+        message(&fields)
+    }
+
+    #[test]
+    fn indexes_symbols_and_dependencies() {
+        let hello = file("hello.proto", &["common.proto"]);
+        let common = message(&[(1, b"common.proto")]);
+        let set = message(&[(1, &hello), (1, &common)]);
+
+        let reflection = ReflectionService::builder()
+            .register_encoded_file_descriptor_set(&set)
+            .build()
+            .unwrap();
+        assert_eq!(reflection.service_names(), ["helloworld.Greeter"]);
+
+        let state = &reflection.state;
+        for symbol in [
+            "helloworld.Greeter",
+            "helloworld.Greeter.SayHello",
+            "helloworld.HelloRequest",
+            "helloworld.HelloRequest.name",
+        ] {
+            assert_eq!(state.symbols[symbol], "hello.proto");
+        }
+
+        let files = state.file_containing_symbol("helloworld.Greeter").unwrap();
+        assert_eq!(files, [hello, common.clone()]);
+        assert_eq!(state.file_by_filename("common.proto").unwrap(), [common]);
+        assert_eq!(
+            state.file_by_filename("missing.proto").unwrap_err().code(),
+            Code::NotFound
+        );
+    }
+
+    #[test]
+    fn filters_listed_services() {
+        let set = message(&[(1, &file("hello.proto", &[]))]);
+        let reflection = ReflectionService::builder()
+            .register_encoded_file_descriptor_set(&set)
+            .with_service_name("helloworld.Other")
+            .build()
+            .unwrap();
+
+        assert_eq!(reflection.service_names(), ["helloworld.Other"]);
+        assert!(reflection
+            .state
+            .file_containing_symbol("helloworld.Greeter")
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_file_descriptor_sets() {
+        let nameless = message(&[(1, &message(&[(2, b"helloworld")]))]);
+        let err = ReflectionService::builder()
+            .register_encoded_file_descriptor_set(&nameless)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid FileDescriptorSet - missing file name"
+        );
+
+        assert!(ReflectionService::builder()
+            .register_encoded_file_descriptor_set([0x0a, 0x05])
+            .build()
+            .is_err());
+    }
+}
@@ -1,14 +1,17 @@
 use crate::metadata::{MetadataMap, MetadataValue};
+use crate::service::PeerCapabilities;
+#[cfg(all(feature = "server", unix))]
+use crate::transport::server::UdsConnectInfo;
 #[cfg(feature = "server")]
-use crate::transport::server::TcpConnectInfo;
+use crate::transport::server::{CancellationToken, TcpConnectInfo};
 #[cfg(all(feature = "server", feature = "_tls-any"))]
-use crate::transport::server::TlsConnectInfo;
+use crate::transport::{server::TlsConnectInfo, TlsInfo};
 use http::Extensions;
 #[cfg(feature = "server")]
 use std::net::SocketAddr;
 #[cfg(all(feature = "server", feature = "_tls-any"))]
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 #[cfg(all(feature = "server", feature = "_tls-any"))]
 use tokio_rustls::rustls::pki_types::CertificateDer;
 use tokio_stream::Stream;
@@ -250,6 +253,17 @@ impl<T> Request<T> {
         addr
     }
 
+    /// Get the credentials of the process of the connected client.
+    ///
+    /// This will return `None` if the connection is not a unix domain socket, or the credentials
+    /// are not supported by the platform. This currently only works on the server side.
+    #[cfg(all(feature = "server", unix))]
+    pub fn peer_cred(&self) -> Option<tokio::net::unix::UCred> {
+        self.extensions()
+            .get::<UdsConnectInfo>()
+            .and_then(|i| i.peer_cred)
+    }
+
     /// Get the peer certificates of the connected client.
     ///
     /// This is used to fetch the certificates from the TLS session
@@ -263,6 +277,32 @@ impl<T> Request<T> {
             .and_then(|i| i.peer_certs())
     }
 
+    /// Get the parameters negotiated by the TLS handshake of this connection, e.g. its ALPN
+    /// protocol, TLS version and cipher suite, and the server name requested with SNI.
+    ///
+    /// This returns `Some` for TLS connections over any transport, on the server side of the
+    /// `transport` server.
+    #[cfg(all(feature = "server", feature = "_tls-any"))]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.extensions().get::<TlsInfo>()
+    }
+
+    /// Get the token that is cancelled once the client of this RPC goes away.
+    ///
+    /// This currently only returns `Some` on the server side of the `transport` server.
+    #[cfg(feature = "server")]
+    pub fn cancellation_token(&self) -> Option<CancellationToken> {
+        self.extensions().get::<CancellationToken>().cloned()
+    }
+
+    /// Get the capabilities advertised by the client.
+    ///
+    /// This is only set on the server side, when the server is wrapped in a
+    /// [`CapabilitiesLayer::server`](crate::service::CapabilitiesLayer::server).
+    pub fn peer_capabilities(&self) -> Option<&PeerCapabilities> {
+        self.extensions().get::<PeerCapabilities>()
+    }
+
     /// Set the max duration the request is allowed to take.
     ///
     /// Requires the server to support the `grpc-timeout` metadata, which Tonic does.
@@ -296,6 +336,21 @@ impl<T> Request<T> {
             .insert(crate::metadata::GRPC_TIMEOUT_HEADER, value);
     }
 
+    /// Get the deadline of the request.
+    ///
+    /// The deadline is set on the server side, by
+    /// [`GrpcTimeoutLayer::server`](crate::service::GrpcTimeoutLayer::server) which the
+    /// `transport` server applies, from the shorter of the `grpc-timeout` sent by the client and
+    /// the timeout of the server. It is measured from when the request was received, with the
+    /// [`Clock`](crate::time::Clock) of the server.
+    ///
+    /// Returns `None` if the request has no deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.extensions()
+            .get::<Deadline>()
+            .map(|deadline| deadline.0)
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &Extensions {
         &self.extensions
@@ -394,7 +449,11 @@ mod sealed {
     pub trait Sealed {}
 }
 
-fn duration_to_grpc_timeout(duration: Duration) -> String {
+/// The deadline of a request, see [`Request::deadline`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline(pub(crate) Instant);
+
+pub(crate) fn duration_to_grpc_timeout(duration: Duration) -> String {
     fn try_format<T: Into<u128>>(
         duration: Duration,
         unit: char,
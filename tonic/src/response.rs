@@ -1,6 +1,8 @@
 use http::Extensions;
+use std::sync::{Arc, Mutex};
 
 use crate::metadata::MetadataMap;
+use crate::service::PeerCapabilities;
 
 /// A gRPC response and metadata from an RPC call.
 #[derive(Debug)]
@@ -112,6 +114,39 @@ impl<T> Response<T> {
         &mut self.extensions
     }
 
+    /// Get a handle to set the trailing metadata of this response, sent by a server after the
+    /// last message of the response.
+    ///
+    /// The handle can be moved into the stream of a streaming response, to set trailers that
+    /// are only known once the stream completes, e.g. a count or a checksum of the messages.
+    /// The trailers are read when the stream ends, and are also sent if it ends with an error,
+    /// in which case the metadata of the error `Status` takes precedence.
+    ///
+    /// ```
+    /// # use tonic::Response;
+    /// # let messages = vec![1, 2, 3];
+    /// let mut response = Response::new(());
+    /// let trailers = response.trailers();
+    ///
+    /// // ... once the last message was sent:
+    /// trailers.update(|trailers| {
+    ///     trailers.insert("x-count", messages.len().into());
+    /// });
+    /// ```
+    pub fn trailers(&mut self) -> ResponseTrailers {
+        self.extensions
+            .get_or_insert_default::<ResponseTrailers>()
+            .clone()
+    }
+
+    /// Get the capabilities advertised by the server.
+    ///
+    /// This is only set on the client side, when the channel is wrapped in a
+    /// [`CapabilitiesLayer::client`](crate::service::CapabilitiesLayer::client).
+    pub fn peer_capabilities(&self) -> Option<&PeerCapabilities> {
+        self.extensions().get::<PeerCapabilities>()
+    }
+
     /// Disable compression of the response body.
     ///
     /// This disables compression of the body of this response, even if compression is enabled on
@@ -127,6 +162,26 @@ impl<T> Response<T> {
     }
 }
 
+/// A handle to the trailing metadata of a server response, see [`Response::trailers`].
+#[derive(Clone, Debug, Default)]
+pub struct ResponseTrailers(Arc<Mutex<MetadataMap>>);
+
+impl ResponseTrailers {
+    /// Replaces the trailers with `trailers`.
+    pub fn set(&self, trailers: MetadataMap) {
+        self.update(|t| *t = trailers);
+    }
+
+    /// Updates the trailers with `f`.
+    pub fn update(&self, f: impl FnOnce(&mut MetadataMap)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    pub(crate) fn take(&self) -> MetadataMap {
+        std::mem::take(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
 impl<T> From<T> for Response<T> {
     fn from(inner: T) -> Self {
         Response::new(inner)
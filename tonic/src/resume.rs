@@ -0,0 +1,274 @@
+//! Resumable server streams.
+//!
+//! This module implements an optional convention for server streaming calls that can pick up
+//! where they left off after a transient failure:
+//!
+//! - The server attaches an opaque resume token to the messages it streams. The token is part of
+//!   the message itself, and exposed to tonic by implementing [`ResumeToken`] for the message
+//!   type.
+//! - When re-issuing the call, the client sends the token of the last message it received in the
+//!   `resume-token-bin` request metadata. The server reads it with [`resume_token`] and continues
+//!   the stream after that message.
+//!
+//! [`ResumableStream`] implements the client side: it re-issues the call with the last token
+//! whenever the stream fails with a transient error, and yields the messages of all attempts as
+//! one stream.
+//!
+//! ```rust,ignore
+//! impl ResumeToken for Event {
+//!     fn resume_token(&self) -> Option<Bytes> {
+//!         Some(Bytes::from(self.sequence.to_be_bytes().to_vec()))
+//!     }
+//! }
+//!
+//! // server
+//! async fn subscribe(&self, request: Request<Topic>) -> Result<Response<Self::SubscribeStream>, Status> {
+//!     let after = resume_token(&request).map(|token| decode_sequence(&token));
+//!     // ...
+//! }
+//!
+//! // client
+//! let events = ResumableStream::new(Request::new(topic), move |request| {
+//!     let mut client = client.clone();
+//!     async move { client.subscribe(request).await }
+//! });
+//! ```
+
+use crate::{
+    codec::Streaming,
+    metadata::MetadataValue,
+    time::{Clock, SharedClock, Sleep},
+    Code, Request, Response, Status,
+};
+use bytes::Bytes;
+use std::{
+    cmp, fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio_stream::Stream;
+
+/// The request metadata key the last received resume token is sent with.
+pub const RESUME_TOKEN_KEY: &str = "resume-token-bin";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// A streamed message that carries a resume token.
+pub trait ResumeToken {
+    /// The token to resume the stream after this message with, if any.
+    fn resume_token(&self) -> Option<Bytes>;
+}
+
+/// Returns the resume token sent by the client, i.e. the token of the last message it received.
+///
+/// Returns `None` for calls that are not resumed.
+pub fn resume_token<T>(request: &Request<T>) -> Option<Bytes> {
+    request
+        .metadata()
+        .get_bin(RESUME_TOKEN_KEY)?
+        .to_bytes()
+        .ok()
+}
+
+/// Sets the resume token to send with `request`.
+pub fn set_resume_token<T>(request: &mut Request<T>, token: &[u8]) {
+    request
+        .metadata_mut()
+        .insert_bin(RESUME_TOKEN_KEY, MetadataValue::from_bytes(token));
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type Call<T> =
+    Box<dyn FnMut(Option<&Bytes>) -> BoxFuture<Result<Response<Streaming<T>>, Status>> + Send>;
+
+/// A server stream that transparently resumes after transient failures.
+///
+/// The call is re-issued with the [`ResumeToken`] of the last received message whenever it
+/// fails with one of the [`retry_on`](Self::retry_on) codes, `UNAVAILABLE` by default. Attempts
+/// are delayed with an exponential backoff, and the error is yielded once
+/// [`max_attempts`](Self::max_attempts) consecutive attempts failed without receiving a message.
+pub struct ResumableStream<T> {
+    call: Call<T>,
+    state: State<T>,
+    token: Option<Bytes>,
+    failures: u32,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_on: Vec<Code>,
+    clock: SharedClock,
+}
+
+enum State<T> {
+    Idle,
+    Calling(BoxFuture<Result<Response<Streaming<T>>, Status>>),
+    Streaming(Box<Streaming<T>>),
+    Backoff(Sleep),
+    Done,
+}
+
+impl<T> ResumableStream<T>
+where
+    T: ResumeToken + 'static,
+{
+    /// Creates a stream that issues `request` through `call`, and re-issues it on failures.
+    ///
+    /// Every attempt sends a clone of the message, metadata and extensions of `request`.
+    pub fn new<M, F, Fut>(request: Request<M>, mut call: F) -> Self
+    where
+        M: Clone + Send + 'static,
+        F: FnMut(Request<M>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Response<Streaming<T>>, Status>> + Send + 'static,
+    {
+        let (metadata, extensions, message) = request.into_parts();
+
+        let call = move |token: Option<&Bytes>| {
+            let mut request =
+                Request::from_parts(metadata.clone(), extensions.clone(), message.clone());
+            if let Some(token) = token {
+                set_resume_token(&mut request, token);
+            }
+
+            Box::pin(call(request)) as BoxFuture<_>
+        };
+
+        Self {
+            call: Box::new(call),
+            state: State::Idle,
+            token: None,
+            failures: 0,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            retry_on: vec![Code::Unavailable],
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Sets the number of consecutive attempts that may fail before the error is yielded.
+    ///
+    /// Receiving a message resets the count. Defaults to 5.
+    pub fn max_attempts(self, attempts: u32) -> Self {
+        ResumableStream {
+            max_attempts: attempts.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the delay before the first re-issued call, doubling with every consecutive failure up
+    /// to `max`. Defaults to 100 milliseconds and 5 seconds.
+    pub fn backoff(self, initial: Duration, max: Duration) -> Self {
+        ResumableStream {
+            initial_backoff: initial,
+            max_backoff: max,
+            ..self
+        }
+    }
+
+    /// Sets the status codes the call is re-issued on. Defaults to `UNAVAILABLE`.
+    pub fn retry_on(self, codes: impl IntoIterator<Item = Code>) -> Self {
+        ResumableStream {
+            retry_on: codes.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Sets the [`Clock`] used to wait between attempts. Uses the Tokio timer by default.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        ResumableStream {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
+    /// The resume token of the last received message.
+    pub fn resume_token(&self) -> Option<&Bytes> {
+        self.token.as_ref()
+    }
+
+    /// Schedules the next attempt, or returns the status if the call should not be re-issued.
+    fn fail(&mut self, status: Status) -> Option<Status> {
+        self.failures += 1;
+
+        if self.failures >= self.max_attempts || !self.retry_on.contains(&status.code()) {
+            self.state = State::Done;
+            return Some(status);
+        }
+
+        tracing::debug!("resuming stream after error: {}", status);
+
+        let backoff = cmp::min(
+            self.initial_backoff
+                .saturating_mul(1 << (self.failures - 1).min(31)),
+            self.max_backoff,
+        );
+        self.state = State::Backoff(self.clock.sleep(backoff));
+        None
+    }
+}
+
+impl<T> Stream for ResumableStream<T>
+where
+    T: ResumeToken + 'static,
+{
+    type Item = Result<T, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match &mut this.state {
+                State::Idle => {
+                    let fut = (this.call)(this.token.as_ref());
+                    this.state = State::Calling(fut);
+                }
+                State::Calling(fut) => match ready!(fut.as_mut().poll(cx)) {
+                    Ok(response) => this.state = State::Streaming(Box::new(response.into_inner())),
+                    Err(status) => {
+                        if let Some(status) = this.fail(status) {
+                            return Poll::Ready(Some(Err(status)));
+                        }
+                    }
+                },
+                State::Streaming(stream) => match ready!(Pin::new(stream).poll_next(cx)) {
+                    Some(Ok(message)) => {
+                        if let Some(token) = message.resume_token() {
+                            this.token = Some(token);
+                        }
+                        this.failures = 0;
+                        return Poll::Ready(Some(Ok(message)));
+                    }
+                    Some(Err(status)) => {
+                        if let Some(status) = this.fail(status) {
+                            return Poll::Ready(Some(Err(status)));
+                        }
+                    }
+                    None => {
+                        this.state = State::Done;
+                        return Poll::Ready(None);
+                    }
+                },
+                State::Backoff(sleep) => {
+                    ready!(sleep.as_mut().poll(cx));
+                    this.state = State::Idle;
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for ResumableStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumableStream")
+            .field("token", &self.token)
+            .field("failures", &self.failures)
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_on", &self.retry_on)
+            .finish_non_exhaustive()
+    }
+}
@@ -2,12 +2,15 @@ use crate::codec::compression::{
     CompressionEncoding, EnabledCompressionEncodings, SingleMessageCompressionOverride,
 };
 use crate::codec::EncodeBody;
-use crate::metadata::GRPC_CONTENT_TYPE;
+use crate::metadata::{MetadataLimits, ReservedHeaderPolicy, GRPC_CONTENT_TYPE};
 use crate::{
     body::Body,
-    codec::{Codec, Streaming},
+    codec::{
+        validate_message, Codec, CodecExecutor, MessageKind, MessageValidators, Streaming,
+        ValidatingDecoder,
+    },
     server::{ClientStreamingService, ServerStreamingService, StreamingService, UnaryService},
-    Request, Status,
+    Request, ResponseTrailers, Status,
 };
 use http_body::Body as HttpBody;
 use std::{fmt, pin::pin};
@@ -22,6 +25,14 @@ macro_rules! t {
     };
 }
 
+/// The default message size limits of the services of a server, used by the services that do
+/// not set their own limits.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct MessageSizeLimits {
+    pub(crate) max_decoding_message_size: Option<usize>,
+    pub(crate) max_encoding_message_size: Option<usize>,
+}
+
 /// A gRPC Server handler.
 ///
 /// This will wrap some inner [`Codec`] and provide utilities to handle
@@ -41,6 +52,10 @@ pub struct Grpc<T> {
     max_decoding_message_size: Option<usize>,
     /// Limits the maximum size of an encoded message.
     max_encoding_message_size: Option<usize>,
+    /// Limits the metadata of responses.
+    metadata_limits: MetadataLimits,
+    /// Validates requests and responses.
+    validators: Option<MessageValidators>,
 }
 
 impl<T> Grpc<T>
@@ -55,6 +70,8 @@ where
             send_compression_encodings: EnabledCompressionEncodings::default(),
             max_decoding_message_size: None,
             max_encoding_message_size: None,
+            metadata_limits: MetadataLimits::default(),
+            validators: None,
         }
     }
 
@@ -123,6 +140,9 @@ where
 
     /// Limits the maximum size of a decoded message.
     ///
+    /// This takes precedence over the default limit of the server, e.g. the one set with
+    /// `Server::max_decoding_message_size`.
+    ///
     /// # Example
     ///
     /// The most common way of using this is through a server generated by tonic-build:
@@ -153,6 +173,9 @@ where
 
     /// Limits the maximum size of a encoded message.
     ///
+    /// This takes precedence over the default limit of the server, e.g. the one set with
+    /// `Server::max_encoding_message_size`.
+    ///
     /// # Example
     ///
     /// The most common way of using this is through a server generated by tonic-build:
@@ -181,6 +204,49 @@ where
         self
     }
 
+    /// Limits the size of the metadata of responses, counted like HTTP/2 counts the size of a
+    /// header list: the length of the name and the value of each entry plus 32 bytes.
+    ///
+    /// Responses exceeding the limit are replaced with a `RESOURCE_EXHAUSTED` status. This takes
+    /// precedence over the default limit of the server, e.g. the one set with
+    /// `Server::max_metadata_size`. Defaults to no limit.
+    pub fn max_metadata_size(mut self, limit: usize) -> Self {
+        self.metadata_limits.max_size = Some(limit);
+        self
+    }
+
+    /// Limits the number of entries of the metadata of responses.
+    ///
+    /// Responses exceeding the limit are replaced with a `RESOURCE_EXHAUSTED` status. This takes
+    /// precedence over the default limit of the server, e.g. the one set with
+    /// `Server::max_metadata_entries`. Defaults to no limit.
+    pub fn max_metadata_entries(mut self, limit: usize) -> Self {
+        self.metadata_limits.max_entries = Some(limit);
+        self
+    }
+
+    /// Sets what to do with reserved headers, e.g. `grpc-encoding` or `connection`, set in the
+    /// metadata of responses.
+    ///
+    /// See [`ReservedHeaderPolicy`] for the headers that are reserved. This takes precedence
+    /// over the default policy of the server, e.g. the one set with
+    /// `Server::reserved_header_policy`. Defaults to [`ReservedHeaderPolicy::Allow`].
+    pub fn reserved_header_policy(mut self, policy: ReservedHeaderPolicy) -> Self {
+        self.metadata_limits.reserved_headers = Some(policy);
+        self
+    }
+
+    /// Validate requests before passing them to the handler and responses before sending them.
+    ///
+    /// Requests that are not valid are rejected with an `INVALID_ARGUMENT` status, and responses
+    /// are replaced with an `INTERNAL` status. See [`MessageValidators`] for more details. This
+    /// takes precedence over the validators of the server, e.g. the ones set with
+    /// `Server::message_validators`.
+    pub fn message_validators(mut self, validators: MessageValidators) -> Self {
+        self.validators = Some(validators);
+        self
+    }
+
     #[doc(hidden)]
     pub fn apply_compression_config(
         mut self,
@@ -226,19 +292,15 @@ where
         B: HttpBody + Send + 'static,
         B::Error: Into<crate::BoxError> + Send,
     {
-        let accept_encoding = CompressionEncoding::from_accept_encoding_header(
-            req.headers(),
-            self.send_compression_encodings,
-        );
+        let settings = self.response_settings(&req);
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
             Err(status) => {
                 return self.map_response::<tokio_stream::Once<Result<T::Encode, Status>>>(
                     Err(status),
-                    accept_encoding,
                     SingleMessageCompressionOverride::default(),
-                    self.max_encoding_message_size,
+                    settings,
                 );
             }
         };
@@ -250,12 +312,7 @@ where
 
         let compression_override = compression_override_from_response(&response);
 
-        self.map_response(
-            response,
-            accept_encoding,
-            compression_override,
-            self.max_encoding_message_size,
-        )
+        self.map_response(response, compression_override, settings)
     }
 
     /// Handle a server side streaming request.
@@ -270,19 +327,15 @@ where
         B: HttpBody + Send + 'static,
         B::Error: Into<crate::BoxError> + Send,
     {
-        let accept_encoding = CompressionEncoding::from_accept_encoding_header(
-            req.headers(),
-            self.send_compression_encodings,
-        );
+        let settings = self.response_settings(&req);
 
         let request = match self.map_request_unary(req).await {
             Ok(r) => r,
             Err(status) => {
                 return self.map_response::<S::ResponseStream>(
                     Err(status),
-                    accept_encoding,
                     SingleMessageCompressionOverride::default(),
-                    self.max_encoding_message_size,
+                    settings,
                 );
             }
         };
@@ -291,11 +344,10 @@ where
 
         self.map_response(
             response,
-            accept_encoding,
             // disabling compression of individual stream items must be done on
             // the items themselves
             SingleMessageCompressionOverride::default(),
-            self.max_encoding_message_size,
+            settings,
         )
     }
 
@@ -310,10 +362,7 @@ where
         B: HttpBody + Send + 'static,
         B::Error: Into<crate::BoxError> + Send + 'static,
     {
-        let accept_encoding = CompressionEncoding::from_accept_encoding_header(
-            req.headers(),
-            self.send_compression_encodings,
-        );
+        let settings = self.response_settings(&req);
 
         let request = t!(self.map_request_streaming(req));
 
@@ -324,12 +373,7 @@ where
 
         let compression_override = compression_override_from_response(&response);
 
-        self.map_response(
-            response,
-            accept_encoding,
-            compression_override,
-            self.max_encoding_message_size,
-        )
+        self.map_response(response, compression_override, settings)
     }
 
     /// Handle a bi-directional streaming gRPC request.
@@ -344,10 +388,7 @@ where
         B: HttpBody + Send + 'static,
         B::Error: Into<crate::BoxError> + Send,
     {
-        let accept_encoding = CompressionEncoding::from_accept_encoding_header(
-            req.headers(),
-            self.send_compression_encodings,
-        );
+        let settings = self.response_settings(&req);
 
         let request = t!(self.map_request_streaming(req));
 
@@ -355,9 +396,8 @@ where
 
         self.map_response(
             response,
-            accept_encoding,
             SingleMessageCompressionOverride::default(),
-            self.max_encoding_message_size,
+            settings,
         )
     }
 
@@ -370,15 +410,23 @@ where
         B::Error: Into<crate::BoxError> + Send,
     {
         let request_compression_encoding = self.request_encoding_if_supported(&request)?;
+        let executor = codec_executor(&request);
+        let max_decoding_message_size = self.max_decoding_message_size_for(&request);
+        let decoder = ValidatingDecoder::new(
+            self.codec.decoder(),
+            self.validators_for(&request),
+            MessageKind::Request,
+        );
 
         let (parts, body) = request.into_parts();
 
         let mut stream = pin!(Streaming::new_request(
-            self.codec.decoder(),
+            decoder,
             body,
             request_compression_encoding,
-            self.max_decoding_message_size,
-        ));
+            max_decoding_message_size,
+        )
+        .with_executor(executor));
 
         let message = stream
             .try_next()
@@ -403,14 +451,17 @@ where
         B::Error: Into<crate::BoxError> + Send,
     {
         let encoding = self.request_encoding_if_supported(&request)?;
+        let executor = codec_executor(&request);
+        let max_decoding_message_size = self.max_decoding_message_size_for(&request);
+        let decoder = ValidatingDecoder::new(
+            self.codec.decoder(),
+            self.validators_for(&request),
+            MessageKind::Request,
+        );
 
         let request = request.map(|body| {
-            Streaming::new_request(
-                self.codec.decoder(),
-                body,
-                encoding,
-                self.max_decoding_message_size,
-            )
+            Streaming::new_request(decoder, body, encoding, max_decoding_message_size)
+                .with_executor(executor)
         });
 
         Ok(Request::from_http(request))
@@ -419,13 +470,29 @@ where
     fn map_response<B>(
         &mut self,
         response: Result<crate::Response<B>, Status>,
-        accept_encoding: Option<CompressionEncoding>,
         compression_override: SingleMessageCompressionOverride,
-        max_message_size: Option<usize>,
+        settings: ResponseSettings,
     ) -> http::Response<Body>
     where
         B: Stream<Item = Result<T::Encode, Status>> + Send + 'static,
     {
+        let ResponseSettings {
+            accept_encoding,
+            max_message_size,
+            metadata_limits,
+            validators,
+            executor,
+        } = settings;
+
+        let response = match response {
+            Ok(mut response) => metadata_limits
+                .enforce("response", response.metadata_mut())
+                .map(|()| response),
+            Err(mut status) => Err(metadata_limits
+                .enforce("response", status.metadata_mut())
+                .err()
+                .unwrap_or(status)),
+        };
         let response = t!(response);
 
         let (mut parts, body) = response.into_http().into_parts();
@@ -444,17 +511,71 @@ where
             );
         }
 
+        let body = body.map(move |message| {
+            message.and_then(|m| validate_message(validators.as_ref(), MessageKind::Response, m))
+        });
         let body = EncodeBody::new_server(
             self.codec.encoder(),
             body,
             accept_encoding,
             compression_override,
             max_message_size,
-        );
+        )
+        .with_executor(executor)
+        .with_trailers(parts.extensions.get::<ResponseTrailers>().cloned());
 
         http::Response::from_parts(parts, Body::new(body))
     }
 
+    /// Gets the settings of the response to `request`.
+    fn response_settings<B>(&self, request: &http::Request<B>) -> ResponseSettings {
+        ResponseSettings {
+            accept_encoding: CompressionEncoding::from_accept_encoding_header(
+                request.headers(),
+                self.send_compression_encodings,
+            ),
+            max_message_size: self.max_encoding_message_size_for(request),
+            metadata_limits: self.metadata_limits_for(request),
+            validators: self.validators_for(request),
+            executor: codec_executor(request),
+        }
+    }
+
+    /// Gets the decoding limit of this service, or else the default of its server.
+    fn max_decoding_message_size_for<B>(&self, request: &http::Request<B>) -> Option<usize> {
+        self.max_decoding_message_size.or_else(|| {
+            request
+                .extensions()
+                .get::<MessageSizeLimits>()
+                .and_then(|limits| limits.max_decoding_message_size)
+        })
+    }
+
+    /// Gets the encoding limit of this service, or else the default of its server.
+    fn max_encoding_message_size_for<B>(&self, request: &http::Request<B>) -> Option<usize> {
+        self.max_encoding_message_size.or_else(|| {
+            request
+                .extensions()
+                .get::<MessageSizeLimits>()
+                .and_then(|limits| limits.max_encoding_message_size)
+        })
+    }
+
+    /// Gets the metadata limits of this service, or else the defaults of its server.
+    fn metadata_limits_for<B>(&self, request: &http::Request<B>) -> MetadataLimits {
+        match request.extensions().get::<MetadataLimits>() {
+            Some(defaults) => self.metadata_limits.or(*defaults),
+            None => self.metadata_limits,
+        }
+    }
+
+    /// Gets the validators of this service, or else the ones of its server.
+    fn validators_for<B>(&self, request: &http::Request<B>) -> Option<MessageValidators> {
+        self.validators
+            .clone()
+            .or_else(|| request.extensions().get::<MessageValidators>().cloned())
+    }
+
     fn request_encoding_if_supported<B>(
         &self,
         request: &http::Request<B>,
@@ -466,6 +587,20 @@ where
     }
 }
 
+/// How to encode the response to a request, captured before the request is consumed.
+struct ResponseSettings {
+    accept_encoding: Option<CompressionEncoding>,
+    max_message_size: Option<usize>,
+    metadata_limits: MetadataLimits,
+    validators: Option<MessageValidators>,
+    executor: Option<CodecExecutor>,
+}
+
+/// The [`CodecExecutor`] the server configured for `request`, if any.
+fn codec_executor<B>(request: &http::Request<B>) -> Option<CodecExecutor> {
+    request.extensions().get::<CodecExecutor>().cloned()
+}
+
 impl<T: fmt::Debug> fmt::Debug for Grpc<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Grpc")
@@ -12,6 +12,8 @@ mod grpc;
 mod service;
 
 pub use self::grpc::Grpc;
+#[cfg(feature = "server")]
+pub(crate) use self::grpc::MessageSizeLimits;
 pub use self::service::{
     ClientStreamingService, ServerStreamingService, StreamingService, UnaryService,
 };
@@ -0,0 +1,658 @@
+//! Middleware that logs a structured record for every RPC served.
+//!
+//! See [`AccessLogLayer`] for more details.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock},
+    transport::server::TcpConnectInfo,
+    Code, Status,
+};
+use bytes::{Buf, Bytes};
+use http::{header, HeaderMap, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type Sink = Arc<dyn Fn(&AccessLogRecord) + Send + Sync + 'static>;
+
+/// A [`Layer`] that logs an [`AccessLogRecord`] for every RPC once its response is sent.
+///
+/// Records are emitted as `tracing` events with the `tonic::access_log` target at the `INFO`
+/// level by default, or passed to a custom [`sink`](Self::sink), e.g. to write them to a file
+/// or export them as metrics.
+///
+/// On busy servers, only one in every [`sample`](Self::sample) RPCs can be logged. Failed
+/// RPCs are still all logged unless [`log_failures`](Self::log_failures) is disabled.
+///
+/// The layer should be applied with [`Server::layer`](crate::transport::Server::layer) so the
+/// peer address of the connection is known.
+///
+/// # Example
+///
+/// ```
+/// # use tonic::service::AccessLogLayer;
+/// let layer = AccessLogLayer::new()
+///     .sample(100)
+///     .sink(|record| println!("{} {:?} {:?}", record.method(), record.code(), record.latency()));
+/// ```
+#[derive(Clone)]
+pub struct AccessLogLayer {
+    sink: Option<Sink>,
+    sample: u64,
+    log_failures: bool,
+    seen: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+impl AccessLogLayer {
+    /// Create a new `AccessLogLayer` that logs every RPC with `tracing`.
+    pub fn new() -> Self {
+        AccessLogLayer {
+            sink: None,
+            sample: 1,
+            log_failures: true,
+            seen: Arc::new(AtomicU64::new(0)),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Passes the records to `sink` instead of emitting them as `tracing` events.
+    pub fn sink<F>(self, sink: F) -> Self
+    where
+        F: Fn(&AccessLogRecord) + Send + Sync + 'static,
+    {
+        AccessLogLayer {
+            sink: Some(Arc::new(sink)),
+            ..self
+        }
+    }
+
+    /// Logs only one in every `one_in` RPCs. Defaults to `1`, i.e. every RPC is logged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `one_in` is `0`.
+    pub fn sample(self, one_in: u64) -> Self {
+        assert!(one_in > 0, "the sampling rate must not be 0");
+        AccessLogLayer {
+            sample: one_in,
+            ..self
+        }
+    }
+
+    /// Whether RPCs that do not complete with `Ok` are logged even if they are not sampled.
+    /// Defaults to `true`.
+    pub fn log_failures(self, enabled: bool) -> Self {
+        AccessLogLayer {
+            log_failures: enabled,
+            ..self
+        }
+    }
+
+    /// Sets the [`Clock`] used to measure latencies. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        AccessLogLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl Default for AccessLogLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AccessLogLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLogLayer")
+            .field("sample", &self.sample)
+            .field("log_failures", &self.log_failures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLog {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that logs a structured record for every RPC served.
+///
+/// See [`AccessLogLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct AccessLog<S> {
+    inner: S,
+    layer: AccessLogLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AccessLog<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ReqBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ReqBody::Error: Into<crate::BoxError>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let layer = &self.layer;
+        let sampled = layer.seen.fetch_add(1, Ordering::Relaxed) % layer.sample == 0;
+
+        let received = Arc::new(Counts::default());
+        let pending = Pending {
+            record: AccessLogRecord {
+                method: req.uri().path().to_owned(),
+                peer: remote_addr(&req),
+                user_agent: req
+                    .headers()
+                    .get(header::USER_AGENT)
+                    .and_then(|value| value.to_str().ok())
+                    .map(ToOwned::to_owned),
+                code: Code::Cancelled,
+                latency: Duration::ZERO,
+                request_bytes: 0,
+                request_messages: 0,
+                response_bytes: 0,
+                response_messages: 0,
+            },
+            code: None,
+            start: layer.clock.now(),
+            received: received.clone(),
+            sent: MessageCounter::default(),
+            sampled,
+            layer: layer.clone(),
+        };
+
+        let req = req.map(|inner| {
+            Body::new(CountingBody {
+                inner,
+                counter: MessageCounter::default(),
+                counts: received,
+            })
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            pending: Some(pending),
+        }
+    }
+}
+
+fn remote_addr<B>(req: &Request<B>) -> Option<SocketAddr> {
+    let addr = req
+        .extensions()
+        .get::<TcpConnectInfo>()
+        .and_then(|i| i.remote_addr());
+
+    #[cfg(feature = "_tls-any")]
+    let addr = addr.or_else(|| {
+        req.extensions()
+            .get::<crate::transport::server::TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|i| i.get_ref().remote_addr())
+    });
+
+    addr
+}
+
+/// Response future for [`AccessLog`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    pending: Option<Pending>,
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let mut pending = this.pending.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                pending.code = Some(Code::Unknown);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        // A trailers-only response carries its status in its headers.
+        pending.update_code(response.headers());
+        let pending = if response.body().is_end_stream() {
+            None
+        } else {
+            Some(pending)
+        };
+
+        Poll::Ready(Ok(
+            response.map(|inner| Body::new(AccessLogBody { inner, pending }))
+        ))
+    }
+}
+
+/// An RPC whose record is logged once it is dropped.
+struct Pending {
+    record: AccessLogRecord,
+    code: Option<Code>,
+    start: Instant,
+    received: Arc<Counts>,
+    sent: MessageCounter,
+    sampled: bool,
+    layer: AccessLogLayer,
+}
+
+impl Pending {
+    fn update_code(&mut self, headers: &HeaderMap) {
+        if let Some(code) = headers.get(Status::GRPC_STATUS) {
+            self.code = Some(Code::from_bytes(code.as_bytes()));
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        // A response without a status is malformed, unless it is dropped before it is sent.
+        let code = self.code.unwrap_or(Code::Cancelled);
+        if !self.sampled && (code == Code::Ok || !self.layer.log_failures) {
+            return;
+        }
+
+        let record = &mut self.record;
+        record.code = code;
+        record.latency = self.layer.clock.now().saturating_duration_since(self.start);
+        record.request_bytes = self.received.bytes.load(Ordering::Relaxed);
+        record.request_messages = self.received.messages.load(Ordering::Relaxed);
+        record.response_bytes = self.sent.bytes;
+        record.response_messages = self.sent.messages;
+
+        match &self.layer.sink {
+            Some(sink) => sink(record),
+            None => tracing::info!(
+                target: "tonic::access_log",
+                method = %record.method,
+                peer = ?record.peer,
+                code = ?record.code,
+                latency = ?record.latency,
+                request_bytes = record.request_bytes,
+                request_messages = record.request_messages,
+                response_bytes = record.response_bytes,
+                response_messages = record.response_messages,
+                user_agent = record.user_agent.as_deref(),
+            ),
+        }
+    }
+}
+
+/// A structured record of an RPC served, logged by [`AccessLogLayer`].
+#[derive(Debug, Clone)]
+pub struct AccessLogRecord {
+    method: String,
+    peer: Option<SocketAddr>,
+    user_agent: Option<String>,
+    code: Code,
+    latency: Duration,
+    request_bytes: u64,
+    request_messages: u64,
+    response_bytes: u64,
+    response_messages: u64,
+}
+
+impl AccessLogRecord {
+    /// The path of the method called, e.g. `/helloworld.Greeter/SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The remote address of the connection, if known.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+
+    /// The `user-agent` of the client, if any.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// The status code the RPC completed with.
+    ///
+    /// RPCs that were cancelled before their response was sent complete with `Cancelled`.
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// The time from the request being received until its response was sent.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// The number of bytes in the request body, including the gRPC framing.
+    pub fn request_bytes(&self) -> u64 {
+        self.request_bytes
+    }
+
+    /// The number of messages in the request.
+    pub fn request_messages(&self) -> u64 {
+        self.request_messages
+    }
+
+    /// The number of bytes in the response body, including the gRPC framing.
+    pub fn response_bytes(&self) -> u64 {
+        self.response_bytes
+    }
+
+    /// The number of messages in the response.
+    pub fn response_messages(&self) -> u64 {
+        self.response_messages
+    }
+}
+
+/// Byte and message counts shared with the request body.
+#[derive(Default)]
+struct Counts {
+    bytes: AtomicU64,
+    messages: AtomicU64,
+}
+
+/// Counts the messages of a gRPC stream by following its length-prefixed framing.
+#[derive(Default)]
+struct MessageCounter {
+    bytes: u64,
+    messages: u64,
+    header: [u8; 5],
+    header_len: usize,
+    remaining: u64,
+}
+
+impl MessageCounter {
+    fn count(&mut self, mut data: &[u8]) {
+        self.bytes += data.len() as u64;
+
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let skip = self.remaining.min(data.len() as u64);
+                self.remaining -= skip;
+                data.advance(skip as usize);
+                continue;
+            }
+
+            let take = (self.header.len() - self.header_len).min(data.len());
+            self.header[self.header_len..][..take].copy_from_slice(&data[..take]);
+            self.header_len += take;
+            data.advance(take);
+
+            if self.header_len == self.header.len() {
+                let len = u32::from_be_bytes([
+                    self.header[1],
+                    self.header[2],
+                    self.header[3],
+                    self.header[4],
+                ]);
+                self.messages += 1;
+                self.header_len = 0;
+                self.remaining = u64::from(len);
+            }
+        }
+    }
+}
+
+/// A request body that counts the bytes and messages it yields.
+#[pin_project]
+struct CountingBody<B> {
+    #[pin]
+    inner: B,
+    counter: MessageCounter,
+    counts: Arc<Counts>,
+}
+
+impl<B> http_body::Body for CountingBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(data) = frame.as_ref().and_then(|f| f.as_ref().ok()?.data_ref()) {
+            this.counter.count(data);
+            this.counts
+                .bytes
+                .store(this.counter.bytes, Ordering::Relaxed);
+            this.counts
+                .messages
+                .store(this.counter.messages, Ordering::Relaxed);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A response body that logs the record of its RPC once it is sent.
+#[pin_project]
+struct AccessLogBody<B> {
+    #[pin]
+    inner: B,
+    pending: Option<Pending>,
+}
+
+impl<B> http_body::Body for AccessLogBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+
+        if let Some(pending) = this.pending {
+            match &frame {
+                Some(Ok(frame)) => {
+                    if let Some(data) = frame.data_ref() {
+                        pending.sent.count(data);
+                    } else if let Some(trailers) = frame.trailers_ref() {
+                        pending.update_code(trailers);
+                    }
+                }
+                Some(Err(_)) => pending.code = Some(Code::Unknown),
+                None => {
+                    pending.code.get_or_insert(Code::Unknown);
+                    this.pending.take();
+                }
+            }
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use std::sync::Mutex;
+
+    fn records(layer: AccessLogLayer) -> (AccessLogLayer, Arc<Mutex<Vec<AccessLogRecord>>>) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = records.clone();
+        let layer = layer.sink(move |record| sink.lock().unwrap().push(record.clone()));
+        (layer, records)
+    }
+
+    fn echo(
+        code: Code,
+    ) -> impl Service<
+        Request<Body>,
+        Response = Response<Body>,
+        Error = crate::BoxError,
+        Future = impl Future<Output = Result<Response<Body>, crate::BoxError>>,
+    > + Clone {
+        tower::service_fn(move |req: Request<Body>| async move {
+            let message = req.into_body().collect().await?.to_bytes();
+            let mut trailers = HeaderMap::new();
+            trailers.insert(Status::GRPC_STATUS, (code as i32).into());
+            let body = http_body_util::StreamBody::new(tokio_stream::iter([
+                Ok::<_, crate::BoxError>(Frame::data(message)),
+                Ok(Frame::trailers(trailers)),
+            ]));
+            Ok(Response::new(Body::new(body)))
+        })
+    }
+
+    fn messages(messages: &[&[u8]]) -> Body {
+        let mut buf = Vec::new();
+        for message in messages {
+            buf.push(0);
+            buf.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            buf.extend_from_slice(message);
+        }
+        Body::new(http_body_util::Full::new(Bytes::from(buf)))
+    }
+
+    #[tokio::test]
+    async fn logs_record_once_response_is_sent() {
+        let (layer, records) = records(AccessLogLayer::new());
+        let mut svc = layer.layer(echo(Code::Ok));
+
+        let req = Request::builder()
+            .uri("/test.Test/UnaryCall")
+            .header(header::USER_AGENT, "test")
+            .body(messages(&[b"hello", b"", b"world"]))
+            .unwrap();
+        let response = svc.call(req).await.unwrap();
+        assert!(records.lock().unwrap().is_empty());
+        response.into_body().collect().await.unwrap();
+
+        let records = records.lock().unwrap();
+        let [record] = &records[..] else {
+            panic!("expected a single record, got {records:?}");
+        };
+        assert_eq!(record.method(), "/test.Test/UnaryCall");
+        assert_eq!(record.user_agent(), Some("test"));
+        assert_eq!(record.peer(), None);
+        assert_eq!(record.code(), Code::Ok);
+        assert_eq!(record.request_bytes(), 25);
+        assert_eq!(record.request_messages(), 3);
+        assert_eq!(record.response_bytes(), 25);
+        assert_eq!(record.response_messages(), 3);
+    }
+
+    #[tokio::test]
+    async fn logs_trailers_only_responses() {
+        let (layer, records) = records(AccessLogLayer::new());
+        let mut svc = layer.layer(tower::service_fn(|_: Request<Body>| async {
+            Ok::<_, crate::BoxError>(Status::not_found("").into_http::<Body>())
+        }));
+
+        svc.call(Request::new(Body::empty())).await.unwrap();
+
+        assert_eq!(records.lock().unwrap()[0].code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn logs_cancelled_requests() {
+        let (layer, records) = records(AccessLogLayer::new());
+        let mut svc = layer.layer(echo(Code::Ok));
+
+        let response = svc.call(Request::new(messages(&[b"hello"]))).await.unwrap();
+        drop(response);
+
+        assert_eq!(records.lock().unwrap()[0].code(), Code::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn samples_successful_requests() {
+        let (layer, records) = records(AccessLogLayer::new().sample(2));
+        let ok = layer.layer(echo(Code::Ok));
+        let failed = layer.layer(echo(Code::Internal));
+
+        for mut svc in [ok.clone(), ok.clone(), ok, failed] {
+            let response = svc.call(Request::new(Body::empty())).await.unwrap();
+            response.into_body().collect().await.unwrap();
+        }
+
+        let codes = records
+            .lock()
+            .unwrap()
+            .iter()
+            .map(AccessLogRecord::code)
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [Code::Ok, Code::Ok, Code::Internal]);
+    }
+
+    #[test]
+    fn counts_messages_split_across_frames() {
+        let mut counter = MessageCounter::default();
+        counter.count(&[0, 0, 0]);
+        counter.count(&[0, 2, b'h']);
+        assert_eq!(counter.messages, 1);
+        counter.count(&[b'i', 0, 0, 0, 0, 0]);
+        assert_eq!(counter.messages, 2);
+        assert_eq!(counter.bytes, 12);
+    }
+}
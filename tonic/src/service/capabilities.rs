@@ -0,0 +1,396 @@
+//! Middleware that exchanges the capabilities of the client and the server.
+//!
+//! See [`CapabilitiesLayer`] for more details.
+
+use http::{HeaderMap, HeaderValue, Request, Response};
+use pin_project::pin_project;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const CAPABILITIES_HEADER: &str = "tonic-capabilities";
+
+/// A set of optional features, each with an optional version.
+///
+/// Capabilities are sent as a comma separated list of `name` or `name=version` items, e.g.
+/// `zstd, fields=2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    features: BTreeMap<String, Option<u32>>,
+}
+
+impl Capabilities {
+    /// Create an empty set of capabilities.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the feature `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is empty or contains whitespace, `,` or `=`.
+    pub fn feature(self, name: impl Into<String>) -> Self {
+        self.insert(name.into(), None)
+    }
+
+    /// Adds the feature `name` at `version`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is empty or contains whitespace, `,` or `=`.
+    pub fn versioned(self, name: impl Into<String>, version: u32) -> Self {
+        self.insert(name.into(), Some(version))
+    }
+
+    fn insert(mut self, name: String, version: Option<u32>) -> Self {
+        assert!(is_valid_name(&name), "invalid capability name {name:?}");
+        self.features.insert(name, version);
+        self
+    }
+
+    /// Returns `true` if the feature `name` is part of the set.
+    pub fn supports(&self, name: &str) -> bool {
+        self.features.contains_key(name)
+    }
+
+    /// Returns the version of the feature `name`, if it is part of the set with a version.
+    pub fn version(&self, name: &str) -> Option<u32> {
+        self.features.get(name).copied().flatten()
+    }
+
+    /// Returns an iterator over the features and their versions.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<u32>)> {
+        self.features
+            .iter()
+            .map(|(name, version)| (name.as_str(), *version))
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// The features supported by both `self` and `peer`, each at the lower of both versions.
+    ///
+    /// A feature versioned on one side only is kept without a version.
+    pub fn negotiate(&self, peer: &PeerCapabilities) -> Capabilities {
+        let features = self
+            .features
+            .iter()
+            .filter_map(|(name, version)| {
+                let peer_version = peer.capabilities.features.get(name)?;
+                let version = match (version, peer_version) {
+                    (Some(a), Some(b)) => Some(*a.min(b)),
+                    _ => None,
+                };
+                Some((name.clone(), version))
+            })
+            .collect();
+
+        Capabilities { features }
+    }
+
+    fn to_header_value(&self) -> Option<HeaderValue> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let value = self
+            .iter()
+            .map(|(name, version)| match version {
+                Some(version) => format!("{name}={version}"),
+                None => name.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        HeaderValue::try_from(value).ok()
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut features = BTreeMap::new();
+        let mut found = false;
+
+        for value in headers.get_all(CAPABILITIES_HEADER) {
+            found = true;
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+
+            for item in value.split(',').map(str::trim) {
+                let (name, version) = match item.split_once('=') {
+                    Some((name, version)) => match version.trim().parse() {
+                        Ok(version) => (name.trim(), Some(version)),
+                        Err(_) => continue,
+                    },
+                    None => (item, None),
+                };
+
+                if is_valid_name(name) {
+                    features.insert(name.to_string(), version);
+                }
+            }
+        }
+
+        found.then_some(Capabilities { features })
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && b != b',' && b != b'=')
+}
+
+/// The capabilities advertised by the peer of a call.
+///
+/// It is added to the extensions of requests by [`CapabilitiesLayer::server`] and to the
+/// extensions of responses by [`CapabilitiesLayer::client`], and can be read with
+/// [`Request::peer_capabilities`](crate::Request::peer_capabilities) and
+/// [`Response::peer_capabilities`](crate::Response::peer_capabilities). It is missing when the
+/// peer did not advertise any, for example because it predates the layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerCapabilities {
+    capabilities: Capabilities,
+}
+
+impl PeerCapabilities {
+    /// Returns `true` if the peer supports the feature `name`.
+    pub fn supports(&self, name: &str) -> bool {
+        self.capabilities.supports(name)
+    }
+
+    /// Returns the version of the feature `name` the peer supports, if it has one.
+    pub fn version(&self, name: &str) -> Option<u32> {
+        self.capabilities.version(name)
+    }
+
+    /// The capabilities as advertised by the peer.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Reads the capabilities advertised in `headers`, if any.
+    ///
+    /// This is useful to inspect calls that did not go through a [`CapabilitiesLayer`].
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Capabilities::from_headers(headers).map(|capabilities| Self { capabilities })
+    }
+}
+
+/// A [`Layer`] that exchanges [`Capabilities`] between clients and servers, so that optional
+/// features can be enabled per peer.
+///
+/// The layer comes in two flavors:
+///
+/// - [`CapabilitiesLayer::client`] advertises the client capabilities in the
+///   `tonic-capabilities` header of each request, and adds the [`PeerCapabilities`] advertised
+///   by the server to the extensions of the response.
+/// - [`CapabilitiesLayer::server`] adds the [`PeerCapabilities`] advertised by the client to the
+///   extensions of the request, and advertises the server capabilities in the headers of the
+///   response.
+///
+/// Both sides can then use [`Capabilities::negotiate`] to find the features they have in
+/// common.
+#[derive(Debug, Clone)]
+pub struct CapabilitiesLayer {
+    capabilities: Option<HeaderValue>,
+    client: bool,
+}
+
+impl CapabilitiesLayer {
+    /// Create a client side layer advertising `capabilities`.
+    pub fn client(capabilities: Capabilities) -> Self {
+        Self {
+            capabilities: capabilities.to_header_value(),
+            client: true,
+        }
+    }
+
+    /// Create a server side layer advertising `capabilities`.
+    pub fn server(capabilities: Capabilities) -> Self {
+        Self {
+            capabilities: capabilities.to_header_value(),
+            client: false,
+        }
+    }
+}
+
+impl<S> Layer<S> for CapabilitiesLayer {
+    type Service = CapabilitiesService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CapabilitiesService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that exchanges capabilities.
+///
+/// See [`CapabilitiesLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct CapabilitiesService<S> {
+    inner: S,
+    layer: CapabilitiesLayer,
+}
+
+impl<S> CapabilitiesService<S> {
+    /// Create a new client side [`CapabilitiesService`].
+    ///
+    /// See [`CapabilitiesLayer::client`] for more details.
+    pub fn client(inner: S, capabilities: Capabilities) -> Self {
+        CapabilitiesLayer::client(capabilities).layer(inner)
+    }
+
+    /// Create a new server side [`CapabilitiesService`].
+    ///
+    /// See [`CapabilitiesLayer::server`] for more details.
+    pub fn server(inner: S, capabilities: Capabilities) -> Self {
+        CapabilitiesLayer::server(capabilities).layer(inner)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CapabilitiesService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let advertised = self.layer.capabilities.clone();
+
+        if self.layer.client {
+            if let Some(value) = &advertised {
+                req.headers_mut().insert(CAPABILITIES_HEADER, value.clone());
+            }
+        } else if let Some(peer) = PeerCapabilities::from_headers(req.headers()) {
+            req.extensions_mut().insert(peer);
+        }
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            advertised: advertised.filter(|_| !self.layer.client),
+        }
+    }
+}
+
+/// Response future for [`CapabilitiesService`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    // Set on the server side only.
+    advertised: Option<HeaderValue>,
+}
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.inner.poll(cx))?;
+
+        match this.advertised.take() {
+            Some(value) => {
+                res.headers_mut().insert(CAPABILITIES_HEADER, value);
+            }
+            None => {
+                if let Some(peer) = PeerCapabilities::from_headers(res.headers()) {
+                    res.extensions_mut().insert(peer);
+                }
+            }
+        }
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_headers() {
+        let capabilities = Capabilities::new().feature("zstd").versioned("fields", 2);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CAPABILITIES_HEADER, capabilities.to_header_value().unwrap());
+        assert_eq!(headers[CAPABILITIES_HEADER], "fields=2, zstd");
+
+        let peer = PeerCapabilities::from_headers(&headers).unwrap();
+        assert_eq!(peer.capabilities(), &capabilities);
+        assert!(peer.supports("zstd"));
+        assert_eq!(peer.version("fields"), Some(2));
+        assert!(!peer.supports("other"));
+    }
+
+    #[test]
+    fn ignores_malformed_items() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CAPABILITIES_HEADER,
+            HeaderValue::from_static("a, b=x, ,c=3"),
+        );
+        headers.append(CAPABILITIES_HEADER, HeaderValue::from_static("d"));
+
+        let peer = PeerCapabilities::from_headers(&headers).unwrap();
+        let features = peer.capabilities().iter().collect::<Vec<_>>();
+        assert_eq!(features, [("a", None), ("c", Some(3)), ("d", None)]);
+
+        assert!(PeerCapabilities::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn negotiates_common_features() {
+        let local = Capabilities::new()
+            .feature("zstd")
+            .versioned("fields", 3)
+            .versioned("paging", 1)
+            .feature("local-only");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CAPABILITIES_HEADER,
+            HeaderValue::from_static("zstd, fields=2, paging, peer-only"),
+        );
+        let peer = PeerCapabilities::from_headers(&headers).unwrap();
+
+        let common = local.negotiate(&peer);
+        let features = common.iter().collect::<Vec<_>>();
+        assert_eq!(
+            features,
+            [("fields", Some(2)), ("paging", None), ("zstd", None)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid capability name")]
+    fn rejects_invalid_names() {
+        let _ = Capabilities::new().feature("a=b");
+    }
+}
@@ -0,0 +1,296 @@
+//! Middleware that propagates the deadline of incoming requests to outgoing calls.
+//!
+//! See [`DeadlinePropagationLayer`] for more details.
+
+use crate::{
+    request::Deadline,
+    service::grpc_timeout::try_parse_grpc_timeout,
+    time::{Clock, SharedClock},
+    Request, Status,
+};
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+thread_local! {
+    static CURRENT: RefCell<Option<PropagatedDeadline>> = const { RefCell::new(None) };
+}
+
+/// A deadline that outgoing calls inherit.
+#[derive(Debug, Clone)]
+struct PropagatedDeadline {
+    deadline: Instant,
+    clock: SharedClock,
+}
+
+impl PropagatedDeadline {
+    fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(self.clock.now())
+    }
+}
+
+/// A [`Layer`] that propagates the deadline of each incoming request to the calls made while
+/// handling it.
+///
+/// While the service handles a request with a [deadline](crate::Request::deadline), that deadline
+/// minus the [`margin`](Self::margin) is made current. Every call a tonic client makes in the
+/// meantime, from the same task, then advertises at most the remaining time in its
+/// `grpc-timeout`, and fails right away with `DEADLINE_EXCEEDED` once there is none left. This
+/// stops the work of a request whose caller has already given up.
+///
+/// The deadline is only current while the future of the service is polled. Tasks spawned by the
+/// handler do not inherit it unless their future is wrapped with [`with_current_deadline`].
+///
+/// The deadline of a request is set by the server side [`GrpcTimeoutLayer`], which the
+/// `transport` server applies outside of the layers added with `Server::layer`:
+///
+/// ```
+/// # use std::time::Duration;
+/// # use tonic::{service::DeadlinePropagationLayer, transport::Server};
+/// # let builder = Server::builder();
+/// builder.layer(DeadlinePropagationLayer::new().margin(Duration::from_millis(50)));
+/// ```
+///
+/// [`GrpcTimeoutLayer`]: super::GrpcTimeoutLayer
+#[derive(Debug, Clone, Default)]
+pub struct DeadlinePropagationLayer {
+    margin: Duration,
+    clock: SharedClock,
+}
+
+impl DeadlinePropagationLayer {
+    /// Create a new layer propagating deadlines without a margin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shortens the propagated deadline by `margin`, leaving the handler time to answer after
+    /// the calls it makes time out. Defaults to zero.
+    pub fn margin(self, margin: Duration) -> Self {
+        DeadlinePropagationLayer { margin, ..self }
+    }
+
+    /// Sets the [`Clock`] that measures the remaining time, which must be the one of the
+    /// server. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        DeadlinePropagationLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for DeadlinePropagationLayer {
+    type Service = DeadlinePropagation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlinePropagation {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that propagates the deadline of incoming requests to outgoing calls.
+///
+/// See [`DeadlinePropagationLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct DeadlinePropagation<S> {
+    inner: S,
+    layer: DeadlinePropagationLayer,
+}
+
+impl<S> DeadlinePropagation<S> {
+    /// Create a new [`DeadlinePropagation`] without a margin.
+    pub fn new(inner: S) -> Self {
+        DeadlinePropagationLayer::new().layer(inner)
+    }
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for DeadlinePropagation<S>
+where
+    S: Service<http::Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = WithDeadline<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        let deadline = req.extensions().get::<Deadline>().map(|deadline| {
+            let deadline = deadline.0;
+            PropagatedDeadline {
+                deadline: deadline.checked_sub(self.layer.margin).unwrap_or(deadline),
+                clock: self.layer.clock.clone(),
+            }
+        });
+
+        WithDeadline {
+            inner: self.inner.call(req),
+            deadline,
+        }
+    }
+}
+
+/// A future that makes a propagated deadline current while it is polled.
+///
+/// See [`DeadlinePropagationLayer`] for more details.
+#[pin_project]
+#[derive(Debug)]
+pub struct WithDeadline<F> {
+    #[pin]
+    inner: F,
+    deadline: Option<PropagatedDeadline>,
+}
+
+impl<F: Future> Future for WithDeadline<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let Some(deadline) = this.deadline else {
+            return this.inner.poll(cx);
+        };
+
+        let previous = CURRENT.with(|current| current.replace(Some(deadline.clone())));
+        let _restore = Restore(previous);
+        this.inner.poll(cx)
+    }
+}
+
+/// Restores the previous deadline when dropped, even if polling panicked.
+struct Restore(Option<PropagatedDeadline>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Makes the current propagated deadline, if any, current while `future` is polled, e.g. in a
+/// task spawned by a handler.
+///
+/// ```
+/// # async fn handler() {
+/// use tonic::service::deadline_propagation::with_current_deadline;
+///
+/// tokio::spawn(with_current_deadline(async {
+///     // Calls made here inherit the deadline of the request.
+/// }));
+/// # }
+/// ```
+pub fn with_current_deadline<F: Future>(future: F) -> WithDeadline<F> {
+    WithDeadline {
+        inner: future,
+        deadline: CURRENT.with(|current| current.borrow().clone()),
+    }
+}
+
+/// The time left before the current propagated deadline, if any.
+pub fn remaining() -> Option<Duration> {
+    CURRENT.with(|current| current.borrow().as_ref().map(PropagatedDeadline::remaining))
+}
+
+/// Caps the `grpc-timeout` of an outgoing request at the time left before the current
+/// propagated deadline, failing if there is none left.
+pub(crate) fn apply<T>(request: &mut Request<T>) -> Result<(), Status> {
+    let Some(remaining) = remaining() else {
+        return Ok(());
+    };
+
+    if remaining.is_zero() {
+        return Err(Status::deadline_exceeded(
+            "propagated deadline exceeded before the call was made",
+        ));
+    }
+
+    let timeout = try_parse_grpc_timeout(request.metadata().as_ref())
+        .ok()
+        .flatten();
+    if !timeout.is_some_and(|timeout| timeout <= remaining) {
+        request.set_timeout(remaining);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn deadline_is_current_while_polled() {
+        let mut svc = DeadlinePropagationLayer::new()
+            .margin(Duration::from_secs(1))
+            .layer(tower::service_fn(|_: http::Request<()>| async {
+                Ok::<_, crate::BoxError>(remaining())
+            }));
+
+        let mut req = http::Request::new(());
+        let deadline = Instant::now() + Duration::from_secs(10);
+        req.extensions_mut().insert(Deadline(deadline));
+
+        poll_fn(|cx| svc.poll_ready(cx)).await.unwrap();
+        let remaining = svc.call(req).await.unwrap().unwrap();
+        assert!(remaining <= Duration::from_secs(9));
+        assert!(remaining > Duration::from_secs(8));
+
+        assert_eq!(super::remaining(), None);
+        let req = http::Request::new(());
+        assert_eq!(svc.call(req).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn outgoing_requests_are_capped() {
+        let deadline = PropagatedDeadline {
+            deadline: Instant::now() + Duration::from_secs(5),
+            clock: SharedClock::default(),
+        };
+        let future = async {
+            let mut short = Request::new(());
+            short.set_timeout(Duration::from_secs(1));
+            apply(&mut short).unwrap();
+
+            let mut long = Request::new(());
+            long.set_timeout(Duration::from_secs(60));
+            apply(&mut long).unwrap();
+
+            (short, long)
+        };
+
+        let (short, long) = WithDeadline {
+            inner: future,
+            deadline: Some(deadline.clone()),
+        }
+        .await;
+        let timeout = |req: &Request<()>| {
+            try_parse_grpc_timeout(req.metadata().as_ref())
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(timeout(&short), Duration::from_secs(1));
+        assert!(timeout(&long) <= Duration::from_secs(5));
+
+        let expired = PropagatedDeadline {
+            deadline: Instant::now(),
+            ..deadline
+        };
+        let status = WithDeadline {
+            inner: async { apply(&mut Request::new(())) },
+            deadline: Some(expired),
+        }
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), crate::Code::DeadlineExceeded);
+    }
+}
@@ -0,0 +1,158 @@
+//! Services that can be added to and removed from a running server.
+
+use super::router::BoxRoute;
+use crate::{body::Body, server::NamedService, Status};
+use axum::response::IntoResponse;
+use http::{Request, Response};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+use tower::{util::Oneshot, Service, ServiceExt};
+
+/// A set of services that can change while it is being served.
+///
+/// `DynamicRoutes` is a cheap handle: its clones share the same services, so one clone can be
+/// served, e.g. with [`Routes::add_dynamic_routes`](super::Routes::add_dynamic_routes), while
+/// another one adds and removes services, e.g. as plugins are loaded and unloaded.
+///
+/// A request is routed to the service registered under the name in its path when it is
+/// received, and answered with `Unimplemented` if there is none, or by the
+/// [fallback service](super::Routes::fallback_service) of the routes it is added to. Requests already routed to a
+/// removed service run to completion.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let dynamic = DynamicRoutes::new();
+/// let server = Server::builder()
+///     .add_routes(Routes::default().add_dynamic_routes(dynamic.clone()))
+///     .serve(addr);
+///
+/// // Later, while the server is running:
+/// dynamic.add_service(GreeterServer::new(MyGreeter::default()));
+/// dynamic.remove_service("helloworld.Greeter");
+/// ```
+#[derive(Clone, Default)]
+pub struct DynamicRoutes {
+    services: Arc<RwLock<HashMap<&'static str, BoxRoute>>>,
+    fallback: Option<BoxRoute>,
+}
+
+impl DynamicRoutes {
+    /// Create a new `DynamicRoutes` without any service.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a service, replacing the service previously added with the same name.
+    ///
+    /// Returns `true` if a service was replaced.
+    pub fn add_service<S>(&self, svc: S) -> bool
+    where
+        S: Service<Request<Body>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Response: IntoResponse,
+        S::Future: Send + 'static,
+    {
+        let svc = svc.map_response(|res| res.into_response().map(Body::new));
+        self.services
+            .write()
+            .unwrap()
+            .insert(S::NAME, BoxRoute::new(svc))
+            .is_some()
+    }
+
+    /// Remove the service with the fully qualified `name`, e.g. `helloworld.Greeter`.
+    ///
+    /// Returns `true` if a service was removed.
+    pub fn remove_service(&self, name: &str) -> bool {
+        self.services.write().unwrap().remove(name).is_some()
+    }
+
+    /// Returns `true` if a service with the fully qualified `name` is added.
+    pub fn contains_service(&self, name: &str) -> bool {
+        self.services.read().unwrap().contains_key(name)
+    }
+
+    /// Get the names of the services currently added.
+    pub fn service_names(&self) -> Vec<&'static str> {
+        self.services.read().unwrap().keys().copied().collect()
+    }
+
+    pub(crate) fn with_fallback(self, fallback: Option<BoxRoute>) -> Self {
+        Self { fallback, ..self }
+    }
+
+    fn route(&self, path: &str) -> Option<BoxRoute> {
+        let (name, _method) = path.strip_prefix('/')?.split_once('/')?;
+        self.services.read().unwrap().get(name).cloned()
+    }
+}
+
+impl fmt::Debug for DynamicRoutes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicRoutes")
+            .field("services", &self.service_names())
+            .finish()
+    }
+}
+
+impl<B> Service<Request<B>> for DynamicRoutes
+where
+    B: http_body::Body<Data = bytes::Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = DynamicRoutesFuture;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The service is only known once the request is, so it is driven to readiness by the
+        // response future.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        match self
+            .route(req.uri().path())
+            .or_else(|| self.fallback.clone())
+        {
+            Some(svc) => DynamicRoutesFuture(Some(svc.oneshot(req.map(Body::new)))),
+            None => DynamicRoutesFuture(None),
+        }
+    }
+}
+
+/// Response future for [`DynamicRoutes`].
+pub struct DynamicRoutesFuture(Option<Oneshot<BoxRoute, Request<Body>>>);
+
+impl fmt::Debug for DynamicRoutesFuture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DynamicRoutesFuture").finish()
+    }
+}
+
+impl Future for DynamicRoutesFuture {
+    type Output = Result<Response<Body>, Infallible>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.0 {
+            Some(future) => Pin::new(future).poll(cx),
+            None => {
+                let (parts, ()) = Status::unimplemented("").into_http::<()>().into_parts();
+                Poll::Ready(Ok(Response::from_parts(parts, Body::empty())))
+            }
+        }
+    }
+}
@@ -0,0 +1,612 @@
+//! Middleware that encrypts the payload of each message.
+//!
+//! See [`MessageEncryptionLayer`] for more details.
+
+use crate::{body::Body, codec::HEADER_SIZE, Status};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{HeaderMap, HeaderValue, Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const ENCRYPTION_HEADER: &str = "grpc-message-encryption";
+const ENCRYPTION_KEY_HEADER: &str = "grpc-message-encryption-key";
+
+/// An authenticated cipher used to encrypt messages.
+///
+/// The implementation is in charge of the nonce, which usually means generating a random one for
+/// each message and prepending it to the ciphertext.
+pub trait Aead: Send + Sync + 'static {
+    /// The name of the algorithm, advertised in the `grpc-message-encryption` header.
+    fn algorithm(&self) -> &str;
+
+    /// Encrypts `message` with `key`.
+    fn seal(
+        &self,
+        key: &EncryptionKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// Decrypts and authenticates `message` with `key`.
+    fn open(
+        &self,
+        key: &EncryptionKey,
+        message: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+}
+
+/// Provides the keys messages are encrypted with.
+///
+/// Keys are identified by their [`id`](EncryptionKey::id), which is sent along with each call so
+/// that the peer can rotate keys without coordination.
+pub trait KeyProvider: Send + Sync + 'static {
+    /// The key to encrypt new calls with.
+    fn current_key(&self) -> Result<EncryptionKey, Status>;
+
+    /// The key identified by `id`, to decrypt a call encrypted by the peer.
+    fn key(&self, id: &str) -> Result<EncryptionKey, Status>;
+}
+
+/// A key used by an [`Aead`].
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    secret: Bytes,
+}
+
+impl EncryptionKey {
+    /// Create a new key named `id`.
+    pub fn new(id: impl Into<String>, secret: impl Into<Bytes>) -> Self {
+        Self {
+            id: id.into(),
+            secret: secret.into(),
+        }
+    }
+
+    /// The identifier of the key, sent to the peer in the `grpc-message-encryption-key` header.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The secret material of the key.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`Layer`] that encrypts the payload of every message end to end, on top of the transport.
+///
+/// This is meant for deployments where TLS is terminated by proxies that must not see the
+/// messages. Each message is encrypted once encoded and compressed, so its compression flag
+/// stays readable, and is decrypted before being decompressed and decoded.
+///
+/// The layer comes in two flavors:
+///
+/// - [`MessageEncryptionLayer::client`] encrypts requests with the current key of its
+///   [`KeyProvider`], advertising the algorithm and the key in the `grpc-message-encryption` and
+///   `grpc-message-encryption-key` headers, and decrypts responses that advertise the same
+///   algorithm.
+/// - [`MessageEncryptionLayer::server`] decrypts requests that advertise its algorithm, and
+///   encrypts their responses with its current key.
+///
+/// Peers without the layer exchange messages in the clear, unless the layer is
+/// [`required`](Self::required).
+#[derive(Clone)]
+pub struct MessageEncryptionLayer {
+    aead: Arc<dyn Aead>,
+    keys: Arc<dyn KeyProvider>,
+    role: Role,
+    required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Client,
+    Server,
+}
+
+impl MessageEncryptionLayer {
+    /// Create a client side layer.
+    pub fn client(aead: impl Aead, keys: impl KeyProvider) -> Self {
+        Self::new(aead, keys, Role::Client)
+    }
+
+    /// Create a server side layer.
+    pub fn server(aead: impl Aead, keys: impl KeyProvider) -> Self {
+        Self::new(aead, keys, Role::Server)
+    }
+
+    fn new(aead: impl Aead, keys: impl KeyProvider, role: Role) -> Self {
+        Self {
+            aead: Arc::new(aead),
+            keys: Arc::new(keys),
+            role,
+            required: false,
+        }
+    }
+
+    /// Refuse to exchange messages in the clear.
+    ///
+    /// Servers reject calls that are not encrypted with `FailedPrecondition`, and clients fail
+    /// on response messages that are not encrypted. Defaults to `false`.
+    pub fn required(self, required: bool) -> Self {
+        MessageEncryptionLayer { required, ..self }
+    }
+
+    /// The key the request messages are encrypted with, as advertised by the client, or a status
+    /// to reject the request with.
+    fn request_key(&self, headers: &HeaderMap) -> Result<Option<EncryptionKey>, Status> {
+        let Some(algorithm) = headers.get(ENCRYPTION_HEADER) else {
+            return if self.required {
+                Err(Status::failed_precondition(
+                    "message encryption is required",
+                ))
+            } else {
+                Ok(None)
+            };
+        };
+
+        if algorithm.as_bytes() != self.aead.algorithm().as_bytes() {
+            return Err(Status::unimplemented(format!(
+                "message encryption {algorithm:?} is not supported"
+            )));
+        }
+
+        let id = headers
+            .get(ENCRYPTION_KEY_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .ok_or_else(|| Status::invalid_argument("missing message encryption key"))?;
+
+        self.keys.key(id).map(Some)
+    }
+
+    /// Advertises the algorithm and `key` in `headers`.
+    fn advertise(&self, headers: &mut HeaderMap, key: &EncryptionKey) -> Result<(), Status> {
+        let algorithm = HeaderValue::from_str(self.aead.algorithm())
+            .map_err(|_| Status::internal("invalid message encryption algorithm"))?;
+        let id = HeaderValue::from_str(key.id())
+            .map_err(|_| Status::internal("invalid message encryption key id"))?;
+
+        headers.insert(ENCRYPTION_HEADER, algorithm);
+        headers.insert(ENCRYPTION_KEY_HEADER, id);
+        Ok(())
+    }
+
+    /// The key the response messages are encrypted with, as advertised by the server.
+    fn response_key(&self, headers: &HeaderMap) -> Result<Option<EncryptionKey>, Status> {
+        if headers.get(ENCRYPTION_HEADER).is_none() {
+            return Ok(None);
+        }
+
+        self.request_key(headers)
+    }
+}
+
+impl fmt::Debug for MessageEncryptionLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageEncryptionLayer")
+            .field("algorithm", &self.aead.algorithm())
+            .field("role", &self.role)
+            .field("required", &self.required)
+            .finish()
+    }
+}
+
+impl<S> Layer<S> for MessageEncryptionLayer {
+    type Service = MessageEncryption<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MessageEncryption {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that encrypts the payload of each message.
+///
+/// See [`MessageEncryptionLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct MessageEncryption<S> {
+    inner: S,
+    layer: MessageEncryptionLayer,
+}
+
+impl<S> MessageEncryption<S> {
+    /// Create a new client side [`MessageEncryption`].
+    ///
+    /// See [`MessageEncryptionLayer::client`] for more details.
+    pub fn client(inner: S, aead: impl Aead, keys: impl KeyProvider) -> Self {
+        MessageEncryptionLayer::client(aead, keys).layer(inner)
+    }
+
+    /// Create a new server side [`MessageEncryption`].
+    ///
+    /// See [`MessageEncryptionLayer::server`] for more details.
+    pub fn server(inner: S, aead: impl Aead, keys: impl KeyProvider) -> Self {
+        MessageEncryptionLayer::server(aead, keys).layer(inner)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for MessageEncryption<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    S::Error: Into<crate::BoxError>,
+    ReqBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ReqBody::Error: Into<crate::BoxError>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let (mut parts, body) = req.into_parts();
+        let aead = self.layer.aead.clone();
+
+        let (body, response_key) = match self.layer.role {
+            Role::Client => {
+                let key = match self.layer.keys.current_key() {
+                    Ok(key) => key,
+                    Err(status) => return ResponseFuture::failed(status, Role::Client),
+                };
+                if let Err(status) = self.layer.advertise(&mut parts.headers, &key) {
+                    return ResponseFuture::failed(status, Role::Client);
+                }
+
+                (MessageBody::new(body, aead, Mode::Seal(key)), None)
+            }
+            Role::Server => match self.layer.request_key(&parts.headers) {
+                Ok(Some(key)) => match self.layer.keys.current_key() {
+                    Ok(current) => (MessageBody::new(body, aead, Mode::Open(key)), Some(current)),
+                    Err(status) => return ResponseFuture::failed(status, Role::Server),
+                },
+                Ok(None) => (MessageBody::new(body, aead, Mode::Clear), None),
+                Err(status) => return ResponseFuture::failed(status, Role::Server),
+            },
+        };
+
+        ResponseFuture {
+            kind: Kind::Inner {
+                future: self.inner.call(Request::from_parts(parts, Body::new(body))),
+                layer: self.layer.clone(),
+                response_key,
+            },
+        }
+    }
+}
+
+/// Response future for [`MessageEncryption`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    kind: Kind<F>,
+}
+
+#[pin_project(project = KindProj)]
+enum Kind<F> {
+    Inner {
+        #[pin]
+        future: F,
+        layer: MessageEncryptionLayer,
+        response_key: Option<EncryptionKey>,
+    },
+    Failed {
+        status: Option<Status>,
+        role: Role,
+    },
+}
+
+impl<F> ResponseFuture<F> {
+    fn failed(status: Status, role: Role) -> Self {
+        Self {
+            kind: Kind::Failed {
+                status: Some(status),
+                role,
+            },
+        }
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    E: Into<crate::BoxError>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::Inner {
+                future,
+                layer,
+                response_key,
+            } => {
+                let res = ready!(future.poll(cx)).map_err(Into::into)?;
+                let (mut parts, body) = res.into_parts();
+                let mode = match layer.role {
+                    Role::Server => match response_key.take() {
+                        Some(key) => match layer.advertise(&mut parts.headers, &key) {
+                            Ok(()) => Mode::Seal(key),
+                            Err(status) => return Poll::Ready(Ok(status.into_http())),
+                        },
+                        None => Mode::Clear,
+                    },
+                    Role::Client => match layer.response_key(&parts.headers) {
+                        Ok(Some(key)) => Mode::Open(key),
+                        Ok(None) if layer.required => Mode::Reject,
+                        Ok(None) => Mode::Clear,
+                        Err(status) => Mode::Failed(Some(status)),
+                    },
+                };
+
+                let body = MessageBody::new(body, layer.aead.clone(), mode);
+                Poll::Ready(Ok(Response::from_parts(parts, Body::new(body))))
+            }
+            KindProj::Failed { status, role } => {
+                let status = status.take().expect("polled after completion");
+                Poll::Ready(match role {
+                    Role::Client => Err(status.into()),
+                    Role::Server => Ok(status.into_http()),
+                })
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+enum Mode {
+    Clear,
+    Seal(EncryptionKey),
+    Open(EncryptionKey),
+    Reject,
+    Failed(Option<Status>),
+}
+
+/// A body whose messages are encrypted or decrypted one at a time.
+#[pin_project]
+struct MessageBody<B> {
+    #[pin]
+    inner: B,
+    aead: Arc<dyn Aead>,
+    mode: Mode,
+    buf: BytesMut,
+}
+
+impl<B> MessageBody<B> {
+    fn new(inner: B, aead: Arc<dyn Aead>, mode: Mode) -> Self {
+        Self {
+            inner,
+            aead,
+            mode,
+            buf: BytesMut::new(),
+        }
+    }
+}
+
+impl<B> http_body::Body for MessageBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<crate::BoxError>,
+{
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+
+        if let Mode::Failed(status) = this.mode {
+            return Poll::Ready(status.take().map(Err));
+        }
+
+        if let Mode::Clear = this.mode {
+            return this
+                .inner
+                .poll_frame(cx)
+                .map_err(|err| Status::from_error(err.into()));
+        }
+
+        loop {
+            if let Some(message) = next_message(this.buf) {
+                return Poll::Ready(Some(
+                    transform(&**this.aead, this.mode, message).map(Frame::data),
+                ));
+            }
+
+            match ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => this.buf.put(data),
+                    Err(frame) if this.buf.is_empty() => return Poll::Ready(Some(Ok(frame))),
+                    Err(_) => return Poll::Ready(Some(Err(incomplete()))),
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(Status::from_error(err.into())))),
+                None if this.buf.is_empty() => return Poll::Ready(None),
+                None => return Poll::Ready(Some(Err(incomplete()))),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.mode {
+            Mode::Failed(status) => status.is_none(),
+            _ => self.buf.is_empty() && self.inner.is_end_stream(),
+        }
+    }
+}
+
+fn incomplete() -> Status {
+    Status::internal("stream ended in the middle of a message")
+}
+
+/// Splits the first complete message, header included, off `buf`.
+fn next_message(buf: &mut BytesMut) -> Option<BytesMut> {
+    if buf.len() < HEADER_SIZE {
+        return None;
+    }
+
+    let len = (&buf[1..HEADER_SIZE]).get_u32() as usize;
+    if buf.len() - HEADER_SIZE < len {
+        return None;
+    }
+
+    Some(buf.split_to(HEADER_SIZE + len))
+}
+
+fn transform(aead: &dyn Aead, mode: &Mode, message: BytesMut) -> Result<Bytes, Status> {
+    let payload = &message[HEADER_SIZE..];
+    let payload = match mode {
+        Mode::Seal(key) => aead
+            .seal(key, payload)
+            .map_err(|err| Status::internal(format!("failed to encrypt message: {err}")))?,
+        Mode::Open(key) => aead
+            .open(key, payload)
+            .map_err(|err| Status::internal(format!("failed to decrypt message: {err}")))?,
+        Mode::Reject => {
+            return Err(Status::failed_precondition(
+                "received a message that is not encrypted",
+            ))
+        }
+        Mode::Clear | Mode::Failed(_) => unreachable!("messages are only split when transformed"),
+    };
+
+    let len = u32::try_from(payload.len())
+        .map_err(|_| Status::resource_exhausted("encrypted message is too large"))?;
+
+    let mut buf = BytesMut::with_capacity(HEADER_SIZE + payload.len());
+    // Keep the compression flag.
+    buf.put_u8(message[0]);
+    buf.put_u32(len);
+    buf.put_slice(&payload);
+    Ok(buf.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    /// Not a cipher, but enough to tell encrypted messages apart.
+    struct Xor;
+
+    impl Aead for Xor {
+        fn algorithm(&self) -> &str {
+            "xor"
+        }
+
+        fn seal(&self, key: &EncryptionKey, message: &[u8]) -> Result<Vec<u8>, crate::BoxError> {
+            let mut sealed = vec![key.secret()[0]];
+            sealed.extend(message.iter().map(|b| b ^ key.secret()[0]));
+            Ok(sealed)
+        }
+
+        fn open(&self, key: &EncryptionKey, message: &[u8]) -> Result<Vec<u8>, crate::BoxError> {
+            match message.split_first() {
+                Some((tag, rest)) if *tag == key.secret()[0] => {
+                    Ok(rest.iter().map(|b| b ^ key.secret()[0]).collect())
+                }
+                _ => Err("authentication failed".into()),
+            }
+        }
+    }
+
+    fn frame(flag: u8, payload: &[u8]) -> Vec<u8> {
+        let mut buf = vec![flag];
+        buf.extend((payload.len() as u32).to_be_bytes());
+        buf.extend(payload);
+        buf
+    }
+
+    async fn run(mode: Mode, chunks: Vec<Vec<u8>>) -> Result<Vec<u8>, Status> {
+        let stream = tokio_stream::iter(
+            chunks
+                .into_iter()
+                .map(|chunk| Ok::<_, Status>(Frame::data(Bytes::from(chunk)))),
+        );
+        let body = MessageBody::new(http_body_util::StreamBody::new(stream), Arc::new(Xor), mode);
+
+        Ok(body.collect().await?.to_bytes().to_vec())
+    }
+
+    #[tokio::test]
+    async fn seals_and_opens_messages_split_across_frames() {
+        let key = EncryptionKey::new("k1", vec![7]);
+
+        let mut input = frame(0, b"hello");
+        input.extend(frame(1, b"world!"));
+        let chunks = input.chunks(3).map(<[u8]>::to_vec).collect();
+
+        let sealed = run(Mode::Seal(key.clone()), chunks).await.unwrap();
+        let mut expected = frame(0, &Xor.seal(&key, b"hello").unwrap());
+        expected.extend(frame(1, &Xor.seal(&key, b"world!").unwrap()));
+        assert_eq!(sealed, expected);
+
+        let opened = run(Mode::Open(key), vec![sealed]).await.unwrap();
+        assert_eq!(opened, input);
+    }
+
+    #[tokio::test]
+    async fn fails_on_wrong_key() {
+        let sealed = run(
+            Mode::Seal(EncryptionKey::new("k1", vec![7])),
+            vec![frame(0, b"hello")],
+        )
+        .await
+        .unwrap();
+
+        let err = run(Mode::Open(EncryptionKey::new("k2", vec![8])), vec![sealed])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), crate::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn rejects_clear_messages_when_required() {
+        let err = run(Mode::Reject, vec![frame(0, b"hello")])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), crate::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn fails_on_truncated_message() {
+        let mut input = frame(0, b"hello");
+        input.truncate(7);
+
+        let err = run(Mode::Seal(EncryptionKey::new("k1", vec![7])), vec![input])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), crate::Code::Internal);
+    }
+}
@@ -0,0 +1,341 @@
+//! Middleware that interleaves the writes of concurrent streams.
+//!
+//! See [`FairWriteLayer`] for more details.
+
+use bytes::{Buf, Bytes};
+use http::{Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A [`Layer`] that splits the messages written by each stream into slices of at most a quantum
+/// of bytes, so that a stream with a large backlog does not delay the small messages of the other
+/// streams of the connection.
+///
+/// HTTP/2 sends the buffered data of the streams of a connection in turns, but a stream that hands
+/// over a large message at once claims the connection flow control window for all of it, and the
+/// messages of other streams wait until it is sent. With this layer each stream hands over one
+/// quantum at a time and yields to the other streams in between, so their DATA frames are
+/// interleaved. Smaller quanta are fairer, larger ones have less overhead.
+///
+/// The layer comes in two flavors:
+///
+/// - [`FairWriteLayer::client`] splits the request bodies, and adds the [`StreamBacklog`] of the
+///   request to the extensions of the response.
+/// - [`FairWriteLayer::server`] splits the response bodies, and adds the [`StreamBacklog`] of the
+///   response to the extensions of the request.
+///
+/// The transport can apply it with
+/// [`Server::fair_write_quantum`](crate::transport::Server::fair_write_quantum) and
+/// [`Endpoint::fair_write_quantum`](crate::transport::Endpoint::fair_write_quantum).
+#[derive(Debug, Clone)]
+pub struct FairWriteLayer {
+    quantum: usize,
+    client: bool,
+}
+
+impl FairWriteLayer {
+    /// Create a client side layer that splits request bodies into slices of `quantum` bytes.
+    pub fn client(quantum: usize) -> Self {
+        Self {
+            quantum: quantum.max(1),
+            client: true,
+        }
+    }
+
+    /// Create a server side layer that splits response bodies into slices of `quantum` bytes.
+    pub fn server(quantum: usize) -> Self {
+        Self {
+            quantum: quantum.max(1),
+            client: false,
+        }
+    }
+}
+
+impl<S> Layer<S> for FairWriteLayer {
+    type Service = FairWrite<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FairWrite {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that interleaves the writes of concurrent streams.
+///
+/// See [`FairWriteLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct FairWrite<S> {
+    inner: S,
+    layer: FairWriteLayer,
+}
+
+impl<S> FairWrite<S> {
+    /// Create a new client side [`FairWrite`].
+    ///
+    /// See [`FairWriteLayer::client`] for more details.
+    pub fn client(inner: S, quantum: usize) -> Self {
+        FairWriteLayer::client(quantum).layer(inner)
+    }
+
+    /// Create a new server side [`FairWrite`].
+    ///
+    /// See [`FairWriteLayer::server`] for more details.
+    pub fn server(inner: S, quantum: usize) -> Self {
+        FairWriteLayer::server(quantum).layer(inner)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for FairWrite<S>
+where
+    S: Service<Request<FairWriteBody<ReqBody>>, Response = Response<ResBody>>,
+{
+    type Response = Response<FairWriteBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let backlog = StreamBacklog::default();
+        let quantum = self.layer.quantum;
+
+        let req = if self.layer.client {
+            req.map(|body| FairWriteBody::new(body, quantum, backlog.clone()))
+        } else {
+            let mut req = req.map(FairWriteBody::passthrough);
+            req.extensions_mut().insert(backlog.clone());
+            req
+        };
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            backlog,
+            quantum,
+            client: self.layer.client,
+        }
+    }
+}
+
+/// Response future for [`FairWrite`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    backlog: StreamBacklog,
+    quantum: usize,
+    client: bool,
+}
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<FairWriteBody<B>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx))?;
+
+        let res = if *this.client {
+            let mut res = res.map(FairWriteBody::passthrough);
+            res.extensions_mut().insert(this.backlog.clone());
+            res
+        } else {
+            res.map(|body| FairWriteBody::new(body, *this.quantum, this.backlog.clone()))
+        };
+
+        Poll::Ready(Ok(res))
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+/// The write progress of the body of a single stream.
+///
+/// It is added to the extensions of the requests and responses by [`FairWriteLayer`].
+#[derive(Debug, Clone, Default)]
+pub struct StreamBacklog {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    pending: AtomicUsize,
+    written: AtomicU64,
+}
+
+impl StreamBacklog {
+    /// The number of bytes produced by the body that wait for their turn to be written.
+    pub fn pending_bytes(&self) -> usize {
+        self.inner.pending.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes of the body handed over to the connection so far.
+    pub fn written_bytes(&self) -> u64 {
+        self.inner.written.load(Ordering::Relaxed)
+    }
+
+    fn queued(&self, len: usize) {
+        self.inner.pending.fetch_add(len, Ordering::Relaxed);
+    }
+
+    fn written(&self, len: usize) {
+        self.inner.pending.fetch_sub(len, Ordering::Relaxed);
+        self.inner.written.fetch_add(len as u64, Ordering::Relaxed);
+    }
+}
+
+/// A body whose data is handed over in slices of at most a quantum of bytes.
+///
+/// See [`FairWriteLayer`] for more details.
+#[pin_project]
+pub struct FairWriteBody<B> {
+    #[pin]
+    inner: B,
+    // `None` for bodies that are passed through as is.
+    quantum: Option<usize>,
+    backlog: StreamBacklog,
+    current: Bytes,
+    yield_next: bool,
+}
+
+impl<B> FairWriteBody<B> {
+    fn new(inner: B, quantum: usize, backlog: StreamBacklog) -> Self {
+        Self {
+            inner,
+            quantum: Some(quantum),
+            backlog,
+            current: Bytes::new(),
+            yield_next: false,
+        }
+    }
+
+    fn passthrough(inner: B) -> Self {
+        Self {
+            inner,
+            quantum: None,
+            backlog: StreamBacklog::default(),
+            current: Bytes::new(),
+            yield_next: false,
+        }
+    }
+}
+
+impl<B> Body for FairWriteBody<B>
+where
+    B: Body,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+
+        loop {
+            if let (Some(quantum), false) = (*this.quantum, this.current.is_empty()) {
+                // Give the other streams a turn between the slices of a chunk.
+                if *this.yield_next {
+                    *this.yield_next = false;
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                let slice = this.current.split_to(quantum.min(this.current.len()));
+                this.backlog.written(slice.len());
+                *this.yield_next = !this.current.is_empty();
+                return Poll::Ready(Some(Ok(Frame::data(slice))));
+            }
+
+            let frame = match ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => frame.map_data(|mut data| data.copy_to_bytes(data.remaining())),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            };
+
+            if this.quantum.is_none() {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+
+            match frame.into_data() {
+                Ok(data) => {
+                    this.backlog.queued(data.len());
+                    *this.current = data;
+                }
+                Err(frame) => return Poll::Ready(Some(Ok(frame))),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.current.is_empty() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let mut hint = self.inner.size_hint();
+        let pending = self.current.len() as u64;
+        hint.set_lower(hint.lower() + pending);
+        if let Some(upper) = hint.upper() {
+            hint.set_upper(upper + pending);
+        }
+        hint
+    }
+}
+
+impl<B> fmt::Debug for FairWriteBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FairWriteBody")
+            .field("quantum", &self.quantum)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+
+    #[tokio::test]
+    async fn splits_data_into_quanta() {
+        let chunks = [Bytes::from(vec![1; 10]), Bytes::from(vec![2; 3])];
+        let body = StreamBody::new(tokio_stream::iter(
+            chunks.map(|chunk| Ok::<_, std::convert::Infallible>(Frame::data(chunk))),
+        ));
+
+        let backlog = StreamBacklog::default();
+        let mut body = FairWriteBody::new(body, 4, backlog.clone());
+
+        let mut lens = Vec::new();
+        while let Some(frame) = body.frame().await {
+            let data = frame.unwrap().into_data().unwrap();
+            lens.push(data.len());
+            assert_eq!(backlog.written_bytes(), lens.iter().sum::<usize>() as u64);
+        }
+
+        assert_eq!(lens, [4, 4, 2, 3]);
+        assert_eq!(backlog.pending_bytes(), 0);
+        assert_eq!(backlog.written_bytes(), 13);
+    }
+}
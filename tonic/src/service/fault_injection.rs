@@ -0,0 +1,508 @@
+//! Middleware that injects faults into RPCs for chaos testing.
+//!
+//! See [`FaultInjectionLayer`] for more details.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock, Sleep},
+    Status,
+};
+use bytes::{Bytes, BytesMut};
+use http::{header::HeaderName, HeaderValue, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Size of the length-prefixed message header in the gRPC framing.
+const HEADER_SIZE: usize = 5;
+
+/// A [`Layer`] that injects [`Fault`]s into the RPCs it serves or sends, to exercise how
+/// clients and servers cope with a misbehaving peer without a fault injecting proxy.
+///
+/// Each fault applies to a percentage of the RPCs, optionally restricted to the requests carrying
+/// some metadata, so that a test or an operator can target individual calls. Every fault that
+/// applies to an RPC is injected:
+///
+/// - delays add up, and hold the response back once the inner service produced it;
+/// - the first status fault answers the RPC without calling the inner service;
+/// - the first message fault corrupts or truncates the response stream.
+///
+/// The layer can wrap a server with [`Server::layer`](crate::transport::Server::layer) or a
+/// channel, in which case the faults apply to the responses the client receives.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use tonic::{service::fault_injection::{Fault, FaultInjectionLayer}, Status};
+/// let layer = FaultInjectionLayer::new()
+///     .fault(Fault::random_delay(Duration::from_millis(10), Duration::from_millis(500)).percentage(5.0))
+///     .fault(Fault::status(Status::unavailable("injected fault")).percentage(1.0))
+///     .fault(Fault::truncate().when_metadata("x-fault", "truncate"));
+/// ```
+#[derive(Clone, Default)]
+pub struct FaultInjectionLayer {
+    faults: Arc<Vec<Fault>>,
+    clock: SharedClock,
+}
+
+impl FaultInjectionLayer {
+    /// Create a layer that injects no faults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injects `fault` in addition to the faults already configured.
+    pub fn fault(mut self, fault: Fault) -> Self {
+        Arc::make_mut(&mut self.faults).push(fault);
+        self
+    }
+
+    /// Sets the [`Clock`] used for delays. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        FaultInjectionLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl fmt::Debug for FaultInjectionLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultInjectionLayer")
+            .field("faults", &self.faults)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for FaultInjectionLayer {
+    type Service = FaultInjection<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FaultInjection {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A fault injected by a [`FaultInjectionLayer`].
+///
+/// Faults apply to every RPC unless restricted with [`percentage`](Self::percentage) or
+/// [`when_metadata`](Self::when_metadata).
+#[derive(Debug, Clone)]
+pub struct Fault {
+    kind: FaultKind,
+    percentage: f64,
+    metadata: Option<(HeaderName, HeaderValue)>,
+}
+
+#[derive(Debug, Clone)]
+enum FaultKind {
+    Delay(Duration),
+    RandomDelay(Duration, Duration),
+    Status(Status),
+    Corrupt,
+    Truncate,
+}
+
+impl Fault {
+    fn new(kind: FaultKind) -> Self {
+        Fault {
+            kind,
+            percentage: 100.0,
+            metadata: None,
+        }
+    }
+
+    /// Delays the response by `delay`.
+    pub fn delay(delay: Duration) -> Self {
+        Self::new(FaultKind::Delay(delay))
+    }
+
+    /// Delays the response by a random duration between `min` and `max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn random_delay(min: Duration, max: Duration) -> Self {
+        assert!(min <= max, "the minimum delay must not exceed the maximum");
+        Self::new(FaultKind::RandomDelay(min, max))
+    }
+
+    /// Fails the RPC with `status`, without calling the inner service.
+    pub fn status(status: Status) -> Self {
+        Self::new(FaultKind::Status(status))
+    }
+
+    /// Flips the bits of a random byte of the first response message.
+    ///
+    /// The framing of the response is preserved, so the corruption is only noticed if the
+    /// message cannot be decoded anymore.
+    pub fn corrupt() -> Self {
+        Self::new(FaultKind::Corrupt)
+    }
+
+    /// Ends the response stream in the middle of its first message, as if the connection was
+    /// cut.
+    pub fn truncate() -> Self {
+        Self::new(FaultKind::Truncate)
+    }
+
+    /// Injects the fault into `percentage` percent of the RPCs, chosen at random. Defaults to
+    /// `100`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percentage` is not between `0` and `100`.
+    pub fn percentage(self, percentage: f64) -> Self {
+        assert!(
+            (0.0..=100.0).contains(&percentage),
+            "the percentage must be between 0 and 100"
+        );
+        Fault { percentage, ..self }
+    }
+
+    /// Only injects the fault into the RPCs whose request has the metadata `key` set to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` or `value` is not valid ASCII metadata.
+    pub fn when_metadata(self, key: &str, value: &str) -> Self {
+        let key = HeaderName::from_bytes(key.as_bytes()).expect("invalid metadata key");
+        let value = HeaderValue::from_str(value).expect("invalid metadata value");
+        Fault {
+            metadata: Some((key, value)),
+            ..self
+        }
+    }
+
+    fn applies_to<B>(&self, req: &Request<B>) -> bool {
+        if let Some((key, value)) = &self.metadata {
+            if req.headers().get(key) != Some(value) {
+                return false;
+            }
+        }
+        self.percentage >= 100.0 || random() * 100.0 < self.percentage
+    }
+}
+
+/// Middleware that injects faults into RPCs.
+///
+/// See [`FaultInjectionLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct FaultInjection<S> {
+    inner: S,
+    layer: FaultInjectionLayer,
+}
+
+impl<S, ResBody> Service<Request<Body>> for FaultInjection<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let mut delay = Duration::ZERO;
+        let mut status = None;
+        let mut message = None;
+        for fault in self.layer.faults.iter() {
+            if !fault.applies_to(&req) {
+                continue;
+            }
+
+            match &fault.kind {
+                FaultKind::Delay(fixed) => delay += *fixed,
+                FaultKind::RandomDelay(min, max) => delay += *min + (*max - *min).mul_f64(random()),
+                FaultKind::Status(fault) => {
+                    status.get_or_insert_with(|| fault.clone());
+                }
+                FaultKind::Corrupt => {
+                    message.get_or_insert(MessageFault::Corrupt);
+                }
+                FaultKind::Truncate => {
+                    message.get_or_insert(MessageFault::Truncate);
+                }
+            }
+        }
+
+        let sleep = (delay > Duration::ZERO).then(|| (self.layer.clock.clone(), delay));
+        let state = match status {
+            Some(status) => State::Aborted(Some(status)),
+            None => State::Called(self.inner.call(req)),
+        };
+
+        ResponseFuture {
+            state,
+            response: None,
+            sleep,
+            sleeping: None,
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MessageFault {
+    Corrupt,
+    Truncate,
+}
+
+/// Response future for [`FaultInjection`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    state: State<F>,
+    response: Option<Response<Body>>,
+    sleep: Option<(SharedClock, Duration)>,
+    sleeping: Option<Sleep>,
+    message: Option<MessageFault>,
+}
+
+#[pin_project(project = StateProj)]
+enum State<F> {
+    Called(#[pin] F),
+    Aborted(Option<Status>),
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if this.response.is_none() {
+            let response = match this.state.as_mut().project() {
+                StateProj::Called(future) => {
+                    let response = ready!(future.poll(cx))?;
+                    match *this.message {
+                        Some(fault) => {
+                            response.map(|inner| Body::new(FaultyBody::new(inner, fault)))
+                        }
+                        None => response.map(Body::new),
+                    }
+                }
+                StateProj::Aborted(status) => {
+                    let status = status.take().expect("polled after completion");
+                    status.into_http()
+                }
+            };
+            *this.response = Some(response);
+        }
+
+        if let Some((clock, delay)) = this.sleep.take() {
+            *this.sleeping = Some(clock.sleep(delay));
+        }
+        if let Some(sleeping) = this.sleeping {
+            ready!(sleeping.as_mut().poll(cx));
+            *this.sleeping = None;
+        }
+
+        let response = this.response.take().expect("polled after completion");
+        Poll::Ready(Ok(response))
+    }
+}
+
+/// A response body whose first message is corrupted or truncated.
+#[pin_project]
+struct FaultyBody<B> {
+    #[pin]
+    inner: B,
+    fault: MessageFault,
+    /// The header of the first message, until it is complete.
+    header: Vec<u8>,
+    /// The offset in the body of the byte to corrupt, or at which to end the body.
+    target: Option<usize>,
+    /// The number of bytes of the body already passed on.
+    position: usize,
+    done: bool,
+}
+
+impl<B> FaultyBody<B> {
+    fn new(inner: B, fault: MessageFault) -> Self {
+        FaultyBody {
+            inner,
+            fault,
+            header: Vec::with_capacity(HEADER_SIZE),
+            target: None,
+            position: 0,
+            done: false,
+        }
+    }
+}
+
+impl<B> http_body::Body for FaultyBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        if *this.done {
+            return match this.fault {
+                MessageFault::Truncate => Poll::Ready(None),
+                MessageFault::Corrupt => this.inner.poll_frame(cx),
+            };
+        }
+
+        let frame = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            frame => return Poll::Ready(frame),
+        };
+        let data = match frame.into_data() {
+            Ok(data) => data,
+            Err(frame) => return Poll::Ready(Some(Ok(frame))),
+        };
+
+        let start = *this.position;
+        *this.position += data.len();
+
+        if this.target.is_none() {
+            let missing = HEADER_SIZE - this.header.len();
+            this.header
+                .extend_from_slice(&data[..missing.min(data.len())]);
+            if this.header.len() < HEADER_SIZE {
+                return Poll::Ready(Some(Ok(Frame::data(data))));
+            }
+
+            let len = u32::from_be_bytes(this.header[1..].try_into().unwrap()) as usize;
+            *this.target = Some(match this.fault {
+                MessageFault::Corrupt if len == 0 => {
+                    // There is no payload to corrupt.
+                    *this.done = true;
+                    return Poll::Ready(Some(Ok(Frame::data(data))));
+                }
+                MessageFault::Corrupt => HEADER_SIZE + (random() * len as f64) as usize % len,
+                // Cut the header of an empty message.
+                MessageFault::Truncate if len == 0 => HEADER_SIZE - 1,
+                MessageFault::Truncate => HEADER_SIZE + len / 2,
+            });
+        }
+
+        let target = this.target.unwrap();
+        if *this.position <= target {
+            return Poll::Ready(Some(Ok(Frame::data(data))));
+        }
+
+        *this.done = true;
+        let offset = target - start;
+        let data = match this.fault {
+            MessageFault::Corrupt => {
+                let mut data = BytesMut::from(&data[..]);
+                data[offset] ^= 0xff;
+                data.freeze()
+            }
+            MessageFault::Truncate => data.slice(..offset),
+        };
+        Poll::Ready(Some(Ok(Frame::data(data))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match self.fault {
+            MessageFault::Truncate if self.done => true,
+            _ => self.inner.is_end_stream(),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.fault {
+            MessageFault::Corrupt => self.inner.size_hint(),
+            MessageFault::Truncate => SizeHint::default(),
+        }
+    }
+}
+
+/// A random number in `[0, 1)`.
+fn random() -> f64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+
+    /// Collects `fault` applied to a body made of `chunks`.
+    async fn collect(chunks: Vec<&'static [u8]>, fault: MessageFault) -> Vec<u8> {
+        let body = StreamBody::new(tokio_stream::iter(chunks.into_iter().map(|chunk| {
+            Ok::<_, std::convert::Infallible>(Frame::data(Bytes::from_static(chunk)))
+        })));
+        let body = FaultyBody::new(body, fault);
+        body.collect().await.unwrap().to_bytes().to_vec()
+    }
+
+    #[tokio::test]
+    async fn truncates_first_message_across_frames() {
+        let chunks = vec![&[0, 0, 0][..], &[0, 4, 1, 2], &[3, 4, 0, 0, 0, 0, 0]];
+        let body = collect(chunks, MessageFault::Truncate).await;
+        assert_eq!(body, [0, 0, 0, 0, 4, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn truncates_header_of_empty_message() {
+        let body = collect(
+            vec![&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0]],
+            MessageFault::Truncate,
+        )
+        .await;
+        assert_eq!(body, [0, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn corrupts_one_payload_byte() {
+        let chunks = vec![&[0, 0][..], &[0, 0, 4, 1, 2], &[3, 4], &[0, 0, 0, 0, 1, 9]];
+        let body = collect(chunks, MessageFault::Corrupt).await;
+
+        assert_eq!(body.len(), 15);
+        assert_eq!(body[..5], [0, 0, 0, 0, 4]);
+        assert_eq!(body[9..], [0, 0, 0, 0, 1, 9]);
+        let changed = body[5..9]
+            .iter()
+            .zip([1, 2, 3, 4])
+            .filter(|(corrupted, original)| **corrupted != *original)
+            .count();
+        assert_eq!(changed, 1);
+    }
+}
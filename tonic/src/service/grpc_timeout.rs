@@ -0,0 +1,648 @@
+//! Middleware that enforces `grpc-timeout` deadlines.
+//!
+//! See [`GrpcTimeoutLayer`] for more details.
+
+use crate::{
+    metadata::GRPC_TIMEOUT_HEADER,
+    request::{duration_to_grpc_timeout, Deadline},
+    time::{Clock, SharedClock, Sleep},
+    Status, TimeoutExpired,
+};
+use http::{HeaderMap, HeaderValue, Request};
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A [`Layer`] that enforces gRPC deadlines as described by the
+/// [gRPC over HTTP2 spec](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md).
+///
+/// The layer comes in two flavors:
+///
+/// - [`GrpcTimeoutLayer::client`] applies a timeout to each outgoing request and advertises it to
+///   the server by setting the `grpc-timeout` header. If the request already carries a shorter
+///   `grpc-timeout`, that one is kept.
+/// - [`GrpcTimeoutLayer::server`] reads the `grpc-timeout` header of each incoming request and
+///   enforces the shorter of it and the optionally configured timeout.
+///
+/// A timeout configured for a method overrides the configured timeout for the requests to that
+/// method, see [`GrpcTimeoutLayer::method_timeout`].
+///
+/// The server may also bound the `grpc-timeout` sent by clients, see
+/// [`GrpcTimeoutLayer::min_client_timeout`] and [`GrpcTimeoutLayer::max_client_timeout`].
+///
+/// When the deadline elapses the response future of the client fails with [`TimeoutExpired`],
+/// and the one of the server fails with a `DeadlineExceeded` [`Status`] that
+/// [`RecoverError`](super::RecoverError) turns into a response.
+///
+/// The server also sets the deadline of each request, see
+/// [`Request::deadline`](crate::Request::deadline).
+#[derive(Debug, Clone)]
+pub struct GrpcTimeoutLayer {
+    timeout: Option<Duration>,
+    method_timeouts: MethodTimeouts,
+    client_bounds: TimeoutBounds,
+    set_header: bool,
+    server: bool,
+    clock: SharedClock,
+}
+
+/// Timeouts keyed by method path, e.g. `/helloworld.Greeter/SayHello`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MethodTimeouts(Arc<HashMap<String, Duration>>);
+
+impl MethodTimeouts {
+    pub(crate) fn insert(&mut self, path: String, timeout: Duration) {
+        let path = if path.starts_with('/') {
+            path
+        } else {
+            format!("/{path}")
+        };
+        Arc::make_mut(&mut self.0).insert(path, timeout);
+    }
+
+    fn get(&self, path: &str) -> Option<Duration> {
+        self.0.get(path).copied()
+    }
+}
+
+/// Bounds of the `grpc-timeout` sent by clients that the server honors.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TimeoutBounds {
+    pub(crate) min: Option<Duration>,
+    pub(crate) max: Option<Duration>,
+}
+
+impl TimeoutBounds {
+    /// Clamps `timeout` to the bounds, the maximum wins if it is below the minimum.
+    fn clamp(&self, timeout: Duration) -> Duration {
+        let timeout = self.min.map_or(timeout, |min| timeout.max(min));
+        self.max.map_or(timeout, |max| timeout.min(max))
+    }
+}
+
+impl GrpcTimeoutLayer {
+    /// Create a client side layer that applies `timeout` to each request and sets the
+    /// `grpc-timeout` header accordingly.
+    pub fn client(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            method_timeouts: MethodTimeouts::default(),
+            client_bounds: TimeoutBounds::default(),
+            set_header: true,
+            server: false,
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Create a server side layer that enforces the `grpc-timeout` sent by the client, capped at
+    /// `timeout` if one is given.
+    pub fn server(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            method_timeouts: MethodTimeouts::default(),
+            client_bounds: TimeoutBounds::default(),
+            set_header: false,
+            server: true,
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Applies `timeout` instead of the configured timeout to the requests for the method at
+    /// `path`, e.g. `/helloworld.Greeter/SayHello`.
+    ///
+    /// The timeout may be longer than the configured one, e.g. for slow administrative methods.
+    /// A shorter `grpc-timeout` is still enforced.
+    pub fn method_timeout(mut self, path: impl Into<String>, timeout: Duration) -> Self {
+        self.method_timeouts.insert(path.into(), timeout);
+        self
+    }
+
+    /// Honors `grpc-timeout`s sent by clients that are shorter than `min` as if they were `min`,
+    /// so a client cannot ask for deadlines that are bound to expire.
+    ///
+    /// This only applies to server side layers.
+    pub fn min_client_timeout(mut self, min: Duration) -> Self {
+        self.client_bounds.min = Some(min);
+        self
+    }
+
+    /// Honors `grpc-timeout`s sent by clients that are longer than `max` as if they were `max`.
+    ///
+    /// Unlike the timeout of [`GrpcTimeoutLayer::server`], this does not apply to the requests
+    /// without a `grpc-timeout`. This only applies to server side layers.
+    pub fn max_client_timeout(mut self, max: Duration) -> Self {
+        self.client_bounds.max = Some(max);
+        self
+    }
+
+    /// Sets the [`Clock`] used to enforce the deadlines. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        GrpcTimeoutLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for GrpcTimeoutLayer {
+    type Service = GrpcTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcTimeout {
+            inner,
+            timeout: self.timeout,
+            method_timeouts: self.method_timeouts.clone(),
+            client_bounds: self.client_bounds,
+            set_header: self.set_header,
+            server: self.server,
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+/// Middleware that enforces gRPC deadlines.
+///
+/// See [`GrpcTimeoutLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct GrpcTimeout<S> {
+    inner: S,
+    timeout: Option<Duration>,
+    method_timeouts: MethodTimeouts,
+    client_bounds: TimeoutBounds,
+    set_header: bool,
+    server: bool,
+    clock: SharedClock,
+}
+
+impl<S> GrpcTimeout<S> {
+    #[cfg(feature = "server")]
+    pub(crate) fn new(
+        inner: S,
+        server_timeout: Option<Duration>,
+        method_timeouts: MethodTimeouts,
+        client_bounds: TimeoutBounds,
+        clock: SharedClock,
+    ) -> Self {
+        Self {
+            inner,
+            timeout: server_timeout,
+            method_timeouts,
+            client_bounds,
+            set_header: false,
+            server: true,
+            clock,
+        }
+    }
+
+    /// Create a client side [`GrpcTimeout`] that does not advertise its timeout to the server.
+    #[cfg(feature = "channel")]
+    pub(crate) fn channel(inner: S, timeout: Option<Duration>, clock: SharedClock) -> Self {
+        Self {
+            inner,
+            timeout,
+            method_timeouts: MethodTimeouts::default(),
+            client_bounds: TimeoutBounds::default(),
+            set_header: false,
+            server: false,
+            clock,
+        }
+    }
+
+    /// Create a new client side [`GrpcTimeout`].
+    ///
+    /// See [`GrpcTimeoutLayer::client`] for more details.
+    pub fn client(inner: S, timeout: Duration) -> Self {
+        GrpcTimeoutLayer::client(timeout).layer(inner)
+    }
+
+    /// Create a new server side [`GrpcTimeout`].
+    ///
+    /// See [`GrpcTimeoutLayer::server`] for more details.
+    pub fn server(inner: S, timeout: Option<Duration>) -> Self {
+        GrpcTimeoutLayer::server(timeout).layer(inner)
+    }
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for GrpcTimeout<S>
+where
+    S: Service<Request<ReqBody>>,
+    S::Error: Into<crate::BoxError>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let header_timeout = try_parse_grpc_timeout(req.headers()).unwrap_or_else(|e| {
+            tracing::trace!("Error parsing `grpc-timeout` header {:?}", e);
+            None
+        });
+        let header_timeout = match header_timeout {
+            Some(timeout) if self.server => Some(self.client_bounds.clamp(timeout)),
+            timeout => timeout,
+        };
+
+        let configured_timeout = self.method_timeouts.get(req.uri().path()).or(self.timeout);
+
+        // Use the shorter of the two durations, if either are set
+        let timeout_duration = match (header_timeout, configured_timeout) {
+            (None, None) => None,
+            (Some(dur), None) => Some(dur),
+            (None, Some(dur)) => Some(dur),
+            (Some(header), Some(configured)) => {
+                let shorter_duration = std::cmp::min(header, configured);
+                Some(shorter_duration)
+            }
+        };
+
+        if self.set_header && timeout_duration != header_timeout {
+            if let Some(dur) = timeout_duration {
+                let value = HeaderValue::try_from(duration_to_grpc_timeout(dur))
+                    .expect("grpc-timeout is always a valid header value");
+                req.headers_mut().insert(GRPC_TIMEOUT_HEADER, value);
+            }
+        }
+
+        let received = self.clock.now();
+        if let Some(dur) = timeout_duration.filter(|_| self.server) {
+            req.extensions_mut().insert(Deadline(received + dur));
+        }
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            sleep: timeout_duration.map(|dur| self.clock.sleep(dur)),
+            received: self.server.then(|| (received, self.clock.clone())),
+        }
+    }
+}
+
+/// Response future for [`GrpcTimeout`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    sleep: Option<Sleep>,
+    /// When the request was received, on the server side.
+    received: Option<(Instant, SharedClock)>,
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, Res, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Res, E>>,
+    E: Into<crate::BoxError>,
+{
+    type Output = Result<Res, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let ready @ Poll::Ready(_) = this.inner.poll(cx) {
+            return ready.map_err(Into::into);
+        }
+
+        if let Some(sleep) = this.sleep {
+            ready!(sleep.as_mut().poll(cx));
+            let error = match this.received {
+                Some((received, clock)) => {
+                    let elapsed = clock.now().saturating_duration_since(*received);
+                    Status::deadline_exceeded(format!("Deadline exceeded after {elapsed:?}")).into()
+                }
+                None => TimeoutExpired(()).into(),
+            };
+            return Poll::Ready(Err(error));
+        }
+
+        Poll::Pending
+    }
+}
+
+const SECONDS_IN_HOUR: u64 = 60 * 60;
+const SECONDS_IN_MINUTE: u64 = 60;
+
+/// Tries to parse the `grpc-timeout` header if it is present. If we fail to parse, returns
+/// the value we attempted to parse.
+///
+/// Follows the [gRPC over HTTP2 spec](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md).
+pub(crate) fn try_parse_grpc_timeout(
+    headers: &HeaderMap<HeaderValue>,
+) -> Result<Option<Duration>, &HeaderValue> {
+    let Some(val) = headers.get(GRPC_TIMEOUT_HEADER) else {
+        return Ok(None);
+    };
+
+    let (timeout_value, timeout_unit) = val
+        .to_str()
+        .map_err(|_| val)
+        .and_then(|s| if s.is_empty() { Err(val) } else { Ok(s) })?
+        // `HeaderValue::to_str` only returns `Ok` if the header contains ASCII so this
+        // `split_at` will never panic from trying to split in the middle of a character.
+        // See https://docs.rs/http/1/http/header/struct.HeaderValue.html#method.to_str
+        //
+        // `len - 1` also wont panic since we just checked `s.is_empty`.
+        .split_at(val.len() - 1);
+
+    // gRPC spec specifies `TimeoutValue` will be at most 8 digits
+    // Caping this at 8 digits also prevents integer overflow from ever occurring
+    if timeout_value.len() > 8 {
+        return Err(val);
+    }
+
+    let timeout_value: u64 = timeout_value.parse().map_err(|_| val)?;
+
+    let duration = match timeout_unit {
+        // Hours
+        "H" => Duration::from_secs(timeout_value * SECONDS_IN_HOUR),
+        // Minutes
+        "M" => Duration::from_secs(timeout_value * SECONDS_IN_MINUTE),
+        // Seconds
+        "S" => Duration::from_secs(timeout_value),
+        // Milliseconds
+        "m" => Duration::from_millis(timeout_value),
+        // Microseconds
+        "u" => Duration::from_micros(timeout_value),
+        // Nanoseconds
+        "n" => Duration::from_nanos(timeout_value),
+        _ => return Err(val),
+    };
+
+    Ok(Some(duration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::{Arbitrary, Gen};
+    use quickcheck_macros::quickcheck;
+
+    // Helper function to reduce the boiler plate of our test cases
+    fn setup_map_try_parse(val: Option<&str>) -> Result<Option<Duration>, HeaderValue> {
+        let mut hm = HeaderMap::new();
+        if let Some(v) = val {
+            let hv = HeaderValue::from_str(v).unwrap();
+            hm.insert(GRPC_TIMEOUT_HEADER, hv);
+        };
+
+        try_parse_grpc_timeout(&hm).map_err(|e| e.clone())
+    }
+
+    #[test]
+    fn test_hours() {
+        let parsed_duration = setup_map_try_parse(Some("3H")).unwrap().unwrap();
+        assert_eq!(Duration::from_secs(3 * 60 * 60), parsed_duration);
+    }
+
+    #[test]
+    fn test_minutes() {
+        let parsed_duration = setup_map_try_parse(Some("1M")).unwrap().unwrap();
+        assert_eq!(Duration::from_secs(60), parsed_duration);
+    }
+
+    #[test]
+    fn test_seconds() {
+        let parsed_duration = setup_map_try_parse(Some("42S")).unwrap().unwrap();
+        assert_eq!(Duration::from_secs(42), parsed_duration);
+    }
+
+    #[test]
+    fn test_milliseconds() {
+        let parsed_duration = setup_map_try_parse(Some("13m")).unwrap().unwrap();
+        assert_eq!(Duration::from_millis(13), parsed_duration);
+    }
+
+    #[test]
+    fn test_microseconds() {
+        let parsed_duration = setup_map_try_parse(Some("2u")).unwrap().unwrap();
+        assert_eq!(Duration::from_micros(2), parsed_duration);
+    }
+
+    #[test]
+    fn test_nanoseconds() {
+        let parsed_duration = setup_map_try_parse(Some("82n")).unwrap().unwrap();
+        assert_eq!(Duration::from_nanos(82), parsed_duration);
+    }
+
+    #[test]
+    fn test_header_not_present() {
+        let parsed_duration = setup_map_try_parse(None).unwrap();
+        assert!(parsed_duration.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "82f")]
+    fn test_invalid_unit() {
+        // "f" is not a valid TimeoutUnit
+        setup_map_try_parse(Some("82f")).unwrap().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "123456789H")]
+    fn test_too_many_digits() {
+        // gRPC spec states TimeoutValue will be at most 8 digits
+        setup_map_try_parse(Some("123456789H")).unwrap().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "oneH")]
+    fn test_invalid_digits() {
+        // gRPC spec states TimeoutValue will be at most 8 digits
+        setup_map_try_parse(Some("oneH")).unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn client_layer_sets_header() {
+        let svc = tower::service_fn(|req: Request<()>| async move {
+            Ok::<_, crate::BoxError>(req.headers().get(GRPC_TIMEOUT_HEADER).cloned())
+        });
+        let mut svc = GrpcTimeoutLayer::client(Duration::from_secs(1)).layer(svc);
+
+        let header = svc.call(Request::new(())).await.unwrap();
+        assert_eq!(header.unwrap(), "1000000u");
+
+        // a shorter timeout set by the caller is kept
+        let mut req = Request::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("5m"));
+        let header = svc.call(req).await.unwrap();
+        assert_eq!(header.unwrap(), "5m");
+    }
+
+    #[tokio::test]
+    async fn server_layer_enforces_header() {
+        let svc = tower::service_fn(|_: Request<()>| async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<_, crate::BoxError>(())
+        });
+        let mut svc = GrpcTimeoutLayer::server(None).layer(svc);
+
+        let mut req = Request::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("5m"));
+        let err = svc.call(req).await.unwrap_err();
+        let status = err.downcast::<Status>().unwrap();
+        assert_eq!(status.code(), crate::Code::DeadlineExceeded);
+        assert!(status.message().starts_with("Deadline exceeded after"));
+    }
+
+    #[tokio::test]
+    async fn server_layer_clamps_client_timeout() {
+        let svc = tower::service_fn(|req: Request<()>| async move {
+            Ok::<_, crate::BoxError>(req.extensions().get::<Deadline>().unwrap().0)
+        });
+        let mut svc = GrpcTimeoutLayer::server(None)
+            .min_client_timeout(Duration::from_secs(5))
+            .max_client_timeout(Duration::from_secs(300))
+            .layer(svc);
+
+        for (header, expected) in [("1m", 5), ("10S", 10), ("10H", 300)] {
+            let before = std::time::Instant::now();
+            let mut req = Request::new(());
+            req.headers_mut()
+                .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static(header));
+            let deadline = svc.call(req).await.unwrap();
+            let timeout = deadline - before;
+            assert!(timeout >= Duration::from_secs(expected));
+            assert!(timeout < Duration::from_secs(expected + 1));
+        }
+    }
+
+    #[test]
+    fn max_bound_wins_over_min_bound() {
+        let bounds = TimeoutBounds {
+            min: Some(Duration::from_secs(10)),
+            max: Some(Duration::from_secs(1)),
+        };
+        assert_eq!(bounds.clamp(Duration::ZERO), Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "channel")]
+    #[tokio::test]
+    async fn channel_fails_with_timeout_expired() {
+        let svc = tower::service_fn(|req: Request<()>| async move {
+            assert!(req.extensions().get::<Deadline>().is_none());
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok::<_, crate::BoxError>(())
+        });
+        let mut svc =
+            GrpcTimeout::channel(svc, Some(Duration::from_millis(5)), SharedClock::default());
+
+        let err = svc.call(Request::new(())).await.unwrap_err();
+        assert!(err.is::<TimeoutExpired>());
+    }
+
+    #[tokio::test]
+    async fn server_layer_sets_deadline() {
+        let svc = tower::service_fn(|req: Request<()>| async move {
+            Ok::<_, crate::BoxError>(req.extensions().get::<Deadline>().map(|d| d.0))
+        });
+        let mut svc = GrpcTimeoutLayer::server(Some(Duration::from_secs(10))).layer(svc);
+
+        let before = std::time::Instant::now();
+        let mut req = Request::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("5S"));
+        let deadline = svc.call(req).await.unwrap().unwrap();
+        assert!(deadline >= before + Duration::from_secs(5));
+        assert!(deadline <= std::time::Instant::now() + Duration::from_secs(5));
+
+        let mut svc = GrpcTimeoutLayer::server(None).layer(svc.inner);
+        assert!(svc.call(Request::new(())).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn server_layer_applies_method_timeout() {
+        let svc = tower::service_fn(|_: Request<()>| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, crate::BoxError>(())
+        });
+        let mut svc = GrpcTimeoutLayer::server(Some(Duration::from_millis(5)))
+            .method_timeout("test.Test/Slow", Duration::from_secs(10))
+            .layer(svc);
+
+        let req = Request::get("/test.Test/Slow").body(()).unwrap();
+        svc.call(req).await.unwrap();
+
+        let req = Request::get("/test.Test/Fast").body(()).unwrap();
+        let err = svc.call(req).await.unwrap_err();
+        assert!(err.is::<Status>());
+    }
+
+    #[tokio::test]
+    async fn layer_uses_clock() {
+        /// A clock where every deadline has already passed.
+        struct ExpiredClock;
+
+        impl Clock for ExpiredClock {
+            fn now(&self) -> std::time::Instant {
+                std::time::Instant::now()
+            }
+
+            fn sleep_until(&self, _: std::time::Instant) -> Sleep {
+                Box::pin(std::future::ready(()))
+            }
+        }
+
+        let svc = tower::service_fn(|_: Request<()>| {
+            std::future::pending::<Result<(), crate::BoxError>>()
+        });
+        let mut svc = GrpcTimeoutLayer::client(Duration::from_secs(3600))
+            .clock(ExpiredClock)
+            .layer(svc);
+
+        let err = svc.call(Request::new(())).await.unwrap_err();
+        assert!(err.is::<TimeoutExpired>());
+    }
+
+    #[quickcheck]
+    fn fuzz(header_value: HeaderValueGen) -> bool {
+        let header_value = header_value.0;
+
+        // this just shouldn't panic
+        let _ = setup_map_try_parse(Some(&header_value));
+
+        true
+    }
+
+    /// Newtype to implement `Arbitrary` for generating `String`s that are valid `HeaderValue`s.
+    #[derive(Clone, Debug)]
+    struct HeaderValueGen(String);
+
+    impl Arbitrary for HeaderValueGen {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let max = g.choose(&(1..70).collect::<Vec<_>>()).copied().unwrap();
+            Self(gen_string(g, 0, max))
+        }
+    }
+
+    // copied from https://github.com/hyperium/http/blob/master/tests/header_map_fuzz.rs
+    fn gen_string(g: &mut Gen, min: usize, max: usize) -> String {
+        let bytes: Vec<_> = (min..max)
+            .map(|_| {
+                // Chars to pick from
+                g.choose(b"ABCDEFGHIJKLMNOPQRSTUVabcdefghilpqrstuvwxyz----")
+                    .copied()
+                    .unwrap()
+            })
+            .collect();
+
+        String::from_utf8(bytes).unwrap()
+    }
+}
@@ -0,0 +1,366 @@
+//! Middleware that sheds requests when the server is overloaded.
+//!
+//! See [`AdaptiveLoadShedLayer`] for more details.
+
+use crate::{body::Body, Status};
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const GRPC_RETRY_PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+
+type LoadSignal = Arc<dyn Fn() -> f64 + Send + Sync + 'static>;
+
+/// A [`Layer`] that rejects requests with a `ResourceExhausted` status while the server is
+/// overloaded, instead of queueing them.
+///
+/// The server is overloaded while:
+///
+/// - more requests than [`max_in_flight`](Self::max_in_flight) are in flight. A request is in
+///   flight from the time it is received until its response body is sent, so requests that
+///   are queued by inner middleware, e.g. a concurrency limit, are counted as well.
+/// - a [load signal](Self::max_load), e.g. the CPU usage of the process, exceeds its threshold.
+///
+/// The in-flight requests are counted across all the services created by a layer, so applying
+/// it with [`Server::layer`](crate::transport::Server::layer) sheds load for the whole server
+/// rather than for each connection.
+///
+/// Rejected responses carry a `grpc-retry-pushback-ms` hint when a
+/// [pushback](Self::pushback) is set, which clients with a retry policy honor before retrying.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use tonic::service::AdaptiveLoadShedLayer;
+/// # fn cpu_usage() -> f64 { 0.5 }
+/// let layer = AdaptiveLoadShedLayer::new()
+///     .max_in_flight(1024)
+///     .max_load(0.9, cpu_usage)
+///     .pushback(Duration::from_millis(250));
+/// ```
+#[derive(Clone, Default)]
+pub struct AdaptiveLoadShedLayer {
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Option<usize>,
+    max_load: Option<(f64, LoadSignal)>,
+    pushback: Option<Duration>,
+}
+
+impl AdaptiveLoadShedLayer {
+    /// Create a new `AdaptiveLoadShedLayer` that does not shed any request until a threshold is
+    /// set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sheds requests while more than `max` requests are in flight.
+    pub fn max_in_flight(self, max: usize) -> Self {
+        AdaptiveLoadShedLayer {
+            max_in_flight: Some(max),
+            ..self
+        }
+    }
+
+    /// Sheds requests while `signal` returns a load above `threshold`.
+    ///
+    /// `signal` is called for every request, so it should return a value that is sampled in the
+    /// background, e.g. the CPU usage between `0.0` and `1.0` over the last second.
+    pub fn max_load<F>(self, threshold: f64, signal: F) -> Self
+    where
+        F: Fn() -> f64 + Send + Sync + 'static,
+    {
+        AdaptiveLoadShedLayer {
+            max_load: Some((threshold, Arc::new(signal))),
+            ..self
+        }
+    }
+
+    /// Asks clients to wait `pushback` before retrying shed requests.
+    pub fn pushback(self, pushback: Duration) -> Self {
+        AdaptiveLoadShedLayer {
+            pushback: Some(pushback),
+            ..self
+        }
+    }
+
+    /// Get the number of requests in flight in the services created by this layer.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for AdaptiveLoadShedLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptiveLoadShedLayer")
+            .field("in_flight", &self.in_flight())
+            .field("max_in_flight", &self.max_in_flight)
+            .field("max_load", &self.max_load.as_ref().map(|(max, _)| max))
+            .field("pushback", &self.pushback)
+            .finish()
+    }
+}
+
+impl<S> Layer<S> for AdaptiveLoadShedLayer {
+    type Service = AdaptiveLoadShed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdaptiveLoadShed {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that sheds requests when the server is overloaded.
+///
+/// See [`AdaptiveLoadShedLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct AdaptiveLoadShed<S> {
+    inner: S,
+    layer: AdaptiveLoadShedLayer,
+}
+
+impl<S> AdaptiveLoadShed<S> {
+    /// Admits the request if the server is not overloaded.
+    fn admit(&self) -> Option<InFlight> {
+        let layer = &self.layer;
+
+        if let Some((threshold, signal)) = &layer.max_load {
+            let load = signal();
+            if load > *threshold {
+                tracing::debug!(load, threshold, "shedding request");
+                return None;
+            }
+        }
+
+        let in_flight = InFlight::new(layer.in_flight.clone());
+        match layer.max_in_flight {
+            Some(max) if in_flight.count > max => {
+                tracing::debug!(in_flight = in_flight.count, max, "shedding request");
+                None
+            }
+            _ => Some(in_flight),
+        }
+    }
+
+    fn overloaded(&self) -> Response<Body> {
+        let mut status = Status::resource_exhausted("server is overloaded");
+        if let Some(pushback) = self.layer.pushback {
+            let millis = u64::try_from(pushback.as_millis()).unwrap_or(u64::MAX);
+            status
+                .metadata_mut()
+                .insert(GRPC_RETRY_PUSHBACK_HEADER, millis.into());
+        }
+        status.into_http()
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AdaptiveLoadShed<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        match self.admit() {
+            Some(in_flight) => ResponseFuture {
+                kind: Kind::Admitted {
+                    future: self.inner.call(req),
+                    in_flight: Some(in_flight),
+                },
+            },
+            None => ResponseFuture {
+                kind: Kind::Shed(Some(self.overloaded())),
+            },
+        }
+    }
+}
+
+/// Response future for [`AdaptiveLoadShed`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    kind: Kind<F>,
+}
+
+#[pin_project(project = KindProj)]
+enum Kind<F> {
+    Admitted {
+        #[pin]
+        future: F,
+        in_flight: Option<InFlight>,
+    },
+    Shed(Option<Response<Body>>),
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::Admitted { future, in_flight } => {
+                let response = ready!(future.poll(cx))?;
+                let in_flight = in_flight.take();
+                Poll::Ready(Ok(
+                    response.map(|inner| Body::new(InFlightBody { inner, in_flight }))
+                ))
+            }
+            KindProj::Shed(response) => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+/// Counts a request as in flight until it is dropped.
+struct InFlight {
+    counter: Arc<AtomicUsize>,
+    count: usize,
+}
+
+impl InFlight {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+        Self { counter, count }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A response body that keeps its request in flight until it is sent.
+#[pin_project]
+struct InFlightBody<B> {
+    #[pin]
+    inner: B,
+    in_flight: Option<InFlight>,
+}
+
+impl<B> http_body::Body for InFlightBody<B>
+where
+    B: http_body::Body,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if frame.is_none() {
+            this.in_flight.take();
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Code;
+    use http_body_util::BodyExt;
+    use std::sync::atomic::AtomicBool;
+
+    fn echo() -> impl Service<
+        Request<Body>,
+        Response = Response<Body>,
+        Error = crate::BoxError,
+        Future = impl Future<Output = Result<Response<Body>, crate::BoxError>>,
+    > {
+        tower::service_fn(|req: Request<Body>| async move { Ok(Response::new(req.into_body())) })
+    }
+
+    #[tokio::test]
+    async fn sheds_requests_beyond_max_in_flight() {
+        let layer = AdaptiveLoadShedLayer::new()
+            .max_in_flight(1)
+            .pushback(Duration::from_millis(250));
+        let mut svc = layer.layer(echo());
+
+        let body = Body::new(http_body_util::Full::new(Bytes::from_static(b"message")));
+        let first = svc.call(Request::new(body)).await.unwrap();
+        assert_eq!(layer.in_flight(), 1);
+
+        let shed = svc.call(Request::new(Body::empty())).await.unwrap();
+        let status = Status::from_header_map(shed.headers()).unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(shed.headers()[GRPC_RETRY_PUSHBACK_HEADER], "250");
+        assert_eq!(layer.in_flight(), 1);
+
+        // The request is in flight until its response body is sent.
+        first.into_body().collect().await.unwrap();
+        assert_eq!(layer.in_flight(), 0);
+        let response = svc.call(Request::new(Body::empty())).await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+    }
+
+    #[tokio::test]
+    async fn sheds_requests_above_max_load() {
+        let overloaded = Arc::new(AtomicBool::new(true));
+        let signal = overloaded.clone();
+        let layer = AdaptiveLoadShedLayer::new().max_load(0.5, move || {
+            if signal.load(Ordering::Relaxed) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        let mut svc = layer.layer(echo());
+
+        let shed = svc.call(Request::new(Body::empty())).await.unwrap();
+        let status = Status::from_header_map(shed.headers()).unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(shed.headers().get(GRPC_RETRY_PUSHBACK_HEADER).is_none());
+
+        overloaded.store(false, Ordering::Relaxed);
+        let response = svc.call(Request::new(Body::empty())).await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+    }
+}
@@ -1,16 +1,83 @@
 //! Utilities for using Tower services with Tonic.
 
+#[cfg(feature = "server")]
+pub mod access_log;
+pub mod capabilities;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod deadline_propagation;
+#[cfg(feature = "router")]
+pub(crate) mod dynamic_routes;
+pub mod encryption;
+pub mod fair_write;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod fault_injection;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod grpc_timeout;
 pub mod interceptor;
 pub(crate) mod layered;
+pub mod load_shed;
+#[cfg(feature = "channel")]
+pub mod response_cache;
 #[cfg(feature = "router")]
 pub(crate) mod router;
+#[cfg(all(feature = "rpc-metrics", any(feature = "server", feature = "channel")))]
+pub mod rpc_metrics;
+#[cfg(feature = "rpc-trace")]
+pub mod rpc_trace;
+#[cfg(feature = "service-config")]
+pub mod service_config;
+#[cfg(feature = "channel")]
+pub mod single_flight;
+#[cfg(any(feature = "server", feature = "channel"))]
+pub mod slow_rpc;
 
+#[doc(inline)]
+#[cfg(feature = "server")]
+pub use self::access_log::{AccessLog, AccessLogLayer, AccessLogRecord};
+#[doc(inline)]
+pub use self::capabilities::{Capabilities, CapabilitiesLayer, PeerCapabilities};
+#[doc(inline)]
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::deadline_propagation::{DeadlinePropagation, DeadlinePropagationLayer};
+#[doc(inline)]
+#[cfg(feature = "router")]
+pub use self::dynamic_routes::{DynamicRoutes, DynamicRoutesFuture};
+#[doc(inline)]
+pub use self::encryption::{MessageEncryption, MessageEncryptionLayer};
+#[doc(inline)]
+pub use self::fair_write::{FairWrite, FairWriteLayer, StreamBacklog};
+#[doc(inline)]
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::fault_injection::{Fault, FaultInjection, FaultInjectionLayer};
+#[doc(inline)]
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::grpc_timeout::{GrpcTimeout, GrpcTimeoutLayer};
 #[doc(inline)]
 pub use self::interceptor::{Interceptor, InterceptorLayer};
 pub use self::layered::{LayerExt, Layered};
 #[doc(inline)]
+pub use self::load_shed::{AdaptiveLoadShed, AdaptiveLoadShedLayer};
+#[doc(inline)]
+#[cfg(feature = "channel")]
+pub use self::response_cache::{ResponseCache, ResponseCacheLayer};
+#[doc(inline)]
 #[cfg(feature = "router")]
 pub use self::router::{Routes, RoutesBuilder};
+#[doc(inline)]
+#[cfg(all(feature = "rpc-metrics", any(feature = "server", feature = "channel")))]
+pub use self::rpc_metrics::{MetricsRecorder, RpcMetrics, RpcMetricsLayer};
+#[doc(inline)]
+#[cfg(feature = "rpc-trace")]
+pub use self::rpc_trace::{RpcTrace, RpcTraceLayer, TraceContext};
+#[doc(inline)]
+#[cfg(feature = "service-config")]
+pub use self::service_config::{ServiceConfig, ServiceConfigLayer};
+#[doc(inline)]
+#[cfg(feature = "channel")]
+pub use self::single_flight::{SingleFlight, SingleFlightLayer};
+#[doc(inline)]
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::slow_rpc::{SlowRpc, SlowRpcLayer};
 #[cfg(feature = "router")]
 pub use axum::{body::Body as AxumBody, Router as AxumRouter};
 
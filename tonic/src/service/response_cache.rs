@@ -0,0 +1,507 @@
+//! Middleware that caches the responses of idempotent unary calls.
+//!
+//! See [`ResponseCacheLayer`] for more details.
+
+use crate::{
+    body::Body,
+    metadata::MetadataMap,
+    time::{Clock, SharedClock},
+    Status,
+};
+use bytes::Bytes;
+use http::{header::CACHE_CONTROL, HeaderMap, HeaderName, HeaderValue, Request, Response};
+use http_body::Frame;
+use http_body_util::{BodyExt, Full, StreamBody};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// How long a response may be cached, as emitted by the server in the `cache-control` metadata.
+///
+/// The value follows the HTTP `Cache-Control` header: `no-store` forbids caching the response,
+/// and `max-age=<seconds>` allows caching it for that long, overriding the
+/// [`ttl`](ResponseCacheLayer::ttl) of the client. It is read from both the response metadata and
+/// the trailers, and `no-store` wins if both are set.
+///
+/// ```
+/// use std::time::Duration;
+/// use tonic::{service::response_cache::CacheControl, Response};
+///
+/// let mut response = Response::new(());
+/// CacheControl::MaxAge(Duration::from_secs(60)).insert_into(response.metadata_mut());
+/// assert_eq!(response.metadata().get("cache-control").unwrap(), "max-age=60");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheControl {
+    /// The response must not be cached.
+    NoStore,
+    /// The response may be cached for at most this long.
+    MaxAge(Duration),
+}
+
+impl CacheControl {
+    /// Sets the `cache-control` entry of `metadata` to this value.
+    pub fn insert_into(self, metadata: &mut MetadataMap) {
+        let value = match self {
+            CacheControl::NoStore => "no-store".to_owned(),
+            CacheControl::MaxAge(max_age) => format!("max-age={}", max_age.as_secs()),
+        };
+        metadata.insert(
+            CACHE_CONTROL.as_str(),
+            value.parse().expect("cache-control is valid metadata"),
+        );
+    }
+
+    /// Parses the `cache-control` entry of `metadata`, if any.
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        Self::from_headers(metadata.as_ref())
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut control = None;
+        for directive in directives(headers) {
+            if directive == "no-store" {
+                return Some(CacheControl::NoStore);
+            }
+            if let Some(max_age) = directive.strip_prefix("max-age=") {
+                if let Ok(secs) = max_age.parse() {
+                    control = Some(CacheControl::MaxAge(Duration::from_secs(secs)));
+                }
+            }
+        }
+        control
+    }
+}
+
+fn directives(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+}
+
+/// The key a response is cached under: the method, the selected request metadata and the
+/// encoded request message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    path: String,
+    metadata: Vec<Option<HeaderValue>>,
+    message: Bytes,
+}
+
+impl CacheKey {
+    pub(crate) fn new(
+        path: &str,
+        headers: &HeaderMap,
+        names: &[HeaderName],
+        message: Bytes,
+    ) -> Self {
+        CacheKey {
+            path: path.to_owned(),
+            metadata: names
+                .iter()
+                .map(|name| headers.get(name).cloned())
+                .collect(),
+            message,
+        }
+    }
+
+    /// The path of the method, e.g. `/helloworld.Greeter/SayHello`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The encoded request, including the gRPC message framing.
+    pub fn message(&self) -> &Bytes {
+        &self.message
+    }
+}
+
+/// A response held by a [`ResponseCacheStore`].
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    headers: HeaderMap,
+    body: Bytes,
+    trailers: HeaderMap,
+    expires_at: Instant,
+}
+
+impl CachedResponse {
+    /// The instant after which the response is stale.
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    /// The encoded response, including the gRPC message framing.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    fn into_response(self) -> Response<Body> {
+        let mut response = Response::new(frames_body(self.body, self.trailers));
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+/// Where a [`ResponseCacheLayer`] keeps its responses.
+///
+/// Stale responses are ignored and removed by the layer, so a store does not need to track
+/// expiry, though it may use [`CachedResponse::expires_at`] to pick what to evict.
+pub trait ResponseCacheStore: Send + Sync + 'static {
+    /// The response cached under `key`, if any.
+    fn get(&self, key: &CacheKey) -> Option<CachedResponse>;
+
+    /// Caches `response` under `key`, replacing any previous response.
+    fn insert(&self, key: CacheKey, response: CachedResponse);
+
+    /// Removes the response cached under `key`.
+    fn remove(&self, key: &CacheKey);
+}
+
+/// A [`ResponseCacheStore`] that keeps up to a fixed number of responses in memory.
+///
+/// When full, the response that expires first is evicted to make room for a new one.
+#[derive(Debug)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<CacheKey, CachedResponse>>,
+    capacity: usize,
+}
+
+impl MemoryStore {
+    /// Create a store holding up to `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        MemoryStore {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+}
+
+impl Default for MemoryStore {
+    /// A store holding up to 1024 responses.
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl ResponseCacheStore for MemoryStore {
+    fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: CacheKey, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, response)| response.expires_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, response);
+    }
+
+    fn remove(&self, key: &CacheKey) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+/// A [`Layer`] that caches the responses of idempotent unary calls on the client side.
+///
+/// Only the methods registered with [`method`](Self::method) are cached, and they must be unary
+/// since the whole response is buffered. A call is looked up by its [`CacheKey`]: the method, the
+/// encoded request message and the values of the metadata registered with
+/// [`key_metadata`](Self::key_metadata), e.g. a tenant or a locale. A fresh cached response
+/// answers the call without reaching the server.
+///
+/// Only successful responses are cached, for the [`ttl`](Self::ttl) of the layer unless the
+/// server emits a [`CacheControl`]. A request carrying `cache-control: no-cache` metadata skips
+/// the lookup, but its response is still cached.
+///
+/// ```
+/// use std::time::Duration;
+/// use tonic::service::{response_cache::MemoryStore, ResponseCacheLayer};
+///
+/// let layer = ResponseCacheLayer::new(MemoryStore::default())
+///     .method("/helloworld.Greeter/SayHello")
+///     .key_metadata(http::HeaderName::from_static("x-tenant"))
+///     .ttl(Duration::from_secs(30));
+/// ```
+#[derive(Clone)]
+pub struct ResponseCacheLayer {
+    store: Arc<dyn ResponseCacheStore>,
+    methods: HashSet<String>,
+    key_metadata: Vec<HeaderName>,
+    ttl: Duration,
+    clock: SharedClock,
+}
+
+impl ResponseCacheLayer {
+    /// Create a layer keeping responses in `store`, with no method cached yet.
+    pub fn new(store: impl ResponseCacheStore) -> Self {
+        ResponseCacheLayer {
+            store: Arc::new(store),
+            methods: HashSet::new(),
+            key_metadata: Vec::new(),
+            ttl: Duration::from_secs(60),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Caches the responses of the unary method with this path, e.g.
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.insert(path.into());
+        self
+    }
+
+    /// Includes the value of the request metadata `name` in the cache key.
+    pub fn key_metadata(mut self, name: HeaderName) -> Self {
+        self.key_metadata.push(name);
+        self
+    }
+
+    /// How long responses are cached when the server does not say. Defaults to 60 seconds.
+    pub fn ttl(self, ttl: Duration) -> Self {
+        ResponseCacheLayer { ttl, ..self }
+    }
+
+    /// Sets the [`Clock`] responses expire by. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        ResponseCacheLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
+    /// How long a response with these headers and trailers may be cached.
+    fn expiry(&self, headers: &HeaderMap, trailers: &HeaderMap) -> Option<Duration> {
+        let ttl = match (
+            CacheControl::from_headers(headers),
+            CacheControl::from_headers(trailers),
+        ) {
+            (Some(CacheControl::NoStore), _) | (_, Some(CacheControl::NoStore)) => return None,
+            (_, Some(CacheControl::MaxAge(max_age))) | (Some(CacheControl::MaxAge(max_age)), _) => {
+                max_age
+            }
+            (None, None) => self.ttl,
+        };
+        (!ttl.is_zero()).then_some(ttl)
+    }
+
+    async fn call<S, B>(
+        self,
+        mut inner: S,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, crate::BoxError>
+    where
+        S: Service<Request<Body>, Response = Response<B>>,
+        S::Error: Into<crate::BoxError>,
+        B: http_body::Body<Data = Bytes>,
+        B::Error: Into<crate::BoxError>,
+    {
+        let (parts, body) = req.into_parts();
+        let message = body.collect().await?.to_bytes();
+        let key = CacheKey::new(
+            parts.uri.path(),
+            &parts.headers,
+            &self.key_metadata,
+            message.clone(),
+        );
+
+        let no_cache = directives(&parts.headers).any(|directive| directive == "no-cache");
+        if !no_cache {
+            if let Some(cached) = self.store.get(&key) {
+                if cached.expires_at > self.clock.now() {
+                    return Ok(cached.into_response());
+                }
+                self.store.remove(&key);
+            }
+        }
+
+        let req = Request::from_parts(parts, Body::new(Full::new(message)));
+        let res = inner.call(req).await.map_err(Into::into)?;
+
+        let (parts, body) = res.into_parts();
+        let collected = body.collect().await.map_err(Into::into)?;
+        let trailers = collected.trailers().cloned().unwrap_or_default();
+        let body = collected.to_bytes();
+
+        let ok = trailers
+            .get(Status::GRPC_STATUS)
+            .or_else(|| parts.headers.get(Status::GRPC_STATUS))
+            .is_some_and(|code| code == "0");
+        if let Some(ttl) = ok.then(|| self.expiry(&parts.headers, &trailers)).flatten() {
+            let cached = CachedResponse {
+                headers: parts.headers.clone(),
+                body: body.clone(),
+                trailers: trailers.clone(),
+                expires_at: self.clock.now() + ttl,
+            };
+            self.store.insert(key, cached);
+        }
+
+        Ok(Response::from_parts(parts, frames_body(body, trailers)))
+    }
+}
+
+impl fmt::Debug for ResponseCacheLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCacheLayer")
+            .field("methods", &self.methods)
+            .field("key_metadata", &self.key_metadata)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for ResponseCacheLayer {
+    type Service = ResponseCache<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseCache {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A body made of a single data frame and the trailers, omitting whichever is empty.
+pub(crate) fn frames_body(data: Bytes, trailers: HeaderMap) -> Body {
+    let data = (!data.is_empty()).then(|| Frame::data(data));
+    let trailers = (!trailers.is_empty()).then(|| Frame::trailers(trailers));
+    let frames = data.into_iter().chain(trailers).map(Ok::<_, Status>);
+    Body::new(StreamBody::new(tokio_stream::iter(frames)))
+}
+
+/// Middleware that caches the responses of idempotent unary calls.
+///
+/// See [`ResponseCacheLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct ResponseCache<S> {
+    inner: S,
+    layer: ResponseCacheLayer,
+}
+
+impl<S> ResponseCache<S> {
+    /// Create a new [`ResponseCache`] keeping the responses of `inner` in `store`.
+    ///
+    /// See [`ResponseCacheLayer::new`] for more details.
+    pub fn new(inner: S, store: impl ResponseCacheStore) -> Self {
+        ResponseCacheLayer::new(store).layer(inner)
+    }
+}
+
+impl<S, B> Service<Request<Body>> for ResponseCache<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::BoxError>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        if !self.layer.methods.contains(req.uri().path()) {
+            let future = inner.call(req);
+            return Box::pin(async move {
+                let res = future.await.map_err(Into::into)?;
+                Ok(res.map(Body::new))
+            });
+        }
+
+        Box::pin(self.layer.clone().call(inner, req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn cache_control_directives() {
+        assert_eq!(
+            CacheControl::from_headers(&metadata("private, max-age=30")),
+            Some(CacheControl::MaxAge(Duration::from_secs(30)))
+        );
+        assert_eq!(
+            CacheControl::from_headers(&metadata("max-age=30, no-store")),
+            Some(CacheControl::NoStore)
+        );
+        assert_eq!(CacheControl::from_headers(&metadata("no-cache")), None);
+        assert_eq!(CacheControl::from_headers(&HeaderMap::new()), None);
+
+        let layer = ResponseCacheLayer::new(MemoryStore::default()).ttl(Duration::from_secs(5));
+        let none = HeaderMap::new();
+        assert_eq!(layer.expiry(&none, &none), Some(Duration::from_secs(5)));
+        assert_eq!(
+            layer.expiry(&metadata("max-age=10"), &none),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            layer.expiry(&metadata("max-age=10"), &metadata("no-store")),
+            None
+        );
+        assert_eq!(layer.expiry(&metadata("max-age=0"), &none), None);
+    }
+
+    #[test]
+    fn memory_store_evicts_the_first_to_expire() {
+        let store = MemoryStore::new(2);
+        let now = Instant::now();
+        let key = |n: u8| CacheKey {
+            path: "/a".into(),
+            metadata: Vec::new(),
+            message: Bytes::from(vec![n]),
+        };
+        let response = |secs| CachedResponse {
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            trailers: HeaderMap::new(),
+            expires_at: now + Duration::from_secs(secs),
+        };
+
+        store.insert(key(1), response(20));
+        store.insert(key(2), response(10));
+        store.insert(key(3), response(30));
+
+        assert!(store.get(&key(1)).is_some());
+        assert!(store.get(&key(2)).is_none());
+        assert!(store.get(&key(3)).is_some());
+    }
+}
@@ -1,3 +1,4 @@
+use super::DynamicRoutes;
 use crate::{body::Body, server::NamedService, Status};
 use http::{Request, Response};
 use std::{
@@ -7,12 +8,16 @@ use std::{
     pin::Pin,
     task::{Context, Poll},
 };
-use tower::{Service, ServiceExt};
+use tower::{util::BoxCloneSyncService, Service, ServiceExt};
+
+pub(crate) type BoxRoute = BoxCloneSyncService<Request<Body>, Response<Body>, Infallible>;
 
 /// A [`Service`] router.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Routes {
     router: axum::Router,
+    dynamic: Option<DynamicRoutes>,
+    fallback: Option<BoxRoute>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -39,6 +44,25 @@ impl RoutesBuilder {
         self
     }
 
+    /// Add a service that only serves the `method` of its gRPC service.
+    ///
+    /// See [`Routes::add_method_service`] for more details.
+    pub fn add_method_service<S>(&mut self, method: &str, svc: S) -> &mut Self
+    where
+        S: Service<Request<Body>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Response: axum::response::IntoResponse,
+        S::Future: Send + 'static,
+    {
+        let routes = self.routes.take().unwrap_or_default();
+        self.routes.replace(routes.add_method_service(method, svc));
+        self
+    }
+
     /// Returns the routes with added services or empty [`Routes`] if no service was added
     pub fn routes(self) -> Routes {
         self.routes.unwrap_or_default()
@@ -49,10 +73,21 @@ impl Default for Routes {
     fn default() -> Self {
         Self {
             router: axum::Router::new().fallback(unimplemented),
+            dynamic: None,
+            fallback: None,
         }
     }
 }
 
+impl fmt::Debug for Routes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Routes")
+            .field("router", &self.router)
+            .field("dynamic", &self.dynamic)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Routes {
     /// Create a new routes with `svc` already added to it.
     pub fn new<S>(svc: S) -> Self
@@ -93,12 +128,98 @@ impl Routes {
         self
     }
 
+    /// Add a service that only serves the `method` of its gRPC service, e.g. `SayHello`.
+    ///
+    /// The requests for `method` are routed to `svc` instead of the service added with
+    /// [`add_service`](Self::add_service) for the other methods. Together with
+    /// [`LayerExt::named_layer`](super::LayerExt::named_layer), this applies middleware to a
+    /// single method, while middleware is applied to a whole service by adding it layered.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let greeter = GreeterServer::new(MyGreeter::default());
+    /// let routes = Routes::new(greeter.clone())
+    ///     .add_service(AuthLayer::new().named_layer(AdminServer::new(MyAdmin::default())))
+    ///     .add_method_service("SayHello", TraceLayer::new_for_grpc().named_layer(greeter));
+    /// ```
+    pub fn add_method_service<S>(mut self, method: &str, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Response: axum::response::IntoResponse,
+        S::Future: Send + 'static,
+    {
+        self.router = self.router.route_service(
+            &format!("/{}/{}", S::NAME, method),
+            svc.map_request(|req: Request<axum::body::Body>| req.map(Body::new)),
+        );
+        self
+    }
+
+    /// Route the requests for services that were not added to these routes to `dynamic`.
+    ///
+    /// The services of `dynamic` can then be added and removed while these routes are served.
+    /// Services added to these routes take precedence over those of `dynamic` with the same name.
+    pub fn add_dynamic_routes(mut self, dynamic: DynamicRoutes) -> Self {
+        self.dynamic = Some(dynamic);
+        self.update_fallback()
+    }
+
+    /// Answer the requests for unknown services with `svc` instead of an `Unimplemented` status.
+    ///
+    /// This can be used to customize the status of these requests, or to serve non-gRPC traffic,
+    /// e.g. with an HTTP `404 Not Found` response. The requests for services that are neither
+    /// added to these routes nor to their [`DynamicRoutes`] are answered by `svc`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::convert::Infallible;
+    /// # use tonic::{body::Body, service::Routes, Status};
+    /// let routes = Routes::default().fallback_service(tower::service_fn(|_| async {
+    ///     let status = Status::unimplemented("see https://example.com/api for the available services");
+    ///     Ok::<_, Infallible>(status.into_http::<Body>())
+    /// }));
+    /// ```
+    pub fn fallback_service<S>(mut self, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Error = Infallible> + Clone + Send + Sync + 'static,
+        S::Response: axum::response::IntoResponse,
+        S::Future: Send + 'static,
+    {
+        let svc =
+            svc.map_response(|res| axum::response::IntoResponse::into_response(res).map(Body::new));
+        self.fallback = Some(BoxRoute::new(svc));
+        self.update_fallback()
+    }
+
+    fn update_fallback(mut self) -> Self {
+        self.router = match (&self.dynamic, &self.fallback) {
+            (Some(dynamic), fallback) => self
+                .router
+                .fallback_service(dynamic.clone().with_fallback(fallback.clone())),
+            (None, Some(fallback)) => self.router.fallback_service(
+                fallback
+                    .clone()
+                    .map_request(|req: Request<axum::body::Body>| req.map(Body::new)),
+            ),
+            (None, None) => self.router.fallback(unimplemented),
+        };
+        self
+    }
+
     /// This makes axum perform update some internals of the router that improves perf.
     ///
     /// See <https://docs.rs/axum/latest/axum/routing/struct.Router.html#a-note-about-performance>
     pub fn prepare(self) -> Self {
         Self {
             router: self.router.with_state(()),
+            ..self
         }
     }
 
@@ -131,7 +252,11 @@ impl From<axum::Router> for RoutesBuilder {
 
 impl From<axum::Router> for Routes {
     fn from(router: axum::Router) -> Self {
-        Self { router }
+        Self {
+            router,
+            dynamic: None,
+            fallback: None,
+        }
     }
 }
 
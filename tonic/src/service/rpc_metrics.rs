@@ -0,0 +1,663 @@
+//! Middleware that records the standard OpenTelemetry metrics of gRPC calls.
+//!
+//! See [`RpcMetricsLayer`] for more details.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock},
+    Code, Status,
+};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Receives the measurements of a [`RpcMetricsLayer`], typically to record them with the
+/// histograms of an OpenTelemetry meter.
+///
+/// Closures taking the same arguments as [`record`](Self::record) are recorders.
+pub trait MetricsRecorder: Send + Sync + 'static {
+    /// Records a measurement of `instrument` for the RPC described by `attributes`.
+    fn record(&self, instrument: Instrument, value: f64, attributes: &RpcAttributes);
+}
+
+impl<F> MetricsRecorder for F
+where
+    F: Fn(Instrument, f64, &RpcAttributes) + Send + Sync + 'static,
+{
+    fn record(&self, instrument: Instrument, value: f64, attributes: &RpcAttributes) {
+        self(instrument, value, attributes)
+    }
+}
+
+/// A histogram of the OpenTelemetry semantic conventions for RPC metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Instrument {
+    /// `rpc.client.duration`: the duration of outgoing calls, in milliseconds.
+    ClientDuration,
+    /// `rpc.client.request.size`: the size of the messages sent by calls, in bytes.
+    ClientRequestSize,
+    /// `rpc.client.response.size`: the size of the messages received by calls, in bytes.
+    ClientResponseSize,
+    /// `rpc.client.requests_per_rpc`: the number of messages sent per call.
+    ClientRequestsPerRpc,
+    /// `rpc.client.responses_per_rpc`: the number of messages received per call.
+    ClientResponsesPerRpc,
+    /// `rpc.server.duration`: the duration of incoming RPCs, in milliseconds.
+    ServerDuration,
+    /// `rpc.server.request.size`: the size of the messages received by RPCs, in bytes.
+    ServerRequestSize,
+    /// `rpc.server.response.size`: the size of the messages sent by RPCs, in bytes.
+    ServerResponseSize,
+    /// `rpc.server.requests_per_rpc`: the number of messages received per RPC.
+    ServerRequestsPerRpc,
+    /// `rpc.server.responses_per_rpc`: the number of messages sent per RPC.
+    ServerResponsesPerRpc,
+}
+
+impl Instrument {
+    /// The name of the instrument, e.g. `rpc.server.duration`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instrument::ClientDuration => "rpc.client.duration",
+            Instrument::ClientRequestSize => "rpc.client.request.size",
+            Instrument::ClientResponseSize => "rpc.client.response.size",
+            Instrument::ClientRequestsPerRpc => "rpc.client.requests_per_rpc",
+            Instrument::ClientResponsesPerRpc => "rpc.client.responses_per_rpc",
+            Instrument::ServerDuration => "rpc.server.duration",
+            Instrument::ServerRequestSize => "rpc.server.request.size",
+            Instrument::ServerResponseSize => "rpc.server.response.size",
+            Instrument::ServerRequestsPerRpc => "rpc.server.requests_per_rpc",
+            Instrument::ServerResponsesPerRpc => "rpc.server.responses_per_rpc",
+        }
+    }
+
+    /// The unit of the instrument in UCUM notation: `ms`, `By` or `{count}`.
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Instrument::ClientDuration | Instrument::ServerDuration => "ms",
+            Instrument::ClientRequestSize
+            | Instrument::ClientResponseSize
+            | Instrument::ServerRequestSize
+            | Instrument::ServerResponseSize => "By",
+            _ => "{count}",
+        }
+    }
+
+    /// A description of the instrument.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Instrument::ClientDuration => "Measures the duration of outbound RPC.",
+            Instrument::ClientRequestSize => "Measures the size of RPC request messages.",
+            Instrument::ClientResponseSize => "Measures the size of RPC response messages.",
+            Instrument::ClientRequestsPerRpc => "Measures the number of messages sent per RPC.",
+            Instrument::ClientResponsesPerRpc => {
+                "Measures the number of messages received per RPC."
+            }
+            Instrument::ServerDuration => "Measures the duration of inbound RPC.",
+            Instrument::ServerRequestSize => "Measures the size of RPC request messages.",
+            Instrument::ServerResponseSize => "Measures the size of RPC response messages.",
+            Instrument::ServerRequestsPerRpc => "Measures the number of messages received per RPC.",
+            Instrument::ServerResponsesPerRpc => "Measures the number of messages sent per RPC.",
+        }
+    }
+}
+
+/// The attributes of the measurements of an RPC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcAttributes {
+    service: String,
+    method: String,
+    status_code: Option<Code>,
+}
+
+impl RpcAttributes {
+    /// `rpc.system`: always `grpc`.
+    pub fn system(&self) -> &'static str {
+        "grpc"
+    }
+
+    /// `rpc.service`: the full name of the service, e.g. `helloworld.Greeter`.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// `rpc.method`: the name of the method, e.g. `SayHello`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// `rpc.grpc.status_code`: the status code the RPC completed with.
+    ///
+    /// Message sizes are recorded as the messages are sent and received, before the status is
+    /// known, so their attributes have no status code.
+    pub fn status_code(&self) -> Option<Code> {
+        self.status_code
+    }
+}
+
+/// The side of the RPCs a [`RpcMetricsLayer`] measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+impl Side {
+    fn instruments(self) -> Instruments {
+        match self {
+            Side::Client => Instruments {
+                duration: Instrument::ClientDuration,
+                request_size: Instrument::ClientRequestSize,
+                response_size: Instrument::ClientResponseSize,
+                requests_per_rpc: Instrument::ClientRequestsPerRpc,
+                responses_per_rpc: Instrument::ClientResponsesPerRpc,
+            },
+            Side::Server => Instruments {
+                duration: Instrument::ServerDuration,
+                request_size: Instrument::ServerRequestSize,
+                response_size: Instrument::ServerResponseSize,
+                requests_per_rpc: Instrument::ServerRequestsPerRpc,
+                responses_per_rpc: Instrument::ServerResponsesPerRpc,
+            },
+        }
+    }
+}
+
+/// The instruments of one side.
+struct Instruments {
+    duration: Instrument,
+    request_size: Instrument,
+    response_size: Instrument,
+    requests_per_rpc: Instrument,
+    responses_per_rpc: Instrument,
+}
+
+/// A [`Layer`] that records the standard OpenTelemetry metrics of every RPC.
+///
+/// The measurements of the `rpc.client.*` or `rpc.server.*` histograms of the
+/// [semantic conventions](https://opentelemetry.io/docs/specs/semconv/rpc/rpc-metrics/) are
+/// passed to a [`MetricsRecorder`], which records them with the instruments of the application,
+/// e.g. OpenTelemetry histograms named after [`Instrument::name`]. Nothing is measured unless the
+/// layer is applied.
+///
+/// Message sizes are the lengths of the messages as framed on the wire, i.e. after compression.
+///
+/// The server layer is applied with `Server::layer`, and the client layer wraps a channel:
+///
+/// ```
+/// use tonic::service::rpc_metrics::{Instrument, RpcAttributes, RpcMetricsLayer};
+///
+/// let layer = RpcMetricsLayer::server(|instrument: Instrument, value: f64, attributes: &RpcAttributes| {
+///     // e.g. `histograms[&instrument].record(value, &[...])`
+///     println!("{} {} {value} {:?}", instrument.name(), attributes.method(), attributes.status_code());
+/// });
+/// ```
+#[derive(Clone)]
+pub struct RpcMetricsLayer {
+    side: Side,
+    recorder: Arc<dyn MetricsRecorder>,
+    clock: SharedClock,
+}
+
+impl RpcMetricsLayer {
+    /// Create a layer measuring the RPCs served by a server.
+    pub fn server(recorder: impl MetricsRecorder) -> Self {
+        Self::new(Side::Server, recorder)
+    }
+
+    /// Create a layer measuring the calls made by a client.
+    pub fn client(recorder: impl MetricsRecorder) -> Self {
+        Self::new(Side::Client, recorder)
+    }
+
+    fn new(side: Side, recorder: impl MetricsRecorder) -> Self {
+        RpcMetricsLayer {
+            side,
+            recorder: Arc::new(recorder),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Sets the [`Clock`] used to measure durations. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        RpcMetricsLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl fmt::Debug for RpcMetricsLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcMetricsLayer")
+            .field("side", &self.side)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for RpcMetricsLayer {
+    type Service = RpcMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcMetrics {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that records the standard OpenTelemetry metrics of every RPC.
+///
+/// See [`RpcMetricsLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct RpcMetrics<S> {
+    inner: S,
+    layer: RpcMetricsLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RpcMetrics<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ReqBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ReqBody::Error: Into<crate::BoxError>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let path = req.uri().path();
+        let name = path.strip_prefix('/').unwrap_or(path);
+        let (service, method) = name.split_once('/').unwrap_or((name, ""));
+
+        let rpc = Arc::new(Rpc {
+            instruments: self.layer.side.instruments(),
+            recorder: self.layer.recorder.clone(),
+            attributes: RpcAttributes {
+                service: service.to_owned(),
+                method: method.to_owned(),
+                status_code: None,
+            },
+            requests: AtomicU64::new(0),
+        });
+        let pending = Pending {
+            rpc: rpc.clone(),
+            code: None,
+            responses: 0,
+            start: self.layer.clock.now(),
+            clock: self.layer.clock.clone(),
+        };
+
+        let req = req.map(|inner| {
+            Body::new(RequestBody {
+                inner,
+                counter: MessageCounter::default(),
+                rpc,
+            })
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            pending: Some(pending),
+        }
+    }
+}
+
+/// What is shared by the request and response of an RPC.
+struct Rpc {
+    instruments: Instruments,
+    recorder: Arc<dyn MetricsRecorder>,
+    attributes: RpcAttributes,
+    /// The number of messages of the request.
+    requests: AtomicU64,
+}
+
+impl Rpc {
+    fn record(&self, instrument: Instrument, value: f64) {
+        self.recorder.record(instrument, value, &self.attributes);
+    }
+}
+
+/// Response future for [`RpcMetrics`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    pending: Option<Pending>,
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let mut pending = this.pending.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                pending.code = Some(Code::Unknown);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        // A trailers-only response carries its status in its headers.
+        pending.update_code(response.headers());
+        let pending = if response.body().is_end_stream() {
+            None
+        } else {
+            Some(pending)
+        };
+
+        Poll::Ready(Ok(response.map(|inner| {
+            Body::new(ResponseBody {
+                inner,
+                counter: MessageCounter::default(),
+                pending,
+            })
+        })))
+    }
+}
+
+/// An RPC whose duration and message counts are recorded once it is dropped.
+struct Pending {
+    rpc: Arc<Rpc>,
+    code: Option<Code>,
+    /// The number of messages of the response.
+    responses: u64,
+    start: Instant,
+    clock: SharedClock,
+}
+
+impl Pending {
+    fn update_code(&mut self, headers: &HeaderMap) {
+        if let Some(code) = headers.get(Status::GRPC_STATUS) {
+            self.code = Some(Code::from_bytes(code.as_bytes()));
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        // A response without a status is malformed, unless it is dropped before it ends.
+        let attributes = RpcAttributes {
+            status_code: Some(self.code.unwrap_or(Code::Cancelled)),
+            ..self.rpc.attributes.clone()
+        };
+        let duration = self.clock.now().saturating_duration_since(self.start);
+        let requests = self.rpc.requests.load(Ordering::Relaxed);
+
+        let instruments = &self.rpc.instruments;
+        let recorder = &self.rpc.recorder;
+        recorder.record(
+            instruments.duration,
+            duration.as_secs_f64() * 1000.0,
+            &attributes,
+        );
+        recorder.record(instruments.requests_per_rpc, requests as f64, &attributes);
+        recorder.record(
+            instruments.responses_per_rpc,
+            self.responses as f64,
+            &attributes,
+        );
+    }
+}
+
+/// Finds the lengths of the messages of a gRPC stream by following its length-prefixed framing.
+#[derive(Default)]
+struct MessageCounter {
+    header: [u8; 5],
+    header_len: usize,
+    remaining: u64,
+}
+
+impl MessageCounter {
+    /// Calls `on_message` with the length of every message whose header is in `data`.
+    fn count(&mut self, mut data: &[u8], mut on_message: impl FnMut(u32)) {
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let skip = self.remaining.min(data.len() as u64);
+                self.remaining -= skip;
+                data = &data[skip as usize..];
+                continue;
+            }
+
+            let take = (self.header.len() - self.header_len).min(data.len());
+            self.header[self.header_len..][..take].copy_from_slice(&data[..take]);
+            self.header_len += take;
+            data = &data[take..];
+
+            if self.header_len == self.header.len() {
+                let [_, len @ ..] = self.header;
+                let len = u32::from_be_bytes(len);
+                on_message(len);
+                self.header_len = 0;
+                self.remaining = u64::from(len);
+            }
+        }
+    }
+}
+
+/// A request body that records the size of its messages.
+#[pin_project]
+struct RequestBody<B> {
+    #[pin]
+    inner: B,
+    counter: MessageCounter,
+    rpc: Arc<Rpc>,
+}
+
+impl<B> http_body::Body for RequestBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(data) = frame.as_ref().and_then(|f| f.as_ref().ok()?.data_ref()) {
+            let rpc = &**this.rpc;
+            this.counter.count(data, |len| {
+                rpc.requests.fetch_add(1, Ordering::Relaxed);
+                rpc.record(rpc.instruments.request_size, f64::from(len));
+            });
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A response body that records the size of its messages, and the metrics of its RPC once it
+/// ends.
+#[pin_project]
+struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    counter: MessageCounter,
+    pending: Option<Pending>,
+}
+
+impl<B> http_body::Body for ResponseBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+
+        if let Some(pending) = this.pending {
+            match &frame {
+                Some(Ok(frame)) => {
+                    if let Some(data) = frame.data_ref() {
+                        let (rpc, responses) = (&*pending.rpc, &mut pending.responses);
+                        this.counter.count(data, |len| {
+                            *responses += 1;
+                            rpc.record(rpc.instruments.response_size, f64::from(len));
+                        });
+                    } else if let Some(trailers) = frame.trailers_ref() {
+                        pending.update_code(trailers);
+                    }
+                }
+                Some(Err(_)) => pending.code = Some(Code::Unknown),
+                None => {
+                    pending.code.get_or_insert(Code::Unknown);
+                    this.pending.take();
+                }
+            }
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::{BodyExt, StreamBody};
+    use std::{convert::Infallible, sync::Mutex};
+
+    type Recorded = Arc<Mutex<Vec<(Instrument, f64, Option<Code>)>>>;
+
+    fn recorder() -> (Recorded, impl MetricsRecorder) {
+        let recorded = Recorded::default();
+        let recorder = recorded.clone();
+        let recorder = move |instrument, value, attributes: &RpcAttributes| {
+            assert_eq!(attributes.service(), "test.Test");
+            assert_eq!(attributes.method(), "Stream");
+            let status = attributes.status_code();
+            recorder.lock().unwrap().push((instrument, value, status));
+        };
+        (recorded, recorder)
+    }
+
+    fn message(len: u8) -> Vec<u8> {
+        let mut message = vec![0, 0, 0, 0, len];
+        message.resize(5 + usize::from(len), 1);
+        message
+    }
+
+    #[test]
+    fn counts_messages_split_across_frames() {
+        let mut data = message(3);
+        data.extend(message(0));
+        data.extend(message(7));
+
+        let mut counter = MessageCounter::default();
+        let mut lengths = Vec::new();
+        for chunk in data.chunks(2) {
+            counter.count(chunk, |len| lengths.push(len));
+        }
+        assert_eq!(lengths, [3, 0, 7]);
+    }
+
+    #[tokio::test]
+    async fn server_records_messages_and_status() {
+        let (recorded, recorder) = recorder();
+        let mut svc = RpcMetricsLayer::server(recorder).layer(tower::service_fn(
+            |req: Request<Body>| async move {
+                req.into_body().collect().await.unwrap();
+
+                let mut trailers = HeaderMap::new();
+                trailers.insert(Status::GRPC_STATUS, "5".parse().unwrap());
+                let frames = [
+                    Frame::data(Bytes::from(message(4))),
+                    Frame::data(Bytes::from(message(2))),
+                    Frame::trailers(trailers),
+                ];
+                let body = StreamBody::new(tokio_stream::iter(frames.map(Ok::<_, Infallible>)));
+                Ok::<_, Infallible>(Response::new(body))
+            },
+        ));
+
+        let mut request = message(10);
+        request.extend(message(1));
+        request.extend(message(6));
+        let req = Request::builder()
+            .uri("/test.Test/Stream")
+            .body(Body::new(http_body_util::Full::new(Bytes::from(request))))
+            .unwrap();
+        let response = svc.call(req).await.unwrap();
+        response.into_body().collect().await.unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        let (duration, rest) = recorded[5..].split_first().unwrap();
+        assert_eq!(duration.0, Instrument::ServerDuration);
+        assert_eq!(duration.2, Some(Code::NotFound));
+        assert_eq!(
+            recorded[..5],
+            [
+                (Instrument::ServerRequestSize, 10.0, None),
+                (Instrument::ServerRequestSize, 1.0, None),
+                (Instrument::ServerRequestSize, 6.0, None),
+                (Instrument::ServerResponseSize, 4.0, None),
+                (Instrument::ServerResponseSize, 2.0, None),
+            ][..]
+        );
+        assert_eq!(
+            rest,
+            [
+                (Instrument::ServerRequestsPerRpc, 3.0, Some(Code::NotFound)),
+                (Instrument::ServerResponsesPerRpc, 2.0, Some(Code::NotFound)),
+            ]
+        );
+    }
+}
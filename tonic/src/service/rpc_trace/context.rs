@@ -0,0 +1,222 @@
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+thread_local! {
+    pub(super) static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+/// The trace context of an RPC: the ids of its trace and span, and the baggage propagated along
+/// the trace.
+///
+/// On the server, the context of each RPC traced by a [`RpcTraceLayer`] is inserted into the
+/// extensions of its request. Inserting a context into the extensions of an outgoing request
+/// makes the call continue that trace, e.g. from a task spawned by a handler.
+///
+/// [`RpcTraceLayer`]: super::RpcTraceLayer
+#[derive(Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+    baggage: Arc<Vec<(String, String)>>,
+}
+
+impl TraceContext {
+    /// Create a context from the ids of a trace and of a span within it, as read by a
+    /// [`Propagator`](super::Propagator). Returns `None` if either id is zero, which is invalid.
+    pub fn new(trace_id: u128, span_id: u64, sampled: bool) -> Option<Self> {
+        (trace_id != 0 && span_id != 0).then(|| TraceContext {
+            trace_id,
+            span_id,
+            flags: u8::from(sampled),
+            baggage: Arc::default(),
+        })
+    }
+
+    /// The context of the RPC being handled, while the handler of a server traced by a
+    /// [`RpcTraceLayer`](super::RpcTraceLayer) is polled.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Parses a [W3C](https://www.w3.org/TR/trace-context/) `traceparent` value, e.g.
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+
+        // Later versions may append fields, which are ignored.
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        u8::from_str_radix(version, 16).ok()?;
+        if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+
+        let mut context = TraceContext::new(
+            parse_hex(trace_id)?,
+            u64::try_from(parse_hex(span_id)?).ok()?,
+            false,
+        )?;
+        context.flags = u8::try_from(parse_hex(flags)?).ok()?;
+        Some(context)
+    }
+
+    /// Formats this context as a W3C `traceparent` value.
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+
+    /// The 16 bytes identifying the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The 8 bytes identifying the span of the RPC within its trace.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Whether the caller sampled the trace.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// The key-value pairs propagated along the trace, in the order they were added.
+    pub fn baggage(&self) -> &[(String, String)] {
+        &self.baggage
+    }
+
+    /// Adds a key-value pair to the baggage propagated along the trace, replacing any value with
+    /// the same key.
+    pub fn with_baggage(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let (key, value) = (key.into(), value.into());
+        let baggage = Arc::make_mut(&mut self.baggage);
+        match baggage.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => baggage.push((key, value)),
+        }
+        self
+    }
+
+    pub(super) fn new_root() -> Self {
+        TraceContext {
+            trace_id: u128::from(random_id()) << 64 | u128::from(random_id()),
+            span_id: random_id(),
+            flags: 1,
+            baggage: Arc::default(),
+        }
+    }
+
+    pub(super) fn child(&self) -> Self {
+        TraceContext {
+            span_id: random_id(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceContext")
+            .field("traceparent", &self.to_traceparent())
+            .field("baggage", &self.baggage)
+            .finish()
+    }
+}
+
+/// Parses lowercase hex digits, as required by the trace context format.
+fn parse_hex(digits: &str) -> Option<u128> {
+    if !digits
+        .bytes()
+        .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return None;
+    }
+    u128::from_str_radix(digits, 16).ok()
+}
+
+/// A random non-zero id.
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let id = hasher.finish();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parses_and_formats_traceparent() {
+        let context = TraceContext::from_traceparent(TRACEPARENT).unwrap();
+        assert_eq!(context.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.span_id(), 0x00f067aa0ba902b7);
+        assert!(context.is_sampled());
+        assert_eq!(context.to_traceparent(), TRACEPARENT);
+
+        let child = context.child();
+        assert_eq!(child.trace_id(), context.trace_id());
+        assert_ne!(child.span_id(), context.span_id());
+
+        // Later versions may have more fields.
+        let future = "cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra";
+        assert_eq!(TraceContext::from_traceparent(future), Some(context));
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::from_traceparent(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn baggage_is_inherited_by_children() {
+        let context = TraceContext::new_root()
+            .with_baggage("tenant", "a")
+            .with_baggage("user", "b")
+            .with_baggage("tenant", "c");
+        let child = context.child();
+        assert_eq!(
+            child.baggage(),
+            [
+                ("tenant".to_owned(), "c".to_owned()),
+                ("user".to_owned(), "b".to_owned())
+            ]
+        );
+    }
+}
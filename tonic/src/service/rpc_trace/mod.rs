@@ -0,0 +1,477 @@
+//! Middleware that creates a `tracing` span for every RPC, following the OpenTelemetry semantic
+//! conventions for gRPC, and propagates the W3C trace context in the `traceparent` metadata.
+//!
+//! See [`RpcTraceLayer`] for more details.
+
+mod context;
+mod propagation;
+
+pub use self::context::TraceContext;
+pub use self::propagation::{GrpcTraceBin, Propagator, W3cTraceContext};
+
+use self::context::CURRENT;
+use crate::{body::Body, metadata::MetadataMap, Code, Status};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{field::Empty, Span};
+
+/// The side of the RPCs a [`RpcTraceLayer`] traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Client,
+    Server,
+}
+
+/// A [`Layer`] that creates a span for every RPC and propagates its trace context.
+///
+/// The spans follow the OpenTelemetry semantic conventions for gRPC, so that they are exported
+/// as proper RPC spans by `tracing-opentelemetry`. They are created at the `INFO` level with the
+/// `tonic::rpc` target and have these fields:
+///
+/// - `otel.name`: the full method name, e.g. `helloworld.Greeter/SayHello`;
+/// - `otel.kind`: `server` or `client`;
+/// - `rpc.system`: always `grpc`;
+/// - `rpc.service` and `rpc.method`, e.g. `helloworld.Greeter` and `SayHello`;
+/// - `rpc.grpc.status_code`: the numeric status code, recorded once the RPC completes;
+/// - `otel.status_code`: `ERROR` if the RPC failed;
+/// - `trace_id`, `span_id` and `parent_span_id`: the W3C trace context of the RPC.
+///
+/// A span lasts until the response is fully sent or received, including the messages of a
+/// streaming response.
+///
+/// On the server, the trace context of the caller is read from the metadata of the request, or a
+/// new trace is started. The context of the RPC is inserted into the extensions of the request,
+/// and is [current](TraceContext::current) while the handler is polled. On the client, the
+/// context of the call continues the one found in the extensions of the request, or the current
+/// one, and is written to the metadata of the request. A server handler making calls with a
+/// client traced by this layer thus propagates the trace, and its baggage, automatically.
+///
+/// The context is propagated in the W3C `traceparent` and `baggage` metadata by default. Other
+/// formats, such as the `grpc-trace-bin` metadata of gRPC C-core and Java, are supported by adding
+/// their [`Propagator`].
+///
+/// The server layer is applied with `Server::layer`. The client layer must wrap the channel
+/// itself, rather than be added with `Endpoint::layer`, so that it runs in the task making the
+/// call:
+///
+/// ```
+/// # #[cfg(feature = "transport")]
+/// # fn example(channel: tonic::transport::Channel) {
+/// use tonic::service::RpcTraceLayer;
+/// use tower::ServiceBuilder;
+///
+/// let channel = ServiceBuilder::new()
+///     .layer(RpcTraceLayer::client())
+///     .service(channel);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RpcTraceLayer {
+    kind: Kind,
+    propagators: Vec<Arc<dyn Propagator>>,
+}
+
+impl RpcTraceLayer {
+    /// Create a layer tracing the RPCs served by a server.
+    pub fn server() -> Self {
+        RpcTraceLayer {
+            kind: Kind::Server,
+            propagators: Vec::new(),
+        }
+    }
+
+    /// Create a layer tracing the calls made by a client.
+    pub fn client() -> Self {
+        RpcTraceLayer {
+            kind: Kind::Client,
+            propagators: Vec::new(),
+        }
+    }
+
+    /// Adds a propagator of the trace context, replacing the default [`W3cTraceContext`] one.
+    ///
+    /// The context of an incoming request is read by the first propagator that finds one, and
+    /// the context of an outgoing request is written by all of them, e.g. to interoperate with
+    /// services using either format:
+    ///
+    /// ```
+    /// use tonic::service::rpc_trace::{GrpcTraceBin, RpcTraceLayer, W3cTraceContext};
+    ///
+    /// let layer = RpcTraceLayer::client()
+    ///     .propagator(W3cTraceContext)
+    ///     .propagator(GrpcTraceBin);
+    /// ```
+    pub fn propagator(mut self, propagator: impl Propagator) -> Self {
+        self.propagators.push(Arc::new(propagator));
+        self
+    }
+}
+
+impl<S> Layer<S> for RpcTraceLayer {
+    type Service = RpcTrace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcTrace {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that creates a span for every RPC and propagates its trace context.
+///
+/// See [`RpcTraceLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct RpcTrace<S> {
+    inner: S,
+    layer: RpcTraceLayer,
+}
+
+impl<S> RpcTrace<S> {
+    fn propagators(&self) -> impl Iterator<Item = &dyn Propagator> {
+        let default = self
+            .layer
+            .propagators
+            .is_empty()
+            .then_some(&W3cTraceContext as &dyn Propagator);
+        self.layer
+            .propagators
+            .iter()
+            .map(|propagator| &**propagator)
+            .chain(default)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RpcTrace<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let kind = self.layer.kind;
+        let mut metadata = MetadataMap::from_headers(mem::take(req.headers_mut()));
+
+        let parent = match kind {
+            Kind::Server => self
+                .propagators()
+                .find_map(|propagator| propagator.extract(&metadata)),
+            Kind::Client => req
+                .extensions()
+                .get::<TraceContext>()
+                .cloned()
+                .or_else(TraceContext::current),
+        };
+        let context = match &parent {
+            Some(parent) => parent.child(),
+            None => TraceContext::new_root(),
+        };
+
+        let span = rpc_span(kind, req.uri().path(), &context, parent.as_ref());
+        match kind {
+            Kind::Server => {
+                req.extensions_mut().insert(context.clone());
+            }
+            Kind::Client => {
+                for propagator in self.propagators() {
+                    propagator.inject(&context, &mut metadata);
+                }
+            }
+        }
+        *req.headers_mut() = metadata.into_headers();
+
+        let inner = {
+            let _entered = span.enter();
+            self.inner.call(req)
+        };
+        ResponseFuture {
+            inner,
+            span: Some(span),
+            context: (kind == Kind::Server).then_some(context),
+        }
+    }
+}
+
+fn rpc_span(kind: Kind, path: &str, context: &TraceContext, parent: Option<&TraceContext>) -> Span {
+    let name = path.strip_prefix('/').unwrap_or(path);
+    let (service, method) = name.split_once('/').unwrap_or((name, ""));
+    let trace_id = format_args!("{:032x}", context.trace_id());
+    let span_id = format_args!("{:016x}", context.span_id());
+    let parent_span_id = parent.map(|parent| format!("{:016x}", parent.span_id()));
+
+    match kind {
+        Kind::Server => tracing::info_span!(
+            target: "tonic::rpc",
+            "grpc.server",
+            otel.name = name,
+            otel.kind = "server",
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method,
+            rpc.grpc.status_code = Empty,
+            otel.status_code = Empty,
+            trace_id = %trace_id,
+            span_id = %span_id,
+            parent_span_id = parent_span_id,
+        ),
+        Kind::Client => tracing::info_span!(
+            target: "tonic::rpc",
+            "grpc.client",
+            otel.name = name,
+            otel.kind = "client",
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method,
+            rpc.grpc.status_code = Empty,
+            otel.status_code = Empty,
+            trace_id = %trace_id,
+            span_id = %span_id,
+            parent_span_id = parent_span_id,
+        ),
+    }
+}
+
+/// Records the status code an RPC completed with on its span.
+fn record_code(span: &Span, code: Code) {
+    span.record("rpc.grpc.status_code", code as i32);
+    if code != Code::Ok {
+        span.record("otel.status_code", "ERROR");
+    }
+}
+
+/// Response future for [`RpcTrace`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    span: Option<Span>,
+    /// The trace context made current while the future is polled, on the server.
+    context: Option<TraceContext>,
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = {
+            let _entered = this.span.as_ref().map(Span::enter);
+            let previous = CURRENT.with(|current| current.replace(this.context.clone()));
+            let _restore = Restore(previous);
+            ready!(this.inner.poll(cx))
+        };
+        let span = this.span.take().expect("polled after completion");
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                record_code(&span, Code::Unknown);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        // A trailers-only response carries its status in its headers.
+        if let Some(code) = grpc_status(response.headers()) {
+            record_code(&span, code);
+            return Poll::Ready(Ok(response.map(Body::new)));
+        }
+
+        Poll::Ready(Ok(response.map(|inner| {
+            Body::new(RpcTraceBody {
+                inner,
+                span: Some(span),
+            })
+        })))
+    }
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<Code> {
+    headers
+        .get(Status::GRPC_STATUS)
+        .map(|code| Code::from_bytes(code.as_bytes()))
+}
+
+/// Restores the previous trace context when dropped, even if polling panicked.
+struct Restore(Option<TraceContext>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// A response body that keeps the span of its RPC open until it ends, and records its status.
+#[pin_project]
+struct RpcTraceBody<B> {
+    #[pin]
+    inner: B,
+    span: Option<Span>,
+}
+
+impl<B> http_body::Body for RpcTraceBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = {
+            let _entered = this.span.as_ref().map(Span::enter);
+            ready!(this.inner.poll_frame(cx))
+        };
+
+        if let Some(span) = this.span {
+            match &frame {
+                Some(Ok(frame)) => {
+                    if let Some(code) = frame.trailers_ref().and_then(grpc_status) {
+                        record_code(span, code);
+                        this.span.take();
+                    }
+                }
+                Some(Err(_)) | None => {
+                    record_code(span, Code::Unknown);
+                    this.span.take();
+                }
+            }
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    const TRACEPARENT: &str = "traceparent";
+    const TRACEPARENT_VALUE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[tokio::test]
+    async fn server_continues_trace_of_caller() {
+        let mut svc =
+            RpcTraceLayer::server().layer(tower::service_fn(|req: Request<Body>| async move {
+                let context = req.extensions().get::<TraceContext>().unwrap().clone();
+                assert_eq!(TraceContext::current().as_ref(), Some(&context));
+                Ok::<_, crate::BoxError>(Response::new(Body::new(context.to_traceparent())))
+            }));
+
+        let req = Request::builder()
+            .uri("/test.Test/UnaryCall")
+            .header(TRACEPARENT, TRACEPARENT_VALUE)
+            .body(Body::empty())
+            .unwrap();
+        let response = svc.call(req).await.unwrap();
+        assert_eq!(TraceContext::current(), None);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let context = TraceContext::from_traceparent(std::str::from_utf8(&body).unwrap()).unwrap();
+        let parent = TraceContext::from_traceparent(TRACEPARENT_VALUE).unwrap();
+        assert_eq!(context.trace_id(), parent.trace_id());
+        assert_ne!(context.span_id(), parent.span_id());
+    }
+
+    #[tokio::test]
+    async fn client_sends_traceparent() {
+        let mut svc =
+            RpcTraceLayer::client().layer(tower::service_fn(|req: Request<Body>| async move {
+                let traceparent = req.headers()[TRACEPARENT].to_str().unwrap().to_owned();
+                Ok::<_, crate::BoxError>(Response::new(Body::new(traceparent)))
+            }));
+        let traceparent = |response: Response<Body>| async move {
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            TraceContext::from_traceparent(std::str::from_utf8(&body).unwrap()).unwrap()
+        };
+
+        let root = traceparent(svc.call(Request::new(Body::empty())).await.unwrap()).await;
+        assert!(root.is_sampled());
+
+        let parent = TraceContext::from_traceparent(TRACEPARENT_VALUE).unwrap();
+        let mut req = Request::new(Body::empty());
+        req.extensions_mut().insert(parent.clone());
+        let child = traceparent(svc.call(req).await.unwrap()).await;
+        assert_eq!(child.trace_id(), parent.trace_id());
+        assert_ne!(child.span_id(), parent.span_id());
+        assert_ne!(child.trace_id(), root.trace_id());
+    }
+
+    #[tokio::test]
+    async fn propagators_replace_w3c_default() {
+        let layer = RpcTraceLayer::client().propagator(GrpcTraceBin);
+        let mut svc = layer.layer(tower::service_fn(|req: Request<Body>| async move {
+            assert!(!req.headers().contains_key(TRACEPARENT));
+            let metadata = MetadataMap::from_headers(req.headers().clone());
+            let context = GrpcTraceBin.extract(&metadata).unwrap();
+            Ok::<_, crate::BoxError>(Response::new(Body::new(context.to_traceparent())))
+        }));
+
+        let parent = TraceContext::from_traceparent(TRACEPARENT_VALUE).unwrap();
+        let mut req = Request::new(Body::empty());
+        req.extensions_mut().insert(parent.clone());
+        let response = svc.call(req).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let context = TraceContext::from_traceparent(std::str::from_utf8(&body).unwrap()).unwrap();
+        assert_eq!(context.trace_id(), parent.trace_id());
+
+        // The first propagator finding a context wins.
+        let layer = RpcTraceLayer::server()
+            .propagator(W3cTraceContext)
+            .propagator(GrpcTraceBin);
+        let mut svc = layer.layer(tower::service_fn(|req: Request<Body>| async move {
+            let context = req.extensions().get::<TraceContext>().unwrap().clone();
+            Ok::<_, crate::BoxError>(Response::new(Body::new(context.to_traceparent())))
+        }));
+        let mut metadata = MetadataMap::new();
+        GrpcTraceBin.inject(&parent, &mut metadata);
+        let mut req = Request::new(Body::empty());
+        *req.headers_mut() = metadata.into_headers();
+        let response = svc.call(req).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let context = TraceContext::from_traceparent(std::str::from_utf8(&body).unwrap()).unwrap();
+        assert_eq!(context.trace_id(), parent.trace_id());
+    }
+}
@@ -0,0 +1,316 @@
+use super::TraceContext;
+use crate::metadata::{MetadataMap, MetadataValue};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::fmt;
+
+const TRACEPARENT: &str = "traceparent";
+const BAGGAGE: &str = "baggage";
+const GRPC_TRACE_BIN: &str = "grpc-trace-bin";
+const GRPC_TAGS_BIN: &str = "grpc-tags-bin";
+
+/// The characters escaped in the values of the `baggage` metadata.
+const BAGGAGE_ENCODING_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'%')
+    .add(b',')
+    .add(b';')
+    .add(b'\\');
+
+/// Reads and writes the trace context of RPCs in their metadata.
+///
+/// A [`RpcTraceLayer`](super::RpcTraceLayer) extracts the context of incoming requests and
+/// injects the context of outgoing requests with its propagators, which allows interoperating
+/// with services that use a different format than the W3C trace context.
+pub trait Propagator: fmt::Debug + Send + Sync + 'static {
+    /// Reads the trace context of an incoming request, if it has one.
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext>;
+
+    /// Writes the trace context of an outgoing request.
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap);
+}
+
+/// Propagates the trace context in the [W3C](https://www.w3.org/TR/trace-context/)
+/// `traceparent` metadata, and its baggage in the [W3C](https://www.w3.org/TR/baggage/)
+/// `baggage` metadata.
+///
+/// This is the propagator of a [`RpcTraceLayer`](super::RpcTraceLayer) unless others are added.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct W3cTraceContext;
+
+impl Propagator for W3cTraceContext {
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext> {
+        let traceparent = metadata.get(TRACEPARENT)?.to_str().ok()?;
+        let mut context = TraceContext::from_traceparent(traceparent)?;
+
+        for value in metadata.get_all(BAGGAGE) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for member in value.split(',') {
+                // Properties of the members are not supported and dropped.
+                let member = member.split(';').next().unwrap_or_default();
+                let Some((key, value)) = member.split_once('=') else {
+                    continue;
+                };
+                let key = key.trim();
+                let Ok(value) = percent_decode_str(value.trim()).decode_utf8() else {
+                    continue;
+                };
+                if !key.is_empty() {
+                    context = context.with_baggage(key, value);
+                }
+            }
+        }
+
+        Some(context)
+    }
+
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap) {
+        let traceparent = MetadataValue::try_from(context.to_traceparent())
+            .expect("traceparent is a valid metadata value");
+        metadata.insert(TRACEPARENT, traceparent);
+
+        let baggage = context
+            .baggage()
+            .iter()
+            .map(|(key, value)| {
+                format!("{key}={}", utf8_percent_encode(value, BAGGAGE_ENCODING_SET))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        match MetadataValue::try_from(baggage) {
+            Ok(baggage) if !baggage.is_empty() => {
+                metadata.insert(BAGGAGE, baggage);
+            }
+            _ => {
+                metadata.remove(BAGGAGE);
+            }
+        }
+    }
+}
+
+/// Propagates the trace context in the binary `grpc-trace-bin` metadata, and its baggage in the
+/// binary `grpc-tags-bin` metadata.
+///
+/// These are the formats of the OpenCensus integrations of gRPC C-core and gRPC Java, for
+/// interoperating with services that do not support the W3C trace context.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcTraceBin;
+
+impl GrpcTraceBin {
+    const TRACE_ID: u8 = 0;
+    const SPAN_ID: u8 = 1;
+    const OPTIONS: u8 = 2;
+    const TAG: u8 = 0;
+}
+
+impl Propagator for GrpcTraceBin {
+    fn extract(&self, metadata: &MetadataMap) -> Option<TraceContext> {
+        let bytes = metadata.get_bin(GRPC_TRACE_BIN)?.to_bytes().ok()?;
+        let mut context = decode_trace(&bytes)?;
+
+        let tags = metadata
+            .get_bin(GRPC_TAGS_BIN)
+            .and_then(|value| value.to_bytes().ok());
+        for (key, value) in tags.as_deref().map(decode_tags).unwrap_or_default() {
+            context = context.with_baggage(key, value);
+        }
+
+        Some(context)
+    }
+
+    fn inject(&self, context: &TraceContext, metadata: &mut MetadataMap) {
+        let mut trace = Vec::with_capacity(29);
+        trace.push(0);
+        trace.push(Self::TRACE_ID);
+        trace.extend_from_slice(&context.trace_id().to_be_bytes());
+        trace.push(Self::SPAN_ID);
+        trace.extend_from_slice(&context.span_id().to_be_bytes());
+        trace.push(Self::OPTIONS);
+        trace.push(u8::from(context.is_sampled()));
+        metadata.insert_bin(GRPC_TRACE_BIN, MetadataValue::from_bytes(&trace));
+
+        if context.baggage().is_empty() {
+            metadata.remove_bin(GRPC_TAGS_BIN);
+            return;
+        }
+        let mut tags = vec![0];
+        for (key, value) in context.baggage() {
+            tags.push(Self::TAG);
+            for bytes in [key.as_bytes(), value.as_bytes()] {
+                put_varint(&mut tags, bytes.len() as u64);
+                tags.extend_from_slice(bytes);
+            }
+        }
+        metadata.insert_bin(GRPC_TAGS_BIN, MetadataValue::from_bytes(&tags));
+    }
+}
+
+/// Decodes a `grpc-trace-bin` value: a version byte followed by fields, each made of an id byte
+/// and a fixed size value. Unknown fields end the value, as their size is not known.
+fn decode_trace(mut bytes: &[u8]) -> Option<TraceContext> {
+    let (_version, rest) = bytes.split_first()?;
+    bytes = rest;
+
+    let (mut trace_id, mut span_id, mut options) = (None, None, 0);
+    while let Some((&field, rest)) = bytes.split_first() {
+        match field {
+            GrpcTraceBin::TRACE_ID if rest.len() >= 16 => {
+                trace_id = Some(u128::from_be_bytes(rest[..16].try_into().unwrap()));
+                bytes = &rest[16..];
+            }
+            GrpcTraceBin::SPAN_ID if rest.len() >= 8 => {
+                span_id = Some(u64::from_be_bytes(rest[..8].try_into().unwrap()));
+                bytes = &rest[8..];
+            }
+            GrpcTraceBin::OPTIONS if !rest.is_empty() => {
+                options = rest[0];
+                bytes = &rest[1..];
+            }
+            _ => break,
+        }
+    }
+
+    TraceContext::new(trace_id?, span_id?, options & 1 != 0)
+}
+
+/// Decodes a `grpc-tags-bin` value: a version byte followed by tags, each made of a field id and
+/// a varint length prefixed key and value. Decoding stops at the first malformed tag.
+fn decode_tags(mut bytes: &[u8]) -> Vec<(String, String)> {
+    let mut tags = Vec::new();
+    let Some((_version, rest)) = bytes.split_first() else {
+        return tags;
+    };
+    bytes = rest;
+
+    while let Some((&GrpcTraceBin::TAG, rest)) = bytes.split_first() {
+        bytes = rest;
+        let (Some(key), Some(value)) = (get_string(&mut bytes), get_string(&mut bytes)) else {
+            break;
+        };
+        tags.push((key, value));
+    }
+    tags
+}
+
+fn get_string(bytes: &mut &[u8]) -> Option<String> {
+    let len = usize::try_from(get_varint(bytes)?).ok()?;
+    if bytes.len() < len {
+        return None;
+    }
+    let (string, rest) = bytes.split_at(len);
+    *bytes = rest;
+    String::from_utf8(string.to_vec()).ok()
+}
+
+fn get_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TraceContext {
+        TraceContext::from_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+            .unwrap()
+    }
+
+    #[test]
+    fn w3c_round_trips_baggage() {
+        let context = context()
+            .with_baggage("tenant", "acme corp")
+            .with_baggage("region", "eu,west;1");
+
+        let mut metadata = MetadataMap::new();
+        W3cTraceContext.inject(&context, &mut metadata);
+        assert_eq!(
+            metadata.get(BAGGAGE).unwrap(),
+            "tenant=acme%20corp,region=eu%2Cwest%3B1"
+        );
+        assert_eq!(W3cTraceContext.extract(&metadata), Some(context));
+    }
+
+    #[test]
+    fn w3c_ignores_baggage_properties() {
+        let mut metadata = MetadataMap::new();
+        W3cTraceContext.inject(&context(), &mut metadata);
+        assert!(!metadata.contains_key(BAGGAGE));
+        metadata.insert(BAGGAGE, "a = 1;prop=x, b=2,invalid".parse().unwrap());
+        metadata.append(BAGGAGE, "c=3".parse().unwrap());
+
+        let baggage = W3cTraceContext
+            .extract(&metadata)
+            .unwrap()
+            .baggage()
+            .to_vec();
+        assert_eq!(
+            baggage,
+            [
+                ("a".to_owned(), "1".to_owned()),
+                ("b".to_owned(), "2".to_owned()),
+                ("c".to_owned(), "3".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn grpc_trace_bin_matches_opencensus_format() {
+        let mut metadata = MetadataMap::new();
+        GrpcTraceBin.inject(&context(), &mut metadata);
+
+        // The example of the OpenCensus binary format specification.
+        let expected = [
+            0, 0, 0x4b, 0xf9, 0x2f, 0x35, 0x77, 0xb3, 0x4d, 0xa6, 0xa3, 0xce, 0x92, 0x9d, 0x0e,
+            0x0e, 0x47, 0x36, 1, 0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7, 2, 1,
+        ];
+        let bytes = metadata
+            .get_bin(GRPC_TRACE_BIN)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert_eq!(&bytes[..], expected);
+        assert!(!metadata.contains_key(GRPC_TAGS_BIN));
+        assert_eq!(GrpcTraceBin.extract(&metadata), Some(context()));
+
+        // Unknown fields end the value.
+        let mut extended = expected.to_vec();
+        extended.extend_from_slice(&[9, 1, 2, 3]);
+        metadata.insert_bin(GRPC_TRACE_BIN, MetadataValue::from_bytes(&extended));
+        assert_eq!(GrpcTraceBin.extract(&metadata), Some(context()));
+
+        metadata.insert_bin(GRPC_TRACE_BIN, MetadataValue::from_bytes(&expected[..20]));
+        assert_eq!(GrpcTraceBin.extract(&metadata), None);
+    }
+
+    #[test]
+    fn grpc_tags_bin_round_trips_baggage() {
+        let context = context()
+            .with_baggage("tenant", "acme")
+            .with_baggage("k".repeat(200), "");
+
+        let mut metadata = MetadataMap::new();
+        GrpcTraceBin.inject(&context, &mut metadata);
+        let tags = metadata.get_bin(GRPC_TAGS_BIN).unwrap().to_bytes().unwrap();
+        assert_eq!(&tags[..9], [0, 0, 6, b't', b'e', b'n', b'a', b'n', b't']);
+        assert_eq!(GrpcTraceBin.extract(&metadata), Some(context));
+    }
+}
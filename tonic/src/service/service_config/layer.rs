@@ -0,0 +1,784 @@
+use super::{MethodConfig, RetryPolicy, RetryThrottling, ServiceConfig};
+use crate::{
+    body::Body,
+    client::SingleMessageRequest,
+    metadata::GRPC_TIMEOUT_HEADER,
+    request::duration_to_grpc_timeout,
+    service::grpc_timeout::try_parse_grpc_timeout,
+    time::{Clock, SharedClock, Sleep},
+    Code, Status, TimeoutExpired,
+};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Request, Response};
+use http_body::Frame;
+use http_body_util::{BodyExt, Full};
+use pin_project::pin_project;
+use std::{
+    collections::hash_map::RandomState,
+    future::{poll_fn, Future},
+    hash::{BuildHasher, Hasher},
+    pin::{pin, Pin},
+    sync::{Arc, Mutex, RwLock},
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const GRPC_RETRY_PUSHBACK_HEADER: &str = "grpc-retry-pushback-ms";
+const GRPC_PREVIOUS_RPC_ATTEMPTS_HEADER: &str = "grpc-previous-rpc-attempts";
+
+/// Size of the length-prefixed message header in the gRPC framing.
+const HEADER_SIZE: usize = 5;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// A [`Layer`] that applies the per-method settings of a [`ServiceConfig`].
+///
+/// For each request the [`MethodConfig`] matching its path is looked up and:
+///
+/// - its `timeout` is enforced like [`GrpcTimeoutLayer::client`] does, including across retries;
+/// - response messages larger than `maxResponseMessageBytes` fail the call with
+///   `RESOURCE_EXHAUSTED`;
+/// - unary and server streaming requests are buffered so that they can be checked against
+///   `maxRequestMessageBytes` and retried according to the `retryPolicy`.
+///
+/// Client and bidirectional streaming requests are never buffered, so they are neither retried
+/// nor checked against `maxRequestMessageBytes`. A call is only retried when the server answers
+/// with a retryable status and no response message, as the gRPC retry design requires. Retries
+/// carry the number of previous attempts in their `grpc-previous-rpc-attempts` metadata.
+///
+/// When the config sets a `retryThrottling` policy, the calls of every method share a token
+/// bucket as the gRPC retry design describes: each call failing with a retryable status takes a
+/// token, each call completing with `OK` gives back `tokenRatio` of one, and retries stop as
+/// long as no more than half of `maxTokens` are left. This keeps retries from amplifying the
+/// load of a backend that is failing globally. The bucket is shared by the services built by the
+/// same layer, so the layer should be applied once per channel.
+///
+/// The config can be replaced at any time with [`ServiceConfigLayer::update`], for instance
+/// when a resolver pushes a new one. The change applies to every service built by this layer.
+///
+/// [`GrpcTimeoutLayer::client`]: crate::service::GrpcTimeoutLayer::client
+#[derive(Debug, Clone)]
+pub struct ServiceConfigLayer {
+    shared: Arc<Shared>,
+    clock: SharedClock,
+}
+
+impl ServiceConfigLayer {
+    /// Create a new layer applying `config`.
+    pub fn new(config: ServiceConfig) -> Self {
+        let throttle = config.retry_throttling().map(Throttle::new);
+        ServiceConfigLayer {
+            shared: Arc::new(Shared {
+                config: RwLock::new(Arc::new(config)),
+                throttle: Mutex::new(throttle),
+            }),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Replace the config applied by this layer and every service it built.
+    ///
+    /// The retry throttling state is kept unless the throttling policy changed.
+    pub fn update(&self, config: ServiceConfig) {
+        let mut throttle = self.shared.throttle.lock().unwrap();
+        if throttle.as_ref().map(|t| t.policy) != config.retry_throttling().copied() {
+            *throttle = config.retry_throttling().map(Throttle::new);
+        }
+        *self.shared.config.write().unwrap() = Arc::new(config);
+    }
+
+    /// The number of tokens left in the retry throttling bucket, or `None` if the config does
+    /// not throttle retries.
+    pub fn retry_tokens(&self) -> Option<f64> {
+        let throttle = self.shared.throttle.lock().unwrap();
+        throttle.as_ref().map(|throttle| throttle.tokens)
+    }
+
+    /// Sets the [`Clock`] used for deadlines and retry backoff. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        ServiceConfigLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for ServiceConfigLayer {
+    type Service = ApplyServiceConfig<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApplyServiceConfig {
+            inner,
+            shared: self.shared.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: RwLock<Arc<ServiceConfig>>,
+    throttle: Mutex<Option<Throttle>>,
+}
+
+impl Shared {
+    fn is_throttling(&self) -> bool {
+        self.throttle.lock().unwrap().is_some()
+    }
+
+    fn record_success(&self) {
+        if let Some(throttle) = self.throttle.lock().unwrap().as_mut() {
+            throttle.tokens =
+                (throttle.tokens + throttle.policy.token_ratio()).min(throttle.policy.max_tokens());
+        }
+    }
+
+    /// Records a retryable failure and returns whether a retry is allowed.
+    fn record_failure(&self) -> bool {
+        match self.throttle.lock().unwrap().as_mut() {
+            Some(throttle) => {
+                throttle.tokens = (throttle.tokens - 1.0).max(0.0);
+                throttle.tokens > throttle.policy.max_tokens() / 2.0
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Throttle {
+    policy: RetryThrottling,
+    tokens: f64,
+}
+
+impl Throttle {
+    fn new(policy: &RetryThrottling) -> Self {
+        Throttle {
+            policy: *policy,
+            tokens: policy.max_tokens(),
+        }
+    }
+}
+
+/// Middleware that applies the per-method settings of a [`ServiceConfig`].
+///
+/// See [`ServiceConfigLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct ApplyServiceConfig<S> {
+    inner: S,
+    shared: Arc<Shared>,
+    clock: SharedClock,
+}
+
+impl<S> ApplyServiceConfig<S> {
+    /// Create a new [`ApplyServiceConfig`] applying `config` to the requests sent to `inner`.
+    pub fn new(inner: S, config: ServiceConfig) -> Self {
+        ServiceConfigLayer::new(config).layer(inner)
+    }
+}
+
+impl<S, B> Service<Request<Body>> for ApplyServiceConfig<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::BoxError>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+
+        let config = self.shared.config.read().unwrap().clone();
+        let Some(method) = config.method_config(req.uri().path()).cloned() else {
+            let mut inner = inner;
+            let future = inner.call(req);
+            let shared = self.shared.clone();
+            return Box::pin(async move {
+                let res = future.await.map_err(Into::into)?;
+                Ok(track_success(res.map(Body::new), &shared))
+            });
+        };
+
+        let timeout = method.timeout().map(|timeout| {
+            let header = try_parse_grpc_timeout(req.headers()).ok().flatten();
+            match header {
+                Some(header) if header <= timeout => header,
+                _ => {
+                    set_grpc_timeout(req.headers_mut(), timeout);
+                    timeout
+                }
+            }
+        });
+
+        let call = Call {
+            inner,
+            method,
+            shared: self.shared.clone(),
+            clock: self.clock.clone(),
+        };
+
+        match timeout {
+            Some(timeout) => {
+                let sleep = self.clock.sleep(timeout);
+                Box::pin(with_deadline(call.run(req), sleep))
+            }
+            None => Box::pin(call.run(req)),
+        }
+    }
+}
+
+struct Call<S> {
+    inner: S,
+    method: MethodConfig,
+    shared: Arc<Shared>,
+    clock: SharedClock,
+}
+
+impl<S, B> Call<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::BoxError>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    async fn run(self, req: Request<Body>) -> Result<Response<Body>, crate::BoxError> {
+        let Call {
+            inner,
+            method,
+            shared,
+            clock,
+        } = self;
+
+        let buffer = req.extensions().get::<SingleMessageRequest>().is_some()
+            && (method.retry_policy().is_some() || method.max_request_message_bytes().is_some());
+        let respond = |res| track_success(limit_response(res, &method), &shared);
+
+        if !buffer {
+            let mut inner = inner;
+            let res = inner.call(req).await.map_err(Into::into)?;
+            return Ok(respond(res));
+        }
+
+        let (parts, body) = req.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        if let Some(max) = method.max_request_message_bytes() {
+            if let Err(len) = FrameScanner::new(max).scan(&body) {
+                return Ok(message_too_large(len, max).into_http());
+            }
+        }
+
+        let mut backoff = method
+            .retry_policy()
+            .map(RetryPolicy::initial_backoff)
+            .unwrap_or_default();
+        let mut attempt = 1;
+        // The first attempt uses the service that was driven to readiness, retries use clones.
+        let template = inner.clone();
+        let mut ready = Some(inner);
+
+        loop {
+            let mut svc = match ready.take() {
+                Some(svc) => svc,
+                None => {
+                    let mut svc = template.clone();
+                    poll_fn(|cx| svc.poll_ready(cx)).await.map_err(Into::into)?;
+                    svc
+                }
+            };
+
+            let mut req = Request::from_parts(parts.clone(), Body::new(Full::new(body.clone())));
+            if attempt > 1 {
+                req.headers_mut().insert(
+                    GRPC_PREVIOUS_RPC_ATTEMPTS_HEADER,
+                    HeaderValue::from(attempt - 1),
+                );
+            }
+            let res = svc.call(req).await.map_err(Into::into)?;
+
+            let Some(policy) = method.retry_policy() else {
+                return Ok(respond(res));
+            };
+
+            let retryable = Status::from_header_map(res.headers())
+                .is_some_and(|status| policy.retryable_status_codes().contains(&status.code()));
+            if !retryable {
+                return Ok(respond(res));
+            }
+
+            let throttled = !shared.record_failure();
+            if throttled {
+                tracing::debug!(attempt, "retry throttled");
+            }
+            if throttled || attempt >= policy.max_attempts() {
+                return Ok(respond(res));
+            }
+
+            let delay = match retry_pushback(res.headers()) {
+                Some(Some(pushback)) => {
+                    backoff = policy.initial_backoff();
+                    pushback
+                }
+                // The server asked us not to retry.
+                Some(None) => return Ok(respond(res)),
+                None => {
+                    let delay = backoff.mul_f64(jitter());
+                    backoff = backoff
+                        .mul_f64(policy.backoff_multiplier())
+                        .min(policy.max_backoff());
+                    delay
+                }
+            };
+
+            tracing::debug!(attempt, ?delay, "retrying call");
+            clock.sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+async fn with_deadline<F, T>(future: F, mut sleep: Sleep) -> Result<T, crate::BoxError>
+where
+    F: Future<Output = Result<T, crate::BoxError>>,
+{
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        if let Poll::Ready(res) = future.as_mut().poll(cx) {
+            return Poll::Ready(res);
+        }
+        ready!(sleep.as_mut().poll(cx));
+        Poll::Ready(Err(TimeoutExpired(()).into()))
+    })
+    .await
+}
+
+fn set_grpc_timeout(headers: &mut HeaderMap, timeout: Duration) {
+    let value = HeaderValue::try_from(duration_to_grpc_timeout(timeout))
+        .expect("grpc-timeout is always a valid header value");
+    headers.insert(GRPC_TIMEOUT_HEADER, value);
+}
+
+/// Parses `grpc-retry-pushback-ms`: `None` if absent, `Some(None)` if the server asked not to
+/// retry, and `Some(Some(delay))` otherwise.
+fn retry_pushback(headers: &HeaderMap) -> Option<Option<Duration>> {
+    let value = headers.get(GRPC_RETRY_PUSHBACK_HEADER)?;
+    Some(
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_millis),
+    )
+}
+
+/// A random factor in `[0, 1)` used to spread retries out.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+fn message_too_large(len: usize, max: usize) -> Status {
+    Status::resource_exhausted(format!(
+        "message length too large: found {len} bytes, the limit is: {max} bytes"
+    ))
+}
+
+fn limit_response<B>(res: Response<B>, method: &MethodConfig) -> Response<Body>
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    match method.max_response_message_bytes() {
+        Some(max) => res.map(|body| {
+            Body::new(LimitMessageSize {
+                inner: body,
+                scanner: FrameScanner::new(max),
+            })
+        }),
+        None => res.map(Body::new),
+    }
+}
+
+/// Gives back a token to the retry throttling bucket once `res` completes with `OK`.
+fn track_success(res: Response<Body>, shared: &Arc<Shared>) -> Response<Body> {
+    if !shared.is_throttling() {
+        return res;
+    }
+
+    // A trailers-only response carries its status in its headers.
+    if let Some(code) = grpc_status(res.headers()) {
+        if code == Code::Ok {
+            shared.record_success();
+        }
+        return res;
+    }
+
+    res.map(|inner| {
+        Body::new(TrackSuccess {
+            inner,
+            shared: Some(shared.clone()),
+        })
+    })
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<Code> {
+    let code = headers.get(Status::GRPC_STATUS)?;
+    Some(Code::from_bytes(code.as_bytes()))
+}
+
+/// A response body that records its call as successful if its trailers carry an `OK` status.
+#[pin_project]
+struct TrackSuccess<B> {
+    #[pin]
+    inner: B,
+    shared: Option<Arc<Shared>>,
+}
+
+impl<B> http_body::Body for TrackSuccess<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(trailers) = frame.as_ref().and_then(|f| f.as_ref().ok()?.trailers_ref()) {
+            if let Some(shared) = this.shared.take() {
+                if grpc_status(trailers) == Some(Code::Ok) {
+                    shared.record_success();
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Follows the length-prefixed message framing of a gRPC body and checks each message length.
+#[derive(Debug)]
+struct FrameScanner {
+    max: usize,
+    header: [u8; HEADER_SIZE],
+    header_len: usize,
+    remaining: usize,
+}
+
+impl FrameScanner {
+    fn new(max: usize) -> Self {
+        FrameScanner {
+            max,
+            header: [0; HEADER_SIZE],
+            header_len: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Scans the next chunk of the body, failing with the message length if it exceeds the limit.
+    fn scan(&mut self, mut data: &[u8]) -> Result<(), usize> {
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(data.len());
+                self.remaining -= n;
+                data = &data[n..];
+                continue;
+            }
+
+            let n = (HEADER_SIZE - self.header_len).min(data.len());
+            self.header[self.header_len..self.header_len + n].copy_from_slice(&data[..n]);
+            self.header_len += n;
+            data = &data[n..];
+
+            if self.header_len == HEADER_SIZE {
+                self.header_len = 0;
+                let len = u32::from_be_bytes([
+                    self.header[1],
+                    self.header[2],
+                    self.header[3],
+                    self.header[4],
+                ]) as usize;
+                if len > self.max {
+                    return Err(len);
+                }
+                self.remaining = len;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[pin_project]
+struct LimitMessageSize<B> {
+    #[pin]
+    inner: B,
+    scanner: FrameScanner,
+}
+
+impl<B> http_body::Body for LimitMessageSize<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<crate::BoxError>,
+{
+    type Data = Bytes;
+    type Error = crate::BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+            None => return Poll::Ready(None),
+        };
+
+        if let Some(data) = frame.data_ref() {
+            if let Err(len) = this.scanner.scan(data) {
+                let status = message_too_large(len, this.scanner.max);
+                return Poll::Ready(Some(Err(status.into())));
+            }
+        }
+
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::StreamBody;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn frame(len: usize) -> Vec<u8> {
+        let mut buf = vec![0];
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+        buf.resize(HEADER_SIZE + len, 1);
+        buf
+    }
+
+    #[test]
+    fn scanner_handles_split_frames() {
+        let mut data = frame(3);
+        data.extend(frame(10));
+
+        let mut scanner = FrameScanner::new(10);
+        for chunk in data.chunks(2) {
+            scanner.scan(chunk).unwrap();
+        }
+
+        let mut scanner = FrameScanner::new(5);
+        let mut result = Ok(());
+        for chunk in data.chunks(4) {
+            result = result.and_then(|_| scanner.scan(chunk));
+        }
+        assert_eq!(result, Err(10));
+    }
+
+    #[derive(Clone)]
+    struct Flaky {
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    impl Service<Request<Body>> for Flaky {
+        type Response = Response<Body>;
+        type Error = crate::BoxError;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<Body>) -> Self::Future {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let failures = self.failures;
+            let previous_attempts = req
+                .headers()
+                .get(GRPC_PREVIOUS_RPC_ATTEMPTS_HEADER)
+                .map(|value| value.to_str().unwrap().parse::<usize>().unwrap());
+            assert_eq!(previous_attempts, (call > 0).then_some(call));
+            Box::pin(async move {
+                let body = req.into_body().collect().await?.to_bytes();
+                assert_eq!(body.len(), HEADER_SIZE + 3);
+
+                if call < failures {
+                    return Ok(Status::unavailable("try again").into_http());
+                }
+
+                let mut trailers = HeaderMap::new();
+                trailers.insert(Status::GRPC_STATUS, HeaderValue::from(0));
+                let frames = [
+                    Frame::data(Bytes::from(frame(3))),
+                    Frame::trailers(trailers),
+                ];
+                let body = StreamBody::new(tokio_stream::iter(frames.map(Ok::<_, Status>)));
+                Ok(Response::new(Body::new(body)))
+            })
+        }
+    }
+
+    fn unary_request() -> Request<Body> {
+        let mut req = Request::new(Body::new(Full::new(Bytes::from(frame(3)))));
+        *req.uri_mut() = "/test.Service/Call".parse().unwrap();
+        req.extensions_mut().insert(SingleMessageRequest);
+        req
+    }
+
+    const RETRY_CONFIG: &str = r#"{
+        "methodConfig": [{
+            "name": [{ "service": "test.Service" }],
+            "retryPolicy": {
+                "maxAttempts": 3,
+                "initialBackoff": "0.001s",
+                "maxBackoff": "0.001s",
+                "backoffMultiplier": 1,
+                "retryableStatusCodes": ["UNAVAILABLE"]
+            }
+        }]
+    }"#;
+
+    #[tokio::test]
+    async fn retries_retryable_status() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = ServiceConfig::from_json(RETRY_CONFIG).unwrap();
+        let mut svc = ApplyServiceConfig::new(
+            Flaky {
+                calls: calls.clone(),
+                failures: 2,
+            },
+            config,
+        );
+
+        let res = svc.call(unary_request()).await.unwrap();
+        assert!(Status::from_header_map(res.headers()).is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = ServiceConfig::from_json(RETRY_CONFIG).unwrap();
+        let mut svc = ApplyServiceConfig::new(
+            Flaky {
+                calls: calls.clone(),
+                failures: 5,
+            },
+            config,
+        );
+
+        let res = svc.call(unary_request()).await.unwrap();
+        let status = Status::from_header_map(res.headers()).unwrap();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn throttles_retries_until_calls_succeed() {
+        let config = ServiceConfig::from_json(
+            r#"{
+                "methodConfig": [{
+                    "name": [{ "service": "test.Service" }],
+                    "retryPolicy": {
+                        "maxAttempts": 3,
+                        "initialBackoff": "0.001s",
+                        "maxBackoff": "0.001s",
+                        "backoffMultiplier": 1,
+                        "retryableStatusCodes": ["UNAVAILABLE"]
+                    }
+                }],
+                "retryThrottling": { "maxTokens": 10, "tokenRatio": 0.5 }
+            }"#,
+        )
+        .unwrap();
+        let layer = ServiceConfigLayer::new(config);
+        let call = |failures| {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let svc = layer.layer(Flaky {
+                calls: calls.clone(),
+                failures,
+            });
+            (calls, svc)
+        };
+
+        // Every failed attempt takes a token, and retries stop once half of them are gone.
+        for (attempts, tokens) in [(3, 7.0), (2, 5.0), (1, 4.0)] {
+            let (calls, mut svc) = call(usize::MAX);
+            let res = svc.call(unary_request()).await.unwrap();
+            let status = Status::from_header_map(res.headers()).unwrap();
+            assert_eq!(status.code(), Code::Unavailable);
+            assert_eq!(calls.load(Ordering::SeqCst), attempts);
+            assert_eq!(layer.retry_tokens(), Some(tokens));
+        }
+
+        // Successful calls give back a fraction of a token once they complete.
+        let (_, mut svc) = call(0);
+        let res = svc.call(unary_request()).await.unwrap();
+        assert_eq!(layer.retry_tokens(), Some(4.0));
+        res.into_body().collect().await.unwrap();
+        assert_eq!(layer.retry_tokens(), Some(4.5));
+    }
+
+    #[tokio::test]
+    async fn rejects_large_messages() {
+        let config = ServiceConfig::from_json(
+            r#"{"methodConfig": [{"name": [{}], "maxRequestMessageBytes": 2}]}"#,
+        )
+        .unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut svc = ApplyServiceConfig::new(
+            Flaky {
+                calls: calls.clone(),
+                failures: 0,
+            },
+            config,
+        );
+
+        let res = svc.call(unary_request()).await.unwrap();
+        let status = Status::from_header_map(res.headers()).unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let config = ServiceConfig::from_json(
+            r#"{"methodConfig": [{"name": [{}], "maxResponseMessageBytes": 2}]}"#,
+        )
+        .unwrap();
+        let mut svc = ApplyServiceConfig::new(Flaky { calls, failures: 0 }, config);
+
+        let res = svc.call(unary_request()).await.unwrap();
+        let err = res.into_body().collect().await.unwrap_err();
+        assert_eq!(err.code(), Code::ResourceExhausted);
+    }
+}
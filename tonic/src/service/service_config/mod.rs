@@ -0,0 +1,521 @@
+//! Support for the gRPC [service config].
+//!
+//! A [`ServiceConfig`] is usually handed to a client by its name resolver or
+//! control plane as JSON, but can also be supplied directly. Its per-method
+//! settings are applied to the requests going through a
+//! [`ServiceConfigLayer`].
+//!
+//! [service config]: https://github.com/grpc/grpc/blob/master/doc/service_config.md
+
+mod layer;
+
+pub use self::layer::{ApplyServiceConfig, ServiceConfigLayer};
+
+use crate::Code;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// The upper bound the gRPC retry design puts on `maxAttempts`.
+const MAX_ATTEMPTS_LIMIT: u32 = 5;
+
+/// A parsed gRPC service config.
+///
+/// Only the parts relevant to individual calls are interpreted: the
+/// `methodConfig` list and `retryThrottling`. Other fields, such as
+/// `loadBalancingConfig`, are accepted and ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceConfig {
+    method_configs: Vec<MethodConfig>,
+    by_method: HashMap<String, usize>,
+    by_service: HashMap<String, usize>,
+    default: Option<usize>,
+    retry_throttling: Option<RetryThrottling>,
+}
+
+impl ServiceConfig {
+    /// Parse a service config from its JSON representation.
+    pub fn from_json(json: &str) -> Result<Self, ServiceConfigError> {
+        let raw: RawServiceConfig =
+            serde_json::from_str(json).map_err(|e| ServiceConfigError::new(e.to_string()))?;
+
+        let mut config = ServiceConfig {
+            retry_throttling: raw
+                .retry_throttling
+                .map(RetryThrottling::from_raw)
+                .transpose()?,
+            ..Default::default()
+        };
+
+        for raw in raw.method_config {
+            let index = config.method_configs.len();
+
+            for name in &raw.name {
+                let duplicate = match (name.service.as_deref(), name.method.as_deref()) {
+                    (None | Some(""), Some(method)) if !method.is_empty() => {
+                        return Err(ServiceConfigError::new(
+                            "method name given without a service",
+                        ));
+                    }
+                    (None | Some(""), _) => config.default.replace(index).is_some(),
+                    (Some(service), None | Some("")) => config
+                        .by_service
+                        .insert(service.to_string(), index)
+                        .is_some(),
+                    (Some(service), Some(method)) => config
+                        .by_method
+                        .insert(format!("/{service}/{method}"), index)
+                        .is_some(),
+                };
+
+                if duplicate {
+                    return Err(ServiceConfigError::new(format!(
+                        "duplicate method config name {name:?}"
+                    )));
+                }
+            }
+
+            config.method_configs.push(MethodConfig::from_raw(raw)?);
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the [`MethodConfig`] that applies to the request path
+    /// `/<service>/<method>`, if any.
+    ///
+    /// A config naming the exact method takes precedence over one naming only
+    /// the service, which takes precedence over the default config.
+    pub fn method_config(&self, path: &str) -> Option<&MethodConfig> {
+        let service = path
+            .strip_prefix('/')
+            .and_then(|path| path.split_once('/'))
+            .map(|(service, _)| service);
+
+        self.by_method
+            .get(path)
+            .or_else(|| service.and_then(|service| self.by_service.get(service)))
+            .or(self.default.as_ref())
+            .map(|index| &self.method_configs[*index])
+    }
+
+    /// Returns the retry throttling policy, if any.
+    pub fn retry_throttling(&self) -> Option<&RetryThrottling> {
+        self.retry_throttling.as_ref()
+    }
+}
+
+/// Settings that apply to the calls of one or more methods.
+#[derive(Debug, Clone, Default)]
+pub struct MethodConfig {
+    timeout: Option<Duration>,
+    wait_for_ready: Option<bool>,
+    max_request_message_bytes: Option<usize>,
+    max_response_message_bytes: Option<usize>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl MethodConfig {
+    fn from_raw(raw: RawMethodConfig) -> Result<Self, ServiceConfigError> {
+        Ok(MethodConfig {
+            timeout: raw.timeout.as_deref().map(parse_duration).transpose()?,
+            wait_for_ready: raw.wait_for_ready,
+            max_request_message_bytes: raw.max_request_message_bytes.map(|v| v.0),
+            max_response_message_bytes: raw.max_response_message_bytes.map(|v| v.0),
+            retry_policy: raw.retry_policy.map(RetryPolicy::from_raw).transpose()?,
+        })
+    }
+
+    /// The deadline applied to each call.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Whether calls should wait for the channel to become ready.
+    ///
+    /// Tonic channels always wait for a ready connection, so this is only
+    /// informational.
+    pub fn wait_for_ready(&self) -> Option<bool> {
+        self.wait_for_ready
+    }
+
+    /// The maximum size of a request message.
+    pub fn max_request_message_bytes(&self) -> Option<usize> {
+        self.max_request_message_bytes
+    }
+
+    /// The maximum size of a response message.
+    pub fn max_response_message_bytes(&self) -> Option<usize> {
+        self.max_response_message_bytes
+    }
+
+    /// The retry policy for calls that fail with a retryable status.
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+}
+
+/// How to retry calls that fail with a retryable status.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    backoff_multiplier: f64,
+    retryable_status_codes: Vec<Code>,
+}
+
+impl RetryPolicy {
+    fn from_raw(raw: RawRetryPolicy) -> Result<Self, ServiceConfigError> {
+        if raw.max_attempts < 2 {
+            return Err(ServiceConfigError::new(
+                "retryPolicy.maxAttempts must be greater than 1",
+            ));
+        }
+        let initial_backoff = parse_duration(&raw.initial_backoff)?;
+        let max_backoff = parse_duration(&raw.max_backoff)?;
+        if initial_backoff.is_zero() || max_backoff.is_zero() {
+            return Err(ServiceConfigError::new(
+                "retryPolicy backoffs must be greater than zero",
+            ));
+        }
+        if raw.backoff_multiplier.is_nan() || raw.backoff_multiplier <= 0.0 {
+            return Err(ServiceConfigError::new(
+                "retryPolicy.backoffMultiplier must be greater than zero",
+            ));
+        }
+        if raw.retryable_status_codes.is_empty() {
+            return Err(ServiceConfigError::new(
+                "retryPolicy.retryableStatusCodes must not be empty",
+            ));
+        }
+
+        Ok(RetryPolicy {
+            max_attempts: raw.max_attempts.min(MAX_ATTEMPTS_LIMIT),
+            initial_backoff,
+            max_backoff,
+            backoff_multiplier: raw.backoff_multiplier,
+            retryable_status_codes: raw
+                .retryable_status_codes
+                .into_iter()
+                .map(|c| c.0)
+                .collect(),
+        })
+    }
+
+    /// The maximum number of attempts, including the original one.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The upper bound of the randomized delay before the first retry.
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// The cap on the backoff between attempts.
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// The factor the backoff grows by after each attempt.
+    pub fn backoff_multiplier(&self) -> f64 {
+        self.backoff_multiplier
+    }
+
+    /// The status codes that make a call eligible for a retry.
+    pub fn retryable_status_codes(&self) -> &[Code] {
+        &self.retryable_status_codes
+    }
+}
+
+/// Limits retries across all calls when too many of them fail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryThrottling {
+    max_tokens: f64,
+    token_ratio: f64,
+}
+
+impl RetryThrottling {
+    fn from_raw(raw: RawRetryThrottling) -> Result<Self, ServiceConfigError> {
+        if raw.max_tokens == 0 || raw.max_tokens > 1000 {
+            return Err(ServiceConfigError::new(
+                "retryThrottling.maxTokens must be in (0, 1000]",
+            ));
+        }
+        if raw.token_ratio.is_nan() || raw.token_ratio <= 0.0 {
+            return Err(ServiceConfigError::new(
+                "retryThrottling.tokenRatio must be greater than zero",
+            ));
+        }
+
+        Ok(RetryThrottling {
+            max_tokens: f64::from(raw.max_tokens),
+            // The token ratio only has a precision of three decimal places.
+            token_ratio: (raw.token_ratio * 1000.0).trunc() / 1000.0,
+        })
+    }
+
+    /// The size of the token bucket.
+    pub fn max_tokens(&self) -> f64 {
+        self.max_tokens
+    }
+
+    /// The number of tokens a successful call adds back to the bucket.
+    pub fn token_ratio(&self) -> f64 {
+        self.token_ratio
+    }
+}
+
+/// Error returned when a service config cannot be parsed.
+#[derive(Debug, Clone)]
+pub struct ServiceConfigError {
+    message: String,
+}
+
+impl ServiceConfigError {
+    fn new(message: impl Into<String>) -> Self {
+        ServiceConfigError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid service config: {}", self.message)
+    }
+}
+
+impl std::error::Error for ServiceConfigError {}
+
+/// Parses a protobuf JSON `Duration`, e.g. `"1.5s"`.
+fn parse_duration(value: &str) -> Result<Duration, ServiceConfigError> {
+    let invalid = || ServiceConfigError::new(format!("invalid duration {value:?}"));
+
+    let value = value.strip_suffix('s').ok_or_else(invalid)?;
+    let (seconds, fraction) = match value.split_once('.') {
+        Some((seconds, fraction)) if !fraction.is_empty() => (seconds, fraction),
+        Some(_) => return Err(invalid()),
+        None => (value, ""),
+    };
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if seconds.is_empty() || fraction.len() > 9 || !is_digits(seconds) || !is_digits(fraction) {
+        return Err(invalid());
+    }
+
+    let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        let digits: u32 = fraction.parse().map_err(|_| invalid())?;
+        digits * 10u32.pow(9 - fraction.len() as u32)
+    };
+
+    Ok(Duration::new(seconds, nanos))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawServiceConfig {
+    #[serde(default)]
+    method_config: Vec<RawMethodConfig>,
+    retry_throttling: Option<RawRetryThrottling>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMethodConfig {
+    #[serde(default)]
+    name: Vec<RawName>,
+    timeout: Option<String>,
+    wait_for_ready: Option<bool>,
+    max_request_message_bytes: Option<MessageBytes>,
+    max_response_message_bytes: Option<MessageBytes>,
+    retry_policy: Option<RawRetryPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawName {
+    service: Option<String>,
+    method: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRetryPolicy {
+    max_attempts: u32,
+    initial_backoff: String,
+    max_backoff: String,
+    backoff_multiplier: f64,
+    #[serde(default)]
+    retryable_status_codes: Vec<StatusCode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRetryThrottling {
+    max_tokens: u32,
+    token_ratio: f64,
+}
+
+/// A message size, which protobuf JSON may encode as a number or a string.
+struct MessageBytes(usize);
+
+impl<'de> Deserialize<'de> for MessageBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(u64),
+            String(String),
+        }
+
+        let value = match Raw::deserialize(deserializer)? {
+            Raw::Number(value) => value,
+            Raw::String(value) => value.parse().map_err(serde::de::Error::custom)?,
+        };
+
+        Ok(MessageBytes(usize::try_from(value).unwrap_or(usize::MAX)))
+    }
+}
+
+/// A status code, given either by name (`"UNAVAILABLE"`) or by number.
+struct StatusCode(Code);
+
+impl<'de> Deserialize<'de> for StatusCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(i32),
+            Name(String),
+        }
+
+        let code = match Raw::deserialize(deserializer)? {
+            Raw::Number(value @ 0..=16) => Code::from_i32(value),
+            Raw::Name(name) => code_from_name(&name)
+                .ok_or_else(|| serde::de::Error::custom(format!("unknown status code {name:?}")))?,
+            Raw::Number(value) => {
+                return Err(serde::de::Error::custom(format!(
+                    "unknown status code {value}"
+                )))
+            }
+        };
+
+        Ok(StatusCode(code))
+    }
+}
+
+fn code_from_name(name: &str) -> Option<Code> {
+    let code = match name {
+        "OK" => Code::Ok,
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "loadBalancingConfig": [{ "round_robin": {} }],
+        "methodConfig": [
+            {
+                "name": [{ "service": "helloworld.Greeter", "method": "SayHello" }],
+                "timeout": "1.5s",
+                "maxResponseMessageBytes": "1024"
+            },
+            {
+                "name": [{ "service": "helloworld.Greeter" }],
+                "waitForReady": true,
+                "retryPolicy": {
+                    "maxAttempts": 10,
+                    "initialBackoff": "0.1s",
+                    "maxBackoff": "1s",
+                    "backoffMultiplier": 2,
+                    "retryableStatusCodes": ["UNAVAILABLE", 4]
+                }
+            },
+            {
+                "name": [{}],
+                "maxRequestMessageBytes": 2048
+            }
+        ],
+        "retryThrottling": { "maxTokens": 10, "tokenRatio": 0.1234 }
+    }"#;
+
+    #[test]
+    fn method_config_lookup() {
+        let config = ServiceConfig::from_json(CONFIG).unwrap();
+
+        let exact = config
+            .method_config("/helloworld.Greeter/SayHello")
+            .unwrap();
+        assert_eq!(exact.timeout(), Some(Duration::from_millis(1500)));
+        assert_eq!(exact.max_response_message_bytes(), Some(1024));
+        assert!(exact.retry_policy().is_none());
+
+        let service = config
+            .method_config("/helloworld.Greeter/SayGoodbye")
+            .unwrap();
+        assert_eq!(service.wait_for_ready(), Some(true));
+        let retry = service.retry_policy().unwrap();
+        assert_eq!(retry.max_attempts(), 5);
+        assert_eq!(retry.initial_backoff(), Duration::from_millis(100));
+        assert_eq!(
+            retry.retryable_status_codes(),
+            [Code::Unavailable, Code::DeadlineExceeded]
+        );
+
+        let default = config.method_config("/other.Service/Call").unwrap();
+        assert_eq!(default.max_request_message_bytes(), Some(2048));
+
+        let throttling = config.retry_throttling().unwrap();
+        assert_eq!(throttling.max_tokens(), 10.0);
+        assert_eq!(throttling.token_ratio(), 0.123);
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        for json in [
+            r#"{"methodConfig": [{"name": [{"method": "Call"}]}]}"#,
+            r#"{"methodConfig": [{"name": [{"service": "a"}]}, {"name": [{"service": "a"}]}]}"#,
+            r#"{"methodConfig": [{"name": [{}], "timeout": "1"}]}"#,
+            r#"{"methodConfig": [{"name": [{}], "retryPolicy": {"maxAttempts": 1, "initialBackoff": "1s", "maxBackoff": "1s", "backoffMultiplier": 1, "retryableStatusCodes": ["UNAVAILABLE"]}}]}"#,
+            r#"{"methodConfig": [{"name": [{}], "retryPolicy": {"maxAttempts": 2, "initialBackoff": "1s", "maxBackoff": "1s", "backoffMultiplier": 1, "retryableStatusCodes": ["NOPE"]}}]}"#,
+            r#"{"retryThrottling": {"maxTokens": 0, "tokenRatio": 1}}"#,
+        ] {
+            assert!(ServiceConfig::from_json(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("0s").unwrap(), Duration::ZERO);
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(
+            parse_duration("0.000000001s").unwrap(),
+            Duration::from_nanos(1)
+        );
+        assert!(parse_duration("1.s").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("1m").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Middleware that coalesces concurrent identical unary calls.
+//!
+//! See [`SingleFlightLayer`] for more details.
+
+use super::response_cache::{frames_body, CacheKey};
+use crate::{body::Body, Status};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, Request, Response};
+use http_body_util::{BodyExt, Full};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'static>>;
+
+/// A [`Layer`] that coalesces concurrent identical unary calls into a single call.
+///
+/// While a call to one of the methods registered with [`method`](Self::method) is in flight,
+/// identical calls, with the same method, encoded request message and values of the metadata
+/// registered with [`key_metadata`](Self::key_metadata), wait for it instead of being sent. Its
+/// response, or its error as a [`Status`], is then shared by all of them. This protects the
+/// backends from a thundering herd of identical calls, e.g. after a cache expired.
+///
+/// Only the calls that overlap are coalesced, nothing is kept once a call completes. If the
+/// caller of the call in flight gives up on it, the calls waiting for it are sent on their own.
+///
+/// The registered methods must be unary since the whole response is buffered, and idempotent
+/// since the calls waiting for another one are never sent.
+///
+/// ```
+/// use tonic::service::SingleFlightLayer;
+///
+/// let layer = SingleFlightLayer::new().method("/helloworld.Greeter/SayHello");
+/// ```
+#[derive(Clone, Default)]
+pub struct SingleFlightLayer {
+    methods: HashSet<String>,
+    key_metadata: Vec<HeaderName>,
+    flights: Arc<Mutex<HashMap<CacheKey, Arc<Flight>>>>,
+}
+
+impl SingleFlightLayer {
+    /// Create a layer with no method coalesced yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coalesces the calls to the unary method with this path, e.g.
+    /// `/helloworld.Greeter/SayHello`.
+    pub fn method(mut self, path: impl Into<String>) -> Self {
+        self.methods.insert(path.into());
+        self
+    }
+
+    /// Only coalesces calls with the same value of the request metadata `name`.
+    pub fn key_metadata(mut self, name: HeaderName) -> Self {
+        self.key_metadata.push(name);
+        self
+    }
+
+    async fn call<S, B>(
+        self,
+        mut inner: S,
+        req: Request<Body>,
+    ) -> Result<Response<Body>, crate::BoxError>
+    where
+        S: Service<Request<Body>, Response = Response<B>>,
+        S::Error: Into<crate::BoxError>,
+        B: http_body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<crate::BoxError>,
+    {
+        let (parts, body) = req.into_parts();
+        let message = body.collect().await?.to_bytes();
+        let key = CacheKey::new(
+            parts.uri.path(),
+            &parts.headers,
+            &self.key_metadata,
+            message.clone(),
+        );
+
+        let (flight, leader) = {
+            let mut flights = self.flights.lock().unwrap();
+            match flights.get(&key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    flights.insert(key.clone(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+
+        if !leader {
+            if let Some(result) = poll_fn(|cx| flight.poll(cx)).await {
+                return result
+                    .map(SharedResponse::into_response)
+                    .map_err(Into::into);
+            }
+            tracing::debug!("coalesced call was abandoned, sending it on its own");
+            let req = Request::from_parts(parts, Body::new(Full::new(message)));
+            let res = inner.call(req).await.map_err(Into::into)?;
+            return Ok(res.map(Body::new));
+        }
+
+        let mut guard = Leader {
+            flights: &self.flights,
+            key,
+            flight,
+            done: false,
+        };
+
+        let req = Request::from_parts(parts, Body::new(Full::new(message)));
+        let result = match inner.call(req).await.map_err(Into::into) {
+            Ok(res) => SharedResponse::collect(res).await,
+            Err(error) => Err(Status::from_error(error)),
+        };
+
+        guard.finish(result.clone());
+        result
+            .map(SharedResponse::into_response)
+            .map_err(Into::into)
+    }
+}
+
+impl fmt::Debug for SingleFlightLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleFlightLayer")
+            .field("methods", &self.methods)
+            .field("key_metadata", &self.key_metadata)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for SingleFlightLayer {
+    type Service = SingleFlight<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SingleFlight {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A buffered response shared by coalesced calls.
+#[derive(Clone)]
+struct SharedResponse {
+    headers: HeaderMap,
+    body: Bytes,
+    trailers: HeaderMap,
+}
+
+impl SharedResponse {
+    async fn collect<B>(res: Response<B>) -> Result<Self, Status>
+    where
+        B: http_body::Body<Data = Bytes>,
+        B::Error: Into<crate::BoxError>,
+    {
+        let (parts, body) = res.into_parts();
+        let collected = body
+            .collect()
+            .await
+            .map_err(|error| Status::from_error(error.into()))?;
+        Ok(SharedResponse {
+            headers: parts.headers,
+            trailers: collected.trailers().cloned().unwrap_or_default(),
+            body: collected.to_bytes(),
+        })
+    }
+
+    fn into_response(self) -> Response<Body> {
+        let mut response = Response::new(frames_body(self.body, self.trailers));
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+/// A call in flight, which identical calls wait for.
+#[derive(Default)]
+struct Flight {
+    state: Mutex<FlightState>,
+}
+
+enum FlightState {
+    Pending(Vec<Waker>),
+    Done(Result<SharedResponse, Status>),
+    Abandoned,
+}
+
+impl Default for FlightState {
+    fn default() -> Self {
+        FlightState::Pending(Vec::new())
+    }
+}
+
+impl Flight {
+    /// Waits for the result of the call, or `None` if it was abandoned.
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<Option<Result<SharedResponse, Status>>> {
+        match &mut *self.state.lock().unwrap() {
+            FlightState::Pending(wakers) => {
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            FlightState::Done(result) => Poll::Ready(Some(result.clone())),
+            FlightState::Abandoned => Poll::Ready(None),
+        }
+    }
+
+    fn complete(&self, state: FlightState) {
+        let previous = std::mem::replace(&mut *self.state.lock().unwrap(), state);
+        if let FlightState::Pending(wakers) = previous {
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// Publishes the result of the call in flight, or abandons it if dropped before that.
+struct Leader<'a> {
+    flights: &'a Mutex<HashMap<CacheKey, Arc<Flight>>>,
+    key: CacheKey,
+    flight: Arc<Flight>,
+    done: bool,
+}
+
+impl Leader<'_> {
+    fn finish(&mut self, result: Result<SharedResponse, Status>) {
+        self.done = true;
+        self.remove();
+        self.flight.complete(FlightState::Done(result));
+    }
+
+    /// Removes the flight so that later calls are sent again.
+    fn remove(&self) {
+        let mut flights = self.flights.lock().unwrap();
+        if flights
+            .get(&self.key)
+            .is_some_and(|flight| Arc::ptr_eq(flight, &self.flight))
+        {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.remove();
+            self.flight.complete(FlightState::Abandoned);
+        }
+    }
+}
+
+/// Middleware that coalesces concurrent identical unary calls.
+///
+/// See [`SingleFlightLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct SingleFlight<S> {
+    inner: S,
+    layer: SingleFlightLayer,
+}
+
+impl<S> SingleFlight<S> {
+    /// Create a new [`SingleFlight`] coalescing the calls to `methods`.
+    pub fn new<I>(inner: S, methods: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        methods
+            .into_iter()
+            .fold(SingleFlightLayer::new(), SingleFlightLayer::method)
+            .layer(inner)
+    }
+}
+
+impl<S, B> Service<Request<Body>> for SingleFlight<S>
+where
+    S: Service<Request<Body>, Response = Response<B>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<crate::BoxError>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        if !self.layer.methods.contains(req.uri().path()) {
+            let future = inner.call(req);
+            return Box::pin(async move {
+                let res = future.await.map_err(Into::into)?;
+                Ok(res.map(Body::new))
+            });
+        }
+
+        Box::pin(self.layer.clone().call(inner, req))
+    }
+}
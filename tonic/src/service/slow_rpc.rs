@@ -0,0 +1,563 @@
+//! Middleware that logs the RPCs exceeding a latency threshold.
+//!
+//! See [`SlowRpcLayer`] for more details.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock},
+    Code, Status,
+};
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A [`Layer`] that logs the RPCs taking longer than a threshold, from the time their request
+/// is received or sent until their response ends.
+///
+/// Slow RPCs are logged as `tracing` events with the `tonic::slow_rpc` target at the `WARN`
+/// level, with their method, peer, status code, latency, message counts, and a breakdown of
+/// where the time was spent:
+///
+/// - On servers, `handler` is the time until the response headers were sent, and `streaming`
+///   the time spent sending the rest of the response.
+/// - On clients, `queued` is the time the call waited for a connection to be ready, `connect`
+///   the part of that wait spent establishing the connection, `server` the time from sending
+///   the request until the response headers were received, and `streaming` the time spent
+///   receiving the rest of the response.
+///
+/// The client breakdown is only known when the layer wraps a
+/// [`Channel`](crate::transport::Channel); otherwise the whole time until the response headers
+/// is attributed to the server.
+///
+/// When many RPCs are slow, e.g. during an incident, only one in every [`sample`](Self::sample)
+/// of them can be logged.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use tonic::service::SlowRpcLayer;
+/// let layer = SlowRpcLayer::server(Duration::from_millis(500)).sample(10);
+/// ```
+#[derive(Clone)]
+pub struct SlowRpcLayer {
+    side: Side,
+    threshold: Duration,
+    sample: u64,
+    seen: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Server,
+    Client,
+}
+
+impl SlowRpcLayer {
+    /// Create a layer logging the RPCs served by a server that take longer than `threshold`.
+    ///
+    /// It should be applied with [`Server::layer`](crate::transport::Server::layer) so the peer
+    /// address of the connection is known.
+    pub fn server(threshold: Duration) -> Self {
+        Self::new(Side::Server, threshold)
+    }
+
+    /// Create a layer logging the calls made by a client that take longer than `threshold`.
+    pub fn client(threshold: Duration) -> Self {
+        Self::new(Side::Client, threshold)
+    }
+
+    fn new(side: Side, threshold: Duration) -> Self {
+        SlowRpcLayer {
+            side,
+            threshold,
+            sample: 1,
+            seen: Arc::new(AtomicU64::new(0)),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Logs only one in every `one_in` slow RPCs. Defaults to `1`, i.e. every slow RPC is
+    /// logged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `one_in` is `0`.
+    pub fn sample(self, one_in: u64) -> Self {
+        assert!(one_in > 0, "the sampling rate must not be 0");
+        SlowRpcLayer {
+            sample: one_in,
+            ..self
+        }
+    }
+
+    /// Sets the [`Clock`] used to measure latencies. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        SlowRpcLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+}
+
+impl fmt::Debug for SlowRpcLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowRpcLayer")
+            .field("side", &self.side)
+            .field("threshold", &self.threshold)
+            .field("sample", &self.sample)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for SlowRpcLayer {
+    type Service = SlowRpc<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRpc {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that logs the RPCs exceeding a latency threshold.
+///
+/// See [`SlowRpcLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct SlowRpc<S> {
+    inner: S,
+    layer: SlowRpcLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SlowRpc<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ReqBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ReqBody::Error: Into<crate::BoxError>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let start = self.layer.clock.now();
+        let timeline = RpcTimeline::new(start);
+        let peer = match self.layer.side {
+            Side::Server => server_peer(&req),
+            Side::Client => {
+                req.extensions_mut().insert(timeline.clone());
+                None
+            }
+        };
+
+        let requests = Arc::new(AtomicU64::new(0));
+        let pending = Pending {
+            method: req.uri().path().to_owned(),
+            peer,
+            code: None,
+            timeline,
+            headers: None,
+            requests: requests.clone(),
+            responses: 0,
+            layer: self.layer.clone(),
+        };
+
+        let req = req.map(|inner| {
+            Body::new(RequestBody {
+                inner,
+                counter: MessageCounter::default(),
+                requests,
+            })
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            pending: Some(pending),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn server_peer<B>(req: &Request<B>) -> Option<String> {
+    use crate::transport::server::TcpConnectInfo;
+
+    let addr = req
+        .extensions()
+        .get::<TcpConnectInfo>()
+        .and_then(|i| i.remote_addr());
+
+    #[cfg(feature = "_tls-any")]
+    let addr = addr.or_else(|| {
+        req.extensions()
+            .get::<crate::transport::server::TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|i| i.get_ref().remote_addr())
+    });
+
+    addr.map(|addr| addr.to_string())
+}
+
+#[cfg(not(feature = "server"))]
+fn server_peer<B>(_: &Request<B>) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "channel")]
+fn client_peer<B>(res: &Response<B>) -> Option<String> {
+    res.extensions()
+        .get::<crate::transport::channel::PinEndpoint>()
+        .map(|pin| pin.uri().to_string())
+}
+
+#[cfg(not(feature = "channel"))]
+fn client_peer<B>(_: &Response<B>) -> Option<String> {
+    None
+}
+
+/// The timestamps of a call made through a [`SlowRpcLayer`], shared with the channel through the
+/// extensions of its request so it can tell when the call was handed to a connection.
+#[derive(Clone)]
+pub(crate) struct RpcTimeline {
+    start: Instant,
+    dispatch: Arc<Mutex<Option<Dispatch>>>,
+}
+
+#[derive(Clone, Copy)]
+struct Dispatch {
+    at: Instant,
+    connect: Duration,
+}
+
+impl RpcTimeline {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            dispatch: Arc::default(),
+        }
+    }
+
+    /// Records the call being handed to a connection `at` that instant, given the start and
+    /// end of the last time the connection was established.
+    #[cfg(feature = "channel")]
+    pub(crate) fn dispatched(&self, at: Instant, connected: Option<(Instant, Instant)>) {
+        // Only the part of the connection attempt during which the call was waiting counts.
+        let connect = connected.map_or(Duration::ZERO, |(started, established)| {
+            established
+                .min(at)
+                .saturating_duration_since(started.max(self.start))
+        });
+        *self.dispatch.lock().unwrap() = Some(Dispatch { at, connect });
+    }
+
+    fn dispatch(&self) -> Option<Dispatch> {
+        *self.dispatch.lock().unwrap()
+    }
+}
+
+/// Response future for [`SlowRpc`].
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    pending: Option<Pending>,
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+        let mut pending = this.pending.take().expect("polled after completion");
+        pending.headers = Some(pending.layer.clock.now());
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => {
+                pending.code = Some(Code::Unknown);
+                return Poll::Ready(Err(error));
+            }
+        };
+
+        if pending.layer.side == Side::Client {
+            pending.peer = client_peer(&response);
+        }
+
+        // A trailers-only response carries its status in its headers.
+        pending.update_code(response.headers());
+        let pending = if response.body().is_end_stream() {
+            None
+        } else {
+            Some(pending)
+        };
+
+        Poll::Ready(Ok(response.map(|inner| {
+            Body::new(ResponseBody {
+                inner,
+                counter: MessageCounter::default(),
+                pending,
+            })
+        })))
+    }
+}
+
+/// An RPC that is logged once it is dropped if it was slow.
+struct Pending {
+    method: String,
+    peer: Option<String>,
+    code: Option<Code>,
+    timeline: RpcTimeline,
+    /// When the response headers were sent or received.
+    headers: Option<Instant>,
+    requests: Arc<AtomicU64>,
+    responses: u64,
+    layer: SlowRpcLayer,
+}
+
+impl Pending {
+    fn update_code(&mut self, headers: &HeaderMap) {
+        if let Some(code) = headers.get(Status::GRPC_STATUS) {
+            self.code = Some(Code::from_bytes(code.as_bytes()));
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let layer = &self.layer;
+        let end = layer.clock.now();
+        let latency = end.saturating_duration_since(self.timeline.start);
+        if latency <= layer.threshold
+            || layer.seen.fetch_add(1, Ordering::Relaxed) % layer.sample != 0
+        {
+            return;
+        }
+
+        // A response without a status is malformed, unless it is dropped before it ends.
+        let code = self.code.unwrap_or(Code::Cancelled);
+        let headers = self.headers.unwrap_or(end);
+        let streaming = end.saturating_duration_since(headers);
+        let requests = self.requests.load(Ordering::Relaxed);
+
+        match layer.side {
+            Side::Server => tracing::warn!(
+                target: "tonic::slow_rpc",
+                method = %self.method,
+                peer = self.peer.as_deref(),
+                code = ?code,
+                latency = ?latency,
+                request_messages = requests,
+                response_messages = self.responses,
+                handler = ?headers.saturating_duration_since(self.timeline.start),
+                streaming = ?streaming,
+                "slow RPC served",
+            ),
+            Side::Client => {
+                let (queued, connect, sent) = match self.timeline.dispatch() {
+                    Some(dispatch) => {
+                        let waited = dispatch.at.saturating_duration_since(self.timeline.start);
+                        (waited, dispatch.connect, dispatch.at)
+                    }
+                    None => (Duration::ZERO, Duration::ZERO, self.timeline.start),
+                };
+
+                tracing::warn!(
+                    target: "tonic::slow_rpc",
+                    method = %self.method,
+                    peer = self.peer.as_deref(),
+                    code = ?code,
+                    latency = ?latency,
+                    request_messages = requests,
+                    response_messages = self.responses,
+                    queued = ?queued,
+                    connect = ?connect,
+                    server = ?headers.saturating_duration_since(sent),
+                    streaming = ?streaming,
+                    "slow RPC call",
+                )
+            }
+        }
+    }
+}
+
+/// Counts the messages of a gRPC stream by following its length-prefixed framing.
+#[derive(Default)]
+struct MessageCounter {
+    header: [u8; 5],
+    header_len: usize,
+    remaining: u64,
+}
+
+impl MessageCounter {
+    /// Returns the number of messages whose header is in `data`.
+    fn count(&mut self, mut data: &[u8]) -> u64 {
+        let mut messages = 0;
+        while !data.is_empty() {
+            if self.remaining > 0 {
+                let skip = self.remaining.min(data.len() as u64);
+                self.remaining -= skip;
+                data = &data[skip as usize..];
+                continue;
+            }
+
+            let take = (self.header.len() - self.header_len).min(data.len());
+            self.header[self.header_len..][..take].copy_from_slice(&data[..take]);
+            self.header_len += take;
+            data = &data[take..];
+
+            if self.header_len == self.header.len() {
+                let [_, len @ ..] = self.header;
+                messages += 1;
+                self.header_len = 0;
+                self.remaining = u64::from(u32::from_be_bytes(len));
+            }
+        }
+        messages
+    }
+}
+
+/// A request body that counts its messages.
+#[pin_project]
+struct RequestBody<B> {
+    #[pin]
+    inner: B,
+    counter: MessageCounter,
+    requests: Arc<AtomicU64>,
+}
+
+impl<B> http_body::Body for RequestBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(data) = frame.as_ref().and_then(|f| f.as_ref().ok()?.data_ref()) {
+            let messages = this.counter.count(data);
+            this.requests.fetch_add(messages, Ordering::Relaxed);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A response body that counts its messages, and logs its RPC once it ends if it was slow.
+#[pin_project]
+struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    counter: MessageCounter,
+    pending: Option<Pending>,
+}
+
+impl<B> http_body::Body for ResponseBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+
+        if let Some(pending) = this.pending {
+            match &frame {
+                Some(Ok(frame)) => {
+                    if let Some(data) = frame.data_ref() {
+                        pending.responses += this.counter.count(data);
+                    } else if let Some(trailers) = frame.trailers_ref() {
+                        pending.update_code(trailers);
+                    }
+                }
+                Some(Err(_)) => pending.code = Some(Code::Unknown),
+                None => {
+                    pending.code.get_or_insert(Code::Unknown);
+                    this.pending.take();
+                }
+            }
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(all(test, feature = "channel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_only_counts_while_waiting() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        let timeline = RpcTimeline::new(at(10));
+        timeline.dispatched(at(50), Some((at(0), at(30))));
+        let dispatch = timeline.dispatch().unwrap();
+        assert_eq!(dispatch.at, at(50));
+        assert_eq!(dispatch.connect, Duration::from_millis(20));
+
+        // A connection established before the call started did not delay it.
+        timeline.dispatched(at(50), Some((at(0), at(5))));
+        assert_eq!(timeline.dispatch().unwrap().connect, Duration::ZERO);
+
+        timeline.dispatched(at(50), None);
+        assert_eq!(timeline.dispatch().unwrap().connect, Duration::ZERO);
+    }
+}
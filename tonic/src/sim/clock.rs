@@ -0,0 +1,190 @@
+use crate::time::{Clock, Sleep};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// A [`Clock`] whose time only moves when it is advanced.
+///
+/// Timers fire in deadline order, ties broken by the order in which they were registered, so
+/// a simulation observes the same sequence of wakeups on every run.
+#[derive(Clone)]
+pub struct SimClock {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    start: Instant,
+    elapsed: Duration,
+    next_id: u64,
+    timers: BTreeMap<(Instant, u64), Option<Waker>>,
+}
+
+impl SimClock {
+    /// Create a clock starting at the current instant.
+    pub fn new() -> Self {
+        SimClock {
+            inner: Arc::new(Mutex::new(Inner {
+                start: Instant::now(),
+                elapsed: Duration::ZERO,
+                next_id: 0,
+                timers: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// The simulated time elapsed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.inner.lock().unwrap().elapsed
+    }
+
+    /// The deadline of the earliest pending timer, if any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let inner = self.inner.lock().unwrap();
+        inner.timers.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    /// Move time forward by `duration`, firing every timer that becomes due.
+    pub fn advance(&self, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.elapsed += duration;
+        let now = inner.start + inner.elapsed;
+
+        let due: Vec<_> = inner
+            .timers
+            .range(..=(now, u64::MAX))
+            .map(|(key, _)| *key)
+            .collect();
+        let wakers: Vec<_> = due
+            .into_iter()
+            .filter_map(|key| inner.timers.remove(&key).flatten())
+            .collect();
+        drop(inner);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Move time forward to the earliest pending timer and fire it.
+    ///
+    /// Returns `false` if there was no timer to fire.
+    pub fn advance_to_next(&self) -> bool {
+        let Some(deadline) = self.next_deadline() else {
+            return false;
+        };
+        let now = self.now();
+        self.advance(deadline.saturating_duration_since(now));
+        true
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SimClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("SimClock")
+            .field("elapsed", &inner.elapsed)
+            .field("timers", &inner.timers.len())
+            .finish()
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        let inner = self.inner.lock().unwrap();
+        inner.start + inner.elapsed
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        if deadline > inner.start + inner.elapsed {
+            inner.timers.insert((deadline, id), None);
+        }
+
+        Box::pin(SimSleep {
+            clock: self.clone(),
+            key: (deadline, id),
+        })
+    }
+}
+
+struct SimSleep {
+    clock: SimClock,
+    key: (Instant, u64),
+}
+
+impl Future for SimSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.clock.inner.lock().unwrap();
+        match inner.timers.get_mut(&self.key) {
+            Some(waker) => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+impl Drop for SimSleep {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.clock.inner.lock() {
+            inner.timers.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn timers_fire_in_deadline_order() {
+        let clock = SimClock::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let fired = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for (name, millis) in [("b", 20), ("a", 10), ("c", 20)] {
+            let sleep = clock.sleep(Duration::from_millis(millis));
+            let order = order.clone();
+            let fired = fired.clone();
+            handles.push(tokio::spawn(async move {
+                sleep.await;
+                order.lock().unwrap().push(name);
+                fired.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+        tokio::task::yield_now().await;
+
+        clock.advance(Duration::from_millis(5));
+        tokio::task::yield_now().await;
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+
+        assert!(clock.advance_to_next());
+        assert_eq!(clock.elapsed(), Duration::from_millis(10));
+        clock.advance(Duration::from_millis(10));
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), ["a", "b", "c"]);
+        assert!(!clock.advance_to_next());
+    }
+}
@@ -0,0 +1,138 @@
+//! Deterministic simulation of tonic clients and servers.
+//!
+//! A [`Simulation`] bundles a [`SimClock`], whose time only moves when the simulation lets it,
+//! and a [`SimNetwork`], an in-memory network whose fault injection draws from a seeded
+//! generator. Wiring both into the transport makes connections, reconnects, deadlines,
+//! keepalives and injected faults a pure function of the seed:
+//!
+//! ```rust,no_run
+//! # use tonic::transport::{Endpoint, Server};
+//! # use tonic::sim::Simulation;
+//! let sim = Simulation::new(42);
+//! let network = sim.network();
+//! network.set_loss_rate(0.01);
+//!
+//! sim.block_on(async {
+//!     let listener = network.bind("server:50051");
+//!     tokio::spawn(
+//!         Server::builder()
+//!             .clock(sim.clock())
+//!             # .add_routes(Default::default())
+//!             .serve_with_incoming(listener),
+//!     );
+//!
+//!     let channel = Endpoint::from_static("http://server:50051")
+//!         .clock(sim.clock())
+//!         .connect_with_connector_lazy(network.connector());
+//!     // ... issue calls on `channel`
+//! });
+//! ```
+//!
+//! [`Simulation::block_on`] runs everything on a single threaded Tokio runtime, so tasks are
+//! polled in a reproducible order, and moves the clock to the next pending timer whenever the
+//! tasks stop making progress. Only time sources that go through the [`Clock`] are simulated;
+//! TLS handshake timeouts still use the Tokio timer.
+//!
+//! [`Clock`]: crate::time::Clock
+
+mod clock;
+mod net;
+mod rng;
+
+pub use self::clock::SimClock;
+pub use self::net::{SimConnectInfo, SimConnector, SimListener, SimNetwork, SimStream};
+
+use self::rng::SimRng;
+use std::{
+    future::{poll_fn, Future},
+    pin::pin,
+    task::Poll,
+};
+
+/// How many times the driver yields before concluding the tasks are idle.
+const DEFAULT_IDLE_ROUNDS: usize = 64;
+
+/// A seeded simulation, see the [module level documentation](self).
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    seed: u64,
+    clock: SimClock,
+    network: SimNetwork,
+    idle_rounds: usize,
+}
+
+impl Simulation {
+    /// Create a simulation whose random decisions are derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        let clock = SimClock::new();
+        Simulation {
+            seed,
+            network: SimNetwork::new(SimRng::new(seed), clock.clone()),
+            clock,
+            idle_rounds: DEFAULT_IDLE_ROUNDS,
+        }
+    }
+
+    /// The seed of this simulation.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The simulated clock, to pass to [`Endpoint::clock`] and [`Server::clock`].
+    ///
+    /// [`Endpoint::clock`]: crate::transport::Endpoint::clock
+    /// [`Server::clock`]: crate::transport::Server::clock
+    pub fn clock(&self) -> SimClock {
+        self.clock.clone()
+    }
+
+    /// The simulated network.
+    pub fn network(&self) -> SimNetwork {
+        self.network.clone()
+    }
+
+    /// Sets how many scheduling rounds without the future completing count as idle before the
+    /// clock is advanced. Defaults to 64.
+    ///
+    /// Raise it if work that needs many rounds races against timers.
+    pub fn idle_rounds(self, rounds: usize) -> Self {
+        Simulation {
+            idle_rounds: rounds.max(1),
+            ..self
+        }
+    }
+
+    /// Run `future` to completion on a fresh single threaded runtime, advancing the simulated
+    /// clock whenever the runtime goes idle.
+    ///
+    /// Tasks spawned with [`tokio::spawn`] from within `future` run on the same runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("failed to build the simulation runtime");
+
+        let clock = self.clock.clone();
+        let idle_rounds = self.idle_rounds;
+
+        runtime.block_on(async move {
+            let mut future = pin!(future);
+            let mut driver = pin!(async move {
+                loop {
+                    for _ in 0..idle_rounds {
+                        tokio::task::yield_now().await;
+                    }
+                    clock.advance_to_next();
+                }
+            });
+
+            poll_fn(|cx| {
+                if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                    return Poll::Ready(output);
+                }
+                let _ = driver.as_mut().poll(cx);
+                Poll::Pending
+            })
+            .await
+        })
+    }
+}
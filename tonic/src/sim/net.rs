@@ -0,0 +1,332 @@
+use super::{rng::SimRng, SimClock};
+use crate::time::Clock;
+use crate::transport::server::Connected;
+use hyper_util::rt::TokioIo;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
+    sync::mpsc,
+};
+use tokio_stream::Stream;
+use tower_service::Service;
+
+/// The buffer size of each direction of a simulated connection.
+const CONNECTION_BUFFER_SIZE: usize = 64 * 1024;
+
+/// An in-memory network with seeded fault injection.
+///
+/// Servers accept connections from a [`SimListener`] returned by [`SimNetwork::bind`] and
+/// clients connect through the [`SimConnector`] returned by [`SimNetwork::connector`]. Every
+/// random decision, such as whether a connection attempt is refused or a write is lost, is
+/// drawn from a generator seeded by the simulation.
+#[derive(Clone)]
+pub struct SimNetwork {
+    inner: Arc<Mutex<NetInner>>,
+    clock: SimClock,
+}
+
+struct NetInner {
+    rng: SimRng,
+    listeners: HashMap<String, mpsc::UnboundedSender<SimStream>>,
+    partitioned: HashSet<String>,
+    connect_failure_rate: f64,
+    loss_rate: f64,
+    connect_latency: Duration,
+    next_client: u64,
+}
+
+impl SimNetwork {
+    pub(crate) fn new(rng: SimRng, clock: SimClock) -> Self {
+        SimNetwork {
+            inner: Arc::new(Mutex::new(NetInner {
+                rng,
+                listeners: HashMap::new(),
+                partitioned: HashSet::new(),
+                connect_failure_rate: 0.0,
+                loss_rate: 0.0,
+                connect_latency: Duration::ZERO,
+                next_client: 0,
+            })),
+            clock,
+        }
+    }
+
+    /// Start accepting connections for `host`.
+    ///
+    /// Clients reach the listener through any URI whose authority is `host`, e.g.
+    /// `http://server:50051` for `server:50051`. Binding the same host again replaces the
+    /// previous listener.
+    pub fn bind(&self, host: impl Into<String>) -> SimListener {
+        let host = host.into();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.inner
+            .lock()
+            .unwrap()
+            .listeners
+            .insert(host.clone(), tx);
+        SimListener { host, rx }
+    }
+
+    /// A connector for [`Endpoint::connect_with_connector`].
+    ///
+    /// [`Endpoint::connect_with_connector`]: crate::transport::Endpoint::connect_with_connector
+    pub fn connector(&self) -> SimConnector {
+        SimConnector {
+            network: self.clone(),
+        }
+    }
+
+    /// The probability, between `0.0` and `1.0`, that a connection attempt is refused.
+    pub fn set_connect_failure_rate(&self, rate: f64) {
+        self.inner.lock().unwrap().connect_failure_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// The probability, between `0.0` and `1.0`, that a write is lost.
+    ///
+    /// HTTP/2 runs over a reliable stream, so a lost write is surfaced the way a reliable
+    /// transport would surface persistent loss: the connection is reset.
+    pub fn set_loss_rate(&self, rate: f64) {
+        self.inner.lock().unwrap().loss_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// The simulated time a connection attempt takes.
+    pub fn set_connect_latency(&self, latency: Duration) {
+        self.inner.lock().unwrap().connect_latency = latency;
+    }
+
+    /// Refuse every connection attempt to `host` until [`SimNetwork::heal`] is called.
+    ///
+    /// Established connections are not affected.
+    pub fn partition(&self, host: impl Into<String>) {
+        self.inner.lock().unwrap().partitioned.insert(host.into());
+    }
+
+    /// Undo a [`SimNetwork::partition`].
+    pub fn heal(&self, host: &str) {
+        self.inner.lock().unwrap().partitioned.remove(host);
+    }
+
+    fn connect(&self, host: &str) -> io::Result<SimStream> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let failure_rate = inner.connect_failure_rate;
+        if inner.partitioned.contains(host) || inner.rng.chance(failure_rate) {
+            return Err(io::ErrorKind::ConnectionRefused.into());
+        }
+
+        let client = format!("client-{}", inner.next_client);
+        inner.next_client += 1;
+
+        let listener = inner
+            .listeners
+            .get(host)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionRefused))?
+            .clone();
+
+        let (a, b) = tokio::io::duplex(CONNECTION_BUFFER_SIZE);
+        let client_end = SimStream::new(a, self.clone(), client.clone(), host.to_string());
+        let server_end = SimStream::new(b, self.clone(), host.to_string(), client);
+        drop(inner);
+
+        listener
+            .send(server_end)
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+
+        Ok(client_end)
+    }
+
+    fn lose_write(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let rate = inner.loss_rate;
+        inner.rng.chance(rate)
+    }
+}
+
+impl fmt::Debug for SimNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        f.debug_struct("SimNetwork")
+            .field("listeners", &inner.listeners.keys().collect::<Vec<_>>())
+            .field("partitioned", &inner.partitioned)
+            .field("connect_failure_rate", &inner.connect_failure_rate)
+            .field("loss_rate", &inner.loss_rate)
+            .field("connect_latency", &inner.connect_latency)
+            .finish()
+    }
+}
+
+/// A stream of connections accepted on a [`SimNetwork`].
+///
+/// Pass it to [`Server::serve_with_incoming`](crate::transport::Server::serve_with_incoming).
+#[derive(Debug)]
+pub struct SimListener {
+    host: String,
+    rx: mpsc::UnboundedReceiver<SimStream>,
+}
+
+impl SimListener {
+    /// The host this listener is bound to.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+}
+
+impl Stream for SimListener {
+    type Item = io::Result<SimStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx).map(|stream| stream.map(Ok))
+    }
+}
+
+/// Connects to the listeners of a [`SimNetwork`].
+#[derive(Debug, Clone)]
+pub struct SimConnector {
+    network: SimNetwork,
+}
+
+impl Service<http::Uri> for SimConnector {
+    type Response = TokioIo<SimStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: http::Uri) -> Self::Future {
+        let network = self.network.clone();
+        Box::pin(async move {
+            let host = uri
+                .authority()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing authority"))?
+                .to_string();
+
+            let latency = network.inner.lock().unwrap().connect_latency;
+            if !latency.is_zero() {
+                network.clock.sleep(latency).await;
+            }
+
+            network.connect(&host).map(TokioIo::new)
+        })
+    }
+}
+
+/// One end of a simulated connection.
+pub struct SimStream {
+    inner: Option<DuplexStream>,
+    network: SimNetwork,
+    info: SimConnectInfo,
+}
+
+impl SimStream {
+    fn new(inner: DuplexStream, network: SimNetwork, local: String, remote: String) -> Self {
+        SimStream {
+            inner: Some(inner),
+            network,
+            info: SimConnectInfo { local, remote },
+        }
+    }
+
+    fn inner(&mut self) -> io::Result<Pin<&mut DuplexStream>> {
+        self.inner
+            .as_mut()
+            .map(Pin::new)
+            .ok_or_else(|| io::ErrorKind::ConnectionReset.into())
+    }
+}
+
+impl fmt::Debug for SimStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimStream")
+            .field("info", &self.info)
+            .field("reset", &self.inner.is_none())
+            .finish()
+    }
+}
+
+impl AsyncRead for SimStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.inner() {
+            Ok(inner) => inner.poll_read(cx, buf),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncWrite for SimStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.inner.is_some() && self.network.lose_write() {
+            tracing::debug!(
+                local = %self.info.local,
+                remote = %self.info.remote,
+                "simulated loss, resetting connection"
+            );
+            // Dropping our end makes the peer observe the reset as well.
+            self.inner = None;
+        }
+
+        match self.inner() {
+            Ok(inner) => inner.poll_write(cx, buf),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.inner() {
+            Ok(inner) => inner.poll_flush(cx),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.inner() {
+            Ok(inner) => inner.poll_shutdown(cx),
+            Err(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl Connected for SimStream {
+    type ConnectInfo = SimConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.info.clone()
+    }
+}
+
+/// Connection info of a [`SimStream`], available through request extensions on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimConnectInfo {
+    local: String,
+    remote: String,
+}
+
+impl SimConnectInfo {
+    /// The name of this end of the connection.
+    pub fn local(&self) -> &str {
+        &self.local
+    }
+
+    /// The name of the other end of the connection.
+    pub fn remote(&self) -> &str {
+        &self.remote
+    }
+}
@@ -0,0 +1,32 @@
+/// A small deterministic generator (xorshift64*), so simulations do not depend on the
+/// randomness of the host.
+#[derive(Debug, Clone)]
+pub(crate) struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // The state must never be zero.
+        SimRng {
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A float in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns `true` with probability `rate`.
+    pub(crate) fn chance(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.next_f64() < rate
+    }
+}
@@ -4,10 +4,15 @@ use base64::Engine as _;
 use bytes::Bytes;
 use http::{
     header::{HeaderMap, HeaderValue},
-    HeaderName,
+    Extensions, HeaderName,
 };
 use percent_encoding::{percent_decode, percent_encode, AsciiSet, CONTROLS};
-use std::{borrow::Cow, error::Error, fmt, sync::Arc};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+};
 use tracing::{debug, trace, warn};
 
 const ENCODING_SET: &AsciiSet = &CONTROLS
@@ -22,6 +27,28 @@ const ENCODING_SET: &AsciiSet = &CONTROLS
     .add(b'{')
     .add(b'}');
 
+type ErrorMapper = Box<dyn Fn(&(dyn Error + 'static)) -> Option<Status> + Send + Sync>;
+
+// The converters registered with `Status::register_error_mapper`.
+static ERROR_MAPPERS: RwLock<Vec<ErrorMapper>> = RwLock::new(Vec::new());
+
+// The codes set with `Status::map_io_error_kind` and `Status::map_h2_reason`.
+static IO_ERROR_CODES: RwLock<Vec<(std::io::ErrorKind, Code)>> = RwLock::new(Vec::new());
+static H2_REASON_CODES: RwLock<Vec<(u32, Code)>> = RwLock::new(Vec::new());
+
+fn set_code<K: PartialEq>(codes: &RwLock<Vec<(K, Code)>>, key: K, code: Code) {
+    let mut codes = codes.write().unwrap_or_else(|e| e.into_inner());
+    match codes.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, c)) => *c = code,
+        None => codes.push((key, code)),
+    }
+}
+
+fn get_code<K: PartialEq>(codes: &RwLock<Vec<(K, Code)>>, key: &K) -> Option<Code> {
+    let codes = codes.read().unwrap_or_else(|e| e.into_inner());
+    codes.iter().find(|(k, _)| k == key).map(|&(_, code)| code)
+}
+
 /// A gRPC status describing the result of an RPC call.
 ///
 /// Values can be created using the `new` function or one of the specialized
@@ -52,6 +79,8 @@ struct StatusInner {
     metadata: MetadataMap,
     /// Optional underlying error.
     source: Option<Arc<dyn Error + Send + Sync + 'static>>,
+    /// Process-local data, which is never sent to the peer.
+    extensions: Extensions,
 }
 
 impl StatusInner {
@@ -60,6 +89,54 @@ impl StatusInner {
     }
 }
 
+/// A builder of [`Status`]es, created with [`Status::builder`].
+#[derive(Debug)]
+#[must_use]
+pub struct StatusBuilder(StatusInner);
+
+impl StatusBuilder {
+    /// Sets the text error message.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.0.message = message.into();
+        self
+    }
+
+    /// Sets the opaque binary details.
+    pub fn details(mut self, details: impl Into<Bytes>) -> Self {
+        self.0.details = details.into();
+        self
+    }
+
+    /// Sets the custom metadata, replacing any metadata set before.
+    pub fn metadata(mut self, metadata: MetadataMap) -> Self {
+        self.0.metadata = metadata;
+        self
+    }
+
+    /// Sets the underlying error.
+    pub fn source(mut self, source: impl Into<Box<dyn Error + Send + Sync + 'static>>) -> Self {
+        self.0.source = Some(source.into().into());
+        self
+    }
+
+    /// Inserts a process-local extension, see [`Status::extensions`].
+    pub fn extension<T: Clone + Send + Sync + 'static>(mut self, extension: T) -> Self {
+        self.0.extensions.insert(extension);
+        self
+    }
+
+    /// Builds the `Status`.
+    pub fn build(self) -> Status {
+        self.0.into_status()
+    }
+}
+
+impl From<StatusBuilder> for Status {
+    fn from(builder: StatusBuilder) -> Self {
+        builder.build()
+    }
+}
+
 /// gRPC status codes used by [`Status`].
 ///
 /// These variants match the [gRPC status codes].
@@ -176,10 +253,33 @@ impl Status {
             details: Bytes::new(),
             metadata: MetadataMap::new(),
             source: None,
+            extensions: Extensions::new(),
         }
         .into_status()
     }
 
+    /// Create a [`StatusBuilder`] of `Status`es with the associated code.
+    ///
+    /// ```
+    /// use tonic::{metadata::MetadataMap, Code, Status};
+    ///
+    /// let mut metadata = MetadataMap::new();
+    /// metadata.insert("x-retry-after", "5".parse().unwrap());
+    ///
+    /// let status = Status::builder(Code::Unavailable)
+    ///     .message("try again later")
+    ///     .metadata(metadata)
+    ///     .details(&b"details"[..])
+    ///     .source("connection pool exhausted")
+    ///     .build();
+    ///
+    /// assert_eq!(status.message(), "try again later");
+    /// assert_eq!(status.metadata().get("x-retry-after").unwrap(), "5");
+    /// ```
+    pub fn builder(code: Code) -> StatusBuilder {
+        StatusBuilder(*Status::new(code, "").0)
+    }
+
     /// The operation completed successfully.
     pub fn ok(message: impl Into<String>) -> Status {
         Status::new(Code::Ok, message)
@@ -322,13 +422,94 @@ impl Status {
         Self::from_error(err.into())
     }
 
+    /// Registers a converter from errors of type `E` to `Status`, used crate-wide by
+    /// [`Status::from_error`] and [`Status::try_from_error`] when an error of type `E` is found in
+    /// the source chain of an error.
+    ///
+    /// This lets domain and third-party errors map to accurate codes instead of `UNKNOWN`, e.g.
+    /// a database "row not found" error to `NOT_FOUND`. Converters are meant to be registered
+    /// once, at startup. A `Status` in the source chain takes precedence over the converters,
+    /// and converters registered earlier take precedence over later ones. A converter must not
+    /// itself register a converter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tonic::{Code, Status};
+    ///
+    /// #[derive(Debug)]
+    /// struct NotFound(String);
+    ///
+    /// impl std::fmt::Display for NotFound {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    ///         write!(f, "{} not found", self.0)
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for NotFound {}
+    ///
+    /// Status::register_error_mapper(|err: &NotFound| Status::not_found(err.to_string()));
+    ///
+    /// let status = Status::from_error(Box::new(NotFound("user".into())));
+    /// assert_eq!(status.code(), Code::NotFound);
+    /// assert_eq!(status.message(), "user not found");
+    /// ```
+    pub fn register_error_mapper<E, F>(mapper: F)
+    where
+        E: Error + 'static,
+        F: Fn(&E) -> Status + Send + Sync + 'static,
+    {
+        let mapper: ErrorMapper = Box::new(move |err| err.downcast_ref::<E>().map(&mapper));
+        ERROR_MAPPERS
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(mapper);
+    }
+
+    /// Maps the `std::io::Error`s of this `kind` to `code` crate-wide, in [`Status::from_error`]
+    /// and [`Status::try_from_error`].
+    ///
+    /// By default, I/O errors are not recognized and end up as an `UNKNOWN` status, unless
+    /// they occur while connecting. This is meant to be called once, at startup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    /// use tonic::{Code, Status};
+    ///
+    /// Status::map_io_error_kind(io::ErrorKind::ConnectionReset, Code::Unavailable);
+    ///
+    /// let err = io::Error::from(io::ErrorKind::ConnectionReset);
+    /// assert_eq!(Status::from_error(Box::new(err)).code(), Code::Unavailable);
+    /// ```
+    pub fn map_io_error_kind(kind: std::io::ErrorKind, code: Code) {
+        set_code(&IO_ERROR_CODES, kind, code);
+    }
+
+    /// Maps the HTTP/2 errors with this `reason` code, e.g. `0x8` for `CANCEL`, to `code`
+    /// crate-wide, in [`Status::from_error`] and [`Status::try_from_error`].
+    ///
+    /// This overrides the default mapping described in the [gRPC over HTTP/2 spec]. This is
+    /// meant to be called once, at startup.
+    ///
+    /// [gRPC over HTTP/2 spec]: https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md#errors
+    pub fn map_h2_reason(reason: u32, code: Code) {
+        set_code(&H2_REASON_CODES, reason, code);
+    }
+
     /// Create a `Status` from various types of `Error`.
     ///
-    /// Inspects the error source chain for recognizable errors, including statuses, HTTP2, and
-    /// hyper, and attempts to maps them to a `Status`, or else returns an Unknown `Status`.
+    /// Inspects the error source chain for recognizable errors, including statuses, HTTP2,
+    /// hyper, I/O errors mapped with [`Status::map_io_error_kind`] and the errors with a
+    /// converter registered with [`Status::register_error_mapper`], and attempts to maps them
+    /// to a `Status`, or else returns an Unknown `Status`.
     pub fn from_error(err: Box<dyn Error + Send + Sync + 'static>) -> Status {
         Status::try_from_error(err).unwrap_or_else(|err| {
             let mut status = Status::new(Code::Unknown, err.to_string());
+            if let Some(cause) = TransportCause::find(&*err) {
+                status.0.extensions.insert(cause);
+            }
             status.0.source = Some(err.into());
             status
         })
@@ -351,6 +532,17 @@ impl Status {
             Err(err) => err,
         };
 
+        let cause = TransportCause::find(&*err);
+        let mut status = Self::try_from_non_status_error(err)?;
+        if let Some(cause) = cause {
+            status.0.extensions.insert(cause);
+        }
+        Ok(status)
+    }
+
+    fn try_from_non_status_error(
+        err: Box<dyn Error + Send + Sync + 'static>,
+    ) -> Result<Status, Box<dyn Error + Send + Sync + 'static>> {
         #[cfg(feature = "server")]
         let err = match err.downcast::<h2::Error>() {
             Ok(h2) => {
@@ -391,6 +583,13 @@ impl Status {
 
     #[cfg(feature = "server")]
     fn code_from_h2(err: &h2::Error) -> Code {
+        if let Some(code) = err
+            .reason()
+            .and_then(|reason| get_code(&H2_REASON_CODES, &u32::from(reason)))
+        {
+            return code;
+        }
+
         // See https://github.com/grpc/grpc/blob/3977c30/doc/PROTOCOL-HTTP2.md#errors
         match err.reason() {
             Some(h2::Reason::NO_ERROR)
@@ -503,6 +702,7 @@ impl Status {
                 details,
                 metadata: MetadataMap::from_headers(other_headers),
                 source: None,
+                extensions: Extensions::new(),
             }
             .into_status(),
         )
@@ -591,6 +791,7 @@ impl Status {
             details,
             metadata,
             source: None,
+            extensions: Extensions::new(),
         }
         .into_status()
     }
@@ -601,6 +802,45 @@ impl Status {
         self
     }
 
+    /// Get the transport-level failure that caused this `Status`, if any.
+    ///
+    /// This is set on the statuses created by [`Status::from_error`], e.g. by a client when a
+    /// call fails because the connection could not be established or was reset, and lets
+    /// callers tell such failures apart programmatically. It is never sent to the peer.
+    pub fn transport_cause(&self) -> Option<TransportCause> {
+        self.0.extensions.get::<TransportCause>().copied()
+    }
+
+    /// Get a reference to the extensions of this `Status`.
+    ///
+    /// Extensions hold typed, process-local data, e.g. a backtrace, an internal error or a
+    /// trace ID, for interceptors and logging layers on the same side of the call. Unlike the
+    /// metadata, they are never sent to the peer.
+    pub fn extensions(&self) -> &Extensions {
+        &self.0.extensions
+    }
+
+    /// Get a mutable reference to the extensions of this `Status`.
+    ///
+    /// ```
+    /// use tonic::Status;
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct TraceId(u64);
+    ///
+    /// let mut status = Status::internal("oops");
+    /// status.extensions_mut().insert(TraceId(42));
+    ///
+    /// assert_eq!(status.extensions().get::<TraceId>(), Some(&TraceId(42)));
+    ///
+    /// let mut trailers = http::HeaderMap::new();
+    /// status.add_header(&mut trailers).unwrap();
+    /// assert_eq!(trailers.len(), 2);
+    /// ```
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.0.extensions
+    }
+
     /// Build an `http::Response` from the given `Status`.
     pub fn into_http<B: Default>(self) -> http::Response<B> {
         let mut response = http::Response::new(B::default());
@@ -621,6 +861,7 @@ impl Status {
 }
 
 fn find_status_in_source_chain(err: &(dyn Error + 'static)) -> Option<Status> {
+    let mappers = ERROR_MAPPERS.read().unwrap_or_else(|e| e.into_inner());
     let mut source = Some(err);
 
     while let Some(err) = source {
@@ -634,11 +875,23 @@ fn find_status_in_source_chain(err: &(dyn Error + 'static)) -> Option<Status> {
                     // Since `Status` is not `Clone`, any `source` on the original Status
                     // cannot be cloned so must remain with the original `Status`.
                     source: None,
+                    extensions: status.0.extensions.clone(),
                 }
                 .into_status(),
             );
         }
 
+        if let Some(status) = mappers.iter().find_map(|mapper| mapper(err)) {
+            return Some(status);
+        }
+
+        if let Some(code) = err
+            .downcast_ref::<std::io::Error>()
+            .and_then(|io| get_code(&IO_ERROR_CODES, &io.kind()))
+        {
+            return Some(Status::new(code, err.to_string()));
+        }
+
         if let Some(timeout) = err.downcast_ref::<TimeoutExpired>() {
             return Some(Status::cancelled(timeout.to_string()));
         }
@@ -693,6 +946,10 @@ impl fmt::Debug for StatusInner {
 
         builder.field("source", &self.source);
 
+        if !self.extensions.is_empty() {
+            builder.field("extensions", &self.extensions);
+        }
+
         builder.finish()
     }
 }
@@ -782,6 +1039,25 @@ pub(crate) fn infer_grpc_status(
         }
     }
     trace!("trailers missing grpc-status");
+
+    // We got a 200 but no trailers, we can infer that this request is finished.
+    //
+    // This can happen when a streaming response sends two Status but
+    // gRPC requires that we end the stream after the first status.
+    //
+    // https://github.com/hyperium/tonic/issues/681
+    if status_code == http::StatusCode::OK {
+        return Err(None);
+    }
+
+    Err(Some(status_from_http_status(status_code, None)))
+}
+
+/// Build a `Status` for a response that carries no `grpc-status`, based on its HTTP status code.
+///
+/// `body` is an optional snippet of the response body, included in the message to help
+/// diagnosing responses that were produced by something other than a gRPC server, e.g. a proxy.
+pub(crate) fn status_from_http_status(status_code: http::StatusCode, body: Option<&str>) -> Status {
     let code = match status_code {
         // Borrowed from https://github.com/grpc/grpc/blob/master/doc/http-grpc-status-mapping.md
         http::StatusCode::BAD_REQUEST => Code::Internal,
@@ -792,22 +1068,18 @@ pub(crate) fn infer_grpc_status(
         | http::StatusCode::BAD_GATEWAY
         | http::StatusCode::SERVICE_UNAVAILABLE
         | http::StatusCode::GATEWAY_TIMEOUT => Code::Unavailable,
-        // We got a 200 but no trailers, we can infer that this request is finished.
-        //
-        // This can happen when a streaming response sends two Status but
-        // gRPC requires that we end the stream after the first status.
-        //
-        // https://github.com/hyperium/tonic/issues/681
-        http::StatusCode::OK => return Err(None),
         _ => Code::Unknown,
     };
 
-    let msg = format!(
-        "grpc-status header missing, mapped from HTTP status code {}",
-        status_code.as_u16(),
-    );
-    let status = Status::new(code, msg);
-    Err(status.into())
+    let http_code = status_code.as_u16();
+    let msg = match body {
+        Some(body) if !body.is_empty() => {
+            format!("grpc-status header missing, mapped from HTTP status code {http_code}, body: {body:?}")
+        }
+        _ => format!("grpc-status header missing, mapped from HTTP status code {http_code}"),
+    };
+
+    Status::new(code, msg)
 }
 
 // ===== impl Code =====
@@ -1047,6 +1319,148 @@ mod tests {
 
         assert_eq!(status.details(), DETAILS);
     }
+
+    #[test]
+    fn registered_error_mapper() {
+        #[derive(Debug)]
+        struct Domain;
+
+        impl fmt::Display for Domain {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("domain")
+            }
+        }
+
+        impl Error for Domain {}
+
+        assert!(Status::try_from_error(Box::new(Domain)).is_err());
+
+        Status::register_error_mapper(|err: &Domain| Status::failed_precondition(err.to_string()));
+        Status::register_error_mapper(|_: &Domain| Status::internal("ignored"));
+
+        let found = Status::from_error(Box::new(Nested(Box::new(Domain))));
+        assert_eq!(found.code(), Code::FailedPrecondition);
+        assert_eq!(found.message(), "domain");
+        assert!(found.source().unwrap().downcast_ref::<Nested>().is_some());
+
+        let orig = Nested(Box::new(Status::new(Code::OutOfRange, "weeaboo")));
+        assert_eq!(Status::from_error(Box::new(orig)).code(), Code::OutOfRange);
+    }
+
+    #[test]
+    fn transport_cause() {
+        #[derive(Debug)]
+        struct Dns(std::io::Error);
+
+        impl fmt::Display for Dns {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("dns error")
+            }
+        }
+
+        impl Error for Dns {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let status = Status::from_error(Box::new(ConnectError(Box::new(refused))));
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(status.transport_cause(), Some(TransportCause::Connect));
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let dns = Dns(std::io::Error::other("failed to lookup address"));
+        let status = Status::from_error(Box::new(ConnectError(Box::new(reset))));
+        assert_eq!(status.transport_cause(), Some(TransportCause::Connect));
+        let status = Status::from_error(Box::new(ConnectError(Box::new(dns))));
+        assert_eq!(status.transport_cause(), Some(TransportCause::Dns));
+
+        let reset = std::io::Error::other(Nested(Box::new(std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset,
+        ))));
+        let status = Status::from_error(Box::new(reset));
+        assert_eq!(status.code(), Code::Unknown);
+        assert_eq!(
+            status.transport_cause(),
+            Some(TransportCause::ConnectionClosed)
+        );
+
+        let status = Status::from_error(Box::new(Status::unavailable("")));
+        assert_eq!(status.transport_cause(), None);
+    }
+
+    #[test]
+    fn mapped_io_error_kind_and_h2_reason() {
+        let err = std::io::Error::from(std::io::ErrorKind::StorageFull);
+        assert!(Status::try_from_error(Box::new(err)).is_err());
+
+        Status::map_io_error_kind(std::io::ErrorKind::StorageFull, Code::Internal);
+        Status::map_io_error_kind(std::io::ErrorKind::StorageFull, Code::ResourceExhausted);
+
+        let err = Nested(Box::new(std::io::Error::from(
+            std::io::ErrorKind::StorageFull,
+        )));
+        let status = Status::from_error(Box::new(err));
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        #[cfg(feature = "server")]
+        {
+            let reason = h2::Reason::HTTP_1_1_REQUIRED;
+            assert_eq!(Status::from(h2::Error::from(reason)).code(), Code::Unknown);
+            Status::map_h2_reason(reason.into(), Code::Unimplemented);
+            assert_eq!(
+                Status::from(h2::Error::from(reason)).code(),
+                Code::Unimplemented
+            );
+        }
+    }
+
+    #[test]
+    fn builder() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct TraceId(u64);
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-key", "value".parse().unwrap());
+
+        let status = Status::builder(Code::Aborted)
+            .message("some message")
+            .details(&[1, 2][..])
+            .metadata(metadata)
+            .source(Nested("inner".into()))
+            .extension(TraceId(42))
+            .build();
+
+        assert_eq!(status.code(), Code::Aborted);
+        assert_eq!(status.message(), "some message");
+        assert_eq!(status.details(), [1, 2]);
+        assert_eq!(status.metadata().get("x-key").unwrap(), "value");
+        assert!(status.source().unwrap().is::<Nested>());
+        assert_eq!(status.extensions().get::<TraceId>(), Some(&TraceId(42)));
+
+        let status: Status = Status::builder(Code::Internal).into();
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "");
+        assert!(status.source().is_none());
+    }
+
+    #[test]
+    fn extensions() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct TraceId(u64);
+
+        let mut status = Status::internal("some message");
+        status.extensions_mut().insert(TraceId(42));
+
+        let header_map = status.to_header_map().unwrap();
+        assert_eq!(header_map.len(), 2);
+        let status_from_headers = Status::from_header_map(&header_map).unwrap();
+        assert!(status_from_headers.extensions().is_empty());
+
+        let found = Status::from_error(Box::new(Nested(Box::new(status))));
+        assert_eq!(found.extensions().get::<TraceId>(), Some(&TraceId(42)));
+    }
 }
 
 /// Error returned if a request didn't complete within the configured timeout.
@@ -1069,6 +1483,110 @@ impl fmt::Display for TimeoutExpired {
 // std::error::Error only requires a type to impl Debug and Display
 impl std::error::Error for TimeoutExpired {}
 
+/// A transport-level failure that caused a [`Status`], see [`Status::transport_cause`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TransportCause {
+    /// Resolving the host name of the endpoint failed.
+    Dns,
+    /// Connecting to the endpoint failed, e.g. the connection was refused or timed out.
+    Connect,
+    /// The TLS handshake failed, e.g. the certificate of the peer was rejected.
+    Tls,
+    /// The peer reset the stream of the call with an HTTP/2 `RST_STREAM` frame carrying this
+    /// error code, e.g. `0x7` (`REFUSED_STREAM`) when the server refused the stream.
+    StreamReset(u32),
+    /// The connection was shut down with an HTTP/2 `GOAWAY` frame carrying this error code.
+    GoAway(u32),
+    /// The connection was closed or broken while the call was in progress.
+    ConnectionClosed,
+    /// The peer stopped responding to HTTP/2 keep-alive pings.
+    KeepAliveTimeout,
+}
+
+impl TransportCause {
+    /// Finds the transport-level failure in the source chain of `err`.
+    fn find(err: &(dyn Error + 'static)) -> Option<Self> {
+        let mut connecting = false;
+        let mut source = Some(err);
+
+        while let Some(err) = source {
+            if let Some(cause) = Self::from_single_error(err, connecting) {
+                return Some(cause);
+            }
+            connecting |= err.is::<ConnectError>();
+
+            // `io::Error` does not return the error it wraps as its source.
+            source = match err.downcast_ref::<std::io::Error>() {
+                Some(io) => io.get_ref().map(|inner| inner as _),
+                None => err.source(),
+            };
+        }
+
+        connecting.then_some(Self::Connect)
+    }
+
+    fn from_single_error(err: &(dyn Error + 'static), connecting: bool) -> Option<Self> {
+        #[cfg(feature = "_tls-any")]
+        if err.is::<tokio_rustls::rustls::Error>() {
+            return Some(Self::Tls);
+        }
+
+        #[cfg(all(feature = "_tls-any", any(feature = "server", feature = "channel")))]
+        if err.is::<crate::transport::service::tls::TlsError>() {
+            return Some(Self::Tls);
+        }
+
+        if connecting {
+            // The error of the connector of `hyper-util` is not exported, but its message is.
+            return (err.to_string() == "dns error").then_some(Self::Dns);
+        }
+
+        #[cfg(any(feature = "server", feature = "channel"))]
+        if let Some(h2) = err.downcast_ref::<h2::Error>() {
+            let reason = h2.reason().map_or(0, u32::from);
+            if h2.is_go_away() {
+                return Some(Self::GoAway(reason));
+            }
+            if h2.is_reset() && h2.is_remote() {
+                return Some(Self::StreamReset(reason));
+            }
+            if h2.is_io() {
+                return Some(Self::ConnectionClosed);
+            }
+        }
+
+        #[cfg(any(feature = "server", feature = "channel"))]
+        if let Some(hyper) = err.downcast_ref::<hyper::Error>() {
+            if hyper.is_timeout() {
+                return Some(Self::KeepAliveTimeout);
+            }
+            // hyper cancels the pending requests of a connection that closed with this message.
+            let closed = hyper.is_canceled()
+                && hyper
+                    .source()
+                    .is_some_and(|err| err.to_string() == "connection closed");
+            if closed || hyper.is_closed() || hyper.is_incomplete_message() {
+                return Some(Self::ConnectionClosed);
+            }
+        }
+
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            ) {
+                return Some(Self::ConnectionClosed);
+            }
+        }
+
+        None
+    }
+}
+
 /// Wrapper type to indicate that an error occurs during the connection
 /// process, so that the appropriate gRPC Status can be inferred.
 #[derive(Debug)]
@@ -0,0 +1,215 @@
+use crate::{
+    body::Body,
+    codec::{Codec, EncodeBody, SingleMessageCompressionOverride, Streaming},
+    Status,
+};
+use http::{header, HeaderValue, Request, Response};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tower_service::Service;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+type Handler = Box<dyn FnOnce(Request<Body>) -> BoxFuture<Response<Body>> + Send>;
+type Check<T> = Box<dyn FnOnce(Vec<T>) + Send>;
+
+/// A scripted gRPC service to make the calls of a generated client against in unit tests.
+///
+/// See the [module level documentation](crate::testing) for more details.
+#[derive(Clone, Default)]
+pub struct MockChannel {
+    script: Arc<Mutex<VecDeque<ExpectedCall>>>,
+}
+
+struct ExpectedCall {
+    path: String,
+    handler: Handler,
+}
+
+impl MockChannel {
+    /// Create a mock that expects no calls.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects a call of the method with the `path`, e.g. `/helloworld.Greeter/SayHello`, after
+    /// the calls already expected.
+    ///
+    /// `codec` decodes the request messages and encodes the response messages of the call. The
+    /// call is only expected once the returned [`Expectation`] is given its response.
+    pub fn expect<C: Codec>(&self, path: impl Into<String>, codec: C) -> Expectation<C> {
+        Expectation {
+            channel: self.clone(),
+            path: path.into(),
+            codec,
+            check: None,
+        }
+    }
+
+    /// Asserts that every expected call was made.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the paths of the calls that were not made, if any.
+    pub fn verify(&self) {
+        let script = self.script.lock().unwrap();
+        let missing = script.iter().map(|call| &call.path).collect::<Vec<_>>();
+        assert!(
+            missing.is_empty(),
+            "expected calls were not made: {missing:?}"
+        );
+    }
+}
+
+impl fmt::Debug for MockChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let script = self.script.lock().unwrap();
+        let expected = script.iter().map(|call| &call.path).collect::<Vec<_>>();
+        f.debug_struct("MockChannel")
+            .field("expected", &expected)
+            .finish()
+    }
+}
+
+impl Service<Request<Body>> for MockChannel {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let path = req.uri().path();
+        let next = self.script.lock().unwrap().pop_front();
+        let Some(expected) = next else {
+            panic!("unexpected call to {path}: no more calls were expected");
+        };
+        assert!(
+            path == expected.path,
+            "unexpected call to {path}: expected a call to {}",
+            expected.path
+        );
+
+        let response = (expected.handler)(req);
+        Box::pin(async move { Ok(response.await) })
+    }
+}
+
+/// A call expected by a [`MockChannel`], created with [`MockChannel::expect`].
+///
+/// The call is added to the script of the mock by giving it a response.
+#[must_use = "the call is only expected once it is given a response"]
+pub struct Expectation<C: Codec> {
+    channel: MockChannel,
+    path: String,
+    codec: C,
+    check: Option<Check<C::Decode>>,
+}
+
+impl<C> Expectation<C>
+where
+    C: Codec + Send + 'static,
+{
+    /// Asserts that the call sends a single request `message`.
+    pub fn request(self, message: C::Decode) -> Self
+    where
+        C::Decode: PartialEq + fmt::Debug,
+    {
+        self.requests([message])
+    }
+
+    /// Asserts that the call sends exactly the request `messages`, e.g. for client streaming
+    /// methods.
+    pub fn requests(self, messages: impl IntoIterator<Item = C::Decode>) -> Self
+    where
+        C::Decode: PartialEq + fmt::Debug,
+    {
+        let expected = messages.into_iter().collect::<Vec<_>>();
+        let path = self.path.clone();
+        Expectation {
+            check: Some(Box::new(move |received| {
+                assert_eq!(received, expected, "unexpected request messages for {path}");
+            })),
+            ..self
+        }
+    }
+
+    /// Answers the call with a single response `message` and an `OK` status.
+    pub fn respond(self, message: C::Encode) {
+        self.respond_stream([Ok(message)]);
+    }
+
+    /// Answers the call with the response messages of `items`, in order. The call completes
+    /// with the status of the first error, or `OK` if there is none.
+    pub fn respond_stream(self, items: impl IntoIterator<Item = Result<C::Encode, Status>>) {
+        let items = items.into_iter().collect::<Vec<_>>();
+        self.respond_with(move |codec| {
+            let body = EncodeBody::new_server(
+                codec.encoder(),
+                tokio_stream::iter(items),
+                None,
+                SingleMessageCompressionOverride::default(),
+                None,
+            );
+            let mut response = Response::new(Body::new(body));
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/grpc"),
+            );
+            response
+        });
+    }
+
+    /// Fails the call with `status`, without any response message.
+    pub fn respond_status(self, status: Status) {
+        self.respond_with(move |_| status.into_http());
+    }
+
+    fn respond_with(self, respond: impl FnOnce(&mut C) -> Response<Body> + Send + 'static) {
+        let Expectation {
+            channel,
+            path,
+            mut codec,
+            check,
+        } = self;
+
+        let handler: Handler = Box::new(move |req| {
+            Box::pin(async move {
+                let mut stream =
+                    Streaming::new_request(codec.decoder(), req.into_body(), None, None);
+                let mut received = Vec::new();
+                loop {
+                    match stream.message().await {
+                        Ok(Some(message)) => received.push(message),
+                        Ok(None) => break,
+                        Err(status) => return status.into_http(),
+                    }
+                }
+
+                if let Some(check) = check {
+                    check(received);
+                }
+                respond(&mut codec)
+            })
+        });
+
+        let call = ExpectedCall { path, handler };
+        channel.script.lock().unwrap().push_back(call);
+    }
+}
+
+impl<C: Codec> fmt::Debug for Expectation<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expectation")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
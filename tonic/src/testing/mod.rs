@@ -0,0 +1,96 @@
+//! Utilities to test gRPC clients and servers.
+//!
+//! # Mocking a server
+//!
+//! A [`MockChannel`] stands in for a `Channel` in generated clients. It is scripted with the
+//! calls it expects, in order, each with the request messages it should receive and the response
+//! it answers with:
+//!
+//! ```rust,ignore
+//! use tonic::testing::MockChannel;
+//! use tonic_prost::ProstCodec;
+//!
+//! let mock = MockChannel::new();
+//! mock.expect("/helloworld.Greeter/SayHello", ProstCodec::default())
+//!     .request(HelloRequest { name: "Tonic".into() })
+//!     .respond(HelloReply { message: "Hello Tonic!".into() });
+//! mock.expect("/helloworld.Greeter/SayHello", ProstCodec::<HelloReply, HelloRequest>::default())
+//!     .respond_status(Status::unavailable("try again later"));
+//!
+//! let mut client = GreeterClient::new(mock.clone());
+//! // ... exercise the code under test with `client`
+//!
+//! mock.verify();
+//! ```
+//!
+//! The codec of an expectation decodes the requests and encodes the responses like the codec of
+//! a server would, so for `tonic-prost` it is a `ProstCodec<Response, Request>`.
+//!
+//! Calls that were not expected, made in the wrong order or with other request messages make the
+//! mock panic, which fails the test making them. The request stream of a call is read to its end
+//! before the call is answered.
+//!
+//! # Running a server
+//!
+//! A [`TestServer`] serves a router on an ephemeral port, or over in-memory connections, and
+//! hands out a channel connected to it:
+//!
+//! ```rust,ignore
+//! use tonic::{testing::TestServer, transport::Server};
+//!
+//! let server = TestServer::start(
+//!     Server::builder().add_service(GreeterServer::new(MyGreeter::default())),
+//! )
+//! .await;
+//!
+//! let mut client = GreeterClient::new(server.channel());
+//! // ... make calls with `client`
+//!
+//! server.shutdown().await;
+//! ```
+//!
+//! A handler panicking fails the call with an `INTERNAL` status, and the test once the
+//! [`TestServer`] is shut down or dropped.
+//!
+//! # Recording and replaying calls
+//!
+//! A [`RecordLayer`] wrapping a server or a channel appends the calls going through it to a
+//! file, which a [`ReplayChannel`] then serves back to a generated client, e.g. to run
+//! deterministic regression tests against traffic captured in production:
+//!
+//! ```rust,ignore
+//! use tonic::testing::{RecordLayer, Recording, ReplayChannel};
+//!
+//! // In the service, record the calls it serves.
+//! Server::builder()
+//!     .layer(RecordLayer::to_file("greeter.jsonl")?)
+//!     .add_service(GreeterServer::new(MyGreeter::default()))
+//!     .serve(addr)
+//!     .await?;
+//!
+//! // In the test, answer the same calls with the recorded responses.
+//! let replay = ReplayChannel::new(Recording::open("greeter.jsonl")?);
+//! let mut client = GreeterClient::new(replay.clone());
+//! // ... exercise the code under test with `client`
+//!
+//! replay.verify();
+//! ```
+//!
+//! The recording holds one call per line of JSON, with the metadata and the encoded messages of
+//! its request and response, and the time each of them was sent at.
+
+mod mock;
+#[cfg(any(feature = "server", feature = "channel"))]
+mod record;
+#[cfg(any(feature = "server", feature = "channel"))]
+mod replay;
+#[cfg(all(feature = "router", feature = "server", feature = "channel"))]
+mod server;
+
+pub use self::mock::{Expectation, MockChannel};
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::record::{Record, RecordFuture, RecordLayer};
+#[cfg(any(feature = "server", feature = "channel"))]
+pub use self::replay::{Recording, ReplayChannel};
+#[cfg(all(feature = "router", feature = "server", feature = "channel"))]
+pub use self::server::{CatchPanic, CatchPanicFuture, TestServer};
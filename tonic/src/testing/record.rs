@@ -0,0 +1,359 @@
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock},
+};
+use base64::Engine as _;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{self, LineWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A [`Layer`] that records the RPCs it serves or sends, so that a [`ReplayChannel`] can serve
+/// them back later.
+///
+/// The layer can wrap a server with [`Server::layer`](crate::transport::Server::layer) or a
+/// channel. It records the method, the metadata and the raw, still encoded, messages of both the
+/// request and the response of every call, along with the time each of them was sent at. Calls
+/// that fail before a response is received, e.g. because the channel could not connect, are not
+/// recorded.
+///
+/// Every call is appended to the recording as a line of JSON once both its request and its
+/// response are done with. The writes are synchronous, and made from the task that completes
+/// the call.
+///
+/// [`ReplayChannel`]: super::ReplayChannel
+#[derive(Clone)]
+pub struct RecordLayer {
+    sink: Arc<Mutex<Box<dyn Write + Send>>>,
+    clock: SharedClock,
+}
+
+impl RecordLayer {
+    /// Appends the recorded calls to the file at `path`, creating it if needed.
+    pub fn to_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::to_writer(LineWriter::new(file)))
+    }
+
+    /// Writes the recorded calls to `writer`.
+    ///
+    /// `writer` is flushed after every call.
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        RecordLayer {
+            sink: Arc::new(Mutex::new(Box::new(writer))),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Sets the [`Clock`] used to time the messages. Defaults to the Tokio timer.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        RecordLayer {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
+    fn write(&self, call: &RecordedCall) {
+        let mut line = serde_json::to_vec(call).expect("recorded calls are serializable");
+        line.push(b'\n');
+
+        let mut sink = self.sink.lock().unwrap();
+        if let Err(error) = sink.write_all(&line).and_then(|()| sink.flush()) {
+            tracing::warn!(%error, method = call.method, "failed to record a call");
+        }
+    }
+}
+
+impl fmt::Debug for RecordLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordLayer").finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for RecordLayer {
+    type Service = Record<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Record {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that records RPCs.
+///
+/// See [`RecordLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct Record<S> {
+    inner: S,
+    layer: RecordLayer,
+}
+
+impl<S, ResBody> Service<Request<Body>> for Record<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = RecordFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let pending = Arc::new(Pending {
+            layer: self.layer.clone(),
+            start: self.layer.clock.now(),
+            call: Mutex::new(RecordedCall {
+                method: req.uri().path().to_owned(),
+                request: RecordedStream::new(req.headers(), Duration::ZERO),
+                response: None,
+            }),
+        });
+
+        let req = req.map(|body| {
+            Body::new(RecordBody {
+                inner: body,
+                pending: pending.clone(),
+                side: Side::Request,
+            })
+        });
+
+        RecordFuture {
+            inner: self.inner.call(req),
+            pending: Some(pending),
+        }
+    }
+}
+
+/// Response future for [`Record`].
+#[pin_project]
+pub struct RecordFuture<F> {
+    #[pin]
+    inner: F,
+    pending: Option<Arc<Pending>>,
+}
+
+impl<F> fmt::Debug for RecordFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordFuture").finish()
+    }
+}
+
+impl<F, ResBody, E> Future for RecordFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+        let pending = this.pending.take().expect("polled after completion");
+
+        let at = pending.elapsed();
+        pending.call.lock().unwrap().response = Some(RecordedStream::new(response.headers(), at));
+
+        Poll::Ready(Ok(response.map(|body| {
+            Body::new(RecordBody {
+                inner: body,
+                pending,
+                side: Side::Response,
+            })
+        })))
+    }
+}
+
+/// A call being recorded, written to the recording once dropped by both of its bodies.
+struct Pending {
+    layer: RecordLayer,
+    start: Instant,
+    call: Mutex<RecordedCall>,
+}
+
+impl Pending {
+    fn elapsed(&self) -> Duration {
+        self.layer.clock.now().saturating_duration_since(self.start)
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let call = self.call.get_mut().unwrap();
+        if call.response.is_some() {
+            self.layer.write(call);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Request,
+    Response,
+}
+
+/// A body whose frames are recorded.
+#[pin_project]
+struct RecordBody<B> {
+    #[pin]
+    inner: B,
+    pending: Arc<Pending>,
+    side: Side,
+}
+
+impl<B> http_body::Body for RecordBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(Ok(frame)) = &frame {
+            let at = this.pending.elapsed();
+            let mut call = this.pending.call.lock().unwrap();
+            let stream = match this.side {
+                Side::Request => Some(&mut call.request),
+                Side::Response => call.response.as_mut(),
+            };
+            if let Some(stream) = stream {
+                if let Some(data) = frame.data_ref() {
+                    stream.messages.push(RecordedData::new(data, at));
+                } else if let Some(trailers) = frame.trailers_ref() {
+                    stream.trailers = Some(to_pairs(trailers));
+                }
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A recorded call, as stored on a line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct RecordedCall {
+    pub(super) method: String,
+    pub(super) request: RecordedStream,
+    pub(super) response: Option<RecordedStream>,
+}
+
+/// The headers, data and trailers of one side of a recorded call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct RecordedStream {
+    /// The time the headers were sent at, since the start of the call, in microseconds.
+    pub(super) at: u64,
+    pub(super) headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub(super) messages: Vec<RecordedData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) trailers: Option<Vec<(String, String)>>,
+}
+
+impl RecordedStream {
+    fn new(headers: &HeaderMap, at: Duration) -> Self {
+        RecordedStream {
+            at: at.as_micros() as u64,
+            headers: to_pairs(headers),
+            messages: Vec::new(),
+            trailers: None,
+        }
+    }
+}
+
+/// A chunk of the encoded messages of a recorded call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct RecordedData {
+    /// The time the data was sent at, since the start of the call, in microseconds.
+    pub(super) at: u64,
+    /// The data, encoded in base64.
+    pub(super) data: String,
+}
+
+impl RecordedData {
+    fn new(data: &Bytes, at: Duration) -> Self {
+        RecordedData {
+            at: at.as_micros() as u64,
+            data: crate::util::base64::STANDARD.encode(data),
+        }
+    }
+
+    pub(super) fn decode(&self) -> io::Result<Bytes> {
+        crate::util::base64::STANDARD
+            .decode(&self.data)
+            .map(Bytes::from)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// Converts `headers` to name and value pairs, skipping the values that are not visible ASCII.
+fn to_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.as_str().to_owned(), value.to_str().ok()?.into())))
+        .collect()
+}
+
+/// Converts name and value pairs back to headers.
+pub(super) fn from_pairs(pairs: &[(String, String)]) -> io::Result<HeaderMap> {
+    let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+    let mut headers = HeaderMap::with_capacity(pairs.len());
+    for (name, value) in pairs {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+        let value = HeaderValue::from_str(value).map_err(|e| invalid(e.to_string()))?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+/// Reads the calls recorded in a file.
+pub(super) fn read(path: &Path) -> io::Result<Vec<RecordedCall>> {
+    from_reader(File::open(path)?)
+}
+
+/// Reads the calls recorded by `reader`, one per line.
+pub(super) fn from_reader(reader: impl io::Read) -> io::Result<Vec<RecordedCall>> {
+    let mut calls = Vec::new();
+    for line in io::BufRead::lines(io::BufReader::new(reader)) {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let call = serde_json::from_str(&line)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        calls.push(call);
+    }
+    Ok(calls)
+}
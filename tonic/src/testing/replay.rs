@@ -0,0 +1,311 @@
+use super::record::{self, RecordedCall, RecordedStream};
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock, Sleep},
+};
+use bytes::{Bytes, BytesMut};
+use http::{HeaderMap, Request, Response};
+use http_body::Frame;
+use http_body_util::BodyExt;
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    io,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_service::Service;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// The calls recorded by a [`RecordLayer`](super::RecordLayer).
+#[derive(Debug, Clone)]
+pub struct Recording {
+    calls: Vec<Call>,
+}
+
+/// A recorded call, decoded.
+#[derive(Debug, Clone)]
+struct Call {
+    method: String,
+    request: Bytes,
+    response: Stream,
+}
+
+/// One side of a recorded call, decoded.
+#[derive(Debug, Clone)]
+struct Stream {
+    at: Duration,
+    headers: HeaderMap,
+    messages: VecDeque<(Duration, Bytes)>,
+    trailers: Option<HeaderMap>,
+}
+
+impl Recording {
+    /// Reads the recording in the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_calls(record::read(path.as_ref())?)
+    }
+
+    /// Reads a recording from `reader`.
+    pub fn from_reader(reader: impl io::Read) -> io::Result<Self> {
+        Self::from_calls(record::from_reader(reader)?)
+    }
+
+    fn from_calls(calls: Vec<RecordedCall>) -> io::Result<Self> {
+        let calls = calls
+            .into_iter()
+            .filter_map(|call| {
+                let response = call.response?;
+                Some(Call::decode(call.method, call.request, response))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Recording { calls })
+    }
+
+    /// The number of calls in the recording.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Whether the recording has no calls.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// The methods of the recorded calls, in the order they were recorded in.
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.calls.iter().map(|call| call.method.as_str())
+    }
+}
+
+impl Call {
+    fn decode(
+        method: String,
+        request: RecordedStream,
+        response: RecordedStream,
+    ) -> io::Result<Self> {
+        let mut body = BytesMut::new();
+        for message in &request.messages {
+            body.extend_from_slice(&message.decode()?);
+        }
+
+        Ok(Call {
+            method,
+            request: body.freeze(),
+            response: Stream::decode(response)?,
+        })
+    }
+}
+
+impl Stream {
+    fn decode(stream: RecordedStream) -> io::Result<Self> {
+        let messages = stream
+            .messages
+            .iter()
+            .map(|message| Ok((Duration::from_micros(message.at), message.decode()?)))
+            .collect::<io::Result<_>>()?;
+        let trailers = stream
+            .trailers
+            .as_deref()
+            .map(record::from_pairs)
+            .transpose()?;
+
+        Ok(Stream {
+            at: Duration::from_micros(stream.at),
+            headers: record::from_pairs(&stream.headers)?,
+            messages,
+            trailers,
+        })
+    }
+}
+
+/// A gRPC service answering calls with the responses of a [`Recording`], to replay captured
+/// traffic against a generated client.
+///
+/// Every call is answered with the response of the first recorded call that was not replayed
+/// yet, with the same method and, unless [`match_requests`](Self::match_requests) is disabled,
+/// the same encoded request messages. The request stream of a call is read to its end before the
+/// call is answered.
+///
+/// Calls that match no recorded call make the channel panic, which fails the test making them.
+///
+/// See the [module level documentation](crate::testing) for more details.
+#[derive(Clone)]
+pub struct ReplayChannel {
+    calls: Arc<Mutex<Vec<Option<Call>>>>,
+    match_requests: bool,
+    timing: Option<SharedClock>,
+}
+
+impl ReplayChannel {
+    /// Create a channel replaying the calls of `recording`.
+    pub fn new(recording: Recording) -> Self {
+        ReplayChannel {
+            calls: Arc::new(Mutex::new(recording.calls.into_iter().map(Some).collect())),
+            match_requests: true,
+            timing: None,
+        }
+    }
+
+    /// Whether calls only match recorded calls with the same request messages. Defaults to
+    /// `true`.
+    ///
+    /// When disabled, calls are answered with the responses recorded for their method, in order,
+    /// whatever they send.
+    pub fn match_requests(self, enabled: bool) -> Self {
+        ReplayChannel {
+            match_requests: enabled,
+            ..self
+        }
+    }
+
+    /// Replays the responses with the delays they were recorded with, measured with the Tokio
+    /// timer. By default the responses are replayed as fast as possible.
+    pub fn with_timing(self) -> Self {
+        self.with_timing_clock(SharedClock::default())
+    }
+
+    /// Replays the responses with the delays they were recorded with, measured with `clock`.
+    pub fn with_timing_clock(self, clock: impl Clock) -> Self {
+        ReplayChannel {
+            timing: Some(SharedClock::new(clock)),
+            ..self
+        }
+    }
+
+    /// Asserts that every recorded call was replayed.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the methods of the calls that were not replayed, if any.
+    pub fn verify(&self) {
+        let calls = self.calls.lock().unwrap();
+        let missing = calls
+            .iter()
+            .flatten()
+            .map(|call| &call.method)
+            .collect::<Vec<_>>();
+        assert!(
+            missing.is_empty(),
+            "recorded calls were not replayed: {missing:?}"
+        );
+    }
+
+    fn take(&self, method: &str, request: Option<&Bytes>) -> Call {
+        let mut calls = self.calls.lock().unwrap();
+        let found = calls.iter_mut().find(|call| {
+            call.as_ref().is_some_and(|call| {
+                call.method == method && request.map_or(true, |request| call.request == *request)
+            })
+        });
+        let Some(call) = found.and_then(Option::take) else {
+            panic!(
+                "unexpected call to {method}: no recorded call that was not replayed matches it"
+            );
+        };
+        call
+    }
+}
+
+impl fmt::Debug for ReplayChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let calls = self.calls.lock().unwrap();
+        f.debug_struct("ReplayChannel")
+            .field("remaining", &calls.iter().flatten().count())
+            .field("match_requests", &self.match_requests)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Service<Request<Body>> for ReplayChannel {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let channel = self.clone();
+        let start = self
+            .timing
+            .as_ref()
+            .map(|clock| (clock.clone(), clock.now()));
+        Box::pin(async move {
+            let method = req.uri().path().to_owned();
+            let call = if channel.match_requests {
+                let request = match req.into_body().collect().await {
+                    Ok(request) => request.to_bytes(),
+                    Err(error) => panic!("failed to read the request of {method}: {error}"),
+                };
+                channel.take(&method, Some(&request))
+            } else {
+                channel.take(&method, None)
+            };
+
+            let response = call.response;
+            if let Some((clock, start)) = &start {
+                clock.sleep_until(*start + response.at).await;
+            }
+
+            let mut http = Response::new(Body::new(ReplayBody {
+                messages: response.messages,
+                trailers: response.trailers,
+                timing: start,
+                sleep: None,
+            }));
+            *http.headers_mut() = response.headers;
+            Ok(http)
+        })
+    }
+}
+
+/// A response body replaying recorded data and trailers.
+struct ReplayBody {
+    messages: VecDeque<(Duration, Bytes)>,
+    trailers: Option<HeaderMap>,
+    /// The clock and the start of the call, to replay the data at the recorded times.
+    timing: Option<(SharedClock, Instant)>,
+    sleep: Option<Sleep>,
+}
+
+impl http_body::Body for ReplayBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let Some(&(at, _)) = this.messages.front() else {
+            return Poll::Ready(
+                this.trailers
+                    .take()
+                    .map(|trailers| Ok(Frame::trailers(trailers))),
+            );
+        };
+
+        if let Some((clock, start)) = &this.timing {
+            let sleep = this
+                .sleep
+                .get_or_insert_with(|| clock.sleep_until(*start + at));
+            ready!(sleep.as_mut().poll(cx));
+            this.sleep = None;
+        }
+
+        let (_, data) = this.messages.pop_front().unwrap();
+        Poll::Ready(Some(Ok(Frame::data(data))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.messages.is_empty() && self.trailers.is_none()
+    }
+}
@@ -0,0 +1,325 @@
+use crate::{
+    body::Body,
+    service::Routes,
+    transport::{
+        server::{Router, TcpIncoming},
+        Channel, Endpoint, Error,
+    },
+    Status,
+};
+use bytes::Bytes;
+use http::{Request, Response, Uri};
+use hyper_util::rt::TokioIo;
+use pin_project::pin_project;
+use std::{
+    any::Any,
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::DuplexStream,
+    net::TcpListener,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_stream::Stream;
+use tower::service_fn;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The size of the buffers of the in-memory connections.
+const IN_MEMORY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The messages of the panics of the handlers of a server.
+type Panics = Arc<Mutex<Vec<String>>>;
+
+/// A server running in the background of a test, with a channel connected to it.
+///
+/// See the [module level documentation](crate::testing) for more details.
+pub struct TestServer {
+    uri: Uri,
+    channel: Channel,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<Result<(), Error>>>,
+    panics: Panics,
+}
+
+impl TestServer {
+    /// Serves `router` on an ephemeral port of the loopback interface, and connects to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no port can be bound or the channel cannot connect.
+    pub async fn start<L, ResBody>(router: Router<L>) -> Self
+    where
+        L: Layer<CatchPanic<Routes>> + Send + 'static,
+        L::Service:
+            Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request<Body>>>::Future: Send,
+        <L::Service as Service<Request<Body>>>::Error: Into<crate::BoxError> + Send + 'static,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::BoxError>,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind an ephemeral port");
+        let addr = listener.local_addr().expect("failed to get the bound port");
+        let uri: Uri = format!("http://{addr}").parse().unwrap();
+
+        let (panics, shutdown, task) = spawn(router, TcpIncoming::from(listener));
+        let channel = Endpoint::from(uri.clone())
+            .connect()
+            .await
+            .expect("failed to connect to the test server");
+
+        TestServer {
+            uri,
+            channel,
+            shutdown: Some(shutdown),
+            task: Some(task),
+            panics,
+        }
+    }
+
+    /// Serves `router` over in-memory connections, and connects to it.
+    ///
+    /// The server can only be reached through [`channel`](Self::channel), which establishes a
+    /// new in-memory connection whenever it (re)connects.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel cannot connect.
+    pub async fn start_in_memory<L, ResBody>(router: Router<L>) -> Self
+    where
+        L: Layer<CatchPanic<Routes>> + Send + 'static,
+        L::Service:
+            Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request<Body>>>::Future: Send,
+        <L::Service as Service<Request<Body>>>::Error: Into<crate::BoxError> + Send + 'static,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::BoxError>,
+    {
+        let (connections, incoming) = mpsc::unbounded_channel();
+        let (panics, shutdown, task) = spawn(router, InMemoryIncoming(incoming));
+
+        let uri = Uri::from_static("http://in-memory");
+        let channel = Endpoint::from(uri.clone())
+            .connect_with_connector(service_fn(move |_: Uri| {
+                let (client, server) = tokio::io::duplex(IN_MEMORY_BUFFER_SIZE);
+                let sent = connections.send(server);
+                async move {
+                    sent.map_err(|_| std::io::Error::other("the test server is shut down"))?;
+                    Ok::<_, std::io::Error>(TokioIo::new(client))
+                }
+            }))
+            .await
+            .expect("failed to connect to the test server");
+
+        TestServer {
+            uri,
+            channel,
+            shutdown: Some(shutdown),
+            task: Some(task),
+            panics,
+        }
+    }
+
+    /// A channel connected to the server.
+    pub fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+
+    /// The URI of the server, e.g. to connect to it with a differently configured
+    /// [`Endpoint`].
+    ///
+    /// Servers running [in memory](Self::start_in_memory) cannot be reached through it.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Shuts the server down gracefully, waiting for the calls in progress to complete.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a handler of the server panicked, or the server failed.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+
+        let task = self
+            .task
+            .take()
+            .expect("the server task is only taken once");
+        match task.await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => panic!("the test server failed: {error}"),
+            Err(error) => match error.try_into_panic() {
+                Ok(payload) => panic::resume_unwind(payload),
+                Err(error) => panic!("the test server task failed: {error}"),
+            },
+        }
+
+        self.check_panics();
+    }
+
+    fn check_panics(&self) {
+        let panics = std::mem::take(&mut *self.panics.lock().unwrap());
+        assert!(panics.is_empty(), "the test server panicked: {panics:?}");
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+
+        // Do not turn a failing test into an abort.
+        if !std::thread::panicking() {
+            self.check_panics();
+        }
+    }
+}
+
+impl fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestServer")
+            .field("uri", &self.uri)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Spawns a task serving `router` on `incoming`, returning the panics of its handlers.
+fn spawn<L, ResBody, I, IO>(
+    router: Router<L>,
+    incoming: I,
+) -> (Panics, oneshot::Sender<()>, JoinHandle<Result<(), Error>>)
+where
+    L: Layer<CatchPanic<Routes>> + Send + 'static,
+    L::Service:
+        Service<Request<Body>, Response = Response<ResBody>> + Clone + Send + Sync + 'static,
+    <L::Service as Service<Request<Body>>>::Future: Send,
+    <L::Service as Service<Request<Body>>>::Error: Into<crate::BoxError> + Send + 'static,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+    I: Stream<Item = Result<IO, std::io::Error>> + Send + 'static,
+    IO: tokio::io::AsyncRead
+        + tokio::io::AsyncWrite
+        + crate::transport::server::Connected
+        + Unpin
+        + Send
+        + 'static,
+{
+    let panics = Panics::default();
+    let (shutdown, signal) = oneshot::channel::<()>();
+    let (server, routes) = router.into_parts();
+    let svc = CatchPanic {
+        inner: routes,
+        panics: panics.clone(),
+    };
+
+    let task = tokio::spawn(async move {
+        server
+            .serve_with_incoming_shutdown(svc, incoming, async {
+                let _ = signal.await;
+            })
+            .await
+    });
+
+    (panics, shutdown, task)
+}
+
+/// The connections made to a server running in memory.
+struct InMemoryIncoming(mpsc::UnboundedReceiver<DuplexStream>);
+
+impl Stream for InMemoryIncoming {
+    type Item = Result<DuplexStream, std::io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx).map(|io| io.map(Ok))
+    }
+}
+
+/// The routes served by a [`TestServer`], answering the calls whose handler panicked with an
+/// `INTERNAL` status and remembering the panic to fail the test.
+///
+/// The layers of the router given to a [`TestServer`] wrap this service.
+#[derive(Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+    panics: Panics,
+}
+
+impl<S> fmt::Debug for CatchPanic<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanic").finish_non_exhaustive()
+    }
+}
+
+impl<S> Service<Request<Body>> for CatchPanic<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = CatchPanicFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        CatchPanicFuture {
+            inner: self.inner.call(req),
+            panics: self.panics.clone(),
+        }
+    }
+}
+
+/// Response future for [`CatchPanic`].
+#[pin_project]
+pub struct CatchPanicFuture<F> {
+    #[pin]
+    inner: F,
+    panics: Panics,
+}
+
+impl<F> fmt::Debug for CatchPanicFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchPanicFuture").finish()
+    }
+}
+
+impl<F, E> Future for CatchPanicFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = this.inner;
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                this.panics.lock().unwrap().push(panic_message(&*payload));
+                Poll::Ready(Ok(Status::internal("the handler panicked").into_http()))
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
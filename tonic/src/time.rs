@@ -0,0 +1,155 @@
+//! Abstractions over the passage of time.
+//!
+//! By default tonic uses the Tokio timer for all time related work: enforcing deadlines,
+//! scheduling HTTP/2 keepalive pings, ejecting outliers and so on. A custom [`Clock`] can be
+//! injected through the builders, e.g. [`Endpoint::clock`](crate::transport::Endpoint::clock)
+//! and [`Server::clock`](crate::transport::Server::clock), to run tonic under a simulated clock.
+//!
+//! A channel uses nothing but its clock for timers, so together with a custom
+//! [`executor`](crate::transport::Endpoint::executor) and a custom
+//! [connector](crate::transport::Endpoint::connect_with_connector) it does not need a Tokio
+//! runtime, and can run on other async runtimes by providing a [`Clock`] backed by their timer.
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// A future returned by [`Clock::sleep_until`].
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + Sync>>;
+
+/// A source of time.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Returns a future that completes once `deadline` has been reached.
+    fn sleep_until(&self, deadline: Instant) -> Sleep;
+
+    /// Returns a future that completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(self.now() + duration)
+    }
+}
+
+/// The default [`Clock`], backed by the Tokio timer.
+///
+/// This honors `tokio::time::pause` and friends.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A cheaply cloneable [`Clock`].
+#[derive(Clone)]
+pub(crate) struct SharedClock {
+    inner: Arc<dyn Clock>,
+}
+
+impl SharedClock {
+    pub(crate) fn new(clock: impl Clock) -> Self {
+        Self {
+            inner: Arc::new(clock),
+        }
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(TokioClock)
+    }
+}
+
+impl Clock for SharedClock {
+    fn now(&self) -> Instant {
+        self.inner.now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        self.inner.sleep_until(deadline)
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.inner.sleep(duration)
+    }
+}
+
+#[cfg(feature = "channel")]
+impl SharedClock {
+    /// Resolves to `future`'s output, or to an error if it did not complete within `duration`.
+    pub(crate) fn timeout<F: Future>(&self, duration: Duration, future: F) -> Timeout<F> {
+        Timeout {
+            future,
+            sleep: Clock::sleep(self, duration),
+        }
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedClock").finish()
+    }
+}
+
+/// A future returned by [`SharedClock::timeout`].
+#[cfg(feature = "channel")]
+#[pin_project::pin_project]
+pub(crate) struct Timeout<F> {
+    #[pin]
+    future: F,
+    sleep: Sleep,
+}
+
+#[cfg(feature = "channel")]
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, ()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        this.sleep.as_mut().poll(cx).map(Err)
+    }
+}
+
+impl hyper::rt::Timer for SharedClock {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn hyper::rt::Sleep>> {
+        Box::pin(HyperSleep(Clock::sleep(self, duration)))
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Pin<Box<dyn hyper::rt::Sleep>> {
+        Box::pin(HyperSleep(Clock::sleep_until(self, deadline)))
+    }
+}
+
+/// Adapts a [`Sleep`] to the hyper timer.
+struct HyperSleep(Sleep);
+
+impl Future for HyperSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl hyper::rt::Sleep for HyperSleep {}
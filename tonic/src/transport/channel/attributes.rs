@@ -0,0 +1,81 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// A set of attributes attached to an [`Endpoint`](super::Endpoint).
+///
+/// Attributes are arbitrary key/value pairs, such as the zone, version or
+/// capacity of a backend, that describe an endpoint. They are carried along
+/// with the endpoint when it is inserted into a balanced [`Channel`](super::Channel)
+/// so load balancing can take them into account, and a copy is added to the
+/// extensions of every response served by that endpoint.
+///
+/// ```
+/// # use tonic::transport::Endpoint;
+/// let endpoint = Endpoint::from_static("http://10.0.0.1:50051")
+///     .attribute("zone", "us-east-1a")
+///     .attribute("version", "canary");
+///
+/// assert_eq!(endpoint.get_attributes().get("zone"), Some("us-east-1a"));
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct EndpointAttributes {
+    inner: Arc<BTreeMap<String, String>>,
+}
+
+impl EndpointAttributes {
+    /// Create an empty set of attributes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the value of the attribute `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.inner.get(key).map(String::as_str)
+    }
+
+    /// Insert an attribute, returning the previous value of `key`, if any.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        Arc::make_mut(&mut self.inner).insert(key.into(), value.into())
+    }
+
+    /// Remove the attribute `key`, returning its value, if any.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        Arc::make_mut(&mut self.inner).remove(key)
+    }
+
+    /// Returns `true` if there are no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the number of attributes.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// An iterator over the attributes, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for EndpointAttributes
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            inner: Arc::new(
+                iter.into_iter()
+                    .map(|(k, v)| (k.into(), v.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for EndpointAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.inner.iter()).finish()
+    }
+}
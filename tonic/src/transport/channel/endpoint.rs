@@ -1,18 +1,30 @@
 #[cfg(feature = "_tls-any")]
 use super::service::TlsConnector;
-use super::service::{self, Executor, SharedExec};
+use super::service::{
+    self, ChannelStats, ConnectionLayer, ConnectionService, Executor, SharedExec,
+};
 use super::uds_connector::UdsConnector;
-use super::Channel;
 #[cfg(feature = "_tls-any")]
 use super::ClientTlsConfig;
+use super::{Channel, EndpointAttributes, KeepAliveStats, LoadReporting, OutlierDetection};
 #[cfg(feature = "_tls-any")]
 use crate::transport::error;
 use crate::transport::Error;
+use crate::{
+    body::Body,
+    codec::CodecExecutor,
+    time::{Clock, SharedClock},
+};
 use bytes::Bytes;
 use http::{uri::Uri, HeaderValue};
 use hyper::rt;
 use hyper_util::client::legacy::connect::HttpConnector;
-use std::{fmt, future::Future, net::IpAddr, pin::Pin, str, str::FromStr, time::Duration};
+use std::{
+    fmt, future::Future, net::IpAddr, pin::Pin, str, str::FromStr, sync::Arc, time::Duration,
+};
+#[cfg(feature = "_tls-any")]
+use tokio_rustls::rustls::pki_types::ServerName;
+use tower::{util::BoxService, Layer, ServiceExt};
 use tower_service::Service;
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -31,11 +43,15 @@ pub struct Endpoint {
     pub(crate) origin: Option<Uri>,
     pub(crate) user_agent: Option<HeaderValue>,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) queue_timeout: Option<Duration>,
     pub(crate) concurrency_limit: Option<usize>,
     pub(crate) rate_limit: Option<(u64, Duration)>,
     #[cfg(feature = "_tls-any")]
     pub(crate) tls: Option<TlsConnector>,
+    #[cfg(feature = "_tls-any")]
+    pub(crate) tls_server_name: Option<ServerName<'static>>,
     pub(crate) buffer_size: Option<usize>,
+    pub(crate) warmup: Option<usize>,
     pub(crate) init_stream_window_size: Option<u32>,
     pub(crate) init_connection_window_size: Option<u32>,
     pub(crate) tcp_keepalive: Option<Duration>,
@@ -48,8 +64,20 @@ pub struct Endpoint {
     pub(crate) http2_max_header_list_size: Option<u32>,
     pub(crate) connect_timeout: Option<Duration>,
     pub(crate) http2_adaptive_window: Option<bool>,
+    pub(crate) keep_alive_stats: KeepAliveStats,
+    pub(crate) wire_debug: bool,
+    pub(crate) channel_stats: ChannelStats,
     pub(crate) local_address: Option<IpAddr>,
     pub(crate) executor: SharedExec,
+    pub(crate) attributes: EndpointAttributes,
+    pub(crate) priority: u32,
+    pub(crate) health_check: Option<(String, Duration)>,
+    pub(crate) outlier_detection: Option<OutlierDetection>,
+    pub(crate) load_reporting: Option<LoadReporting>,
+    pub(crate) connection_layer: Option<ConnectionLayer>,
+    pub(crate) codec_executor: Option<CodecExecutor>,
+    pub(crate) fair_write_quantum: Option<usize>,
+    pub(crate) clock: SharedClock,
 }
 
 impl Endpoint {
@@ -80,9 +108,13 @@ impl Endpoint {
             concurrency_limit: None,
             rate_limit: None,
             timeout: None,
+            queue_timeout: None,
             #[cfg(feature = "_tls-any")]
             tls: None,
+            #[cfg(feature = "_tls-any")]
+            tls_server_name: None,
             buffer_size: None,
+            warmup: None,
             init_stream_window_size: None,
             init_connection_window_size: None,
             tcp_keepalive: None,
@@ -95,8 +127,20 @@ impl Endpoint {
             http2_max_header_list_size: None,
             connect_timeout: None,
             http2_adaptive_window: None,
+            keep_alive_stats: KeepAliveStats::default(),
+            wire_debug: false,
+            channel_stats: ChannelStats::default(),
             executor: SharedExec::tokio(),
             local_address: None,
+            attributes: EndpointAttributes::new(),
+            priority: 0,
+            health_check: None,
+            outlier_detection: None,
+            load_reporting: None,
+            connection_layer: None,
+            codec_executor: None,
+            fair_write_quantum: None,
+            clock: SharedClock::default(),
         }
     }
 
@@ -109,9 +153,13 @@ impl Endpoint {
             concurrency_limit: None,
             rate_limit: None,
             timeout: None,
+            queue_timeout: None,
             #[cfg(feature = "_tls-any")]
             tls: None,
+            #[cfg(feature = "_tls-any")]
+            tls_server_name: None,
             buffer_size: None,
+            warmup: None,
             init_stream_window_size: None,
             init_connection_window_size: None,
             tcp_keepalive: None,
@@ -124,8 +172,20 @@ impl Endpoint {
             http2_max_header_list_size: None,
             connect_timeout: None,
             http2_adaptive_window: None,
+            keep_alive_stats: KeepAliveStats::default(),
+            wire_debug: false,
+            channel_stats: ChannelStats::default(),
             executor: SharedExec::tokio(),
             local_address: None,
+            attributes: EndpointAttributes::new(),
+            priority: 0,
+            health_check: None,
+            outlier_detection: None,
+            load_reporting: None,
+            connection_layer: None,
+            codec_executor: None,
+            fair_write_quantum: None,
+            clock: SharedClock::default(),
         }
     }
 
@@ -202,7 +262,8 @@ impl Endpoint {
     ///
     /// Override the `origin`, mainly useful when you are reaching a Server/LoadBalancer
     /// which serves multiple services at the same time.
-    /// It will play the role of SNI (Server Name Indication).
+    /// It is sent as the `:authority` of the requests. The TLS server name is set independently,
+    /// see [`Endpoint::tls_server_name`].
     ///
     /// ```
     /// # use tonic::transport::Endpoint;
@@ -240,6 +301,27 @@ impl Endpoint {
         }
     }
 
+    /// Fail requests that wait longer than `dur` to be sent, with an `UNAVAILABLE` status
+    /// mentioning how long they waited.
+    ///
+    /// Requests wait when the buffer of the channel is full, see [`Endpoint::buffer_size`], or
+    /// while the connection is being established. Without a queue timeout they wait until they
+    /// are sent or their overall [`timeout`](Endpoint::timeout) elapses. The queue timeout does
+    /// not apply once a request was sent.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::time::Duration;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.queue_timeout(Duration::from_millis(100));
+    /// ```
+    pub fn queue_timeout(self, dur: Duration) -> Self {
+        Endpoint {
+            queue_timeout: Some(dur),
+            ..self
+        }
+    }
+
     /// Apply a timeout to connecting to the uri.
     ///
     /// Defaults to no timeout.
@@ -358,6 +440,54 @@ impl Endpoint {
         }
     }
 
+    /// Open `connections` connections to the endpoint as soon as a channel is created, and
+    /// balance requests across them.
+    ///
+    /// [`Endpoint::connect`] only returns once all the connections are established, while the
+    /// channels returned by [`Endpoint::connect_lazy`] establish them in the background right away,
+    /// which can be awaited with [`Channel::warmup`]. Either way, the first requests do not pay for
+    /// the connection setup, which avoids latency spikes when clients start, e.g. after a deploy.
+    ///
+    /// This does not apply to endpoints balanced with [`Channel::balance_list`] or
+    /// [`Channel::balance_channel`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `connections` is zero.
+    pub fn warmup(self, connections: usize) -> Self {
+        assert!(connections > 0, "warmup requires at least one connection");
+        Endpoint {
+            warmup: Some(connections),
+            ..self
+        }
+    }
+
+    /// Sets the name sent as TLS SNI (Server Name Indication) and against which the server's
+    /// certificate is verified.
+    ///
+    /// It takes precedence over [`ClientTlsConfig::domain_name`] and the host of the endpoint URI,
+    /// and is independent from the `:authority` of the requests set with [`Endpoint::origin`]. This
+    /// is useful when dialing an IP address or a TCP load balancer while the server expects a
+    /// specific virtual host.
+    ///
+    /// Returns an error if `server_name` is neither a valid DNS name nor an IP address.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// let endpoint = Endpoint::from_static("https://10.0.0.1:443")
+    ///     .origin("https://api.example.com".parse().unwrap())
+    ///     .tls_server_name("tls.example.com")
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "_tls-any")]
+    pub fn tls_server_name(self, server_name: impl Into<String>) -> Result<Self, Error> {
+        let server_name = ServerName::try_from(server_name.into()).map_err(Error::from_source)?;
+        Ok(Endpoint {
+            tls_server_name: Some(server_name),
+            ..self
+        })
+    }
+
     /// Configures TLS for the endpoint.
     #[cfg(feature = "_tls-any")]
     pub fn tls_config(self, tls_config: ClientTlsConfig) -> Result<Self, Error> {
@@ -414,6 +544,40 @@ impl Endpoint {
         }
     }
 
+    /// The keepalive statistics of the connections to this endpoint: the round trip time of the
+    /// last ping, the pings that were never acknowledged and the `GOAWAY` frames received.
+    ///
+    /// Clones of this endpoint share the same statistics.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::time::Duration;
+    /// let endpoint = Endpoint::from_static("http://example.com")
+    ///     .http2_keep_alive_interval(Duration::from_secs(10));
+    /// let stats = endpoint.keep_alive_stats();
+    /// // Connect the channel, then poll the statistics.
+    /// assert_eq!(stats.last_ping_rtt(), None);
+    /// ```
+    pub fn keep_alive_stats(&self) -> KeepAliveStats {
+        self.keep_alive_stats.clone()
+    }
+
+    /// Logs the HTTP/2 frames of the connections to this endpoint, to debug interoperability
+    /// issues without capturing the traffic.
+    ///
+    /// The type, stream id, flags and size of every frame read or written are logged at the
+    /// `TRACE` level under the `tonic::wire` target, along with the flow-control updates, the
+    /// settings and the boundaries of the gRPC messages. Header blocks and message payloads are
+    /// never logged. Connections are not inspected unless that target is enabled.
+    ///
+    /// Default is `false`.
+    pub fn wire_debug(self, enabled: bool) -> Self {
+        Endpoint {
+            wire_debug: enabled,
+            ..self
+        }
+    }
+
     /// Sets the max size of received header frames.
     ///
     /// This will default to whatever the default in hyper is. As of v1.4.1, it is 16 KiB.
@@ -435,11 +599,171 @@ impl Endpoint {
         self
     }
 
+    /// Sets the [`CodecExecutor`] that decompresses large responses.
+    ///
+    /// Requests are compressed on the executor only when it is also inserted into their
+    /// extensions. By default all messages are compressed and decompressed on the thread
+    /// driving the call.
+    pub fn codec_executor(self, executor: CodecExecutor) -> Self {
+        Endpoint {
+            codec_executor: Some(executor),
+            ..self
+        }
+    }
+
+    /// Splits the requests of each call into slices of at most `quantum` bytes, so that a call
+    /// with a large backlog does not delay the small messages of the other calls of the
+    /// connection.
+    ///
+    /// The [`StreamBacklog`](crate::service::StreamBacklog) of each request is added to the
+    /// extensions of its response. See [`FairWriteLayer`](crate::service::FairWriteLayer) for
+    /// more details.
+    pub fn fair_write_quantum(self, quantum: impl Into<Option<usize>>) -> Self {
+        Endpoint {
+            fair_write_quantum: quantum.into(),
+            ..self
+        }
+    }
+
+    /// Attach an attribute to this endpoint.
+    ///
+    /// Attributes describe the endpoint, e.g. its zone or version, and are made available to the
+    /// load balancer of a balanced [`Channel`] as well as in the extensions of every response
+    /// served by this endpoint. See [`EndpointAttributes`] for more details.
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key, value);
+        self
+    }
+
+    /// Replace all attributes of this endpoint.
+    pub fn attributes(self, attributes: EndpointAttributes) -> Self {
+        Endpoint { attributes, ..self }
+    }
+
+    /// Enable active health checking of this endpoint.
+    ///
+    /// The health of `service_name` is checked every `interval` by calling
+    /// `grpc.health.v1.Health/Check` on the same connection used for requests. An empty
+    /// `service_name` checks the overall health of the server. While the endpoint is not reported
+    /// as `SERVING` it is not ready, so a balanced [`Channel`] routes requests to the other
+    /// endpoints instead. Endpoints start out unhealthy until the first check succeeds.
+    ///
+    /// If the server does not implement the health service, the endpoint is considered healthy
+    /// and no further checks are made.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use std::time::Duration;
+    /// # let mut builder = Endpoint::from_static("https://example.com");
+    /// builder.health_check("my.package.MyService", Duration::from_secs(5));
+    /// ```
+    pub fn health_check(self, service_name: impl Into<String>, interval: Duration) -> Self {
+        Endpoint {
+            health_check: Some((service_name.into(), interval)),
+            ..self
+        }
+    }
+
+    /// Set the priority tier of this endpoint, where lower values are preferred.
+    ///
+    /// Channels created with [`Channel::priority_list`] or [`Channel::priority_channel`] only
+    /// send requests to the endpoints of a tier once the more preferred tiers are unhealthy. See
+    /// [`Priority`](super::Priority) for more details. Defaults to 0.
+    pub fn priority(self, tier: u32) -> Self {
+        Endpoint {
+            priority: tier,
+            ..self
+        }
+    }
+
+    /// Enable outlier detection for this endpoint.
+    ///
+    /// The endpoint is temporarily ejected from a balanced [`Channel`] once too many of its
+    /// requests fail. See [`OutlierDetection`] for more details.
+    pub fn outlier_detection(self, config: OutlierDetection) -> Self {
+        Endpoint {
+            outlier_detection: Some(config),
+            ..self
+        }
+    }
+
+    /// Enable utilization based load balancing for this endpoint.
+    ///
+    /// The load reports the endpoint sends with its responses are used by a balanced
+    /// [`Channel`] to prefer less utilized endpoints. See [`LoadReporting`] for more details.
+    pub fn load_reporting(self, config: LoadReporting) -> Self {
+        Endpoint {
+            load_reporting: Some(config),
+            ..self
+        }
+    }
+
+    /// Adds a [`Layer`] to the stack of every connection made to this endpoint.
+    ///
+    /// The layer wraps the connection itself, so it sees each request as it is sent to this
+    /// endpoint, after the [`timeout`](Self::timeout), origin and user agent were applied, and
+    /// each response before it reaches the load balancer. Unlike layers applied on top of a
+    /// [`Channel`], this allows per endpoint middleware in a balanced channel.
+    ///
+    /// Layers added first are the outermost ones.
+    ///
+    /// ```
+    /// # use tonic::transport::Endpoint;
+    /// # use tower::timeout::TimeoutLayer;
+    /// # use std::time::Duration;
+    /// # let builder = Endpoint::from_static("https://example.com");
+    /// builder.layer(TimeoutLayer::new(Duration::from_secs(5)));
+    /// ```
+    pub fn layer<L, ResBody>(self, layer: L) -> Self
+    where
+        L: Layer<ConnectionService> + Send + Sync + 'static,
+        L::Service:
+            Service<http::Request<Body>, Response = http::Response<ResBody>> + Send + 'static,
+        <L::Service as Service<http::Request<Body>>>::Error: Into<crate::BoxError>,
+        <L::Service as Service<http::Request<Body>>>::Future: Send + 'static,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<crate::BoxError>,
+    {
+        let inner = move |svc: ConnectionService| {
+            let svc = layer
+                .layer(svc)
+                .map_response(|res| res.map(Body::new))
+                .map_err(Into::into);
+            BoxService::new(svc)
+        };
+
+        let connection_layer: ConnectionLayer = match self.connection_layer.clone() {
+            Some(outer) => Arc::new(move |svc| outer(inner(svc))),
+            None => Arc::new(inner),
+        };
+
+        Endpoint {
+            connection_layer: Some(connection_layer),
+            ..self
+        }
+    }
+
+    /// Sets the [`Clock`] used for all time related work of the channel, e.g. enforcing the
+    /// [`timeout`](Self::timeout) and [`rate_limit`](Self::rate_limit), scheduling keepalive
+    /// pings or health checks.
+    ///
+    /// Uses the Tokio timer by default.
+    pub fn clock(self, clock: impl Clock) -> Self {
+        Endpoint {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
     pub(crate) fn connector<C>(&self, c: C) -> service::Connector<C> {
         service::Connector::new(
             c,
+            self.clock.clone(),
             #[cfg(feature = "_tls-any")]
-            self.tls.clone(),
+            self.tls.clone().map(|tls| match &self.tls_server_name {
+                Some(server_name) => tls.with_server_name(server_name.clone()),
+                None => tls,
+            }),
         )
     }
 
@@ -506,15 +830,8 @@ impl Endpoint {
         C::Future: Send,
         crate::BoxError: From<C::Error> + Send,
     {
-        let connector = self.connector(connector);
-
-        if let Some(connect_timeout) = self.connect_timeout {
-            let mut connector = hyper_timeout::TimeoutConnector::new(connector);
-            connector.set_connect_timeout(Some(connect_timeout));
-            Channel::connect(connector, self.clone()).await
-        } else {
-            Channel::connect(connector, self.clone()).await
-        }
+        let connector = self.connector(connector).timeout(self.connect_timeout);
+        Channel::connect(connector, self.clone()).await
     }
 
     /// Connect with a custom connector lazily.
@@ -531,14 +848,8 @@ impl Endpoint {
         C::Future: Send,
         crate::BoxError: From<C::Error> + Send,
     {
-        let connector = self.connector(connector);
-        if let Some(connect_timeout) = self.connect_timeout {
-            let mut connector = hyper_timeout::TimeoutConnector::new(connector);
-            connector.set_connect_timeout(Some(connect_timeout));
-            Channel::new(connector, self.clone())
-        } else {
-            Channel::new(connector, self.clone())
-        }
+        let connector = self.connector(connector).timeout(self.connect_timeout);
+        Channel::new(connector, self.clone())
     }
 
     /// Get the endpoint uri.
@@ -585,6 +896,11 @@ impl Endpoint {
     pub fn get_tcp_keepalive_retries(&self) -> Option<u32> {
         self.tcp_keepalive_retries
     }
+
+    /// Get the attributes attached to this endpoint.
+    pub fn get_attributes(&self) -> &EndpointAttributes {
+        &self.attributes
+    }
 }
 
 impl From<Uri> for Endpoint {
@@ -1,18 +1,26 @@
 //! Client implementation and builder.
 
+mod attributes;
 mod endpoint;
 pub(crate) mod service;
 #[cfg(feature = "_tls-any")]
 mod tls;
 mod uds_connector;
 
-pub use self::service::Change;
+pub use self::attributes::EndpointAttributes;
+pub use self::service::{
+    Change, ChannelMetrics, ConnectivityState, KeepAliveStats, LoadReport, LoadReporting,
+    OutlierDetection, PinEndpoint, Priority, RingHash,
+};
 pub use endpoint::Endpoint;
 #[cfg(feature = "_tls-any")]
 pub use tls::ClientTlsConfig;
 
-use self::service::{Connection, DynamicServiceStream, Executor, SharedExec};
-use crate::body::Body;
+use self::service::{
+    ChannelStats, Connection, DynamicServiceStream, Executor, InFlight, InFlightBody,
+    PriorityBalance, RingHashBalance, SharedExec, Warmup, GRPC_PREVIOUS_RPC_ATTEMPTS,
+};
+use crate::{body::Body, time::SharedClock};
 use bytes::Bytes;
 use http::{
     uri::{InvalidUri, Uri},
@@ -23,12 +31,12 @@ use std::{
     future::Future,
     hash::Hash,
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
+    time::Duration,
 };
 use tokio::sync::mpsc::{channel, Sender};
 
 use hyper::rt;
-use tower::balance::p2c::Balance;
 use tower::{
     buffer::{future::ResponseFuture as BufferResponseFuture, Buffer},
     discover::Discover,
@@ -66,13 +74,22 @@ const DEFAULT_BUFFER_SIZE: usize = 1024;
 #[derive(Clone)]
 pub struct Channel {
     svc: Buffer<Request<Body>, BoxFuture<'static, Result<Response<Body>, crate::BoxError>>>,
+    warmup: Option<Warmup>,
+    queue_timeout: Option<(Duration, SharedClock)>,
+    stats: ChannelStats,
 }
 
 /// A future that resolves to an HTTP response.
 ///
 /// This is returned by the `Service::call` on [`Channel`].
 pub struct ResponseFuture {
-    inner: BufferResponseFuture<BoxFuture<'static, Result<Response<Body>, crate::BoxError>>>,
+    inner: Inner,
+    in_flight: Option<InFlight>,
+}
+
+enum Inner {
+    Buffered(BufferResponseFuture<BoxFuture<'static, Result<Response<Body>, crate::BoxError>>>),
+    Queued(BoxFuture<'static, Result<Response<Body>, crate::BoxError>>),
 }
 
 impl Channel {
@@ -141,14 +158,88 @@ impl Channel {
         E: Executor<Pin<Box<dyn Future<Output = ()> + Send>>> + Send + Sync + 'static,
     {
         let (tx, rx) = channel(capacity);
-        let list = DynamicServiceStream::new(rx);
-        (Self::balance(list, DEFAULT_BUFFER_SIZE, executor), tx)
+        let stats = ChannelStats::default();
+        let list = DynamicServiceStream::new(rx, stats.clone());
+        (
+            Self::balance(list, DEFAULT_BUFFER_SIZE, executor, stats),
+            tx,
+        )
+    }
+
+    /// Route requests to a list of [`Endpoint`]'s by consistent hashing.
+    ///
+    /// This creates a [`Channel`] that sends requests with the same hash key to the same
+    /// endpoint. See [`RingHash`] for more details.
+    pub fn ring_hash_list(list: impl Iterator<Item = Endpoint>, config: RingHash) -> Self {
+        let (channel, tx) = Self::ring_hash_channel(DEFAULT_BUFFER_SIZE, config);
+        list.for_each(|endpoint| {
+            tx.try_send(Change::Insert(endpoint.uri.clone(), endpoint))
+                .unwrap();
+        });
+
+        channel
+    }
+
+    /// Route requests to a changing set of [`Endpoint`]'s by consistent hashing.
+    ///
+    /// This creates a [`Channel`] that will listen to a stream of change events and will add or
+    /// remove provided endpoints. See [`RingHash`] for more details.
+    pub fn ring_hash_channel<K>(
+        capacity: usize,
+        config: RingHash,
+    ) -> (Self, Sender<Change<K, Endpoint>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+    {
+        let (tx, rx) = channel(capacity);
+        let stats = ChannelStats::default();
+        let list = DynamicServiceStream::new(rx, stats.clone());
+        let svc = BoxService::new(RingHashBalance::new(list, config));
+        let (svc, worker) = Buffer::pair(svc, DEFAULT_BUFFER_SIZE);
+        SharedExec::tokio().execute(Box::pin(worker));
+
+        (Channel::from_buffer(svc, stats), tx)
+    }
+
+    /// Fail over between tiers of [`Endpoint`]'s.
+    ///
+    /// This creates a [`Channel`] that balances requests across the endpoints of the most
+    /// preferred healthy [`priority`](Endpoint::priority) tier. See [`Priority`] for more details.
+    pub fn priority_list(list: impl Iterator<Item = Endpoint>, config: Priority) -> Self {
+        let (channel, tx) = Self::priority_channel(DEFAULT_BUFFER_SIZE, config);
+        list.for_each(|endpoint| {
+            tx.try_send(Change::Insert(endpoint.uri.clone(), endpoint))
+                .unwrap();
+        });
+
+        channel
+    }
+
+    /// Fail over between tiers of a changing set of [`Endpoint`]'s.
+    ///
+    /// This creates a [`Channel`] that will listen to a stream of change events and will add or
+    /// remove provided endpoints. See [`Priority`] for more details.
+    pub fn priority_channel<K>(
+        capacity: usize,
+        config: Priority,
+    ) -> (Self, Sender<Change<K, Endpoint>>)
+    where
+        K: Hash + Eq + Send + Clone + 'static,
+    {
+        let (tx, rx) = channel(capacity);
+        let stats = ChannelStats::default();
+        let list = DynamicServiceStream::new(rx, stats.clone());
+        let svc = BoxService::new(PriorityBalance::new(list, config));
+        let (svc, worker) = Buffer::pair(svc, DEFAULT_BUFFER_SIZE);
+        SharedExec::tokio().execute(Box::pin(worker));
+
+        (Channel::from_buffer(svc, stats), tx)
     }
 
     /// Create a new [`Channel`] using a custom connector to the provided [Endpoint].
     ///
     /// This is a lower level API, prefer to use [`Endpoint::connect_lazy`] if you are not using a custom connector.
-    pub fn new<C>(connector: C, endpoint: Endpoint) -> Self
+    pub fn new<C>(connector: C, mut endpoint: Endpoint) -> Self
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::BoxError> + Send,
@@ -157,27 +248,56 @@ impl Channel {
     {
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
+        let queue_timeout = endpoint
+            .queue_timeout
+            .map(|timeout| (timeout, endpoint.clock.clone()));
+        let stats = ChannelStats::default();
+        endpoint.channel_stats = stats.clone();
+
+        if let Some(connections) = endpoint.warmup {
+            let (discover, warmup) = Warmup::spawn(connector, endpoint, connections);
+            let channel = Self::balance(discover, buffer_size, executor, stats);
+            return Channel {
+                warmup: Some(warmup),
+                queue_timeout,
+                ..channel
+            };
+        }
 
         let svc = Connection::lazy(connector, endpoint);
         let (svc, worker) = Buffer::pair(svc, buffer_size);
 
         executor.execute(worker);
 
-        Channel { svc }
+        Channel {
+            queue_timeout,
+            ..Channel::from_buffer(svc, stats)
+        }
     }
 
     /// Connect to the provided [`Endpoint`] using the provided connector, and return a new [`Channel`].
     ///
     /// This is a lower level API, prefer to use [`Endpoint::connect`] if you are not using a custom connector.
-    pub async fn connect<C>(connector: C, endpoint: Endpoint) -> Result<Self, super::Error>
+    pub async fn connect<C>(connector: C, mut endpoint: Endpoint) -> Result<Self, super::Error>
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::BoxError> + Send,
         C::Future: Unpin + Send,
         C::Response: rt::Read + rt::Write + Unpin + Send + 'static,
     {
+        if endpoint.warmup.is_some() {
+            let channel = Self::new(connector, endpoint);
+            channel.warmup().await?;
+            return Ok(channel);
+        }
+
         let buffer_size = endpoint.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE);
         let executor = endpoint.executor.clone();
+        let queue_timeout = endpoint
+            .queue_timeout
+            .map(|timeout| (timeout, endpoint.clock.clone()));
+        let stats = ChannelStats::default();
+        endpoint.channel_stats = stats.clone();
 
         let svc = Connection::connect(connector, endpoint)
             .await
@@ -185,23 +305,78 @@ impl Channel {
         let (svc, worker) = Buffer::pair(svc, buffer_size);
         executor.execute(worker);
 
-        Ok(Channel { svc })
+        Ok(Channel {
+            queue_timeout,
+            ..Channel::from_buffer(svc, stats)
+        })
     }
 
-    pub(crate) fn balance<D, E>(discover: D, buffer_size: usize, executor: E) -> Self
+    /// Wait until the connections opened by [`Endpoint::warmup`] are established.
+    ///
+    /// This is useful to warm up a channel returned by [`Endpoint::connect_lazy`] before serving
+    /// traffic. It returns the first error if any connection could not be established, in which
+    /// case that connection is attempted again on first use. It returns immediately for channels
+    /// created without [`Endpoint::warmup`].
+    pub async fn warmup(&self) -> Result<(), super::Error> {
+        match &self.warmup {
+            Some(warmup) => warmup.wait().await.map_err(super::Error::from_source),
+            None => Ok(()),
+        }
+    }
+
+    /// A snapshot of the transport health of this channel: its connections, the requests it is
+    /// sending and the bytes it exchanged.
+    ///
+    /// Clones of this channel share the same metrics.
+    ///
+    /// ```
+    /// # use tonic::transport::{channel::ConnectivityState, Endpoint};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let channel = Endpoint::from_static("http://[::1]:50051").connect_lazy();
+    /// let metrics = channel.metrics();
+    /// // Lazy channels only connect on their first request.
+    /// assert_eq!(metrics.state(), ConnectivityState::Idle);
+    /// assert_eq!(metrics.connections(), 0);
+    /// # }
+    /// ```
+    pub fn metrics(&self) -> ChannelMetrics {
+        self.stats.snapshot()
+    }
+
+    pub(crate) fn from_buffer(
+        svc: Buffer<Request<Body>, BoxFuture<'static, Result<Response<Body>, crate::BoxError>>>,
+        stats: ChannelStats,
+    ) -> Self {
+        Channel {
+            svc,
+            warmup: None,
+            queue_timeout: None,
+            stats,
+        }
+    }
+
+    pub(crate) fn balance<D, E>(
+        discover: D,
+        buffer_size: usize,
+        executor: E,
+        stats: ChannelStats,
+    ) -> Self
     where
         D: Discover<Service = Connection> + Unpin + Send + 'static,
         D::Error: Into<crate::BoxError>,
         D::Key: Hash + Send + Clone,
         E: Executor<BoxFuture<'static, ()>> + Send + Sync + 'static,
     {
-        let svc = Balance::new(discover);
+        // A single tier of endpoints, balanced by the power of two choices like
+        // `tower::balance::p2c::Balance` does, which cannot route pinned requests.
+        let svc = PriorityBalance::new(discover, Priority::flat());
 
         let svc = BoxService::new(svc);
         let (svc, worker) = Buffer::pair(svc, buffer_size);
         executor.execute(Box::pin(worker));
 
-        Channel { svc }
+        Channel::from_buffer(svc, stats)
     }
 }
 
@@ -211,13 +386,33 @@ impl Service<http::Request<Body>> for Channel {
     type Future = ResponseFuture;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // With a queue timeout, requests wait for room in the buffer in their response future.
+        if self.queue_timeout.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+
         Service::poll_ready(&mut self.svc, cx).map_err(super::Error::from_source)
     }
 
-    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
-        let inner = Service::call(&mut self.svc, request);
-
-        ResponseFuture { inner }
+    fn call(&mut self, mut request: http::Request<Body>) -> Self::Future {
+        let retry = request.headers().contains_key(GRPC_PREVIOUS_RPC_ATTEMPTS);
+        let in_flight = self.stats.start(retry);
+        request.extensions_mut().insert(self.stats.enqueue());
+
+        let inner = match &self.queue_timeout {
+            Some((timeout, clock)) => Inner::Queued(Box::pin(service::queue::send(
+                self.svc.clone(),
+                request,
+                *timeout,
+                clock.clone(),
+            ))),
+            None => Inner::Buffered(Service::call(&mut self.svc, request)),
+        };
+
+        ResponseFuture {
+            inner,
+            in_flight: Some(in_flight),
+        }
     }
 }
 
@@ -225,9 +420,16 @@ impl Future for ResponseFuture {
     type Output = Result<Response<Body>, super::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Pin::new(&mut self.inner)
-            .poll(cx)
-            .map_err(super::Error::from_source)
+        let result = match &mut self.inner {
+            Inner::Buffered(inner) => Pin::new(inner).poll(cx),
+            Inner::Queued(inner) => inner.as_mut().poll(cx),
+        };
+
+        let response = ready!(result).map_err(super::Error::from_source)?;
+        let in_flight = self.in_flight.take().expect("polled after completion");
+        Poll::Ready(Ok(
+            response.map(|body| Body::new(InFlightBody::new(body, in_flight)))
+        ))
     }
 }
 
@@ -1,31 +1,79 @@
-use super::{AddOrigin, Reconnect, SharedExec, UserAgent};
+#[cfg(feature = "_tls-any")]
+use super::BoxedIo;
+use super::{
+    health, metrics::QueuedRequest, queue::Queued, AddOrigin, ChannelStats, KeepAliveIo,
+    KeepAliveStats, LoadReporter, LoadTracker, OutlierDetector, PinEndpoint, RateLimit, Reconnect,
+    SharedExec, UserAgent,
+};
+#[cfg(feature = "_tls-any")]
+use crate::transport::TlsInfo;
 use crate::{
     body::Body,
-    transport::{channel::BoxFuture, service::GrpcTimeout, Endpoint},
+    service::{fair_write::FairWriteBody, slow_rpc::RpcTimeline, FairWriteLayer},
+    time::{Clock, SharedClock},
+    transport::{
+        channel::{BoxFuture, EndpointAttributes},
+        service::{wire_debug::WireDebug, GrpcTimeout},
+        Endpoint,
+    },
+    Status,
 };
 use http::{Request, Response, Uri};
 use hyper::rt;
 use hyper::{client::conn::http2::Builder, rt::Executor};
-use hyper_util::rt::TokioTimer;
+use hyper_util::rt::TokioIo;
+#[cfg(feature = "_tls-any")]
+use std::any::Any;
 use std::{
     fmt,
-    task::{Context, Poll},
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::Instant,
 };
 use tower::load::Load;
 use tower::{
+    buffer::Buffer,
     layer::Layer,
-    limit::{concurrency::ConcurrencyLimitLayer, rate::RateLimitLayer},
-    util::BoxService,
+    limit::concurrency::ConcurrencyLimitLayer,
+    util::{BoxService, Either, MapResponseLayer},
     ServiceBuilder, ServiceExt,
 };
 use tower_service::Service;
 
+const HEALTH_CHECK_BUFFER_SIZE: usize = 1024;
+
+/// The type erased connection that user supplied layers wrap.
+pub(crate) type ConnectionService = BoxService<Request<Body>, Response<Body>, crate::BoxError>;
+pub(crate) type ConnectionLayer = Arc<dyn Fn(ConnectionService) -> ConnectionService + Send + Sync>;
+
 pub(crate) struct Connection {
-    inner: BoxService<Request<Body>, Response<Body>, crate::BoxError>,
+    inner: ConnectionService,
+    uri: Uri,
+    attributes: EndpointAttributes,
+    priority: u32,
+    health: Option<Arc<health::HealthState>>,
+    load: Option<Arc<LoadTracker>>,
+    last_connect: LastConnect,
+    clock: SharedClock,
+}
+
+/// The start and end of the last time a connection was established, telling the requests
+/// dispatched to it how long they waited for it.
+#[derive(Clone, Default)]
+struct LastConnect(Arc<Mutex<Option<(Instant, Instant)>>>);
+
+impl LastConnect {
+    fn get(&self) -> Option<(Instant, Instant)> {
+        *self.0.lock().unwrap()
+    }
+
+    fn set(&self, started: Instant, established: Instant) {
+        *self.0.lock().unwrap() = Some((started, established));
+    }
 }
 
 impl Connection {
-    fn new<C>(connector: C, endpoint: Endpoint, is_lazy: bool) -> Self
+    pub(super) fn new<C>(connector: C, endpoint: Endpoint, is_lazy: bool) -> Self
     where
         C: Service<Uri> + Send + 'static,
         C::Error: Into<crate::BoxError> + Send,
@@ -36,7 +84,7 @@ impl Connection {
             .initial_stream_window_size(endpoint.init_stream_window_size)
             .initial_connection_window_size(endpoint.init_connection_window_size)
             .keep_alive_interval(endpoint.http2_keep_alive_interval)
-            .timer(TokioTimer::new())
+            .timer(endpoint.clock.clone())
             .clone();
 
         if let Some(val) = endpoint.http2_keep_alive_timeout {
@@ -55,25 +103,101 @@ impl Connection {
             settings.max_header_list_size(val);
         }
 
+        let load = endpoint
+            .load_reporting
+            .clone()
+            .map(|config| Arc::new(LoadTracker::new(config, endpoint.clock.clone())));
+
         let stack = ServiceBuilder::new()
+            .option_layer(load.clone().map(|tracker| {
+                tower::layer::layer_fn(move |s| LoadReporter::new(s, tracker.clone()))
+            }))
+            .option_layer(endpoint.outlier_detection.clone().map(|config| {
+                let clock = endpoint.clock.clone();
+                tower::layer::layer_fn(move |s| {
+                    OutlierDetector::new(s, config.clone(), clock.clone())
+                })
+            }))
             .layer_fn(|s| {
                 let origin = endpoint.origin.as_ref().unwrap_or(endpoint.uri()).clone();
 
                 AddOrigin::new(s, origin)
             })
             .layer_fn(|s| UserAgent::new(s, endpoint.user_agent.clone()))
-            .layer_fn(|s| GrpcTimeout::new(s, endpoint.timeout))
+            .option_layer(endpoint.codec_executor.clone().map(|executor| {
+                MapResponseLayer::new(move |mut res: Response<Body>| {
+                    res.extensions_mut().insert(executor.clone());
+                    res
+                })
+            }))
+            .option_layer(endpoint.fair_write_quantum.map(|quantum| {
+                ServiceBuilder::new()
+                    .map_response(|res: Response<FairWriteBody<Body>>| res.map(Body::new))
+                    .layer(FairWriteLayer::client(quantum))
+                    .map_request(|req: Request<FairWriteBody<Body>>| req.map(Body::new))
+            }))
+            .layer_fn(|s| GrpcTimeout::channel(s, endpoint.timeout, endpoint.clock.clone()))
             .option_layer(endpoint.concurrency_limit.map(ConcurrencyLimitLayer::new))
-            .option_layer(endpoint.rate_limit.map(|(l, d)| RateLimitLayer::new(l, d)))
+            .option_layer(endpoint.rate_limit.map(|(num, per)| {
+                let clock = endpoint.clock.clone();
+                tower::layer::layer_fn(move |s| RateLimit::new(s, num, per, clock.clone()))
+            }))
             .into_inner();
 
-        let make_service =
-            MakeSendRequestService::new(connector, endpoint.executor.clone(), settings);
+        let last_connect = LastConnect::default();
+        let make_service = MakeSendRequestService::new(
+            connector,
+            endpoint.executor.clone(),
+            settings,
+            endpoint.keep_alive_stats.clone(),
+            endpoint.channel_stats.clone(),
+            last_connect.clone(),
+            endpoint.wire_debug,
+            endpoint.clock.clone(),
+        );
 
         let conn = Reconnect::new(make_service, endpoint.uri().clone(), is_lazy);
+        let conn = match &endpoint.connection_layer {
+            Some(layer) => Either::Right(layer(BoxService::new(conn))),
+            None => Either::Left(conn),
+        };
+        let inner = BoxService::new(stack.layer(conn));
+
+        let Some((service_name, interval)) = endpoint.health_check.clone() else {
+            return Self {
+                inner,
+                uri: endpoint.uri().clone(),
+                attributes: endpoint.attributes.clone(),
+                priority: endpoint.priority,
+                health: None,
+                load,
+                last_connect,
+                clock: endpoint.clock.clone(),
+            };
+        };
+
+        // The health checking task shares the connection with the requests, so the stack is
+        // buffered to allow driving it from both sides.
+        let (inner, worker) = Buffer::pair(inner, HEALTH_CHECK_BUFFER_SIZE);
+        Executor::<BoxFuture<'static, ()>>::execute(&endpoint.executor, Box::pin(worker));
+
+        let health = health::spawn(
+            inner.clone(),
+            service_name,
+            interval,
+            &endpoint.executor,
+            endpoint.clock.clone(),
+        );
 
         Self {
-            inner: BoxService::new(stack.layer(conn)),
+            inner: BoxService::new(inner),
+            uri: endpoint.uri().clone(),
+            attributes: endpoint.attributes.clone(),
+            priority: endpoint.priority,
+            health: Some(health),
+            load,
+            last_connect,
+            clock: endpoint.clock.clone(),
         }
     }
 
@@ -99,6 +223,16 @@ impl Connection {
     {
         Self::new(connector, endpoint, true)
     }
+
+    /// The URI of the endpoint.
+    pub(crate) fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The tier of the endpoint, see [`Endpoint::priority`].
+    pub(crate) fn priority(&self) -> u32 {
+        self.priority
+    }
 }
 
 impl Service<Request<Body>> for Connection {
@@ -107,19 +241,48 @@ impl Service<Request<Body>> for Connection {
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(health) = &self.health {
+            ready!(health.poll_healthy(cx));
+        }
+
         Service::poll_ready(&mut self.inner, cx).map_err(Into::into)
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
-        self.inner.call(req)
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        // The request leaves the queue of the channel.
+        req.extensions_mut().remove::<QueuedRequest>();
+        if let Some(queued) = req.extensions_mut().remove::<Queued>() {
+            if !queued.dispatch() {
+                // The caller already gave up on the request.
+                return Box::pin(async {
+                    Err(Status::unavailable("request expired in the queue").into())
+                });
+            }
+        }
+        if let Some(timeline) = req.extensions().get::<RpcTimeline>() {
+            timeline.dispatched(self.clock.now(), self.last_connect.get());
+        }
+
+        let fut = self.inner.call(req);
+
+        let pin = PinEndpoint::new(self.uri.clone());
+        let attributes = (!self.attributes.is_empty()).then(|| self.attributes.clone());
+        Box::pin(async move {
+            let mut res = fut.await?;
+            res.extensions_mut().insert(pin);
+            if let Some(attributes) = attributes {
+                res.extensions_mut().insert(attributes);
+            }
+            Ok(res)
+        })
     }
 }
 
 impl Load for Connection {
-    type Metric = usize;
+    type Metric = f64;
 
     fn load(&self) -> Self::Metric {
-        0
+        self.load.as_ref().map_or(0.0, |load| load.load())
     }
 }
 
@@ -131,11 +294,17 @@ impl fmt::Debug for Connection {
 
 struct SendRequest {
     inner: hyper::client::conn::http2::SendRequest<Body>,
+    #[cfg(feature = "_tls-any")]
+    tls_info: Option<TlsInfo>,
 }
 
 impl From<hyper::client::conn::http2::SendRequest<Body>> for SendRequest {
     fn from(inner: hyper::client::conn::http2::SendRequest<Body>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            #[cfg(feature = "_tls-any")]
+            tls_info: None,
+        }
     }
 }
 
@@ -151,7 +320,20 @@ impl tower::Service<Request<Body>> for SendRequest {
     fn call(&mut self, req: Request<Body>) -> Self::Future {
         let fut = self.inner.send_request(req);
 
-        Box::pin(async move { fut.await.map_err(Into::into).map(|res| res.map(Body::new)) })
+        #[cfg(feature = "_tls-any")]
+        let tls_info = self.tls_info.clone();
+
+        Box::pin(async move {
+            #[allow(unused_mut)]
+            let mut res = fut.await.map_err(Into::<crate::BoxError>::into)?;
+
+            #[cfg(feature = "_tls-any")]
+            if let Some(tls_info) = tls_info {
+                res.extensions_mut().insert(tls_info);
+            }
+
+            Ok(res.map(Body::new))
+        })
     }
 }
 
@@ -159,14 +341,34 @@ struct MakeSendRequestService<C> {
     connector: C,
     executor: SharedExec,
     settings: Builder<SharedExec>,
+    keep_alive_stats: KeepAliveStats,
+    channel_stats: ChannelStats,
+    last_connect: LastConnect,
+    wire_debug: bool,
+    clock: SharedClock,
 }
 
 impl<C> MakeSendRequestService<C> {
-    fn new(connector: C, executor: SharedExec, settings: Builder<SharedExec>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        connector: C,
+        executor: SharedExec,
+        settings: Builder<SharedExec>,
+        keep_alive_stats: KeepAliveStats,
+        channel_stats: ChannelStats,
+        last_connect: LastConnect,
+        wire_debug: bool,
+        clock: SharedClock,
+    ) -> Self {
         Self {
             connector,
             executor,
             settings,
+            keep_alive_stats,
+            channel_stats,
+            last_connect,
+            wire_debug,
+            clock,
         }
     }
 }
@@ -176,7 +378,7 @@ where
     C: Service<Uri> + Send + 'static,
     C::Error: Into<crate::BoxError> + Send,
     C::Future: Send,
-    C::Response: rt::Read + rt::Write + Unpin + Send,
+    C::Response: rt::Read + rt::Write + Unpin + Send + 'static,
 {
     type Response = SendRequest;
     type Error = crate::BoxError;
@@ -190,10 +392,34 @@ where
         let fut = self.connector.call(req);
         let builder = self.settings.clone();
         let executor = self.executor.clone();
+        let keep_alive_stats = self.keep_alive_stats.clone();
+        let connecting = self.channel_stats.connecting();
+        let last_connect = self.last_connect.clone();
+        let wire_debug = self.wire_debug;
+        let clock = self.clock.clone();
+        let started = clock.now();
 
         Box::pin(async move {
-            let io = fut.await.map_err(Into::into)?;
+            let io = match fut.await {
+                Ok(io) => io,
+                Err(error) => {
+                    connecting.failed();
+                    return Err(error.into());
+                }
+            };
+
+            // Only the connections established by the transport know their TLS parameters.
+            #[cfg(feature = "_tls-any")]
+            let tls_info = (&io as &dyn Any)
+                .downcast_ref::<BoxedIo>()
+                .and_then(BoxedIo::tls_info)
+                .cloned();
+
+            let io = connecting.connected(TokioIo::new(io));
+            let io = TokioIo::new(WireDebug::client(io, wire_debug));
+            let io = KeepAliveIo::new(io, keep_alive_stats, clock.clone());
             let (send_request, conn) = builder.handshake(io).await?;
+            last_connect.set(started, clock.now());
 
             Executor::<BoxFuture<'static, ()>>::execute(
                 &executor,
@@ -204,7 +430,11 @@ where
                 }) as _,
             );
 
-            Ok(SendRequest::from(send_request))
+            Ok(SendRequest {
+                #[cfg(feature = "_tls-any")]
+                tls_info,
+                ..SendRequest::from(send_request)
+            })
         })
     }
 }
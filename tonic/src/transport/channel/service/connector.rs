@@ -1,12 +1,15 @@
 use super::BoxedIo;
 #[cfg(feature = "_tls-any")]
 use super::TlsConnector;
+use crate::time::SharedClock;
 use crate::transport::channel::BoxFuture;
 use crate::ConnectError;
 use http::Uri;
 #[cfg(feature = "_tls-any")]
 use std::fmt;
+use std::io;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use hyper::rt;
 
@@ -16,18 +19,31 @@ use tower_service::Service;
 
 pub(crate) struct Connector<C> {
     inner: C,
+    clock: SharedClock,
+    timeout: Option<Duration>,
     #[cfg(feature = "_tls-any")]
     tls: Option<TlsConnector>,
 }
 
 impl<C> Connector<C> {
-    pub(crate) fn new(inner: C, #[cfg(feature = "_tls-any")] tls: Option<TlsConnector>) -> Self {
+    pub(crate) fn new(
+        inner: C,
+        clock: SharedClock,
+        #[cfg(feature = "_tls-any")] tls: Option<TlsConnector>,
+    ) -> Self {
         Self {
             inner,
+            clock,
+            timeout: None,
             #[cfg(feature = "_tls-any")]
             tls,
         }
     }
+
+    /// Fails connection attempts, including the TLS handshake, that take longer than `timeout`.
+    pub(crate) fn timeout(self, timeout: Option<Duration>) -> Self {
+        Connector { timeout, ..self }
+    }
 }
 
 impl<C> Service<Uri> for Connector<C>
@@ -48,6 +64,9 @@ where
     }
 
     fn call(&mut self, uri: Uri) -> Self::Future {
+        let clock = self.clock.clone();
+        let timeout = self.timeout;
+
         #[cfg(feature = "_tls-any")]
         let tls = self.tls.clone();
 
@@ -56,13 +75,13 @@ where
         let connect = self.inner.call(uri);
 
         Box::pin(async move {
-            async {
+            let connect = async {
                 let io = connect.await?;
 
                 #[cfg(feature = "_tls-any")]
                 if is_https {
                     return if let Some(tls) = tls {
-                        let io = tls.connect(TokioIo::new(io)).await?;
+                        let io = tls.connect(TokioIo::new(io), &clock).await?;
                         Ok(io)
                     } else {
                         Err(HttpsUriWithoutTlsSupport(()).into())
@@ -70,8 +89,14 @@ where
                 }
 
                 Ok::<_, crate::BoxError>(BoxedIo::new(io))
+            };
+
+            match timeout {
+                Some(timeout) => clock.timeout(timeout, connect).await.unwrap_or_else(|()| {
+                    Err(io::Error::new(io::ErrorKind::TimedOut, "connect timed out").into())
+                }),
+                None => connect.await,
             }
-            .await
             .map_err(ConnectError)
         })
     }
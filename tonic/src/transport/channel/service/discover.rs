@@ -1,4 +1,5 @@
 use super::super::{Connection, Endpoint};
+use super::ChannelStats;
 
 use std::{
     hash::Hash,
@@ -20,11 +21,12 @@ pub enum Change<K, V> {
 
 pub(crate) struct DynamicServiceStream<K: Hash + Eq + Clone> {
     changes: Receiver<Change<K, Endpoint>>,
+    stats: ChannelStats,
 }
 
 impl<K: Hash + Eq + Clone> DynamicServiceStream<K> {
-    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>) -> Self {
-        Self { changes }
+    pub(crate) fn new(changes: Receiver<Change<K, Endpoint>>, stats: ChannelStats) -> Self {
+        Self { changes, stats }
     }
 }
 
@@ -35,7 +37,8 @@ impl<K: Hash + Eq + Clone> Stream for DynamicServiceStream<K> {
         match Pin::new(&mut self.changes).poll_recv(cx) {
             Poll::Pending | Poll::Ready(None) => Poll::Pending,
             Poll::Ready(Some(change)) => match change {
-                Change::Insert(k, endpoint) => {
+                Change::Insert(k, mut endpoint) => {
+                    endpoint.channel_stats = self.stats.clone();
                     let connection = Connection::lazy(endpoint.http_connector(), endpoint);
                     Poll::Ready(Some(Ok(TowerChange::Insert(k, connection))))
                 }
@@ -0,0 +1,245 @@
+//! Active health checking of endpoints using the
+//! [gRPC health checking protocol](https://github.com/grpc/grpc/blob/master/doc/health-checking.md).
+
+use super::{ChannelStats, Executor, SharedExec};
+use crate::{
+    body::Body,
+    client::Grpc,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    time::{Clock, SharedClock},
+    transport::channel::{BoxFuture, Channel},
+    Code, Request, Status,
+};
+use bytes::{Buf, BufMut};
+use http::uri::PathAndQuery;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tower::buffer::Buffer;
+
+const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// `grpc.health.v1.HealthCheckResponse.ServingStatus.SERVING`
+const SERVING: u64 = 1;
+
+type HealthCheckedService =
+    Buffer<http::Request<Body>, BoxFuture<'static, Result<http::Response<Body>, crate::BoxError>>>;
+
+/// The health of a single endpoint, as last reported by its health checking task.
+#[derive(Debug, Default)]
+pub(crate) struct HealthState {
+    healthy: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl HealthState {
+    /// Returns `Poll::Ready` once the endpoint is healthy.
+    pub(crate) fn poll_healthy(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.healthy.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        // Check again in case the state changed before the waker was registered.
+        if self.healthy.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::Release);
+
+        if healthy {
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Spawn a task that checks the health of `svc` every `interval` and returns the shared state it
+/// updates.
+///
+/// The task stops once the returned state is dropped.
+pub(crate) fn spawn(
+    svc: HealthCheckedService,
+    service_name: String,
+    interval: Duration,
+    executor: &SharedExec,
+    clock: SharedClock,
+) -> Arc<HealthState> {
+    let state = Arc::new(HealthState::default());
+    let task = check_loop(
+        Channel::from_buffer(svc, ChannelStats::default()),
+        service_name,
+        interval,
+        clock,
+        Arc::downgrade(&state),
+    );
+
+    executor.execute(Box::pin(task));
+
+    state
+}
+
+async fn check_loop(
+    channel: Channel,
+    service_name: String,
+    interval: Duration,
+    clock: SharedClock,
+    state: Weak<HealthState>,
+) {
+    let mut client = Grpc::new(channel);
+
+    loop {
+        let result = check(&mut client, &service_name, interval).await;
+
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+
+        match result {
+            Ok(serving) => state.set_healthy(serving),
+            Err(status) if status.code() == Code::Unimplemented => {
+                // As per the spec, a server that does not implement the health service is
+                // considered healthy and is not checked any further.
+                tracing::error!(
+                    "endpoint does not implement the health service, disabling health checks"
+                );
+                state.set_healthy(true);
+                return;
+            }
+            Err(status) => {
+                tracing::debug!("health check failed: {:?}", status);
+                state.set_healthy(false);
+            }
+        }
+
+        drop(state);
+        clock.sleep(interval).await;
+    }
+}
+
+async fn check(
+    client: &mut Grpc<Channel>,
+    service_name: &str,
+    timeout: Duration,
+) -> Result<bool, Status> {
+    client
+        .ready()
+        .await
+        .map_err(|e| Status::unavailable(format!("health check service was not ready: {e}")))?;
+
+    let mut request = Request::new(service_name.to_string());
+    request.set_timeout(timeout);
+
+    let response = client
+        .unary(request, PathAndQuery::from_static(CHECK_PATH), HealthCodec)
+        .await?;
+
+    Ok(response.into_inner() == SERVING)
+}
+
+/// A minimal codec for `grpc.health.v1.HealthCheckRequest` and `HealthCheckResponse`.
+#[derive(Debug, Clone, Copy)]
+struct HealthCodec;
+
+impl Codec for HealthCodec {
+    type Encode = String;
+    type Decode = u64;
+
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        *self
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        *self
+    }
+}
+
+impl Encoder for HealthCodec {
+    type Item = String;
+    type Error = Status;
+
+    fn encode(&mut self, service: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        if !service.is_empty() {
+            // field 1, wire type 2 (length delimited)
+            dst.put_u8(0x0a);
+            put_varint(dst, service.len() as u64);
+            dst.put_slice(service.as_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for HealthCodec {
+    type Item = u64;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let invalid = || Status::internal("invalid health check response");
+        let mut status = 0;
+
+        while src.has_remaining() {
+            let key = get_varint(src).ok_or_else(invalid)?;
+
+            match (key >> 3, key & 0x7) {
+                (1, 0) => status = get_varint(src).ok_or_else(invalid)?,
+                (_, 0) => {
+                    get_varint(src).ok_or_else(invalid)?;
+                }
+                (_, 1) if src.remaining() >= 8 => src.advance(8),
+                (_, 2) => {
+                    let len = get_varint(src).ok_or_else(invalid)? as usize;
+                    if src.remaining() < len {
+                        return Err(invalid());
+                    }
+                    src.advance(len);
+                }
+                (_, 5) if src.remaining() >= 4 => src.advance(4),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Some(status))
+    }
+}
+
+fn put_varint(dst: &mut impl BufMut, mut value: u64) {
+    while value >= 0x80 {
+        dst.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    dst.put_u8(value as u8);
+}
+
+pub(super) fn get_varint(src: &mut impl Buf) -> Option<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        if !src.has_remaining() {
+            return None;
+        }
+
+        let byte = src.get_u8();
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
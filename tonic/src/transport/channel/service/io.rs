@@ -5,6 +5,9 @@ use std::task::{Context, Poll};
 use hyper::rt;
 use hyper_util::client::legacy::connect::{Connected as HyperConnected, Connection};
 
+#[cfg(feature = "_tls-any")]
+use crate::transport::TlsInfo;
+
 pub(in crate::transport) trait Io:
     rt::Read + rt::Write + Send + 'static
 {
@@ -12,11 +15,33 @@ pub(in crate::transport) trait Io:
 
 impl<T> Io for T where T: rt::Read + rt::Write + Send + 'static {}
 
-pub(crate) struct BoxedIo(Pin<Box<dyn Io>>);
+pub(crate) struct BoxedIo {
+    io: Pin<Box<dyn Io>>,
+    #[cfg(feature = "_tls-any")]
+    tls_info: Option<TlsInfo>,
+}
 
 impl BoxedIo {
     pub(in crate::transport) fn new<I: Io>(io: I) -> Self {
-        BoxedIo(Box::pin(io))
+        BoxedIo {
+            io: Box::pin(io),
+            #[cfg(feature = "_tls-any")]
+            tls_info: None,
+        }
+    }
+
+    /// Attaches the parameters negotiated by the TLS handshake of the connection.
+    #[cfg(feature = "_tls-any")]
+    pub(in crate::transport) fn with_tls_info(self, tls_info: TlsInfo) -> Self {
+        BoxedIo {
+            tls_info: Some(tls_info),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "_tls-any")]
+    pub(in crate::transport) fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
     }
 }
 
@@ -32,7 +57,7 @@ impl rt::Read for BoxedIo {
         cx: &mut Context<'_>,
         buf: rt::ReadBufCursor<'_>,
     ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.0).poll_read(cx, buf)
+        self.io.as_mut().poll_read(cx, buf)
     }
 }
 
@@ -42,15 +67,15 @@ impl rt::Write for BoxedIo {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.0).poll_write(cx, buf)
+        self.io.as_mut().poll_write(cx, buf)
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.0).poll_flush(cx)
+        self.io.as_mut().poll_flush(cx)
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.0).poll_shutdown(cx)
+        self.io.as_mut().poll_shutdown(cx)
     }
 
     fn poll_write_vectored(
@@ -58,10 +83,10 @@ impl rt::Write for BoxedIo {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<Result<usize, io::Error>> {
-        Pin::new(&mut self.0).poll_write_vectored(cx, bufs)
+        self.io.as_mut().poll_write_vectored(cx, bufs)
     }
 
     fn is_write_vectored(&self) -> bool {
-        self.0.is_write_vectored()
+        self.io.is_write_vectored()
     }
 }
@@ -0,0 +1,418 @@
+//! HTTP/2 keepalive statistics, gathered by watching the `PING` and `GOAWAY` frames exchanged on
+//! the connections of an endpoint.
+
+use crate::time::{Clock, SharedClock};
+use hyper::rt;
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+const PREFACE_LEN: usize = 24;
+const FRAME_HEADER_LEN: usize = 9;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const ACK: u8 = 0x1;
+
+/// The size of the buffer reads from the connection go through.
+const SCRATCH_LEN: usize = 16 * 1024;
+
+/// Keepalive statistics of the HTTP/2 connections to an endpoint.
+///
+/// Obtained with [`Endpoint::keep_alive_stats`](super::super::Endpoint::keep_alive_stats), they
+/// are updated by every connection the endpoint establishes, including the ones replacing a lost
+/// connection. Clones share the same counters.
+///
+/// The round trip time is measured for every `PING` frame sent by the client, which includes the
+/// keepalive pings enabled with
+/// [`http2_keep_alive_interval`](super::super::Endpoint::http2_keep_alive_interval) and the pings
+/// of the [adaptive window](super::super::Endpoint::http2_adaptive_window). A growing round trip
+/// time, missed pings or `GOAWAY` frames are early signs of a degrading network path or server.
+#[derive(Debug, Clone, Default)]
+pub struct KeepAliveStats {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    pings_sent: AtomicU64,
+    pings_acked: AtomicU64,
+    missed_pings: AtomicU64,
+    goaways_received: AtomicU64,
+    /// The last round trip time in nanoseconds, or `u64::MAX` if no ping was acknowledged yet.
+    last_ping_rtt: AtomicU64,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            pings_sent: AtomicU64::new(0),
+            pings_acked: AtomicU64::new(0),
+            missed_pings: AtomicU64::new(0),
+            goaways_received: AtomicU64::new(0),
+            last_ping_rtt: AtomicU64::new(u64::MAX),
+        }
+    }
+}
+
+impl KeepAliveStats {
+    /// The round trip time of the last acknowledged ping, if any.
+    pub fn last_ping_rtt(&self) -> Option<Duration> {
+        match self.inner.last_ping_rtt.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    /// The number of pings sent.
+    pub fn pings_sent(&self) -> u64 {
+        self.inner.pings_sent.load(Ordering::Relaxed)
+    }
+
+    /// The number of pings the server acknowledged.
+    pub fn pings_acked(&self) -> u64 {
+        self.inner.pings_acked.load(Ordering::Relaxed)
+    }
+
+    /// The number of pings that were never acknowledged because their connection was closed
+    /// first, typically by the [keepalive timeout](super::super::Endpoint::keep_alive_timeout).
+    pub fn missed_pings(&self) -> u64 {
+        self.inner.missed_pings.load(Ordering::Relaxed)
+    }
+
+    /// The number of `GOAWAY` frames received from the server.
+    pub fn goaways_received(&self) -> u64 {
+        self.inner.goaways_received.load(Ordering::Relaxed)
+    }
+
+    fn record_rtt(&self, rtt: Duration) {
+        let nanos = u64::try_from(rtt.as_nanos()).unwrap_or(u64::MAX - 1);
+        self.inner.last_ping_rtt.store(nanos, Ordering::Relaxed);
+        self.inner.pings_acked.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A frame of interest to the statistics.
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Ping { ack: bool, payload: [u8; 8] },
+    GoAway,
+}
+
+/// Splits one direction of an HTTP/2 connection into frames, however the bytes are chunked.
+#[derive(Debug)]
+struct FrameReader {
+    /// The bytes of the connection preface left to skip.
+    preface: usize,
+    header: [u8; FRAME_HEADER_LEN],
+    header_len: usize,
+    /// The bytes of the payload of the current frame left to read.
+    remaining: usize,
+    payload: [u8; 8],
+    payload_len: usize,
+}
+
+impl FrameReader {
+    fn new(preface: usize) -> Self {
+        Self {
+            preface,
+            header: [0; FRAME_HEADER_LEN],
+            header_len: 0,
+            remaining: 0,
+            payload: [0; 8],
+            payload_len: 0,
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        self.header[3]
+    }
+
+    fn read(&mut self, mut bytes: &[u8], mut on_frame: impl FnMut(Frame)) {
+        while !bytes.is_empty() {
+            if self.preface > 0 {
+                let n = self.preface.min(bytes.len());
+                self.preface -= n;
+                bytes = &bytes[n..];
+            } else if self.header_len < FRAME_HEADER_LEN {
+                let n = (FRAME_HEADER_LEN - self.header_len).min(bytes.len());
+                self.header[self.header_len..self.header_len + n].copy_from_slice(&bytes[..n]);
+                self.header_len += n;
+                bytes = &bytes[n..];
+
+                if self.header_len == FRAME_HEADER_LEN {
+                    let [a, b, c, ..] = self.header;
+                    self.remaining = u32::from_be_bytes([0, a, b, c]) as usize;
+                    self.payload_len = 0;
+                    if self.kind() == GOAWAY {
+                        on_frame(Frame::GoAway);
+                    }
+                    if self.remaining == 0 {
+                        self.header_len = 0;
+                    }
+                }
+            } else {
+                let n = self.remaining.min(bytes.len());
+                if self.kind() == PING {
+                    let copied = (self.payload.len() - self.payload_len).min(n);
+                    self.payload[self.payload_len..self.payload_len + copied]
+                        .copy_from_slice(&bytes[..copied]);
+                    self.payload_len += copied;
+                }
+                self.remaining -= n;
+                bytes = &bytes[n..];
+
+                if self.remaining == 0 {
+                    if self.kind() == PING && self.payload_len == self.payload.len() {
+                        on_frame(Frame::Ping {
+                            ack: self.header[4] & ACK != 0,
+                            payload: self.payload,
+                        });
+                    }
+                    self.header_len = 0;
+                }
+            }
+        }
+    }
+}
+
+/// The IO of a client connection, recording its keepalive statistics.
+pub(crate) struct KeepAliveIo<I> {
+    io: I,
+    recorder: Recorder,
+    scratch: Box<[u8]>,
+}
+
+impl<I> KeepAliveIo<I> {
+    pub(crate) fn new(io: I, stats: KeepAliveStats, clock: SharedClock) -> Self {
+        Self {
+            io,
+            recorder: Recorder::new(stats, clock),
+            scratch: vec![0; SCRATCH_LEN].into_boxed_slice(),
+        }
+    }
+}
+
+/// Matches the pings sent on a connection with their acknowledgements.
+struct Recorder {
+    stats: KeepAliveStats,
+    clock: SharedClock,
+    sent: FrameReader,
+    received: FrameReader,
+    /// The pings waiting for their acknowledgement, with the time they were sent.
+    outstanding: Vec<([u8; 8], Instant)>,
+}
+
+impl Recorder {
+    fn new(stats: KeepAliveStats, clock: SharedClock) -> Self {
+        Self {
+            stats,
+            clock,
+            sent: FrameReader::new(PREFACE_LEN),
+            received: FrameReader::new(0),
+            outstanding: Vec::new(),
+        }
+    }
+
+    fn on_sent(&mut self, bytes: &[u8]) {
+        let Self {
+            stats,
+            clock,
+            sent,
+            outstanding,
+            ..
+        } = self;
+        sent.read(bytes, |frame| {
+            if let Frame::Ping {
+                ack: false,
+                payload,
+            } = frame
+            {
+                stats.inner.pings_sent.fetch_add(1, Ordering::Relaxed);
+                outstanding.push((payload, clock.now()));
+            }
+        });
+    }
+
+    fn on_received(&mut self, bytes: &[u8]) {
+        let Self {
+            stats,
+            clock,
+            received,
+            outstanding,
+            ..
+        } = self;
+        received.read(bytes, |frame| match frame {
+            Frame::Ping { ack: true, payload } => {
+                if let Some(i) = outstanding.iter().position(|(sent, _)| *sent == payload) {
+                    let (_, sent_at) = outstanding.remove(i);
+                    stats.record_rtt(clock.now().saturating_duration_since(sent_at));
+                }
+            }
+            Frame::Ping { ack: false, .. } => {}
+            Frame::GoAway => {
+                stats.inner.goaways_received.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let missed = self.outstanding.len() as u64;
+        self.stats
+            .inner
+            .missed_pings
+            .fetch_add(missed, Ordering::Relaxed);
+    }
+}
+
+impl<I: rt::Read + Unpin> rt::Read for KeepAliveIo<I> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: rt::ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        let Self {
+            io,
+            recorder,
+            scratch,
+        } = self.get_mut();
+
+        let len = buf.remaining().min(scratch.len());
+        let mut read = rt::ReadBuf::new(&mut scratch[..len]);
+        ready!(Pin::new(io).poll_read(cx, read.unfilled()))?;
+
+        let filled = read.filled();
+        recorder.on_received(filled);
+        buf.put_slice(filled);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<I: rt::Write + Unpin> rt::Write for KeepAliveIo<I> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let n = ready!(Pin::new(&mut self.io).poll_write(cx, buf))?;
+        self.recorder.on_sent(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let n = ready!(Pin::new(&mut self.io).poll_write_vectored(cx, bufs))?;
+
+        let mut left = n;
+        for buf in bufs {
+            let len = buf.len().min(left);
+            self.recorder.on_sent(&buf[..len]);
+            left -= len;
+            if left == 0 {
+                break;
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn read_all(reader: &mut FrameReader, chunks: &[&[u8]]) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        for chunk in chunks {
+            reader.read(chunk, |frame| frames.push(frame));
+        }
+        frames
+    }
+
+    #[test]
+    fn skips_preface_and_other_frames() {
+        let mut bytes = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        bytes.extend(frame(0x4, 0, &[0; 6]));
+        bytes.extend(frame(PING, 0, &[1, 2, 3, 4, 5, 6, 7, 8]));
+        bytes.extend(frame(0x0, 0, &[PING; 20]));
+        bytes.extend(frame(GOAWAY, 0, &[0; 8]));
+
+        let frames = read_all(&mut FrameReader::new(PREFACE_LEN), &[&bytes]);
+        assert_eq!(
+            frames,
+            [
+                Frame::Ping {
+                    ack: false,
+                    payload: [1, 2, 3, 4, 5, 6, 7, 8]
+                },
+                Frame::GoAway
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_frames_split_across_chunks() {
+        let mut bytes = frame(0x1, 0x4, &[0; 3]);
+        bytes.extend(frame(PING, ACK, &[8; 8]));
+
+        let chunks = bytes.chunks(1).collect::<Vec<_>>();
+        let frames = read_all(&mut FrameReader::new(0), &chunks);
+        assert_eq!(
+            frames,
+            [Frame::Ping {
+                ack: true,
+                payload: [8; 8]
+            }]
+        );
+    }
+
+    #[test]
+    fn records_rtt_of_acked_pings() {
+        let stats = KeepAliveStats::default();
+        let mut recorder = Recorder::new(stats.clone(), SharedClock::default());
+        recorder.sent = FrameReader::new(0);
+
+        recorder.on_sent(&frame(PING, 0, &[1; 8]));
+        recorder.on_sent(&frame(PING, 0, &[2; 8]));
+        assert_eq!(stats.pings_sent(), 2);
+        assert_eq!(stats.last_ping_rtt(), None);
+
+        recorder.on_received(&frame(PING, ACK, &[1; 8]));
+        recorder.on_received(&frame(GOAWAY, 0, &[0; 8]));
+        assert_eq!(stats.pings_acked(), 1);
+        assert!(stats.last_ping_rtt().is_some());
+        assert_eq!(stats.goaways_received(), 1);
+
+        drop(recorder);
+        assert_eq!(stats.missed_pings(), 1);
+    }
+}
@@ -0,0 +1,497 @@
+//! Per-call [ORCA](https://github.com/grpc/proposal/blob/master/A51-custom-backend-metrics.md)
+//! load reports and utilization based load balancing.
+
+use super::health::get_varint;
+use crate::{
+    body::Body,
+    metadata::MetadataMap,
+    time::{Clock, SharedClock},
+    transport::channel::BoxFuture,
+    Status,
+};
+use base64::Engine as _;
+use bytes::{Buf, Bytes};
+use http::{HeaderMap, Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_service::Service;
+
+/// The metadata key servers send per-call load reports with.
+const LOAD_REPORT_HEADER: &str = "endpoint-load-metrics-bin";
+
+/// A backend load report, as sent by servers in the `endpoint-load-metrics-bin` trailer.
+///
+/// The report is the protobuf encoded `xds.data.orca.v3.OrcaLoadReport` message. Reports sent on
+/// the calls of an endpoint with [`load_reporting`](super::super::Endpoint::load_reporting)
+/// enabled are used by a balanced [`Channel`](super::super::Channel) automatically, and can be
+/// read from the trailers of any call with [`LoadReport::from_metadata`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    cpu_utilization: f64,
+    mem_utilization: f64,
+    application_utilization: f64,
+    rps_fractional: f64,
+    eps: f64,
+    request_cost: HashMap<String, f64>,
+    utilization: HashMap<String, f64>,
+    named_metrics: HashMap<String, f64>,
+}
+
+impl LoadReport {
+    /// Extracts the load report from the `endpoint-load-metrics-bin` entry of `metadata`.
+    ///
+    /// Returns `None` if there is no such entry or it is not a valid load report.
+    pub fn from_metadata(metadata: &MetadataMap) -> Option<Self> {
+        Self::from_header_map(metadata.as_ref())
+    }
+
+    pub(crate) fn from_header_map(headers: &HeaderMap) -> Option<Self> {
+        let value = headers.get(LOAD_REPORT_HEADER)?;
+        let bytes = crate::util::base64::STANDARD
+            .decode(value.as_bytes())
+            .ok()?;
+        Self::decode(&bytes)
+    }
+
+    /// Decodes a protobuf encoded `xds.data.orca.v3.OrcaLoadReport`.
+    ///
+    /// Returns `None` if `buf` is not a valid encoding.
+    pub fn decode(mut buf: &[u8]) -> Option<Self> {
+        let mut report = LoadReport::default();
+
+        while buf.has_remaining() {
+            let key = get_varint(&mut buf)?;
+
+            match (key >> 3, key & 0x7) {
+                (1, 1) => report.cpu_utilization = get_f64(&mut buf)?,
+                (2, 1) => report.mem_utilization = get_f64(&mut buf)?,
+                (4, 2) => insert_entry(&mut report.request_cost, get_bytes(&mut buf)?)?,
+                (5, 2) => insert_entry(&mut report.utilization, get_bytes(&mut buf)?)?,
+                (6, 1) => report.rps_fractional = get_f64(&mut buf)?,
+                (7, 1) => report.eps = get_f64(&mut buf)?,
+                (8, 2) => insert_entry(&mut report.named_metrics, get_bytes(&mut buf)?)?,
+                (9, 1) => report.application_utilization = get_f64(&mut buf)?,
+                (_, wire_type) => skip(&mut buf, wire_type)?,
+            }
+        }
+
+        Some(report)
+    }
+
+    /// The CPU utilization of the endpoint, usually in `[0, 1]`.
+    pub fn cpu_utilization(&self) -> f64 {
+        self.cpu_utilization
+    }
+
+    /// The memory utilization of the endpoint, in `[0, 1]`.
+    pub fn mem_utilization(&self) -> f64 {
+        self.mem_utilization
+    }
+
+    /// The application specific utilization of the endpoint, usually in `[0, 1]`.
+    pub fn application_utilization(&self) -> f64 {
+        self.application_utilization
+    }
+
+    /// The requests per second the endpoint is serving.
+    pub fn rps(&self) -> f64 {
+        self.rps_fractional
+    }
+
+    /// The errors per second the endpoint is returning.
+    pub fn eps(&self) -> f64 {
+        self.eps
+    }
+
+    /// Application specific costs of the request this report was sent with.
+    pub fn request_cost(&self) -> &HashMap<String, f64> {
+        &self.request_cost
+    }
+
+    /// Application specific utilizations of the endpoint, in `[0, 1]`.
+    pub fn utilization(&self) -> &HashMap<String, f64> {
+        &self.utilization
+    }
+
+    /// Application specific named metrics.
+    pub fn named_metrics(&self) -> &HashMap<String, f64> {
+        &self.named_metrics
+    }
+
+    /// The load of the endpoint according to this report, as used by the balancer.
+    ///
+    /// This is the inverse of the weight computed by gRPC's weighted round robin policy, so the
+    /// endpoint with the lowest load is the one that has the most capacity left per request.
+    fn load(&self, error_utilization_penalty: f64) -> Option<f64> {
+        let utilization = if self.application_utilization > 0.0 {
+            self.application_utilization
+        } else {
+            self.cpu_utilization
+        };
+
+        if utilization <= 0.0 || self.rps_fractional <= 0.0 {
+            return None;
+        }
+
+        let penalty = self.eps / self.rps_fractional * error_utilization_penalty;
+        Some((utilization + penalty) / self.rps_fractional)
+    }
+}
+
+fn get_f64(buf: &mut &[u8]) -> Option<f64> {
+    (buf.remaining() >= 8).then(|| buf.get_f64_le())
+}
+
+fn get_bytes<'a>(buf: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = get_varint(buf)? as usize;
+    if buf.len() < len {
+        return None;
+    }
+
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Some(bytes)
+}
+
+fn skip(buf: &mut &[u8], wire_type: u64) -> Option<()> {
+    match wire_type {
+        0 => {
+            get_varint(buf)?;
+        }
+        1 if buf.remaining() >= 8 => buf.advance(8),
+        2 => {
+            get_bytes(buf)?;
+        }
+        5 if buf.remaining() >= 4 => buf.advance(4),
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Decodes a `map<string, double>` entry into `map`.
+fn insert_entry(map: &mut HashMap<String, f64>, mut entry: &[u8]) -> Option<()> {
+    let mut key = String::new();
+    let mut value = 0.0;
+
+    while entry.has_remaining() {
+        let tag = get_varint(&mut entry)?;
+
+        match (tag >> 3, tag & 0x7) {
+            (1, 2) => key = std::str::from_utf8(get_bytes(&mut entry)?).ok()?.to_owned(),
+            (2, 1) => value = get_f64(&mut entry)?,
+            (_, wire_type) => skip(&mut entry, wire_type)?,
+        }
+    }
+
+    map.insert(key, value);
+    Some(())
+}
+
+/// Configures utilization based load balancing for an [`Endpoint`](super::super::Endpoint).
+///
+/// With load reporting enabled, the ORCA load reports the endpoint sends in the
+/// `endpoint-load-metrics-bin` trailer of its responses feed into the load of the endpoint. A
+/// balanced [`Channel`](super::super::Channel) then prefers endpoints that report a lower
+/// utilization relative to the requests per second they serve, which is the same weighting as
+/// gRPC's weighted round robin policy.
+///
+/// Endpoints that did not send a usable report, i.e. one with a non-zero utilization and request
+/// rate, within the [`expiration_period`](Self::expiration_period) have no load, so new endpoints
+/// receive requests until they start reporting.
+///
+/// ```
+/// # use tonic::transport::{channel::LoadReporting, Endpoint};
+/// # use std::time::Duration;
+/// let endpoint = Endpoint::from_static("http://10.0.0.1:50051").load_reporting(
+///     LoadReporting::new().expiration_period(Duration::from_secs(60)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoadReporting {
+    expiration_period: Duration,
+    error_utilization_penalty: f64,
+}
+
+impl LoadReporting {
+    /// Creates a new `LoadReporting` with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long a load report is used for. Defaults to 3 minutes.
+    pub fn expiration_period(self, period: Duration) -> Self {
+        LoadReporting {
+            expiration_period: period,
+            ..self
+        }
+    }
+
+    /// Sets the multiplier for the errors per second reported by an endpoint, which are added
+    /// to its utilization relative to its request rate. Defaults to `1.0`.
+    pub fn error_utilization_penalty(self, penalty: f64) -> Self {
+        LoadReporting {
+            error_utilization_penalty: penalty.max(0.0),
+            ..self
+        }
+    }
+}
+
+impl Default for LoadReporting {
+    fn default() -> Self {
+        Self {
+            expiration_period: Duration::from_secs(180),
+            error_utilization_penalty: 1.0,
+        }
+    }
+}
+
+/// The most recent load of a single endpoint.
+#[derive(Debug)]
+pub(crate) struct LoadTracker {
+    config: LoadReporting,
+    clock: SharedClock,
+    last: Mutex<Option<(f64, Instant)>>,
+}
+
+impl LoadTracker {
+    pub(crate) fn new(config: LoadReporting, clock: SharedClock) -> Self {
+        Self {
+            config,
+            clock,
+            last: Mutex::new(None),
+        }
+    }
+
+    fn record(&self, report: &LoadReport) {
+        if let Some(load) = report.load(self.config.error_utilization_penalty) {
+            *self.last.lock().unwrap() = Some((load, self.clock.now()));
+        }
+    }
+
+    /// The load of the endpoint, or `0.0` if it has no recent load report.
+    pub(crate) fn load(&self) -> f64 {
+        let now = self.clock.now();
+
+        match *self.last.lock().unwrap() {
+            Some((load, at)) if now.duration_since(at) < self.config.expiration_period => load,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Records the load reports received on the responses of the wrapped service.
+pub(crate) struct LoadReporter<S> {
+    inner: S,
+    tracker: Arc<LoadTracker>,
+}
+
+impl<S> LoadReporter<S> {
+    pub(crate) fn new(inner: S, tracker: Arc<LoadTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl<S> Service<Request<Body>> for LoadReporter<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<crate::BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let fut = self.inner.call(req);
+        let tracker = self.tracker.clone();
+
+        Box::pin(async move {
+            let res = fut.await.map_err(Into::into)?;
+
+            // Trailers-only responses carry the report in the headers.
+            if let Some(report) = LoadReport::from_header_map(res.headers()) {
+                tracker.record(&report);
+                return Ok(res);
+            }
+
+            Ok(res.map(|body| {
+                Body::new(ReportedBody {
+                    inner: body,
+                    tracker,
+                })
+            }))
+        })
+    }
+}
+
+impl<S> fmt::Debug for LoadReporter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadReporter").finish()
+    }
+}
+
+/// A response body that records the load report sent in its trailers.
+#[pin_project]
+struct ReportedBody {
+    #[pin]
+    inner: Body,
+    tracker: Arc<LoadTracker>,
+}
+
+impl http_body::Body for ReportedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+
+        if let Some(trailers) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::trailers_ref)
+        {
+            if let Some(report) = LoadReport::from_header_map(trailers) {
+                this.tracker.record(&report);
+            }
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BufMut;
+
+    fn put_double(buf: &mut Vec<u8>, field: u8, value: f64) {
+        buf.put_u8(field << 3 | 1);
+        buf.put_f64_le(value);
+    }
+
+    fn put_entry(buf: &mut Vec<u8>, field: u8, key: &str, value: f64) {
+        let mut entry = vec![0x0a, key.len() as u8];
+        entry.extend_from_slice(key.as_bytes());
+        put_double(&mut entry, 2, value);
+
+        buf.put_u8(field << 3 | 2);
+        buf.put_u8(entry.len() as u8);
+        buf.extend_from_slice(&entry);
+    }
+
+    #[test]
+    fn decodes_reports() {
+        let mut buf = Vec::new();
+        put_double(&mut buf, 1, 0.5);
+        put_double(&mut buf, 2, 0.25);
+        // the deprecated integer rps field is skipped
+        buf.extend_from_slice(&[0x18, 0x96, 0x01]);
+        put_entry(&mut buf, 4, "db", 3.0);
+        put_entry(&mut buf, 5, "queue", 0.75);
+        put_double(&mut buf, 6, 100.0);
+        put_double(&mut buf, 7, 2.0);
+        put_double(&mut buf, 9, 0.6);
+
+        let report = LoadReport::decode(&buf).unwrap();
+        assert_eq!(report.cpu_utilization(), 0.5);
+        assert_eq!(report.mem_utilization(), 0.25);
+        assert_eq!(report.request_cost().get("db"), Some(&3.0));
+        assert_eq!(report.utilization().get("queue"), Some(&0.75));
+        assert_eq!(report.rps(), 100.0);
+        assert_eq!(report.eps(), 2.0);
+        assert_eq!(report.application_utilization(), 0.6);
+        assert!(report.named_metrics().is_empty());
+
+        assert_eq!(LoadReport::decode(&buf[..buf.len() - 1]), None);
+    }
+
+    #[test]
+    fn reads_reports_from_metadata() {
+        let mut buf = Vec::new();
+        put_double(&mut buf, 1, 0.5);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LOAD_REPORT_HEADER,
+            crate::util::base64::STANDARD_NO_PAD
+                .encode(&buf)
+                .parse()
+                .unwrap(),
+        );
+
+        let report = LoadReport::from_metadata(&MetadataMap::from_headers(headers)).unwrap();
+        assert_eq!(report.cpu_utilization(), 0.5);
+    }
+
+    #[test]
+    fn load_follows_utilization_per_request() {
+        let report = |cpu: f64, rps: f64, eps: f64| {
+            let mut buf = Vec::new();
+            put_double(&mut buf, 1, cpu);
+            put_double(&mut buf, 6, rps);
+            put_double(&mut buf, 7, eps);
+            LoadReport::decode(&buf).unwrap()
+        };
+
+        assert_eq!(report(0.5, 100.0, 0.0).load(1.0), Some(0.005));
+        assert_eq!(report(0.5, 100.0, 10.0).load(1.0), Some(0.006));
+        assert_eq!(report(0.5, 0.0, 0.0).load(1.0), None);
+        assert_eq!(report(0.0, 100.0, 0.0).load(1.0), None);
+    }
+
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+
+        fn sleep_until(&self, _: Instant) -> crate::time::Sleep {
+            Box::pin(std::future::pending())
+        }
+    }
+
+    #[test]
+    fn reports_expire() {
+        let clock = ManualClock(Arc::new(Mutex::new(Instant::now())));
+        let tracker = LoadTracker::new(
+            LoadReporting::new().expiration_period(Duration::from_secs(10)),
+            SharedClock::new(clock.clone()),
+        );
+
+        let mut buf = Vec::new();
+        put_double(&mut buf, 1, 0.5);
+        put_double(&mut buf, 6, 10.0);
+        tracker.record(&LoadReport::decode(&buf).unwrap());
+        assert_eq!(tracker.load(), 0.05);
+
+        *clock.0.lock().unwrap() += Duration::from_secs(10);
+        assert_eq!(tracker.load(), 0.0);
+    }
+}
@@ -0,0 +1,371 @@
+//! Transport health metrics of a channel, gathered by its connections and the requests it sends.
+
+use crate::body::Body;
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// The metadata key of the attempts of a call that were made before a retry, as set by the
+/// [`ServiceConfigLayer`](crate::service::ServiceConfigLayer) retries.
+pub(crate) const GRPC_PREVIOUS_RPC_ATTEMPTS: &str = "grpc-previous-rpc-attempts";
+
+/// The connectivity state of a [`Channel`](super::super::Channel), derived from the state of its
+/// connections like the connectivity state of gRPC channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectivityState {
+    /// No connection is established nor being established, e.g. before the first request of a
+    /// lazy channel or after the connections were closed.
+    Idle,
+    /// A connection is being established.
+    Connecting,
+    /// At least one connection is established.
+    Ready,
+    /// The last attempt to establish a connection failed.
+    TransientFailure,
+}
+
+/// A snapshot of the transport health of a [`Channel`](super::super::Channel).
+///
+/// Obtained with [`Channel::metrics`](super::super::Channel::metrics), the counters cover the
+/// connections to every endpoint of the channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMetrics {
+    connections: u64,
+    state: ConnectivityState,
+    queued_requests: u64,
+    in_flight: u64,
+    retries: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl ChannelMetrics {
+    /// The number of established connections.
+    pub fn connections(&self) -> u64 {
+        self.connections
+    }
+
+    /// The connectivity state of the channel.
+    pub fn state(&self) -> ConnectivityState {
+        self.state
+    }
+
+    /// The number of requests waiting to be handed to a connection, e.g. while it is being
+    /// established.
+    pub fn queued_requests(&self) -> u64 {
+        self.queued_requests
+    }
+
+    /// The number of calls in flight, from the time they are sent until their response ends or
+    /// is dropped.
+    pub fn in_flight(&self) -> u64 {
+        self.in_flight
+    }
+
+    /// The number of calls sent as retries of a previous attempt, e.g. by the retry policy of a
+    /// `ServiceConfigLayer` wrapping the channel.
+    ///
+    /// Retries are recognized by their `grpc-previous-rpc-attempts` metadata.
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// The number of bytes written to the connections, including the HTTP/2 framing but
+    /// excluding TLS.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// The number of bytes read from the connections, including the HTTP/2 framing but
+    /// excluding TLS.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+}
+
+/// The counters shared by a channel and its connections.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChannelStats {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    connections: AtomicU64,
+    connecting: AtomicU64,
+    failed: AtomicBool,
+    queued: AtomicU64,
+    in_flight: AtomicU64,
+    retries: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl ChannelStats {
+    pub(crate) fn snapshot(&self) -> ChannelMetrics {
+        let inner = &self.inner;
+        let connections = inner.connections.load(Ordering::Relaxed);
+        let state = if connections > 0 {
+            ConnectivityState::Ready
+        } else if inner.connecting.load(Ordering::Relaxed) > 0 {
+            ConnectivityState::Connecting
+        } else if inner.failed.load(Ordering::Relaxed) {
+            ConnectivityState::TransientFailure
+        } else {
+            ConnectivityState::Idle
+        };
+
+        ChannelMetrics {
+            connections,
+            state,
+            queued_requests: inner.queued.load(Ordering::Relaxed),
+            in_flight: inner.in_flight.load(Ordering::Relaxed),
+            retries: inner.retries.load(Ordering::Relaxed),
+            bytes_sent: inner.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: inner.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Records a connection being established until the returned guard is dropped.
+    pub(crate) fn connecting(&self) -> Connecting {
+        self.inner.connecting.fetch_add(1, Ordering::Relaxed);
+        Connecting(self.clone())
+    }
+
+    /// Records a request in flight until the returned guard is dropped.
+    pub(crate) fn start(&self, retry: bool) -> InFlight {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        if retry {
+            self.inner.retries.fetch_add(1, Ordering::Relaxed);
+        }
+        InFlight(self.clone())
+    }
+
+    /// Records a request waiting in the queue of the channel until the returned extension is
+    /// taken by a connection or dropped with the request.
+    pub(crate) fn enqueue(&self) -> QueuedRequest {
+        self.inner.queued.fetch_add(1, Ordering::Relaxed);
+        QueuedRequest {
+            _dequeue: Arc::new(Dequeue(self.clone())),
+        }
+    }
+}
+
+/// A connection being established.
+pub(crate) struct Connecting(ChannelStats);
+
+impl Connecting {
+    /// Records the established connection for as long as the returned IO is alive.
+    pub(crate) fn connected<IO>(self, io: IO) -> CountingIo<IO> {
+        let inner = &self.0.inner;
+        inner.failed.store(false, Ordering::Relaxed);
+        inner.connections.fetch_add(1, Ordering::Relaxed);
+        CountingIo {
+            io,
+            connected: Connected(self.0.clone()),
+        }
+    }
+
+    /// Records that the connection could not be established.
+    pub(crate) fn failed(self) {
+        self.0.inner.failed.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Connecting {
+    fn drop(&mut self) {
+        self.0.inner.connecting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A request in flight.
+pub(crate) struct InFlight(ChannelStats);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.inner.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Marks a request waiting in the queue of a channel, in its extensions.
+#[derive(Clone)]
+pub(crate) struct QueuedRequest {
+    _dequeue: Arc<Dequeue>,
+}
+
+struct Dequeue(ChannelStats);
+
+impl Drop for Dequeue {
+    fn drop(&mut self) {
+        self.0.inner.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// An established connection.
+struct Connected(ChannelStats);
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        self.0.inner.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The IO of an established connection, counting the bytes sent and received.
+#[pin_project]
+pub(crate) struct CountingIo<IO> {
+    #[pin]
+    io: IO,
+    connected: Connected,
+}
+
+impl<IO: AsyncRead> AsyncRead for CountingIo<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+        ready!(this.io.poll_read(cx, buf))?;
+        let read = (buf.filled().len() - filled) as u64;
+        let stats = &this.connected.0.inner;
+        stats.bytes_received.fetch_add(read, Ordering::Relaxed);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncWrite> AsyncWrite for CountingIo<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.io.poll_write(cx, buf))?;
+        let stats = &this.connected.0.inner;
+        stats.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.io.poll_write_vectored(cx, bufs))?;
+        let stats = &this.connected.0.inner;
+        stats.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+        Poll::Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+/// A response body keeping its call in flight until it is dropped.
+#[pin_project]
+pub(crate) struct InFlightBody {
+    #[pin]
+    inner: Body,
+    _in_flight: InFlight,
+}
+
+impl InFlightBody {
+    pub(crate) fn new(inner: Body, in_flight: InFlight) -> Self {
+        Self {
+            inner,
+            _in_flight: in_flight,
+        }
+    }
+}
+
+impl http_body::Body for InFlightBody {
+    type Data = Bytes;
+    type Error = crate::Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.project().inner.poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_connectivity_state() {
+        let stats = ChannelStats::default();
+        assert_eq!(stats.snapshot().state(), ConnectivityState::Idle);
+
+        let connecting = stats.connecting();
+        assert_eq!(stats.snapshot().state(), ConnectivityState::Connecting);
+        connecting.failed();
+        assert_eq!(
+            stats.snapshot().state(),
+            ConnectivityState::TransientFailure
+        );
+
+        let io = stats.connecting().connected(());
+        let metrics = stats.snapshot();
+        assert_eq!(metrics.state(), ConnectivityState::Ready);
+        assert_eq!(metrics.connections(), 1);
+
+        drop(io);
+        assert_eq!(stats.snapshot().state(), ConnectivityState::Idle);
+    }
+
+    #[test]
+    fn counts_queued_and_in_flight_requests() {
+        let stats = ChannelStats::default();
+        let queued = stats.enqueue();
+        let in_flight = stats.start(false);
+        let retry = stats.start(true);
+
+        let metrics = stats.snapshot();
+        assert_eq!(metrics.queued_requests(), 1);
+        assert_eq!(metrics.in_flight(), 2);
+        assert_eq!(metrics.retries(), 1);
+
+        // Clones of the extension share the same place in the queue.
+        drop(queued.clone());
+        assert_eq!(stats.snapshot().queued_requests(), 1);
+        drop((queued, in_flight, retry));
+
+        let metrics = stats.snapshot();
+        assert_eq!(metrics.queued_requests(), 0);
+        assert_eq!(metrics.in_flight(), 0);
+        assert_eq!(metrics.retries(), 1);
+    }
+}
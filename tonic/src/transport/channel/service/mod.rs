@@ -8,15 +8,53 @@ mod reconnect;
 use self::reconnect::Reconnect;
 
 mod connection;
-pub(super) use self::connection::Connection;
+pub(super) use self::connection::{Connection, ConnectionLayer, ConnectionService};
+
+mod health;
+
+mod load_report;
+pub use self::load_report::{LoadReport, LoadReporting};
+pub(super) use self::load_report::{LoadReporter, LoadTracker};
+
+mod outlier;
+pub use self::outlier::OutlierDetection;
+pub(super) use self::outlier::OutlierDetector;
+
+mod rate_limit;
+use self::rate_limit::RateLimit;
+
+mod ring_hash;
+pub use self::ring_hash::RingHash;
+pub(super) use self::ring_hash::RingHashBalance;
+
+mod pin;
+use self::pin::select_pinned;
+pub use self::pin::PinEndpoint;
+
+mod priority;
+pub use self::priority::Priority;
+pub(super) use self::priority::PriorityBalance;
 
 mod discover;
 pub use self::discover::Change;
 pub(super) use self::discover::DynamicServiceStream;
 
+pub(super) mod queue;
+
+mod warmup;
+pub(super) use self::warmup::Warmup;
+
 mod io;
 use self::io::BoxedIo;
 
+mod metrics;
+pub use self::metrics::{ChannelMetrics, ConnectivityState};
+pub(super) use self::metrics::{ChannelStats, InFlight, InFlightBody, GRPC_PREVIOUS_RPC_ATTEMPTS};
+
+mod keep_alive;
+use self::keep_alive::KeepAliveIo;
+pub use self::keep_alive::KeepAliveStats;
+
 mod connector;
 pub(crate) use self::connector::Connector;
 
@@ -27,3 +65,6 @@ pub(super) use self::executor::{Executor, SharedExec};
 mod tls;
 #[cfg(feature = "_tls-any")]
 pub(super) use self::tls::TlsConnector;
+
+#[cfg(feature = "_tls-any")]
+mod spiffe;
@@ -0,0 +1,435 @@
+//! Per-endpoint outlier detection and ejection.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock, Sleep},
+    transport::channel::BoxFuture,
+    Code, Status,
+};
+use bytes::Bytes;
+use http::{Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use std::{
+    cmp, fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_service::Service;
+
+/// Configures outlier detection for an [`Endpoint`](super::super::Endpoint).
+///
+/// Outlier detection tracks the outcome of the requests sent to an endpoint and temporarily
+/// ejects it, i.e. stops routing requests to it, once it fails too often. An ejected endpoint is
+/// reintroduced after its ejection time elapsed. Every consecutive ejection multiplies the
+/// ejection time, up to [`max_ejection_time`](Self::max_ejection_time), and each interval the
+/// endpoint stays healthy decreases the multiplier again, so flapping endpoints are reintroduced
+/// gradually.
+///
+/// A request is considered failed if it could not be sent, or if it completed with one of the
+/// `UNKNOWN`, `DEADLINE_EXCEEDED`, `INTERNAL`, `UNAVAILABLE` or `DATA_LOSS` status codes.
+///
+/// ```
+/// # use tonic::transport::{channel::OutlierDetection, Endpoint};
+/// # use std::time::Duration;
+/// let endpoint = Endpoint::from_static("http://10.0.0.1:50051").outlier_detection(
+///     OutlierDetection::new()
+///         .consecutive_failures(5)
+///         .base_ejection_time(Duration::from_secs(30)),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutlierDetection {
+    interval: Duration,
+    consecutive_failures: Option<u32>,
+    failure_percentage: Option<(u32, u32)>,
+    base_ejection_time: Duration,
+    max_ejection_time: Duration,
+}
+
+impl OutlierDetection {
+    /// Creates a new `OutlierDetection` that ejects an endpoint after 5 consecutive failures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interval over which the failure percentage is computed. Defaults to 10 seconds.
+    pub fn interval(self, interval: Duration) -> Self {
+        OutlierDetection { interval, ..self }
+    }
+
+    /// Sets the number of consecutive failures after which an endpoint is ejected.
+    ///
+    /// `None` disables ejection based on consecutive failures. Defaults to 5.
+    pub fn consecutive_failures(self, failures: impl Into<Option<u32>>) -> Self {
+        OutlierDetection {
+            consecutive_failures: failures.into(),
+            ..self
+        }
+    }
+
+    /// Ejects an endpoint once `percentage` percent of its requests failed within one
+    /// [`interval`](Self::interval), provided it received at least `minimum_requests` requests.
+    ///
+    /// Disabled by default.
+    pub fn failure_percentage(self, percentage: u32, minimum_requests: u32) -> Self {
+        OutlierDetection {
+            failure_percentage: Some((percentage.min(100), minimum_requests)),
+            ..self
+        }
+    }
+
+    /// Sets the time an endpoint is ejected for the first time. Defaults to 30 seconds.
+    pub fn base_ejection_time(self, time: Duration) -> Self {
+        OutlierDetection {
+            base_ejection_time: time,
+            ..self
+        }
+    }
+
+    /// Sets the maximum time an endpoint is ejected. Defaults to 300 seconds.
+    pub fn max_ejection_time(self, time: Duration) -> Self {
+        OutlierDetection {
+            max_ejection_time: time,
+            ..self
+        }
+    }
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            consecutive_failures: Some(5),
+            failure_percentage: None,
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(300),
+        }
+    }
+}
+
+/// The outcome tracking of a single endpoint.
+#[derive(Debug)]
+struct Tracker {
+    config: OutlierDetection,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    interval_start: Instant,
+    successes: u32,
+    failures: u32,
+    consecutive_failures: u32,
+    ejection_multiplier: u32,
+    ejected_until: Option<Instant>,
+}
+
+impl Tracker {
+    fn new(config: OutlierDetection, now: Instant) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State {
+                interval_start: now,
+                successes: 0,
+                failures: 0,
+                consecutive_failures: 0,
+                ejection_multiplier: 0,
+                ejected_until: None,
+            }),
+        }
+    }
+
+    /// Returns the instant the endpoint is ejected until, if it is currently ejected.
+    fn ejected_until(&self, now: Instant) -> Option<Instant> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.ejected_until {
+            Some(until) if until > now => Some(until),
+            Some(_) => {
+                state.ejected_until = None;
+                state.interval_start = now;
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn record(&self, success: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.ejected_until.is_some() {
+            return;
+        }
+
+        if now.duration_since(state.interval_start) >= self.config.interval {
+            state.interval_start = now;
+            state.successes = 0;
+            state.failures = 0;
+            state.ejection_multiplier = state.ejection_multiplier.saturating_sub(1);
+        }
+
+        if success {
+            state.successes += 1;
+            state.consecutive_failures = 0;
+            return;
+        }
+
+        state.failures += 1;
+        state.consecutive_failures += 1;
+
+        let too_many_consecutive = self
+            .config
+            .consecutive_failures
+            .is_some_and(|max| state.consecutive_failures >= max);
+
+        let too_high_percentage =
+            self.config
+                .failure_percentage
+                .is_some_and(|(percentage, minimum_requests)| {
+                    let total = state.successes + state.failures;
+                    total >= minimum_requests && state.failures * 100 >= percentage * total
+                });
+
+        if too_many_consecutive || too_high_percentage {
+            state.ejection_multiplier += 1;
+
+            let ejection_time = cmp::min(
+                self.config
+                    .base_ejection_time
+                    .saturating_mul(state.ejection_multiplier),
+                cmp::max(
+                    self.config.base_ejection_time,
+                    self.config.max_ejection_time,
+                ),
+            );
+
+            tracing::debug!("ejecting endpoint for {:?}", ejection_time);
+
+            state.ejected_until = Some(now + ejection_time);
+            state.successes = 0;
+            state.failures = 0;
+            state.consecutive_failures = 0;
+        }
+    }
+}
+
+fn is_failure(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unknown
+            | Code::DeadlineExceeded
+            | Code::Internal
+            | Code::Unavailable
+            | Code::DataLoss
+    )
+}
+
+/// Ejects the wrapped service from the pick set while it is an outlier by keeping it not ready.
+pub(crate) struct OutlierDetector<S> {
+    inner: S,
+    tracker: Arc<Tracker>,
+    clock: SharedClock,
+    sleep: Option<(Instant, Sleep)>,
+}
+
+impl<S> OutlierDetector<S> {
+    pub(crate) fn new(inner: S, config: OutlierDetection, clock: SharedClock) -> Self {
+        Self {
+            inner,
+            tracker: Arc::new(Tracker::new(config, clock.now())),
+            clock,
+            sleep: None,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for OutlierDetector<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<crate::BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        while let Some(until) = self.tracker.ejected_until(self.clock.now()) {
+            let (deadline, sleep) = self
+                .sleep
+                .get_or_insert_with(|| (until, self.clock.sleep_until(until)));
+
+            if *deadline != until {
+                *deadline = until;
+                *sleep = self.clock.sleep_until(until);
+            }
+
+            ready!(sleep.as_mut().poll(cx));
+            self.sleep = None;
+        }
+
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let fut = self.inner.call(req);
+        let tracker = self.tracker.clone();
+        let clock = self.clock.clone();
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(res) => {
+                    if let Some(status) = Status::from_header_map(res.headers()) {
+                        tracker.record(!is_failure(status.code()), clock.now());
+                        return Ok(res);
+                    }
+
+                    Ok(res.map(|body| {
+                        Body::new(TrackedBody {
+                            inner: body,
+                            tracker: Some(tracker),
+                            clock,
+                        })
+                    }))
+                }
+                Err(err) => {
+                    tracker.record(false, clock.now());
+                    Err(err.into())
+                }
+            }
+        })
+    }
+}
+
+impl<S> fmt::Debug for OutlierDetector<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutlierDetector").finish()
+    }
+}
+
+/// A response body that records the outcome of the request once its trailers are received.
+#[pin_project]
+struct TrackedBody {
+    #[pin]
+    inner: Body,
+    tracker: Option<Arc<Tracker>>,
+    clock: SharedClock,
+}
+
+impl TrackedBody {
+    fn record(self: Pin<&mut Self>, success: bool) {
+        let this = self.project();
+        if let Some(tracker) = this.tracker.take() {
+            tracker.record(success, this.clock.now());
+        }
+    }
+}
+
+impl http_body::Body for TrackedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let frame = ready!(self.as_mut().project().inner.poll_frame(cx));
+
+        match &frame {
+            Some(Ok(frame)) => {
+                if let Some(trailers) = frame.trailers_ref() {
+                    let success = Status::from_header_map(trailers)
+                        .map_or(true, |status| !is_failure(status.code()));
+                    self.record(success);
+                }
+            }
+            Some(Err(_)) => self.record(false),
+            None => {}
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ejects_after_consecutive_failures() {
+        let now = Instant::now();
+        let tracker = Tracker::new(OutlierDetection::new().consecutive_failures(2), now);
+
+        tracker.record(false, now);
+        tracker.record(true, now);
+        tracker.record(false, now);
+        assert_eq!(tracker.ejected_until(now), None);
+
+        tracker.record(false, now);
+        assert_eq!(
+            tracker.ejected_until(now),
+            Some(now + Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn ejects_on_failure_percentage() {
+        let now = Instant::now();
+        let config = OutlierDetection::new()
+            .consecutive_failures(None)
+            .failure_percentage(50, 4);
+        let tracker = Tracker::new(config, now);
+
+        tracker.record(false, now);
+        tracker.record(true, now);
+        tracker.record(true, now);
+        assert_eq!(tracker.ejected_until(now), None);
+
+        tracker.record(false, now);
+        assert!(tracker.ejected_until(now).is_some());
+    }
+
+    #[test]
+    fn ejection_time_grows_and_decays() {
+        let mut now = Instant::now();
+        let config = OutlierDetection::new()
+            .consecutive_failures(1)
+            .base_ejection_time(Duration::from_secs(10))
+            .max_ejection_time(Duration::from_secs(25));
+        let tracker = Tracker::new(config, now);
+
+        let eject = |now: &mut Instant| {
+            tracker.record(false, *now);
+            let until = tracker.ejected_until(*now).unwrap();
+            let ejection_time = until - *now;
+            *now = until;
+            assert_eq!(tracker.ejected_until(*now), None);
+            ejection_time
+        };
+
+        assert_eq!(eject(&mut now), Duration::from_secs(10));
+        assert_eq!(eject(&mut now), Duration::from_secs(20));
+        assert_eq!(eject(&mut now), Duration::from_secs(25));
+
+        // staying healthy for an interval decreases the multiplier again
+        now += Duration::from_secs(10);
+        tracker.record(true, now);
+        assert_eq!(eject(&mut now), Duration::from_secs(25));
+        now += Duration::from_secs(10);
+        tracker.record(true, now);
+        now += Duration::from_secs(10);
+        tracker.record(true, now);
+        assert_eq!(eject(&mut now), Duration::from_secs(20));
+    }
+}
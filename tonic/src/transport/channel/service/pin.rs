@@ -0,0 +1,71 @@
+//! Pinning of requests to a specific endpoint of a balanced channel.
+
+use super::Connection;
+use crate::{body::Body, Status};
+use http::{Request, Uri};
+use std::hash::Hash;
+use tower::ready_cache::ReadyCache;
+
+/// Pins a request to the endpoint with this URI in a balanced [`Channel`](super::super::Channel),
+/// bypassing load balancing.
+///
+/// This is for follow-up calls that must reach the same server as a previous call, e.g. because
+/// the server holds session state or is the leader accepting writes. Every response of a channel
+/// carries the `PinEndpoint` of the endpoint that served it, which can be copied to the
+/// extensions of the follow-up requests.
+///
+/// A pinned request fails with an `UNAVAILABLE` status if its endpoint is not part of the
+/// channel or is not ready, instead of being sent to another endpoint.
+///
+/// ```no_run
+/// # use tonic::transport::{channel::PinEndpoint, Channel, Endpoint};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// # let channel = Channel::balance_list(std::iter::empty());
+/// # let response = tonic::Response::new(());
+/// # let mut request = tonic::Request::new(());
+/// // After a first call, pin the next one to the same endpoint.
+/// if let Some(pin) = response.extensions().get::<PinEndpoint>() {
+///     request.extensions_mut().insert(pin.clone());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PinEndpoint(Uri);
+
+impl PinEndpoint {
+    /// Pins requests to the endpoint with this URI, as set with
+    /// [`Endpoint::from_shared`](super::super::Endpoint::from_shared) and friends.
+    pub fn new(uri: Uri) -> Self {
+        Self(uri)
+    }
+
+    /// The URI of the endpoint.
+    pub fn uri(&self) -> &Uri {
+        &self.0
+    }
+}
+
+/// Finds the index of the ready endpoint of `services` that `req` is pinned to, if it is
+/// pinned, or else fails with an `UNAVAILABLE` status if that endpoint is not ready.
+pub(crate) fn select_pinned<K>(
+    services: &ReadyCache<K, Connection, Request<Body>>,
+    req: &Request<Body>,
+) -> Option<Result<usize, Status>>
+where
+    K: Hash + Eq,
+{
+    let pin = req.extensions().get::<PinEndpoint>()?;
+
+    let index = (0..services.ready_len()).find(|&index| {
+        services
+            .get_ready_index(index)
+            .is_some_and(|(_, svc)| svc.uri() == pin.uri())
+    });
+
+    Some(
+        index.ok_or_else(|| {
+            Status::unavailable(format!("pinned endpoint {} is not ready", pin.uri()))
+        }),
+    )
+}
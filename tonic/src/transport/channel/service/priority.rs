@@ -0,0 +1,260 @@
+//! Failover of requests across tiers of endpoints.
+
+use super::{select_pinned, Connection};
+use crate::{body::Body, transport::channel::BoxFuture};
+use http::{Request, Response};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    load::Load,
+    ready_cache::ReadyCache,
+};
+use tower_service::Service;
+
+/// Configures a [`Channel`](super::super::Channel) that fails over between tiers of endpoints.
+///
+/// Every endpoint belongs to the tier set with [`Endpoint::priority`], where lower values are
+/// preferred, e.g. `0` for the endpoints of the primary region and `1` for those of a fallback
+/// region. Requests are balanced across the ready endpoints of the most preferred healthy tier,
+/// and only spill over to the next tier once too few endpoints of the preferred tiers are ready,
+/// for example because they are failing their [health checks](super::super::Endpoint::health_check)
+/// or were [ejected](super::super::Endpoint::outlier_detection).
+///
+/// ```no_run
+/// # use tonic::transport::{channel::Priority, Channel, Endpoint};
+/// let primary = ["http://10.0.0.1:50051", "http://10.0.0.2:50051"]
+///     .into_iter()
+///     .map(|uri| Endpoint::from_static(uri).priority(0));
+/// let fallback = ["http://10.1.0.1:50051", "http://10.1.0.2:50051"]
+///     .into_iter()
+///     .map(|uri| Endpoint::from_static(uri).priority(1));
+///
+/// let channel = Channel::priority_list(
+///     primary.chain(fallback),
+///     Priority::new().spillover_threshold(50),
+/// );
+/// ```
+///
+/// [`Endpoint::priority`]: super::super::Endpoint::priority
+#[derive(Debug, Clone, Default)]
+pub struct Priority {
+    spillover_threshold: u32,
+    flat: bool,
+}
+
+impl Priority {
+    /// Creates a new `Priority` that keeps sending requests to a tier as long as any of its
+    /// endpoints is ready.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spill requests over to the next tier once less than `percentage` percent of the endpoints
+    /// of a tier are ready.
+    ///
+    /// A tier without any ready endpoint is always skipped, and if no tier reaches the threshold
+    /// the most preferred tier with a ready endpoint is used. Defaults to 0.
+    pub fn spillover_threshold(self, percentage: u32) -> Self {
+        Priority {
+            spillover_threshold: percentage.min(100),
+            ..self
+        }
+    }
+
+    /// Balances requests across all the ready endpoints, ignoring their tiers, like the
+    /// channels created with [`Channel::balance_channel`](super::super::Channel::balance_channel).
+    pub(crate) fn flat() -> Self {
+        Priority {
+            spillover_threshold: 0,
+            flat: true,
+        }
+    }
+
+    fn is_healthy(&self, ready: usize, total: usize) -> bool {
+        ready > 0 && ready * 100 >= self.spillover_threshold as usize * total
+    }
+}
+
+/// Balances requests across the endpoints of `D`, preferring the endpoints of lower tiers.
+pub(crate) struct PriorityBalance<D>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    discover: D,
+    config: Priority,
+    services: ReadyCache<D::Key, Connection, Request<Body>>,
+    tiers: HashMap<D::Key, u32>,
+}
+
+impl<D> PriorityBalance<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<crate::BoxError>,
+{
+    pub(crate) fn new(discover: D, config: Priority) -> Self {
+        Self {
+            discover,
+            config,
+            services: ReadyCache::default(),
+            tiers: HashMap::new(),
+        }
+    }
+
+    fn update_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), crate::BoxError> {
+        while let Poll::Ready(Some(change)) = Pin::new(&mut self.discover).poll_discover(cx) {
+            match change.map_err(Into::into)? {
+                Change::Insert(key, svc) => {
+                    self.tiers.insert(key.clone(), svc.priority());
+                    self.services.push(key, svc);
+                }
+                Change::Remove(key) => {
+                    self.tiers.remove(&key);
+                    self.services.evict(&key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("dropping failed endpoint: {}", error);
+                }
+            }
+        }
+    }
+
+    /// The indices of the ready endpoints of the tier requests should be sent to.
+    fn select_tier(&self) -> Vec<usize> {
+        if self.config.flat {
+            return (0..self.services.ready_len()).collect();
+        }
+
+        // The number of endpoints and the ready endpoints of each tier.
+        let mut tiers = BTreeMap::<u32, (usize, Vec<usize>)>::new();
+        for tier in self.tiers.values() {
+            tiers.entry(*tier).or_default().0 += 1;
+        }
+        for index in 0..self.services.ready_len() {
+            if let Some((_, svc)) = self.services.get_ready_index(index) {
+                tiers.entry(svc.priority()).or_default().1.push(index);
+            }
+        }
+
+        let mut fallback = None;
+        for (total, ready) in tiers.into_values() {
+            if self.config.is_healthy(ready.len(), total) {
+                return ready;
+            }
+            if fallback.is_none() && !ready.is_empty() {
+                fallback = Some(ready);
+            }
+        }
+
+        fallback.unwrap_or_default()
+    }
+}
+
+impl<D> Service<Request<Body>> for PriorityBalance<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+
+        if self.services.ready_len() > 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        match select_pinned(&self.services, &req) {
+            Some(Ok(index)) => return self.services.call_ready_index(index, req),
+            Some(Err(status)) => return Box::pin(async move { Err(status.into()) }),
+            None => {}
+        }
+
+        let ready = self.select_tier();
+
+        // Pick the less loaded of two random endpoints of the tier.
+        let index = match ready.len() {
+            0 => 0,
+            1 => ready[0],
+            len => {
+                // Two distinct endpoints, as comparing an endpoint with itself is pointless.
+                let a = random() as usize % len;
+                let b = (a + 1 + random() as usize % (len - 1)) % len;
+                let (a, b) = (ready[a], ready[b]);
+                let load = |index| {
+                    self.services
+                        .get_ready_index(index)
+                        .map_or(f64::MAX, |(_, svc)| svc.load())
+                };
+                if load(b) < load(a) {
+                    b
+                } else {
+                    a
+                }
+            }
+        };
+
+        self.services.call_ready_index(index, req)
+    }
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+impl<D> fmt::Debug for PriorityBalance<D>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityBalance")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spills_over_below_threshold() {
+        let config = Priority::new();
+        assert!(config.is_healthy(1, 4));
+        assert!(!config.is_healthy(0, 4));
+
+        let config = Priority::new().spillover_threshold(50);
+        assert!(config.is_healthy(2, 4));
+        assert!(!config.is_healthy(1, 4));
+
+        let config = Priority::new().spillover_threshold(150);
+        assert!(config.is_healthy(4, 4));
+        assert!(!config.is_healthy(3, 4));
+    }
+}
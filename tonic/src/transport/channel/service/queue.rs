@@ -0,0 +1,97 @@
+//! Deadline for the requests waiting to be sent on a channel.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock},
+    transport::channel::BoxFuture,
+    Status,
+};
+use http::{Request, Response};
+use std::{
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Duration,
+};
+use tower::{buffer::Buffer, ServiceExt};
+use tower_service::Service;
+
+const QUEUED: u8 = 0;
+const DISPATCHED: u8 = 1;
+const EXPIRED: u8 = 2;
+
+type ChannelService =
+    Buffer<Request<Body>, BoxFuture<'static, Result<Response<Body>, crate::BoxError>>>;
+
+/// Marks a request that may only be sent before its queue timeout elapsed.
+///
+/// It is added to the extensions of the request by [`send`] and taken by the connection right
+/// before the request is sent, so whichever of the two comes first decides its fate.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Queued {
+    state: Arc<AtomicU8>,
+}
+
+impl Queued {
+    /// Returns `true` if the request may be sent, i.e. it did not expire yet.
+    pub(crate) fn dispatch(&self) -> bool {
+        self.transition(DISPATCHED)
+    }
+
+    /// Returns `true` if the request expired before it was sent.
+    fn expire(&self) -> bool {
+        self.transition(EXPIRED)
+    }
+
+    fn transition(&self, to: u8) -> bool {
+        self.state
+            .compare_exchange(QUEUED, to, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// Sends `request` on `svc`, failing with `UNAVAILABLE` if it is not handed to a connection within
+/// `timeout`, whether because the buffer is full or because the connection is being established.
+pub(crate) async fn send(
+    mut svc: ChannelService,
+    mut request: Request<Body>,
+    timeout: Duration,
+    clock: SharedClock,
+) -> Result<Response<Body>, crate::BoxError> {
+    let start = clock.now();
+    let queued = Queued::default();
+    request.extensions_mut().insert(queued.clone());
+
+    let mut response = pin!(async move {
+        svc.ready().await?;
+        svc.call(request).await
+    });
+    let mut sleep = Some(clock.sleep(timeout));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(result) = response.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+
+        if let Some(expired) = sleep.as_mut() {
+            if expired.as_mut().poll(cx).is_ready() {
+                sleep = None;
+
+                if queued.expire() {
+                    let waited = clock.now().saturating_duration_since(start);
+                    return Poll::Ready(Err(Status::unavailable(format!(
+                        "request was not sent after waiting {waited:?} in the queue"
+                    ))
+                    .into()));
+                }
+            }
+        }
+
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,95 @@
+//! Rate limiting of requests, driven by the channel's clock.
+
+use crate::time::{Clock, SharedClock, Sleep};
+use std::{
+    fmt,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_service::Service;
+
+/// Allows `num` requests to be sent every `per` period.
+///
+/// This behaves like `tower::limit::RateLimit`, but waits on a [`Clock`] instead of the Tokio
+/// timer.
+pub(crate) struct RateLimit<S> {
+    inner: S,
+    num: u64,
+    per: Duration,
+    clock: SharedClock,
+    state: State,
+}
+
+enum State {
+    Ready { until: Instant, rem: u64 },
+    Limited(Sleep),
+}
+
+impl<S> RateLimit<S> {
+    pub(crate) fn new(inner: S, num: u64, per: Duration, clock: SharedClock) -> Self {
+        let until = clock.now();
+
+        Self {
+            inner,
+            num,
+            per,
+            clock,
+            state: State::Ready { until, rem: num },
+        }
+    }
+}
+
+impl<S, Request> Service<Request> for RateLimit<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let State::Limited(sleep) = &mut self.state {
+            ready!(sleep.as_mut().poll(cx));
+            self.state = State::Ready {
+                until: self.clock.now() + self.per,
+                rem: self.num,
+            };
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let State::Ready { until, rem } = self.state else {
+            panic!("service not ready; poll_ready must be called first");
+        };
+
+        let now = self.clock.now();
+        let (until, rem) = if now >= until {
+            (now + self.per, self.num)
+        } else {
+            (until, rem)
+        };
+
+        self.state = if rem > 1 {
+            State::Ready {
+                until,
+                rem: rem - 1,
+            }
+        } else {
+            State::Limited(self.clock.sleep_until(until))
+        };
+
+        self.inner.call(request)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for RateLimit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("inner", &self.inner)
+            .field("num", &self.num)
+            .field("per", &self.per)
+            .finish_non_exhaustive()
+    }
+}
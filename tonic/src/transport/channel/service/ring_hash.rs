@@ -0,0 +1,332 @@
+//! Consistent hashing of requests onto endpoints.
+
+use super::{select_pinned, Connection};
+use crate::{
+    body::Body,
+    metadata::{MetadataKey, ValueEncoding},
+    transport::channel::BoxFuture,
+};
+use http::{header::HeaderName, Extensions, Request, Response};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::ReadyCache,
+};
+use tower_service::Service;
+
+type ExtensionKey = Arc<dyn Fn(&Extensions) -> Option<u64> + Send + Sync>;
+
+/// Configures a [`Channel`](super::super::Channel) that routes requests by consistent hashing.
+///
+/// Every endpoint is placed on a hash ring at a number of points derived from its key. A request
+/// is sent to the first ready endpoint at or after the hash of its hash key on the ring, so
+/// requests with the same hash key keep going to the same endpoint as long as it is available,
+/// and only the requests of a removed endpoint move elsewhere when the endpoints change.
+///
+/// The hash key is taken from a metadata value or a request extension. Requests without a hash
+/// key are sent to a random endpoint.
+///
+/// ```no_run
+/// # use tonic::transport::{channel::RingHash, Channel, Endpoint};
+/// # use tonic::metadata::AsciiMetadataKey;
+/// let endpoints = ["http://10.0.0.1:50051", "http://10.0.0.2:50051"]
+///     .into_iter()
+///     .map(Endpoint::from_static);
+/// let channel = Channel::ring_hash_list(
+///     endpoints,
+///     RingHash::metadata(AsciiMetadataKey::from_static("session-id")),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct RingHash {
+    key: HashKey,
+    min_ring_size: usize,
+}
+
+#[derive(Clone)]
+enum HashKey {
+    Metadata(HeaderName),
+    Extension(ExtensionKey),
+}
+
+impl RingHash {
+    /// Hashes requests by the value of the metadata entry `key`.
+    pub fn metadata<VE: ValueEncoding>(key: MetadataKey<VE>) -> Self {
+        Self::new(HashKey::Metadata(key.inner))
+    }
+
+    /// Hashes requests by their extension of type `T`.
+    pub fn extension<T>() -> Self
+    where
+        T: Hash + Send + Sync + 'static,
+    {
+        Self::new(HashKey::Extension(Arc::new(|extensions| {
+            extensions.get::<T>().map(stable_hash)
+        })))
+    }
+
+    fn new(key: HashKey) -> Self {
+        Self {
+            key,
+            min_ring_size: 1024,
+        }
+    }
+
+    /// Sets the minimum number of points on the ring, which are split evenly between the
+    /// endpoints. Larger rings spread the requests more evenly. Defaults to 1024.
+    pub fn min_ring_size(self, size: usize) -> Self {
+        RingHash {
+            min_ring_size: size.max(1),
+            ..self
+        }
+    }
+
+    fn hash_key<B>(&self, request: &Request<B>) -> Option<u64> {
+        match &self.key {
+            HashKey::Metadata(name) => request
+                .headers()
+                .get(name)
+                .map(|value| fnv(value.as_bytes())),
+            HashKey::Extension(key) => key(request.extensions()),
+        }
+    }
+}
+
+impl fmt::Debug for RingHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("RingHash");
+        match &self.key {
+            HashKey::Metadata(name) => f.field("metadata", name),
+            HashKey::Extension(_) => f.field("extension", &".."),
+        };
+        f.field("min_ring_size", &self.min_ring_size).finish()
+    }
+}
+
+/// Hashes `bytes` with FNV-1a, which unlike the std hashers is stable across processes, so all
+/// clients agree on the endpoint of a hash key. The result is run through the MurmurHash3
+/// finalizer, as FNV alone hardly changes the high bits for short inputs.
+fn fnv(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = Fnv::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = self.0;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^ (hash >> 33)
+    }
+}
+
+/// Routes requests to the endpoints of `D` by consistent hashing.
+pub(crate) struct RingHashBalance<D>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    discover: D,
+    config: RingHash,
+    services: ReadyCache<D::Key, Connection, Request<Body>>,
+    keys: Vec<D::Key>,
+    ring: Vec<(u64, D::Key)>,
+}
+
+impl<D> RingHashBalance<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<crate::BoxError>,
+{
+    pub(crate) fn new(discover: D, config: RingHash) -> Self {
+        Self {
+            discover,
+            config,
+            services: ReadyCache::default(),
+            keys: Vec::new(),
+            ring: Vec::new(),
+        }
+    }
+
+    fn update_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), crate::BoxError> {
+        let mut changed = false;
+
+        while let Poll::Ready(Some(change)) = Pin::new(&mut self.discover).poll_discover(cx) {
+            match change.map_err(Into::into)? {
+                Change::Insert(key, svc) => {
+                    if !self.keys.contains(&key) {
+                        self.keys.push(key.clone());
+                        changed = true;
+                    }
+                    self.services.push(key, svc);
+                }
+                Change::Remove(key) => {
+                    self.keys.retain(|k| *k != key);
+                    self.services.evict(&key);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            self.build_ring();
+        }
+
+        Ok(())
+    }
+
+    fn build_ring(&mut self) {
+        self.ring.clear();
+
+        if self.keys.is_empty() {
+            return;
+        }
+
+        let points = self.config.min_ring_size.div_ceil(self.keys.len());
+        for key in &self.keys {
+            for point in 0..points {
+                self.ring
+                    .push((stable_hash(&(key, point as u64)), key.clone()));
+            }
+        }
+        self.ring.sort_unstable_by_key(|(hash, _)| *hash);
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(error)) => {
+                    tracing::debug!("dropping failed endpoint: {}", error);
+                }
+            }
+        }
+    }
+}
+
+impl<D> Service<Request<Body>> for RingHashBalance<D>
+where
+    D: Discover<Service = Connection> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = crate::BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+
+        if self.services.ready_len() > 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        match select_pinned(&self.services, &req) {
+            Some(Ok(index)) => return self.services.call_ready_index(index, req),
+            Some(Err(status)) => return Box::pin(async move { Err(status.into()) }),
+            None => {}
+        }
+
+        let hash = self
+            .config
+            .hash_key(&req)
+            .unwrap_or_else(|| RandomState::new().build_hasher().finish());
+
+        // Walk the ring clockwise from the hash to the first ready endpoint.
+        let start = self.ring.partition_point(|(point, _)| *point < hash);
+        let (head, tail) = self.ring.split_at(start);
+        let key = tail
+            .iter()
+            .chain(head)
+            .map(|(_, key)| key)
+            .find(|key| self.services.get_ready(*key).is_some());
+
+        match key {
+            Some(key) => self.services.call_ready(key, req),
+            // Endpoints that became ready without being on the ring, e.g. after a discover error,
+            // are still used.
+            None => self.services.call_ready_index(0, req),
+        }
+    }
+}
+
+impl<D> fmt::Debug for RingHashBalance<D>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingHashBalance")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::AsciiMetadataKey;
+
+    #[test]
+    fn hashes_are_stable() {
+        assert_eq!(fnv(b""), 0xefd0_1f60_ba99_2926);
+        assert_eq!(fnv(b"a"), 0x82a2_a958_a9be_ce5b);
+        assert_eq!(fnv(b"foobar"), 0x2c22_1949_22d1_672b);
+    }
+
+    #[test]
+    fn reads_hash_keys() {
+        let config = RingHash::metadata(AsciiMetadataKey::from_static("session-id"));
+        let mut request = Request::new(());
+        assert_eq!(config.hash_key(&request), None);
+        request
+            .headers_mut()
+            .insert("session-id", "abc".parse().unwrap());
+        assert_eq!(config.hash_key(&request), Some(fnv(b"abc")));
+
+        #[derive(Clone, Hash)]
+        struct Session(u32);
+
+        let config = RingHash::extension::<Session>();
+        assert_eq!(config.hash_key(&request), None);
+        request.extensions_mut().insert(Session(7));
+        assert_eq!(config.hash_key(&request), Some(stable_hash(&Session(7))));
+    }
+}
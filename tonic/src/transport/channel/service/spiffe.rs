@@ -0,0 +1,140 @@
+//! Verification of the SPIFFE ID of servers.
+
+use crate::transport::service::x509::subject_alt_names;
+use std::{fmt, sync::Arc};
+use tokio_rustls::rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    CertificateError, DigitallySignedStruct, Error, RootCertStore, SignatureScheme,
+};
+
+/// Verifies that the certificate of the server is issued by a trusted root for the expected
+/// SPIFFE ID, which is carried as URI subject alternative name, instead of for the host name.
+pub(crate) struct SpiffeVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    spiffe_id: String,
+}
+
+impl SpiffeVerifier {
+    pub(crate) fn new(
+        roots: RootCertStore,
+        provider: Arc<CryptoProvider>,
+        spiffe_id: String,
+    ) -> Result<Self, crate::BoxError> {
+        let inner =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+        Ok(Self { inner, spiffe_id })
+    }
+}
+
+impl ServerCertVerifier for SpiffeVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        // The chain is verified before the name, so a name mismatch means the chain is trusted.
+        match self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            Ok(_)
+            | Err(Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) => {}
+            Err(err) => return Err(err),
+        }
+
+        let names = subject_alt_names(end_entity)
+            .ok_or(Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        if names.uris.contains(&self.spiffe_id.as_bytes()) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            tracing::debug!(
+                "server certificate is not valid for SPIFFE ID {}",
+                self.spiffe_id
+            );
+            Err(Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+impl fmt::Debug for SpiffeVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiffeVerifier")
+            .field("spiffe_id", &self.spiffe_id)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "tls-ring"))]
+mod tests {
+    use super::*;
+    use crate::transport::{service::tls::convert_certificate_to_pki_types, Certificate};
+    use tokio_rustls::rustls::crypto::ring;
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/data/tls");
+
+    fn cert(name: &str) -> CertificateDer<'static> {
+        let pem = std::fs::read(format!("{DATA}/{name}.pem")).unwrap();
+        convert_certificate_to_pki_types(&Certificate::from_pem(pem))
+            .unwrap()
+            .remove(0)
+    }
+
+    fn verify(spiffe_id: &str) -> Result<ServerCertVerified, Error> {
+        let cert = cert("spiffe");
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.clone()).unwrap();
+
+        let provider = Arc::new(ring::default_provider());
+        let verifier = SpiffeVerifier::new(roots, provider, spiffe_id.into()).unwrap();
+        verifier.verify_server_cert(
+            &cert,
+            &[],
+            &ServerName::try_from("localhost").unwrap(),
+            &[],
+            UnixTime::now(),
+        )
+    }
+
+    #[test]
+    fn verifies_spiffe_id_instead_of_host_name() {
+        verify("spiffe://example.org/ns/default/sa/server").unwrap();
+        verify("spiffe://example.org/ns/default/sa/other").unwrap_err();
+    }
+}
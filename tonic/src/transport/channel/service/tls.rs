@@ -3,27 +3,31 @@ use std::{sync::Arc, time::Duration};
 
 use hyper_util::rt::TokioIo;
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::time;
 use tokio_rustls::{
     rustls::{
+        client::Resumption,
         crypto,
         pki_types::{ServerName, TrustAnchor},
-        ClientConfig, ConfigBuilder, RootCertStore, WantsVerifier,
+        ClientConfig, ConfigBuilder, HandshakeKind, RootCertStore, WantsVerifier,
     },
     TlsConnector as RustlsConnector,
 };
 
 use super::io::BoxedIo;
+use super::spiffe::SpiffeVerifier;
+use crate::time::SharedClock;
+use crate::transport::service::identity::{IdentitySource, ReloadingIdentity};
 use crate::transport::service::tls::{
     convert_certificate_to_pki_types, convert_identity_to_pki_types, TlsError, ALPN_H2,
 };
-use crate::transport::tls::{Certificate, Identity};
+use crate::transport::tls::{Certificate, TlsInfo, TlsStats};
 
 #[derive(Clone)]
 pub(crate) struct TlsConnector {
     config: Arc<ClientConfig>,
     domain: Arc<ServerName<'static>>,
     assume_http2: bool,
+    stats: TlsStats,
     timeout: Option<Duration>,
 }
 
@@ -32,10 +36,14 @@ impl TlsConnector {
     pub(crate) fn new(
         ca_certs: Vec<Certificate>,
         trust_anchors: Vec<TrustAnchor<'static>>,
-        identity: Option<Identity>,
+        identity: Option<IdentitySource>,
         domain: &str,
+        spiffe_id: Option<String>,
         assume_http2: bool,
         use_key_log: bool,
+        session_resumption: bool,
+        stats: TlsStats,
+        crypto_provider: Option<Arc<crypto::CryptoProvider>>,
         timeout: Option<Duration>,
         #[cfg(feature = "tls-native-roots")] with_native_roots: bool,
         #[cfg(feature = "tls-webpki-roots")] with_webpki_roots: bool,
@@ -49,17 +57,22 @@ impl TlsConnector {
         }
 
         #[allow(unreachable_patterns)]
-        let builder = match crypto::CryptoProvider::get_default() {
-            Some(provider) => with_provider(provider.clone()),
-            #[cfg(feature = "tls-ring")]
-            None => with_provider(Arc::new(crypto::ring::default_provider())),
-            #[cfg(feature = "tls-aws-lc")]
-            None => with_provider(Arc::new(crypto::aws_lc_rs::default_provider())),
-            // somehow tls is enabled, but neither of the crypto features are enabled.
-            _ => ClientConfig::builder(),
-        };
+        let builder =
+            match crypto_provider.or_else(|| crypto::CryptoProvider::get_default().cloned()) {
+                Some(provider) => with_provider(provider),
+                #[cfg(feature = "tls-ring")]
+                None => with_provider(Arc::new(crypto::ring::default_provider())),
+                #[cfg(feature = "tls-aws-lc")]
+                None => with_provider(Arc::new(crypto::aws_lc_rs::default_provider())),
+                // somehow tls is enabled, but neither of the crypto features are enabled.
+                _ => ClientConfig::builder(),
+            };
 
+        // The custom roots are merged with the enabled platform and webpki roots.
         let mut roots = RootCertStore::from_iter(trust_anchors);
+        for cert in ca_certs {
+            roots.add_parsable_certificates(convert_certificate_to_pki_types(&cert)?);
+        }
 
         #[cfg(feature = "tls-native-roots")]
         if with_native_roots {
@@ -68,7 +81,8 @@ impl TlsConnector {
             if !errors.is_empty() {
                 tracing::debug!("errors occurred when loading native certs: {errors:?}");
             }
-            if certs.is_empty() {
+            // Missing platform roots are only fatal if there is nothing else to trust.
+            if certs.is_empty() && roots.is_empty() {
                 return Err(TlsError::NativeCertsNotFound.into());
             }
             roots.add_parsable_certificates(certs);
@@ -79,53 +93,83 @@ impl TlsConnector {
             roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
         }
 
-        for cert in ca_certs {
-            roots.add_parsable_certificates(convert_certificate_to_pki_types(&cert)?);
-        }
-
-        let builder = builder.with_root_certificates(roots);
+        let builder = match spiffe_id {
+            Some(spiffe_id) => {
+                let provider = builder.crypto_provider().clone();
+                let verifier = SpiffeVerifier::new(roots, provider, spiffe_id)?;
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(verifier))
+            }
+            None => builder.with_root_certificates(roots),
+        };
         let mut config = match identity {
-            Some(identity) => {
+            Some(IdentitySource::Static(identity)) => {
                 let (client_cert, client_key) = convert_identity_to_pki_types(&identity)?;
                 builder.with_client_auth_cert(client_cert, client_key)?
             }
+            Some(source) => {
+                let resolver = ReloadingIdentity::new(source, builder.crypto_provider().clone())?;
+                builder.with_client_cert_resolver(Arc::new(resolver))
+            }
             None => builder.with_no_client_auth(),
         };
 
         if use_key_log {
             config.key_log = Arc::new(tokio_rustls::rustls::KeyLogFile::new());
         }
+        if !session_resumption {
+            config.resumption = Resumption::disabled();
+        }
 
         config.alpn_protocols.push(ALPN_H2.into());
         Ok(Self {
             config: Arc::new(config),
             domain: Arc::new(ServerName::try_from(domain)?.to_owned()),
             assume_http2,
+            stats,
             timeout,
         })
     }
 
-    pub(crate) async fn connect<I>(&self, io: I) -> Result<BoxedIo, crate::BoxError>
+    /// Overrides the name sent as SNI and used to verify the server's certificate.
+    pub(crate) fn with_server_name(self, server_name: ServerName<'static>) -> Self {
+        Self {
+            domain: Arc::new(server_name),
+            ..self
+        }
+    }
+
+    pub(crate) async fn connect<I>(
+        &self,
+        io: I,
+        clock: &SharedClock,
+    ) -> Result<BoxedIo, crate::BoxError>
     where
         I: AsyncRead + AsyncWrite + Send + Unpin + 'static,
     {
         let conn_fut =
             RustlsConnector::from(self.config.clone()).connect(self.domain.as_ref().to_owned(), io);
         let io = match self.timeout {
-            Some(timeout) => time::timeout(timeout, conn_fut)
+            Some(timeout) => clock
+                .timeout(timeout, conn_fut)
                 .await
-                .map_err(|_| TlsError::HandshakeTimeout)?,
+                .map_err(|()| TlsError::HandshakeTimeout)?,
             None => conn_fut.await,
         }?;
 
         // Generally we require ALPN to be negotiated, but if the user has
         // explicitly set `assume_http2` to true, we'll allow it to be missing.
         let (_, session) = io.get_ref();
+        self.stats
+            .record(session.handshake_kind() == Some(HandshakeKind::Resumed));
+
         let alpn_protocol = session.alpn_protocol();
         if !(alpn_protocol == Some(ALPN_H2) || self.assume_http2) {
             return Err(TlsError::H2NotNegotiated.into());
         }
-        Ok(BoxedIo::new(TokioIo::new(io)))
+        let tls_info = TlsInfo::new(session);
+        Ok(BoxedIo::new(TokioIo::new(io)).with_tls_info(tls_info))
     }
 }
 
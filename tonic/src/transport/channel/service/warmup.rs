@@ -0,0 +1,127 @@
+//! Eager establishment of the connections of a channel.
+
+use super::{Connection, Executor};
+use crate::transport::Endpoint;
+use http::Uri;
+use hyper::rt;
+use std::{
+    error::Error,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::{mpsc, watch};
+use tokio_stream::Stream;
+use tower::{buffer::Buffer, discover::Change, ServiceExt};
+use tower_service::Service;
+
+#[derive(Debug)]
+struct State {
+    pending: usize,
+    error: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+/// Tracks the connections being established by [`Warmup::spawn`].
+#[derive(Debug, Clone)]
+pub(crate) struct Warmup {
+    state: watch::Receiver<State>,
+}
+
+impl Warmup {
+    /// Spawn a task per connection that connects to `endpoint`, and returns the connections to
+    /// balance over.
+    ///
+    /// A connection that fails to be established is still handed over, and connects again on
+    /// first use like a lazy one.
+    pub(crate) fn spawn<C>(
+        connector: C,
+        endpoint: Endpoint,
+        connections: usize,
+    ) -> (WarmupDiscover, Self)
+    where
+        C: Service<Uri> + Send + 'static,
+        C::Error: Into<crate::BoxError> + Send,
+        C::Future: Send,
+        C::Response: rt::Read + rt::Write + Unpin + Send + 'static,
+    {
+        let executor = endpoint.executor.clone();
+
+        // The connections share the connector.
+        let connector = connector.map_err(Into::<crate::BoxError>::into);
+        let (connector, worker) = Buffer::pair(connector, connections);
+        executor.execute(worker);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (state_tx, state_rx) = watch::channel(State {
+            pending: connections,
+            error: None,
+        });
+        let state_tx = Arc::new(state_tx);
+
+        for key in 0..connections {
+            let connector = connector.clone();
+            let endpoint = endpoint.clone();
+            let tx = tx.clone();
+            let state_tx = state_tx.clone();
+
+            executor.execute(async move {
+                let result = Connection::new(connector.clone(), endpoint.clone(), false)
+                    .ready_oneshot()
+                    .await;
+
+                let connection = match result {
+                    Ok(connection) => connection,
+                    Err(error) => {
+                        tracing::debug!("connection warm-up failed: {:?}", error);
+                        state_tx.send_modify(|state| {
+                            state.error.get_or_insert_with(|| Arc::from(error));
+                        });
+                        Connection::lazy(connector, endpoint)
+                    }
+                };
+
+                let _ = tx.send((key, connection));
+                state_tx.send_modify(|state| state.pending -= 1);
+            });
+        }
+
+        (
+            WarmupDiscover { connections: rx },
+            Warmup { state: state_rx },
+        )
+    }
+
+    /// Waits until all connections are established, and returns the first error if any of them
+    /// failed.
+    pub(crate) async fn wait(&self) -> Result<(), crate::BoxError> {
+        let mut state = self.state.clone();
+        let state = state
+            .wait_for(|state| state.pending == 0)
+            .await
+            .map_err(|_| "connection warm-up was cancelled")?;
+
+        match &state.error {
+            Some(error) => Err(error.clone().into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The connections established by [`Warmup::spawn`], as they become ready.
+pub(crate) struct WarmupDiscover {
+    connections: mpsc::UnboundedReceiver<(usize, Connection)>,
+}
+
+impl Stream for WarmupDiscover {
+    type Item = Result<Change<usize, Connection>, crate::BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.connections.poll_recv(cx) {
+            // The set of connections never changes once they are all established.
+            Poll::Pending | Poll::Ready(None) => Poll::Pending,
+            Poll::Ready(Some((key, connection))) => {
+                Poll::Ready(Some(Ok(Change::Insert(key, connection))))
+            }
+        }
+    }
+}
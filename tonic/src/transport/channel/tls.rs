@@ -1,11 +1,13 @@
 use super::service::TlsConnector;
+use crate::transport::service::identity::IdentitySource;
 use crate::transport::{
-    tls::{Certificate, Identity},
+    tls::{Certificate, Identity, TlsStats},
     Error,
 };
 use http::Uri;
-use std::time::Duration;
-use tokio_rustls::rustls::pki_types::TrustAnchor;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::watch;
+use tokio_rustls::rustls::{crypto::CryptoProvider, pki_types::TrustAnchor};
 
 /// Configures TLS settings for endpoints.
 #[derive(Debug, Clone, Default)]
@@ -13,13 +15,17 @@ pub struct ClientTlsConfig {
     domain: Option<String>,
     certs: Vec<Certificate>,
     trust_anchors: Vec<TrustAnchor<'static>>,
-    identity: Option<Identity>,
+    identity: Option<IdentitySource>,
+    spiffe_id: Option<String>,
     assume_http2: bool,
     #[cfg(feature = "tls-native-roots")]
     with_native_roots: bool,
     #[cfg(feature = "tls-webpki-roots")]
     with_webpki_roots: bool,
     use_key_log: bool,
+    disable_session_resumption: bool,
+    stats: TlsStats,
+    crypto_provider: Option<Arc<CryptoProvider>>,
     timeout: Option<Duration>,
 }
 
@@ -38,6 +44,9 @@ impl ClientTlsConfig {
     }
 
     /// Adds the CA Certificate against which to verify the server's TLS certificate.
+    ///
+    /// It is trusted in addition to the roots enabled with
+    /// [`with_enabled_roots`](Self::with_enabled_roots).
     pub fn ca_certificate(self, ca_certificate: Certificate) -> Self {
         let mut certs = self.certs;
         certs.push(ca_certificate);
@@ -73,7 +82,48 @@ impl ClientTlsConfig {
     /// Sets the client identity to present to the server.
     pub fn identity(self, identity: Identity) -> Self {
         ClientTlsConfig {
-            identity: Some(identity),
+            identity: Some(IdentitySource::Static(identity)),
+            ..self
+        }
+    }
+
+    /// Presents the latest identity received on `identity` to the server.
+    ///
+    /// Every new connection uses the identity that is current when its TLS handshake starts, so
+    /// short-lived certificates can be rotated without recreating the [`Channel`](super::Channel).
+    /// Established connections keep the identity they were created with.
+    pub fn identity_watch(self, identity: watch::Receiver<Identity>) -> Self {
+        ClientTlsConfig {
+            identity: Some(IdentitySource::Watch(identity)),
+            ..self
+        }
+    }
+
+    /// Presents the identity read from the PEM encoded certificate and key files to the server.
+    ///
+    /// The files are read again for a new connection whenever their modification time changed,
+    /// so certificates renewed on disk are used without recreating the
+    /// [`Channel`](super::Channel). If the renewed files cannot be parsed, the previous identity
+    /// keeps being used. Creating the endpoint fails if the files cannot be read initially.
+    pub fn identity_files(self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        ClientTlsConfig {
+            identity: Some(IdentitySource::Files {
+                cert: cert.into(),
+                key: key.into(),
+            }),
+            ..self
+        }
+    }
+
+    /// Verifies the server's TLS certificate against the expected SPIFFE ID instead of the domain
+    /// name.
+    ///
+    /// The certificate must still be issued by one of the trusted roots, and carry `spiffe_id`,
+    /// e.g. `spiffe://example.org/ns/default/sa/server`, as URI subject alternative name. The
+    /// domain name is still sent as SNI.
+    pub fn spiffe_id(self, spiffe_id: impl Into<String>) -> Self {
+        ClientTlsConfig {
+            spiffe_id: Some(spiffe_id.into()),
             ..self
         }
     }
@@ -88,6 +138,11 @@ impl ClientTlsConfig {
     }
 
     /// Use key log as specified by the `SSLKEYLOGFILE` environment variable.
+    ///
+    /// The secrets of every TLS session are appended to the file named by the variable, in the NSS
+    /// key log format that Wireshark reads to decrypt captured traffic. Nothing is logged if the
+    /// variable is not set. This is meant for debugging only, as anyone who can read the file can
+    /// decrypt the traffic.
     pub fn use_key_log(self) -> Self {
         ClientTlsConfig {
             use_key_log: true,
@@ -95,7 +150,34 @@ impl ClientTlsConfig {
         }
     }
 
+    /// Sets whether sessions are resumed when reconnecting to a server.
+    ///
+    /// Resumed handshakes skip the certificate exchange and are considerably faster. The sessions
+    /// are cached in memory by the [`Endpoint`](super::Endpoint) this config is set on, and are
+    /// shared by all channels connected with it and its clones. See [`stats`](Self::stats) for
+    /// how many handshakes were resumed.
+    ///
+    /// # Default
+    /// By default, this option is set to `true`.
+    pub fn session_resumption(self, enabled: bool) -> Self {
+        ClientTlsConfig {
+            disable_session_resumption: !enabled,
+            ..self
+        }
+    }
+
+    /// Returns the handshake statistics of the connections created with this config and its
+    /// clones.
+    pub fn stats(&self) -> TlsStats {
+        self.stats.clone()
+    }
+
     /// Enables the platform's trusted certs.
+    ///
+    /// They are trusted in addition to the certificates added with
+    /// [`ca_certificate`](Self::ca_certificate) and [`trust_anchor`](Self::trust_anchor), so a
+    /// private CA can be trusted alongside the public ones. Setting the config on an endpoint fails
+    /// if the platform has no trusted certs and no other certificates were added.
     #[cfg(feature = "tls-native-roots")]
     pub fn with_native_roots(self) -> Self {
         ClientTlsConfig {
@@ -125,6 +207,19 @@ impl ClientTlsConfig {
         config
     }
 
+    /// Sets the rustls [`CryptoProvider`] used for the connections, e.g. a FIPS validated or a
+    /// post-quantum key exchange provider.
+    ///
+    /// # Default
+    /// By default, the process wide default provider is used if one is installed, and otherwise
+    /// the provider selected with the `tls-ring` or `tls-aws-lc` feature.
+    pub fn crypto_provider(self, provider: Arc<CryptoProvider>) -> Self {
+        ClientTlsConfig {
+            crypto_provider: Some(provider),
+            ..self
+        }
+    }
+
     /// Sets the timeout for the TLS handshake.
     pub fn timeout(self, timeout: Duration) -> Self {
         ClientTlsConfig {
@@ -143,8 +238,12 @@ impl ClientTlsConfig {
             self.trust_anchors,
             self.identity,
             domain,
+            self.spiffe_id,
             self.assume_http2,
             self.use_key_log,
+            !self.disable_session_resumption,
+            self.stats,
+            self.crypto_provider,
             self.timeout,
             #[cfg(feature = "tls-native-roots")]
             self.with_native_roots,
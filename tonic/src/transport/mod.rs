@@ -94,7 +94,7 @@ pub mod channel;
 pub mod server;
 
 mod error;
-mod service;
+pub(crate) mod service;
 #[cfg(feature = "_tls-any")]
 mod tls;
 
@@ -117,4 +117,4 @@ pub use self::channel::ClientTlsConfig;
 #[cfg(all(feature = "server", feature = "_tls-any"))]
 pub use self::server::ServerTlsConfig;
 #[cfg(feature = "_tls-any")]
-pub use self::tls::Identity;
+pub use self::tls::{Identity, TlsInfo, TlsStats};
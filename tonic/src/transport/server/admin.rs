@@ -0,0 +1,78 @@
+#[cfg(feature = "health")]
+use crate::health::HealthReporter;
+#[cfg(feature = "reflection")]
+use crate::reflection::ReflectionService;
+
+/// The admin services mounted on a [`Router`](super::Router) in one call with
+/// [`add_admin_services`](super::Router::add_admin_services).
+///
+/// The services included depend on the enabled features:
+///
+/// - `health`: the `grpc.health.v1.Health` service, reporting the server as `Serving` unless
+///   another [`HealthReporter`] is set.
+/// - `reflection`: the `grpc.reflection.v1.ServerReflection` service and its `v1alpha` twin,
+///   once a [`ReflectionService`] describing the files of the server is set.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let admin = AdminServices::new().reflection(
+///     ReflectionService::builder()
+///         .register_encoded_file_descriptor_set(helloworld::FILE_DESCRIPTOR_SET)
+///         .build()?,
+/// );
+/// admin.health_reporter().set_serving::<GreeterServer<MyGreeter>>();
+///
+/// Server::builder()
+///     .add_service(GreeterServer::new(MyGreeter::default()))
+///     .add_admin_services(&admin)
+///     .serve(addr)
+///     .await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AdminServices {
+    #[cfg(feature = "health")]
+    health: HealthReporter,
+    #[cfg(feature = "reflection")]
+    reflection: Option<ReflectionService>,
+}
+
+impl AdminServices {
+    /// Create new `AdminServices` with their defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the statuses set with `reporter` instead of a new one.
+    #[cfg(feature = "health")]
+    #[must_use]
+    pub fn with_health_reporter(mut self, reporter: HealthReporter) -> Self {
+        self.health = reporter;
+        self
+    }
+
+    /// Get the reporter of the statuses served by the health service.
+    #[cfg(feature = "health")]
+    pub fn health_reporter(&self) -> &HealthReporter {
+        &self.health
+    }
+
+    /// Serve `reflection`, describing the files of the server.
+    #[cfg(feature = "reflection")]
+    #[must_use]
+    pub fn reflection(mut self, reflection: ReflectionService) -> Self {
+        self.reflection = Some(reflection);
+        self
+    }
+
+    pub(crate) fn add_to<L>(&self, router: super::Router<L>) -> super::Router<L> {
+        #[cfg(feature = "health")]
+        let router = router.add_health_service(&self.health);
+        #[cfg(feature = "reflection")]
+        let router = match &self.reflection {
+            Some(reflection) => router.add_reflection_service(reflection.clone()),
+            None => router,
+        };
+        router
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Notify;
+
+/// A token that is cancelled once the client of an RPC goes away.
+///
+/// The server cancels the token of an RPC when its client disconnects or resets the stream
+/// before the response was sent, so handlers can stop the work done for it promptly, e.g. in
+/// tasks spawned for a streaming response, instead of finding out once sending fails.
+///
+/// The token of an RPC is available with
+/// [`Request::cancellation_token`](crate::Request::cancellation_token).
+///
+/// # Example
+///
+/// ```
+/// # use tonic::transport::server::CancellationToken;
+/// # async fn expensive() {}
+/// async fn work(token: CancellationToken) {
+///     tokio::select! {
+///         _ = token.cancelled() => {}
+///         _ = expensive() => {}
+///     }
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the RPC was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Waits until the RPC is cancelled.
+    ///
+    /// This never completes for RPCs that complete normally.
+    pub async fn cancelled(&self) {
+        loop {
+            // Registered before checking the flag so a concurrent `cancel` is not missed.
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.notify.notify_waiters();
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Cancels its token when dropped, unless it was disarmed first.
+#[derive(Debug)]
+pub(crate) struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    pub(crate) fn new(token: CancellationToken) -> Self {
+        Self(Some(token))
+    }
+
+    pub(crate) fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
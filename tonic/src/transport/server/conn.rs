@@ -1,6 +1,9 @@
+use super::ConnectionConfig;
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
 
+#[cfg(feature = "_tls-any")]
+use crate::transport::TlsInfo;
 #[cfg(feature = "_tls-any")]
 use std::sync::Arc;
 #[cfg(feature = "_tls-any")]
@@ -55,6 +58,14 @@ pub trait Connected {
 
     /// Create type holding information about the connection.
     fn connect_info(&self) -> Self::ConnectInfo;
+
+    /// Returns the settings that override those of the server for this connection.
+    ///
+    /// By default, nothing is overridden. See [`IncomingConnection`](super::IncomingConnection)
+    /// to set them for any IO resource.
+    fn connection_config(&self) -> ConnectionConfig {
+        ConnectionConfig::default()
+    }
 }
 
 /// Connection info for standard TCP streams.
@@ -113,17 +124,21 @@ where
         let (inner, session) = self.get_ref();
         let inner = inner.connect_info();
 
-        let certs = session
-            .peer_certificates()
-            .map(|certs| certs.to_owned().into());
+        TlsConnectInfo {
+            inner,
+            info: TlsInfo::new(session).with_server_name(session.server_name()),
+        }
+    }
 
-        TlsConnectInfo { inner, certs }
+    fn connection_config(&self) -> ConnectionConfig {
+        self.get_ref().0.connection_config()
     }
 }
 
 /// Connection info for TLS streams.
 ///
-/// This type will be accessible through [request extensions][ext] if you're using a TLS connector.
+/// This type will be accessible through [request extensions][ext] if you're using a TLS connector,
+/// along with the [`TlsInfo`] of the connection.
 ///
 /// See [`Connected`] for more details.
 ///
@@ -132,7 +147,7 @@ where
 #[derive(Debug, Clone)]
 pub struct TlsConnectInfo<T> {
     inner: T,
-    certs: Option<Arc<Vec<CertificateDer<'static>>>>,
+    info: TlsInfo,
 }
 
 #[cfg(feature = "_tls-any")]
@@ -149,6 +164,11 @@ impl<T> TlsConnectInfo<T> {
 
     /// Return the set of connected peer TLS certificates.
     pub fn peer_certs(&self) -> Option<Arc<Vec<CertificateDer<'static>>>> {
-        self.certs.clone()
+        self.info.peer_certs()
+    }
+
+    /// Get the parameters negotiated by the TLS handshake.
+    pub fn tls_info(&self) -> &TlsInfo {
+        &self.info
     }
 }
@@ -0,0 +1,187 @@
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::Connected;
+#[cfg(feature = "_tls-any")]
+use super::{service::TlsAcceptor, ServerTlsConfig};
+#[cfg(feature = "_tls-any")]
+use crate::transport::Error;
+
+/// Settings of a single connection that override those of the [`Server`](super::Server).
+///
+/// The settings of a connection are returned by [`Connected::connection_config`], so custom
+/// acceptors can set them for each connection they yield, e.g. with [`IncomingConnection`].
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionConfig {
+    #[cfg(feature = "_tls-any")]
+    pub(crate) tls: Option<ConnectionTls>,
+    pub(crate) max_connection_age: Option<Duration>,
+    pub(crate) max_concurrent_streams: Option<u32>,
+}
+
+#[cfg(feature = "_tls-any")]
+#[derive(Debug, Clone)]
+pub(crate) enum ConnectionTls {
+    Plaintext,
+    Acceptor(TlsAcceptor),
+}
+
+impl ConnectionConfig {
+    /// Creates a new `ConnectionConfig` that overrides nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts the connection with `tls_config` instead of the TLS config of the server.
+    ///
+    /// The config is checked right away, so it is cheaper to build the `ConnectionConfig` once
+    /// and clone it for every connection.
+    #[cfg(feature = "_tls-any")]
+    pub fn tls_config(self, tls_config: ServerTlsConfig) -> Result<Self, Error> {
+        Ok(ConnectionConfig {
+            tls: Some(ConnectionTls::Acceptor(
+                tls_config.tls_acceptor().map_err(Error::from_source)?,
+            )),
+            ..self
+        })
+    }
+
+    /// Serves the connection in plaintext even if the server is configured with TLS, e.g.
+    /// because the acceptor already terminated TLS.
+    #[cfg(feature = "_tls-any")]
+    pub fn plaintext(self) -> Self {
+        ConnectionConfig {
+            tls: Some(ConnectionTls::Plaintext),
+            ..self
+        }
+    }
+
+    /// Overrides [`Server::max_connection_age`](super::Server::max_connection_age).
+    pub fn max_connection_age(self, max_connection_age: Duration) -> Self {
+        ConnectionConfig {
+            max_connection_age: Some(max_connection_age),
+            ..self
+        }
+    }
+
+    /// Overrides [`Server::max_concurrent_streams`](super::Server::max_concurrent_streams).
+    pub fn max_concurrent_streams(self, max: u32) -> Self {
+        ConnectionConfig {
+            max_concurrent_streams: Some(max),
+            ..self
+        }
+    }
+}
+
+/// An IO resource yielded by a custom acceptor along with its own [`ConnectionConfig`].
+///
+/// A stream of `IncomingConnection`s can be served with
+/// [`Router::serve_with_incoming`](super::Router::serve_with_incoming) like any other
+/// stream of IO resources, so the connections still get every feature of the server. The
+/// connect info of the connection is the one of the wrapped IO resource.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use tokio_stream::StreamExt;
+/// # use tonic::transport::server::{ConnectionConfig, IncomingConnection, TcpIncoming};
+/// # async fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// let incoming = TcpIncoming::bind("127.0.0.1:0".parse()?)?.map(|io| {
+///     io.map(|io| {
+///         // e.g. chosen by port, by peer, or by steering rules of the acceptor.
+///         let config = ConnectionConfig::new().max_connection_age(Duration::from_secs(60));
+///         IncomingConnection::new(io, config)
+///     })
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[pin_project]
+#[derive(Debug)]
+pub struct IncomingConnection<IO> {
+    #[pin]
+    io: IO,
+    config: ConnectionConfig,
+}
+
+impl<IO> IncomingConnection<IO> {
+    /// Wraps `io`, to be served with `config`.
+    pub fn new(io: IO, config: ConnectionConfig) -> Self {
+        Self { io, config }
+    }
+
+    /// Get a reference to the wrapped IO resource.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Get a mutable reference to the wrapped IO resource.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Consumes `self`, returning the wrapped IO resource.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO: Connected> Connected for IncomingConnection<IO> {
+    type ConnectInfo = IO::ConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.io.connect_info()
+    }
+
+    fn connection_config(&self) -> ConnectionConfig {
+        self.config.clone()
+    }
+}
+
+impl<IO: AsyncRead> AsyncRead for IncomingConnection<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite> AsyncWrite for IncomingConnection<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
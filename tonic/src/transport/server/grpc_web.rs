@@ -0,0 +1,129 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
+
+use crate::{body::Body, Status};
+
+type AllowOriginFn = Arc<dyn Fn(&HeaderValue) -> bool + Send + Sync + 'static>;
+
+/// Configures the gRPC-Web support of servers.
+///
+/// See [`Server::grpc_web`](super::Server::grpc_web) for more details.
+#[derive(Clone, Default)]
+pub struct GrpcWebConfig {
+    allowed_origins: Vec<HeaderValue>,
+    allow_origin_fn: Option<AllowOriginFn>,
+    exposed_headers: Vec<HeaderName>,
+    max_age: Option<Duration>,
+}
+
+impl fmt::Debug for GrpcWebConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcWebConfig")
+            .field("allowed_origins", &self.allowed_origins)
+            .field("exposed_headers", &self.exposed_headers)
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GrpcWebConfig {
+    /// Creates a new `GrpcWebConfig` that allows requests from any origin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows requests from `origin`, e.g. `https://example.com`.
+    ///
+    /// Once an origin is allowed, requests from origins that are not are rejected with
+    /// `403 Forbidden`.
+    pub fn allow_origin(mut self, origin: HeaderValue) -> Self {
+        self.allowed_origins.push(origin);
+        self
+    }
+
+    /// Allows requests from the origins for which `f` returns `true`, in addition to the ones
+    /// allowed with [`allow_origin`](Self::allow_origin).
+    pub fn allow_origin_fn<F>(self, f: F) -> Self
+    where
+        F: Fn(&HeaderValue) -> bool + Send + Sync + 'static,
+    {
+        GrpcWebConfig {
+            allow_origin_fn: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Exposes the response header `name` to browsers, in addition to the `grpc-status`,
+    /// `grpc-message` and `grpc-status-details-bin` headers.
+    pub fn expose_header(mut self, name: HeaderName) -> Self {
+        self.exposed_headers.push(name);
+        self
+    }
+
+    /// Sets how long browsers may cache the result of preflight requests.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        GrpcWebConfig {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    pub(crate) fn allows(&self, origin: &HeaderValue) -> bool {
+        if self.allowed_origins.is_empty() && self.allow_origin_fn.is_none() {
+            return true;
+        }
+
+        self.allowed_origins.contains(origin)
+            || self.allow_origin_fn.as_ref().is_some_and(|f| f(origin))
+    }
+
+    /// Answers a CORS preflight request.
+    pub(crate) fn preflight(&self, headers: &HeaderMap) -> Response<Body> {
+        let mut response = Response::new(Body::empty());
+
+        let origin = match headers.get(header::ORIGIN) {
+            Some(origin) if self.allows(origin) => origin.clone(),
+            _ => {
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                return response;
+            }
+        };
+
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        let response_headers = response.headers_mut();
+        response_headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("POST"),
+        );
+        if let Some(requested) = headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            response_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            response_headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+        self.cors_headers(origin, response_headers);
+
+        response
+    }
+
+    /// Adds the CORS headers of a response to a request from `origin`.
+    pub(crate) fn cors_headers(&self, origin: HeaderValue, headers: &mut HeaderMap) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(header::VARY, HeaderValue::from_static("origin"));
+
+        let exposed = [
+            Status::GRPC_STATUS,
+            Status::GRPC_MESSAGE,
+            Status::GRPC_STATUS_DETAILS,
+        ]
+        .iter()
+        .chain(&self.exposed_headers)
+        .map(HeaderName::as_str)
+        .collect::<Vec<_>>()
+        .join(",");
+        if let Ok(exposed) = HeaderValue::from_str(&exposed) {
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 #[cfg(feature = "_tls-any")]
-use std::future::Future;
+use std::{any::Any, future::Future};
 use std::{
     io,
     ops::ControlFlow,
@@ -15,12 +15,9 @@ use tokio_stream::Stream;
 #[cfg(feature = "_tls-any")]
 use tokio_stream::StreamExt as _;
 
-use super::service::ServerIo;
 #[cfg(feature = "_tls-any")]
-use super::service::TlsAcceptor;
-
-#[cfg(feature = "_tls-any")]
-struct State<IO>(TlsAcceptor, JoinSet<Result<ServerIo<IO>, crate::BoxError>>);
+use super::{connection::ConnectionTls, service::TlsAcceptor, TcpConnectInfo};
+use super::{service::ServerIo, Connected};
 
 #[pin_project]
 pub(crate) struct ServerIoStream<S, IO, IE>
@@ -30,7 +27,9 @@ where
     #[pin]
     inner: S,
     #[cfg(feature = "_tls-any")]
-    state: Option<State<IO>>,
+    tls: Option<TlsAcceptor>,
+    #[cfg(feature = "_tls-any")]
+    tasks: JoinSet<Result<ServerIo<IO>, crate::BoxError>>,
 }
 
 impl<S, IO, IE> ServerIoStream<S, IO, IE>
@@ -41,10 +40,13 @@ where
         Self {
             inner: incoming,
             #[cfg(feature = "_tls-any")]
-            state: tls.map(|tls| State(tls, JoinSet::new())),
+            tls,
+            #[cfg(feature = "_tls-any")]
+            tasks: JoinSet::new(),
         }
     }
 
+    #[cfg(not(feature = "_tls-any"))]
     fn poll_next_without_tls(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -69,7 +71,7 @@ where
 impl<S, IO, IE> Stream for ServerIoStream<S, IO, IE>
 where
     S: Stream<Item = Result<IO, IE>>,
-    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
     IE: Into<crate::BoxError>,
 {
     type Item = Result<ServerIo<IO>, crate::BoxError>;
@@ -82,18 +84,27 @@ where
     #[cfg(feature = "_tls-any")]
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut projected = self.as_mut().project();
-
-        let Some(State(tls, tasks)) = projected.state else {
-            return self.poll_next_without_tls(cx);
-        };
+        let tasks = projected.tasks;
 
         let select_output = ready!(pin!(select(&mut projected.inner, tasks)).poll(cx));
 
         match select_output {
             SelectOutput::Incoming(stream) => {
-                let tls = tls.clone();
+                let tls = match stream.connection_config().tls {
+                    None => projected.tls.clone(),
+                    Some(ConnectionTls::Plaintext) => None,
+                    Some(ConnectionTls::Acceptor(tls)) => Some(tls),
+                };
+                let Some(tls) = tls else {
+                    return Poll::Ready(Some(Ok(ServerIo::new_io(stream))));
+                };
+
+                let connect_info = stream.connect_info();
+                let remote_addr = (&connect_info as &dyn Any)
+                    .downcast_ref::<TcpConnectInfo>()
+                    .and_then(TcpConnectInfo::remote_addr);
                 tasks.spawn(async move {
-                    let io = tls.accept(stream).await?;
+                    let io = tls.accept(stream, remote_addr).await?;
                     Ok(ServerIo::new_tls_io(io))
                 });
                 cx.waker().wake_by_ref();
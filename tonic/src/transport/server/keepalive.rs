@@ -0,0 +1,430 @@
+//! Enforcement of the keepalive policy of the server on the pings of clients.
+//!
+//! hyper answers the HTTP/2 pings of clients on its own, so the frames read from and written to
+//! the connection are inspected to count the pings and the active streams, in the same way as
+//! the keepalive enforcement of grpc-go.
+
+use crate::time::{Clock, SharedClock};
+use pin_project::pin_project;
+use std::{
+    collections::HashSet,
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::debug;
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_LEN: usize = 9;
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+/// The number of pings received too early that are tolerated before closing the connection.
+const MAX_PING_STRIKES: u8 = 2;
+
+/// The minimum interval between pings on connections without active streams, unless the policy
+/// permits pings without streams.
+const IDLE_MIN_PING_INTERVAL: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How often clients may send keepalive pings.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KeepalivePolicy {
+    pub(crate) min_ping_interval: Duration,
+    pub(crate) permit_without_streams: bool,
+}
+
+/// An IO resource that closes the connection with a `GOAWAY` frame carrying
+/// `ENHANCE_YOUR_CALM` and `too_many_pings` when the client pings more often than the
+/// [`KeepalivePolicy`] allows.
+#[pin_project]
+pub(crate) struct EnforceKeepalive<IO> {
+    #[pin]
+    io: IO,
+    // `None` when there is no policy, or when the connection does not use HTTP/2.
+    state: Option<Box<State>>,
+}
+
+impl<IO> EnforceKeepalive<IO> {
+    pub(crate) fn new(io: IO, policy: Option<KeepalivePolicy>, clock: SharedClock) -> Self {
+        Self {
+            io,
+            state: policy.map(|policy| {
+                Box::new(State {
+                    policy,
+                    clock,
+                    read: Frames::default(),
+                    write: Frames::default(),
+                    preface: 0,
+                    streams: HashSet::new(),
+                    last_stream_id: 0,
+                    last_ping: None,
+                    strikes: 0,
+                    reset_strikes: false,
+                    goaway: None,
+                    read_waker: None,
+                })
+            }),
+        }
+    }
+}
+
+struct State {
+    policy: KeepalivePolicy,
+    clock: SharedClock,
+    read: Frames,
+    write: Frames,
+    /// The number of bytes of the connection preface read so far.
+    preface: usize,
+    streams: HashSet<u32>,
+    last_stream_id: u32,
+    last_ping: Option<Instant>,
+    strikes: u8,
+    reset_strikes: bool,
+    goaway: Option<GoAway>,
+    read_waker: Option<Waker>,
+}
+
+struct GoAway {
+    frame: Vec<u8>,
+    written: usize,
+    done: bool,
+}
+
+/// Whether the bytes read so far are those of an HTTP/2 connection.
+enum Protocol {
+    Http2,
+    Other,
+}
+
+impl State {
+    fn on_read(&mut self, mut buf: &[u8]) -> Protocol {
+        if self.preface < PREFACE.len() {
+            let len = buf.len().min(PREFACE.len() - self.preface);
+            if buf[..len] != PREFACE[self.preface..self.preface + len] {
+                return Protocol::Other;
+            }
+            self.preface += len;
+            buf = &buf[len..];
+        }
+
+        let mut headers = Vec::new();
+        self.read.feed(buf, |header| headers.push(header));
+        for header in headers {
+            match header.kind {
+                HEADERS if header.stream_id % 2 == 1 => {
+                    self.streams.insert(header.stream_id);
+                    self.last_stream_id = self.last_stream_id.max(header.stream_id);
+                }
+                RST_STREAM => {
+                    self.streams.remove(&header.stream_id);
+                }
+                PING if header.flags & ACK == 0 => self.on_ping(),
+                _ => {}
+            }
+        }
+
+        Protocol::Http2
+    }
+
+    fn on_write(&mut self, buf: &[u8]) {
+        let mut headers = Vec::new();
+        self.write.feed(buf, |header| headers.push(header));
+        for header in headers {
+            match header.kind {
+                DATA | HEADERS => {
+                    self.reset_strikes = true;
+                    if header.flags & END_STREAM != 0 {
+                        self.streams.remove(&header.stream_id);
+                    }
+                }
+                RST_STREAM => {
+                    self.streams.remove(&header.stream_id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_ping(&mut self) {
+        let now = self.clock.now();
+        let last_ping = self.last_ping.replace(now);
+
+        // Sending data resets the strikes, as pings are then expected to keep the connection
+        // alive.
+        if std::mem::take(&mut self.reset_strikes) {
+            self.strikes = 0;
+            return;
+        }
+
+        let min_interval = if self.streams.is_empty() && !self.policy.permit_without_streams {
+            self.policy.min_ping_interval.max(IDLE_MIN_PING_INTERVAL)
+        } else {
+            self.policy.min_ping_interval
+        };
+        let too_early =
+            last_ping.is_some_and(|last| now.saturating_duration_since(last) < min_interval);
+        if !too_early {
+            return;
+        }
+
+        self.strikes += 1;
+        if self.strikes > MAX_PING_STRIKES && self.goaway.is_none() {
+            debug!("client sent too many pings, closing connection");
+            self.goaway = Some(GoAway {
+                frame: goaway_frame(self.last_stream_id, ENHANCE_YOUR_CALM, b"too_many_pings"),
+                written: 0,
+                done: false,
+            });
+        }
+    }
+
+    /// Writes the pending `GOAWAY` frame, then shuts the connection down.
+    fn poll_goaway<IO: AsyncWrite>(
+        &mut self,
+        mut io: Pin<&mut IO>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let Some(goaway) = &mut self.goaway else {
+            return Poll::Ready(Ok(()));
+        };
+
+        while !goaway.done {
+            if goaway.written < goaway.frame.len() {
+                let n = ready!(io.as_mut().poll_write(cx, &goaway.frame[goaway.written..]))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                goaway.written += n;
+            } else {
+                ready!(io.as_mut().poll_flush(cx))?;
+                ready!(io.as_mut().poll_shutdown(cx))?;
+                goaway.done = true;
+                if let Some(waker) = self.read_waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn goaway_frame(last_stream_id: u32, error_code: u32, debug_data: &[u8]) -> Vec<u8> {
+    let len = 8 + debug_data.len();
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + len);
+    frame.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+    frame.extend_from_slice(&[GOAWAY, 0]);
+    frame.extend_from_slice(&0u32.to_be_bytes());
+    frame.extend_from_slice(&last_stream_id.to_be_bytes());
+    frame.extend_from_slice(&error_code.to_be_bytes());
+    frame.extend_from_slice(debug_data);
+    frame
+}
+
+struct FrameHeader {
+    kind: u8,
+    flags: u8,
+    stream_id: u32,
+}
+
+/// Splits a sequence of HTTP/2 frames into their headers, skipping their payloads.
+#[derive(Default)]
+struct Frames {
+    header: [u8; FRAME_HEADER_LEN],
+    header_len: usize,
+    remaining: usize,
+}
+
+impl Frames {
+    fn feed(&mut self, mut buf: &[u8], mut on_header: impl FnMut(FrameHeader)) {
+        while !buf.is_empty() {
+            if self.remaining > 0 {
+                let skipped = self.remaining.min(buf.len());
+                self.remaining -= skipped;
+                buf = &buf[skipped..];
+                continue;
+            }
+
+            let len = buf.len().min(FRAME_HEADER_LEN - self.header_len);
+            self.header[self.header_len..self.header_len + len].copy_from_slice(&buf[..len]);
+            self.header_len += len;
+            buf = &buf[len..];
+
+            if self.header_len == FRAME_HEADER_LEN {
+                let header = &self.header;
+                self.remaining = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+                self.header_len = 0;
+                on_header(FrameHeader {
+                    kind: header[3],
+                    flags: header[4],
+                    stream_id: u32::from_be_bytes([header[5], header[6], header[7], header[8]])
+                        & 0x7fff_ffff,
+                });
+            }
+        }
+    }
+
+    /// The number of bytes of `buf` that complete the frame being written, if any.
+    fn until_boundary(&self, buf: &[u8]) -> usize {
+        if self.header_len == 0 {
+            return self.remaining.min(buf.len());
+        }
+
+        let needed = FRAME_HEADER_LEN - self.header_len;
+        if buf.len() < needed {
+            return buf.len();
+        }
+        let mut header = self.header;
+        header[self.header_len..].copy_from_slice(&buf[..needed]);
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        (needed + len).min(buf.len())
+    }
+
+    fn at_boundary(&self) -> bool {
+        self.header_len == 0 && self.remaining == 0
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite> AsyncRead for EnforceKeepalive<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        let Some(state) = this.state.as_mut() else {
+            return this.io.poll_read(cx, buf);
+        };
+
+        if state.goaway.is_some() {
+            if !state.write.at_boundary() {
+                // The GOAWAY frame is written once the frame being written is complete.
+                state.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            // Reading nothing more closes the connection once the GOAWAY frame is sent.
+            return state.poll_goaway(this.io, cx);
+        }
+
+        let filled = buf.filled().len();
+        ready!(this.io.as_mut().poll_read(cx, buf))?;
+        if let Protocol::Other = state.on_read(&buf.filled()[filled..]) {
+            *this.state = None;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncWrite> AsyncWrite for EnforceKeepalive<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let Some(state) = this.state.as_mut() else {
+            return this.io.poll_write(cx, buf);
+        };
+
+        let len = match state.goaway {
+            Some(_) => state.write.until_boundary(buf),
+            None => buf.len(),
+        };
+        if len == 0 {
+            ready!(state.poll_goaway(this.io, cx))?;
+            return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+        }
+
+        let n = ready!(this.io.poll_write(cx, &buf[..len]))?;
+        state.on_write(&buf[..n]);
+        if state.goaway.is_some() && state.write.at_boundary() {
+            if let Some(waker) = state.read_waker.take() {
+                waker.wake();
+            }
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if self.state.is_some() {
+            let buf = bufs
+                .iter()
+                .find(|buf| !buf.is_empty())
+                .map_or(&[][..], |buf| &**buf);
+            return self.poll_write(cx, buf);
+        }
+
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.state.is_none() && self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream_id.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn splits_frames_across_buffers() {
+        let bytes = [
+            frame(HEADERS, 0, 1, b"abc"),
+            frame(DATA, END_STREAM, 1, &[0; 20]),
+            frame(PING, 0, 0, &[0; 8]),
+        ]
+        .concat();
+
+        let mut frames = Frames::default();
+        let mut kinds = Vec::new();
+        for chunk in bytes.chunks(4) {
+            assert_eq!(frames.until_boundary(chunk) == 0, frames.at_boundary());
+            frames.feed(chunk, |header| kinds.push((header.kind, header.stream_id)));
+        }
+
+        assert_eq!(kinds, [(HEADERS, 1), (DATA, 1), (PING, 0)]);
+        assert!(frames.at_boundary());
+    }
+
+    #[test]
+    fn finds_frame_boundary() {
+        let bytes = frame(DATA, 0, 1, &[0; 20]);
+
+        let mut frames = Frames::default();
+        frames.feed(&bytes[..5], |_| {});
+        assert_eq!(frames.until_boundary(&[bytes.clone(), bytes].concat()), 24);
+    }
+}
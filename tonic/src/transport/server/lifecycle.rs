@@ -0,0 +1,89 @@
+use super::TcpConnectInfo;
+#[cfg(feature = "_tls-any")]
+use super::TlsConnectInfo;
+#[cfg(feature = "_tls-any")]
+use crate::transport::TlsInfo;
+use http::Extensions;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+pub(crate) type OnConnect = Arc<dyn Fn(&ConnectionInfo) -> bool + Send + Sync + 'static>;
+pub(crate) type OnDisconnect =
+    Arc<dyn Fn(&ConnectionInfo, &ConnectionStats) + Send + Sync + 'static>;
+
+/// A connection accepted by the server.
+///
+/// It is passed to the [`on_connect`](super::Server::on_connect) and
+/// [`on_disconnect`](super::Server::on_disconnect) hooks.
+#[derive(Debug)]
+pub struct ConnectionInfo {
+    extensions: Extensions,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn new(extensions: Extensions) -> Self {
+        Self { extensions }
+    }
+
+    /// Get the remote address of this connection.
+    ///
+    /// This will return `None` if the `IO` type used does not implement `Connected` or when using
+    /// a unix domain socket.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.tcp_connect_info()
+            .and_then(TcpConnectInfo::remote_addr)
+    }
+
+    /// Get the local address of this connection.
+    ///
+    /// This will return `None` if the `IO` type used does not implement `Connected` or when using
+    /// a unix domain socket.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.tcp_connect_info().and_then(TcpConnectInfo::local_addr)
+    }
+
+    /// Get the parameters negotiated by the TLS handshake of this connection.
+    #[cfg(feature = "_tls-any")]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.extensions.get::<TlsInfo>()
+    }
+
+    /// Get the connection info of the IO resource, as added to the extensions of its requests.
+    ///
+    /// See [`Connected`](super::Connected) for more details.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    fn tcp_connect_info(&self) -> Option<&TcpConnectInfo> {
+        let info = self.extensions.get::<TcpConnectInfo>();
+
+        #[cfg(feature = "_tls-any")]
+        let info = info.or_else(|| {
+            self.extensions
+                .get::<TlsConnectInfo<TcpConnectInfo>>()
+                .map(TlsConnectInfo::get_ref)
+        });
+
+        info
+    }
+}
+
+/// What happened on a connection, passed to the [`on_disconnect`](super::Server::on_disconnect)
+/// hook.
+#[derive(Debug, Clone)]
+pub struct ConnectionStats {
+    pub(crate) duration: Duration,
+    pub(crate) streams: u64,
+}
+
+impl ConnectionStats {
+    /// How long the connection was open.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The number of requests received on the connection.
+    pub fn streams(&self) -> u64 {
+        self.streams
+    }
+}
@@ -0,0 +1,178 @@
+use super::{TcpIncoming, UdsIncoming};
+use socket2::{Socket, Type};
+use std::{
+    env, io,
+    os::fd::{FromRawFd, OwnedFd, RawFd},
+};
+use tokio::net::{TcpListener, UnixListener};
+
+// The first file descriptor passed by systemd, see sd_listen_fds(3).
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Listening sockets bound by another process, e.g. by systemd with socket activation, to serve
+/// with [`Router::serve_with_incoming`](super::Router::serve_with_incoming) instead of binding
+/// them in the server.
+///
+/// Each socket is taken once, as a [`TcpIncoming`] or an [`UdsIncoming`] depending on its
+/// address family.
+///
+/// # Example
+///
+/// ```no_run
+/// # use tonic::transport::server::ListenFds;
+/// # async fn run() -> std::io::Result<()> {
+/// let mut fds = ListenFds::from_env()?;
+/// let index = fds.position("grpc").unwrap_or(0);
+/// let incoming = fds.take_tcp(index)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ListenFds {
+    fds: Vec<Option<OwnedFd>>,
+    names: Vec<String>,
+}
+
+impl ListenFds {
+    /// Takes the sockets passed with systemd socket activation, as described by the
+    /// `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` environment variables.
+    ///
+    /// There are no sockets if the variables are not set or were set for another process. The
+    /// variables are removed so that the sockets are taken only once, and the sockets are not
+    /// inherited by child processes.
+    pub fn from_env() -> io::Result<Self> {
+        let count = parse_listen_fds(
+            env::var("LISTEN_PID").ok().as_deref(),
+            env::var("LISTEN_FDS").ok().as_deref(),
+            std::process::id(),
+        )?;
+        let names = env::var("LISTEN_FDNAMES").unwrap_or_default();
+
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+
+        let fds = (LISTEN_FDS_START..LISTEN_FDS_START + count as RawFd)
+            .map(|fd| {
+                // SAFETY: the service manager passed `fd` to this process for it to own, and the
+                // variables describing it were removed above so it is not taken again.
+                let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+                socket2::SockRef::from(&fd).set_cloexec(true)?;
+                Ok(fd)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut listen_fds = Self::from_fds(fds);
+        if !names.is_empty() {
+            listen_fds.names = names.split(':').map(str::to_owned).collect();
+        }
+        Ok(listen_fds)
+    }
+
+    /// Creates an instance from listening sockets received some other way, e.g. from a previous
+    /// instance of the server over a unix domain socket.
+    pub fn from_fds(fds: impl IntoIterator<Item = OwnedFd>) -> Self {
+        Self {
+            fds: fds.into_iter().map(Some).collect(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Returns the number of sockets, including the ones already taken.
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Returns `true` if there are no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+
+    /// Returns the name of the socket at `index`, as set with `FileDescriptorName=` in its
+    /// systemd socket unit.
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.names.get(index).map(String::as_str)
+    }
+
+    /// Returns the index of the first socket named `name`.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Takes the TCP socket at `index`.
+    ///
+    /// This must be called within a tokio runtime.
+    pub fn take_tcp(&mut self, index: usize) -> io::Result<TcpIncoming> {
+        let socket = self.take_listener(index)?;
+        let addr = socket.local_addr()?;
+        if !addr.is_ipv4() && !addr.is_ipv6() {
+            return Err(invalid_input(format!(
+                "listen fd {index} is not a TCP socket"
+            )));
+        }
+
+        Ok(TcpListener::from_std(socket.into())?.into())
+    }
+
+    /// Takes the unix domain socket at `index`.
+    ///
+    /// This must be called within a tokio runtime.
+    pub fn take_uds(&mut self, index: usize) -> io::Result<UdsIncoming> {
+        let socket = self.take_listener(index)?;
+        if !socket.local_addr()?.is_unix() {
+            return Err(invalid_input(format!(
+                "listen fd {index} is not a unix domain socket"
+            )));
+        }
+
+        Ok(UnixListener::from_std(socket.into())?.into())
+    }
+
+    fn take_listener(&mut self, index: usize) -> io::Result<Socket> {
+        let fd = self
+            .fds
+            .get_mut(index)
+            .ok_or_else(|| invalid_input(format!("no listen fd at index {index}")))?
+            .take()
+            .ok_or_else(|| invalid_input(format!("listen fd {index} was already taken")))?;
+
+        let socket = Socket::from(fd);
+        if socket.r#type()? != Type::STREAM {
+            return Err(invalid_input(format!(
+                "listen fd {index} is not a stream socket"
+            )));
+        }
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    }
+}
+
+fn parse_listen_fds(pid: Option<&str>, fds: Option<&str>, own_pid: u32) -> io::Result<usize> {
+    let (Some(pid), Some(fds)) = (pid, fds) else {
+        return Ok(0);
+    };
+    if pid.parse::<u32>().ok() != Some(own_pid) {
+        return Ok(0);
+    }
+
+    fds.parse()
+        .map_err(|_| invalid_input(format!("invalid LISTEN_FDS: {fds:?}")))
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_listen_fds_for_own_pid() {
+        assert_eq!(parse_listen_fds(Some("42"), Some("2"), 42).unwrap(), 2);
+        assert_eq!(parse_listen_fds(Some("41"), Some("2"), 42).unwrap(), 0);
+        assert_eq!(parse_listen_fds(None, Some("2"), 42).unwrap(), 0);
+        assert_eq!(parse_listen_fds(Some("42"), None, 42).unwrap(), 0);
+        assert!(parse_listen_fds(Some("42"), Some("two"), 42).is_err());
+    }
+}
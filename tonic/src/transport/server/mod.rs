@@ -1,9 +1,22 @@
 //! Server implementation and builder.
 
+#[cfg(all(feature = "router", any(feature = "health", feature = "reflection")))]
+mod admin;
+mod cancellation;
 mod conn;
+mod connection;
 mod display_error_stack;
+#[cfg(feature = "grpc-web")]
+mod grpc_web;
 mod incoming;
 mod io_stream;
+mod keepalive;
+mod lifecycle;
+#[cfg(unix)]
+mod listen_fds;
+#[cfg(feature = "_tls-any")]
+mod peer_identity;
+mod proxy_protocol;
 mod service;
 #[cfg(feature = "_tls-any")]
 mod tls;
@@ -14,39 +27,67 @@ use tokio_stream::StreamExt as _;
 use tracing::{debug, trace};
 
 #[cfg(feature = "router")]
-use crate::{server::NamedService, service::Routes};
+use crate::{
+    server::NamedService,
+    service::{DynamicRoutes, Routes},
+};
 
 #[cfg(feature = "router")]
 use std::convert::Infallible;
 
+#[cfg(all(feature = "router", any(feature = "health", feature = "reflection")))]
+pub use admin::AdminServices;
+pub use cancellation::CancellationToken;
 pub use conn::{Connected, TcpConnectInfo};
+pub use connection::{ConnectionConfig, IncomingConnection};
+#[cfg(feature = "grpc-web")]
+pub use grpc_web::GrpcWebConfig;
 use hyper_util::{
-    rt::{TokioExecutor, TokioIo, TokioTimer},
+    rt::{TokioExecutor, TokioIo},
     server::conn::auto::{Builder as ConnectionBuilder, HttpServerConnExec},
     service::TowerToHyperService,
 };
 #[cfg(feature = "_tls-any")]
-pub use tls::ServerTlsConfig;
+pub use tls::{ClientAuth, ClientAuthContext, ServerTlsConfig};
 
 #[cfg(feature = "_tls-any")]
 pub use conn::TlsConnectInfo;
 
+#[cfg(feature = "_tls-any")]
+pub use peer_identity::{
+    PeerIdentity, PeerIdentityPolicy, VerifyPeerIdentity, VerifyPeerIdentityLayer,
+};
+
 #[cfg(feature = "_tls-any")]
 use self::service::TlsAcceptor;
 
 #[cfg(unix)]
-pub use unix::UdsConnectInfo;
+pub use listen_fds::ListenFds;
+#[cfg(unix)]
+pub use unix::{UdsConnectInfo, UdsIncoming};
 
 pub use incoming::TcpIncoming;
+pub use lifecycle::{ConnectionInfo, ConnectionStats};
+pub use proxy_protocol::{ProxiedStream, ProxyProtocolIncoming};
 
 #[cfg(feature = "_tls-any")]
 use crate::transport::Error;
 
-use self::service::{ConnectInfoLayer, ServerIo};
-use super::service::GrpcTimeout;
+use self::keepalive::{EnforceKeepalive, KeepalivePolicy};
+use self::lifecycle::{OnConnect, OnDisconnect};
+#[cfg(feature = "grpc-web")]
+use self::service::GrpcWeb;
+use self::service::{Cancellation, ConnectInfoLayer, MaxRpcLifetime, ServerIo};
+use super::service::{wire_debug::WireDebug, GrpcTimeout, MethodTimeouts, TimeoutBounds};
 use crate::body::Body;
-use crate::service::RecoverErrorLayer;
+use crate::codec::{CodecExecutor, MessageValidators};
+use crate::service::{fair_write::FairWriteBody, FairWriteLayer, RecoverErrorLayer};
+use crate::time::{Clock, SharedClock};
 use crate::transport::server::display_error_stack::DisplayErrorStack;
+use crate::{
+    metadata::{MetadataLimits, ReservedHeaderPolicy},
+    server::MessageSizeLimits,
+};
 use bytes::Bytes;
 use http::{Request, Response};
 use http_body_util::BodyExt;
@@ -58,18 +99,21 @@ use std::{
     marker::PhantomData,
     net::SocketAddr,
     pin::{pin, Pin},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     task::{ready, Context, Poll},
     time::Duration,
 };
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio_stream::Stream;
 use tower::{
     layer::util::{Identity, Stack},
     layer::Layer,
     limit::concurrency::ConcurrencyLimitLayer,
     load_shed::LoadShedLayer,
-    util::BoxCloneService,
+    util::{BoxCloneService, MapRequestLayer},
     Service, ServiceBuilder, ServiceExt,
 };
 
@@ -92,6 +136,8 @@ pub struct Server<L = Identity> {
     concurrency_limit: Option<usize>,
     load_shed: bool,
     timeout: Option<Duration>,
+    method_timeouts: MethodTimeouts,
+    client_timeout_bounds: TimeoutBounds,
     #[cfg(feature = "_tls-any")]
     tls: Option<TlsAcceptor>,
     init_stream_window_size: Option<u32>,
@@ -101,13 +147,30 @@ pub struct Server<L = Identity> {
     tcp_nodelay: bool,
     http2_keepalive_interval: Option<Duration>,
     http2_keepalive_timeout: Duration,
+    http2_min_ping_interval: Option<Duration>,
+    http2_permit_pings_without_streams: bool,
     http2_adaptive_window: Option<bool>,
     http2_max_pending_accept_reset_streams: Option<usize>,
     http2_max_header_list_size: Option<u32>,
     max_frame_size: Option<u32>,
     accept_http1: bool,
+    wire_debug: bool,
+    #[cfg(feature = "grpc-web")]
+    grpc_web: Option<GrpcWebConfig>,
     service_builder: ServiceBuilder<L>,
     max_connection_age: Option<Duration>,
+    max_connection_age_grace: Option<Duration>,
+    max_connections: Option<usize>,
+    header_read_timeout: Option<Duration>,
+    on_connect: Option<OnConnect>,
+    on_disconnect: Option<OnDisconnect>,
+    max_rpc_lifetime: Option<Duration>,
+    codec_executor: Option<CodecExecutor>,
+    message_validators: Option<MessageValidators>,
+    fair_write_quantum: Option<usize>,
+    message_size_limits: MessageSizeLimits,
+    metadata_limits: MetadataLimits,
+    clock: SharedClock,
 }
 
 impl Default for Server<Identity> {
@@ -117,6 +180,8 @@ impl Default for Server<Identity> {
             concurrency_limit: None,
             load_shed: false,
             timeout: None,
+            method_timeouts: MethodTimeouts::default(),
+            client_timeout_bounds: TimeoutBounds::default(),
             #[cfg(feature = "_tls-any")]
             tls: None,
             init_stream_window_size: None,
@@ -126,13 +191,30 @@ impl Default for Server<Identity> {
             tcp_nodelay: false,
             http2_keepalive_interval: None,
             http2_keepalive_timeout: DEFAULT_HTTP2_KEEPALIVE_TIMEOUT,
+            http2_min_ping_interval: None,
+            http2_permit_pings_without_streams: false,
             http2_adaptive_window: None,
             http2_max_pending_accept_reset_streams: None,
             http2_max_header_list_size: None,
             max_frame_size: None,
             accept_http1: false,
+            wire_debug: false,
+            #[cfg(feature = "grpc-web")]
+            grpc_web: None,
             service_builder: Default::default(),
             max_connection_age: None,
+            max_connection_age_grace: None,
+            max_connections: None,
+            header_read_timeout: None,
+            on_connect: None,
+            on_disconnect: None,
+            max_rpc_lifetime: None,
+            codec_executor: None,
+            message_validators: None,
+            fair_write_quantum: None,
+            message_size_limits: MessageSizeLimits::default(),
+            metadata_limits: MetadataLimits::default(),
+            clock: SharedClock::default(),
         }
     }
 }
@@ -207,6 +289,9 @@ impl<L> Server<L> {
 
     /// Set a timeout on for all request handlers.
     ///
+    /// Requests that exceed it, or the `grpc-timeout` sent by the client, are answered with a
+    /// `DeadlineExceeded` status.
+    ///
     /// # Example
     ///
     /// ```
@@ -224,6 +309,66 @@ impl<L> Server<L> {
         }
     }
 
+    /// Set a timeout for the handlers of the method at `path`, e.g.
+    /// `/helloworld.Greeter/SayHello`, instead of the [timeout](Self::timeout) of all handlers.
+    ///
+    /// The timeout may also be longer than the one of all handlers, e.g. for slow administrative
+    /// methods. A shorter `grpc-timeout` sent by the client is still enforced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder
+    ///     .timeout(Duration::from_millis(100))
+    ///     .method_timeout("/admin.Admin/Compact", Duration::from_secs(600));
+    /// ```
+    #[must_use]
+    pub fn method_timeout(mut self, path: impl Into<String>, timeout: Duration) -> Self {
+        self.method_timeouts.insert(path.into(), timeout);
+        self
+    }
+
+    /// Honor the `grpc-timeout` sent by clients as at least `min`.
+    ///
+    /// This keeps a buggy client from asking for deadlines that expire before its requests can
+    /// possibly be handled. Default is no minimum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder.min_client_timeout(Duration::from_millis(5));
+    /// ```
+    #[must_use]
+    pub fn min_client_timeout(mut self, min: Duration) -> Self {
+        self.client_timeout_bounds.min = Some(min);
+        self
+    }
+
+    /// Honor the `grpc-timeout` sent by clients as at most `max`.
+    ///
+    /// Unlike [`Server::timeout`], this does not apply to requests without a `grpc-timeout`.
+    /// Default is no maximum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder.max_client_timeout(Duration::from_secs(300));
+    /// ```
+    #[must_use]
+    pub fn max_client_timeout(mut self, max: Duration) -> Self {
+        self.client_timeout_bounds.max = Some(max);
+        self
+    }
+
     /// Sets the [`SETTINGS_INITIAL_WINDOW_SIZE`][spec] option for HTTP2
     /// stream-level flow control.
     ///
@@ -249,9 +394,61 @@ impl<L> Server<L> {
         }
     }
 
+    /// Sets the maximum number of connections served at the same time.
+    ///
+    /// Connections accepted while the limit is reached are closed right away, so that clients back
+    /// off or try another replica, which bounds the memory used under connection storms. If the
+    /// server only accepts HTTP/2, they are sent a `GOAWAY` frame with the `ENHANCE_YOUR_CALM`
+    /// error code first. The number of streams per connection is limited with
+    /// [`max_concurrent_streams`](Self::max_concurrent_streams).
+    ///
+    /// Default is no limit (`None`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.max_connections(10_000).max_concurrent_streams(100);
+    /// ```
+    #[must_use]
+    pub fn max_connections(self, max: impl Into<Option<usize>>) -> Self {
+        Server {
+            max_connections: max.into(),
+            ..self
+        }
+    }
+
+    /// Sets how long a connection may stay open before the headers of its first request are
+    /// received, and, for HTTP/1 connections, how long the headers of each request may take.
+    ///
+    /// Connections that do not send request headers in time are closed, so that slow or idle
+    /// clients cannot pin connections. The timeout starts once the connection is established,
+    /// after the TLS handshake, which is bounded by `ServerTlsConfig::timeout`.
+    ///
+    /// Default is no timeout (`None`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder.header_read_timeout(Duration::from_secs(10));
+    /// ```
+    #[must_use]
+    pub fn header_read_timeout(self, timeout: impl Into<Option<Duration>>) -> Self {
+        Server {
+            header_read_timeout: timeout.into(),
+            ..self
+        }
+    }
+
     /// Sets the [`SETTINGS_MAX_CONCURRENT_STREAMS`][spec] option for HTTP2
     /// connections.
     ///
+    /// Streams opened beyond the limit are refused with `REFUSED_STREAM`.
+    ///
     /// Default is no limit (`None`).
     ///
     /// [spec]: https://httpwg.org/specs/rfc9113.html#n-stream-concurrency
@@ -263,8 +460,194 @@ impl<L> Server<L> {
         }
     }
 
+    /// Sets the maximum time a call may run for, regardless of its deadline.
+    ///
+    /// Calls that are still running once this time elapsed, e.g. long lived watch streams, are
+    /// ended with an `UNAVAILABLE` status carrying a `google.rpc.RetryInfo` detail, which tells
+    /// clients to re-issue the call right away. Until the response headers were sent, the call
+    /// is ended with a trailers-only response instead.
+    ///
+    /// Default is no limit (`None`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder.max_rpc_lifetime(Duration::from_secs(60 * 60));
+    /// ```
+    #[must_use]
+    pub fn max_rpc_lifetime(self, max_rpc_lifetime: impl Into<Option<Duration>>) -> Self {
+        Server {
+            max_rpc_lifetime: max_rpc_lifetime.into(),
+            ..self
+        }
+    }
+
+    /// Sets the [`CodecExecutor`] that compresses and decompresses large messages.
+    ///
+    /// By default all messages are compressed and decompressed on the thread driving the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::{codec::CodecExecutor, transport::Server};
+    /// # use hyper_util::rt::TokioExecutor;
+    /// # let builder = Server::builder();
+    /// builder.codec_executor(CodecExecutor::new(TokioExecutor::new()));
+    /// ```
+    #[must_use]
+    pub fn codec_executor(self, executor: CodecExecutor) -> Self {
+        Server {
+            codec_executor: Some(executor),
+            ..self
+        }
+    }
+
+    /// Sets the [`MessageValidators`] that check the requests and responses of the services that
+    /// do not set their own, e.g. with the `message_validators` method of `tonic::server::Grpc`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::{codec::MessageValidators, transport::Server};
+    /// # let builder = Server::builder();
+    /// builder.message_validators(MessageValidators::new());
+    /// ```
+    #[must_use]
+    pub fn message_validators(self, validators: MessageValidators) -> Self {
+        Server {
+            message_validators: Some(validators),
+            ..self
+        }
+    }
+
+    /// Limits the maximum size of a decoded message for the services that do not set their own
+    /// limit, e.g. with the `max_decoding_message_size` method of a generated server.
+    ///
+    /// Defaults to 4MB.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.max_decoding_message_size(16 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.message_size_limits.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the maximum size of an encoded message for the services that do not set their own
+    /// limit, e.g. with the `max_encoding_message_size` method of a generated server.
+    ///
+    /// Defaults to `usize::MAX`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.max_encoding_message_size(16 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.message_size_limits.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the size of the metadata of responses for the services that do not set their own
+    /// limit, counted like HTTP/2 counts the size of a header list: the length of the name and
+    /// the value of each entry plus 32 bytes.
+    ///
+    /// Responses exceeding the limit are replaced with a `RESOURCE_EXHAUSTED` status, instead of
+    /// being rejected by the client or by the HTTP/2 layer. Defaults to no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.max_metadata_size(16 * 1024);
+    /// ```
+    #[must_use]
+    pub fn max_metadata_size(mut self, limit: usize) -> Self {
+        self.metadata_limits.max_size = Some(limit);
+        self
+    }
+
+    /// Limits the number of entries of the metadata of responses for the services that do not
+    /// set their own limit.
+    ///
+    /// Responses exceeding the limit are replaced with a `RESOURCE_EXHAUSTED` status. Defaults
+    /// to no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.max_metadata_entries(64);
+    /// ```
+    #[must_use]
+    pub fn max_metadata_entries(mut self, limit: usize) -> Self {
+        self.metadata_limits.max_entries = Some(limit);
+        self
+    }
+
+    /// Sets what to do with reserved headers, e.g. `grpc-encoding` or `connection`, set in the
+    /// metadata of responses for the services that do not set their own policy.
+    ///
+    /// With [`ReservedHeaderPolicy::Strip`], each removed header is logged as a warning.
+    /// Defaults to [`ReservedHeaderPolicy::Allow`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::{metadata::ReservedHeaderPolicy, transport::Server};
+    /// # let builder = Server::builder();
+    /// builder.reserved_header_policy(ReservedHeaderPolicy::Strip);
+    /// ```
+    #[must_use]
+    pub fn reserved_header_policy(mut self, policy: ReservedHeaderPolicy) -> Self {
+        self.metadata_limits.reserved_headers = Some(policy);
+        self
+    }
+
+    /// Splits the responses of each call into slices of at most `quantum` bytes, so that a call
+    /// with a large backlog does not delay the small messages of the other calls of the
+    /// connection.
+    ///
+    /// The [`StreamBacklog`](crate::service::StreamBacklog) of each response is added to the
+    /// extensions of its request. See [`FairWriteLayer`] for more details.
+    ///
+    /// Default is `None`, responses are written as they are produced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.fair_write_quantum(16 * 1024);
+    /// ```
+    #[must_use]
+    pub fn fair_write_quantum(self, quantum: impl Into<Option<usize>>) -> Self {
+        Server {
+            fair_write_quantum: quantum.into(),
+            ..self
+        }
+    }
+
     /// Sets the maximum time option in milliseconds that a connection may exist
     ///
+    /// Once the connection is older, the server sends a `GOAWAY` frame so that clients open a new
+    /// connection, which lets long-lived clients rebalance across server replicas. The requests in
+    /// flight are allowed to finish, see [`max_connection_age_grace`](Self::max_connection_age_grace)
+    /// to bound how long.
+    ///
     /// Default is no limit (`None`).
     ///
     /// # Example
@@ -284,6 +667,51 @@ impl<L> Server<L> {
         }
     }
 
+    /// Sets the time the requests in flight on a connection that exceeded its
+    /// [maximum age](Self::max_connection_age) are given to finish before the connection is closed
+    /// forcibly.
+    ///
+    /// Default is no limit (`None`), the connection is closed once its requests finished.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder
+    ///     .max_connection_age(Duration::from_secs(300))
+    ///     .max_connection_age_grace(Duration::from_secs(30));
+    /// ```
+    #[must_use]
+    pub fn max_connection_age_grace(self, grace: Duration) -> Self {
+        Server {
+            max_connection_age_grace: Some(grace),
+            ..self
+        }
+    }
+
+    /// Sets the [`Clock`] used for all time related work of the server, e.g. enforcing
+    /// [timeouts](Self::timeout), the [maximum connection age](Self::max_connection_age) or
+    /// scheduling keepalive pings.
+    ///
+    /// Uses the Tokio timer by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::{time::TokioClock, transport::Server};
+    /// # let builder = Server::builder();
+    /// builder.clock(TokioClock);
+    /// ```
+    #[must_use]
+    pub fn clock(self, clock: impl Clock) -> Self {
+        Server {
+            clock: SharedClock::new(clock),
+            ..self
+        }
+    }
+
     /// Set whether HTTP2 Ping frames are enabled on accepted connections.
     ///
     /// If `None` is specified, HTTP2 keepalive is disabled, otherwise the duration
@@ -316,6 +744,46 @@ impl<L> Server<L> {
         self
     }
 
+    /// Sets the minimum interval between the HTTP2 Ping frames of clients.
+    ///
+    /// Clients that send pings more often are considered abusive: after a few early pings the
+    /// connection is closed with a `GOAWAY` frame carrying the `ENHANCE_YOUR_CALM` error code and
+    /// `too_many_pings`, as grpc-go servers do. Sending data on the connection resets the count
+    /// of early pings.
+    ///
+    /// While there is no active stream on the connection, pings are only allowed every two hours
+    /// unless [`Server::http2_permit_pings_without_streams`] is enabled.
+    ///
+    /// Default is no enforcement (`None`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # use std::time::Duration;
+    /// # let builder = Server::builder();
+    /// builder.http2_min_ping_interval(Some(Duration::from_secs(60)));
+    /// ```
+    #[must_use]
+    pub fn http2_min_ping_interval(self, interval: Option<Duration>) -> Self {
+        Server {
+            http2_min_ping_interval: interval,
+            ..self
+        }
+    }
+
+    /// Sets whether clients may send HTTP2 Ping frames while there is no active stream on the
+    /// connection, at the interval set with [`Server::http2_min_ping_interval`].
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn http2_permit_pings_without_streams(self, permit: bool) -> Self {
+        Server {
+            http2_permit_pings_without_streams: permit,
+            ..self
+        }
+    }
+
     /// Sets whether to use an adaptive flow control. Defaults to false.
     /// Enabling this will override the limits set in http2_initial_stream_window_size and
     /// http2_initial_connection_window_size.
@@ -391,6 +859,23 @@ impl<L> Server<L> {
         }
     }
 
+    /// Logs the HTTP/2 frames of every connection, to debug interoperability issues without
+    /// capturing the traffic.
+    ///
+    /// The type, stream id, flags and size of every frame read or written are logged at the
+    /// `TRACE` level under the `tonic::wire` target, along with the flow-control updates, the
+    /// settings and the boundaries of the gRPC messages. Header blocks and message payloads are
+    /// never logged. Connections are not inspected unless that target is enabled.
+    ///
+    /// Default is `false`.
+    #[must_use]
+    pub fn wire_debug(self, enabled: bool) -> Self {
+        Server {
+            wire_debug: enabled,
+            ..self
+        }
+    }
+
     /// Allow this server to accept http1 requests.
     ///
     /// Accepting http1 requests is only useful when developing `grpc-web`
@@ -407,6 +892,92 @@ impl<L> Server<L> {
         }
     }
 
+    /// Serves [gRPC-Web] requests, so browsers can call the services without a proxy.
+    ///
+    /// gRPC-Web requests, whose `content-type` is one of `application/grpc-web`,
+    /// `application/grpc-web+proto`, `application/grpc-web-text` or
+    /// `application/grpc-web-text+proto`, are translated into gRPC requests and their responses
+    /// back, trailers included in the body. They are accepted over HTTP/1.1 even if
+    /// [`accept_http1`](Self::accept_http1) is not set, while other HTTP/1.1 requests are
+    /// rejected with `400 Bad Request` unless it is. CORS preflight requests are answered
+    /// according to `config`.
+    ///
+    /// Browsers only support unary and server streaming calls over gRPC-Web.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::{server::GrpcWebConfig, Server};
+    /// # let builder = Server::builder();
+    /// builder.grpc_web(
+    ///     GrpcWebConfig::new().allow_origin_fn(|origin| origin.as_bytes().ends_with(b".example.com")),
+    /// );
+    /// ```
+    ///
+    /// [gRPC-Web]: https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md
+    #[cfg(feature = "grpc-web")]
+    #[must_use]
+    pub fn grpc_web(self, config: GrpcWebConfig) -> Self {
+        Server {
+            grpc_web: Some(config),
+            ..self
+        }
+    }
+
+    /// Calls `f` with every accepted connection, before it is served.
+    ///
+    /// The connection is closed right away if `f` returns `false`, e.g. to rate limit abusive
+    /// peers. If the server only accepts HTTP/2, the peer is sent a `GOAWAY` frame with the
+    /// `ENHANCE_YOUR_CALM` error code first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.on_connect(|conn| {
+    ///     tracing::info!(peer = ?conn.remote_addr(), "connection accepted");
+    ///     true
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_connect<F>(self, f: F) -> Self
+    where
+        F: Fn(&ConnectionInfo) -> bool + Send + Sync + 'static,
+    {
+        Server {
+            on_connect: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Calls `f` with every served connection once it is closed, along with statistics about it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::Server;
+    /// # let builder = Server::builder();
+    /// builder.on_disconnect(|conn, stats| {
+    ///     tracing::info!(
+    ///         peer = ?conn.remote_addr(),
+    ///         duration = ?stats.duration(),
+    ///         streams = stats.streams(),
+    ///         "connection closed",
+    ///     );
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_disconnect<F>(self, f: F) -> Self
+    where
+        F: Fn(&ConnectionInfo, &ConnectionStats) + Send + Sync + 'static,
+    {
+        Server {
+            on_disconnect: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
     /// Intercept inbound headers and add a [`tracing::Span`] to each response future.
     #[must_use]
     pub fn trace_fn<F>(self, f: F) -> Self
@@ -544,6 +1115,8 @@ impl<L> Server<L> {
             concurrency_limit: self.concurrency_limit,
             load_shed: self.load_shed,
             timeout: self.timeout,
+            method_timeouts: self.method_timeouts,
+            client_timeout_bounds: self.client_timeout_bounds,
             #[cfg(feature = "_tls-any")]
             tls: self.tls,
             init_stream_window_size: self.init_stream_window_size,
@@ -553,12 +1126,29 @@ impl<L> Server<L> {
             tcp_nodelay: self.tcp_nodelay,
             http2_keepalive_interval: self.http2_keepalive_interval,
             http2_keepalive_timeout: self.http2_keepalive_timeout,
+            http2_min_ping_interval: self.http2_min_ping_interval,
+            http2_permit_pings_without_streams: self.http2_permit_pings_without_streams,
             http2_adaptive_window: self.http2_adaptive_window,
             http2_max_pending_accept_reset_streams: self.http2_max_pending_accept_reset_streams,
             http2_max_header_list_size: self.http2_max_header_list_size,
             max_frame_size: self.max_frame_size,
             accept_http1: self.accept_http1,
+            wire_debug: self.wire_debug,
+            #[cfg(feature = "grpc-web")]
+            grpc_web: self.grpc_web,
             max_connection_age: self.max_connection_age,
+            max_connection_age_grace: self.max_connection_age_grace,
+            max_connections: self.max_connections,
+            header_read_timeout: self.header_read_timeout,
+            on_connect: self.on_connect,
+            on_disconnect: self.on_disconnect,
+            max_rpc_lifetime: self.max_rpc_lifetime,
+            codec_executor: self.codec_executor,
+            message_validators: self.message_validators,
+            fair_write_quantum: self.fair_write_quantum,
+            message_size_limits: self.message_size_limits,
+            metadata_limits: self.metadata_limits,
+            clock: self.clock,
         }
     }
 
@@ -677,15 +1267,44 @@ impl<L> Server<L> {
         let init_stream_window_size = self.init_stream_window_size;
         let max_concurrent_streams = self.max_concurrent_streams;
         let timeout = self.timeout;
+        let method_timeouts = self.method_timeouts;
+        let client_timeout_bounds = self.client_timeout_bounds;
         let max_header_list_size = self.http2_max_header_list_size;
         let max_frame_size = self.max_frame_size;
-        let http2_only = !self.accept_http1;
+        let accept_http1 = self.accept_http1;
+        let wire_debug = self.wire_debug;
+        #[cfg(feature = "grpc-web")]
+        let grpc_web = self.grpc_web;
+        #[cfg(feature = "grpc-web")]
+        let http2_only = !accept_http1 && grpc_web.is_none();
+        #[cfg(not(feature = "grpc-web"))]
+        let http2_only = !accept_http1;
 
         let http2_keepalive_interval = self.http2_keepalive_interval;
         let http2_keepalive_timeout = self.http2_keepalive_timeout;
+        let keepalive_policy =
+            self.http2_min_ping_interval
+                .map(|min_ping_interval| KeepalivePolicy {
+                    min_ping_interval,
+                    permit_without_streams: self.http2_permit_pings_without_streams,
+                });
         let http2_adaptive_window = self.http2_adaptive_window;
         let http2_max_pending_accept_reset_streams = self.http2_max_pending_accept_reset_streams;
         let max_connection_age = self.max_connection_age;
+        let max_connection_age_grace = self.max_connection_age_grace;
+        let header_read_timeout = self.header_read_timeout;
+        let connections = self
+            .max_connections
+            .map(|max| Arc::new(tokio::sync::Semaphore::new(max)));
+        let on_connect = self.on_connect;
+        let on_disconnect = self.on_disconnect;
+        let max_rpc_lifetime = self.max_rpc_lifetime;
+        let codec_executor = self.codec_executor;
+        let message_validators = self.message_validators;
+        let fair_write_quantum = self.fair_write_quantum;
+        let message_size_limits = self.message_size_limits;
+        let metadata_limits = self.metadata_limits;
+        let clock = self.clock;
 
         let svc = self.service_builder.service(svc);
 
@@ -699,7 +1318,20 @@ impl<L> Server<L> {
             concurrency_limit,
             load_shed,
             timeout,
+            method_timeouts,
+            client_timeout_bounds,
+            max_rpc_lifetime,
+            codec_executor,
+            message_validators,
+            fair_write_quantum,
+            message_size_limits,
+            metadata_limits,
             trace_interceptor,
+            #[cfg(feature = "grpc-web")]
+            grpc_web,
+            #[cfg(feature = "grpc-web")]
+            accept_http1,
+            clock: clock.clone(),
             _io: PhantomData,
         };
 
@@ -712,7 +1344,7 @@ impl<L> Server<L> {
 
             builder
                 .http2()
-                .timer(TokioTimer::new())
+                .timer(clock.clone())
                 .initial_connection_window_size(init_connection_window_size)
                 .initial_stream_window_size(init_stream_window_size)
                 .max_concurrent_streams(max_concurrent_streams)
@@ -726,6 +1358,13 @@ impl<L> Server<L> {
                 builder.http2().max_header_list_size(max_header_list_size);
             }
 
+            if let Some(header_read_timeout) = header_read_timeout {
+                builder
+                    .http1()
+                    .timer(clock.clone())
+                    .header_read_timeout(header_read_timeout);
+            }
+
             builder
         };
 
@@ -761,10 +1400,78 @@ impl<L> Server<L> {
                         .await
                         .map_err(super::Error::from_source)?;
 
-                    let hyper_io = TokioIo::new(io);
-                    let hyper_svc = TowerToHyperService::new(req_svc.map_request(|req: Request<Incoming>| req.map(Body::new)));
+                    let info = (on_connect.is_some() || on_disconnect.is_some()).then(|| {
+                        let mut extensions = http::Extensions::new();
+                        io.connect_info().insert_into(&mut extensions);
+                        ConnectionInfo::new(extensions)
+                    });
+
+                    if let (Some(on_connect), Some(info)) = (&on_connect, &info) {
+                        if !on_connect(info) {
+                            debug!("connection refused by on_connect hook");
+                            reject_connection(io, http2_only);
+                            continue;
+                        }
+                    }
+
+                    let permit = match &connections {
+                        Some(connections) => match connections.clone().try_acquire_owned() {
+                            Ok(permit) => Some(permit),
+                            Err(_) => {
+                                debug!("connection limit reached, rejecting connection");
+                                reject_connection(io, http2_only);
+                                continue;
+                            }
+                        },
+                        None => None,
+                    };
+
+                    let streams = Arc::new(AtomicU64::new(0));
+                    let closed = match (&on_disconnect, info) {
+                        (Some(on_disconnect), Some(info)) => {
+                            let on_disconnect = on_disconnect.clone();
+                            let streams = streams.clone();
+                            let clock = clock.clone();
+                            let start = clock.now();
+                            Some(Box::new(move || {
+                                let stats = ConnectionStats {
+                                    duration: clock.now().saturating_duration_since(start),
+                                    streams: streams.load(Ordering::Relaxed),
+                                };
+                                on_disconnect(&info, &stats);
+                            }) as Box<dyn FnOnce() + Send>)
+                        }
+                        _ => None,
+                    };
+                    let guard = ConnectionGuard { _permit: permit, closed };
+
+                    let config = io.connection_config();
+                    let mut builder = server.clone();
+                    if let Some(max) = config.max_concurrent_streams {
+                        builder.http2().max_concurrent_streams(max);
+                    }
+                    let max_connection_age = config.max_connection_age.or(max_connection_age);
+
+                    let first_request = header_read_timeout.map(|timeout| (timeout, Arc::new(tokio::sync::Notify::new())));
+                    let received = first_request.as_ref().map(|(_, received)| received.clone());
+
+                    let io = WireDebug::server(io, wire_debug);
+                    let hyper_io = TokioIo::new(EnforceKeepalive::new(io, keepalive_policy, clock.clone()));
+                    let hyper_svc = TowerToHyperService::new(req_svc.map_request(move |req: Request<Incoming>| {
+                        streams.fetch_add(1, Ordering::Relaxed);
+                        if let Some(received) = &received {
+                            received.notify_one();
+                        }
+                        req.map(Body::new)
+                    }));
+
+                    let timeouts = ConnectionTimeouts {
+                        max_age: max_connection_age,
+                        max_age_grace: max_connection_age_grace,
+                        first_request,
+                    };
 
-                    serve_connection(hyper_io, hyper_svc, server.clone(), graceful.then(|| signal_rx.clone()), max_connection_age);
+                    serve_connection(hyper_io, hyper_svc, builder, graceful.then(|| signal_rx.clone()), timeouts, clock.clone(), guard);
                 }
             }
         }
@@ -792,7 +1499,9 @@ fn serve_connection<B, IO, S, E>(
     hyper_svc: S,
     builder: ConnectionBuilder<E>,
     mut watcher: Option<tokio::sync::watch::Receiver<()>>,
-    max_connection_age: Option<Duration>,
+    timeouts: ConnectionTimeouts,
+    clock: SharedClock,
+    guard: ConnectionGuard,
 ) where
     B: http_body::Body + Send + 'static,
     B::Data: Send,
@@ -811,7 +1520,18 @@ fn serve_connection<B, IO, S, E>(
 
             let mut conn = pin!(builder.serve_connection(hyper_io, hyper_svc));
 
-            let mut sleep = pin!(sleep_or_pending(max_connection_age));
+            let mut sleep = pin!(sleep_or_pending(&clock, timeouts.max_age));
+            let mut aged = false;
+
+            let mut header_read_timeout = pin!(async {
+                let Some((timeout, received)) = &timeouts.first_request else {
+                    return future::pending().await;
+                };
+                tokio::select! {
+                    _ = received.notified() => future::pending().await,
+                    _ = clock.sleep(*timeout) => {},
+                }
+            });
 
             loop {
                 tokio::select! {
@@ -822,24 +1542,80 @@ fn serve_connection<B, IO, S, E>(
                         break;
                     },
                     _ = &mut sleep  => {
+                        if aged {
+                            debug!("closing connection after max connection age grace period");
+                            break;
+                        }
+                        aged = true;
                         conn.as_mut().graceful_shutdown();
-                        sleep.set(sleep_or_pending(None));
+                        sleep.set(sleep_or_pending(&clock, timeouts.max_age_grace));
                     },
                     _ = &mut sig => {
                         conn.as_mut().graceful_shutdown();
+                    },
+                    _ = &mut header_read_timeout => {
+                        debug!("closing connection without request headers after header read timeout");
+                        break;
                     }
                 }
             }
         }
 
         drop(watcher);
+        drop(guard);
         trace!("connection closed");
     });
 }
 
-async fn sleep_or_pending(wait_for: Option<Duration>) {
+/// The timeouts of a connection.
+struct ConnectionTimeouts {
+    max_age: Option<Duration>,
+    max_age_grace: Option<Duration>,
+    /// The header read timeout, and the notification of the first request.
+    first_request: Option<(Duration, Arc<tokio::sync::Notify>)>,
+}
+
+/// Held for as long as a connection is served.
+struct ConnectionGuard {
+    _permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    closed: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Some(closed) = self.closed.take() {
+            closed();
+        }
+    }
+}
+
+/// The frames sent to reject an HTTP/2 connection: an empty `SETTINGS` frame, which has to be the
+/// first frame sent by the server, and a `GOAWAY` frame with the `ENHANCE_YOUR_CALM` error code.
+const REJECT_FRAMES: [u8; 26] = [
+    0, 0, 0, 0x4, 0, 0, 0, 0, 0, // SETTINGS, stream 0
+    0, 0, 8, 0x7, 0, 0, 0, 0, 0, // GOAWAY, stream 0
+    0, 0, 0, 0, // last stream id
+    0, 0, 0, 0xb, // ENHANCE_YOUR_CALM
+];
+
+/// Closes a connection without serving it, telling HTTP/2 clients why.
+fn reject_connection<IO>(mut io: IO, http2_only: bool)
+where
+    IO: AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        if http2_only {
+            if let Err(err) = io.write_all(&REJECT_FRAMES).await {
+                debug!("failed rejecting connection: {}", err);
+            }
+        }
+        let _ = io.shutdown().await;
+    });
+}
+
+async fn sleep_or_pending(clock: &SharedClock, wait_for: Option<Duration>) {
     match wait_for {
-        Some(wait) => tokio::time::sleep(wait).await,
+        Some(wait) => clock.sleep(wait).await,
         None => future::pending().await,
     };
 }
@@ -849,6 +1625,12 @@ impl<L> Router<L> {
     pub(crate) fn new(server: Server<L>, routes: Routes) -> Self {
         Self { server, routes }
     }
+
+    /// The server and the routes it serves, to serve them with a service wrapping the routes.
+    #[cfg(all(feature = "testing", feature = "channel"))]
+    pub(crate) fn into_parts(self) -> (Server<L>, Routes) {
+        (self.server, self.routes.prepare())
+    }
 }
 
 #[cfg(feature = "router")]
@@ -891,6 +1673,94 @@ impl<L> Router<L> {
         self
     }
 
+    /// Add the `grpc.health.v1.Health` service, serving the statuses set with `reporter`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let reporter = HealthReporter::new();
+    /// reporter.set_serving::<GreeterServer<MyGreeter>>();
+    ///
+    /// Server::builder()
+    ///     .add_service(GreeterServer::new(MyGreeter::default()))
+    ///     .add_health_service(&reporter)
+    ///     .serve(addr)
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "health")]
+    pub fn add_health_service(self, reporter: &crate::health::HealthReporter) -> Self {
+        self.add_service(reporter.service())
+    }
+
+    /// Add the `grpc.reflection.v1.ServerReflection` service and its `v1alpha` twin, so tools
+    /// like `grpcurl` can discover the services of the server.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let reflection = ReflectionService::builder()
+    ///     .register_encoded_file_descriptor_set(helloworld::FILE_DESCRIPTOR_SET)
+    ///     .build()?;
+    ///
+    /// Server::builder()
+    ///     .add_service(GreeterServer::new(MyGreeter::default()))
+    ///     .add_reflection_service(reflection)
+    ///     .serve(addr)
+    ///     .await?;
+    /// ```
+    #[cfg(feature = "reflection")]
+    pub fn add_reflection_service(self, reflection: crate::reflection::ReflectionService) -> Self {
+        let v1alpha = reflection.v1alpha();
+        self.add_service(reflection).add_service(v1alpha)
+    }
+
+    /// Add the admin services enabled by the `health` and `reflection` features.
+    ///
+    /// See [`AdminServices`] for more details.
+    #[cfg(any(feature = "health", feature = "reflection"))]
+    pub fn add_admin_services(self, admin: &AdminServices) -> Self {
+        admin.add_to(self)
+    }
+
+    /// Add a service that only serves the `method` of its gRPC service.
+    ///
+    /// See [`Routes::add_method_service`] for more details.
+    pub fn add_method_service<S>(mut self, method: &str, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Response: axum::response::IntoResponse,
+        S::Future: Send + 'static,
+    {
+        self.routes = self.routes.add_method_service(method, svc);
+        self
+    }
+
+    /// Route the requests for services that were not added to this router to `dynamic`.
+    ///
+    /// See [`Routes::add_dynamic_routes`] for more details.
+    pub fn add_dynamic_routes(mut self, dynamic: DynamicRoutes) -> Self {
+        self.routes = self.routes.add_dynamic_routes(dynamic);
+        self
+    }
+
+    /// Answer the requests for unknown services with `svc` instead of an `Unimplemented` status.
+    ///
+    /// See [`Routes::fallback_service`] for more details.
+    pub fn fallback_service<S>(mut self, svc: S) -> Self
+    where
+        S: Service<Request<Body>, Error = Infallible> + Clone + Send + Sync + 'static,
+        S::Response: axum::response::IntoResponse,
+        S::Future: Send + 'static,
+    {
+        self.routes = self.routes.fallback_service(svc);
+        self
+    }
+
     /// Consume this [`Server`] creating a future that will execute the server
     /// on [tokio]'s default executor.
     ///
@@ -1080,8 +1950,21 @@ struct MakeSvc<S, IO> {
     concurrency_limit: Option<usize>,
     load_shed: bool,
     timeout: Option<Duration>,
+    method_timeouts: MethodTimeouts,
+    client_timeout_bounds: TimeoutBounds,
+    max_rpc_lifetime: Option<Duration>,
+    codec_executor: Option<CodecExecutor>,
+    message_validators: Option<MessageValidators>,
+    fair_write_quantum: Option<usize>,
+    message_size_limits: MessageSizeLimits,
+    metadata_limits: MetadataLimits,
     inner: S,
     trace_interceptor: Option<TraceInterceptor>,
+    #[cfg(feature = "grpc-web")]
+    grpc_web: Option<GrpcWebConfig>,
+    #[cfg(feature = "grpc-web")]
+    accept_http1: bool,
+    clock: SharedClock,
     _io: PhantomData<fn() -> IO>,
 }
 
@@ -1109,17 +1992,64 @@ where
         let concurrency_limit = self.concurrency_limit;
         let timeout = self.timeout;
         let trace_interceptor = self.trace_interceptor.clone();
+        let codec_executor = self.codec_executor.clone();
+        let message_validators = self.message_validators.clone();
+        let message_size_limits = self.message_size_limits;
+        let metadata_limits = self.metadata_limits;
+        let clock = self.clock.clone();
 
         let svc = ServiceBuilder::new()
             .layer(RecoverErrorLayer::new())
             .option_layer(self.load_shed.then_some(LoadShedLayer::new()))
             .option_layer(concurrency_limit.map(ConcurrencyLimitLayer::new))
-            .layer_fn(|s| GrpcTimeout::new(s, timeout))
+            .layer_fn(|s| {
+                GrpcTimeout::new(
+                    s,
+                    timeout,
+                    self.method_timeouts.clone(),
+                    self.client_timeout_bounds,
+                    clock.clone(),
+                )
+            })
             .service(svc);
 
+        #[cfg(feature = "grpc-web")]
+        let grpc_web = self.grpc_web.clone().map(|config| {
+            let accept_http1 = self.accept_http1;
+            tower::layer::layer_fn(move |s| GrpcWeb::new(s, config.clone(), accept_http1))
+        });
+        #[cfg(not(feature = "grpc-web"))]
+        let grpc_web = Option::<Identity>::None;
+
         let svc = ServiceBuilder::new()
             .layer(BoxCloneService::layer())
             .layer(ConnectInfoLayer::new(conn_info.clone()))
+            .option_layer(grpc_web)
+            .layer_fn(|s| MaxRpcLifetime::new(s, self.max_rpc_lifetime, clock.clone()))
+            .layer_fn(Cancellation::new)
+            .option_layer(codec_executor.map(|executor| {
+                MapRequestLayer::new(move |mut req: Request<Body>| {
+                    req.extensions_mut().insert(executor.clone());
+                    req
+                })
+            }))
+            .option_layer(message_validators.map(|validators| {
+                MapRequestLayer::new(move |mut req: Request<Body>| {
+                    req.extensions_mut().insert(validators.clone());
+                    req
+                })
+            }))
+            .layer(MapRequestLayer::new(move |mut req: Request<Body>| {
+                req.extensions_mut().insert(message_size_limits);
+                req.extensions_mut().insert(metadata_limits);
+                req
+            }))
+            .option_layer(self.fair_write_quantum.map(|quantum| {
+                ServiceBuilder::new()
+                    .map_response(|res: Response<FairWriteBody<Body>>| res.map(Body::new))
+                    .layer(FairWriteLayer::server(quantum))
+                    .map_request(|req: Request<FairWriteBody<Body>>| req.map(Body::new))
+            }))
             .service(Svc {
                 inner: svc,
                 trace_interceptor,
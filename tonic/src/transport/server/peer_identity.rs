@@ -0,0 +1,323 @@
+//! Middleware that verifies the identity of mTLS clients.
+
+use crate::{
+    body::Body,
+    transport::{service::x509::subject_alt_names, CertificateDer, TlsInfo},
+    Status,
+};
+use bytes::Bytes;
+use http::{Request, Response};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const SPIFFE_SCHEME: &str = "spiffe://";
+
+/// The identity of a client, read from the certificate it presented during the TLS handshake.
+///
+/// It is added to the request extensions by [`VerifyPeerIdentity`], so handlers and interceptors
+/// can read it with [`Request::extensions`](crate::Request::extensions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    spiffe_id: Option<String>,
+    dns_names: Vec<String>,
+    uris: Vec<String>,
+    certificate: CertificateDer<'static>,
+}
+
+impl PeerIdentity {
+    /// Reads the identity of the end-entity `certificate`, or returns `None` if it is malformed.
+    fn new(certificate: CertificateDer<'static>) -> Option<Self> {
+        let names = subject_alt_names(&certificate)?;
+        let to_strings = |names: Vec<&[u8]>| {
+            names
+                .into_iter()
+                .map(|name| std::str::from_utf8(name).map(str::to_owned))
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        };
+
+        let dns_names = to_strings(names.dns_names)?;
+        let uris = to_strings(names.uris)?;
+        let spiffe_id = uris
+            .iter()
+            .find(|uri| uri.starts_with(SPIFFE_SCHEME))
+            .cloned();
+
+        Some(Self {
+            spiffe_id,
+            dns_names,
+            uris,
+            certificate,
+        })
+    }
+
+    /// The SPIFFE ID of the client, i.e. its URI subject alternative name with the `spiffe`
+    /// scheme.
+    pub fn spiffe_id(&self) -> Option<&str> {
+        self.spiffe_id.as_deref()
+    }
+
+    /// The trust domain of the SPIFFE ID of the client, e.g. `example.org` for
+    /// `spiffe://example.org/ns/default/sa/client`.
+    pub fn trust_domain(&self) -> Option<&str> {
+        let id = self.spiffe_id.as_deref()?.strip_prefix(SPIFFE_SCHEME)?;
+        Some(id.split('/').next().unwrap_or(id))
+    }
+
+    /// The DNS subject alternative names of the client certificate.
+    pub fn dns_names(&self) -> &[String] {
+        &self.dns_names
+    }
+
+    /// The URI subject alternative names of the client certificate.
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    /// The end-entity certificate presented by the client.
+    pub fn certificate(&self) -> &CertificateDer<'static> {
+        &self.certificate
+    }
+}
+
+/// The identities allowed by [`VerifyPeerIdentityLayer`].
+///
+/// A client is allowed if its identity matches any of the rules. A policy without rules allows
+/// every client that presented a certificate.
+#[derive(Debug, Clone, Default)]
+pub struct PeerIdentityPolicy {
+    spiffe_ids: Vec<String>,
+    trust_domains: Vec<String>,
+    dns_names: Vec<String>,
+}
+
+impl PeerIdentityPolicy {
+    /// Create a policy that allows every client that presented a certificate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow clients with the SPIFFE ID `spiffe_id`.
+    pub fn allow_spiffe_id(mut self, spiffe_id: impl Into<String>) -> Self {
+        self.spiffe_ids.push(spiffe_id.into());
+        self
+    }
+
+    /// Allow clients with any SPIFFE ID of the trust domain `trust_domain`, e.g. `example.org`.
+    pub fn allow_trust_domain(mut self, trust_domain: impl Into<String>) -> Self {
+        self.trust_domains.push(trust_domain.into());
+        self
+    }
+
+    /// Allow clients with the DNS subject alternative name `dns_name`, ignoring ASCII case.
+    pub fn allow_dns_name(mut self, dns_name: impl Into<String>) -> Self {
+        self.dns_names.push(dns_name.into());
+        self
+    }
+
+    fn allows(&self, identity: &PeerIdentity) -> bool {
+        if self.spiffe_ids.is_empty() && self.trust_domains.is_empty() && self.dns_names.is_empty()
+        {
+            return true;
+        }
+
+        identity
+            .spiffe_id()
+            .is_some_and(|id| self.spiffe_ids.iter().any(|allowed| allowed == id))
+            || identity
+                .trust_domain()
+                .is_some_and(|domain| self.trust_domains.iter().any(|allowed| allowed == domain))
+            || identity.dns_names().iter().any(|name| {
+                self.dns_names
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// The identity of the client of `req`, or a status to reject the request with.
+    fn verify<B>(&self, req: &Request<B>) -> Result<PeerIdentity, Status> {
+        let certificate = req
+            .extensions()
+            .get::<TlsInfo>()
+            .and_then(TlsInfo::peer_certs)
+            .and_then(|certs| certs.first().cloned())
+            .ok_or_else(|| Status::unauthenticated("client certificate required"))?;
+
+        let identity = PeerIdentity::new(certificate)
+            .ok_or_else(|| Status::unauthenticated("malformed client certificate"))?;
+
+        if self.allows(&identity) {
+            Ok(identity)
+        } else {
+            tracing::debug!(?identity, "client identity rejected by policy");
+            Err(Status::permission_denied("client identity is not allowed"))
+        }
+    }
+}
+
+/// A [`Layer`] that verifies the certificate of mTLS clients against a [`PeerIdentityPolicy`]
+/// and adds their [`PeerIdentity`] to the request extensions.
+///
+/// Requests of clients that presented no certificate are rejected with `Unauthenticated`, and
+/// those of clients not allowed by the policy with `PermissionDenied`. The certificate itself
+/// is verified during the TLS handshake, so the server must be configured with a
+/// [client CA](super::ServerTlsConfig::client_ca_root).
+///
+/// # Example
+///
+/// ```
+/// # use tonic::transport::{server::{PeerIdentityPolicy, VerifyPeerIdentityLayer}, Server};
+/// let policy = PeerIdentityPolicy::new().allow_trust_domain("example.org");
+/// let builder = Server::builder().layer(VerifyPeerIdentityLayer::new(policy));
+/// ```
+#[derive(Debug, Clone)]
+pub struct VerifyPeerIdentityLayer {
+    policy: PeerIdentityPolicy,
+}
+
+impl VerifyPeerIdentityLayer {
+    /// Create a layer that allows the clients allowed by `policy`.
+    pub fn new(policy: PeerIdentityPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for VerifyPeerIdentityLayer {
+    type Service = VerifyPeerIdentity<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VerifyPeerIdentity {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// Middleware that verifies the identity of mTLS clients.
+///
+/// See [`VerifyPeerIdentityLayer`] for more details.
+#[derive(Debug, Clone)]
+pub struct VerifyPeerIdentity<S> {
+    inner: S,
+    policy: PeerIdentityPolicy,
+}
+
+impl<S> VerifyPeerIdentity<S> {
+    /// Create a new [`VerifyPeerIdentity`].
+    pub fn new(inner: S, policy: PeerIdentityPolicy) -> Self {
+        VerifyPeerIdentityLayer::new(policy).layer(inner)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for VerifyPeerIdentity<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<crate::BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        match self.policy.verify(&req) {
+            Ok(identity) => {
+                req.extensions_mut().insert(identity);
+                ResponseFuture {
+                    kind: Kind::Future(self.inner.call(req)),
+                }
+            }
+            Err(status) => ResponseFuture {
+                kind: Kind::Status(Some(status)),
+            },
+        }
+    }
+}
+
+/// Response future for [`VerifyPeerIdentity`].
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    kind: Kind<F>,
+}
+
+#[pin_project(project = KindProj)]
+#[derive(Debug)]
+enum Kind<F> {
+    Future(#[pin] F),
+    Status(Option<Status>),
+}
+
+impl<F, E, B> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<crate::BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::Future(future) => {
+                let response = ready!(future.poll(cx))?;
+                Poll::Ready(Ok(response.map(Body::new)))
+            }
+            KindProj::Status(status) => Poll::Ready(Ok(status.take().unwrap().into_http())),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tls-ring"))]
+mod tests {
+    use super::*;
+    use crate::transport::{service::tls::convert_certificate_to_pki_types, Certificate};
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/data/tls");
+
+    fn identity(name: &str) -> PeerIdentity {
+        let pem = std::fs::read(format!("{DATA}/{name}.pem")).unwrap();
+        let cert = convert_certificate_to_pki_types(&Certificate::from_pem(pem))
+            .unwrap()
+            .remove(0);
+        PeerIdentity::new(cert).unwrap()
+    }
+
+    #[test]
+    fn reads_spiffe_id() {
+        let identity = identity("spiffe");
+        assert_eq!(
+            identity.spiffe_id(),
+            Some("spiffe://example.org/ns/default/sa/server")
+        );
+        assert_eq!(identity.trust_domain(), Some("example.org"));
+    }
+
+    #[test]
+    fn allows_matching_identities() {
+        let spiffe = identity("spiffe");
+        let other = identity("other");
+
+        assert!(PeerIdentityPolicy::new().allows(&spiffe));
+
+        let policy = PeerIdentityPolicy::new().allow_trust_domain("example.org");
+        assert!(policy.allows(&spiffe));
+        assert!(!policy.allows(&other));
+
+        let policy = PeerIdentityPolicy::new()
+            .allow_spiffe_id("spiffe://example.org/ns/default/sa/client")
+            .allow_dns_name("OTHER.test");
+        assert!(!policy.allows(&spiffe));
+        assert!(policy.allows(&other));
+    }
+}
@@ -0,0 +1,450 @@
+use std::{
+    future::poll_fn,
+    io::{self, IoSlice},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use pin_project::pin_project;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    task::JoinSet,
+};
+use tokio_stream::Stream;
+
+use super::{Connected, ConnectionConfig, TcpConnectInfo};
+
+const DEFAULT_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// The longest v1 header, including its `\r\n`.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LEN: usize = 16;
+
+/// An incoming stream of connections that start with a
+/// [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt) header, e.g.
+/// the connections forwarded by a L4 load balancer.
+///
+/// Both the v1 (text) and v2 (binary) headers are accepted. The [`TcpConnectInfo`] of the
+/// yielded connections holds the addresses of the header, so
+/// [`Request::remote_addr`](crate::Request::remote_addr) is the address of the client rather
+/// than the one of the load balancer. The addresses of the connection itself are kept when the
+/// header does not carry TCP addresses, e.g. for the health checks of the load balancer.
+///
+/// Connections without a valid header within the [header
+/// timeout](Self::with_header_timeout) are closed, so the PROXY protocol should only be accepted
+/// on the listeners that are reachable from the load balancer alone.
+///
+/// # Example
+///
+/// ```
+/// # use tonic::transport::server::{ProxyProtocolIncoming, TcpIncoming};
+/// # fn f() -> Result<(), Box<dyn std::error::Error>> {
+/// let incoming = ProxyProtocolIncoming::new(TcpIncoming::bind("127.0.0.1:0".parse()?)?);
+/// # Ok(())
+/// # }
+/// ```
+#[pin_project]
+#[derive(Debug)]
+pub struct ProxyProtocolIncoming<S, IO> {
+    #[pin]
+    inner: S,
+    header_timeout: Duration,
+    tasks: JoinSet<io::Result<ProxiedStream<IO>>>,
+    done: bool,
+}
+
+impl<S, IO, IE> ProxyProtocolIncoming<S, IO>
+where
+    S: Stream<Item = Result<IO, IE>>,
+{
+    /// Reads the PROXY protocol header of the connections of `incoming`.
+    pub fn new(incoming: S) -> Self {
+        Self {
+            inner: incoming,
+            header_timeout: DEFAULT_HEADER_TIMEOUT,
+            tasks: JoinSet::new(),
+            done: false,
+        }
+    }
+
+    /// Sets how long to wait for the header of a connection before closing it.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn with_header_timeout(self, header_timeout: Duration) -> Self {
+        Self {
+            header_timeout,
+            ..self
+        }
+    }
+}
+
+impl<S, IO, IE> Stream for ProxyProtocolIncoming<S, IO>
+where
+    S: Stream<Item = Result<IO, IE>>,
+    IO: AsyncRead + AsyncWrite + Connected<ConnectInfo = TcpConnectInfo> + Unpin + Send + 'static,
+{
+    type Item = Result<ProxiedStream<IO>, IE>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        // The headers are read concurrently, so slow clients do not hold back the others.
+        while !*this.done {
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(io))) => {
+                    this.tasks
+                        .spawn(ProxiedStream::accept(io, *this.header_timeout));
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        loop {
+            match this.tasks.poll_join_next(cx) {
+                Poll::Ready(Some(Ok(Ok(io)))) => return Poll::Ready(Some(Ok(io))),
+                Poll::Ready(Some(Ok(Err(e)))) => {
+                    tracing::debug!(error = %e, "PROXY protocol header error");
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    tracing::debug!(error = %e, "PROXY protocol header task error");
+                }
+                Poll::Ready(None) if *this.done => return Poll::Ready(None),
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// A connection yielded by [`ProxyProtocolIncoming`], past its PROXY protocol header.
+#[derive(Debug)]
+pub struct ProxiedStream<IO> {
+    io: IO,
+    /// The bytes read past the header.
+    buffered: Bytes,
+    connect_info: TcpConnectInfo,
+}
+
+impl<IO> ProxiedStream<IO>
+where
+    IO: AsyncRead + Connected<ConnectInfo = TcpConnectInfo> + Unpin,
+{
+    async fn accept(mut io: IO, header_timeout: Duration) -> io::Result<Self> {
+        let mut buf = BytesMut::new();
+        let read_header = async {
+            loop {
+                if let Some(header) = parse_header(&buf)? {
+                    return Ok(header);
+                }
+
+                let mut chunk = [0; 512];
+                let mut read_buf = ReadBuf::new(&mut chunk);
+                poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut read_buf)).await?;
+                if read_buf.filled().is_empty() {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                }
+                buf.extend_from_slice(read_buf.filled());
+            }
+        };
+
+        let (addrs, len) = tokio::time::timeout(header_timeout, read_header)
+            .await
+            .map_err(|_| {
+                io::Error::new(io::ErrorKind::TimedOut, "PROXY protocol header timeout")
+            })??;
+
+        let connect_info = match addrs {
+            Some((source, destination)) => TcpConnectInfo {
+                local_addr: Some(destination),
+                remote_addr: Some(source),
+            },
+            None => io.connect_info(),
+        };
+        buf.advance(len);
+
+        Ok(Self {
+            io,
+            buffered: buf.freeze(),
+            connect_info,
+        })
+    }
+}
+
+impl<IO> ProxiedStream<IO> {
+    /// Get a reference to the underlying connection.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+}
+
+impl<IO> Connected for ProxiedStream<IO>
+where
+    IO: Connected,
+{
+    type ConnectInfo = TcpConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.connect_info.clone()
+    }
+
+    fn connection_config(&self) -> ConnectionConfig {
+        self.io.connection_config()
+    }
+}
+
+impl<IO> AsyncRead for ProxiedStream<IO>
+where
+    IO: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.buffered.has_remaining() {
+            let len = self.buffered.len().min(buf.remaining());
+            buf.put_slice(&self.buffered.split_to(len));
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<IO> AsyncWrite for ProxiedStream<IO>
+where
+    IO: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+/// The source and destination addresses of a header, if it carries TCP addresses.
+type Addresses = Option<(SocketAddr, SocketAddr)>;
+
+/// Parses the header at the start of `buf`, returning its addresses and length, or `None` if
+/// more bytes are needed.
+fn parse_header(buf: &[u8]) -> io::Result<Option<(Addresses, usize)>> {
+    if starts_with(buf, V2_SIGNATURE) {
+        parse_v2(buf)
+    } else if starts_with(buf, V1_PREFIX) {
+        parse_v1(buf)
+    } else {
+        Err(invalid("missing PROXY protocol header"))
+    }
+}
+
+/// Returns `true` if `buf` starts with `prefix`, or could once more bytes are read.
+fn starts_with(buf: &[u8], prefix: &[u8]) -> bool {
+    let len = buf.len().min(prefix.len());
+    buf[..len] == prefix[..len]
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Option<(Addresses, usize)>> {
+    let Some(end) = buf.windows(2).position(|w| w == b"\r\n") else {
+        if buf.len() >= V1_MAX_LEN {
+            return Err(invalid("PROXY protocol v1 header too long"));
+        }
+        return Ok(None);
+    };
+    if end + 2 > V1_MAX_LEN {
+        return Err(invalid("PROXY protocol v1 header too long"));
+    }
+
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..end])
+        .map_err(|_| invalid("invalid PROXY protocol v1 header"))?;
+    let mut parts = line.split(' ');
+
+    let addrs = match parts.next() {
+        Some("UNKNOWN") => None,
+        Some(family @ ("TCP4" | "TCP6")) => {
+            let mut next = || {
+                parts
+                    .next()
+                    .ok_or_else(|| invalid("invalid PROXY protocol v1 header"))
+            };
+            let (source, destination) = (next()?, next()?);
+            let (source_port, destination_port) = (next()?, next()?);
+
+            let addr = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+                let ip: IpAddr = ip
+                    .parse()
+                    .map_err(|_| invalid("invalid PROXY protocol v1 address"))?;
+                if ip.is_ipv4() != (family == "TCP4") {
+                    return Err(invalid("invalid PROXY protocol v1 address"));
+                }
+                let port = port
+                    .parse()
+                    .map_err(|_| invalid("invalid PROXY protocol v1 port"))?;
+                Ok(SocketAddr::new(ip, port))
+            };
+            Some((
+                addr(source, source_port)?,
+                addr(destination, destination_port)?,
+            ))
+        }
+        _ => return Err(invalid("invalid PROXY protocol v1 header")),
+    };
+
+    Ok(Some((addrs, end + 2)))
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Option<(Addresses, usize)>> {
+    if buf.len() < V2_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let version_command = buf[12];
+    let family = buf[13];
+    let len = V2_HEADER_LEN + usize::from(u16::from_be_bytes([buf[14], buf[15]]));
+
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    if buf.len() < len {
+        return Ok(None);
+    }
+
+    let addrs = &buf[V2_HEADER_LEN..len];
+    let addrs = match (version_command & 0xf, family >> 4) {
+        // LOCAL, e.g. the health checks of the proxy itself.
+        (0, _) => None,
+        // PROXY over IPv4
+        (1, 1) if addrs.len() >= 12 => {
+            let ip = |at: usize| {
+                IpAddr::V4(Ipv4Addr::new(
+                    addrs[at],
+                    addrs[at + 1],
+                    addrs[at + 2],
+                    addrs[at + 3],
+                ))
+            };
+            let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+            Some((
+                SocketAddr::new(ip(0), port(8)),
+                SocketAddr::new(ip(4), port(10)),
+            ))
+        }
+        // PROXY over IPv6
+        (1, 2) if addrs.len() >= 36 => {
+            let ip = |at: usize| {
+                let octets: [u8; 16] = addrs[at..at + 16].try_into().unwrap();
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+            Some((
+                SocketAddr::new(ip(0), port(32)),
+                SocketAddr::new(ip(16), port(34)),
+            ))
+        }
+        // PROXY over an unspecified family or a unix socket.
+        (1, 0 | 3) => None,
+        _ => return Err(invalid("invalid PROXY protocol v2 header")),
+    };
+
+    Ok(Some((addrs, len)))
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_v1_headers() {
+        let header = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nPRI";
+        assert_eq!(
+            parse_header(header).unwrap(),
+            Some((
+                Some((addr("192.0.2.1:56324"), addr("198.51.100.1:443"))),
+                header.len() - 3
+            ))
+        );
+
+        let header = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n";
+        assert_eq!(
+            parse_header(header).unwrap(),
+            Some((
+                Some((addr("[2001:db8::1]:56324"), addr("[2001:db8::2]:443"))),
+                header.len()
+            ))
+        );
+
+        assert_eq!(
+            parse_header(b"PROXY UNKNOWN\r\n").unwrap(),
+            Some((None, 15))
+        );
+        assert_eq!(parse_header(b"PROXY TCP4 192.0").unwrap(), None);
+        assert_eq!(parse_header(b"PRO").unwrap(), None);
+
+        assert!(parse_header(b"PROXY TCP4 2001:db8::1 192.0.2.1 1 2\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 192.0.2.1\r\n").is_err());
+        assert!(parse_header(&[b'P'; V1_MAX_LEN]).is_err());
+        assert!(parse_header(b"PRI * HTTP/2.0\r\n").is_err());
+    }
+
+    #[test]
+    fn parses_v2_headers() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1]);
+        header.extend_from_slice(&56324u16.to_be_bytes());
+        header.extend_from_slice(&443u16.to_be_bytes());
+
+        assert_eq!(parse_header(&header[..20]).unwrap(), None);
+        assert_eq!(
+            parse_header(&header).unwrap(),
+            Some((
+                Some((addr("192.0.2.1:56324"), addr("198.51.100.1:443"))),
+                28
+            ))
+        );
+
+        // LOCAL, with a TLV that is skipped.
+        let mut local = V2_SIGNATURE.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0, 4, 0x04, 0, 1, 0]);
+        assert_eq!(parse_header(&local).unwrap(), Some((None, 20)));
+
+        let mut v1 = V2_SIGNATURE.to_vec();
+        v1.extend_from_slice(&[0x11, 0x11, 0, 0]);
+        assert!(parse_header(&v1).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Cancellation of calls whose client went away.
+
+use crate::{
+    body::Body,
+    transport::server::cancellation::{CancelOnDrop, CancellationToken},
+};
+use http::{Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower_service::Service;
+
+/// Gives every call a [`CancellationToken`] that is cancelled if the call is dropped before its
+/// response was sent.
+#[derive(Debug, Clone)]
+pub(crate) struct Cancellation<S> {
+    inner: S,
+}
+
+impl<S> Cancellation<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<Request<Body>> for Cancellation<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let token = CancellationToken::new();
+        req.extensions_mut().insert(token.clone());
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            guard: Some(CancelOnDrop::new(token)),
+        }
+    }
+}
+
+#[pin_project]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    guard: Option<CancelOnDrop>,
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+
+        let mut guard = this.guard.take().expect("polled after completion");
+        if http_body::Body::is_end_stream(response.body()) {
+            guard.disarm();
+            return Poll::Ready(Ok(response));
+        }
+
+        Poll::Ready(Ok(
+            response.map(|inner| Body::new(CancellableBody { inner, guard }))
+        ))
+    }
+}
+
+/// A response body that cancels its call if it is dropped before it was fully sent.
+#[pin_project]
+struct CancellableBody {
+    #[pin]
+    inner: Body,
+    guard: CancelOnDrop,
+}
+
+impl http_body::Body for CancellableBody {
+    type Data = <Body as http_body::Body>::Data;
+    type Error = <Body as http_body::Body>::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+
+        // The trailers end the response, the body may not be polled again once they are sent.
+        match &frame {
+            Some(Ok(frame)) if frame.is_trailers() => this.guard.disarm(),
+            None => this.guard.disarm(),
+            _ => {}
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
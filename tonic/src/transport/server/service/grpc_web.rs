@@ -0,0 +1,430 @@
+//! Translation of gRPC-Web requests into gRPC, and of their responses back.
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use base64::Engine as _;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Version};
+use http_body::{Frame, SizeHint};
+use pin_project::pin_project;
+use tower_service::Service;
+use tracing::debug;
+
+use crate::{body::Body, metadata::GRPC_CONTENT_TYPE, transport::server::GrpcWebConfig, Status};
+
+const GRPC_WEB: &str = "application/grpc-web";
+const GRPC_WEB_PROTO: &str = "application/grpc-web+proto";
+const GRPC_WEB_TEXT: &str = "application/grpc-web-text";
+const GRPC_WEB_TEXT_PROTO: &str = "application/grpc-web-text+proto";
+
+const BUFFER_SIZE: usize = 8 * 1024;
+const FRAME_HEADER_SIZE: usize = 5;
+
+// 8th (MSB) bit of the 1st gRPC frame byte
+// denotes an uncompressed trailer (as part of the body)
+const GRPC_WEB_TRAILERS_BIT: u8 = 0b10000000;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Encoding {
+    Base64,
+    None,
+}
+
+impl Encoding {
+    fn from_header(value: Option<&HeaderValue>) -> Self {
+        match value.and_then(|val| val.to_str().ok()) {
+            Some(GRPC_WEB_TEXT_PROTO | GRPC_WEB_TEXT) => Encoding::Base64,
+            _ => Encoding::None,
+        }
+    }
+
+    fn to_content_type(self) -> &'static str {
+        match self {
+            Encoding::Base64 => GRPC_WEB_TEXT_PROTO,
+            Encoding::None => GRPC_WEB_PROTO,
+        }
+    }
+}
+
+fn is_grpc_web(headers: &HeaderMap) -> bool {
+    matches!(
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok()),
+        Some(GRPC_WEB | GRPC_WEB_PROTO | GRPC_WEB_TEXT | GRPC_WEB_TEXT_PROTO)
+    )
+}
+
+fn is_preflight<B>(req: &Request<B>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(header::ORIGIN)
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// Serves gRPC-Web requests and their CORS preflight requests.
+///
+/// Other requests are passed through if they use HTTP/2 or if the server accepts HTTP/1, and
+/// are rejected with `400 Bad Request` otherwise.
+#[derive(Debug, Clone)]
+pub(crate) struct GrpcWeb<S> {
+    inner: S,
+    config: GrpcWebConfig,
+    accept_http1: bool,
+}
+
+impl<S> GrpcWeb<S> {
+    pub(crate) fn new(inner: S, config: GrpcWebConfig, accept_http1: bool) -> Self {
+        Self {
+            inner,
+            config,
+            accept_http1,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for GrpcWeb<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        if is_preflight(&req) {
+            return ResponseFuture::immediate(self.config.preflight(req.headers()));
+        }
+
+        if !is_grpc_web(req.headers()) {
+            if req.version() == Version::HTTP_2 || self.accept_http1 {
+                return ResponseFuture {
+                    kind: Kind::Other(self.inner.call(req)),
+                };
+            }
+
+            debug!(content_type = ?req.headers().get(header::CONTENT_TYPE), "rejecting HTTP/1 request that is not gRPC-Web");
+            return ResponseFuture::immediate(status(StatusCode::BAD_REQUEST));
+        }
+
+        if req.method() != Method::POST {
+            return ResponseFuture::immediate(status(StatusCode::METHOD_NOT_ALLOWED));
+        }
+
+        let origin = req.headers().get(header::ORIGIN).cloned();
+        if let Some(origin) = &origin {
+            if !self.config.allows(origin) {
+                debug!(?origin, "rejecting gRPC-Web request from disallowed origin");
+                return ResponseFuture::immediate(status(StatusCode::FORBIDDEN));
+            }
+        }
+
+        let encoding = Encoding::from_header(req.headers().get(header::CONTENT_TYPE));
+        let accept = Encoding::from_header(req.headers().get(header::ACCEPT));
+
+        let headers = req.headers_mut();
+        headers.remove(header::CONTENT_LENGTH);
+        headers.insert(header::CONTENT_TYPE, GRPC_CONTENT_TYPE);
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+
+        let req = req.map(|body| Body::new(GrpcWebBody::new(body, Direction::Decode, encoding)));
+        ResponseFuture {
+            kind: Kind::GrpcWeb {
+                future: self.inner.call(req),
+                accept,
+                origin,
+                config: self.config.clone(),
+            },
+        }
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// Response future for [`GrpcWeb`].
+#[pin_project]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    kind: Kind<F>,
+}
+
+#[pin_project(project = KindProj)]
+enum Kind<F> {
+    GrpcWeb {
+        #[pin]
+        future: F,
+        accept: Encoding,
+        origin: Option<HeaderValue>,
+        config: GrpcWebConfig,
+    },
+    Other(#[pin] F),
+    Immediate(Option<Response<Body>>),
+}
+
+impl<F> ResponseFuture<F> {
+    fn immediate(response: Response<Body>) -> Self {
+        Self {
+            kind: Kind::Immediate(Some(response)),
+        }
+    }
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().kind.project() {
+            KindProj::GrpcWeb {
+                future,
+                accept,
+                origin,
+                config,
+            } => {
+                let accept = *accept;
+                let mut response = ready!(future.poll(cx))?
+                    .map(|body| Body::new(GrpcWebBody::new(body, Direction::Encode, accept)));
+
+                let headers = response.headers_mut();
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(accept.to_content_type()),
+                );
+                if let Some(origin) = origin.take() {
+                    config.cors_headers(origin, headers);
+                }
+
+                Poll::Ready(Ok(response))
+            }
+            KindProj::Other(future) => future.poll(cx),
+            KindProj::Immediate(response) => Poll::Ready(Ok(response.take().unwrap())),
+        }
+    }
+}
+
+impl<F> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Direction {
+    /// Decodes a gRPC-Web request body into gRPC.
+    Decode,
+    /// Encodes a gRPC response body into gRPC-Web, trailers included.
+    Encode,
+}
+
+#[pin_project]
+struct GrpcWebBody<B> {
+    #[pin]
+    inner: B,
+    buf: BytesMut,
+    direction: Direction,
+    encoding: Encoding,
+}
+
+impl<B> GrpcWebBody<B> {
+    fn new(inner: B, direction: Direction, encoding: Encoding) -> Self {
+        Self {
+            inner,
+            buf: BytesMut::with_capacity(match (direction, encoding) {
+                (Direction::Decode, Encoding::Base64) => BUFFER_SIZE,
+                _ => 0,
+            }),
+            direction,
+            encoding,
+        }
+    }
+
+    fn decode_chunk(self: Pin<&mut Self>) -> Result<Option<Bytes>, Status> {
+        let buf = self.project().buf;
+        if buf.len() < 4 {
+            return Ok(None);
+        }
+
+        // Split `buf` at the largest index that is multiple of 4, keeping the rest for the next
+        // attempt. Peers may encode each frame on its own, so padding can also end a chunk in
+        // the middle of `buf`.
+        let max_decodable = (buf.len() / 4) * 4;
+        let index = match buf.iter().position(|b| *b == b'=') {
+            Some(pad) => std::cmp::min(max_decodable, (pad / 4 + 1) * 4),
+            None => max_decodable,
+        };
+
+        crate::util::base64::STANDARD
+            .decode(buf.split_to(index))
+            .map(|decoded| Some(Bytes::from(decoded)))
+            .map_err(internal_error)
+    }
+}
+
+impl<B> GrpcWebBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: fmt::Display,
+{
+    fn poll_decode(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Status>>> {
+        if self.encoding == Encoding::None {
+            return self.project().inner.poll_frame(cx).map_err(internal_error);
+        }
+
+        loop {
+            if let Some(bytes) = self.as_mut().decode_chunk()? {
+                return Poll::Ready(Some(Ok(Frame::data(bytes))));
+            }
+
+            let this = self.as_mut().project();
+            match ready!(this.inner.poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => this.buf.put(data),
+                    Err(_) => {
+                        return Poll::Ready(Some(Err(internal_error(
+                            "malformed base64 request has unencoded trailers",
+                        ))))
+                    }
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(internal_error(e)))),
+                None if this.buf.has_remaining() => {
+                    return Poll::Ready(Some(Err(internal_error("malformed base64 request"))))
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn poll_encode(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Status>>> {
+        let this = self.project();
+
+        let mut data = match ready!(this.inner.poll_frame(cx)) {
+            Some(Ok(frame)) => match frame.into_data() {
+                Ok(data) => data,
+                Err(frame) => match frame.into_trailers() {
+                    Ok(trailers) => make_trailers_frame(trailers),
+                    Err(_) => {
+                        return Poll::Ready(Some(Err(internal_error("unexpected frame type"))))
+                    }
+                },
+            },
+            Some(Err(e)) => return Poll::Ready(Some(Err(internal_error(e)))),
+            None => return Poll::Ready(None),
+        };
+
+        if *this.encoding == Encoding::Base64 {
+            data = crate::util::base64::STANDARD.encode(data).into();
+        }
+
+        Poll::Ready(Some(Ok(Frame::data(data))))
+    }
+}
+
+impl<B> http_body::Body for GrpcWebBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: fmt::Display,
+{
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.direction {
+            Direction::Decode => self.poll_decode(cx),
+            Direction::Encode => self.poll_encode(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match (self.direction, self.encoding) {
+            (_, Encoding::None) | (Direction::Decode, _) => self.inner.size_hint(),
+            // Base64 changes the length of the body.
+            (Direction::Encode, Encoding::Base64) => SizeHint::default(),
+        }
+    }
+}
+
+fn internal_error(e: impl fmt::Display) -> Status {
+    Status::internal(format!("grpc-web: {e}"))
+}
+
+/// Encodes the trailers as a gRPC-Web trailers frame, i.e. an HTTP/1 header block.
+fn make_trailers_frame(trailers: HeaderMap) -> Bytes {
+    let trailers = trailers.iter().fold(Vec::new(), |mut acc, (key, value)| {
+        acc.put_slice(key.as_ref());
+        acc.push(b':');
+        acc.put_slice(value.as_bytes());
+        acc.put_slice(b"\r\n");
+        acc
+    });
+
+    let mut frame = BytesMut::with_capacity(trailers.len() + FRAME_HEADER_SIZE);
+    frame.put_u8(GRPC_WEB_TRAILERS_BIT);
+    frame.put_u32(trailers.len() as u32);
+    frame.put_slice(&trailers);
+    frame.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[tokio::test]
+    async fn decodes_separately_encoded_base64_frames() {
+        let frames: [&[u8]; 2] = [b"\0\0\0\0\x02ab", b"\0\0\0\0\x01c"];
+        let body = frames
+            .iter()
+            .map(|frame| crate::util::base64::STANDARD.encode(frame))
+            .collect::<String>();
+
+        let body = GrpcWebBody::new(body, Direction::Decode, Encoding::Base64);
+        let decoded = body.collect().await.unwrap().to_bytes();
+        assert_eq!(decoded, [frames[0], frames[1]].concat());
+    }
+
+    #[tokio::test]
+    async fn encodes_trailers_in_body() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(Status::GRPC_STATUS, 0.into());
+        let body = http_body_util::StreamBody::new(tokio_stream::iter([
+            Ok::<_, Status>(Frame::data(Bytes::from_static(b"\0\0\0\0\x02ab"))),
+            Ok(Frame::trailers(trailers)),
+        ]));
+
+        let body = GrpcWebBody::new(body, Direction::Encode, Encoding::None);
+        let encoded = body.collect().await.unwrap().to_bytes();
+        assert_eq!(
+            encoded,
+            &b"\0\0\0\0\x02ab\x80\0\0\0\x0fgrpc-status:0\r\n"[..]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::transport::server::Connected;
+use crate::transport::server::{Connected, ConnectionConfig};
 use std::io;
 use std::io::IoSlice;
 use std::pin::Pin;
@@ -60,16 +60,7 @@ where
     }
 
     fn call(&mut self, mut req: http::Request<ReqBody>) -> Self::Future {
-        match self.connect_info.clone() {
-            ServerIoConnectInfo::Io(inner) => {
-                req.extensions_mut().insert(inner);
-            }
-            #[cfg(feature = "_tls-any")]
-            ServerIoConnectInfo::TlsIo(inner) => {
-                req.extensions_mut().insert(inner.get_ref().clone());
-                req.extensions_mut().insert(inner);
-            }
-        }
+        self.connect_info.insert_into(req.extensions_mut());
         self.inner.call(req)
     }
 }
@@ -86,6 +77,23 @@ pub(crate) enum ServerIoConnectInfo<IO: Connected> {
     TlsIo(<TlsStream<IO> as Connected>::ConnectInfo),
 }
 
+impl<IO: Connected> ServerIoConnectInfo<IO> {
+    /// Adds the connection info to the extensions of a request.
+    pub(crate) fn insert_into(&self, extensions: &mut http::Extensions) {
+        match self.clone() {
+            Self::Io(inner) => {
+                extensions.insert(inner);
+            }
+            #[cfg(feature = "_tls-any")]
+            Self::TlsIo(inner) => {
+                extensions.insert(inner.get_ref().clone());
+                extensions.insert(inner.tls_info().clone());
+                extensions.insert(inner);
+            }
+        }
+    }
+}
+
 impl<IO: Connected> Clone for ServerIoConnectInfo<IO> {
     fn clone(&self) -> Self {
         match self {
@@ -116,6 +124,17 @@ impl<IO> ServerIo<IO> {
             Self::TlsIo(io) => ServerIoConnectInfo::TlsIo(io.connect_info()),
         }
     }
+
+    pub(in crate::transport) fn connection_config(&self) -> ConnectionConfig
+    where
+        IO: Connected,
+    {
+        match self {
+            Self::Io(io) => io.connection_config(),
+            #[cfg(feature = "_tls-any")]
+            Self::TlsIo(io) => io.connection_config(),
+        }
+    }
 }
 
 impl<IO> AsyncRead for ServerIo<IO>
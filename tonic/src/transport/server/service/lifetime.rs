@@ -0,0 +1,173 @@
+//! Enforcement of the maximum lifetime of a call.
+
+use crate::{
+    body::Body,
+    time::{Clock, SharedClock, Sleep},
+    Code, Status,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use http::{Request, Response};
+use http_body::Frame;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tower_service::Service;
+
+const RETRY_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.RetryInfo";
+
+/// Ends calls that are still running once the maximum call lifetime elapsed.
+#[derive(Debug, Clone)]
+pub(crate) struct MaxRpcLifetime<S> {
+    inner: S,
+    lifetime: Option<Duration>,
+    clock: SharedClock,
+}
+
+impl<S> MaxRpcLifetime<S> {
+    pub(crate) fn new(inner: S, lifetime: Option<Duration>, clock: SharedClock) -> Self {
+        Self {
+            inner,
+            lifetime,
+            clock,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for MaxRpcLifetime<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let deadline = self.lifetime.map(|lifetime| self.clock.now() + lifetime);
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            sleep: deadline.map(|deadline| self.clock.sleep_until(deadline)),
+        }
+    }
+}
+
+#[pin_project]
+pub(crate) struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    sleep: Option<Sleep>,
+}
+
+impl<F, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(result) = this.inner.poll(cx) {
+            let sleep = this.sleep.take();
+            return Poll::Ready(result.map(|res| {
+                res.map(|body| match sleep {
+                    Some(sleep) => Body::new(LimitedBody { inner: body, sleep }),
+                    None => body,
+                })
+            }));
+        }
+
+        if let Some(sleep) = this.sleep {
+            ready!(sleep.as_mut().poll(cx));
+            return Poll::Ready(Ok(lifetime_exceeded().into_http()));
+        }
+
+        Poll::Pending
+    }
+}
+
+/// A response body that is cut off with [`lifetime_exceeded`] trailers once the sleep elapsed.
+#[pin_project]
+struct LimitedBody {
+    #[pin]
+    inner: Body,
+    sleep: Sleep,
+}
+
+impl http_body::Body for LimitedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+
+        if let Poll::Ready(frame) = this.inner.as_mut().poll_frame(cx) {
+            return Poll::Ready(frame);
+        }
+
+        ready!(this.sleep.as_mut().poll(cx));
+
+        // Dropping the inner body releases whatever the handler holds on to for the stream.
+        this.inner.set(Body::empty());
+        *this.sleep = Box::pin(std::future::pending());
+
+        let trailers = lifetime_exceeded().to_header_map().unwrap_or_default();
+        Poll::Ready(Some(Ok(Frame::trailers(trailers))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// The status calls are ended with once they exceeded their maximum lifetime.
+///
+/// The call is not at fault, so it is ended with `UNAVAILABLE` and a `google.rpc.RetryInfo`
+/// detail asking to retry right away.
+fn lifetime_exceeded() -> Status {
+    let message = "maximum RPC lifetime exceeded";
+
+    // google.rpc.RetryInfo { retry_delay: google.protobuf.Duration {} }
+    let retry_info = [0x0a, 0x00];
+
+    let mut any = BytesMut::new();
+    put_len_delimited(&mut any, 1, RETRY_INFO_TYPE_URL.as_bytes());
+    put_len_delimited(&mut any, 2, &retry_info);
+
+    // google.rpc.Status { code, message, details: [any] }
+    let mut details = BytesMut::new();
+    details.put_u8(0x08);
+    details.put_u8(Code::Unavailable as u8);
+    put_len_delimited(&mut details, 2, message.as_bytes());
+    put_len_delimited(&mut details, 3, &any);
+
+    Status::with_details(Code::Unavailable, message, details.freeze())
+}
+
+fn put_len_delimited(dst: &mut BytesMut, field: u8, bytes: &[u8]) {
+    dst.put_u8(field << 3 | 2);
+
+    let mut len = bytes.len();
+    while len >= 0x80 {
+        dst.put_u8((len as u8) | 0x80);
+        len >>= 7;
+    }
+    dst.put_u8(len as u8);
+
+    dst.put_slice(bytes);
+}
@@ -1,6 +1,19 @@
+mod cancellation;
+pub(crate) use self::cancellation::Cancellation;
+
 mod io;
 pub(crate) use self::io::{ConnectInfoLayer, ServerIo};
 
+#[cfg(feature = "grpc-web")]
+mod grpc_web;
+#[cfg(feature = "grpc-web")]
+pub(crate) use self::grpc_web::GrpcWeb;
+
+mod lifetime;
+pub(crate) use self::lifetime::MaxRpcLifetime;
+
+#[cfg(feature = "_tls-any")]
+mod sni;
 #[cfg(feature = "_tls-any")]
 mod tls;
 #[cfg(feature = "_tls-any")]
@@ -0,0 +1,63 @@
+//! Selection of the server identity by the server name requested by clients.
+
+use crate::transport::{
+    service::identity::{certified_key, IdentitySource, ReloadingIdentity},
+    Identity,
+};
+use std::{collections::HashMap, fmt, sync::Arc};
+use tokio_rustls::rustls::{
+    crypto::CryptoProvider,
+    pki_types::DnsName,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+
+/// Resolves the identity of every TLS handshake to the one configured for the SNI sent by the
+/// client, falling back to the default identity.
+pub(crate) struct SniResolver {
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<ReloadingIdentity>,
+}
+
+impl SniResolver {
+    /// Loads all identities, failing if any of them or of their server names is not valid.
+    pub(crate) fn new(
+        default: Option<IdentitySource>,
+        sni_identities: &[(String, Identity)],
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, crate::BoxError> {
+        let mut by_name = HashMap::with_capacity(sni_identities.len());
+        for (name, identity) in sni_identities {
+            let name = DnsName::try_from(name.to_ascii_lowercase())
+                .map_err(|_| format!("invalid server name: {name}"))?;
+            by_name.insert(
+                name.as_ref().to_owned(),
+                certified_key(identity, &provider)?,
+            );
+        }
+
+        let default = default
+            .map(|source| ReloadingIdentity::new(source, provider))
+            .transpose()?;
+
+        Ok(Self { by_name, default })
+    }
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        client_hello
+            .server_name()
+            .and_then(|name| self.by_name.get(&name.to_ascii_lowercase()))
+            .cloned()
+            .or_else(|| self.default.as_ref().map(ReloadingIdentity::current))
+    }
+}
+
+impl fmt::Debug for SniResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SniResolver")
+            .field("server_names", &self.by_name.keys())
+            .finish_non_exhaustive()
+    }
+}
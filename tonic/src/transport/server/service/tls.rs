@@ -1,80 +1,183 @@
-use std::{fmt, sync::Arc, time::Duration};
+use std::{fmt, net::SocketAddr, sync::Arc, time::Duration};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time;
 use tokio_rustls::{
-    rustls::{server::WebPkiClientVerifier, RootCertStore, ServerConfig},
+    rustls::{
+        crypto::CryptoProvider,
+        server::{Acceptor, NoServerSessionStorage, ResolvesServerCert, WebPkiClientVerifier},
+        sign::{CertifiedKey, SingleCertAndKey},
+        HandshakeKind, RootCertStore, ServerConfig,
+    },
     server::TlsStream,
-    TlsAcceptor as RustlsAcceptor,
+    LazyConfigAcceptor, TlsAcceptor as RustlsAcceptor,
 };
 
+use super::sni::SniResolver;
+use crate::transport::server::tls::{ClientAuth, ClientAuthContext, ClientAuthPolicy};
 use crate::transport::{
+    service::identity::{IdentitySource, ReloadingIdentity},
     service::tls::{
         convert_certificate_to_pki_types, convert_identity_to_pki_types, TlsError, ALPN_H2,
     },
-    Certificate, Identity,
+    Certificate, Identity, TlsStats,
 };
 
 #[derive(Clone)]
 pub(crate) struct TlsAcceptor {
     inner: Arc<ServerConfig>,
+    policy: Option<Arc<PolicyConfigs>>,
+    stats: TlsStats,
     timeout: Option<Duration>,
 }
 
+struct PolicyConfigs {
+    policy: ClientAuthPolicy,
+    required: Arc<ServerConfig>,
+    optional: Arc<ServerConfig>,
+    disabled: Arc<ServerConfig>,
+}
+
 impl TlsAcceptor {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
-        identity: &Identity,
+        identity: Option<IdentitySource>,
+        sni_identities: &[(String, Identity)],
         client_ca_root: Option<&Certificate>,
         client_auth_optional: bool,
+        client_auth_policy: Option<ClientAuthPolicy>,
         ignore_client_order: bool,
         use_key_log: bool,
+        session_resumption: bool,
+        stats: TlsStats,
+        crypto_provider: Option<Arc<CryptoProvider>>,
         timeout: Option<Duration>,
     ) -> Result<Self, crate::BoxError> {
-        let builder = ServerConfig::builder();
+        let builder = match crypto_provider {
+            Some(provider) => ServerConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()?,
+            None => ServerConfig::builder(),
+        };
 
-        let builder = match client_ca_root {
-            None => builder.with_no_client_auth(),
+        let provider = builder.crypto_provider().clone();
+        let resolver: Arc<dyn ResolvesServerCert> = match identity {
+            _ if !sni_identities.is_empty() => Arc::new(SniResolver::new(
+                identity,
+                sni_identities,
+                provider.clone(),
+            )?),
+            Some(IdentitySource::Static(identity)) => {
+                let (cert, key) = convert_identity_to_pki_types(&identity)?;
+                Arc::new(SingleCertAndKey::from(CertifiedKey::from_der(
+                    cert, key, &provider,
+                )?))
+            }
+            Some(source) => Arc::new(ReloadingIdentity::new(source, provider.clone())?),
+            None => return Err(TlsError::NoServerIdentity.into()),
+        };
+
+        let roots = match client_ca_root {
+            None => None,
             Some(cert) => {
                 let mut roots = RootCertStore::empty();
                 roots.add_parsable_certificates(convert_certificate_to_pki_types(cert)?);
-                let verifier = if client_auth_optional {
-                    WebPkiClientVerifier::builder(roots.into()).allow_unauthenticated()
-                } else {
-                    WebPkiClientVerifier::builder(roots.into())
-                }
-                .build()?;
-                builder.with_client_cert_verifier(verifier)
+                Some(Arc::new(roots))
             }
         };
 
-        let (cert, key) = convert_identity_to_pki_types(identity)?;
-        let mut config = builder.with_single_cert(cert, key)?;
-        config.ignore_client_order = ignore_client_order;
+        let config_for = |client_auth: ClientAuth| -> Result<Arc<ServerConfig>, crate::BoxError> {
+            let builder = builder.clone();
+            let builder = match (&roots, client_auth) {
+                (None, _) | (_, ClientAuth::Disabled) => builder.with_no_client_auth(),
+                (Some(roots), _) => {
+                    let verifier = WebPkiClientVerifier::builder_with_provider(
+                        roots.clone(),
+                        provider.clone(),
+                    );
+                    let verifier = if client_auth == ClientAuth::Optional {
+                        verifier.allow_unauthenticated()
+                    } else {
+                        verifier
+                    }
+                    .build()?;
+                    builder.with_client_cert_verifier(verifier)
+                }
+            };
 
-        if use_key_log {
-            config.key_log = Arc::new(tokio_rustls::rustls::KeyLogFile::new());
-        }
+            let mut config = builder.with_cert_resolver(resolver.clone());
+            config.ignore_client_order = ignore_client_order;
+
+            if use_key_log {
+                config.key_log = Arc::new(tokio_rustls::rustls::KeyLogFile::new());
+            }
+
+            if !session_resumption {
+                config.session_storage = Arc::new(NoServerSessionStorage {});
+                config.send_tls13_tickets = 0;
+            }
+
+            config.alpn_protocols.push(ALPN_H2.into());
+            Ok(Arc::new(config))
+        };
+
+        let inner = config_for(if client_auth_optional {
+            ClientAuth::Optional
+        } else {
+            ClientAuth::Required
+        })?;
+        let policy = match client_auth_policy {
+            Some(policy) => Some(Arc::new(PolicyConfigs {
+                policy,
+                required: config_for(ClientAuth::Required)?,
+                optional: config_for(ClientAuth::Optional)?,
+                disabled: config_for(ClientAuth::Disabled)?,
+            })),
+            None => None,
+        };
 
-        config.alpn_protocols.push(ALPN_H2.into());
         Ok(Self {
-            inner: Arc::new(config),
+            inner,
+            policy,
+            stats,
             timeout,
         })
     }
 
-    pub(crate) async fn accept<IO>(&self, io: IO) -> Result<TlsStream<IO>, crate::BoxError>
+    pub(crate) async fn accept<IO>(
+        &self,
+        io: IO,
+        remote_addr: Option<SocketAddr>,
+    ) -> Result<TlsStream<IO>, crate::BoxError>
     where
         IO: AsyncRead + AsyncWrite + Unpin,
     {
-        let acceptor = RustlsAcceptor::from(self.inner.clone());
-        let accept_fut = acceptor.accept(io);
-        match self.timeout {
+        let accept_fut = async {
+            match &self.policy {
+                None => Ok(RustlsAcceptor::from(self.inner.clone()).accept(io).await?),
+                Some(configs) => {
+                    let start = LazyConfigAcceptor::new(Acceptor::default(), io).await?;
+                    let client_hello = start.client_hello();
+                    let context = ClientAuthContext::new(client_hello.server_name(), remote_addr);
+                    let config = match (configs.policy)(&context) {
+                        ClientAuth::Required => &configs.required,
+                        ClientAuth::Optional => &configs.optional,
+                        ClientAuth::Disabled => &configs.disabled,
+                    };
+                    Ok::<_, crate::BoxError>(start.into_stream(config.clone()).await?)
+                }
+            }
+        };
+        let stream = match self.timeout {
             Some(timeout) => time::timeout(timeout, accept_fut)
                 .await
                 .map_err(|_| TlsError::HandshakeTimeout)?,
             None => accept_fut.await,
-        }
-        .map_err(Into::into)
+        }?;
+
+        let (_, session) = stream.get_ref();
+        self.stats
+            .record(session.handshake_kind() == Some(HandshakeKind::Resumed));
+        Ok(stream)
     }
 }
 
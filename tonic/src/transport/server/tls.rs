@@ -1,16 +1,70 @@
-use std::{fmt, time::Duration};
+use std::{fmt, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+use tokio_rustls::rustls::crypto::CryptoProvider;
 
 use super::service::TlsAcceptor;
-use crate::transport::tls::{Certificate, Identity};
+use crate::transport::{
+    service::identity::IdentitySource,
+    tls::{Certificate, Identity, TlsStats},
+};
+
+pub(crate) type ClientAuthPolicy =
+    Arc<dyn Fn(&ClientAuthContext<'_>) -> ClientAuth + Send + Sync + 'static>;
+
+/// How a connection authenticates its client with a certificate, as decided by the
+/// [client auth policy](ServerTlsConfig::client_auth_policy) of the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// The client must present a certificate that is valid for the client CA root.
+    Required,
+    /// The client may present a certificate, which must be valid for the client CA root if it
+    /// does.
+    Optional,
+    /// The client is not asked for a certificate, so an invalid one cannot fail the handshake.
+    Disabled,
+}
+
+/// The connection that a [client auth policy](ServerTlsConfig::client_auth_policy) decides
+/// for, as known when its TLS handshake starts.
+#[derive(Debug)]
+pub struct ClientAuthContext<'a> {
+    server_name: Option<&'a str>,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl<'a> ClientAuthContext<'a> {
+    pub(crate) fn new(server_name: Option<&'a str>, remote_addr: Option<SocketAddr>) -> Self {
+        Self {
+            server_name,
+            remote_addr,
+        }
+    }
+
+    /// Get the server name requested by the client with SNI, if any.
+    pub fn server_name(&self) -> Option<&'a str> {
+        self.server_name
+    }
+
+    /// Get the remote address of the connection, if it is a TCP connection.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+}
 
 /// Configures TLS settings for servers.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct ServerTlsConfig {
-    identity: Option<Identity>,
+    identity: Option<IdentitySource>,
+    sni_identities: Vec<(String, Identity)>,
     client_ca_root: Option<Certificate>,
     client_auth_optional: bool,
+    client_auth_policy: Option<ClientAuthPolicy>,
     ignore_client_order: bool,
     use_key_log: bool,
+    session_resumption: bool,
+    stats: TlsStats,
+    crypto_provider: Option<Arc<CryptoProvider>>,
     timeout: Option<Duration>,
 }
 
@@ -20,6 +74,24 @@ impl fmt::Debug for ServerTlsConfig {
     }
 }
 
+impl Default for ServerTlsConfig {
+    fn default() -> Self {
+        ServerTlsConfig {
+            identity: None,
+            sni_identities: Vec::new(),
+            client_ca_root: None,
+            client_auth_optional: false,
+            client_auth_policy: None,
+            ignore_client_order: false,
+            use_key_log: false,
+            session_resumption: true,
+            stats: TlsStats::default(),
+            crypto_provider: None,
+            timeout: None,
+        }
+    }
+}
+
 impl ServerTlsConfig {
     /// Creates a new `ServerTlsConfig`.
     pub fn new() -> Self {
@@ -27,13 +99,67 @@ impl ServerTlsConfig {
     }
 
     /// Sets the [`Identity`] of the server.
+    ///
+    /// If identities are also set with [`sni_identity`](Self::sni_identity), this one is the
+    /// default used for clients that request another server name or none at all.
     pub fn identity(self, identity: Identity) -> Self {
         ServerTlsConfig {
-            identity: Some(identity),
+            identity: Some(IdentitySource::Static(identity)),
+            ..self
+        }
+    }
+
+    /// Presents the latest identity received on `identity` to clients.
+    ///
+    /// Every new connection uses the identity that is current when its TLS handshake starts, so
+    /// certificates can be rotated without restarting the server or dropping in-flight
+    /// connections, which keep the identity they were accepted with. Like
+    /// [`identity`](Self::identity), this is the default identity if identities are also set by
+    /// server name.
+    pub fn identity_watch(self, identity: watch::Receiver<Identity>) -> Self {
+        ServerTlsConfig {
+            identity: Some(IdentitySource::Watch(identity)),
+            ..self
+        }
+    }
+
+    /// Presents the identity read from the PEM encoded certificate and key files to clients.
+    ///
+    /// The files are read again for a new connection whenever their modification time changed,
+    /// so certificates renewed on disk are used without restarting the server. If the renewed
+    /// files cannot be parsed, the previous identity keeps being used. Setting the config on the
+    /// server fails if the files cannot be read initially.
+    pub fn identity_files(self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        ServerTlsConfig {
+            identity: Some(IdentitySource::Files {
+                cert: cert.into(),
+                key: key.into(),
+            }),
             ..self
         }
     }
 
+    /// Sets the [`Identity`] presented to clients that request `server_name` with SNI, so that a
+    /// single listener can serve several host names with distinct certificates.
+    ///
+    /// The server name is matched exactly, ignoring ASCII case. Handshakes that match no server
+    /// name use the [default identity](Self::identity), and fail if there is none.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use tonic::transport::{Identity, ServerTlsConfig};
+    /// # let (default, api, admin) = (Identity::from_pem("", ""), Identity::from_pem("", ""), Identity::from_pem("", ""));
+    /// let config = ServerTlsConfig::new()
+    ///     .identity(default)
+    ///     .sni_identity("api.example.com", api)
+    ///     .sni_identity("admin.example.com", admin);
+    /// ```
+    pub fn sni_identity(mut self, server_name: impl Into<String>, identity: Identity) -> Self {
+        self.sni_identities.push((server_name.into(), identity));
+        self
+    }
+
     /// Sets a certificate against which to validate client TLS certificates.
     pub fn client_ca_root(self, cert: Certificate) -> Self {
         ServerTlsConfig {
@@ -55,6 +181,36 @@ impl ServerTlsConfig {
         }
     }
 
+    /// Decides for each connection how its client is authenticated with a certificate, e.g.
+    /// to require certificates from internal clients only on a listener that is shared with
+    /// external clients.
+    ///
+    /// The `policy` is called when the TLS handshake of a connection starts, with the server
+    /// name requested by the client and the address of the connection. It overrides
+    /// [`client_auth_optional`](Self::client_auth_optional), and has effect only if a CA
+    /// certificate is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use tonic::transport::{ServerTlsConfig, server::ClientAuth};
+    /// let config = ServerTlsConfig::new().client_auth_policy(|conn| {
+    ///     match conn.server_name() {
+    ///         Some("internal.example.com") => ClientAuth::Required,
+    ///         _ => ClientAuth::Disabled,
+    ///     }
+    /// });
+    /// ```
+    pub fn client_auth_policy<F>(self, policy: F) -> Self
+    where
+        F: Fn(&ClientAuthContext<'_>) -> ClientAuth + Send + Sync + 'static,
+    {
+        ServerTlsConfig {
+            client_auth_policy: Some(Arc::new(policy)),
+            ..self
+        }
+    }
+
     /// Sets whether the server's cipher preferences are followed instead of the client's.
     ///
     /// # Default
@@ -67,6 +223,11 @@ impl ServerTlsConfig {
     }
 
     /// Use key log as specified by the `SSLKEYLOGFILE` environment variable.
+    ///
+    /// The secrets of every TLS session are appended to the file named by the variable, in the NSS
+    /// key log format that Wireshark reads to decrypt captured traffic. Nothing is logged if the
+    /// variable is not set. This is meant for debugging only, as anyone who can read the file can
+    /// decrypt the traffic.
     pub fn use_key_log(self) -> Self {
         ServerTlsConfig {
             use_key_log: true,
@@ -74,6 +235,38 @@ impl ServerTlsConfig {
         }
     }
 
+    /// Sets whether clients may resume their previous sessions.
+    ///
+    /// If enabled, the server issues session tickets for TLS 1.3 and caches sessions in memory
+    /// for TLS 1.2. See [`stats`](Self::stats) for how many handshakes were resumed.
+    ///
+    /// # Default
+    /// By default, this option is set to `true`.
+    pub fn session_resumption(self, enabled: bool) -> Self {
+        ServerTlsConfig {
+            session_resumption: enabled,
+            ..self
+        }
+    }
+
+    /// Returns the handshake statistics of the connections accepted with this config and its
+    /// clones.
+    pub fn stats(&self) -> TlsStats {
+        self.stats.clone()
+    }
+
+    /// Sets the rustls [`CryptoProvider`] used for the connections, e.g. a FIPS validated or a
+    /// post-quantum key exchange provider.
+    ///
+    /// # Default
+    /// By default, the process wide default provider is used.
+    pub fn crypto_provider(self, provider: Arc<CryptoProvider>) -> Self {
+        ServerTlsConfig {
+            crypto_provider: Some(provider),
+            ..self
+        }
+    }
+
     /// Sets the timeout for the TLS handshake.
     pub fn timeout(self, timeout: Duration) -> Self {
         ServerTlsConfig {
@@ -84,11 +277,16 @@ impl ServerTlsConfig {
 
     pub(crate) fn tls_acceptor(&self) -> Result<TlsAcceptor, crate::BoxError> {
         TlsAcceptor::new(
-            self.identity.as_ref().unwrap(),
+            self.identity.clone(),
+            &self.sni_identities,
             self.client_ca_root.as_ref(),
             self.client_auth_optional,
+            self.client_auth_policy.clone(),
             self.ignore_client_order,
             self.use_key_log,
+            self.session_resumption,
+            self.stats.clone(),
+            self.crypto_provider.clone(),
             self.timeout,
         )
     }
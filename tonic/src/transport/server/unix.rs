@@ -1,5 +1,14 @@
 use super::Connected;
-use std::sync::Arc;
+use std::{
+    io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_stream::{wrappers::UnixListenerStream, Stream};
 
 /// Connection info for Unix domain socket streams.
 ///
@@ -11,6 +20,8 @@ use std::sync::Arc;
 /// [ext]: crate::Request::extensions
 #[derive(Clone, Debug)]
 pub struct UdsConnectInfo {
+    /// Local address, i.e. the path of the socket that the server is bound to.
+    pub local_addr: Option<Arc<tokio::net::unix::SocketAddr>>,
     /// Peer address. This will be "unnamed" for client unix sockets.
     pub peer_addr: Option<Arc<tokio::net::unix::SocketAddr>>,
     /// Process credentials for the unix socket.
@@ -22,8 +33,91 @@ impl Connected for tokio::net::UnixStream {
 
     fn connect_info(&self) -> Self::ConnectInfo {
         UdsConnectInfo {
+            local_addr: self.local_addr().ok().map(Arc::new),
             peer_addr: self.peer_addr().ok().map(Arc::new),
             peer_cred: self.peer_cred().ok(),
         }
     }
 }
+
+/// Binds a unix domain socket for a [Router](super::Router)
+///
+/// An incoming stream, usable with [Router::serve_with_incoming](super::Router::serve_with_incoming),
+/// of the connections of the clients that connect to a unix domain socket. The credentials of
+/// the connected clients are available from the [`UdsConnectInfo`] of their requests, e.g. with
+/// [`Request::peer_cred`](crate::Request::peer_cred).
+///
+/// The permissions of the socket are set right after it is bound, before any connection is
+/// accepted. To also keep clients from connecting in between, bind the socket in a directory
+/// that only the server can access.
+///
+/// # Example
+///
+/// ```no_run
+/// # use tonic::transport::server::UdsIncoming;
+/// # fn main() -> std::io::Result<()> {
+/// let incoming = UdsIncoming::bind("/run/my-server/grpc.sock")?
+///     .with_mode(0o660)?
+///     .with_owner(None, Some(1000))?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct UdsIncoming {
+    inner: UnixListenerStream,
+    path: PathBuf,
+}
+
+impl UdsIncoming {
+    /// Creates an instance by binding the socket at `path`.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let listener = UnixListener::bind(path)?;
+
+        Ok(Self {
+            inner: UnixListenerStream::new(listener),
+            path: path.to_owned(),
+        })
+    }
+
+    /// Sets the permissions of the socket to `mode`, e.g. `0o660` to only let the owner and the
+    /// group of the socket connect.
+    pub fn with_mode(self, mode: u32) -> io::Result<Self> {
+        std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode))?;
+        Ok(self)
+    }
+
+    /// Sets the owner and the group of the socket, leaving them unchanged when `None`.
+    pub fn with_owner(self, uid: Option<u32>, gid: Option<u32>) -> io::Result<Self> {
+        std::os::unix::fs::chown(&self.path, uid, gid)?;
+        Ok(self)
+    }
+
+    /// Returns the path of the socket, which is empty if the socket is not bound to a path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl From<UnixListener> for UdsIncoming {
+    fn from(listener: UnixListener) -> Self {
+        let path = listener
+            .local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_owned))
+            .unwrap_or_default();
+
+        Self {
+            inner: UnixListenerStream::new(listener),
+            path,
+        }
+    }
+}
+
+impl Stream for UdsIncoming {
+    type Item = io::Result<UnixStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
@@ -0,0 +1,205 @@
+//! TLS identities that are reloaded without recreating the channel or the server.
+
+use crate::transport::{service::tls::convert_identity_to_pki_types, tls::Identity};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tokio::sync::watch;
+#[cfg(feature = "server")]
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+#[cfg(feature = "channel")]
+use tokio_rustls::rustls::{client::ResolvesClientCert, SignatureScheme};
+use tokio_rustls::rustls::{crypto::CryptoProvider, sign::CertifiedKey};
+
+/// Where the identity presented to peers comes from.
+#[derive(Debug, Clone)]
+pub(crate) enum IdentitySource {
+    Static(Identity),
+    Watch(watch::Receiver<Identity>),
+    Files { cert: PathBuf, key: PathBuf },
+}
+
+/// Resolves the identity of every TLS handshake to the latest one of its source.
+pub(crate) struct ReloadingIdentity {
+    state: Mutex<State>,
+    provider: Arc<CryptoProvider>,
+}
+
+enum ReloadingSource {
+    Watch(watch::Receiver<Identity>),
+    Files {
+        cert: PathBuf,
+        key: PathBuf,
+        modified: (SystemTime, SystemTime),
+    },
+}
+
+struct State {
+    source: ReloadingSource,
+    current: Arc<CertifiedKey>,
+}
+
+impl ReloadingIdentity {
+    /// Loads the current identity of `source`, failing if it is not valid.
+    pub(crate) fn new(
+        source: IdentitySource,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, crate::BoxError> {
+        let (source, current) = match source {
+            IdentitySource::Static(identity) => {
+                // A receiver without sender never changes.
+                let (_, rx) = watch::channel(identity);
+                let current = certified_key(&rx.borrow(), &provider)?;
+                (ReloadingSource::Watch(rx), current)
+            }
+            IdentitySource::Watch(mut rx) => {
+                let current = certified_key(&rx.borrow_and_update(), &provider)?;
+                (ReloadingSource::Watch(rx), current)
+            }
+            IdentitySource::Files { cert, key } => {
+                let modified = (modified(&cert)?, modified(&key)?);
+                let current = certified_key(&read_identity(&cert, &key)?, &provider)?;
+                (
+                    ReloadingSource::Files {
+                        cert,
+                        key,
+                        modified,
+                    },
+                    current,
+                )
+            }
+        };
+
+        Ok(Self {
+            state: Mutex::new(State { source, current }),
+            provider,
+        })
+    }
+
+    /// Returns the latest identity of the source, reloading it if it changed.
+    pub(crate) fn current(&self) -> Arc<CertifiedKey> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let reloaded = match &mut state.source {
+            ReloadingSource::Watch(rx) => match rx.has_changed() {
+                Ok(true) => Some(certified_key(&rx.borrow_and_update(), &self.provider)),
+                _ => None,
+            },
+            ReloadingSource::Files {
+                cert,
+                key,
+                modified: last_modified,
+            } => match (modified(cert), modified(key)) {
+                (Ok(cert_modified), Ok(key_modified))
+                    if (cert_modified, key_modified) != *last_modified =>
+                {
+                    *last_modified = (cert_modified, key_modified);
+                    Some(
+                        read_identity(cert, key)
+                            .and_then(|identity| certified_key(&identity, &self.provider)),
+                    )
+                }
+                _ => None,
+            },
+        };
+
+        match reloaded {
+            Some(Ok(key)) => state.current = key,
+            Some(Err(err)) => {
+                tracing::warn!(
+                    "failed to reload the TLS identity, keeping the previous one: {err}"
+                );
+            }
+            None => {}
+        }
+
+        state.current.clone()
+    }
+}
+
+#[cfg(feature = "channel")]
+impl ResolvesClientCert for ReloadingIdentity {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "server")]
+impl ResolvesServerCert for ReloadingIdentity {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+impl fmt::Debug for ReloadingIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadingIdentity").finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn certified_key(
+    identity: &Identity,
+    provider: &CryptoProvider,
+) -> Result<Arc<CertifiedKey>, crate::BoxError> {
+    let (cert, key) = convert_identity_to_pki_types(identity)?;
+    let key = provider.key_provider.load_private_key(key)?;
+    Ok(Arc::new(CertifiedKey::new(cert, key)))
+}
+
+fn read_identity(cert: &Path, key: &Path) -> Result<Identity, crate::BoxError> {
+    Ok(Identity::from_pem(fs::read(cert)?, fs::read(key)?))
+}
+
+fn modified(path: &Path) -> io::Result<SystemTime> {
+    fs::metadata(path)?.modified()
+}
+
+#[cfg(all(test, feature = "tls-ring"))]
+mod tests {
+    use super::*;
+    use tokio_rustls::rustls::crypto::ring;
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/data/tls");
+
+    fn identity(name: &str) -> Identity {
+        read_identity(
+            &Path::new(DATA).join(format!("{name}.pem")),
+            &Path::new(DATA).join(format!("{name}.key")),
+        )
+        .unwrap()
+    }
+
+    fn resolve(identity: &ReloadingIdentity) -> Arc<CertifiedKey> {
+        identity.current()
+    }
+
+    #[test]
+    fn resolves_latest_watched_identity() {
+        let (tx, rx) = watch::channel(identity("client1"));
+        let provider = Arc::new(ring::default_provider());
+        let reloading = ReloadingIdentity::new(IdentitySource::Watch(rx), provider).unwrap();
+
+        let first = resolve(&reloading);
+        assert_eq!(first.cert, resolve(&reloading).cert);
+
+        tx.send(identity("client2")).unwrap();
+        let second = resolve(&reloading);
+        assert_ne!(first.cert, second.cert);
+
+        // An invalid identity keeps the previous one.
+        tx.send(Identity::from_pem("invalid", "invalid")).unwrap();
+        assert_eq!(second.cert, resolve(&reloading).cert);
+    }
+}
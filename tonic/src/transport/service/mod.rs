@@ -1,5 +1,11 @@
-pub(crate) mod grpc_timeout;
+#[cfg(feature = "_tls-any")]
+pub(crate) mod identity;
 #[cfg(feature = "_tls-any")]
 pub(crate) mod tls;
+pub(crate) mod wire_debug;
+#[cfg(feature = "_tls-any")]
+pub(crate) mod x509;
 
-pub(crate) use self::grpc_timeout::GrpcTimeout;
+#[cfg(feature = "server")]
+pub(crate) use crate::service::grpc_timeout::{MethodTimeouts, TimeoutBounds};
+pub(crate) use crate::service::GrpcTimeout;
@@ -16,6 +16,8 @@ pub(crate) enum TlsError {
     CertificateParseError,
     PrivateKeyParseError,
     HandshakeTimeout,
+    #[cfg(feature = "server")]
+    NoServerIdentity,
 }
 
 impl fmt::Display for TlsError {
@@ -31,6 +33,8 @@ impl fmt::Display for TlsError {
                 "Error parsing TLS private key - no RSA or PKCS8-encoded keys found."
             ),
             TlsError::HandshakeTimeout => write!(f, "TLS handshake timeout."),
+            #[cfg(feature = "server")]
+            TlsError::NoServerIdentity => write!(f, "No server identity configured."),
         }
     }
 }
@@ -0,0 +1,417 @@
+//! Frame-level logging of HTTP/2 connections, to debug interoperability issues without capturing
+//! the traffic.
+//!
+//! The bytes read from and written to a connection are split into HTTP/2 frames, which are
+//! logged at the `TRACE` level under the `tonic::wire` target along with the boundaries of the
+//! gRPC messages carried by `DATA` frames. Only the frame types, stream ids, flags, sizes and
+//! flow-control fields are logged: header blocks and message payloads are never.
+
+use pin_project::pin_project;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{self, IoSlice},
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::{trace, Level};
+
+const TARGET: &str = "tonic::wire";
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_LEN: usize = 9;
+const MESSAGE_HEADER_LEN: usize = 5;
+const SETTING_LEN: usize = 6;
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const ACK: u8 = 0x1;
+const PADDED: u8 = 0x8;
+
+/// Logs an event of a [`FrameLogger`] with the fields identifying its connection.
+macro_rules! log {
+    ($logger:expr, $($fields:tt)*) => {
+        trace!(
+            target: TARGET,
+            connection = $logger.connection,
+            side = $logger.side,
+            direction = $logger.direction,
+            $($fields)*
+        )
+    };
+}
+
+/// An IO resource that logs the HTTP/2 frames read from and written to it.
+#[pin_project]
+pub(crate) struct WireDebug<IO> {
+    #[pin]
+    io: IO,
+    // `None` when wire debugging is disabled, or when the connection does not use HTTP/2.
+    state: Option<Box<State>>,
+}
+
+struct State {
+    read: FrameLogger,
+    write: FrameLogger,
+}
+
+impl<IO> WireDebug<IO> {
+    /// Wraps the IO of a client connection, logging its frames if `enabled` and `TRACE` events
+    /// of `tonic::wire` are enabled.
+    #[cfg(feature = "channel")]
+    pub(crate) fn client(io: IO, enabled: bool) -> Self {
+        Self::new(io, "client", enabled)
+    }
+
+    /// Wraps the IO of a server connection, logging its frames if `enabled` and `TRACE` events
+    /// of `tonic::wire` are enabled.
+    #[cfg(feature = "server")]
+    pub(crate) fn server(io: IO, enabled: bool) -> Self {
+        Self::new(io, "server", enabled)
+    }
+
+    fn new(io: IO, side: &'static str, enabled: bool) -> Self {
+        static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+        let enabled = enabled && tracing::enabled!(target: TARGET, Level::TRACE);
+        Self {
+            io,
+            state: enabled.then(|| {
+                let connection = CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+                // Only the client sends the connection preface.
+                let client = side == "client";
+                Box::new(State {
+                    read: FrameLogger::new(connection, side, "recv", !client),
+                    write: FrameLogger::new(connection, side, "send", client),
+                })
+            }),
+        }
+    }
+
+    fn on_read(state: &mut Option<Box<State>>, bytes: &[u8]) {
+        if let Some(s) = state {
+            if !s.read.read(bytes) {
+                *state = None;
+            }
+        }
+    }
+
+    fn on_write(state: &mut Option<Box<State>>, bytes: &[u8]) {
+        if let Some(s) = state {
+            if !s.write.read(bytes) {
+                *state = None;
+            }
+        }
+    }
+}
+
+impl<IO: AsyncRead> AsyncRead for WireDebug<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled = buf.filled().len();
+        ready!(this.io.poll_read(cx, buf))?;
+        Self::on_read(this.state, &buf.filled()[filled..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncWrite> AsyncWrite for WireDebug<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.io.poll_write(cx, buf))?;
+        Self::on_write(this.state, &buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.io.poll_write_vectored(cx, bufs))?;
+
+        let mut left = n;
+        for buf in bufs {
+            let len = buf.len().min(left);
+            Self::on_write(this.state, &buf[..len]);
+            left -= len;
+            if left == 0 {
+                break;
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+/// The gRPC message framing of the `DATA` frames of a stream.
+#[derive(Debug, Default)]
+struct Messages {
+    header: [u8; MESSAGE_HEADER_LEN],
+    header_len: usize,
+    remaining: u64,
+}
+
+/// Splits one direction of an HTTP/2 connection into frames and logs them, however the bytes
+/// are chunked.
+struct FrameLogger {
+    connection: u64,
+    /// `client` or `server`.
+    side: &'static str,
+    /// `send` or `recv`.
+    direction: &'static str,
+    /// The bytes of the connection preface matched so far, until it is complete.
+    preface: Option<usize>,
+    header: [u8; FRAME_HEADER_LEN],
+    header_len: usize,
+    /// The offset of the next byte of the payload of the current frame.
+    offset: usize,
+    /// The first bytes of the payload, holding the fields logged with the frame.
+    fields: [u8; 8],
+    /// The length of the padding of a padded `DATA` frame.
+    padding: usize,
+    setting: [u8; SETTING_LEN],
+    settings: Vec<(u16, u32)>,
+    messages: HashMap<u32, Messages>,
+}
+
+impl FrameLogger {
+    fn new(connection: u64, side: &'static str, direction: &'static str, preface: bool) -> Self {
+        Self {
+            connection,
+            side,
+            direction,
+            preface: preface.then_some(0),
+            header: [0; FRAME_HEADER_LEN],
+            header_len: 0,
+            offset: 0,
+            fields: [0; 8],
+            padding: 0,
+            setting: [0; SETTING_LEN],
+            settings: Vec::new(),
+            messages: HashMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        let [a, b, c, ..] = self.header;
+        u32::from_be_bytes([0, a, b, c]) as usize
+    }
+
+    fn kind(&self) -> u8 {
+        self.header[3]
+    }
+
+    fn flags(&self) -> u8 {
+        self.header[4]
+    }
+
+    fn stream_id(&self) -> u32 {
+        let [.., a, b, c, d] = self.header;
+        u32::from_be_bytes([a, b, c, d]) & 0x7fff_ffff
+    }
+
+    fn field(&self, at: usize) -> u32 {
+        u32::from_be_bytes(self.fields[at..at + 4].try_into().unwrap())
+    }
+
+    /// Logs the frames in `bytes`. Returns `false` if the connection is not an HTTP/2
+    /// connection, e.g. an HTTP/1.1 connection accepted by the server.
+    fn read(&mut self, mut bytes: &[u8]) -> bool {
+        while !bytes.is_empty() {
+            if let Some(matched) = self.preface {
+                let n = (PREFACE.len() - matched).min(bytes.len());
+                if bytes[..n] != PREFACE[matched..matched + n] {
+                    return false;
+                }
+                self.preface = Some(matched + n).filter(|&matched| matched < PREFACE.len());
+                bytes = &bytes[n..];
+            } else if self.header_len < FRAME_HEADER_LEN {
+                let n = (FRAME_HEADER_LEN - self.header_len).min(bytes.len());
+                self.header[self.header_len..self.header_len + n].copy_from_slice(&bytes[..n]);
+                self.header_len += n;
+                bytes = &bytes[n..];
+
+                if self.header_len == FRAME_HEADER_LEN {
+                    self.offset = 0;
+                    self.fields = [0; 8];
+                    self.padding = 0;
+                    if self.len() == 0 {
+                        self.end_frame();
+                    }
+                }
+            } else {
+                let n = (self.len() - self.offset).min(bytes.len());
+                self.payload(&bytes[..n]);
+                self.offset += n;
+                bytes = &bytes[n..];
+
+                if self.offset == self.len() {
+                    self.end_frame();
+                }
+            }
+        }
+        true
+    }
+
+    fn payload(&mut self, bytes: &[u8]) {
+        let offset = self.offset;
+        if offset < self.fields.len() {
+            let n = (self.fields.len() - offset).min(bytes.len());
+            self.fields[offset..offset + n].copy_from_slice(&bytes[..n]);
+        }
+
+        match self.kind() {
+            DATA => {
+                let padded = self.flags() & PADDED != 0;
+                if padded && offset == 0 {
+                    self.padding = usize::from(bytes[0]);
+                }
+                // The data is between the padding length and the padding.
+                let start = usize::from(padded).saturating_sub(offset);
+                let end = self
+                    .len()
+                    .saturating_sub(self.padding)
+                    .saturating_sub(offset);
+                let end = end.min(bytes.len());
+                if start < end {
+                    self.data(&bytes[start..end]);
+                }
+            }
+            SETTINGS => {
+                for (i, &byte) in bytes.iter().enumerate() {
+                    let at = (offset + i) % SETTING_LEN;
+                    self.setting[at] = byte;
+                    if at == SETTING_LEN - 1 {
+                        let [a, b, c, d, e, f] = self.setting;
+                        let value = u32::from_be_bytes([c, d, e, f]);
+                        self.settings.push((u16::from_be_bytes([a, b]), value));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Logs the boundaries of the gRPC messages in the data of a `DATA` frame.
+    fn data(&mut self, mut bytes: &[u8]) {
+        let stream_id = self.stream_id();
+        let messages = self.messages.entry(stream_id).or_default();
+        while !bytes.is_empty() {
+            if messages.remaining > 0 {
+                let n = messages.remaining.min(bytes.len() as u64);
+                messages.remaining -= n;
+                bytes = &bytes[n as usize..];
+                continue;
+            }
+
+            let n = (MESSAGE_HEADER_LEN - messages.header_len).min(bytes.len());
+            messages.header[messages.header_len..][..n].copy_from_slice(&bytes[..n]);
+            messages.header_len += n;
+            bytes = &bytes[n..];
+
+            if messages.header_len == MESSAGE_HEADER_LEN {
+                let [compressed, len @ ..] = messages.header;
+                let len = u32::from_be_bytes(len);
+                log!(
+                    self,
+                    stream_id,
+                    compressed = compressed & 1 != 0,
+                    length = len,
+                    "gRPC message"
+                );
+                messages.header_len = 0;
+                messages.remaining = u64::from(len);
+            }
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.header_len = 0;
+        let (stream_id, length, flags) = (self.stream_id(), self.len(), self.flags());
+        let end_stream = flags & END_STREAM != 0;
+
+        match self.kind() {
+            DATA => log!(self, stream_id, length, end_stream, "DATA"),
+            HEADERS => log!(
+                self,
+                stream_id,
+                length,
+                end_stream,
+                end_headers = flags & END_HEADERS != 0,
+                "HEADERS"
+            ),
+            RST_STREAM => log!(self, stream_id, error_code = self.field(0), "RST_STREAM"),
+            SETTINGS => {
+                let settings = self
+                    .settings
+                    .drain(..)
+                    .map(|(id, value)| format!("{}={value}", setting_name(id)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                log!(self, ack = flags & ACK != 0, settings, "SETTINGS");
+            }
+            PING => log!(self, ack = flags & ACK != 0, "PING"),
+            GOAWAY => log!(
+                self,
+                last_stream_id = self.field(0) & 0x7fff_ffff,
+                error_code = self.field(4),
+                "GOAWAY"
+            ),
+            WINDOW_UPDATE => log!(
+                self,
+                stream_id,
+                increment = self.field(0) & 0x7fff_ffff,
+                "WINDOW_UPDATE"
+            ),
+            kind => log!(self, kind, stream_id, length, flags, "frame"),
+        }
+
+        if end_stream && matches!(self.kind(), DATA | HEADERS) || self.kind() == RST_STREAM {
+            self.messages.remove(&stream_id);
+        }
+    }
+}
+
+fn setting_name(id: u16) -> Cow<'static, str> {
+    let name = match id {
+        0x1 => "HEADER_TABLE_SIZE",
+        0x2 => "ENABLE_PUSH",
+        0x3 => "MAX_CONCURRENT_STREAMS",
+        0x4 => "INITIAL_WINDOW_SIZE",
+        0x5 => "MAX_FRAME_SIZE",
+        0x6 => "MAX_HEADER_LIST_SIZE",
+        0x8 => "ENABLE_CONNECT_PROTOCOL",
+        id => return Cow::Owned(format!("{id:#x}")),
+    };
+    Cow::Borrowed(name)
+}
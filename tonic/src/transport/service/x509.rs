@@ -0,0 +1,123 @@
+//! Minimal reading of the subject alternative names of DER encoded certificates.
+
+const SEQUENCE: u8 = 0x30;
+const OID: u8 = 0x06;
+const BOOLEAN: u8 = 0x01;
+const OCTET_STRING: u8 = 0x04;
+const EXTENSIONS: u8 = 0xa3;
+const DNS_NAME: u8 = 0x82;
+const URI_NAME: u8 = 0x86;
+// 2.5.29.17
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// The DNS and URI subject alternative names of a certificate.
+#[derive(Debug, Default)]
+pub(crate) struct SubjectAltNames<'a> {
+    pub(crate) dns_names: Vec<&'a [u8]>,
+    pub(crate) uris: Vec<&'a [u8]>,
+}
+
+/// Returns the subject alternative names of a DER encoded certificate, or `None` if it is
+/// malformed.
+pub(crate) fn subject_alt_names(cert: &[u8]) -> Option<SubjectAltNames<'_>> {
+    let (cert, _) = read(cert, SEQUENCE)?;
+    let (mut tbs, _) = read(cert, SEQUENCE)?;
+
+    let mut extensions = None;
+    while !tbs.is_empty() {
+        let (tag, value, rest) = read_any(tbs)?;
+        if tag == EXTENSIONS {
+            extensions = Some(read(value, SEQUENCE)?.0);
+        }
+        tbs = rest;
+    }
+
+    let mut names = SubjectAltNames::default();
+    let mut extensions = extensions.unwrap_or_default();
+    while !extensions.is_empty() {
+        let (extension, rest) = read(extensions, SEQUENCE)?;
+        extensions = rest;
+
+        let (oid, extension) = read(extension, OID)?;
+        if oid != SUBJECT_ALT_NAME {
+            continue;
+        }
+        let extension = match read(extension, BOOLEAN) {
+            Some((_, rest)) => rest,
+            None => extension,
+        };
+        let (value, _) = read(extension, OCTET_STRING)?;
+        let (mut general_names, _) = read(value, SEQUENCE)?;
+        while !general_names.is_empty() {
+            let (tag, name, rest) = read_any(general_names)?;
+            match tag {
+                DNS_NAME => names.dns_names.push(name),
+                URI_NAME => names.uris.push(name),
+                _ => {}
+            }
+            general_names = rest;
+        }
+    }
+
+    Some(names)
+}
+
+/// Reads a DER value with the `expected` tag, returning its contents and the remaining input.
+fn read(input: &[u8], expected: u8) -> Option<(&[u8], &[u8])> {
+    match read_any(input)? {
+        (tag, value, rest) if tag == expected => Some((value, rest)),
+        _ => None,
+    }
+}
+
+/// Reads a DER value, returning its tag, its contents and the remaining input.
+fn read_any(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let octets = (first & 0x7f) as usize;
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let (len, rest) = input.split_at(octets);
+        input = rest;
+        len.iter().fold(0, |len, &octet| len << 8 | octet as usize)
+    };
+
+    if input.len() < len {
+        return None;
+    }
+    let (value, rest) = input.split_at(len);
+    Some((tag, value, rest))
+}
+
+#[cfg(all(test, feature = "tls-ring"))]
+mod tests {
+    use super::*;
+    use crate::transport::{service::tls::convert_certificate_to_pki_types, Certificate};
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/data/tls");
+
+    fn cert(name: &str) -> Vec<u8> {
+        let pem = std::fs::read(format!("{DATA}/{name}.pem")).unwrap();
+        convert_certificate_to_pki_types(&Certificate::from_pem(pem)).unwrap()[0].to_vec()
+    }
+
+    #[test]
+    fn reads_subject_alt_names() {
+        let spiffe = cert("spiffe");
+        let names = subject_alt_names(&spiffe).unwrap();
+        assert!(names.dns_names.is_empty());
+        assert_eq!(names.uris, [b"spiffe://example.org/ns/default/sa/server"]);
+
+        let server = cert("server");
+        let names = subject_alt_names(&server).unwrap();
+        assert!(names.dns_names.contains(&&b"localhost"[..]));
+        assert!(names.uris.is_empty());
+
+        assert!(subject_alt_names(b"invalid").is_none());
+    }
+}
@@ -1,3 +1,9 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio_rustls::rustls::{pki_types::CertificateDer, CipherSuite, CommonState, ProtocolVersion};
+
 /// Represents a X509 certificate.
 #[derive(Debug, Clone)]
 pub struct Certificate {
@@ -58,3 +64,109 @@ impl Identity {
         Self { cert, key }
     }
 }
+
+/// The parameters negotiated by the TLS handshake of a connection.
+///
+/// It is added to the extensions of the responses received by a client, and of the requests
+/// received by a server, on TLS connections.
+#[derive(Debug, Clone)]
+pub struct TlsInfo {
+    protocol_version: Option<ProtocolVersion>,
+    cipher_suite: Option<CipherSuite>,
+    alpn_protocol: Option<Vec<u8>>,
+    peer_certs: Option<Arc<Vec<CertificateDer<'static>>>>,
+    server_name: Option<String>,
+}
+
+impl TlsInfo {
+    pub(crate) fn new(session: &CommonState) -> Self {
+        Self {
+            protocol_version: session.protocol_version(),
+            cipher_suite: session.negotiated_cipher_suite().map(|suite| suite.suite()),
+            alpn_protocol: session.alpn_protocol().map(Into::into),
+            peer_certs: session
+                .peer_certificates()
+                .map(|certs| certs.to_owned().into()),
+            server_name: None,
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn with_server_name(self, server_name: Option<&str>) -> Self {
+        Self {
+            server_name: server_name.map(Into::into),
+            ..self
+        }
+    }
+
+    /// The negotiated TLS protocol version.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version
+    }
+
+    /// The negotiated cipher suite.
+    pub fn cipher_suite(&self) -> Option<CipherSuite> {
+        self.cipher_suite
+    }
+
+    /// The protocol negotiated with ALPN, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// The certificate chain presented by the peer, starting with its end-entity certificate.
+    pub fn peer_certs(&self) -> Option<Arc<Vec<CertificateDer<'static>>>> {
+        self.peer_certs.clone()
+    }
+
+    /// The server name requested by the client with SNI, if any.
+    ///
+    /// This is only set on the server side.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+}
+
+/// Counts the TLS handshakes of the connections created with a TLS config, and how many of them
+/// resumed a previous session instead of doing a full handshake.
+///
+/// Obtained with [`ClientTlsConfig::stats`](super::ClientTlsConfig::stats) and
+/// [`ServerTlsConfig::stats`](super::ServerTlsConfig::stats). Clones share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct TlsStats {
+    inner: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    handshakes: AtomicU64,
+    resumed: AtomicU64,
+}
+
+impl TlsStats {
+    /// The number of completed handshakes.
+    pub fn handshakes(&self) -> u64 {
+        self.inner.handshakes.load(Ordering::Relaxed)
+    }
+
+    /// The number of completed handshakes that resumed a previous session.
+    pub fn resumed_handshakes(&self) -> u64 {
+        self.inner.resumed.load(Ordering::Relaxed)
+    }
+
+    /// The fraction of the completed handshakes that resumed a previous session, between `0.0`
+    /// and `1.0`.
+    pub fn resumption_rate(&self) -> f64 {
+        match self.handshakes() {
+            0 => 0.0,
+            handshakes => self.resumed_handshakes() as f64 / handshakes as f64,
+        }
+    }
+
+    pub(crate) fn record(&self, resumed: bool) {
+        self.inner.handshakes.fetch_add(1, Ordering::Relaxed);
+        if resumed {
+            self.inner.resumed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}